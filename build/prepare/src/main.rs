@@ -431,6 +431,29 @@ fn copy_build_config() -> Result<()> {
   Ok(())
 }
 
+fn copy_embedded_directories(build_config: &yaml_rust2::Yaml) -> Result<()> {
+  println!("Copying embedded directories...");
+
+  if let Some(embedded_directories) = build_config["embedded"].as_vec() {
+    for embedded_directory in embedded_directories.iter() {
+      let path = embedded_directory["path"]
+        .as_str()
+        .ok_or_else(|| BuildError::ConfigError("Embedded directory missing 'path' field".to_string()))?;
+
+      // Absolute paths are used directly by the build script
+      if Path::new(path).is_absolute() {
+        continue;
+      }
+
+      copy_dir_all(path, format!("build/workspace/{path}"))
+        .map_err(|_| BuildError::MissingFile(format!("embedded directory '{path}'")))?;
+      println!("Copied embedded directory: {path}");
+    }
+  }
+
+  Ok(())
+}
+
 fn main() -> Result<()> {
   println!("Preparing Ferron build environment...");
 
@@ -444,6 +467,8 @@ fn main() -> Result<()> {
 
   copy_build_config()?;
 
+  copy_embedded_directories(&build_config)?;
+
   println!("✓ Ferron build environment prepared successfully!");
 
   Ok(())
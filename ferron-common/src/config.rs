@@ -2,13 +2,14 @@ use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hasher;
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{cmp::Ordering, collections::HashMap};
 
 use fancy_regex::Regex;
 
 use crate::modules::Module;
 use crate::observability::ObservabilityBackendChannels;
-use crate::util::IpBlockList;
+use crate::util::{parse_duration, parse_size, IpBlockList};
 
 /// Conditional data
 #[non_exhaustive]
@@ -26,6 +27,9 @@ pub enum ConditionalData {
   IsRego(Arc<regorus::Engine>),
   SetConstant(String, String),
   IsLanguage(String),
+  IsTagged(String),
+  IsBodyJson(String, String),
+  IsBodyRegex(Regex),
 }
 
 impl PartialEq for ConditionalData {
@@ -42,6 +46,9 @@ impl PartialEq for ConditionalData {
       (Self::IsRego(v1), Self::IsRego(v2)) => v1.get_policies().ok() == v2.get_policies().ok(),
       (Self::SetConstant(v1, v2), Self::SetConstant(v3, v4)) => v1 == v3 && v2 == v4,
       (Self::IsLanguage(v1), Self::IsLanguage(v2)) => v1 == v2,
+      (Self::IsTagged(v1), Self::IsTagged(v2)) => v1 == v2,
+      (Self::IsBodyJson(v1, v2), Self::IsBodyJson(v3, v4)) => v1 == v3 && v2 == v4,
+      (Self::IsBodyRegex(v1), Self::IsBodyRegex(v2)) => v1.as_str() == v2.as_str(),
       _ => false,
     }
   }
@@ -62,6 +69,9 @@ impl Ord for ConditionalData {
       (Self::IsNotRegex(v1, v2), Self::IsNotRegex(v3, v4)) => v1.cmp(v3).then(v2.as_str().cmp(v4.as_str())),
       (Self::IsRego(v1), Self::IsRego(v2)) => v1.get_policies().ok().cmp(&v2.get_policies().ok()),
       (Self::SetConstant(v1, v2), Self::SetConstant(v3, v4)) => v1.cmp(v3).then(v2.cmp(v4)),
+      (Self::IsTagged(v1), Self::IsTagged(v2)) => v1.cmp(v2),
+      (Self::IsBodyJson(v1, v2), Self::IsBodyJson(v3, v4)) => v1.cmp(v3).then(v2.cmp(v4)),
+      (Self::IsBodyRegex(v1), Self::IsBodyRegex(v2)) => v1.as_str().cmp(v2.as_str()),
       _ => {
         // SAFETY: See https://doc.rust-lang.org/core/mem/fn.discriminant.html
         let discriminant_self = unsafe { *<*const ConditionalData>::from(self).cast::<u8>() };
@@ -106,12 +116,33 @@ fn count_logical_slashes(s: &str) -> usize {
   count
 }
 
+/// The location modifier, specifying how the location matches the request path
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LocationModifier {
+  /// The location matches request paths starting with the location prefix
+  #[default]
+  Prefix,
+
+  /// The location matches request paths starting with the location prefix,
+  /// and takes priority over regular expression locations (`^~`)
+  PriorityPrefix,
+
+  /// The location matches only the exact request path (`=`)
+  Exact,
+
+  /// The location matches request paths matching a regular expression (`~` and `~*`)
+  Regex,
+}
+
 /// The struct containing conditions
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Conditions {
   /// The location prefix
   pub location_prefix: String,
 
+  /// The location modifier
+  pub location_modifier: LocationModifier,
+
   /// The conditionals
   pub conditionals: Vec<Conditional>,
 }
@@ -212,6 +243,11 @@ impl ServerConfigurationFilters {
   pub fn is_global_non_host(&self) -> bool {
     !self.is_host
   }
+
+  /// Checks if the hostname of the server configuration is a regular expression (prefixed with `~`)
+  pub fn has_regex_hostname(&self) -> bool {
+    self.hostname.as_ref().is_some_and(|h| h.starts_with('~'))
+  }
 }
 
 impl Ord for ServerConfigurationFilters {
@@ -225,9 +261,14 @@ impl Ord for ServerConfigurationFilters {
         self
           .hostname
           .as_ref()
-          .map(|h| !h.starts_with("*."))
-          .cmp(&other.hostname.as_ref().map(|h| !h.starts_with("*.")))
-      }) // Take wildcard hostnames into account
+          .map(|h| !h.starts_with("*.") && !h.starts_with('~'))
+          .cmp(
+            &other
+              .hostname
+              .as_ref()
+              .map(|h| !h.starts_with("*.") && !h.starts_with('~')),
+          )
+      }) // Take wildcard and regular expression hostnames into account
       .then_with(|| {
         self
           .hostname
@@ -475,6 +516,40 @@ impl ServerConfigurationValue {
       None
     }
   }
+
+  /// Extracts a duration from the value. Strings can contain human-readable durations (like `"30s"` or `"5m"`),
+  /// while non-negative integers are interpreted as milliseconds
+  pub fn as_duration(&self) -> Option<Duration> {
+    self.as_duration_in(Duration::from_millis(1))
+  }
+
+  /// Extracts a duration from the value. Strings can contain human-readable durations (like `"30s"` or `"5m"`),
+  /// while non-negative integers (and strings without a unit) are interpreted in the specified unit
+  pub fn as_duration_in(&self, integer_unit: Duration) -> Option<Duration> {
+    let count = match self {
+      Self::String(s) => match s.trim().parse::<u128>() {
+        Ok(count) => count,
+        Err(_) => return parse_duration(s),
+      },
+      Self::Integer(i) => u128::try_from(*i).ok()?,
+      _ => return None,
+    };
+    let nanos = integer_unit.as_nanos().checked_mul(count)?;
+    Some(Duration::new(
+      u64::try_from(nanos / 1_000_000_000).ok()?,
+      (nanos % 1_000_000_000) as u32,
+    ))
+  }
+
+  /// Extracts a size (in bytes) from the value. Strings can contain human-readable sizes (like `"10MB"` or `"1GiB"`),
+  /// while non-negative integers are interpreted as bytes
+  pub fn as_size(&self) -> Option<u64> {
+    match self {
+      Self::String(s) => parse_size(s),
+      Self::Integer(i) => u64::try_from(*i).ok(),
+      _ => None,
+    }
+  }
 }
 
 impl Eq for ServerConfigurationValue {}
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{ProxyToKeyInner, UpstreamInner};
+
+/// The set of backend servers receiving the requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum UpstreamSet {
+  /// The primary backend servers.
+  Primary,
+  /// The backup backend servers, used when all primary backend servers are unhealthy.
+  Backup,
+}
+
+impl UpstreamSet {
+  /// Obtains the name of the backend server set
+  pub(super) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Primary => "primary",
+      Self::Backup => "backup",
+    }
+  }
+}
+
+/// Failover state of the active set of backend servers.
+struct BackupFailoverState {
+  active: UpstreamSet,
+  primaries_healthy_since: Option<Instant>,
+}
+
+/// Active-passive failover between the primary and the backup backend servers, shared by reverse proxy handlers.
+/// The requests are switched back to the primary backend servers only after they stay healthy for the failback delay,
+/// which prevents flapping between the primary and the backup backend servers.
+pub(super) struct BackupFailover {
+  backups: HashSet<UpstreamInner>,
+  failback_delay: Duration,
+  state: Mutex<BackupFailoverState>,
+}
+
+impl BackupFailover {
+  /// Creates a new backup failover state.
+  pub(super) fn new(backups: HashSet<UpstreamInner>, failback_delay: Duration) -> Self {
+    Self {
+      backups,
+      failback_delay,
+      state: Mutex::new(BackupFailoverState {
+        active: UpstreamSet::Primary,
+        primaries_healthy_since: None,
+      }),
+    }
+  }
+
+  /// Checks whether a backend server is a backup backend server.
+  #[inline]
+  pub(super) fn is_backup(&self, upstream: &UpstreamInner) -> bool {
+    self.backups.contains(upstream)
+  }
+
+  /// Updates the active set of backend servers based on whether any primary backend server is healthy.
+  /// Returns the active set of backend servers, along with a set transition, if any.
+  pub(super) fn update(
+    &self,
+    has_healthy_primaries: bool,
+    now: Instant,
+  ) -> (UpstreamSet, Option<(UpstreamSet, UpstreamSet)>) {
+    let mut state = match self.state.lock() {
+      Ok(state) => state,
+      Err(_) => return (UpstreamSet::Primary, None),
+    };
+    let previous_active = state.active;
+    match state.active {
+      UpstreamSet::Primary => {
+        if !has_healthy_primaries {
+          state.active = UpstreamSet::Backup;
+          state.primaries_healthy_since = None;
+        }
+      }
+      UpstreamSet::Backup => {
+        if has_healthy_primaries {
+          let primaries_healthy_since = *state.primaries_healthy_since.get_or_insert(now);
+          if now.duration_since(primaries_healthy_since) >= self.failback_delay {
+            state.active = UpstreamSet::Primary;
+            state.primaries_healthy_since = None;
+          }
+        } else {
+          state.primaries_healthy_since = None;
+        }
+      }
+    }
+    let transition = (previous_active != state.active).then_some((previous_active, state.active));
+    (state.active, transition)
+  }
+
+  /// Keeps only the backend servers from the active set. `is_healthy` checks whether a primary backend server is healthy.
+  /// If no backup backend servers are available, the primary backend servers are kept.
+  /// Returns a set transition, if any.
+  pub(super) fn retain_active(
+    &self,
+    proxy_to_vector: &mut Vec<ProxyToKeyInner>,
+    is_healthy: impl Fn(&UpstreamInner) -> bool,
+  ) -> Option<(UpstreamSet, UpstreamSet)> {
+    let has_healthy_primaries = proxy_to_vector
+      .iter()
+      .any(|(upstream, _, _)| !self.is_backup(upstream) && is_healthy(upstream));
+    let (active, transition) = self.update(has_healthy_primaries, Instant::now());
+    let has_backups = proxy_to_vector.iter().any(|(upstream, _, _)| self.is_backup(upstream));
+    let use_backups = active == UpstreamSet::Backup && has_backups;
+    proxy_to_vector.retain(|(upstream, _, _)| self.is_backup(upstream) == use_backups);
+    transition
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn upstream(proxy_to: &str) -> UpstreamInner {
+    UpstreamInner {
+      proxy_to: proxy_to.to_string(),
+      proxy_unix: None,
+    }
+  }
+
+  #[test]
+  fn backups_are_used_only_when_all_primaries_are_unhealthy() {
+    let primary1 = upstream("http://primary-1");
+    let primary2 = upstream("http://primary-2");
+    let backup = upstream("http://backup");
+    let failover = BackupFailover::new(HashSet::from([backup.clone()]), Duration::from_secs(30));
+    let proxy_to_vector = || {
+      vec![
+        (primary1.clone(), None, None),
+        (primary2.clone(), None, None),
+        (backup.clone(), None, None),
+      ]
+    };
+
+    let mut backends = proxy_to_vector();
+    assert_eq!(failover.retain_active(&mut backends, |u| u != &primary1), None);
+    assert_eq!(backends.len(), 2);
+    assert!(backends.iter().all(|(u, _, _)| u != &backup));
+
+    let mut backends = proxy_to_vector();
+    assert_eq!(
+      failover.retain_active(&mut backends, |_| false),
+      Some((UpstreamSet::Primary, UpstreamSet::Backup))
+    );
+    assert_eq!(backends.len(), 1);
+    assert!(backends[0].0 == backup);
+  }
+
+  #[test]
+  fn failback_to_primaries_is_delayed() {
+    let failover = BackupFailover::new(HashSet::new(), Duration::from_secs(30));
+    let now = Instant::now();
+
+    assert_eq!(
+      failover.update(false, now),
+      (UpstreamSet::Backup, Some((UpstreamSet::Primary, UpstreamSet::Backup)))
+    );
+    // The primary backend servers are healthy again, but not for long enough
+    assert_eq!(failover.update(true, now), (UpstreamSet::Backup, None));
+    assert_eq!(
+      failover.update(true, now + Duration::from_secs(10)),
+      (UpstreamSet::Backup, None)
+    );
+    // A primary backend server failure resets the failback delay
+    assert_eq!(
+      failover.update(false, now + Duration::from_secs(20)),
+      (UpstreamSet::Backup, None)
+    );
+    assert_eq!(
+      failover.update(true, now + Duration::from_secs(40)),
+      (UpstreamSet::Backup, None)
+    );
+    assert_eq!(
+      failover.update(true, now + Duration::from_secs(70)),
+      (UpstreamSet::Primary, Some((UpstreamSet::Backup, UpstreamSet::Primary)))
+    );
+  }
+}
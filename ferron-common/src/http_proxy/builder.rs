@@ -1,15 +1,22 @@
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, net::IpAddr};
 
 use hickory_resolver::{config::ResolverConfig, name_server::TokioConnectionProvider};
 use hyper::header::HeaderName;
 use tokio::sync::RwLock;
 
-use super::{Connections, LoadBalancerAlgorithm, LoadBalancerAlgorithmInner, ProxyHeader, ProxyToKey, ReverseProxy};
+use super::{
+  Connections, ForwardedFor, HeaderForwarding, HeaderModifications, Http2Keepalive, LoadBalancerAlgorithm,
+  LoadBalancerAlgorithmInner, ProxyCookieFlags, ProxyCookieRewrites, ProxyHeader, ProxyHostHeader, ProxyNextUpstream,
+  ProxyRequestSigning, ProxyToKey, ReverseProxy, TcpKeepalive,
+};
 use crate::{
-  http_proxy::{SrvUpstreamData, Upstream, UpstreamInner},
+  http_proxy::{
+    BackupFailover, CircuitBreaker, CircuitBreakers, Http3Client, OutlierDetection, OutlierDetector, SessionAffinity,
+    SrvUpstreamData, StickySessions, Upstream, UpstreamInner,
+  },
   util::TtlCache,
 };
 
@@ -18,21 +25,43 @@ pub struct ReverseProxyBuilder<'a> {
   pub(super) connections: &'a mut Connections,
   #[allow(clippy::type_complexity)]
   pub(super) upstreams: Vec<(Upstream, Option<usize>, Option<Duration>)>,
+  pub(super) backup_upstreams: Vec<UpstreamInner>,
   pub(super) lb_algorithm: LoadBalancerAlgorithm,
   pub(super) lb_health_check_window: Duration,
   pub(super) lb_health_check_max_fails: u64,
   pub(super) lb_health_check: bool,
-  pub(super) lb_retry_connection: bool,
+  pub(super) lb_failback_delay: Duration,
+  pub(super) sticky_sessions: Option<StickySessions>,
+  pub(super) draining_upstreams: Vec<UpstreamInner>,
   pub(super) proxy_no_verification: bool,
+  pub(super) proxy_require_ct: Option<usize>,
   pub(super) proxy_intercept_errors: bool,
+  pub(super) proxy_sse: bool,
+  pub(super) proxy_flush_interval: Option<Duration>,
+  pub(super) proxy_next_upstream: ProxyNextUpstream,
+  pub(super) outlier_detection: Option<OutlierDetection>,
+  pub(super) circuit_breaker: Option<CircuitBreaker>,
   pub(super) proxy_http2_only: bool,
   pub(super) proxy_http2: bool,
+  pub(super) proxy_http3: bool,
+  pub(super) http3_runtime: Option<tokio::runtime::Handle>,
+  pub(super) proxy_tcp_keepalive: Option<TcpKeepalive>,
+  pub(super) proxy_http2_keepalive: Option<Http2Keepalive>,
   pub(super) proxy_keepalive: bool,
+  pub(super) proxy_pool_max_idle_per_host: Option<usize>,
+  pub(super) proxy_max_requests_per_connection: Option<u64>,
   pub(super) proxy_proxy_header: Option<ProxyHeader>,
   pub(super) proxy_request_header: Vec<(HeaderName, String)>,
   pub(super) proxy_request_header_replace: Vec<(HeaderName, String)>,
   pub(super) proxy_request_header_remove: Vec<HeaderName>,
-  pub(super) rewrite_host: bool,
+  pub(super) proxy_response_header: Vec<(HeaderName, String)>,
+  pub(super) proxy_response_header_replace: Vec<(HeaderName, String)>,
+  pub(super) proxy_response_header_remove: Vec<HeaderName>,
+  pub(super) proxy_cookie_rewrites: ProxyCookieRewrites,
+  pub(super) proxy_header_forwarding: HeaderForwarding,
+  pub(super) proxy_host_header: Option<ProxyHostHeader>,
+  pub(super) proxy_forwarded_for: ForwardedFor,
+  pub(super) proxy_sign: Option<ProxyRequestSigning>,
 }
 
 impl<'a> ReverseProxyBuilder<'a> {
@@ -57,6 +86,25 @@ impl<'a> ReverseProxyBuilder<'a> {
     self
   }
 
+  /// Adds a backup upstream backend target, which receives requests only when all other upstream backend targets
+  /// are unhealthy.
+  ///
+  /// The parameters are the same as for [`ReverseProxyBuilder::upstream`].
+  pub fn backup_upstream(
+    mut self,
+    proxy_to: String,
+    proxy_unix: Option<String>,
+    local_limit: Option<usize>,
+    keepalive_idle_timeout: Option<Duration>,
+  ) -> Self {
+    let upstream = UpstreamInner { proxy_to, proxy_unix };
+    self.backup_upstreams.push(upstream.clone());
+    self
+      .upstreams
+      .push((Upstream::Static(upstream), local_limit, keepalive_idle_timeout));
+    self
+  }
+
   /// Adds a dynamic (SRV-based) upstream backend target.
   ///
   /// `to` is the backend URL (for example `http://_http._tcp.example.com`).
@@ -102,6 +150,26 @@ impl<'a> ReverseProxyBuilder<'a> {
     self
   }
 
+  /// Marks an upstream backend target as draining. Draining upstream backends don't receive new sticky sessions,
+  /// and are removed after the existing sticky sessions expire or the drain timeout passes.
+  pub fn drain_upstream(mut self, proxy_to: String, proxy_unix: Option<String>) -> Self {
+    self.draining_upstreams.push(UpstreamInner { proxy_to, proxy_unix });
+    self
+  }
+
+  /// Sets the sticky session settings. If `None`, sticky sessions are disabled.
+  pub fn sticky_sessions(mut self, sticky_sessions: Option<StickySessions>) -> Self {
+    self.sticky_sessions = sticky_sessions;
+    self
+  }
+
+  /// Sets the time for which the primary upstream backends must stay healthy,
+  /// before the requests are switched back from the backup upstream backends.
+  pub fn lb_failback_delay(mut self, delay: Duration) -> Self {
+    self.lb_failback_delay = delay;
+    self
+  }
+
   /// Sets load balancing algorithm.
   pub fn lb_algorithm(mut self, algorithm: LoadBalancerAlgorithm) -> Self {
     self.lb_algorithm = algorithm;
@@ -132,15 +200,62 @@ impl<'a> ReverseProxyBuilder<'a> {
     self
   }
 
+  /// Requires upstream TLS certificates to have embedded SCTs from at least the specified number
+  /// of distinct certificate transparency logs. If `None`, the SCTs aren't required.
+  pub fn proxy_require_ct(mut self, required_scts: Option<usize>) -> Self {
+    self.proxy_require_ct = required_scts;
+    self
+  }
+
   /// Intercepts upstream errors and converts them to proxy-generated responses.
   pub fn proxy_intercept_errors(mut self, intercept_errors: bool) -> Self {
     self.proxy_intercept_errors = intercept_errors;
     self
   }
 
+  /// Treats all upstream responses as streaming responses (like Server-Sent Events or long-polling responses),
+  /// which aren't buffered, compressed or subject to the request processing timeout.
+  ///
+  /// Responses with the `text/event-stream` content type are treated as streaming responses regardless of this option.
+  pub fn proxy_sse(mut self, sse: bool) -> Self {
+    self.proxy_sse = sse;
+    self
+  }
+
+  /// Sets how often the upstream response data held in buffers (for example, by compressors) is flushed to the client.
+  /// A zero flush interval flushes the data immediately after it's received from the upstream.
+  pub fn proxy_flush_interval(mut self, flush_interval: Option<Duration>) -> Self {
+    self.proxy_flush_interval = flush_interval;
+    self
+  }
+
   /// Enables retrying a different backend when connection setup fails.
+  ///
+  /// This is a shorthand for setting [`ProxyNextUpstream::error_and_timeout`] or [`ProxyNextUpstream::off`].
   pub fn lb_retry_connection(mut self, retry: bool) -> Self {
-    self.lb_retry_connection = retry;
+    self.proxy_next_upstream = if retry {
+      ProxyNextUpstream::error_and_timeout()
+    } else {
+      ProxyNextUpstream::off()
+    };
+    self
+  }
+
+  /// Sets conditions under which a request is passed to the next backend server.
+  pub fn proxy_next_upstream(mut self, proxy_next_upstream: ProxyNextUpstream) -> Self {
+    self.proxy_next_upstream = proxy_next_upstream;
+    self
+  }
+
+  /// Enables outlier detection, which temporarily ejects misbehaving backends.
+  pub fn outlier_detection(mut self, outlier_detection: Option<OutlierDetection>) -> Self {
+    self.outlier_detection = outlier_detection;
+    self
+  }
+
+  /// Enables a circuit breaker, which stops sending requests to failing backends and probes them after a cooldown.
+  pub fn circuit_breaker(mut self, circuit_breaker: Option<CircuitBreaker>) -> Self {
+    self.circuit_breaker = circuit_breaker;
     self
   }
 
@@ -156,12 +271,49 @@ impl<'a> ReverseProxyBuilder<'a> {
     self
   }
 
+  /// Enables HTTP/3 support for HTTPS upstream connections, with fallback to HTTP/2 or HTTP/1.1.
+  pub fn proxy_http3(mut self, http3: bool) -> Self {
+    self.proxy_http3 = http3;
+    self
+  }
+
+  /// Sets the Tokio runtime driving QUIC connections for HTTP/3 upstreams.
+  /// Without it, HTTP/3 upstreams are connected to over HTTP/2 or HTTP/1.1.
+  pub fn http3_runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+    self.http3_runtime = Some(runtime);
+    self
+  }
+
   /// Enables connection pooling and keepalive reuse.
   pub fn proxy_keepalive(mut self, keepalive: bool) -> Self {
     self.proxy_keepalive = keepalive;
     self
   }
 
+  /// Enables TCP keepalive for upstream connections.
+  pub fn proxy_tcp_keepalive(mut self, tcp_keepalive: Option<TcpKeepalive>) -> Self {
+    self.proxy_tcp_keepalive = tcp_keepalive;
+    self
+  }
+
+  /// Enables HTTP/2 PING frames for upstream connections, including idle pooled connections.
+  pub fn proxy_http2_keepalive(mut self, http2_keepalive: Option<Http2Keepalive>) -> Self {
+    self.proxy_http2_keepalive = http2_keepalive;
+    self
+  }
+
+  /// Sets the maximum number of idle connections kept in the connection pool for each backend server.
+  pub fn proxy_pool_max_idle_per_host(mut self, max_idle_per_host: Option<usize>) -> Self {
+    self.proxy_pool_max_idle_per_host = max_idle_per_host;
+    self
+  }
+
+  /// Sets the maximum number of requests sent over a single backend server connection.
+  pub fn proxy_max_requests_per_connection(mut self, max_requests_per_connection: Option<u64>) -> Self {
+    self.proxy_max_requests_per_connection = max_requests_per_connection;
+    self
+  }
+
   /// Sets PROXY protocol header mode for upstream connections.
   pub fn proxy_proxy_header(mut self, proxy_header: Option<ProxyHeader>) -> Self {
     self.proxy_proxy_header = proxy_header;
@@ -186,9 +338,93 @@ impl<'a> ReverseProxyBuilder<'a> {
     self
   }
 
-  /// Enables or disables `Host` header rewriting for non-HTTPS upstream requests.
-  pub fn rewrite_host(mut self, rewrite_host: bool) -> Self {
-    self.rewrite_host = rewrite_host;
+  /// Adds a response header to responses from upstream backends.
+  pub fn proxy_response_header(mut self, header_name: HeaderName, header_value: String) -> Self {
+    self.proxy_response_header.push((header_name, header_value));
+    self
+  }
+
+  /// Replaces a response header on responses from upstream backends.
+  pub fn proxy_response_header_replace(mut self, header_name: HeaderName, header_value: String) -> Self {
+    self.proxy_response_header_replace.push((header_name, header_value));
+    self
+  }
+
+  /// Removes a response header from responses from upstream backends.
+  pub fn proxy_response_header_remove(mut self, header_name: HeaderName) -> Self {
+    self.proxy_response_header_remove.push(header_name);
+    self
+  }
+
+  /// Passes a hop-by-hop header (like `Keep-Alive` or a header listed in the `Connection` header)
+  /// between clients and upstream backends, instead of removing it.
+  pub fn proxy_pass_header(mut self, header_name: HeaderName) -> Self {
+    self.proxy_header_forwarding.pass_headers.push(header_name);
+    self
+  }
+
+  /// Hides a response header from upstream backends, so that it isn't passed to clients.
+  pub fn proxy_hide_header(mut self, header_name: HeaderName) -> Self {
+    self.proxy_header_forwarding.hide_headers.push(header_name);
+    self
+  }
+
+  /// Enables folding of duplicate request headers into a single header for upstream requests.
+  pub fn proxy_fold_headers(mut self, fold_headers: bool) -> Self {
+    self.proxy_header_forwarding.fold_headers = fold_headers;
+    self
+  }
+
+  /// Sets whether the `X-Forwarded-*` headers from trusted clients are appended to, instead of being overwritten.
+  pub fn proxy_x_forwarded_for_append(mut self, append: bool) -> Self {
+    self.proxy_forwarded_for.append = append;
+    self
+  }
+
+  /// Sets the maximum number of entries in the `X-Forwarded-For` header for upstream requests.
+  /// The oldest entries are removed if the limit is exceeded.
+  pub fn proxy_x_forwarded_for_max_entries(mut self, max_entries: usize) -> Self {
+    self.proxy_forwarded_for.max_entries = max_entries;
+    self
+  }
+
+  /// Sets the maximum size (in bytes) of the `X-Forwarded-For` header for upstream requests.
+  /// The oldest entries are removed if the limit is exceeded.
+  pub fn proxy_x_forwarded_for_max_size(mut self, max_size: usize) -> Self {
+    self.proxy_forwarded_for.max_size = max_size;
+    self
+  }
+
+  /// Rewrites the `Domain` attribute of cookies set by upstream backends.
+  /// An empty replacement removes the `Domain` attribute.
+  pub fn proxy_cookie_domain(mut self, domain: String, replacement: String) -> Self {
+    self.proxy_cookie_rewrites.domains.push((domain, replacement));
+    self
+  }
+
+  /// Rewrites the prefix of the `Path` attribute of cookies set by upstream backends.
+  pub fn proxy_cookie_path(mut self, path: String, replacement: String) -> Self {
+    self.proxy_cookie_rewrites.paths.push((path, replacement));
+    self
+  }
+
+  /// Changes the `Secure`, `HttpOnly` and `SameSite` attributes of cookies set by upstream backends.
+  pub fn proxy_cookie_flags(mut self, flags: ProxyCookieFlags) -> Self {
+    self.proxy_cookie_rewrites.flags.push(flags);
+    self
+  }
+
+  /// Sets the `Host` header sent to upstream backends. If not set, the `Host` header is rewritten
+  /// to the upstream authority for HTTPS and HTTP/3 backends, and preserved for other backends.
+  pub fn proxy_host_header(mut self, host_header: Option<ProxyHostHeader>) -> Self {
+    self.proxy_host_header = host_header;
+    self
+  }
+
+  /// Sets the signing method for requests sent to upstream backends. Requests are signed
+  /// after the request headers are modified.
+  pub fn proxy_sign(mut self, signing: Option<ProxyRequestSigning>) -> Self {
+    self.proxy_sign = signing;
     self
   }
 
@@ -255,6 +491,81 @@ impl<'a> ReverseProxyBuilder<'a> {
       );
       new_failed
     };
+    let outlier_detector = self.outlier_detection.take().map(|outlier_detection| {
+      let key = (outlier_detection, proxy_to.clone());
+      if let Some(outlier_detector) = self.connections.outlier_detector_cache.get(&key) {
+        outlier_detector.clone()
+      } else {
+        let new_outlier_detector = Arc::new(OutlierDetector::new(key.0.clone()));
+        self
+          .connections
+          .outlier_detector_cache
+          .insert(key, new_outlier_detector.clone());
+        new_outlier_detector
+      }
+    });
+    let circuit_breakers = self.circuit_breaker.take().map(|circuit_breaker| {
+      let key = (circuit_breaker, proxy_to.clone());
+      if let Some(circuit_breakers) = self.connections.circuit_breaker_cache.get(&key) {
+        circuit_breakers.clone()
+      } else {
+        let new_circuit_breakers = Arc::new(CircuitBreakers::new(key.0.clone()));
+        self
+          .connections
+          .circuit_breaker_cache
+          .insert(key, new_circuit_breakers.clone());
+        new_circuit_breakers
+      }
+    });
+    let backup_failover = if self.backup_upstreams.is_empty() {
+      None
+    } else {
+      let key = (
+        self.lb_failback_delay,
+        std::mem::take(&mut self.backup_upstreams),
+        proxy_to.clone(),
+      );
+      if let Some(backup_failover) = self.connections.backup_failover_cache.get(&key) {
+        Some(backup_failover.clone())
+      } else {
+        let new_backup_failover = Arc::new(BackupFailover::new(key.1.iter().cloned().collect(), key.0));
+        self
+          .connections
+          .backup_failover_cache
+          .insert(key, new_backup_failover.clone());
+        Some(new_backup_failover)
+      }
+    };
+    let session_affinity = if let Some(sticky_sessions) = self.sticky_sessions.take() {
+      let key = (
+        sticky_sessions,
+        std::mem::take(&mut self.draining_upstreams),
+        proxy_to.clone(),
+      );
+      if let Some(session_affinity) = self.connections.session_affinity_cache.get(&key) {
+        Some(session_affinity.clone())
+      } else {
+        let new_session_affinity = Arc::new(SessionAffinity::new(
+          key.0.clone(),
+          key.1.iter().cloned(),
+          Instant::now(),
+        ));
+        self
+          .connections
+          .session_affinity_cache
+          .insert(key, new_session_affinity.clone());
+        Some(new_session_affinity)
+      }
+    } else {
+      None
+    };
+    let http3_client = self.http3_runtime.take().map(|runtime| {
+      self
+        .connections
+        .http3_client
+        .get_or_insert_with(|| Arc::new(Http3Client::new(runtime)))
+        .clone()
+    });
     ReverseProxy {
       failed_backends,
       load_balancer_algorithm,
@@ -262,19 +573,44 @@ impl<'a> ReverseProxyBuilder<'a> {
       health_check_max_fails: self.lb_health_check_max_fails,
       enable_health_check: self.lb_health_check,
       disable_certificate_verification: self.proxy_no_verification,
+      required_scts: self.proxy_require_ct,
       proxy_intercept_errors: self.proxy_intercept_errors,
-      retry_connection: self.lb_retry_connection,
+      proxy_sse: self.proxy_sse,
+      proxy_flush_interval: self.proxy_flush_interval,
+      proxy_next_upstream: Arc::new(self.proxy_next_upstream),
+      outlier_detector,
+      circuit_breakers,
+      backup_failover,
+      session_affinity,
       proxy_http2_only: self.proxy_http2_only,
       proxy_http2: self.proxy_http2,
+      proxy_http3: self.proxy_http3,
+      http3_client,
+      proxy_tcp_keepalive: self.proxy_tcp_keepalive,
+      proxy_http2_keepalive: self.proxy_http2_keepalive,
       proxy_keepalive: self.proxy_keepalive,
+      pool_max_idle_per_host: self.proxy_pool_max_idle_per_host,
+      max_requests_per_connection: self.proxy_max_requests_per_connection,
       proxy_header: self.proxy_proxy_header,
-      headers_to_add: Arc::new(self.proxy_request_header.drain(..).collect()),
-      headers_to_replace: Arc::new(self.proxy_request_header_replace.drain(..).collect()),
-      headers_to_remove: Arc::new(self.proxy_request_header_remove.drain(..).collect()),
-      rewrite_host: self.rewrite_host,
+      request_header_modifications: Arc::new(HeaderModifications {
+        headers_to_add: self.proxy_request_header.drain(..).collect(),
+        headers_to_replace: self.proxy_request_header_replace.drain(..).collect(),
+        headers_to_remove: self.proxy_request_header_remove.drain(..).collect(),
+      }),
+      response_header_modifications: Arc::new(HeaderModifications {
+        headers_to_add: self.proxy_response_header.drain(..).collect(),
+        headers_to_replace: self.proxy_response_header_replace.drain(..).collect(),
+        headers_to_remove: self.proxy_response_header_remove.drain(..).collect(),
+      }),
+      cookie_rewrites: Arc::new(std::mem::take(&mut self.proxy_cookie_rewrites)),
+      header_forwarding: Arc::new(std::mem::take(&mut self.proxy_header_forwarding)),
+      host_header: self.proxy_host_header.take(),
+      forwarded_for: self.proxy_forwarded_for,
+      signing: self.proxy_sign.take().map(Arc::new),
       connections,
       #[cfg(unix)]
       unix_connections,
+      idle_connections: self.connections.idle_connections.clone(),
     }
   }
 }
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::UpstreamInner;
+
+/// Circuit breaker settings for a group of backend servers.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct CircuitBreaker {
+  /// Number of consecutive failures after which the circuit is opened.
+  pub failure_threshold: u64,
+  /// Time for which the circuit stays open before probe requests are allowed.
+  pub cooldown: Duration,
+  /// Maximum number of concurrent probe requests while the circuit is half-open.
+  pub half_open_requests: u64,
+  /// Number of successful probe requests after which the circuit is closed.
+  pub success_threshold: u64,
+}
+
+impl Default for CircuitBreaker {
+  fn default() -> Self {
+    Self {
+      failure_threshold: 5,
+      cooldown: Duration::from_secs(30),
+      half_open_requests: 1,
+      success_threshold: 1,
+    }
+  }
+}
+
+/// State of a circuit breaker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum CircuitState {
+  /// Requests are sent to the backend server.
+  Closed,
+  /// Requests aren't sent to the backend server.
+  Open,
+  /// A limited number of probe requests are sent to the backend server.
+  HalfOpen,
+}
+
+impl CircuitState {
+  /// Obtains the name of the circuit breaker state
+  pub(super) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Closed => "closed",
+      Self::Open => "open",
+      Self::HalfOpen => "half_open",
+    }
+  }
+}
+
+/// Circuit breaker state of a backend server.
+struct UpstreamCircuit {
+  state: CircuitState,
+  failures: u64,
+  successes: u64,
+  in_flight_probes: u64,
+  changed_at: Instant,
+}
+
+impl Default for UpstreamCircuit {
+  fn default() -> Self {
+    Self {
+      state: CircuitState::Closed,
+      failures: 0,
+      successes: 0,
+      in_flight_probes: 0,
+      changed_at: Instant::now(),
+    }
+  }
+}
+
+impl UpstreamCircuit {
+  #[inline]
+  fn transition(&mut self, state: CircuitState, now: Instant) -> (CircuitState, CircuitState) {
+    let previous_state = self.state;
+    self.state = state;
+    self.failures = 0;
+    self.successes = 0;
+    self.in_flight_probes = 0;
+    self.changed_at = now;
+    (previous_state, state)
+  }
+}
+
+/// Circuit breakers shared by reverse proxy handlers for a group of backend servers.
+pub(super) struct CircuitBreakers {
+  config: CircuitBreaker,
+  circuits: Mutex<HashMap<UpstreamInner, UpstreamCircuit>>,
+}
+
+impl CircuitBreakers {
+  /// Creates new circuit breakers.
+  pub(super) fn new(config: CircuitBreaker) -> Self {
+    Self {
+      config,
+      circuits: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Checks whether a request can be sent to a backend server, and reserves a probe request if the circuit is half-open.
+  /// Returns whether the request is allowed, along with a state transition, if any.
+  pub(super) fn try_acquire(&self, upstream: &UpstreamInner) -> (bool, Option<(CircuitState, CircuitState)>) {
+    let mut circuits = match self.circuits.lock() {
+      Ok(circuits) => circuits,
+      Err(_) => return (true, None),
+    };
+    let now = Instant::now();
+    let circuit = match circuits.get_mut(upstream) {
+      Some(circuit) => circuit,
+      None => return (true, None),
+    };
+    let mut transition = None;
+    match circuit.state {
+      CircuitState::Closed => return (true, None),
+      CircuitState::Open => {
+        if now.duration_since(circuit.changed_at) < self.config.cooldown {
+          return (false, None);
+        }
+        transition = Some(circuit.transition(CircuitState::HalfOpen, now));
+      }
+      CircuitState::HalfOpen => {
+        if now.duration_since(circuit.changed_at) >= self.config.cooldown {
+          // Probe requests that never completed don't keep the circuit half-open forever
+          circuit.in_flight_probes = 0;
+          circuit.changed_at = now;
+        }
+      }
+    }
+    if circuit.in_flight_probes < self.config.half_open_requests.max(1) {
+      circuit.in_flight_probes += 1;
+      (true, transition)
+    } else {
+      (false, transition)
+    }
+  }
+
+  /// Records the outcome of a request sent to a backend server. Returns a state transition, if any.
+  pub(super) fn record(&self, upstream: &UpstreamInner, success: bool) -> Option<(CircuitState, CircuitState)> {
+    let mut circuits = self.circuits.lock().ok()?;
+    let now = Instant::now();
+    let circuit = circuits.entry(upstream.clone()).or_default();
+    match circuit.state {
+      CircuitState::Closed => {
+        if success {
+          circuit.failures = 0;
+          None
+        } else {
+          circuit.failures += 1;
+          if circuit.failures >= self.config.failure_threshold.max(1) {
+            Some(circuit.transition(CircuitState::Open, now))
+          } else {
+            None
+          }
+        }
+      }
+      CircuitState::HalfOpen => {
+        circuit.in_flight_probes = circuit.in_flight_probes.saturating_sub(1);
+        if success {
+          circuit.successes += 1;
+          if circuit.successes >= self.config.success_threshold.max(1) {
+            Some(circuit.transition(CircuitState::Closed, now))
+          } else {
+            None
+          }
+        } else {
+          Some(circuit.transition(CircuitState::Open, now))
+        }
+      }
+      // Requests that were sent before the circuit was opened
+      CircuitState::Open => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn upstream(proxy_to: &str) -> UpstreamInner {
+    UpstreamInner {
+      proxy_to: proxy_to.to_string(),
+      proxy_unix: None,
+    }
+  }
+
+  #[test]
+  fn circuit_opens_after_consecutive_failures() {
+    let circuit_breakers = CircuitBreakers::new(CircuitBreaker {
+      failure_threshold: 2,
+      ..Default::default()
+    });
+    let backend = upstream("http://backend-1");
+
+    assert_eq!(circuit_breakers.record(&backend, false), None);
+    assert_eq!(
+      circuit_breakers.record(&backend, false),
+      Some((CircuitState::Closed, CircuitState::Open))
+    );
+    assert_eq!(circuit_breakers.try_acquire(&backend), (false, None));
+  }
+
+  #[test]
+  fn half_open_circuit_limits_probes_and_closes_on_success() {
+    let circuit_breakers = CircuitBreakers::new(CircuitBreaker {
+      failure_threshold: 1,
+      cooldown: Duration::ZERO,
+      half_open_requests: 1,
+      success_threshold: 1,
+    });
+    let backend = upstream("http://backend-1");
+
+    circuit_breakers.record(&backend, false);
+    assert_eq!(
+      circuit_breakers.try_acquire(&backend),
+      (true, Some((CircuitState::Open, CircuitState::HalfOpen)))
+    );
+    assert_eq!(
+      circuit_breakers.record(&backend, true),
+      Some((CircuitState::HalfOpen, CircuitState::Closed))
+    );
+    assert_eq!(circuit_breakers.try_acquire(&backend), (true, None));
+  }
+
+  #[test]
+  fn half_open_circuit_reopens_on_failure() {
+    let circuit_breakers = CircuitBreakers::new(CircuitBreaker {
+      failure_threshold: 1,
+      cooldown: Duration::from_secs(60),
+      ..Default::default()
+    });
+    let backend = upstream("http://backend-1");
+
+    circuit_breakers.record(&backend, false);
+    {
+      let mut circuits = circuit_breakers.circuits.lock().unwrap();
+      let circuit = circuits.get_mut(&backend).unwrap();
+      circuit.transition(CircuitState::HalfOpen, Instant::now());
+    }
+    assert_eq!(circuit_breakers.try_acquire(&backend), (true, None));
+    assert_eq!(circuit_breakers.try_acquire(&backend), (false, None));
+    assert_eq!(
+      circuit_breakers.record(&backend, false),
+      Some((CircuitState::HalfOpen, CircuitState::Open))
+    );
+  }
+}
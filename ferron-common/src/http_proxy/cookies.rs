@@ -0,0 +1,202 @@
+use hyper::header::{self, HeaderValue};
+use hyper::HeaderMap;
+
+/// Attribute changes for cookies set by backend servers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxyCookieFlags {
+  /// The name of the cookie, or `*` for all cookies
+  pub cookie_name: String,
+  /// Whether the `Secure` attribute is added (`true`) or removed (`false`)
+  pub secure: Option<bool>,
+  /// Whether the `HttpOnly` attribute is added (`true`) or removed (`false`)
+  pub http_only: Option<bool>,
+  /// The value of the `SameSite` attribute (`Strict`, `Lax` or `None`), replacing the existing one
+  pub same_site: Option<String>,
+}
+
+/// Rewrites of the `Set-Cookie` headers in responses from backend servers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct ProxyCookieRewrites {
+  /// The cookie domains and their replacements (empty replacements remove the `Domain` attribute)
+  pub(super) domains: Vec<(String, String)>,
+  /// The cookie path prefixes and their replacements
+  pub(super) paths: Vec<(String, String)>,
+  /// The cookie attribute changes
+  pub(super) flags: Vec<ProxyCookieFlags>,
+}
+
+impl ProxyCookieRewrites {
+  /// Checks if there are no cookie rewrites.
+  pub(super) fn is_empty(&self) -> bool {
+    self.domains.is_empty() && self.paths.is_empty() && self.flags.is_empty()
+  }
+
+  /// Rewrites the domain, the path and the attributes of a cookie from the `Set-Cookie` header value.
+  fn rewrite_set_cookie(&self, set_cookie: &str) -> String {
+    let mut parts = set_cookie.split(';');
+    let name_value = parts.next().unwrap_or("").trim();
+    let cookie_name = name_value.split_once('=').map_or(name_value, |(name, _)| name).trim();
+    let mut attributes = parts
+      .map(|attribute| attribute.trim().to_string())
+      .filter(|attribute| !attribute.is_empty())
+      .collect::<Vec<_>>();
+
+    attributes.retain_mut(|attribute| {
+      let Some((attribute_name, attribute_value)) = attribute.split_once('=') else {
+        return true;
+      };
+      let attribute_name = attribute_name.trim();
+      let attribute_value = attribute_value.trim();
+      if attribute_name.eq_ignore_ascii_case("domain") {
+        let domain = attribute_value.trim_start_matches('.');
+        if let Some((_, replacement)) = self.domains.iter().find(|(from, _)| from.eq_ignore_ascii_case(domain)) {
+          if replacement.is_empty() {
+            return false;
+          }
+          *attribute = format!("Domain={replacement}");
+        }
+      } else if attribute_name.eq_ignore_ascii_case("path") {
+        if let Some((from, replacement)) = self
+          .paths
+          .iter()
+          .find(|(from, _)| attribute_value.starts_with(from.as_str()))
+        {
+          *attribute = format!("Path={replacement}{}", &attribute_value[from.len()..]);
+        }
+      }
+      true
+    });
+
+    for flags in self
+      .flags
+      .iter()
+      .filter(|flags| flags.cookie_name == "*" || flags.cookie_name == cookie_name)
+    {
+      for (flag_name, enabled) in [("Secure", flags.secure), ("HttpOnly", flags.http_only)] {
+        match enabled {
+          Some(true)
+            if !attributes
+              .iter()
+              .any(|attribute| attribute.eq_ignore_ascii_case(flag_name)) =>
+          {
+            attributes.push(flag_name.to_string());
+          }
+          Some(false) => attributes.retain(|attribute| !attribute.eq_ignore_ascii_case(flag_name)),
+          _ => (),
+        }
+      }
+      if let Some(same_site) = &flags.same_site {
+        attributes.retain(|attribute| {
+          !attribute
+            .split_once('=')
+            .is_some_and(|(attribute_name, _)| attribute_name.trim().eq_ignore_ascii_case("samesite"))
+        });
+        attributes.push(format!("SameSite={same_site}"));
+      }
+    }
+
+    std::iter::once(name_value.to_string())
+      .chain(attributes)
+      .collect::<Vec<_>>()
+      .join("; ")
+  }
+
+  /// Rewrites the `Set-Cookie` headers of a response.
+  pub(super) fn apply(&self, headers: &mut HeaderMap) {
+    let header::Entry::Occupied(set_cookie_entry) = headers.entry(header::SET_COOKIE) else {
+      return;
+    };
+    let (_, set_cookie_values) = set_cookie_entry.remove_entry_mult();
+    let rewritten_values = set_cookie_values
+      .filter_map(|value| match value.to_str() {
+        Ok(set_cookie) => HeaderValue::from_str(&self.rewrite_set_cookie(set_cookie)).ok(),
+        // Set-Cookie header values that aren't valid strings are passed as is
+        Err(_) => Some(value),
+      })
+      .collect::<Vec<_>>();
+    for value in rewritten_values {
+      headers.append(header::SET_COOKIE, value);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rewrite_set_cookie_domain_and_path() {
+    let cookie_rewrites = ProxyCookieRewrites {
+      domains: vec![
+        ("backend.internal".to_string(), "example.com".to_string()),
+        ("localhost".to_string(), "".to_string()),
+      ],
+      paths: vec![("/app/".to_string(), "/".to_string())],
+      flags: vec![],
+    };
+    assert_eq!(
+      cookie_rewrites.rewrite_set_cookie("session=abc; Domain=.Backend.internal; Path=/app/admin; HttpOnly"),
+      "session=abc; Domain=example.com; Path=/admin; HttpOnly"
+    );
+    assert_eq!(
+      cookie_rewrites.rewrite_set_cookie("session=abc; domain=localhost; path=/other"),
+      "session=abc; path=/other"
+    );
+  }
+
+  #[test]
+  fn test_rewrite_set_cookie_flags() {
+    let cookie_rewrites = ProxyCookieRewrites {
+      domains: vec![],
+      paths: vec![],
+      flags: vec![
+        ProxyCookieFlags {
+          cookie_name: "*".to_string(),
+          secure: Some(true),
+          http_only: None,
+          same_site: Some("Lax".to_string()),
+        },
+        ProxyCookieFlags {
+          cookie_name: "tracking".to_string(),
+          secure: None,
+          http_only: Some(false),
+          same_site: None,
+        },
+      ],
+    };
+    assert_eq!(
+      cookie_rewrites.rewrite_set_cookie("session=abc; Path=/; SameSite=None"),
+      "session=abc; Path=/; Secure; SameSite=Lax"
+    );
+    assert_eq!(
+      cookie_rewrites.rewrite_set_cookie("tracking=1; secure; HttpOnly"),
+      "tracking=1; secure; SameSite=Lax"
+    );
+  }
+
+  #[test]
+  fn test_rewrite_set_cookie_headers() {
+    let cookie_rewrites = ProxyCookieRewrites {
+      domains: vec![],
+      paths: vec![],
+      flags: vec![ProxyCookieFlags {
+        cookie_name: "*".to_string(),
+        secure: Some(true),
+        http_only: None,
+        same_site: None,
+      }],
+    };
+    let mut headers = HeaderMap::new();
+    headers.append(header::SET_COOKIE, HeaderValue::from_static("a=1"));
+    headers.append(header::SET_COOKIE, HeaderValue::from_static("b=2"));
+    cookie_rewrites.apply(&mut headers);
+    assert_eq!(
+      headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect::<Vec<_>>(),
+      vec!["a=1; Secure", "b=2; Secure"]
+    );
+  }
+}
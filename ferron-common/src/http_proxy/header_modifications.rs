@@ -0,0 +1,135 @@
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::HeaderMap;
+
+use crate::modules::SocketData;
+use crate::util::replace_header_placeholders;
+
+/// Header modifications applied to requests sent to backend servers, or to responses received from them.
+pub(super) struct HeaderModifications {
+  /// Headers added if they aren't present, with header values that can contain placeholders
+  pub(super) headers_to_add: Vec<(HeaderName, String)>,
+  /// Headers replacing existing headers, with header values that can contain placeholders
+  pub(super) headers_to_replace: Vec<(HeaderName, String)>,
+  /// Headers to remove
+  pub(super) headers_to_remove: Vec<HeaderName>,
+}
+
+impl HeaderModifications {
+  /// Checks if there are no header modifications.
+  pub(super) fn is_empty(&self) -> bool {
+    self.headers_to_add.is_empty() && self.headers_to_replace.is_empty() && self.headers_to_remove.is_empty()
+  }
+
+  /// Resolves the placeholders in the header values against the client request.
+  /// Header values that are invalid after resolving the placeholders are skipped.
+  pub(super) fn resolve(
+    &self,
+    request_parts: &hyper::http::request::Parts,
+    socket_data: &SocketData,
+  ) -> ResolvedHeaderModifications {
+    let resolve_headers = |headers: &[(HeaderName, String)]| {
+      headers
+        .iter()
+        .filter_map(|(name, value)| {
+          HeaderValue::from_str(&replace_header_placeholders(value, request_parts, Some(socket_data)))
+            .ok()
+            .map(|value| (name.clone(), value))
+        })
+        .collect()
+    };
+    ResolvedHeaderModifications {
+      headers_to_add: resolve_headers(&self.headers_to_add),
+      headers_to_replace: resolve_headers(&self.headers_to_replace),
+      headers_to_remove: self.headers_to_remove.clone(),
+    }
+  }
+}
+
+/// Header modifications with the placeholders in the header values resolved.
+pub(super) struct ResolvedHeaderModifications {
+  headers_to_add: Vec<(HeaderName, HeaderValue)>,
+  headers_to_replace: Vec<(HeaderName, HeaderValue)>,
+  headers_to_remove: Vec<HeaderName>,
+}
+
+impl ResolvedHeaderModifications {
+  /// Applies the header modifications. The headers are added first (only if they weren't present before),
+  /// then replaced, and then removed. If the same header is specified multiple times, the last value is used.
+  pub(super) fn apply(&self, headers: &mut HeaderMap) {
+    let mut added_header_names: Vec<&HeaderName> = Vec::new();
+    for (header_name, header_value) in &self.headers_to_add {
+      if added_header_names.contains(&header_name) {
+        headers.insert(header_name, header_value.clone());
+      } else if !headers.contains_key(header_name) {
+        headers.insert(header_name, header_value.clone());
+        added_header_names.push(header_name);
+      }
+    }
+
+    for (header_name, header_value) in &self.headers_to_replace {
+      headers.insert(header_name, header_value.clone());
+    }
+
+    for header_name in &self.headers_to_remove {
+      headers.remove(header_name);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn header_values<'a>(headers: &'a HeaderMap, header_name: &str) -> Vec<&'a str> {
+    headers
+      .get_all(header_name)
+      .iter()
+      .map(|value| value.to_str().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_header_modifications_order() {
+    let (request_parts, _) = hyper::Request::builder()
+      .uri("/path")
+      .header("X-Tenant", "tenant1")
+      .body(())
+      .unwrap()
+      .into_parts();
+    let socket_data = SocketData {
+      remote_addr: "192.0.2.1:12345".parse().unwrap(),
+      local_addr: "127.0.0.1:443".parse().unwrap(),
+      encrypted: true,
+      tls_sni: Some("example.com".to_string()),
+      tls_alpn: None,
+    };
+    let header_modifications = HeaderModifications {
+      headers_to_add: vec![
+        (HeaderName::from_static("x-existing"), "added".to_string()),
+        (HeaderName::from_static("x-added"), "{remote_ip}".to_string()),
+        (HeaderName::from_static("x-added"), "{tls.sni}".to_string()),
+        (HeaderName::from_static("x-client-ip"), "{remote_ip}".to_string()),
+      ],
+      headers_to_replace: vec![
+        (HeaderName::from_static("x-replaced"), "first".to_string()),
+        (HeaderName::from_static("x-replaced"), "{header:X-Tenant}".to_string()),
+        (HeaderName::from_static("x-removed"), "replaced".to_string()),
+      ],
+      headers_to_remove: vec![HeaderName::from_static("x-removed")],
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-existing", HeaderValue::from_static("original"));
+    headers.insert("x-replaced", HeaderValue::from_static("original"));
+    headers.insert("x-removed", HeaderValue::from_static("original"));
+    header_modifications
+      .resolve(&request_parts, &socket_data)
+      .apply(&mut headers);
+
+    assert_eq!(header_values(&headers, "x-existing"), vec!["original"]);
+    assert_eq!(header_values(&headers, "x-added"), vec!["example.com"]);
+    assert_eq!(header_values(&headers, "x-client-ip"), vec!["192.0.2.1"]);
+    assert_eq!(header_values(&headers, "x-replaced"), vec!["tenant1"]);
+    assert!(!headers.contains_key("x-removed"));
+  }
+}
@@ -0,0 +1,202 @@
+use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::HeaderMap;
+
+/// The hop-by-hop headers (RFC 9110, section 7.6.1), other than `Connection` and `Upgrade`
+static HOP_BY_HOP_HEADERS: [HeaderName; 3] = [
+  HeaderName::from_static("keep-alive"),
+  HeaderName::from_static("proxy-connection"),
+  header::TE,
+];
+
+/// The handling of hop-by-hop headers and duplicate headers between clients and backend servers.
+#[derive(Clone, Debug, Default)]
+pub(super) struct HeaderForwarding {
+  /// Hop-by-hop headers that are passed between clients and backend servers
+  pub(super) pass_headers: Vec<HeaderName>,
+  /// Headers from backend server responses that aren't passed to clients
+  pub(super) hide_headers: Vec<HeaderName>,
+  /// Whether duplicate request headers are folded into a single header
+  pub(super) fold_headers: bool,
+}
+
+impl HeaderForwarding {
+  /// Removes the hop-by-hop headers (RFC 9110, section 7.6.1) from a request sent to a backend server,
+  /// and folds duplicate request headers. The `Upgrade` header is kept for HTTP upgrade requests,
+  /// and the `TE: trailers` header is kept, since it's required by some protocols (like gRPC).
+  /// Returns whether the request is an HTTP upgrade request.
+  pub(super) fn sanitize_request_headers(&self, headers: &mut HeaderMap) -> bool {
+    let connection_options = connection_options(headers);
+    let is_upgrade = headers.contains_key(header::UPGRADE) && connection_options.iter().any(|o| o == "upgrade");
+    let accepts_trailers = headers
+      .get_all(header::TE)
+      .iter()
+      .filter_map(|v| v.to_str().ok())
+      .flat_map(|v| v.split(','))
+      .any(|v| {
+        v.split(';')
+          .next()
+          .unwrap_or("")
+          .trim()
+          .eq_ignore_ascii_case("trailers")
+      });
+
+    self.remove_hop_by_hop_headers(headers, &connection_options, is_upgrade);
+    if accepts_trailers && !self.pass_headers.contains(&header::TE) {
+      headers.insert(header::TE, HeaderValue::from_static("trailers"));
+    }
+
+    // HTTP/2 and HTTP/3 clients can send the cookies in multiple headers, while HTTP/1.x allows only a single one
+    fold_header(headers, header::COOKIE, "; ");
+    if self.fold_headers {
+      let duplicate_header_names = headers
+        .keys()
+        .filter(|name| headers.get_all(*name).iter().nth(1).is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+      for header_name in duplicate_header_names {
+        fold_header(headers, header_name, ", ");
+      }
+    }
+
+    is_upgrade
+  }
+
+  /// Removes the hop-by-hop headers and the hidden headers from a response received from a backend server.
+  /// The `Upgrade` and `Connection` headers are kept for "101 Switching Protocols" responses.
+  pub(super) fn sanitize_response_headers(&self, headers: &mut HeaderMap, is_upgrade: bool) {
+    if !is_upgrade {
+      let connection_options = connection_options(headers);
+      self.remove_hop_by_hop_headers(headers, &connection_options, false);
+    }
+    for header_name in &self.hide_headers {
+      headers.remove(header_name);
+    }
+  }
+
+  /// Removes the hop-by-hop headers, except for the ones specified to be passed.
+  fn remove_hop_by_hop_headers(&self, headers: &mut HeaderMap, connection_options: &[String], keep_upgrade: bool) {
+    // The `Connection` and `Upgrade` headers are managed by the reverse proxy, so they can't be passed
+    headers.remove(header::CONNECTION);
+    if !keep_upgrade {
+      headers.remove(header::UPGRADE);
+    }
+    let connection_option_headers = connection_options
+      .iter()
+      .filter_map(|option| HeaderName::from_bytes(option.as_bytes()).ok());
+    for header_name in HOP_BY_HOP_HEADERS.iter().cloned().chain(connection_option_headers) {
+      if header_name != header::CONNECTION
+        && header_name != header::UPGRADE
+        && !self.pass_headers.contains(&header_name)
+      {
+        headers.remove(header_name);
+      }
+    }
+  }
+}
+
+/// Obtains the lowercase connection options from the `Connection` headers.
+fn connection_options(headers: &HeaderMap) -> Vec<String> {
+  headers
+    .get_all(header::CONNECTION)
+    .iter()
+    .filter_map(|v| v.to_str().ok())
+    .flat_map(|v| v.split(','))
+    .map(|v| v.trim().to_lowercase())
+    .filter(|v| !v.is_empty())
+    .collect()
+}
+
+/// Folds multiple headers with the same name into a single header, joining the values with the separator.
+fn fold_header(headers: &mut HeaderMap, header_name: HeaderName, separator: &str) {
+  let header::Entry::Occupied(entry) = headers.entry(&header_name) else {
+    return;
+  };
+  if entry.iter().nth(1).is_none() {
+    return;
+  }
+  let values = entry.iter().map(|v| v.as_bytes()).collect::<Vec<_>>();
+  if let Ok(folded_value) = HeaderValue::from_bytes(&values.join(separator.as_bytes())) {
+    headers.insert(header_name, folded_value);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn header_values<'a>(headers: &'a HeaderMap, header_name: &str) -> Vec<&'a str> {
+    headers
+      .get_all(header_name)
+      .iter()
+      .map(|value| value.to_str().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_sanitize_request_headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert("connection", HeaderValue::from_static("close, X-Internal"));
+    headers.insert("x-internal", HeaderValue::from_static("secret"));
+    headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+    headers.insert("te", HeaderValue::from_static("gzip, trailers;q=1"));
+    headers.insert("upgrade", HeaderValue::from_static("websocket"));
+    headers.append("cookie", HeaderValue::from_static("a=1"));
+    headers.append("cookie", HeaderValue::from_static("b=2"));
+    headers.append("accept", HeaderValue::from_static("text/html"));
+    headers.append("accept", HeaderValue::from_static("*/*"));
+
+    let is_upgrade = HeaderForwarding::default().sanitize_request_headers(&mut headers);
+
+    assert!(!is_upgrade);
+    for header_name in ["connection", "x-internal", "keep-alive", "upgrade"] {
+      assert!(!headers.contains_key(header_name), "{header_name} wasn't removed");
+    }
+    assert_eq!(header_values(&headers, "te"), vec!["trailers"]);
+    assert_eq!(header_values(&headers, "cookie"), vec!["a=1; b=2"]);
+    assert_eq!(header_values(&headers, "accept"), vec!["text/html", "*/*"]);
+  }
+
+  #[test]
+  fn test_sanitize_request_headers_upgrade_and_folding() {
+    let mut headers = HeaderMap::new();
+    headers.insert("connection", HeaderValue::from_static("Upgrade, Keep-Alive"));
+    headers.insert("upgrade", HeaderValue::from_static("websocket"));
+    headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+    headers.append("accept", HeaderValue::from_static("text/html"));
+    headers.append("accept", HeaderValue::from_static("*/*"));
+
+    let header_forwarding = HeaderForwarding {
+      pass_headers: vec![HeaderName::from_static("keep-alive")],
+      hide_headers: vec![],
+      fold_headers: true,
+    };
+    let is_upgrade = header_forwarding.sanitize_request_headers(&mut headers);
+
+    assert!(is_upgrade);
+    assert!(!headers.contains_key("connection"));
+    assert_eq!(header_values(&headers, "upgrade"), vec!["websocket"]);
+    assert_eq!(header_values(&headers, "keep-alive"), vec!["timeout=5"]);
+    assert_eq!(header_values(&headers, "accept"), vec!["text/html, */*"]);
+  }
+
+  #[test]
+  fn test_sanitize_response_headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert("connection", HeaderValue::from_static("close"));
+    headers.insert("upgrade", HeaderValue::from_static("h2c"));
+    headers.insert("x-powered-by", HeaderValue::from_static("backend"));
+    headers.insert("content-type", HeaderValue::from_static("text/plain"));
+
+    let header_forwarding = HeaderForwarding {
+      pass_headers: vec![],
+      hide_headers: vec![HeaderName::from_static("x-powered-by")],
+      fold_headers: false,
+    };
+    header_forwarding.sanitize_response_headers(&mut headers, false);
+
+    assert!(!headers.contains_key("connection"));
+    assert!(!headers.contains_key("upgrade"));
+    assert!(!headers.contains_key("x-powered-by"));
+    assert_eq!(header_values(&headers, "content-type"), vec!["text/plain"]);
+  }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use quinn::crypto::rustls::QuicClientConfig;
+use tokio::sync::{Mutex, RwLock};
+
+use super::{build_tls_client_config, UpstreamInner};
+use crate::util::{format_connect_address, strip_ip_literal_host, TtlCache};
+
+/// Timeout for establishing an HTTP/3 connection to a backend server.
+const HTTP3_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Duration for which HTTP/3 isn't attempted for a backend server after an HTTP/3 connection failure.
+const HTTP3_FALLBACK_DURATION: Duration = Duration::from_secs(300);
+
+/// A sender for HTTP/3 requests.
+pub(super) type Http3SendRequest = h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>;
+
+/// A pooled HTTP/3 connection to a backend server.
+struct Http3Connection {
+  connection: quinn::Connection,
+  sender: Http3SendRequest,
+}
+
+/// The key of a pooled HTTP/3 connection: the backend server, whether the certificate verification is disabled,
+/// and the number of required SCTs
+type Http3ConnectionKey = (UpstreamInner, bool, Option<usize>);
+
+/// An HTTP/3 client for backend servers, with QUIC connection pooling.
+///
+/// QUIC endpoints and connections are driven by a Tokio runtime, so they can be used from any runtime.
+pub(super) struct Http3Client {
+  runtime: tokio::runtime::Handle,
+  endpoints: Mutex<HashMap<bool, quinn::Endpoint>>,
+  connections: Mutex<HashMap<Http3ConnectionKey, Http3Connection>>,
+  unavailable: RwLock<TtlCache<UpstreamInner, ()>>,
+}
+
+impl Http3Client {
+  /// Creates a new HTTP/3 client, which uses the specified Tokio runtime.
+  pub(super) fn new(runtime: tokio::runtime::Handle) -> Self {
+    Self {
+      runtime,
+      endpoints: Mutex::new(HashMap::new()),
+      connections: Mutex::new(HashMap::new()),
+      unavailable: RwLock::new(TtlCache::new(HTTP3_FALLBACK_DURATION)),
+    }
+  }
+
+  /// Checks whether an HTTP/3 connection to a backend server failed recently.
+  pub(super) async fn is_unavailable(&self, upstream: &UpstreamInner) -> bool {
+    self.unavailable.read().await.get(upstream).is_some()
+  }
+
+  /// Obtains a sender for HTTP/3 requests to a backend server, either from a pooled connection, or a new connection.
+  /// Returns the sender, along with information on whether the connection was reused.
+  pub(super) async fn get_sender(
+    &self,
+    upstream: &UpstreamInner,
+    host: &str,
+    port: u16,
+    disable_certificate_verification: bool,
+    required_scts: Option<usize>,
+  ) -> Result<(Http3SendRequest, bool), Box<dyn Error + Send + Sync>> {
+    let key = (upstream.clone(), disable_certificate_verification, required_scts);
+    if let Some(pooled_connection) = self.connections.lock().await.get(&key) {
+      if pooled_connection.connection.close_reason().is_none() {
+        return Ok((pooled_connection.sender.clone(), true));
+      }
+    }
+
+    match self
+      .connect(host, port, disable_certificate_verification, required_scts)
+      .await
+    {
+      Ok(http3_connection) => {
+        let sender = http3_connection.sender.clone();
+        self.connections.lock().await.insert(key, http3_connection);
+        Ok((sender, false))
+      }
+      Err(err) => {
+        self.connections.lock().await.remove(&key);
+        self.unavailable.write().await.insert(upstream.clone(), ());
+        Err(err)
+      }
+    }
+  }
+
+  /// Establishes a new HTTP/3 connection to a backend server.
+  async fn connect(
+    &self,
+    host: &str,
+    port: u16,
+    disable_certificate_verification: bool,
+    required_scts: Option<usize>,
+  ) -> Result<Http3Connection, Box<dyn Error + Send + Sync>> {
+    let mut tls_client_config = build_tls_client_config(disable_certificate_verification, required_scts)?;
+    tls_client_config.alpn_protocols = vec![b"h3".to_vec()];
+    let client_config = quinn::ClientConfig::new(Arc::new(QuicClientConfig::try_from(tls_client_config)?));
+
+    let server_name = strip_ip_literal_host(host);
+    let addr = format_connect_address(host, port);
+    let address = self
+      .runtime
+      .spawn(async move { tokio::net::lookup_host(addr).await.map(|mut addrs| addrs.next()) })
+      .await??
+      .ok_or(anyhow::anyhow!("The backend server address can't be resolved"))?;
+    let endpoint = self.get_endpoint(address).await?;
+
+    // The QUIC connection is established in the Tokio runtime, so that its driver is spawned there
+    self
+      .runtime
+      .spawn(async move {
+        let connection = tokio::time::timeout(
+          HTTP3_CONNECT_TIMEOUT,
+          endpoint.connect_with(client_config, address, &server_name)?,
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("HTTP/3 connection timed out"))??;
+        let (mut driver, sender) = h3::client::new(h3_quinn::Connection::new(connection.clone())).await?;
+        tokio::spawn(async move {
+          let _ = futures_util::future::poll_fn(|cx| driver.poll_close(cx)).await;
+        });
+        Ok::<_, Box<dyn Error + Send + Sync>>(Http3Connection { connection, sender })
+      })
+      .await?
+  }
+
+  /// Obtains a QUIC client endpoint for the address family of the backend server address.
+  async fn get_endpoint(&self, address: SocketAddr) -> Result<quinn::Endpoint, Box<dyn Error + Send + Sync>> {
+    let is_ipv6 = address.is_ipv6();
+    let mut endpoints = self.endpoints.lock().await;
+    if let Some(endpoint) = endpoints.get(&is_ipv6) {
+      return Ok(endpoint.clone());
+    }
+    let bind_address = if is_ipv6 {
+      SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0)
+    } else {
+      SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0)
+    };
+    let endpoint = self
+      .runtime
+      .spawn(async move {
+        let socket = UdpSocket::bind(bind_address)?;
+        quinn::Endpoint::new(
+          quinn::EndpointConfig::default(),
+          None,
+          socket,
+          Arc::new(quinn::TokioRuntime),
+        )
+      })
+      .await??;
+    endpoints.insert(is_ipv6, endpoint.clone());
+    Ok(endpoint)
+  }
+}
@@ -1,9 +1,20 @@
+mod backup;
 mod builder;
+mod circuit_breaker;
+mod cookies;
+mod header_modifications;
+mod hop_by_hop;
+mod http3;
 mod load_balancer;
+mod outlier_detection;
 mod proxy_client;
 mod request_parts;
 mod send_net_io;
 mod send_request;
+mod signing;
+mod sticky_sessions;
+mod timer;
+mod transfer;
 
 use std::collections::HashMap;
 use std::error::Error;
@@ -12,23 +23,26 @@ use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::task::{Context, Poll, Waker};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use connpool::{Item, Pool};
 use futures_util::FutureExt;
 use http_body_util::combinators::BoxBody;
-use hyper::header::{self, HeaderName};
-use hyper::{Request, StatusCode, Uri};
+use http_body_util::{BodyExt, Empty, Full, LengthLimitError, Limited};
+use hyper::body::Body;
+use hyper::header;
+use hyper::{Request, Response, StatusCode, Uri};
 #[cfg(feature = "runtime-monoio")]
 use monoio::net::TcpStream;
 #[cfg(all(feature = "runtime-monoio", unix))]
 use monoio::net::UnixStream;
+use rustls::client::danger::ServerCertVerifier;
 use rustls::client::WebPkiServerVerifier;
 use rustls_pki_types::ServerName;
-use rustls_platform_verifier::BuilderVerifierExt;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 #[cfg(feature = "runtime-tokio")]
 use tokio::net::TcpStream;
@@ -42,23 +56,74 @@ use vibeio::net::TcpStream;
 use vibeio::net::UnixStream;
 
 use crate::config::ServerConfiguration;
-use crate::http_proxy::send_request::SendRequestWrapper;
-use crate::logging::ErrorLogger;
+use crate::http_proxy::send_request::{ConnectionReuse, SendRequest, SendRequestWrapper};
+use crate::logging::{ErrorLogger, LogLevel};
 use crate::modules::{ModuleHandlers, ResponseData, SocketData};
 use crate::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
-use crate::util::{NoServerVerifier, TtlCache};
+use crate::util::{
+  format_connect_address, is_streaming_response, replace_header_placeholders, strip_ip_literal_host,
+  CtEnforcingVerifier, ErrorCode, NoServerVerifier, ResponseFlushInterval, StreamingResponse, TtlCache,
+};
 
 pub use self::builder::ReverseProxyBuilder;
+pub use self::circuit_breaker::CircuitBreaker;
+pub use self::cookies::ProxyCookieFlags;
+pub use self::outlier_detection::OutlierDetection;
 #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
 use self::send_net_io::{SendTcpStreamPoll, SendTcpStreamPollDropGuard};
 #[cfg(all(any(feature = "runtime-vibeio", feature = "runtime-monoio"), unix))]
 use self::send_net_io::{SendUnixStreamPoll, SendUnixStreamPollDropGuard};
+pub use self::signing::{AwsCredentials, ProxyRequestSigning};
+pub use self::sticky_sessions::StickySessions;
 use self::{
+  backup::{BackupFailover, UpstreamSet},
+  circuit_breaker::{CircuitBreakers, CircuitState},
+  cookies::ProxyCookieRewrites,
+  header_modifications::{HeaderModifications, ResolvedHeaderModifications},
+  hop_by_hop::HeaderForwarding,
+  http3::Http3Client,
   load_balancer::{determine_proxy_to, resolve_upstreams},
-  proxy_client::{http_proxy, http_proxy_handshake},
-  request_parts::construct_proxy_request_parts,
+  outlier_detection::OutlierDetector,
+  proxy_client::{http3_proxy, http_proxy, http_proxy_handshake, StreamingResponseBody},
+  request_parts::{construct_proxy_request_parts, ForwardedFor},
+  signing::{payload_hash, MAX_SIGNED_BODY_SIZE},
+  sticky_sessions::{DrainEvent, SessionAffinity},
+  transfer::{BackendTransfer, TransferCountingBody, TransferDirection},
 };
 
+/// The target (module name) of leveled log messages logged by the reverse proxy
+const LOG_TARGET: &str = "http_proxy";
+
+/// Builds a TLS client configuration for connections to backend servers.
+/// If `required_scts` is specified, the backend server certificates must have embedded SCTs
+/// from at least that many distinct certificate transparency logs.
+fn build_tls_client_config(
+  disable_certificate_verification: bool,
+  required_scts: Option<usize>,
+) -> Result<rustls::ClientConfig, Box<dyn Error + Send + Sync>> {
+  let builder = rustls::ClientConfig::builder();
+  let verifier: Arc<dyn ServerCertVerifier> = if disable_certificate_verification {
+    Arc::new(NoServerVerifier::new())
+  } else if let Ok(verifier) = rustls_platform_verifier::Verifier::new(builder.crypto_provider().clone()) {
+    Arc::new(verifier)
+  } else {
+    WebPkiServerVerifier::builder(Arc::new(rustls::RootCertStore {
+      roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    }))
+    .build()?
+  };
+  let verifier: Arc<dyn ServerCertVerifier> = match required_scts {
+    Some(required_scts) => Arc::new(CtEnforcingVerifier::new(verifier, required_scts)),
+    None => verifier,
+  };
+  Ok(
+    builder
+      .dangerous()
+      .with_custom_certificate_verifier(verifier)
+      .with_no_client_auth(),
+  )
+}
+
 type ConnectionsTrackState = Arc<RwLock<HashMap<UpstreamInner, Arc<()>>>>;
 
 enum LoadBalancerAlgorithmInner {
@@ -90,6 +155,125 @@ pub enum ProxyHeader {
   V2,
 }
 
+/// The `Host` header sent to backend servers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProxyHostHeader {
+  /// The `Host` header from the client request.
+  Preserve,
+  /// The authority of the backend server URL.
+  Upstream,
+  /// A custom value, which can contain header placeholders.
+  Custom(String),
+}
+
+/// TCP keepalive settings for connections to backend servers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcpKeepalive {
+  /// Idle time after which TCP keepalive probes are sent.
+  pub time: Duration,
+  /// Interval between TCP keepalive probes.
+  pub interval: Duration,
+}
+
+/// HTTP/2 PING settings for connections to backend servers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Http2Keepalive {
+  /// Interval between HTTP/2 PING frames.
+  pub interval: Duration,
+  /// Timeout for HTTP/2 PING acknowledgements, after which the connection is closed.
+  pub timeout: Duration,
+}
+
+/// Enables TCP keepalive on a connection to a backend server.
+fn set_tcp_keepalive(stream: &TcpStream, tcp_keepalive: TcpKeepalive) -> Result<(), std::io::Error> {
+  // Safety: the socket is borrowed only for the duration of this function, while the stream is alive
+  #[cfg(unix)]
+  let socket = unsafe { std::os::fd::BorrowedFd::borrow_raw(std::os::fd::AsRawFd::as_raw_fd(stream)) };
+  #[cfg(windows)]
+  let socket = unsafe {
+    std::os::windows::io::BorrowedSocket::borrow_raw(std::os::windows::io::AsRawSocket::as_raw_socket(stream))
+  };
+  let keepalive = socket2::TcpKeepalive::new().with_time(tcp_keepalive.time);
+  #[cfg(any(
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "illumos",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    windows
+  ))]
+  let keepalive = keepalive.with_interval(tcp_keepalive.interval);
+  socket2::SockRef::from(&socket).set_tcp_keepalive(&keepalive)
+}
+
+/// Conditions under which a request is passed to the next backend server.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProxyNextUpstream {
+  error: bool,
+  timeout: bool,
+  non_idempotent: bool,
+  status_codes: Vec<StatusCode>,
+}
+
+impl ProxyNextUpstream {
+  /// Creates conditions that never pass a request to the next backend server.
+  pub fn off() -> Self {
+    Self::default()
+  }
+
+  /// Creates conditions that pass a request to the next backend server on connection errors and timeouts.
+  pub fn error_and_timeout() -> Self {
+    Self {
+      error: true,
+      timeout: true,
+      ..Default::default()
+    }
+  }
+
+  /// Adds a condition (`error`, `timeout`, `non_idempotent`, `http_<status_code>` or `off`).
+  pub fn add_condition(&mut self, condition: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match condition {
+      "off" => *self = Self::off(),
+      "error" => self.error = true,
+      "timeout" => self.timeout = true,
+      "non_idempotent" => self.non_idempotent = true,
+      _ => {
+        let status_code = condition
+          .strip_prefix("http_")
+          .and_then(|status_code| status_code.parse::<u16>().ok())
+          .and_then(|status_code| StatusCode::from_u16(status_code).ok())
+          .filter(|status_code| status_code.is_client_error() || status_code.is_server_error())
+          .ok_or(anyhow::anyhow!(
+            "Invalid condition for passing a request to the next backend server: {condition}"
+          ))?;
+        if !self.status_codes.contains(&status_code) {
+          self.status_codes.push(status_code);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks whether a request is passed to the next backend server after a connection I/O error.
+  #[inline]
+  fn retries_on_io_error(&self, err: &std::io::Error) -> bool {
+    if err.kind() == std::io::ErrorKind::TimedOut {
+      self.timeout
+    } else {
+      self.error
+    }
+  }
+
+  /// Checks whether a request that was already sent to a backend server can be sent to the next one.
+  #[inline]
+  fn can_resend_request(&self, method: &hyper::Method, has_empty_body: bool) -> bool {
+    // The request body isn't buffered, so only requests without a body can be sent again
+    has_empty_body && (self.non_idempotent || method.is_idempotent())
+  }
+}
+
 #[derive(Clone, Eq, PartialEq, Hash)]
 struct UpstreamInner {
   proxy_to: String,
@@ -211,6 +395,7 @@ type ProxyToKeyInner = (UpstreamInner, Option<usize>, Option<Duration>);
 
 type ConnectionPool = Arc<Pool<(UpstreamInner, Option<IpAddr>), SendRequestWrapper>>;
 type ConnectionPoolItem = Item<(UpstreamInner, Option<IpAddr>), SendRequestWrapper>;
+type IdleConnectionCounts = Arc<Mutex<HashMap<UpstreamInner, Arc<AtomicUsize>>>>;
 
 #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
 #[allow(unused)]
@@ -317,9 +502,35 @@ pub struct Connections {
     (Duration, u64, Arc<Vec<(Upstream, Option<usize>, Option<Duration>)>>),
     Arc<RwLock<TtlCache<UpstreamInner, u64>>>,
   >,
+  #[allow(clippy::type_complexity)]
+  outlier_detector_cache:
+    HashMap<(OutlierDetection, Arc<Vec<(Upstream, Option<usize>, Option<Duration>)>>), Arc<OutlierDetector>>,
+  #[allow(clippy::type_complexity)]
+  circuit_breaker_cache:
+    HashMap<(CircuitBreaker, Arc<Vec<(Upstream, Option<usize>, Option<Duration>)>>), Arc<CircuitBreakers>>,
+  #[allow(clippy::type_complexity)]
+  backup_failover_cache: HashMap<
+    (
+      Duration,
+      Vec<UpstreamInner>,
+      Arc<Vec<(Upstream, Option<usize>, Option<Duration>)>>,
+    ),
+    Arc<BackupFailover>,
+  >,
+  #[allow(clippy::type_complexity)]
+  session_affinity_cache: HashMap<
+    (
+      StickySessions,
+      Vec<UpstreamInner>,
+      Arc<Vec<(Upstream, Option<usize>, Option<Duration>)>>,
+    ),
+    Arc<SessionAffinity>,
+  >,
   connections: ConnectionPool,
   #[cfg(unix)]
   unix_connections: ConnectionPool,
+  idle_connections: IdleConnectionCounts,
+  http3_client: Option<Arc<Http3Client>>,
 }
 
 impl Connections {
@@ -328,9 +539,15 @@ impl Connections {
     Self {
       load_balancer_cache: HashMap::new(),
       failed_backend_cache: HashMap::new(),
+      outlier_detector_cache: HashMap::new(),
+      circuit_breaker_cache: HashMap::new(),
+      backup_failover_cache: HashMap::new(),
+      session_affinity_cache: HashMap::new(),
       connections: Arc::new(Pool::new_unbounded()),
       #[cfg(unix)]
       unix_connections: Arc::new(Pool::new_unbounded()),
+      idle_connections: Arc::new(Mutex::new(HashMap::new())),
+      http3_client: None,
     }
   }
 
@@ -341,9 +558,15 @@ impl Connections {
     Self {
       load_balancer_cache: HashMap::new(),
       failed_backend_cache: HashMap::new(),
+      outlier_detector_cache: HashMap::new(),
+      circuit_breaker_cache: HashMap::new(),
+      backup_failover_cache: HashMap::new(),
+      session_affinity_cache: HashMap::new(),
       connections: Arc::new(Pool::new(global_limit)),
       #[cfg(unix)]
       unix_connections: Arc::new(Pool::new_unbounded()),
+      idle_connections: Arc::new(Mutex::new(HashMap::new())),
+      http3_client: None,
     }
   }
 
@@ -352,21 +575,43 @@ impl Connections {
     ReverseProxyBuilder {
       connections: self,
       upstreams: Vec::new(),
+      backup_upstreams: Vec::new(),
       lb_algorithm: LoadBalancerAlgorithm::TwoRandomChoices,
       lb_health_check_window: Duration::from_millis(5000),
       lb_health_check_max_fails: 3,
       lb_health_check: false,
+      lb_failback_delay: Duration::from_secs(30),
+      sticky_sessions: None,
+      draining_upstreams: Vec::new(),
       proxy_no_verification: false,
+      proxy_require_ct: None,
       proxy_intercept_errors: false,
-      lb_retry_connection: true,
+      proxy_sse: false,
+      proxy_flush_interval: None,
+      proxy_next_upstream: ProxyNextUpstream::error_and_timeout(),
+      outlier_detection: None,
+      circuit_breaker: None,
       proxy_http2_only: false,
       proxy_http2: false,
+      proxy_http3: false,
+      http3_runtime: None,
+      proxy_tcp_keepalive: None,
+      proxy_http2_keepalive: None,
       proxy_keepalive: true,
+      proxy_pool_max_idle_per_host: None,
+      proxy_max_requests_per_connection: None,
       proxy_proxy_header: None,
       proxy_request_header: Vec::new(),
       proxy_request_header_replace: Vec::new(),
       proxy_request_header_remove: Vec::new(),
-      rewrite_host: false,
+      proxy_response_header: Vec::new(),
+      proxy_response_header_replace: Vec::new(),
+      proxy_response_header_remove: Vec::new(),
+      proxy_cookie_rewrites: ProxyCookieRewrites::default(),
+      proxy_header_forwarding: HeaderForwarding::default(),
+      proxy_host_header: None,
+      proxy_forwarded_for: ForwardedFor::default(),
+      proxy_sign: None,
     }
   }
 }
@@ -386,19 +631,36 @@ pub struct ReverseProxy {
   health_check_max_fails: u64,
   enable_health_check: bool,
   disable_certificate_verification: bool,
+  required_scts: Option<usize>,
   proxy_intercept_errors: bool,
-  retry_connection: bool,
+  proxy_sse: bool,
+  proxy_flush_interval: Option<Duration>,
+  proxy_next_upstream: Arc<ProxyNextUpstream>,
+  outlier_detector: Option<Arc<OutlierDetector>>,
+  circuit_breakers: Option<Arc<CircuitBreakers>>,
+  backup_failover: Option<Arc<BackupFailover>>,
+  session_affinity: Option<Arc<SessionAffinity>>,
   proxy_http2_only: bool,
   proxy_http2: bool,
+  proxy_http3: bool,
+  http3_client: Option<Arc<Http3Client>>,
+  proxy_tcp_keepalive: Option<TcpKeepalive>,
+  proxy_http2_keepalive: Option<Http2Keepalive>,
   proxy_keepalive: bool,
+  pool_max_idle_per_host: Option<usize>,
+  max_requests_per_connection: Option<u64>,
   proxy_header: Option<ProxyHeader>,
-  headers_to_add: Arc<Vec<(HeaderName, String)>>,
-  headers_to_replace: Arc<Vec<(HeaderName, String)>>,
-  headers_to_remove: Arc<Vec<HeaderName>>,
-  rewrite_host: bool,
+  request_header_modifications: Arc<HeaderModifications>,
+  response_header_modifications: Arc<HeaderModifications>,
+  cookie_rewrites: Arc<ProxyCookieRewrites>,
+  header_forwarding: Arc<HeaderForwarding>,
+  host_header: Option<ProxyHostHeader>,
+  forwarded_for: ForwardedFor,
+  signing: Option<Arc<ProxyRequestSigning>>,
   connections: ConnectionPool,
   #[cfg(unix)]
   unix_connections: ConnectionPool,
+  idle_connections: IdleConnectionCounts,
 }
 
 impl ReverseProxy {
@@ -411,22 +673,204 @@ impl ReverseProxy {
       health_check_max_fails: self.health_check_max_fails,
       selected_backends_metrics: None,
       unhealthy_backends_metrics: None,
+      ejected_backends_metrics: None,
+      circuit_breaker_transitions_metrics: None,
+      backup_transitions_metrics: None,
+      drain_metrics: None,
+      sticky_session_cookie: None,
+      backend_transfer: None,
       connection_reused: false,
       enable_health_check: self.enable_health_check,
       disable_certificate_verification: self.disable_certificate_verification,
+      required_scts: self.required_scts,
       proxy_intercept_errors: self.proxy_intercept_errors,
-      retry_connection: self.retry_connection,
+      proxy_sse: self.proxy_sse,
+      proxy_flush_interval: self.proxy_flush_interval,
+      streaming_metrics_sender: None,
+      proxy_next_upstream: self.proxy_next_upstream.clone(),
+      outlier_detector: self.outlier_detector.clone(),
+      circuit_breakers: self.circuit_breakers.clone(),
+      backup_failover: self.backup_failover.clone(),
+      session_affinity: self.session_affinity.clone(),
       proxy_http2_only: self.proxy_http2_only,
       proxy_http2: self.proxy_http2,
+      proxy_http3: self.proxy_http3,
+      http3_client: self.http3_client.clone(),
+      proxy_tcp_keepalive: self.proxy_tcp_keepalive,
+      proxy_http2_keepalive: self.proxy_http2_keepalive,
       proxy_keepalive: self.proxy_keepalive,
+      pool_max_idle_per_host: self.pool_max_idle_per_host,
+      max_requests_per_connection: self.max_requests_per_connection,
       proxy_header: self.proxy_header,
-      headers_to_add: self.headers_to_add.clone(),
-      headers_to_replace: self.headers_to_replace.clone(),
-      headers_to_remove: self.headers_to_remove.clone(),
-      rewrite_host: self.rewrite_host,
+      request_header_modifications: self.request_header_modifications.clone(),
+      response_header_modifications: self.response_header_modifications.clone(),
+      resolved_response_header_modifications: None,
+      cookie_rewrites: self.cookie_rewrites.clone(),
+      header_forwarding: self.header_forwarding.clone(),
+      host_header: self.host_header.clone(),
+      forwarded_for: self.forwarded_for,
+      signing: self.signing.clone(),
       connections: self.connections.clone(),
       #[cfg(unix)]
       unix_connections: self.unix_connections.clone(),
+      idle_connections: self.idle_connections.clone(),
+    }
+  }
+
+  /// Pre-establishes connections to the backend servers, and puts them into the connection pool.
+  /// This must be called on a request handler thread, because the connections are bound to its async runtime.
+  ///
+  /// Backend servers resolved via SRV records and HTTP/3 backend servers are skipped.
+  /// Connections aren't pre-established, if keep-alive is disabled or PROXY protocol headers are sent,
+  /// as the PROXY protocol headers depend on the client address.
+  pub async fn warm_up(&self, connections_per_backend: usize, error_logger: &ErrorLogger) {
+    if !self.proxy_keepalive || self.proxy_header.is_some() {
+      return;
+    }
+    for (upstream, local_limit_index, _) in self.proxy_to.iter() {
+      let Upstream::Static(upstream) = upstream else {
+        continue;
+      };
+      #[cfg(unix)]
+      let connections = if upstream.proxy_unix.is_some() {
+        &self.unix_connections
+      } else {
+        &self.connections
+      };
+      #[cfg(not(unix))]
+      let connections = &self.connections;
+
+      // The connection pool items are held until all the connections are established,
+      // so that pooled connections aren't pulled again. The items are put back into the pool when dropped.
+      let mut connection_pool_items = Vec::new();
+      for _ in 0..connections_per_backend {
+        let mut connection_pool_item = if let Poll::Ready(connection_pool_item) = connections
+          .pull_with_wait_local_limit((upstream.clone(), None), *local_limit_index)
+          .boxed_local()
+          .poll_unpin(&mut Context::from_waker(Waker::noop()))
+        {
+          connection_pool_item
+        } else {
+          // The connection limit is reached
+          break;
+        };
+        if connection_pool_item.inner_mut().is_none() {
+          match self.establish_connection(upstream).await {
+            Ok(sender) => {
+              let connection_reuse = ConnectionReuse {
+                served_requests: 0,
+                max_requests: self.max_requests_per_connection,
+                idle_connections: self.pool_max_idle_per_host.and_then(|limit| {
+                  let mut idle_connections = self.idle_connections.lock().ok()?;
+                  Some((idle_connections.entry(upstream.clone()).or_default().clone(), limit))
+                }),
+              };
+              if let Some(sender) = connection_reuse.wrap(sender) {
+                connection_pool_item.inner_mut().replace(sender);
+              }
+            }
+            Err(err) => {
+              error_logger
+                .log(&format!(
+                  "Failed to pre-establish a connection to the backend server \"{}\": {err}",
+                  upstream.proxy_to
+                ))
+                .await;
+              break;
+            }
+          }
+        }
+        connection_pool_items.push(connection_pool_item);
+      }
+    }
+  }
+
+  /// Establishes a new connection to a backend server, used to warm up the connection pool.
+  async fn establish_connection(&self, upstream: &UpstreamInner) -> Result<SendRequest, Box<dyn Error + Send + Sync>> {
+    let proxy_request_url = upstream.proxy_to.parse::<hyper::Uri>()?;
+    let (encrypted, use_h2c) = match proxy_request_url.scheme_str() {
+      Some("http") => (false, false),
+      Some("https") => (true, false),
+      Some("h2c") => (false, true),
+      _ => Err(anyhow::anyhow!(
+        "Only HTTP, HTTPS and h2c reverse proxy URLs are supported for pre-established connections."
+      ))?,
+    };
+    let host = match proxy_request_url.host() {
+      Some(host) => host,
+      None => Err(anyhow::anyhow!("The reverse proxy URL doesn't include the host"))?,
+    };
+    let port = proxy_request_url.port_u16().unwrap_or(if encrypted { 443 } else { 80 });
+
+    let stream = if let Some(proxy_unix_str) = &upstream.proxy_unix {
+      #[cfg(not(unix))]
+      {
+        let _ = proxy_unix_str; // Discard the variable to avoid unused variable warning
+        Err(anyhow::anyhow!("Unix sockets are not supported on this platform"))?
+      }
+
+      #[cfg(unix)]
+      {
+        let stream = UnixStream::connect(proxy_unix_str).await?;
+        #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+        let stream = SendUnixStreamPoll::new_comp_io(stream)?;
+        Connection::Unix(stream)
+      }
+    } else {
+      let stream = TcpStream::connect(&format_connect_address(host, port)).await?;
+      stream.set_nodelay(true)?;
+      if let Some(tcp_keepalive) = self.proxy_tcp_keepalive {
+        set_tcp_keepalive(&stream, tcp_keepalive).unwrap_or_default();
+      }
+      #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+      let stream = SendTcpStreamPoll::new_comp_io(stream)?;
+      Connection::Tcp(stream)
+    };
+
+    // Safety: the drop guard is dropped when the connection future is completed,
+    // and after the underlying connection is moved across threads,
+    // see the "http_proxy_handshake" function.
+    #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+    let (stream, drop_guard) = {
+      let mut stream = stream;
+      let drop_guard = unsafe { stream.get_drop_guard() };
+      (stream, drop_guard)
+    };
+
+    if !encrypted {
+      http_proxy_handshake(
+        stream,
+        self.proxy_http2_only || use_h2c,
+        self.proxy_http2_keepalive,
+        #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+        drop_guard,
+      )
+      .await
+    } else {
+      let enable_http2_config = self.proxy_http2_only || self.proxy_http2;
+      let mut tls_client_config = build_tls_client_config(self.disable_certificate_verification, self.required_scts)?;
+      if self.proxy_http2_only {
+        tls_client_config.alpn_protocols = vec![b"h2".to_vec()];
+      } else if enable_http2_config {
+        tls_client_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec(), b"http/1.0".to_vec()];
+      } else {
+        tls_client_config.alpn_protocols = vec![b"http/1.1".to_vec(), b"http/1.0".to_vec()];
+      }
+      let connector = TlsConnector::from(Arc::new(tls_client_config));
+      let domain = ServerName::try_from(strip_ip_literal_host(host))?;
+      let tls_stream = connector.connect(domain, stream).await?;
+
+      // Enable HTTP/2 when the ALPN protocol is "h2"
+      let enable_http2 = enable_http2_config && tls_stream.get_ref().1.alpn_protocol() == Some(b"h2");
+
+      http_proxy_handshake(
+        tls_stream,
+        enable_http2,
+        self.proxy_http2_keepalive,
+        #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+        drop_guard,
+      )
+      .await
     }
   }
 }
@@ -440,22 +884,47 @@ pub struct ReverseProxyHandler {
   health_check_max_fails: u64,
   selected_backends_metrics: Option<Vec<UpstreamInner>>,
   unhealthy_backends_metrics: Option<Vec<UpstreamInner>>,
+  ejected_backends_metrics: Option<Vec<UpstreamInner>>,
+  circuit_breaker_transitions_metrics: Option<Vec<(UpstreamInner, CircuitState)>>,
+  backup_transitions_metrics: Option<Vec<UpstreamSet>>,
+  drain_metrics: Option<Vec<DrainEvent>>,
+  sticky_session_cookie: Option<header::HeaderValue>,
+  backend_transfer: Option<Arc<BackendTransfer>>,
   connection_reused: bool,
   enable_health_check: bool,
   disable_certificate_verification: bool,
+  required_scts: Option<usize>,
   proxy_intercept_errors: bool,
-  retry_connection: bool,
+  proxy_sse: bool,
+  proxy_flush_interval: Option<Duration>,
+  streaming_metrics_sender: Option<MetricsMultiSender>,
+  proxy_next_upstream: Arc<ProxyNextUpstream>,
+  outlier_detector: Option<Arc<OutlierDetector>>,
+  circuit_breakers: Option<Arc<CircuitBreakers>>,
+  backup_failover: Option<Arc<BackupFailover>>,
+  session_affinity: Option<Arc<SessionAffinity>>,
   proxy_http2_only: bool,
   proxy_http2: bool,
+  proxy_http3: bool,
+  http3_client: Option<Arc<Http3Client>>,
+  proxy_tcp_keepalive: Option<TcpKeepalive>,
+  proxy_http2_keepalive: Option<Http2Keepalive>,
   proxy_keepalive: bool,
+  pool_max_idle_per_host: Option<usize>,
+  max_requests_per_connection: Option<u64>,
   proxy_header: Option<ProxyHeader>,
-  headers_to_add: Arc<Vec<(HeaderName, String)>>,
-  headers_to_replace: Arc<Vec<(HeaderName, String)>>,
-  headers_to_remove: Arc<Vec<HeaderName>>,
-  rewrite_host: bool,
+  request_header_modifications: Arc<HeaderModifications>,
+  response_header_modifications: Arc<HeaderModifications>,
+  resolved_response_header_modifications: Option<ResolvedHeaderModifications>,
+  cookie_rewrites: Arc<ProxyCookieRewrites>,
+  header_forwarding: Arc<HeaderForwarding>,
+  host_header: Option<ProxyHostHeader>,
+  forwarded_for: ForwardedFor,
+  signing: Option<Arc<ProxyRequestSigning>>,
   connections: ConnectionPool,
   #[cfg(unix)]
   unix_connections: ConnectionPool,
+  idle_connections: IdleConnectionCounts,
 }
 
 impl ReverseProxyHandler {
@@ -470,7 +939,31 @@ impl ReverseProxyHandler {
     }
   }
 
-  async fn mark_backend_failure(&mut self, upstream: &UpstreamInner) {
+  /// Creates an error response with the internal error code
+  fn error_code_response(error_code: ErrorCode) -> ResponseData {
+    ResponseData {
+      request: None,
+      response: None,
+      response_status: Some(error_code.status_code()),
+      response_headers: Some(error_code.to_headers()),
+      new_remote_address: None,
+    }
+  }
+
+  /// Obtains the limits for putting a connection to a backend server back into the connection pool.
+  fn connection_reuse(&self, upstream: &UpstreamInner, served_requests: u64) -> ConnectionReuse {
+    ConnectionReuse {
+      served_requests,
+      max_requests: self.max_requests_per_connection,
+      idle_connections: self.pool_max_idle_per_host.and_then(|limit| {
+        let mut idle_connections = self.idle_connections.lock().ok()?;
+        Some((idle_connections.entry(upstream.clone()).or_default().clone(), limit))
+      }),
+    }
+  }
+
+  async fn mark_backend_failure(&mut self, upstream: &UpstreamInner, error_logger: &ErrorLogger) {
+    self.record_backend_outcome(upstream, false, None, error_logger).await;
     if !self.enable_health_check {
       return;
     }
@@ -482,34 +975,276 @@ impl ReverseProxyHandler {
     failed_backends_write.insert(upstream.clone(), failed_attempts.map_or(1, |x| x + 1));
   }
 
+  async fn record_backend_outcome(
+    &mut self,
+    upstream: &UpstreamInner,
+    success: bool,
+    latency: Option<Duration>,
+    error_logger: &ErrorLogger,
+  ) {
+    if let Some(transition) = self
+      .circuit_breakers
+      .as_ref()
+      .and_then(|circuit_breakers| circuit_breakers.record(upstream, success))
+    {
+      self.record_circuit_transition(upstream, transition, error_logger).await;
+    }
+    let ejected = match self.outlier_detector.as_ref() {
+      Some(outlier_detector) => outlier_detector.record(upstream, success, latency),
+      None => return,
+    };
+    for (ejected_upstream, ejection_time) in ejected {
+      error_logger
+        .log(&format!(
+          "Backend server {} ejected as an outlier for {} seconds",
+          ejected_upstream.proxy_to,
+          ejection_time.as_secs()
+        ))
+        .await;
+      if let Some(ejected_backends_metrics) = self.ejected_backends_metrics.as_mut() {
+        ejected_backends_metrics.push(ejected_upstream);
+      }
+    }
+  }
+
+  async fn record_circuit_transition(
+    &mut self,
+    upstream: &UpstreamInner,
+    (previous_state, state): (CircuitState, CircuitState),
+    error_logger: &ErrorLogger,
+  ) {
+    error_logger
+      .log(&format!(
+        "Circuit breaker for backend server {} changed state from {} to {}",
+        upstream.proxy_to,
+        previous_state.as_str(),
+        state.as_str()
+      ))
+      .await;
+    if let Some(circuit_breaker_transitions_metrics) = self.circuit_breaker_transitions_metrics.as_mut() {
+      circuit_breaker_transitions_metrics.push((upstream.clone(), state));
+    }
+  }
+
+  async fn record_backup_transition(
+    &mut self,
+    (previous_set, set): (UpstreamSet, UpstreamSet),
+    error_logger: &ErrorLogger,
+  ) {
+    error_logger
+      .log(&format!(
+        "Requests switched from the {} backend servers to the {} backend servers",
+        previous_set.as_str(),
+        set.as_str()
+      ))
+      .await;
+    if let Some(backup_transitions_metrics) = self.backup_transitions_metrics.as_mut() {
+      backup_transitions_metrics.push(set);
+    }
+  }
+
+  async fn record_drain_events(&mut self, events: Vec<DrainEvent>, error_logger: &ErrorLogger) {
+    for event in events {
+      if let DrainEvent::Drained(upstream) = &event {
+        error_logger
+          .log(&format!(
+            "Draining backend server {} was removed",
+            upstream.proxy_unix.as_deref().unwrap_or(&upstream.proxy_to)
+          ))
+          .await;
+      }
+      if let Some(drain_metrics) = self.drain_metrics.as_mut() {
+        drain_metrics.push(event);
+      }
+    }
+  }
+
+  /// Records the outcome of forwarding a request to a backend server for outlier detection and circuit breaking.
+  async fn record_proxy_result(
+    &mut self,
+    upstream: &UpstreamInner,
+    result: &Result<ResponseData, impl std::fmt::Display>,
+    request_start: Instant,
+    error_logger: &ErrorLogger,
+  ) {
+    if error_logger.is_enabled(LogLevel::Debug, LOG_TARGET) {
+      let outcome = match result {
+        Ok(response) => format!(
+          "responded with {} in {} ms",
+          response
+            .response
+            .as_ref()
+            .map(|response| response.status())
+            .or(response.response_status)
+            .map_or(0, |status_code| status_code.as_u16()),
+          request_start.elapsed().as_millis()
+        ),
+        Err(err) => format!("failed: {err}"),
+      };
+      error_logger
+        .log_with_level(
+          LogLevel::Debug,
+          LOG_TARGET,
+          &format!("Backend server {} {outcome}", upstream.proxy_to),
+        )
+        .await;
+    }
+    if self.outlier_detector.is_none() && self.circuit_breakers.is_none() {
+      return;
+    }
+    let (success, latency) = match result {
+      Ok(response) => (
+        !response
+          .response
+          .as_ref()
+          .map(|response| response.status())
+          .or(response.response_status)
+          .is_some_and(|status_code| status_code.is_server_error()),
+        Some(request_start.elapsed()),
+      ),
+      Err(_) => (false, None),
+    };
+    self
+      .record_backend_outcome(upstream, success, latency, error_logger)
+      .await;
+  }
+
   async fn retry_or_respond(
     &self,
     error_logger: &ErrorLogger,
     err: &dyn std::fmt::Display,
-    retry_connection: bool,
+    retry: bool,
     has_more_backends: bool,
-    status_code: StatusCode,
+    error_code: ErrorCode,
     log_prefix: &str,
   ) -> Option<ResponseData> {
-    if retry_connection && has_more_backends {
+    if retry && has_more_backends {
       error_logger
         .log(&format!("Failed to connect to backend, trying another backend: {err}"))
         .await;
       None
     } else {
-      error_logger.log(&format!("{log_prefix}: {err}")).await;
-      Some(Self::status_response(status_code))
+      error_logger.log(&format!("{log_prefix} ({error_code}): {err}")).await;
+      Some(Self::error_code_response(error_code))
+    }
+  }
+
+  /// Handles the result of forwarding a request to a backend server.
+  /// Returns `None` if the request should be passed to the next backend server.
+  async fn next_upstream_or_respond(
+    &self,
+    error_logger: &ErrorLogger,
+    result: Result<ResponseData, impl std::fmt::Display>,
+    can_resend_request: bool,
+    has_more_backends: bool,
+  ) -> Option<ResponseData> {
+    match result {
+      Ok(response) => {
+        let status_code = response
+          .response
+          .as_ref()
+          .map(|response| response.status())
+          .or(response.response_status);
+        if can_resend_request
+          && has_more_backends
+          && status_code.is_some_and(|status_code| self.proxy_next_upstream.status_codes.contains(&status_code))
+        {
+          error_logger
+            .log(&format!(
+              "Backend server responded with {}, trying another backend",
+              status_code.map_or(0, |status_code| status_code.as_u16())
+            ))
+            .await;
+          None
+        } else {
+          Some(self.prepare_streaming_response(response))
+        }
+      }
+      Err(err) => {
+        self
+          .retry_or_respond(
+            error_logger,
+            &err,
+            can_resend_request && self.proxy_next_upstream.error,
+            has_more_backends,
+            ErrorCode::BACKEND_REQUEST_FAILED,
+            "Bad gateway",
+          )
+          .await
+      }
     }
   }
 
+  /// Starts counting the bytes transferred to and from the backend server, if the metrics are enabled.
+  /// Returns the request body, which counts the bytes sent to the backend server.
+  fn track_backend_transfer(
+    &mut self,
+    upstream: &UpstreamInner,
+    proxy_request_parts: &hyper::http::request::Parts,
+    request_body: BoxBody<Bytes, std::io::Error>,
+  ) -> BoxBody<Bytes, std::io::Error> {
+    match self.streaming_metrics_sender.clone() {
+      Some(metrics_sender) => {
+        let backend_transfer = BackendTransfer::new(upstream.clone(), proxy_request_parts, metrics_sender);
+        self.backend_transfer = Some(backend_transfer.clone());
+        TransferCountingBody::new(request_body, backend_transfer, TransferDirection::Sent).boxed()
+      }
+      None => request_body,
+    }
+  }
+
+  /// Marks streaming responses (Server-Sent Events responses, or all responses if `proxy_sse` is enabled),
+  /// so that other modules don't buffer or compress them, and tracks them in the open streaming responses metric.
+  /// Also sets the flush interval of the responses, if it's configured, removes the hop-by-hop and hidden headers,
+  /// and applies the cookie rewrites and the response header modifications.
+  fn prepare_streaming_response(&self, mut response_data: ResponseData) -> ResponseData {
+    if let Some(mut response) = response_data.response.take() {
+      if let Some(backend_transfer) = self.backend_transfer.clone() {
+        let (response_parts, response_body) = response.into_parts();
+        backend_transfer.record_response_head(&response_parts);
+        let response_body =
+          TransferCountingBody::new(response_body, backend_transfer, TransferDirection::Received).boxed();
+        response = Response::from_parts(response_parts, response_body);
+      }
+      let is_upgrade = response.status() == StatusCode::SWITCHING_PROTOCOLS;
+      self
+        .header_forwarding
+        .sanitize_response_headers(response.headers_mut(), is_upgrade);
+      if !self.cookie_rewrites.is_empty() {
+        self.cookie_rewrites.apply(response.headers_mut());
+      }
+      if let Some(response_header_modifications) = &self.resolved_response_header_modifications {
+        response_header_modifications.apply(response.headers_mut());
+      }
+      if let Some(sticky_session_cookie) = self.sticky_session_cookie.clone() {
+        response.headers_mut().append(header::SET_COOKIE, sticky_session_cookie);
+      }
+      if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+        response_data.response = Some(response);
+        return response_data;
+      }
+      let is_streaming = self.proxy_sse || is_streaming_response(&response);
+      let (mut response_parts, mut response_body) = response.into_parts();
+      if let Some(flush_interval) = self.proxy_flush_interval {
+        response_parts.extensions.insert(ResponseFlushInterval(flush_interval));
+      }
+      if is_streaming {
+        response_parts.extensions.insert(StreamingResponse);
+        response_body = StreamingResponseBody::new(response_body, self.streaming_metrics_sender.clone()).boxed();
+      }
+      response_data.response = Some(Response::from_parts(response_parts, response_body));
+    }
+    response_data
+  }
+
   #[inline]
-  fn io_error_status(err: &std::io::Error) -> (StatusCode, &'static str) {
+  fn io_error_code(err: &std::io::Error) -> (ErrorCode, &'static str) {
     match err.kind() {
       std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound | std::io::ErrorKind::HostUnreachable => {
-        (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable")
+        (ErrorCode::BACKEND_UNAVAILABLE, "Service unavailable")
       }
-      std::io::ErrorKind::TimedOut => (StatusCode::GATEWAY_TIMEOUT, "Gateway timeout"),
-      _ => (StatusCode::BAD_GATEWAY, "Bad gateway"),
+      std::io::ErrorKind::TimedOut => (ErrorCode::BACKEND_CONNECTION_TIMEOUT, "Gateway timeout"),
+      _ => (ErrorCode::BACKEND_CONNECTION_FAILED, "Bad gateway"),
     }
   }
 }
@@ -542,6 +1277,7 @@ impl ModuleHandlers for ReverseProxyHandler {
     let enable_health_check = self.enable_health_check;
     let health_check_max_fails = self.health_check_max_fails;
     let disable_certificate_verification = self.disable_certificate_verification;
+    let required_scts = self.required_scts;
     let proxy_intercept_errors = self.proxy_intercept_errors;
     if self.proxy_to.is_empty() {
       // No upstreams configured...
@@ -559,42 +1295,216 @@ impl ModuleHandlers for ReverseProxyHandler {
       self.health_check_max_fails,
     )
     .await;
+    if let Some(outlier_detector) = self.outlier_detector.as_ref() {
+      outlier_detector.filter_ejected(&mut proxy_to_vector);
+    }
+    if let Some(backup_failover) = self.backup_failover.clone() {
+      // Backup backend servers receive the requests only if all primary backend servers are unhealthy
+      let failed_backends = self.failed_backends.read().await;
+      let transition = backup_failover.retain_active(&mut proxy_to_vector, |upstream| {
+        !enable_health_check
+          || failed_backends
+            .get(upstream)
+            .is_none_or(|fails| fails <= health_check_max_fails)
+      });
+      drop(failed_backends);
+      if let Some(transition) = transition {
+        self.record_backup_transition(transition, error_logger).await;
+      }
+    }
     let load_balancer_algorithm = self.load_balancer_algorithm.clone();
     let connection_track = match &*load_balancer_algorithm {
       LoadBalancerAlgorithmInner::LeastConnections(connection_track) => Some(connection_track),
       LoadBalancerAlgorithmInner::TwoRandomChoices(connection_track) => Some(connection_track),
       _ => None,
     };
-    let retry_connection = self.retry_connection;
-    let (request_parts, request_body) = request.into_parts();
+    let (request_parts, mut request_body) = request.into_parts();
+    // Backend URLs may contain placeholders (such as `{var:NAME}`), which are resolved for each request.
+    // Backends resolving to invalid URLs (for example, because of a missing variable) are skipped.
+    // Backend server state (health checks, outlier detection, circuit breakers, load balancing) is tracked
+    // for the configured backend URL, so that varying placeholder values don't create new state entries.
+    let mut resolved_proxy_to = HashMap::new();
+    for (upstream, _, _) in proxy_to_vector.iter() {
+      if upstream.proxy_to.contains('{') {
+        resolved_proxy_to.insert(
+          upstream.clone(),
+          replace_header_placeholders(&upstream.proxy_to, &request_parts, Some(socket_data)),
+        );
+      }
+    }
+    if !resolved_proxy_to.is_empty() {
+      proxy_to_vector.retain(|(upstream, _, _)| {
+        resolved_proxy_to.get(upstream).is_none_or(|proxy_to| {
+          proxy_to
+            .parse::<hyper::Uri>()
+            .is_ok_and(|url| url.host().is_some_and(|host| !host.is_empty()))
+        })
+      });
+      if proxy_to_vector.is_empty() {
+        // No backend URLs resolved for this request, so the request is passed to the next modules
+        return Ok(ResponseData {
+          request: Some(Request::from_parts(request_parts, request_body)),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    }
+    // The request body is buffered, if it's included in the request signature
+    let signed_payload_hash = match self.signing.as_deref() {
+      Some(signing) if signing.signs_payload() => {
+        if request_body.is_end_stream() {
+          Some(payload_hash(b""))
+        } else {
+          let body = match Limited::new(request_body, MAX_SIGNED_BODY_SIZE).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(err) if err.is::<LengthLimitError>() => {
+              return Ok(Self::status_response(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+            Err(err) => Err(err)?,
+          };
+          let signed_payload_hash = payload_hash(&body);
+          request_body = Full::new(body).map_err(|e| match e {}).boxed();
+          Some(signed_payload_hash)
+        }
+      }
+      _ => None,
+    };
+    let can_resend_request = self
+      .proxy_next_upstream
+      .can_resend_request(&request_parts.method, request_body.is_end_stream());
+    // The placeholders in the response header values are resolved against the client request
+    if !self.response_header_modifications.is_empty() {
+      self.resolved_response_header_modifications =
+        Some(self.response_header_modifications.resolve(&request_parts, socket_data));
+    }
+    // Requests from sticky sessions are sent to the backend server bound to the session, if it's healthy
+    let mut sticky_affinity = None;
+    let now_unix = SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map_or(0, |time| time.as_secs());
+    if let Some(session_affinity) = self.session_affinity.clone() {
+      let session = session_affinity.session_from_headers(&request_parts.headers);
+      let (affinity, events) = session_affinity.route(&mut proxy_to_vector, session, Instant::now(), now_unix);
+      self.record_drain_events(events, error_logger).await;
+      if let Some(affinity) = affinity {
+        let is_healthy = !enable_health_check
+          || self
+            .failed_backends
+            .read()
+            .await
+            .get(&affinity.0)
+            .is_none_or(|fails| fails <= health_check_max_fails);
+        if is_healthy {
+          sticky_affinity = Some(affinity);
+        }
+      }
+    }
+    let sticky_upstream = sticky_affinity.as_ref().map(|(upstream, _, _)| upstream.clone());
     let mut request_parts = Some(request_parts);
 
-    loop {
-      if let Some((upstream, local_limit_index, keepalive_idle_timeout)) = determine_proxy_to(
-        &mut proxy_to_vector,
-        &self.failed_backends,
-        enable_health_check,
-        health_check_max_fails,
-        &load_balancer_algorithm,
-      )
-      .await
-      {
+    'backends: loop {
+      let proxy_to = match sticky_affinity.take() {
+        Some(proxy_to) => Some(proxy_to),
+        None => {
+          determine_proxy_to(
+            &mut proxy_to_vector,
+            &self.failed_backends,
+            enable_health_check,
+            health_check_max_fails,
+            &load_balancer_algorithm,
+          )
+          .await
+        }
+      };
+      if let Some((upstream, local_limit_index, keepalive_idle_timeout)) = proxy_to {
+        if let Some(circuit_breakers) = self.circuit_breakers.clone() {
+          let (allowed, transition) = circuit_breakers.try_acquire(&upstream);
+          if let Some(transition) = transition {
+            self
+              .record_circuit_transition(&upstream, transition, error_logger)
+              .await;
+          }
+          if !allowed {
+            // The circuit is open, or the half-open circuit has no probe requests left
+            error_logger
+              .log_with_level(
+                LogLevel::Debug,
+                LOG_TARGET,
+                &format!("Skipping backend server {} with an open circuit", upstream.proxy_to),
+              )
+              .await;
+            continue 'backends;
+          }
+        }
+        if let Some(session_affinity) = self.session_affinity.as_ref() {
+          // A new sticky session is created, if the request isn't sent to the backend server bound to the session
+          self.sticky_session_cookie = if sticky_upstream.as_ref() == Some(&upstream) {
+            if session_affinity.is_draining(&upstream) {
+              if let Some(drain_metrics) = self.drain_metrics.as_mut() {
+                drain_metrics.push(DrainEvent::Request(upstream.clone()));
+              }
+            }
+            None
+          } else {
+            session_affinity.new_session_cookie(&upstream, now_unix, socket_data.encrypted)
+          };
+        }
         if let Some(selected_backends_metrics) = self.selected_backends_metrics.as_mut() {
           selected_backends_metrics.push(upstream.clone());
         }
-        let UpstreamInner { proxy_to, proxy_unix } = &upstream;
+        if error_logger.is_enabled(LogLevel::Debug, LOG_TARGET) {
+          error_logger
+            .log_with_level(
+              LogLevel::Debug,
+              LOG_TARGET,
+              &format!(
+                "Selected backend server {}{}",
+                upstream.proxy_to,
+                if sticky_upstream.as_ref() == Some(&upstream) {
+                  " (sticky session)"
+                } else {
+                  ""
+                }
+              ),
+            )
+            .await;
+        }
+        // The connection is established to the backend URL with the placeholders resolved
+        let connection_upstream = match resolved_proxy_to.get(&upstream) {
+          Some(proxy_to) => UpstreamInner {
+            proxy_to: proxy_to.clone(),
+            proxy_unix: upstream.proxy_unix.clone(),
+          },
+          None => upstream.clone(),
+        };
+        let UpstreamInner { proxy_to, proxy_unix } = &connection_upstream;
         let proxy_request_url = proxy_to.parse::<hyper::Uri>()?;
         let scheme_str = proxy_request_url.scheme_str();
         let mut encrypted = false;
+        let mut use_http3 = false;
+        let mut use_h2c = false;
 
         match scheme_str {
           Some("http") => {
             encrypted = false;
           }
+          Some("h2c") => {
+            // HTTP/2 over cleartext is used with prior knowledge (RFC 9113, section 3.3)
+            encrypted = false;
+            use_h2c = true;
+          }
           Some("https") => {
             encrypted = true;
           }
-          _ => Err(anyhow::anyhow!("Only HTTP and HTTPS reverse proxy URLs are supported."))?,
+          Some("h3") => {
+            encrypted = true;
+            use_http3 = true;
+          }
+          _ => Err(anyhow::anyhow!(
+            "Only HTTP, HTTPS, h2c and HTTP/3 reverse proxy URLs are supported."
+          ))?,
         };
 
         let host = match proxy_request_url.host() {
@@ -603,12 +1513,12 @@ impl ModuleHandlers for ReverseProxyHandler {
         };
 
         let port = proxy_request_url.port_u16().unwrap_or(match scheme_str {
-          Some("http") => 80,
-          Some("https") => 443,
+          Some("http") | Some("h2c") => 80,
+          Some("https") | Some("h3") => 443,
           _ => 80,
         });
 
-        let addr = format!("{host}:{port}");
+        let addr = format_connect_address(host, port);
 
         let request_parts_option = if proxy_to_vector.is_empty() {
           request_parts.take()
@@ -616,16 +1526,24 @@ impl ModuleHandlers for ReverseProxyHandler {
           request_parts.clone()
         };
         let request_parts = request_parts_option.ok_or(anyhow::anyhow!("Request parts not found"))?;
-        let proxy_request_parts = construct_proxy_request_parts(
+        let mut proxy_request_parts = construct_proxy_request_parts(
           request_parts,
           config,
           socket_data,
           &proxy_request_url,
-          &self.headers_to_add,
-          &self.headers_to_replace,
-          &self.headers_to_remove,
-          self.rewrite_host,
+          &self.request_header_modifications,
+          &self.header_forwarding,
+          self.host_header.as_ref(),
+          self.forwarded_for,
         )?;
+        if let Some(signing) = self.signing.as_deref() {
+          signing.sign(
+            &mut proxy_request_parts,
+            &proxy_request_url,
+            signed_payload_hash.as_deref(),
+            SystemTime::now(),
+          )?;
+        }
 
         let tracked_connection = if let Some(connection_track) = connection_track {
           let connection_track_read = connection_track.read().await;
@@ -647,20 +1565,68 @@ impl ModuleHandlers for ReverseProxyHandler {
         let proxy_header = self.proxy_header;
 
         let is_http_upgrade = proxy_request_parts.headers.contains_key(header::UPGRADE);
-        let enable_http2_only_config = self.proxy_http2_only;
+        let enable_http2_only_config = self.proxy_http2_only || use_h2c;
         let enable_http2_config = self.proxy_http2;
 
+        // HTTP/3 is used for "h3://" URLs, or for HTTPS URLs if enabled. It's not used for HTTP upgrades,
+        // Unix sockets and PROXY protocol headers, which can't be sent over QUIC.
+        let try_http3 = (use_http3 || (encrypted && self.proxy_http3))
+          && !is_http_upgrade
+          && proxy_unix.is_none()
+          && proxy_header.is_none();
+        if let Some(http3_client) = self.http3_client.clone().filter(|_| try_http3) {
+          if !http3_client.is_unavailable(&connection_upstream).await {
+            match http3_client
+              .get_sender(
+                &connection_upstream,
+                host,
+                port,
+                disable_certificate_verification,
+                required_scts,
+              )
+              .await
+            {
+              Ok((sender, connection_reused)) => {
+                self.connection_reused = connection_reused;
+                let proxy_request_body = self.track_backend_transfer(&upstream, &proxy_request_parts, request_body);
+                let proxy_request = Request::from_parts(proxy_request_parts, proxy_request_body);
+                let request_start = Instant::now();
+                let result = http3_proxy(sender, proxy_request, proxy_intercept_errors, tracked_connection).await;
+                self
+                  .record_proxy_result(&upstream, &result, request_start, error_logger)
+                  .await;
+                if let Some(response) = self
+                  .next_upstream_or_respond(error_logger, result, can_resend_request, !proxy_to_vector.is_empty())
+                  .await
+                {
+                  return Ok(response);
+                }
+                request_body = Empty::new().map_err(|e| match e {}).boxed();
+                continue 'backends;
+              }
+              Err(err) => {
+                // Fall back to HTTP/2 or HTTP/1.1 over TCP
+                error_logger
+                  .log(&format!(
+                    "Failed to establish an HTTP/3 connection to backend, falling back to TCP: {err}"
+                  ))
+                  .await;
+              }
+            }
+          }
+        }
+
         let enable_keepalive =
           (enable_http2_only_config || !enable_http2_config || !is_http_upgrade) && self.proxy_keepalive;
         let connection_pool_item = {
           #[cfg(unix)]
           let connections = if proxy_unix.is_some() {
-            &self.unix_connections
+            self.unix_connections.clone()
           } else {
-            &self.connections
+            self.connections.clone()
           };
           #[cfg(not(unix))]
-          let connections = &self.connections;
+          let connections = self.connections.clone();
           let sender;
           let mut send_request_items = Vec::new();
           let proxy_client_ip = match proxy_header {
@@ -670,10 +1636,10 @@ impl ModuleHandlers for ReverseProxyHandler {
           loop {
             let mut send_request_item = if send_request_items.is_empty() {
               connections
-                .pull_with_wait_local_limit((upstream.clone(), proxy_client_ip), local_limit_index)
+                .pull_with_wait_local_limit((connection_upstream.clone(), proxy_client_ip), local_limit_index)
                 .await
             } else if let Poll::Ready(send_request_item_option) = connections
-              .pull_with_wait_local_limit((upstream.clone(), proxy_client_ip), local_limit_index)
+              .pull_with_wait_local_limit((connection_upstream.clone(), proxy_client_ip), local_limit_index)
               .boxed_local()
               .poll_unpin(&mut Context::from_waker(Waker::noop()))
             {
@@ -692,7 +1658,7 @@ impl ModuleHandlers for ReverseProxyHandler {
               };
               crate::runtime::select! {
                 item = connections
-                  .pull_with_wait_local_limit((upstream.clone(), proxy_client_ip), local_limit_index)
+                  .pull_with_wait_local_limit((connection_upstream.clone(), proxy_client_ip), local_limit_index)
                 => {
                   item
                 },
@@ -703,12 +1669,14 @@ impl ModuleHandlers for ReverseProxyHandler {
             };
             if let Some(send_request) = send_request_item.inner_mut() {
               match send_request.get(keepalive_idle_timeout) {
-                (Some(send_request), true) => {
+                (Some((send_request, served_requests)), true) => {
                   // Connection ready, send a request to it
                   send_request_items.clear();
                   self.connection_reused = true;
                   let _ = send_request_item.inner_mut().take();
-                  let proxy_request = Request::from_parts(proxy_request_parts, request_body);
+                  let proxy_request_body = self.track_backend_transfer(&upstream, &proxy_request_parts, request_body);
+                  let proxy_request = Request::from_parts(proxy_request_parts, proxy_request_body);
+                  let request_start = Instant::now();
                   let result = http_proxy(
                     send_request,
                     send_request_item,
@@ -716,10 +1684,20 @@ impl ModuleHandlers for ReverseProxyHandler {
                     error_logger,
                     proxy_intercept_errors,
                     tracked_connection,
-                    true,
+                    Some(self.connection_reuse(&upstream, served_requests + 1)),
                   )
                   .await;
-                  return result;
+                  self
+                    .record_proxy_result(&upstream, &result, request_start, error_logger)
+                    .await;
+                  if let Some(response) = self
+                    .next_upstream_or_respond(error_logger, result, can_resend_request, !proxy_to_vector.is_empty())
+                    .await
+                  {
+                    return Ok(response);
+                  }
+                  request_body = Empty::new().map_err(|e| match e {}).boxed();
+                  continue 'backends;
                 }
                 (None, true) => {
                   // Connection not ready
@@ -765,15 +1743,15 @@ impl ModuleHandlers for ReverseProxyHandler {
             let stream = match UnixStream::connect(proxy_unix_str).await {
               Ok(stream) => stream,
               Err(err) => {
-                self.mark_backend_failure(&upstream).await;
-                let (status_code, log_prefix) = Self::io_error_status(&err);
+                self.mark_backend_failure(&upstream, error_logger).await;
+                let (error_code, log_prefix) = Self::io_error_code(&err);
                 if let Some(response) = self
                   .retry_or_respond(
                     error_logger,
                     &err,
-                    retry_connection,
+                    self.proxy_next_upstream.retries_on_io_error(&err),
                     !proxy_to_vector.is_empty(),
-                    status_code,
+                    error_code,
                     log_prefix,
                   )
                   .await
@@ -788,14 +1766,14 @@ impl ModuleHandlers for ReverseProxyHandler {
             let stream = match SendUnixStreamPoll::new_comp_io(stream) {
               Ok(stream) => stream,
               Err(err) => {
-                self.mark_backend_failure(&upstream).await;
+                self.mark_backend_failure(&upstream, error_logger).await;
                 if let Some(response) = self
                   .retry_or_respond(
                     error_logger,
                     &err,
-                    retry_connection,
+                    self.proxy_next_upstream.retries_on_io_error(&err),
                     !proxy_to_vector.is_empty(),
-                    StatusCode::BAD_GATEWAY,
+                    ErrorCode::BACKEND_CONNECTION_FAILED,
                     "Bad gateway",
                   )
                   .await
@@ -812,15 +1790,15 @@ impl ModuleHandlers for ReverseProxyHandler {
           let stream = match TcpStream::connect(&addr).await {
             Ok(stream) => stream,
             Err(err) => {
-              self.mark_backend_failure(&upstream).await;
-              let (status_code, log_prefix) = Self::io_error_status(&err);
+              self.mark_backend_failure(&upstream, error_logger).await;
+              let (error_code, log_prefix) = Self::io_error_code(&err);
               if let Some(response) = self
                 .retry_or_respond(
                   error_logger,
                   &err,
-                  retry_connection,
+                  self.proxy_next_upstream.retries_on_io_error(&err),
                   !proxy_to_vector.is_empty(),
-                  status_code,
+                  error_code,
                   log_prefix,
                 )
                 .await
@@ -832,14 +1810,14 @@ impl ModuleHandlers for ReverseProxyHandler {
           };
 
           if let Err(err) = stream.set_nodelay(true) {
-            self.mark_backend_failure(&upstream).await;
+            self.mark_backend_failure(&upstream, error_logger).await;
             if let Some(response) = self
               .retry_or_respond(
                 error_logger,
                 &err,
-                retry_connection,
+                self.proxy_next_upstream.retries_on_io_error(&err),
                 !proxy_to_vector.is_empty(),
-                StatusCode::BAD_GATEWAY,
+                ErrorCode::BACKEND_CONNECTION_FAILED,
                 "Bad gateway",
               )
               .await
@@ -849,18 +1827,22 @@ impl ModuleHandlers for ReverseProxyHandler {
             continue;
           };
 
+          if let Some(tcp_keepalive) = self.proxy_tcp_keepalive {
+            set_tcp_keepalive(&stream, tcp_keepalive).unwrap_or_default();
+          }
+
           #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
           let stream = match SendTcpStreamPoll::new_comp_io(stream) {
             Ok(stream) => stream,
             Err(err) => {
-              self.mark_backend_failure(&upstream).await;
+              self.mark_backend_failure(&upstream, error_logger).await;
               if let Some(response) = self
                 .retry_or_respond(
                   error_logger,
                   &err,
-                  retry_connection,
+                  self.proxy_next_upstream.retries_on_io_error(&err),
                   !proxy_to_vector.is_empty(),
-                  StatusCode::BAD_GATEWAY,
+                  ErrorCode::BACKEND_CONNECTION_FAILED,
                   "Bad gateway",
                 )
                 .await
@@ -988,14 +1970,14 @@ impl ModuleHandlers for ReverseProxyHandler {
 
         if let Some(proxy_header_to_write) = proxy_header_to_write {
           if let Err(err) = stream.write_all(&proxy_header_to_write).await {
-            self.mark_backend_failure(&upstream).await;
+            self.mark_backend_failure(&upstream, error_logger).await;
             if let Some(response) = self
               .retry_or_respond(
                 error_logger,
                 &err,
-                retry_connection,
+                self.proxy_next_upstream.retries_on_io_error(&err),
                 !proxy_to_vector.is_empty(),
-                StatusCode::BAD_GATEWAY,
+                ErrorCode::BACKEND_CONNECTION_FAILED,
                 "Bad gateway",
               )
               .await
@@ -1016,6 +1998,7 @@ impl ModuleHandlers for ReverseProxyHandler {
           let sender = match http_proxy_handshake(
             stream,
             enable_http2_only_config,
+            self.proxy_http2_keepalive,
             #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
             drop_guard,
           )
@@ -1023,14 +2006,14 @@ impl ModuleHandlers for ReverseProxyHandler {
           {
             Ok(sender) => sender,
             Err(err) => {
-              self.mark_backend_failure(&upstream).await;
+              self.mark_backend_failure(&upstream, error_logger).await;
               if let Some(response) = self
                 .retry_or_respond(
                   error_logger,
                   &err,
-                  retry_connection,
+                  self.proxy_next_upstream.error,
                   !proxy_to_vector.is_empty(),
-                  StatusCode::BAD_GATEWAY,
+                  ErrorCode::BACKEND_HTTP_HANDSHAKE_FAILED,
                   "Bad gateway",
                 )
                 .await
@@ -1044,22 +2027,7 @@ impl ModuleHandlers for ReverseProxyHandler {
           sender
         } else {
           let enable_http2_config = enable_http2_only_config || (enable_http2_config && !is_http_upgrade);
-          let mut tls_client_config = (if disable_certificate_verification {
-            rustls::ClientConfig::builder()
-              .dangerous()
-              .with_custom_certificate_verifier(Arc::new(NoServerVerifier::new()))
-          } else if let Ok(client_config) = BuilderVerifierExt::with_platform_verifier(rustls::ClientConfig::builder())
-          {
-            client_config
-          } else {
-            rustls::ClientConfig::builder().with_webpki_verifier(
-              WebPkiServerVerifier::builder(Arc::new(rustls::RootCertStore {
-                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-              }))
-              .build()?,
-            )
-          })
-          .with_no_client_auth();
+          let mut tls_client_config = build_tls_client_config(disable_certificate_verification, required_scts)?;
           if enable_http2_only_config {
             tls_client_config.alpn_protocols = vec![b"h2".to_vec()];
           } else if enable_http2_config {
@@ -1068,19 +2036,19 @@ impl ModuleHandlers for ReverseProxyHandler {
             tls_client_config.alpn_protocols = vec![b"http/1.1".to_vec(), b"http/1.0".to_vec()];
           }
           let connector = TlsConnector::from(Arc::new(tls_client_config));
-          let domain = ServerName::try_from(host)?.to_owned();
+          let domain = ServerName::try_from(strip_ip_literal_host(host))?;
 
           let tls_stream = match connector.connect(domain, stream).await {
             Ok(stream) => stream,
             Err(err) => {
-              self.mark_backend_failure(&upstream).await;
+              self.mark_backend_failure(&upstream, error_logger).await;
               if let Some(response) = self
                 .retry_or_respond(
                   error_logger,
                   &err,
-                  retry_connection,
+                  self.proxy_next_upstream.retries_on_io_error(&err),
                   !proxy_to_vector.is_empty(),
-                  StatusCode::BAD_GATEWAY,
+                  ErrorCode::BACKEND_TLS_HANDSHAKE_FAILED,
                   "Bad gateway",
                 )
                 .await
@@ -1097,6 +2065,7 @@ impl ModuleHandlers for ReverseProxyHandler {
           let sender = match http_proxy_handshake(
             tls_stream,
             enable_http2,
+            self.proxy_http2_keepalive,
             #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
             drop_guard,
           )
@@ -1104,14 +2073,14 @@ impl ModuleHandlers for ReverseProxyHandler {
           {
             Ok(sender) => sender,
             Err(err) => {
-              self.mark_backend_failure(&upstream).await;
+              self.mark_backend_failure(&upstream, error_logger).await;
               if let Some(response) = self
                 .retry_or_respond(
                   error_logger,
                   &err,
-                  retry_connection,
+                  self.proxy_next_upstream.error,
                   !proxy_to_vector.is_empty(),
-                  StatusCode::BAD_GATEWAY,
+                  ErrorCode::BACKEND_HTTP_HANDSHAKE_FAILED,
                   "Bad gateway",
                 )
                 .await
@@ -1125,26 +2094,43 @@ impl ModuleHandlers for ReverseProxyHandler {
           sender
         };
 
-        let proxy_request = Request::from_parts(proxy_request_parts, request_body);
+        let proxy_request_body = self.track_backend_transfer(&upstream, &proxy_request_parts, request_body);
+        let proxy_request = Request::from_parts(proxy_request_parts, proxy_request_body);
 
-        return http_proxy(
+        let request_start = Instant::now();
+        let result = http_proxy(
           sender,
           connection_pool_item,
           proxy_request,
           error_logger,
           proxy_intercept_errors,
           tracked_connection,
-          enable_keepalive,
+          enable_keepalive.then(|| self.connection_reuse(&upstream, 1)),
         )
         .await;
+        self
+          .record_proxy_result(&upstream, &result, request_start, error_logger)
+          .await;
+        if let Some(response) = self
+          .next_upstream_or_respond(error_logger, result, can_resend_request, !proxy_to_vector.is_empty())
+          .await
+        {
+          return Ok(response);
+        }
+        request_body = Empty::new().map_err(|e| match e {}).boxed();
       } else {
         let request_parts = request_parts.ok_or(anyhow::anyhow!("Request parts are missing"))?;
-        error_logger.log("No upstreams available").await;
+        error_logger
+          .log(&format!(
+            "No upstreams available ({})",
+            ErrorCode::NO_BACKENDS_AVAILABLE
+          ))
+          .await;
         return Ok(ResponseData {
           request: Some(Request::from_parts(request_parts, request_body)),
           response: None,
-          response_status: Some(StatusCode::SERVICE_UNAVAILABLE), // No upstreams available
-          response_headers: None,
+          response_status: Some(ErrorCode::NO_BACKENDS_AVAILABLE.status_code()), // No upstreams available
+          response_headers: Some(ErrorCode::NO_BACKENDS_AVAILABLE.to_headers()),
           new_remote_address: None,
         });
       }
@@ -1155,10 +2141,15 @@ impl ModuleHandlers for ReverseProxyHandler {
     &mut self,
     _request: &Request<BoxBody<Bytes, std::io::Error>>,
     _socket_data: &SocketData,
-    _metrics_sender: &MetricsMultiSender,
+    metrics_sender: &MetricsMultiSender,
   ) {
+    self.streaming_metrics_sender = Some(metrics_sender.clone());
     self.selected_backends_metrics = Some(Vec::new());
     self.unhealthy_backends_metrics = Some(Vec::new());
+    self.ejected_backends_metrics = Some(Vec::new());
+    self.circuit_breaker_transitions_metrics = Some(Vec::new());
+    self.backup_transitions_metrics = Some(Vec::new());
+    self.drain_metrics = Some(Vec::new());
   }
 
   async fn metric_data_after_handler(&mut self, metrics_sender: &MetricsMultiSender) {
@@ -1212,6 +2203,128 @@ impl ModuleHandlers for ReverseProxyHandler {
           .await;
       }
     }
+    if let Some(ejected_backends_metrics) = self.ejected_backends_metrics.take() {
+      for ejected_backend in ejected_backends_metrics {
+        let mut attributes = Vec::new();
+        attributes.push((
+          "ferron.proxy.backend_url",
+          MetricAttributeValue::String(ejected_backend.proxy_to),
+        ));
+        if let Some(backend_unix) = ejected_backend.proxy_unix {
+          attributes.push((
+            "ferron.proxy.backend_unix_path",
+            MetricAttributeValue::String(backend_unix),
+          ));
+        }
+        metrics_sender
+          .send(Metric::new(
+            "ferron.proxy.backends.ejected",
+            attributes,
+            MetricType::Counter,
+            MetricValue::U64(1),
+            Some("{backend}"),
+            Some("Number of times a backend server was ejected by outlier detection."),
+          ))
+          .await;
+      }
+    }
+    if let Some(circuit_breaker_transitions_metrics) = self.circuit_breaker_transitions_metrics.take() {
+      for (backend, state) in circuit_breaker_transitions_metrics {
+        let mut attributes = Vec::new();
+        attributes.push((
+          "ferron.proxy.backend_url",
+          MetricAttributeValue::String(backend.proxy_to),
+        ));
+        if let Some(backend_unix) = backend.proxy_unix {
+          attributes.push((
+            "ferron.proxy.backend_unix_path",
+            MetricAttributeValue::String(backend_unix),
+          ));
+        }
+        attributes.push((
+          "ferron.proxy.circuit_breaker.state",
+          MetricAttributeValue::String(state.as_str().to_string()),
+        ));
+        metrics_sender
+          .send(Metric::new(
+            "ferron.proxy.circuit_breaker.transitions",
+            attributes,
+            MetricType::Counter,
+            MetricValue::U64(1),
+            Some("{transition}"),
+            Some("Number of circuit breaker state transitions for a backend server."),
+          ))
+          .await;
+      }
+    }
+    if let Some(drain_metrics) = self.drain_metrics.take() {
+      for event in drain_metrics {
+        let (name, backend, metric_type, value, unit, description) = match event {
+          DrainEvent::Request(backend) => (
+            "ferron.proxy.drain.requests",
+            backend,
+            MetricType::Counter,
+            MetricValue::U64(1),
+            "{request}",
+            "Number of requests from existing sticky sessions sent to a draining backend server.",
+          ),
+          DrainEvent::Sessions(backend, change) => (
+            "ferron.proxy.drain.sessions",
+            backend,
+            MetricType::UpDownCounter,
+            MetricValue::I64(change),
+            "{session}",
+            "Number of active sticky sessions on a draining backend server.",
+          ),
+          DrainEvent::Drained(backend) => (
+            "ferron.proxy.drain.completed",
+            backend,
+            MetricType::Counter,
+            MetricValue::U64(1),
+            "{backend}",
+            "Number of draining backend servers removed after the sticky sessions expired or the drain timeout passed.",
+          ),
+        };
+        let mut attributes = Vec::new();
+        attributes.push((
+          "ferron.proxy.backend_url",
+          MetricAttributeValue::String(backend.proxy_to),
+        ));
+        if let Some(backend_unix) = backend.proxy_unix {
+          attributes.push((
+            "ferron.proxy.backend_unix_path",
+            MetricAttributeValue::String(backend_unix),
+          ));
+        }
+        metrics_sender
+          .send(Metric::new(
+            name,
+            attributes,
+            metric_type,
+            value,
+            Some(unit),
+            Some(description),
+          ))
+          .await;
+      }
+    }
+    if let Some(backup_transitions_metrics) = self.backup_transitions_metrics.take() {
+      for set in backup_transitions_metrics {
+        metrics_sender
+          .send(Metric::new(
+            "ferron.proxy.backup.transitions",
+            vec![(
+              "ferron.proxy.backup.active_set",
+              MetricAttributeValue::String(set.as_str().to_string()),
+            )],
+            MetricType::Counter,
+            MetricValue::U64(1),
+            Some("{transition}"),
+            Some("Number of switches between the primary and the backup backend servers."),
+          ))
+          .await;
+      }
+    }
     metrics_sender
       .send(Metric::new(
         "ferron.proxy.requests",
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{ProxyToKeyInner, UpstreamInner};
+
+/// Maximum number of latency samples kept for a backend server within a single interval.
+const MAX_LATENCY_SAMPLES: usize = 1024;
+
+/// Outlier detection settings for a group of backend servers.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct OutlierDetection {
+  /// Number of consecutive 5xx responses (or failed requests) after which a backend server is ejected.
+  pub consecutive_5xx: Option<u64>,
+  /// Backend servers whose success rate is lower than the mean success rate
+  /// by more than this factor (divided by 1000) of standard deviations are ejected.
+  pub success_rate_stdev_factor: Option<u64>,
+  /// Backend servers whose 95th percentile latency is higher than
+  /// the median 95th percentile latency of all backend servers by this factor are ejected.
+  pub latency_factor: Option<u64>,
+  /// Minimum number of backend servers with enough requests for success rate and latency outlier detection.
+  pub minimum_hosts: usize,
+  /// Minimum number of requests within an interval for a backend server to be included in
+  /// success rate and latency outlier detection.
+  pub request_volume: u64,
+  /// Interval between success rate and latency outlier detection sweeps.
+  pub interval: Duration,
+  /// Base ejection time. The actual ejection time is multiplied by the number of times the backend server was ejected.
+  pub base_ejection_time: Duration,
+  /// Maximum ejection time.
+  pub max_ejection_time: Duration,
+  /// Maximum percentage of backend servers that can be ejected at once. At least one backend server can be ejected.
+  pub max_ejection_percent: u64,
+}
+
+impl Default for OutlierDetection {
+  fn default() -> Self {
+    Self {
+      consecutive_5xx: Some(5),
+      success_rate_stdev_factor: Some(1900),
+      latency_factor: None,
+      minimum_hosts: 5,
+      request_volume: 100,
+      interval: Duration::from_secs(10),
+      base_ejection_time: Duration::from_secs(30),
+      max_ejection_time: Duration::from_secs(300),
+      max_ejection_percent: 10,
+    }
+  }
+}
+
+/// Outlier detection state of a backend server.
+#[derive(Default)]
+struct UpstreamOutlierState {
+  consecutive_5xx: u64,
+  requests: u64,
+  successes: u64,
+  latencies: Vec<Duration>,
+  ejection_count: u32,
+  ejected_until: Option<Instant>,
+}
+
+impl UpstreamOutlierState {
+  #[inline]
+  fn is_ejected(&self, now: Instant) -> bool {
+    self.ejected_until.is_some_and(|ejected_until| ejected_until > now)
+  }
+}
+
+struct OutlierDetectorState {
+  upstreams: HashMap<UpstreamInner, UpstreamOutlierState>,
+  interval_start: Instant,
+}
+
+/// Outlier detector shared by reverse proxy handlers for a group of backend servers.
+pub(super) struct OutlierDetector {
+  config: OutlierDetection,
+  state: Mutex<OutlierDetectorState>,
+}
+
+impl OutlierDetector {
+  /// Creates a new outlier detector.
+  pub(super) fn new(config: OutlierDetection) -> Self {
+    Self {
+      config,
+      state: Mutex::new(OutlierDetectorState {
+        upstreams: HashMap::new(),
+        interval_start: Instant::now(),
+      }),
+    }
+  }
+
+  /// Removes ejected backend servers from the list of backend servers.
+  /// If all backend servers are ejected, the list is left intact.
+  pub(super) fn filter_ejected(&self, upstreams: &mut Vec<ProxyToKeyInner>) {
+    let state = match self.state.lock() {
+      Ok(state) => state,
+      Err(_) => return,
+    };
+    let now = Instant::now();
+    let is_ejected = |upstream: &UpstreamInner| state.upstreams.get(upstream).is_some_and(|s| s.is_ejected(now));
+    if upstreams.iter().all(|(upstream, _, _)| is_ejected(upstream)) {
+      return;
+    }
+    upstreams.retain(|(upstream, _, _)| !is_ejected(upstream));
+  }
+
+  /// Records the outcome of a request sent to a backend server.
+  /// Returns backend servers that were ejected, along with their ejection times.
+  pub(super) fn record(
+    &self,
+    upstream: &UpstreamInner,
+    success: bool,
+    latency: Option<Duration>,
+  ) -> Vec<(UpstreamInner, Duration)> {
+    let mut state = match self.state.lock() {
+      Ok(state) => state,
+      Err(_) => return vec![],
+    };
+    let now = Instant::now();
+    let mut ejected = Vec::new();
+
+    let upstream_state = state.upstreams.entry(upstream.clone()).or_default();
+    upstream_state.requests += 1;
+    if success {
+      upstream_state.successes += 1;
+      upstream_state.consecutive_5xx = 0;
+    } else {
+      upstream_state.consecutive_5xx += 1;
+    }
+    if let Some(latency) = latency {
+      if upstream_state.latencies.len() < MAX_LATENCY_SAMPLES {
+        upstream_state.latencies.push(latency);
+      }
+    }
+    let consecutive_5xx_reached = self
+      .config
+      .consecutive_5xx
+      .is_some_and(|threshold| upstream_state.consecutive_5xx >= threshold);
+    if consecutive_5xx_reached {
+      upstream_state.consecutive_5xx = 0;
+      if let Some(ejection_time) = self.eject(&mut state.upstreams, upstream, now) {
+        ejected.push((upstream.clone(), ejection_time));
+      }
+    }
+
+    if now.duration_since(state.interval_start) >= self.config.interval {
+      state.interval_start = now;
+      ejected.append(&mut self.sweep(&mut state.upstreams, now));
+    }
+
+    ejected
+  }
+
+  /// Ejects a backend server, if the maximum ejection percentage allows it. Returns the ejection time.
+  fn eject(
+    &self,
+    upstreams: &mut HashMap<UpstreamInner, UpstreamOutlierState>,
+    upstream: &UpstreamInner,
+    now: Instant,
+  ) -> Option<Duration> {
+    let total = upstreams.len() as u64;
+    let ejected = upstreams.values().filter(|s| s.is_ejected(now)).count() as u64;
+    let upstream_state = upstreams.get_mut(upstream)?;
+    if upstream_state.is_ejected(now) || (ejected > 0 && (ejected + 1) * 100 > self.config.max_ejection_percent * total)
+    {
+      return None;
+    }
+    upstream_state.ejection_count = upstream_state.ejection_count.saturating_add(1);
+    let ejection_time = self
+      .config
+      .base_ejection_time
+      .saturating_mul(upstream_state.ejection_count)
+      .min(self.config.max_ejection_time);
+    upstream_state.ejected_until = Some(now + ejection_time);
+    Some(ejection_time)
+  }
+
+  /// Performs success rate and latency outlier detection, and resets the interval statistics.
+  fn sweep(
+    &self,
+    upstreams: &mut HashMap<UpstreamInner, UpstreamOutlierState>,
+    now: Instant,
+  ) -> Vec<(UpstreamInner, Duration)> {
+    let mut outliers = Vec::new();
+
+    let eligible = upstreams
+      .iter()
+      .filter(|(_, s)| !s.is_ejected(now) && s.requests >= self.config.request_volume)
+      .collect::<Vec<_>>();
+
+    if eligible.len() >= self.config.minimum_hosts.max(1) {
+      if let Some(stdev_factor) = self.config.success_rate_stdev_factor {
+        let success_rates = eligible
+          .iter()
+          .map(|(upstream, s)| (*upstream, s.successes as f64 / s.requests as f64))
+          .collect::<Vec<_>>();
+        let mean = success_rates.iter().map(|(_, rate)| rate).sum::<f64>() / success_rates.len() as f64;
+        let variance =
+          success_rates.iter().map(|(_, rate)| (rate - mean).powi(2)).sum::<f64>() / success_rates.len() as f64;
+        let threshold = mean - variance.sqrt() * (stdev_factor as f64 / 1000.0);
+        for (upstream, rate) in success_rates {
+          if rate < threshold && !outliers.contains(upstream) {
+            outliers.push(upstream.clone());
+          }
+        }
+      }
+
+      if let Some(latency_factor) = self.config.latency_factor {
+        let p95_latencies = eligible
+          .iter()
+          .filter(|(_, s)| !s.latencies.is_empty())
+          .map(|(upstream, s)| (*upstream, percentile(&s.latencies, 95)))
+          .collect::<Vec<_>>();
+        if p95_latencies.len() >= self.config.minimum_hosts.max(1) {
+          let median = percentile(
+            &p95_latencies.iter().map(|(_, latency)| *latency).collect::<Vec<_>>(),
+            50,
+          );
+          let threshold = median.saturating_mul(latency_factor.min(u32::MAX as u64) as u32);
+          for (upstream, latency) in p95_latencies {
+            if latency > threshold && !outliers.contains(upstream) {
+              outliers.push(upstream.clone());
+            }
+          }
+        }
+      }
+    }
+
+    for upstream_state in upstreams.values_mut() {
+      if upstream_state
+        .ejected_until
+        .is_some_and(|ejected_until| ejected_until <= now)
+      {
+        // The ejection time has passed, so the backend server is brought back
+        upstream_state.ejected_until = None;
+      } else if upstream_state.ejected_until.is_none() && upstream_state.requests > 0 {
+        // The backend server was healthy for an entire interval
+        upstream_state.ejection_count = upstream_state.ejection_count.saturating_sub(1);
+      }
+      upstream_state.requests = 0;
+      upstream_state.successes = 0;
+      upstream_state.latencies.clear();
+    }
+
+    outliers
+      .into_iter()
+      .filter_map(|upstream| {
+        self
+          .eject(upstreams, &upstream, now)
+          .map(|ejection_time| (upstream, ejection_time))
+      })
+      .collect()
+  }
+}
+
+/// Calculates the specified percentile of the durations.
+fn percentile(durations: &[Duration], percentile: usize) -> Duration {
+  let mut sorted = durations.to_vec();
+  sorted.sort_unstable();
+  let index = (sorted.len() * percentile).div_ceil(100).saturating_sub(1);
+  sorted.get(index).copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn upstream(proxy_to: &str) -> UpstreamInner {
+    UpstreamInner {
+      proxy_to: proxy_to.to_string(),
+      proxy_unix: None,
+    }
+  }
+
+  #[test]
+  fn consecutive_5xx_ejects_backend() {
+    let detector = OutlierDetector::new(OutlierDetection {
+      consecutive_5xx: Some(3),
+      max_ejection_percent: 100,
+      ..Default::default()
+    });
+    let backend = upstream("http://backend-1");
+
+    assert!(detector.record(&backend, false, None).is_empty());
+    assert!(detector.record(&backend, false, None).is_empty());
+    let ejected = detector.record(&backend, false, None);
+    assert_eq!(ejected.len(), 1);
+    assert_eq!(ejected[0].1, Duration::from_secs(30));
+  }
+
+  #[test]
+  fn success_resets_consecutive_5xx() {
+    let detector = OutlierDetector::new(OutlierDetection {
+      consecutive_5xx: Some(2),
+      ..Default::default()
+    });
+    let backend = upstream("http://backend-1");
+
+    assert!(detector.record(&backend, false, None).is_empty());
+    assert!(detector.record(&backend, true, None).is_empty());
+    assert!(detector.record(&backend, false, None).is_empty());
+  }
+
+  #[test]
+  fn filter_ejected_keeps_backends_when_all_are_ejected() {
+    let detector = OutlierDetector::new(OutlierDetection {
+      consecutive_5xx: Some(1),
+      max_ejection_percent: 100,
+      ..Default::default()
+    });
+    let healthy = upstream("http://backend-healthy");
+    let unhealthy = upstream("http://backend-unhealthy");
+    detector.record(&healthy, true, None);
+    detector.record(&unhealthy, false, None);
+
+    let mut upstreams = vec![(healthy.clone(), None, None), (unhealthy.clone(), None, None)];
+    detector.filter_ejected(&mut upstreams);
+    assert_eq!(upstreams.len(), 1);
+    assert!(upstreams[0].0 == healthy);
+
+    let mut upstreams = vec![(unhealthy, None, None)];
+    detector.filter_ejected(&mut upstreams);
+    assert_eq!(upstreams.len(), 1);
+  }
+
+  #[test]
+  fn percentile_of_durations() {
+    let durations = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+    assert_eq!(percentile(&durations, 95), Duration::from_millis(95));
+    assert_eq!(percentile(&durations, 50), Duration::from_millis(50));
+    assert_eq!(percentile(&[], 50), Duration::ZERO);
+  }
+}
@@ -4,11 +4,12 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
 use http_body_util::combinators::BoxBody;
-use http_body_util::BodyExt;
-use hyper::body::Body;
-use hyper::{Request, Response, StatusCode};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Body, Frame};
+use hyper::header::{self, HeaderName};
+use hyper::{Request, Response, StatusCode, Uri};
 #[cfg(feature = "runtime-tokio")]
 use hyper_util::rt::{TokioExecutor, TokioIo};
 #[cfg(feature = "runtime-monoio")]
@@ -17,12 +18,15 @@ use tokio::io::{AsyncRead, AsyncWrite};
 #[cfg(feature = "runtime-vibeio")]
 use vibeio_hyper::{VibeioExecutor, VibeioIo};
 
-use super::ConnectionPoolItem;
+use super::http3::Http3SendRequest;
+use super::timer::RuntimeTimer;
 #[cfg(any(feature = "runtime-monoio", feature = "runtime-vibeio"))]
 use super::DropGuard;
-use crate::http_proxy::send_request::{SendRequest, SendRequestWrapper};
+use super::{ConnectionPoolItem, Http2Keepalive};
+use crate::http_proxy::send_request::{ConnectionReuse, SendRequest};
 use crate::logging::ErrorLogger;
 use crate::modules::ResponseData;
+use crate::observability::{Metric, MetricType, MetricValue, MetricsMultiSender};
 
 /// A tracked response body.
 struct TrackedBody<B> {
@@ -72,10 +76,72 @@ where
 unsafe impl<B> Send for TrackedBody<B> where B: Send {}
 unsafe impl<B> Sync for TrackedBody<B> where B: Sync {}
 
+/// A response body of a streaming response (like Server-Sent Events), tracked in the open streaming responses metric.
+pub(super) struct StreamingResponseBody<B> {
+  inner: B,
+  metrics_sender: Option<MetricsMultiSender>,
+}
+
+impl<B> StreamingResponseBody<B> {
+  pub(super) fn new(inner: B, metrics_sender: Option<MetricsMultiSender>) -> Self {
+    if let Some(metrics_sender) = &metrics_sender {
+      metrics_sender.try_send(open_streaming_responses_metric(1));
+    }
+    Self { inner, metrics_sender }
+  }
+}
+
+impl<B> Body for StreamingResponseBody<B>
+where
+  B: Body + Unpin,
+{
+  type Data = B::Data;
+  type Error = B::Error;
+
+  #[inline]
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<hyper::body::Frame<Self::Data>, Self::Error>>> {
+    Pin::new(&mut self.inner).poll_frame(cx)
+  }
+
+  #[inline]
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> hyper::body::SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+impl<B> Drop for StreamingResponseBody<B> {
+  fn drop(&mut self) {
+    if let Some(metrics_sender) = &self.metrics_sender {
+      metrics_sender.try_send(open_streaming_responses_metric(-1));
+    }
+  }
+}
+
+/// Creates a metric for a change in the number of open streaming responses.
+fn open_streaming_responses_metric(change: i64) -> Metric {
+  Metric::new(
+    "ferron.proxy.streaming_responses.active",
+    vec![],
+    MetricType::UpDownCounter,
+    MetricValue::I64(change),
+    Some("{response}"),
+    Some("Number of open streaming reverse proxy responses."),
+  )
+}
+
 /// Establishes a new HTTP connection to a backend server.
 pub(super) async fn http_proxy_handshake(
   stream: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
   use_http2: bool,
+  http2_keepalive: Option<Http2Keepalive>,
   #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))] drop_guard: DropGuard,
 ) -> Result<SendRequest, Box<dyn Error + Send + Sync>> {
   #[cfg(feature = "runtime-vibeio")]
@@ -93,7 +159,16 @@ pub(super) async fn http_proxy_handshake(
     #[cfg(feature = "runtime-tokio")]
     let executor = TokioExecutor::new();
 
-    let (sender, conn) = hyper::client::conn::http2::handshake(executor, io).await?;
+    let mut builder = hyper::client::conn::http2::Builder::new(executor);
+    if let Some(http2_keepalive) = http2_keepalive {
+      // HTTP/2 PING frames are sent also on idle connections, so that dead connections are closed before reuse
+      builder
+        .timer(RuntimeTimer)
+        .keep_alive_interval(http2_keepalive.interval)
+        .keep_alive_timeout(http2_keepalive.timeout)
+        .keep_alive_while_idle(true);
+    }
+    let (sender, conn) = builder.handshake(io).await?;
 
     crate::runtime::spawn(async move {
       conn.await.unwrap_or_default();
@@ -117,6 +192,7 @@ pub(super) async fn http_proxy_handshake(
 }
 
 /// Forwards an HTTP request to a backend server.
+/// Returns an error if the request couldn't be sent to the backend server.
 pub(super) async fn http_proxy(
   mut sender: SendRequest,
   connection_pool_item: ConnectionPoolItem,
@@ -124,8 +200,8 @@ pub(super) async fn http_proxy(
   error_logger: &ErrorLogger,
   proxy_intercept_errors: bool,
   tracked_connection: Option<Arc<()>>,
-  enable_keepalive: bool,
-) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  connection_reuse: Option<ConnectionReuse>,
+) -> Result<ResponseData, hyper::Error> {
   let (proxy_request_parts, proxy_request_body) = proxy_request.into_parts();
   #[cfg(feature = "runtime-vibeio")]
   let mut proxy_request_cloned = Request::from_parts(
@@ -140,19 +216,7 @@ pub(super) async fn http_proxy(
   #[allow(clippy::arc_with_non_send_sync)]
   let connection_pool_item = Arc::new(UnsafeCell::new(connection_pool_item));
 
-  let proxy_response = match send_request_result {
-    Ok(response) => response,
-    Err(err) => {
-      error_logger.log(&format!("Bad gateway: {err}")).await;
-      return Ok(ResponseData {
-        request: None,
-        response: None,
-        response_status: Some(StatusCode::BAD_GATEWAY),
-        response_headers: None,
-        new_remote_address: None,
-      });
-    }
-  };
+  let proxy_response = send_request_result?;
 
   let status_code = proxy_response.status();
 
@@ -211,6 +275,13 @@ pub(super) async fn http_proxy(
   }
   let proxy_response = Response::from_parts(proxy_response_parts, proxy_response_body);
 
+  // The connection is put back into the pool only if keep-alive is enabled and the pool limits allow it
+  let pooled_sender = if sender.is_closed() {
+    None
+  } else {
+    connection_reuse.and_then(|connection_reuse| connection_reuse.wrap(sender))
+  };
+
   let response = if proxy_intercept_errors && status_code.as_u16() >= 400 {
     ResponseData {
       request: None,
@@ -224,7 +295,7 @@ pub(super) async fn http_proxy(
     let boxed_body = TrackedBody::new(
       response_body.map_err(|e| std::io::Error::other(e.to_string())),
       tracked_connection,
-      if enable_keepalive && !sender.is_closed() {
+      if pooled_sender.is_some() {
         None
       } else {
         Some(connection_pool_item.clone())
@@ -240,14 +311,115 @@ pub(super) async fn http_proxy(
     }
   };
 
-  if enable_keepalive && !sender.is_closed() {
+  if let Some(pooled_sender) = pooled_sender {
     let connection_pool_item = unsafe { &mut *connection_pool_item.get() };
-    connection_pool_item
-      .inner_mut()
-      .replace(SendRequestWrapper::new(sender));
+    connection_pool_item.inner_mut().replace(pooled_sender);
   }
 
   drop(connection_pool_item);
 
   Ok(response)
 }
+
+/// Forwards an HTTP request to a backend server over HTTP/3.
+/// Returns an error if the request couldn't be sent to the backend server.
+pub(super) async fn http3_proxy(
+  mut sender: Http3SendRequest,
+  proxy_request: Request<BoxBody<Bytes, std::io::Error>>,
+  proxy_intercept_errors: bool,
+  tracked_connection: Option<Arc<()>>,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let (mut proxy_request_parts, mut proxy_request_body) = proxy_request.into_parts();
+
+  // HTTP/3 requests use the ":authority" pseudo-header instead of the "Host" header,
+  // and don't have connection-specific headers.
+  let authority = proxy_request_parts
+    .headers
+    .remove(header::HOST)
+    .and_then(|host| host.to_str().ok().map(|host| host.to_string()));
+  for connection_header in [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+  ] {
+    proxy_request_parts.headers.remove(connection_header);
+  }
+  let mut uri_parts = proxy_request_parts.uri.into_parts();
+  uri_parts.scheme = Some(hyper::http::uri::Scheme::HTTPS);
+  if let Some(authority) = authority {
+    uri_parts.authority = Some(authority.parse()?);
+  }
+  proxy_request_parts.uri = Uri::from_parts(uri_parts)?;
+
+  let mut stream = sender
+    .send_request(Request::from_parts(proxy_request_parts, ()))
+    .await?;
+
+  let mut had_trailers = false;
+  while let Some(frame) = proxy_request_body.frame().await {
+    let frame = frame?;
+    if frame.is_data() {
+      if let Ok(data) = frame.into_data() {
+        stream.send_data(data).await?;
+      }
+    } else if let Ok(trailers) = frame.into_trailers() {
+      had_trailers = true;
+      stream.send_trailers(trailers).await?;
+    }
+  }
+  if !had_trailers {
+    stream.finish().await?;
+  }
+
+  let proxy_response = stream.recv_response().await?;
+  let status_code = proxy_response.status();
+
+  let response = if proxy_intercept_errors && status_code.as_u16() >= 400 {
+    ResponseData {
+      request: None,
+      response: None,
+      response_status: Some(status_code),
+      response_headers: None,
+      new_remote_address: None,
+    }
+  } else {
+    let response_body_stream =
+      futures_util::stream::unfold((stream, false), |(mut stream, mut is_body_finished)| async move {
+        loop {
+          if !is_body_finished {
+            match stream.recv_data().await {
+              Ok(Some(mut data)) => {
+                return Some((Ok(Frame::data(data.copy_to_bytes(data.remaining()))), (stream, false)));
+              }
+              Ok(None) => is_body_finished = true,
+              Err(err) => return Some((Err(std::io::Error::other(err.to_string())), (stream, true))),
+            }
+          } else {
+            match stream.recv_trailers().await {
+              Ok(Some(trailers)) => return Some((Ok(Frame::trailers(trailers)), (stream, true))),
+              Ok(None) => return None,
+              Err(err) => return Some((Err(std::io::Error::other(err.to_string())), (stream, true))),
+            }
+          }
+        }
+      });
+    let (response_parts, _) = proxy_response.into_parts();
+    let boxed_body = TrackedBody::new(
+      StreamBody::new(Box::pin(response_body_stream)),
+      tracked_connection,
+      None,
+    )
+    .boxed();
+    ResponseData {
+      request: None,
+      response: Some(Response::from_parts(response_parts, boxed_body)),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    }
+  };
+
+  Ok(response)
+}
@@ -1,13 +1,60 @@
 use std::error::Error;
+use std::net::IpAddr;
 use std::str::FromStr;
 
 use hyper::header::{self, HeaderName};
-use hyper::{HeaderMap, Uri, Version};
+use hyper::{Uri, Version};
 
+use super::header_modifications::HeaderModifications;
+use super::hop_by_hop::HeaderForwarding;
+use super::ProxyHostHeader;
 use crate::config::ServerConfiguration;
 use crate::get_value;
-use crate::modules::SocketData;
-use crate::util::replace_header_placeholders;
+use crate::modules::{RequestData, SocketData};
+use crate::util::{parse_ip_literal, replace_header_placeholders};
+
+/// The default maximum number of entries in the `X-Forwarded-For` header sent to backend servers
+const DEFAULT_X_FORWARDED_FOR_MAX_ENTRIES: usize = 32;
+/// The default maximum size (in bytes) of the `X-Forwarded-For` header sent to backend servers
+const DEFAULT_X_FORWARDED_FOR_MAX_SIZE: usize = 4096;
+
+/// The `X-Forwarded-For` header handling for requests sent to backend servers.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ForwardedFor {
+  /// Whether the existing forwarding headers from trusted proxies are appended to, instead of being overwritten
+  pub(super) append: bool,
+  /// The maximum number of entries in the `X-Forwarded-For` header
+  pub(super) max_entries: usize,
+  /// The maximum size (in bytes) of the `X-Forwarded-For` header
+  pub(super) max_size: usize,
+}
+
+impl Default for ForwardedFor {
+  fn default() -> Self {
+    Self {
+      append: true,
+      max_entries: DEFAULT_X_FORWARDED_FOR_MAX_ENTRIES,
+      max_size: DEFAULT_X_FORWARDED_FOR_MAX_SIZE,
+    }
+  }
+}
+
+impl ForwardedFor {
+  /// Builds the `X-Forwarded-For` header value from the existing chain and the address to append.
+  /// The oldest entries are removed if the chain exceeds the maximum number of entries or the maximum size.
+  fn build_chain<'a>(&self, mut chain: Vec<&'a str>, address: &'a str) -> String {
+    chain.push(address);
+    let excess_entries = chain.len().saturating_sub(self.max_entries.max(1));
+    chain.drain(..excess_entries);
+    let mut size = chain.iter().map(|entry| entry.len()).sum::<usize>() + (chain.len() - 1) * 2;
+    let mut first_entry = 0;
+    while size > self.max_size && first_entry < chain.len() - 1 {
+      size -= chain[first_entry].len() + 2;
+      first_entry += 1;
+    }
+    chain[first_entry..].join(", ")
+  }
+}
 
 /// Constructs a proxy request based on the original request.
 #[inline]
@@ -17,26 +64,15 @@ pub(super) fn construct_proxy_request_parts(
   config: &ServerConfiguration,
   socket_data: &SocketData,
   proxy_request_url: &Uri,
-  headers_to_add: &[(HeaderName, String)],
-  headers_to_replace: &[(HeaderName, String)],
-  headers_to_remove: &[HeaderName],
-  rewrite_host: bool,
+  header_modifications: &HeaderModifications,
+  header_forwarding: &HeaderForwarding,
+  host_header: Option<&ProxyHostHeader>,
+  forwarded_for: ForwardedFor,
 ) -> Result<hyper::http::request::Parts, Box<dyn Error + Send + Sync>> {
-  let headers_to_add = HeaderMap::from_iter(headers_to_add.iter().cloned().filter_map(|(name, value)| {
-    replace_header_placeholders(&value, &request_parts, Some(socket_data))
-      .parse()
-      .ok()
-      .map(|v| (name, v))
-  }));
-  let headers_to_replace = HeaderMap::from_iter(headers_to_replace.iter().cloned().filter_map(|(name, value)| {
-    replace_header_placeholders(&value, &request_parts, Some(socket_data))
-      .parse()
-      .ok()
-      .map(|v| (name, v))
-  }));
-  let headers_to_remove = headers_to_remove.to_vec();
+  let header_modifications = header_modifications.resolve(&request_parts, socket_data);
 
   let authority = proxy_request_url.authority().cloned();
+  let request_authority = request_parts.uri.authority().cloned();
 
   let request_path = request_parts.uri.path();
 
@@ -62,59 +98,86 @@ pub(super) fn construct_proxy_request_parts(
 
   let original_host = request_parts.headers.get(header::HOST).cloned();
 
-  if rewrite_host || proxy_request_url.scheme_str() == Some("https") {
-    match authority {
+  let host_header = match host_header {
+    Some(host_header) => Some(host_header),
+    // The `Host` header is rewritten for HTTPS and HTTP/3 backend servers by default, since they usually verify it
+    None if matches!(proxy_request_url.scheme_str(), Some("https") | Some("h3")) => Some(&ProxyHostHeader::Upstream),
+    None => None,
+  };
+  match host_header {
+    Some(ProxyHostHeader::Upstream) => match authority {
       Some(authority) => {
-        request_parts
-          .headers
-          .insert(header::HOST, authority.to_string().parse()?);
+        // IPv6 zones are local to the proxy, so they aren't sent to backend servers (RFC 6874, section 2)
+        let host = match parse_ip_literal(authority.host()) {
+          Some((IpAddr::V6(ip), Some(_))) => match authority.port_u16() {
+            Some(port) => format!("[{ip}]:{port}"),
+            None => format!("[{ip}]"),
+          },
+          _ => authority.to_string(),
+        };
+        request_parts.headers.insert(header::HOST, host.parse()?);
       }
       None => {
         request_parts.headers.remove(header::HOST);
       }
+    },
+    Some(ProxyHostHeader::Custom(host)) => {
+      let host = replace_header_placeholders(host, &request_parts, Some(socket_data));
+      request_parts.headers.insert(header::HOST, host.parse()?);
     }
+    // HTTP/2 and HTTP/3 clients send the host in the `:authority` pseudo-header instead of the `Host` header
+    Some(ProxyHostHeader::Preserve) if !request_parts.headers.contains_key(header::HOST) => {
+      if let Some(request_authority) = request_authority {
+        request_parts
+          .headers
+          .insert(header::HOST, request_authority.as_str().parse()?);
+      }
+    }
+    _ => (),
   }
 
-  if let Some(connection_header) = request_parts.headers.get(&header::CONNECTION) {
-    let connection_str = String::from_utf8_lossy(connection_header.as_bytes());
-    if connection_str
-      .to_lowercase()
-      .split(",")
-      .map(|c| c.trim())
-      .all(|c| c != "keep-alive" && c != "upgrade" && c != "close")
-    {
-      request_parts
-        .headers
-        .insert(header::CONNECTION, format!("keep-alive, {connection_str}").parse()?);
-    }
-  } else {
-    request_parts.headers.insert(header::CONNECTION, "keep-alive".parse()?);
+  let is_upgrade = header_forwarding.sanitize_request_headers(&mut request_parts.headers);
+  let mut connection_options = vec![if is_upgrade { "upgrade" } else { "keep-alive" }];
+  if request_parts.headers.contains_key(header::TE) {
+    // The TE header has to be listed in the Connection header (RFC 9110, section 10.1.4)
+    connection_options.push("te");
   }
+  request_parts
+    .headers
+    .insert(header::CONNECTION, connection_options.join(", ").parse()?);
 
-  let trust_x_forwarded_for = get_value!("trust_x_forwarded_for", config)
-    .and_then(|v| v.as_bool())
-    .unwrap_or(false);
+  // The existing forwarding headers are kept only if the client is a trusted proxy
+  let append_forwarded = forwarded_for.append
+    && get_value!("trust_x_forwarded_for", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
 
-  let remote_addr_str = socket_data.remote_addr.ip().to_canonical().to_string();
-  request_parts.headers.insert(
-    HeaderName::from_static("x-forwarded-for"),
-    (if let Some(ref forwarded_for) = request_parts
+  let x_forwarded_for = if append_forwarded {
+    // The address of the directly connected proxy is appended, since the client address was already obtained
+    // from the `X-Forwarded-For` header
+    let peer_address = request_parts
+      .extensions
+      .get::<RequestData>()
+      .and_then(|d| d.proxy_protocol_address.or(d.peer_address))
+      .unwrap_or(socket_data.remote_addr);
+    let existing_chain = request_parts
       .headers
-      .get(HeaderName::from_static("x-forwarded-for"))
-      .and_then(|h| h.to_str().ok())
-    {
-      if trust_x_forwarded_for {
-        format!("{forwarded_for}, {remote_addr_str}")
-      } else {
-        remote_addr_str
-      }
-    } else {
-      remote_addr_str
-    })
-    .parse()?,
-  );
+      .get_all(HeaderName::from_static("x-forwarded-for"))
+      .iter()
+      .filter_map(|h| h.to_str().ok())
+      .flat_map(|h| h.split(','))
+      .map(|s| s.trim())
+      .filter(|s| !s.is_empty())
+      .collect::<Vec<_>>();
+    forwarded_for.build_chain(existing_chain, &peer_address.ip().to_canonical().to_string())
+  } else {
+    socket_data.remote_addr.ip().to_canonical().to_string()
+  };
+  request_parts
+    .headers
+    .insert(HeaderName::from_static("x-forwarded-for"), x_forwarded_for.parse()?);
 
-  if !trust_x_forwarded_for
+  if !append_forwarded
     || !request_parts
       .headers
       .contains_key(HeaderName::from_static("x-forwarded-proto"))
@@ -130,7 +193,7 @@ pub(super) fn construct_proxy_request_parts(
     }
   }
 
-  if !trust_x_forwarded_for
+  if !append_forwarded
     || !request_parts
       .headers
       .contains_key(HeaderName::from_static("x-forwarded-host"))
@@ -142,6 +205,17 @@ pub(super) fn construct_proxy_request_parts(
     }
   }
 
+  if !append_forwarded
+    || !request_parts
+      .headers
+      .contains_key(HeaderName::from_static("x-forwarded-port"))
+  {
+    request_parts.headers.insert(
+      HeaderName::from_static("x-forwarded-port"),
+      socket_data.local_addr.port().to_string().parse()?,
+    );
+  }
+
   let mut forwarded_header_value = None;
   if let Some(forwarded_header_value_obtained) = request_parts
     .headers
@@ -222,27 +296,48 @@ pub(super) fn construct_proxy_request_parts(
     request_parts.headers.remove(header::FORWARDED);
   }
 
-  for (header_name_option, header_value) in headers_to_add {
-    if let Some(header_name) = header_name_option {
-      if !request_parts.headers.contains_key(&header_name) {
-        request_parts.headers.insert(header_name, header_value);
-      }
-    }
-  }
-
-  for (header_name_option, header_value) in headers_to_replace {
-    if let Some(header_name) = header_name_option {
-      request_parts.headers.insert(header_name, header_value);
-    }
-  }
-
-  for header_to_remove in headers_to_remove.into_iter().rev() {
-    if request_parts.headers.contains_key(&header_to_remove) {
-      while request_parts.headers.remove(&header_to_remove).is_some() {}
-    }
-  }
+  header_modifications.apply(&mut request_parts.headers);
 
   request_parts.version = Version::default();
 
   Ok(request_parts)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_build_x_forwarded_for_chain() {
+    let forwarded_for = ForwardedFor::default();
+    assert_eq!(forwarded_for.build_chain(vec![], "192.0.2.1"), "192.0.2.1");
+    assert_eq!(
+      forwarded_for.build_chain(vec!["192.0.2.1", "198.51.100.1"], "203.0.113.1"),
+      "192.0.2.1, 198.51.100.1, 203.0.113.1"
+    );
+
+    let forwarded_for = ForwardedFor {
+      append: true,
+      max_entries: 2,
+      max_size: 4096,
+    };
+    assert_eq!(
+      forwarded_for.build_chain(vec!["192.0.2.1", "198.51.100.1"], "203.0.113.1"),
+      "198.51.100.1, 203.0.113.1"
+    );
+
+    let forwarded_for = ForwardedFor {
+      append: true,
+      max_entries: 32,
+      max_size: 20,
+    };
+    assert_eq!(
+      forwarded_for.build_chain(vec!["192.0.2.1", "198.51.100.1"], "203.0.113.1"),
+      "203.0.113.1"
+    );
+    assert_eq!(
+      forwarded_for.build_chain(vec![], "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"),
+      "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+    );
+  }
+}
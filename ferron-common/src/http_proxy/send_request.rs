@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -52,25 +54,76 @@ impl SendRequest {
   }
 }
 
+/// A guard for an idle connection kept in the connection pool. The idle connection count is decreased when it's dropped.
+struct IdleConnectionGuard(Arc<AtomicUsize>);
+
+impl IdleConnectionGuard {
+  /// Increases the idle connection count, if it's below the limit.
+  fn try_new(idle_connections: Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+    idle_connections
+      .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+        if count < limit {
+          Some(count + 1)
+        } else {
+          None
+        }
+      })
+      .ok()?;
+    Some(Self(idle_connections))
+  }
+}
+
+impl Drop for IdleConnectionGuard {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::AcqRel);
+  }
+}
+
+/// Limits for putting a connection back into the connection pool.
+pub struct ConnectionReuse {
+  /// Number of requests served by the connection, including the current one.
+  pub served_requests: u64,
+  /// Maximum number of requests served by a single connection.
+  pub max_requests: Option<u64>,
+  /// Number of idle connections to the backend server, along with the maximum number of idle connections.
+  pub idle_connections: Option<(Arc<AtomicUsize>, usize)>,
+}
+
+impl ConnectionReuse {
+  /// Wraps the `SendRequest` for putting it back into the connection pool, if the limits allow it.
+  pub fn wrap(self, inner: SendRequest) -> Option<SendRequestWrapper> {
+    if self
+      .max_requests
+      .is_some_and(|max_requests| self.served_requests >= max_requests)
+    {
+      return None;
+    }
+    let idle_guard = match self.idle_connections {
+      Some((idle_connections, limit)) => Some(IdleConnectionGuard::try_new(idle_connections, limit)?),
+      None => None,
+    };
+    Some(SendRequestWrapper {
+      inner: Some(inner),
+      instant: Instant::now(),
+      served_requests: self.served_requests,
+      _idle_guard: idle_guard,
+    })
+  }
+}
+
 /// A wrapper around `SendRequest`, with idle keep-alive timeout support.
 pub struct SendRequestWrapper {
   inner: Option<SendRequest>,
   instant: Instant,
+  served_requests: u64,
+  _idle_guard: Option<IdleConnectionGuard>,
 }
 
 impl SendRequestWrapper {
-  /// Creates a new `SendRequestWrapper`
-  #[inline]
-  pub fn new(inner: SendRequest) -> Self {
-    Self {
-      inner: Some(inner),
-      instant: Instant::now(),
-    }
-  }
-
-  /// Gets the inner `SendRequest`, along with information on whether to put back the connection to the pool.
+  /// Gets the inner `SendRequest` along with the number of requests it served,
+  /// and information on whether to put back the connection to the pool.
   #[inline]
-  pub fn get(&mut self, timeout: Option<Duration>) -> (Option<SendRequest>, bool) {
+  pub fn get(&mut self, timeout: Option<Duration>) -> (Option<(SendRequest, u64)>, bool) {
     let inner_mut = if let Some(inner) = self.inner.as_mut() {
       inner
     } else {
@@ -81,7 +134,9 @@ impl SendRequestWrapper {
     }
     (
       if inner_mut.is_ready() {
-        self.inner.take()
+        // The connection is no longer idle
+        self._idle_guard = None;
+        self.inner.take().map(|inner| (inner, self.served_requests))
       } else {
         self.instant = Instant::now();
         None
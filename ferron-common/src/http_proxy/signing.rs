@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, KeyInit, Mac};
+use hyper::header::{self, HeaderName};
+use hyper::Uri;
+use sha2::{Digest, Sha256};
+
+/// The payload hash used by AWS Signature Version 4, if the request body isn't signed
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// The maximum size of a request body buffered for computing the payload hash
+pub(super) const MAX_SIGNED_BODY_SIZE: usize = 16777216;
+
+/// AWS credentials used for signing requests to backend servers
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+  /// The AWS access key ID
+  pub access_key_id: String,
+  /// The AWS secret access key
+  pub secret_access_key: String,
+  /// The session token for temporary credentials (like the ones of an assumed IAM role)
+  pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+  /// Obtains the AWS credentials from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`
+  /// and `AWS_SESSION_TOKEN` environment variables
+  pub fn from_env() -> Option<Self> {
+    let get_env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    Some(Self {
+      access_key_id: get_env("AWS_ACCESS_KEY_ID")?,
+      secret_access_key: get_env("AWS_SECRET_ACCESS_KEY")?,
+      session_token: get_env("AWS_SESSION_TOKEN"),
+    })
+  }
+}
+
+/// The signing method for requests sent to backend servers
+#[derive(Clone, Debug)]
+pub enum ProxyRequestSigning {
+  /// AWS Signature Version 4 (used by services like Amazon S3 and Amazon OpenSearch Service)
+  AwsSigV4 {
+    region: String,
+    service: String,
+    credentials: AwsCredentials,
+  },
+  /// HMAC-SHA256 signature of the request method, path, host, timestamp and body
+  Hmac {
+    secret: String,
+    key_id: Option<String>,
+    header: HeaderName,
+  },
+}
+
+impl ProxyRequestSigning {
+  /// Checks whether the request body is included in the signature. Amazon S3 accepts unsigned payloads,
+  /// so request bodies sent to S3 don't need to be buffered.
+  pub(super) fn signs_payload(&self) -> bool {
+    !matches!(self, Self::AwsSigV4 { service, .. } if service == "s3")
+  }
+
+  /// Signs the request sent to the backend server. The request is signed after the request headers are modified,
+  /// so that the signature covers the headers actually sent. The payload hash is a hexadecimal SHA-256 hash
+  /// of the request body, or `None`, if the request body isn't signed.
+  pub(super) fn sign(
+    &self,
+    request_parts: &mut hyper::http::request::Parts,
+    proxy_request_url: &Uri,
+    payload_hash: Option<&str>,
+    time: SystemTime,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !request_parts.headers.contains_key(header::HOST) {
+      // The signature covers the host, so the `Host` header is always sent
+      let authority = proxy_request_url
+        .authority()
+        .ok_or(anyhow::anyhow!("The reverse proxy URL doesn't include the host"))?;
+      request_parts.headers.insert(header::HOST, authority.as_str().parse()?);
+    }
+
+    match self {
+      Self::AwsSigV4 {
+        region,
+        service,
+        credentials,
+      } => {
+        let (amz_date, date) = format_amz_date(time);
+        let payload_hash = payload_hash.unwrap_or(UNSIGNED_PAYLOAD);
+        let headers = &mut request_parts.headers;
+        headers.remove(header::AUTHORIZATION);
+        headers.insert(HeaderName::from_static("x-amz-date"), amz_date.parse()?);
+        if service == "s3" {
+          headers.insert(HeaderName::from_static("x-amz-content-sha256"), payload_hash.parse()?);
+        }
+        match &credentials.session_token {
+          Some(session_token) => {
+            headers.insert(HeaderName::from_static("x-amz-security-token"), session_token.parse()?);
+          }
+          None => {
+            headers.remove(HeaderName::from_static("x-amz-security-token"));
+          }
+        }
+
+        // The `Host`, `Content-Type` and `X-Amz-*` headers are signed. Other headers may be changed
+        // by the HTTP client (such as the `Connection` header), so they aren't signed.
+        let mut signed_headers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (name, value) in headers.iter() {
+          if name == header::HOST || name == header::CONTENT_TYPE || name.as_str().starts_with("x-amz-") {
+            signed_headers
+              .entry(name.as_str())
+              .or_default()
+              .push(value.to_str()?.split_whitespace().collect::<Vec<_>>().join(" "));
+          }
+        }
+        let canonical_headers = signed_headers
+          .iter()
+          .map(|(name, values)| format!("{name}:{}\n", values.join(",")))
+          .collect::<String>();
+        let signed_header_names = signed_headers.keys().copied().collect::<Vec<_>>().join(";");
+
+        // Amazon S3 expects the path to be URI-encoded once, while other services expect it to be encoded twice
+        let mut canonical_uri = uri_encode(&percent_decode(request_parts.uri.path()), false);
+        if service != "s3" {
+          canonical_uri = uri_encode(canonical_uri.as_bytes(), false);
+        }
+        if canonical_uri.is_empty() {
+          canonical_uri = "/".to_string();
+        }
+        let canonical_request = format!(
+          "{}\n{canonical_uri}\n{}\n{canonical_headers}\n{signed_header_names}\n{payload_hash}",
+          request_parts.method,
+          canonical_query_string(request_parts.uri.query().unwrap_or("")),
+        );
+
+        let scope = format!("{date}/{region}/{service}/aws4_request");
+        let string_to_sign = format!(
+          "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+          hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = hmac_sha256(
+          format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+          date.as_bytes(),
+        );
+        for scope_part in [region.as_str(), service.as_str(), "aws4_request"] {
+          signing_key = hmac_sha256(&signing_key, scope_part.as_bytes());
+        }
+        let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        headers.insert(
+          header::AUTHORIZATION,
+          format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_header_names}, Signature={signature}",
+            credentials.access_key_id
+          )
+          .parse()?,
+        );
+      }
+      Self::Hmac {
+        secret,
+        key_id,
+        header: signature_header,
+      } => {
+        let timestamp = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let string_to_sign = format!(
+          "{}\n{}\n{}\n{timestamp}\n{}",
+          request_parts.method,
+          request_parts
+            .uri
+            .path_and_query()
+            .map_or(request_parts.uri.path(), |path_and_query| path_and_query.as_str()),
+          request_parts
+            .headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(""),
+          payload_hash.unwrap_or(UNSIGNED_PAYLOAD)
+        );
+        let signature = hex(&hmac_sha256(secret.as_bytes(), string_to_sign.as_bytes()));
+
+        let headers = &mut request_parts.headers;
+        headers.insert(signature_header.clone(), signature.parse()?);
+        headers.insert(
+          HeaderName::from_static("x-signature-timestamp"),
+          timestamp.to_string().parse()?,
+        );
+        match key_id {
+          Some(key_id) => {
+            headers.insert(HeaderName::from_static("x-signature-key-id"), key_id.parse()?);
+          }
+          None => {
+            headers.remove(HeaderName::from_static("x-signature-key-id"));
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Computes a hexadecimal SHA-256 hash of the request body
+pub(super) fn payload_hash(body: &[u8]) -> String {
+  hex(&Sha256::digest(body))
+}
+
+/// Computes an HMAC-SHA256 authentication code
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+  let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC should accept keys of any length");
+  mac.update(data);
+  mac.finalize().into_bytes().to_vec()
+}
+
+/// Encodes the bytes as a lowercase hexadecimal string
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// URI-encodes the bytes as specified for AWS Signature Version 4 (every byte except unreserved characters is encoded)
+fn uri_encode(bytes: &[u8], encode_slash: bool) -> String {
+  let mut encoded = String::with_capacity(bytes.len());
+  for &byte in bytes {
+    if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') || (byte == b'/' && !encode_slash) {
+      encoded.push(byte as char);
+    } else {
+      encoded.push_str(&format!("%{byte:02X}"));
+    }
+  }
+  encoded
+}
+
+/// Decodes the percent-encoded string. Invalid percent-encoded sequences are left as is.
+fn percent_decode(input: &str) -> Vec<u8> {
+  let bytes = input.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index] == b'%' {
+      if let Some(byte) = input
+        .get(index + 1..index + 3)
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+      {
+        decoded.push(byte);
+        index += 3;
+        continue;
+      }
+    }
+    decoded.push(bytes[index]);
+    index += 1;
+  }
+  decoded
+}
+
+/// Builds the canonical query string, with the parameters URI-encoded and sorted by name and value
+fn canonical_query_string(query: &str) -> String {
+  let mut parameters = query
+    .split('&')
+    .filter(|parameter| !parameter.is_empty())
+    .map(|parameter| {
+      let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+      (
+        uri_encode(&percent_decode(name), true),
+        uri_encode(&percent_decode(value), true),
+      )
+    })
+    .collect::<Vec<_>>();
+  parameters.sort();
+  parameters
+    .into_iter()
+    .map(|(name, value)| format!("{name}={value}"))
+    .collect::<Vec<_>>()
+    .join("&")
+}
+
+/// Formats the time as the `X-Amz-Date` header value (like `20150830T123600Z`), along with the date (like `20150830`)
+fn format_amz_date(time: SystemTime) -> (String, String) {
+  let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+  let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+  // Converts the number of days since the Unix epoch into a civil date
+  let days = days as i64 + 719468;
+  let era = days.div_euclid(146097);
+  let day_of_era = days.rem_euclid(146097);
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  let date = format!("{year:04}{month:02}{day:02}");
+  (
+    format!(
+      "{date}T{:02}{:02}{:02}Z",
+      seconds_of_day / 3600,
+      (seconds_of_day % 3600) / 60,
+      seconds_of_day % 60
+    ),
+    date,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  fn aws_signing() -> ProxyRequestSigning {
+    ProxyRequestSigning::AwsSigV4 {
+      region: "us-east-1".to_string(),
+      service: "service".to_string(),
+      credentials: AwsCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: None,
+      },
+    }
+  }
+
+  fn sign_request(signing: &ProxyRequestSigning, uri: &str) -> hyper::http::request::Parts {
+    let (mut request_parts, _) = hyper::Request::builder()
+      .method("GET")
+      .uri(uri)
+      .header(header::HOST, "example.amazonaws.com")
+      .body(())
+      .unwrap()
+      .into_parts();
+    signing
+      .sign(
+        &mut request_parts,
+        &"https://example.amazonaws.com".parse().unwrap(),
+        Some(&payload_hash(b"")),
+        UNIX_EPOCH + Duration::from_secs(1440938160),
+      )
+      .unwrap();
+    request_parts
+  }
+
+  #[test]
+  fn test_format_amz_date() {
+    assert_eq!(
+      format_amz_date(UNIX_EPOCH + Duration::from_secs(1440938160)),
+      ("20150830T123600Z".to_string(), "20150830".to_string())
+    );
+    assert_eq!(
+      format_amz_date(UNIX_EPOCH + Duration::from_secs(951825600)),
+      ("20000229T120000Z".to_string(), "20000229".to_string())
+    );
+  }
+
+  #[test]
+  fn test_aws_sigv4_signature() {
+    // The "get-vanilla" and "get-vanilla-query-order-key-case" cases from the AWS Signature Version 4 test suite
+    let request_parts = sign_request(&aws_signing(), "/");
+    assert_eq!(
+      request_parts.headers.get(header::AUTHORIZATION).unwrap(),
+      "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+       SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+    let request_parts = sign_request(&aws_signing(), "/?Param2=value2&Param1=value1");
+    assert!(request_parts
+      .headers
+      .get(header::AUTHORIZATION)
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .ends_with("Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"));
+  }
+
+  #[test]
+  fn test_canonical_query_string() {
+    assert_eq!(canonical_query_string("b=2&a=1&a=0&c"), "a=0&a=1&b=2&c=");
+    assert_eq!(canonical_query_string("key=a%20b+c&x=%2F"), "key=a%20b%2Bc&x=%2F");
+  }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hyper::header::{self, HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+
+use super::{ProxyToKeyInner, UpstreamInner};
+
+/// Sticky session settings for a group of backend servers.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct StickySessions {
+  /// Name of the cookie binding the client to a backend server.
+  pub cookie_name: String,
+  /// Lifetime of a sticky session.
+  pub ttl: Duration,
+  /// Maximum time for which draining backend servers receive requests from existing sticky sessions.
+  pub drain_timeout: Duration,
+}
+
+impl Default for StickySessions {
+  fn default() -> Self {
+    Self {
+      cookie_name: "ferron_backend".to_string(),
+      ttl: Duration::from_secs(3600),
+      drain_timeout: Duration::from_secs(600),
+    }
+  }
+}
+
+/// A sticky session read from the request cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct StickySession {
+  backend_id: u64,
+  session_id: u64,
+  expires_at: u64,
+}
+
+impl StickySession {
+  /// Parses the sticky session cookie value (`<backend ID>.<expiration time>.<session ID>`)
+  fn parse(value: &str) -> Option<Self> {
+    let mut parts = value.split('.');
+    let backend_id = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let expires_at = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let session_id = u64::from_str_radix(parts.next()?, 16).ok()?;
+    if parts.next().is_some() {
+      return None;
+    }
+    Some(Self {
+      backend_id,
+      session_id,
+      expires_at,
+    })
+  }
+}
+
+/// An event related to draining a backend server.
+pub(super) enum DrainEvent {
+  /// A request from an existing sticky session was sent to the draining backend server.
+  Request(UpstreamInner),
+  /// The number of active sticky sessions on the draining backend server changed.
+  Sessions(UpstreamInner, i64),
+  /// The draining backend server was removed.
+  Drained(UpstreamInner),
+}
+
+/// Drain state of a backend server.
+#[derive(Default)]
+struct DrainState {
+  drained: bool,
+  sessions: HashMap<u64, u64>,
+}
+
+/// Sticky session routing, shared by reverse proxy handlers.
+/// Draining backend servers don't receive new sticky sessions, but still receive requests from existing sticky sessions,
+/// until the sessions expire or the drain timeout passes. Afterwards, the draining backend servers are removed.
+pub(super) struct SessionAffinity {
+  settings: StickySessions,
+  drain_deadline: Instant,
+  draining: Mutex<HashMap<UpstreamInner, DrainState>>,
+}
+
+impl SessionAffinity {
+  /// Creates a new sticky session routing state. The backend servers are draining since `drain_started`.
+  pub(super) fn new(
+    settings: StickySessions,
+    draining: impl IntoIterator<Item = UpstreamInner>,
+    drain_started: Instant,
+  ) -> Self {
+    // The sessions created before draining started expire before `drain_started + ttl`
+    let drain_deadline = drain_started + settings.ttl.min(settings.drain_timeout);
+    Self {
+      settings,
+      drain_deadline,
+      draining: Mutex::new(
+        draining
+          .into_iter()
+          .map(|upstream| (upstream, DrainState::default()))
+          .collect(),
+      ),
+    }
+  }
+
+  /// Reads the sticky session from the request cookies.
+  pub(super) fn session_from_headers(&self, headers: &HeaderMap) -> Option<StickySession> {
+    headers
+      .get_all(header::COOKIE)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(';'))
+      .filter_map(|cookie| cookie.trim().split_once('='))
+      .find(|(name, _)| *name == self.settings.cookie_name)
+      .and_then(|(_, value)| StickySession::parse(value))
+  }
+
+  /// Removes the drained backend servers, and the draining backend servers, if other backend servers are available.
+  /// Returns the backend server bound to the sticky session (removed from the backend server list), if it's available,
+  /// along with the drain events.
+  pub(super) fn route(
+    &self,
+    proxy_to_vector: &mut Vec<ProxyToKeyInner>,
+    session: Option<StickySession>,
+    now: Instant,
+    now_unix: u64,
+  ) -> (Option<ProxyToKeyInner>, Vec<DrainEvent>) {
+    let mut events = Vec::new();
+    let mut draining = match self.draining.lock() {
+      Ok(draining) => draining,
+      Err(_) => return (None, events),
+    };
+    let drain_deadline_passed = now >= self.drain_deadline;
+    for (upstream, state) in draining.iter_mut() {
+      let previous_sessions = state.sessions.len();
+      if drain_deadline_passed {
+        state.sessions.clear();
+        if !state.drained {
+          state.drained = true;
+          events.push(DrainEvent::Drained(upstream.clone()));
+        }
+      } else {
+        state.sessions.retain(|_, expires_at| *expires_at > now_unix);
+      }
+      let expired_sessions = previous_sessions - state.sessions.len();
+      if expired_sessions > 0 {
+        events.push(DrainEvent::Sessions(upstream.clone(), -(expired_sessions as i64)));
+      }
+    }
+    proxy_to_vector.retain(|(upstream, _, _)| draining.get(upstream).is_none_or(|state| !state.drained));
+
+    let mut affinity = None;
+    if let Some(session) = session.filter(|session| session.expires_at > now_unix) {
+      if let Some(index) = proxy_to_vector
+        .iter()
+        .position(|(upstream, _, _)| backend_id(upstream) == session.backend_id)
+      {
+        let upstream = proxy_to_vector.remove(index);
+        if let Some(state) = draining.get_mut(&upstream.0) {
+          if state.sessions.insert(session.session_id, session.expires_at).is_none() {
+            events.push(DrainEvent::Sessions(upstream.0.clone(), 1));
+          }
+        }
+        affinity = Some(upstream);
+      }
+    }
+
+    // Draining backend servers don't receive new sticky sessions, unless no other backend servers are available
+    if proxy_to_vector
+      .iter()
+      .any(|(upstream, _, _)| !draining.contains_key(upstream))
+    {
+      proxy_to_vector.retain(|(upstream, _, _)| !draining.contains_key(upstream));
+    }
+    (affinity, events)
+  }
+
+  /// Checks whether a backend server is draining.
+  pub(super) fn is_draining(&self, upstream: &UpstreamInner) -> bool {
+    self
+      .draining
+      .lock()
+      .is_ok_and(|draining| draining.contains_key(upstream))
+  }
+
+  /// Creates the `Set-Cookie` header value for a new sticky session bound to a backend server.
+  pub(super) fn new_session_cookie(
+    &self,
+    upstream: &UpstreamInner,
+    now_unix: u64,
+    secure: bool,
+  ) -> Option<HeaderValue> {
+    let ttl = self.settings.ttl.as_secs();
+    HeaderValue::from_str(&format!(
+      "{}={:016x}.{:x}.{:016x}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax{}",
+      self.settings.cookie_name,
+      backend_id(upstream),
+      now_unix.saturating_add(ttl),
+      rand::random::<u64>(),
+      ttl,
+      if secure { "; Secure" } else { "" }
+    ))
+    .ok()
+  }
+}
+
+/// Obtains the backend server ID stored in the sticky session cookie.
+/// The ID is derived from the backend server URL, so that it's the same across reloads and server instances.
+fn backend_id(upstream: &UpstreamInner) -> u64 {
+  let mut hasher = Sha256::new();
+  hasher.update(upstream.proxy_to.as_bytes());
+  if let Some(proxy_unix) = upstream.proxy_unix.as_ref() {
+    hasher.update(b"\0");
+    hasher.update(proxy_unix.as_bytes());
+  }
+  let digest = hasher.finalize();
+  let mut id = [0u8; 8];
+  id.copy_from_slice(&digest[..8]);
+  u64::from_be_bytes(id)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn upstream(proxy_to: &str) -> UpstreamInner {
+    UpstreamInner {
+      proxy_to: proxy_to.to_string(),
+      proxy_unix: None,
+    }
+  }
+
+  fn session_for(affinity: &SessionAffinity, upstream: &UpstreamInner, now_unix: u64) -> StickySession {
+    let cookie = affinity
+      .new_session_cookie(upstream, now_unix, false)
+      .expect("the cookie should be valid");
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::COOKIE,
+      HeaderValue::from_str(cookie.to_str().unwrap().split(';').next().unwrap()).unwrap(),
+    );
+    affinity
+      .session_from_headers(&headers)
+      .expect("the session should be parsed")
+  }
+
+  #[test]
+  fn sticky_session_binds_to_backend() {
+    let backend1 = upstream("http://backend-1");
+    let backend2 = upstream("http://backend-2");
+    let affinity = SessionAffinity::new(StickySessions::default(), [], Instant::now());
+    let session = session_for(&affinity, &backend2, 1000);
+
+    let mut backends = vec![(backend1.clone(), None, None), (backend2.clone(), None, None)];
+    let (selected, _) = affinity.route(&mut backends, Some(session), Instant::now(), 1000);
+    assert!(selected.is_some_and(|(u, _, _)| u == backend2));
+    assert_eq!(backends.len(), 1);
+
+    // Expired sessions aren't honored
+    let mut backends = vec![(backend1, None, None), (backend2, None, None)];
+    let (selected, _) = affinity.route(&mut backends, Some(session), Instant::now(), 1000 + 3600);
+    assert!(selected.is_none());
+    assert_eq!(backends.len(), 2);
+  }
+
+  #[test]
+  fn draining_backend_keeps_existing_sessions_until_deadline() {
+    let backend1 = upstream("http://backend-1");
+    let draining = upstream("http://backend-draining");
+    let now = Instant::now();
+    let affinity = SessionAffinity::new(StickySessions::default(), [draining.clone()], now);
+    let session = session_for(&affinity, &draining, 1000);
+    let backends = || vec![(backend1.clone(), None, None), (draining.clone(), None, None)];
+
+    // New sessions aren't assigned to the draining backend server
+    let mut new_session_backends = backends();
+    let (selected, _) = affinity.route(&mut new_session_backends, None, now, 1000);
+    assert!(selected.is_none());
+    assert!(new_session_backends.iter().all(|(u, _, _)| u == &backend1));
+
+    // Existing sessions are still honored
+    let mut existing_session_backends = backends();
+    let (selected, events) = affinity.route(&mut existing_session_backends, Some(session), now, 1000);
+    assert!(selected.is_some_and(|(u, _, _)| u == draining));
+    assert!(events
+      .iter()
+      .any(|event| matches!(event, DrainEvent::Sessions(u, 1) if u == &draining)));
+
+    // After the drain timeout, the draining backend server is removed
+    let mut drained_backends = backends();
+    let (selected, events) = affinity.route(
+      &mut drained_backends,
+      Some(session),
+      now + Duration::from_secs(600),
+      1600,
+    );
+    assert!(selected.is_none());
+    assert!(drained_backends.iter().all(|(u, _, _)| u == &backend1));
+    assert!(events
+      .iter()
+      .any(|event| matches!(event, DrainEvent::Drained(u) if u == &draining)));
+    assert!(events
+      .iter()
+      .any(|event| matches!(event, DrainEvent::Sessions(u, -1) if u == &draining)));
+  }
+}
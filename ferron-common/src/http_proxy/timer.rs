@@ -0,0 +1,57 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+#[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+use send_wrapper::SendWrapper;
+
+/// A timer for Hyper that utilizes the asynchronous runtime's timer.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct RuntimeTimer;
+
+impl hyper::rt::Timer for RuntimeTimer {
+  fn sleep(&self, duration: Duration) -> Pin<Box<dyn hyper::rt::Sleep>> {
+    Box::pin(RuntimeSleep::new(duration))
+  }
+
+  fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn hyper::rt::Sleep>> {
+    Box::pin(RuntimeSleep::new(deadline.saturating_duration_since(Instant::now())))
+  }
+}
+
+/// A sleep future for Hyper that utilizes the asynchronous runtime's timer.
+struct RuntimeSleep {
+  #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+  inner: SendWrapper<Pin<Box<dyn Future<Output = ()>>>>,
+  #[cfg(feature = "runtime-tokio")]
+  inner: Pin<Box<tokio::time::Sleep>>,
+}
+
+impl RuntimeSleep {
+  fn new(duration: Duration) -> Self {
+    Self {
+      // Safety of the SendWrapper: Hyper connections (and their timers) are driven on the thread that created them
+      #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+      inner: SendWrapper::new(Box::pin(crate::runtime::sleep(duration))),
+      #[cfg(feature = "runtime-tokio")]
+      inner: Box::pin(crate::runtime::sleep(duration)),
+    }
+  }
+}
+
+impl Future for RuntimeSleep {
+  type Output = ();
+
+  #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    (*self.inner).as_mut().poll(cx)
+  }
+
+  #[cfg(feature = "runtime-tokio")]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self.inner.as_mut().poll(cx)
+  }
+}
+
+impl hyper::rt::Sleep for RuntimeSleep {}
@@ -0,0 +1,214 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use hyper::HeaderMap;
+
+use super::UpstreamInner;
+use crate::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+
+/// Estimates the size of the header fields in the HTTP/1.x wire format (including the empty line after the headers)
+fn estimate_header_fields_size(headers: &HeaderMap) -> u64 {
+  headers
+    .iter()
+    .map(|(name, value)| (name.as_str().len() + value.len() + 4) as u64)
+    .sum::<u64>()
+    + 2
+}
+
+/// Numbers of bytes transferred between the reverse proxy and a backend server for a single request.
+/// The numbers are sent as metrics, after both the request body and the response body are dropped.
+pub(super) struct BackendTransfer {
+  upstream: UpstreamInner,
+  bytes_sent: AtomicU64,
+  bytes_received: AtomicU64,
+  metrics_sender: MetricsMultiSender,
+}
+
+impl BackendTransfer {
+  /// Starts counting the transferred bytes, including the estimated size of the request head.
+  pub(super) fn new(
+    upstream: UpstreamInner,
+    request_parts: &hyper::http::request::Parts,
+    metrics_sender: MetricsMultiSender,
+  ) -> Arc<Self> {
+    let request_line_size = request_parts.method.as_str().len() + request_parts.uri.to_string().len() + 12;
+    Arc::new(Self {
+      upstream,
+      bytes_sent: AtomicU64::new(request_line_size as u64 + estimate_header_fields_size(&request_parts.headers)),
+      bytes_received: AtomicU64::new(0),
+      metrics_sender,
+    })
+  }
+
+  /// Counts the estimated size of the response head received from the backend server.
+  pub(super) fn record_response_head(&self, response_parts: &hyper::http::response::Parts) {
+    let status_line_size = response_parts
+      .status
+      .canonical_reason()
+      .map_or(0, |reason| reason.len() + 1)
+      + 14;
+    self.bytes_received.fetch_add(
+      status_line_size as u64 + estimate_header_fields_size(&response_parts.headers),
+      Ordering::Relaxed,
+    );
+  }
+}
+
+impl Drop for BackendTransfer {
+  fn drop(&mut self) {
+    for (name, value, description) in [
+      (
+        "ferron.proxy.backend.bytes_sent",
+        self.bytes_sent.load(Ordering::Relaxed),
+        "Number of bytes sent to a backend server.",
+      ),
+      (
+        "ferron.proxy.backend.bytes_received",
+        self.bytes_received.load(Ordering::Relaxed),
+        "Number of bytes received from a backend server.",
+      ),
+    ] {
+      let mut attributes = Vec::new();
+      attributes.push((
+        "ferron.proxy.backend_url",
+        MetricAttributeValue::String(self.upstream.proxy_to.clone()),
+      ));
+      if let Some(backend_unix) = self.upstream.proxy_unix.clone() {
+        attributes.push((
+          "ferron.proxy.backend_unix_path",
+          MetricAttributeValue::String(backend_unix),
+        ));
+      }
+      self.metrics_sender.try_send(Metric::new(
+        name,
+        attributes,
+        MetricType::Counter,
+        MetricValue::U64(value),
+        Some("By"),
+        Some(description),
+      ));
+    }
+  }
+}
+
+/// Direction of the data passing through a [`TransferCountingBody`].
+#[derive(Clone, Copy)]
+pub(super) enum TransferDirection {
+  /// The data is sent to the backend server.
+  Sent,
+  /// The data is received from the backend server.
+  Received,
+}
+
+/// A body counting the bytes transferred between the reverse proxy and a backend server.
+pub(super) struct TransferCountingBody<B> {
+  inner: B,
+  transfer: Arc<BackendTransfer>,
+  direction: TransferDirection,
+}
+
+impl<B> TransferCountingBody<B> {
+  pub(super) fn new(inner: B, transfer: Arc<BackendTransfer>, direction: TransferDirection) -> Self {
+    Self {
+      inner,
+      transfer,
+      direction,
+    }
+  }
+}
+
+impl<B> Body for TransferCountingBody<B>
+where
+  B: Body<Data = Bytes> + Unpin,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  #[inline]
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let result = Pin::new(&mut self.inner).poll_frame(cx);
+    if let Poll::Ready(Some(Ok(frame))) = &result {
+      if let Some(data) = frame.data_ref() {
+        let counter = match self.direction {
+          TransferDirection::Sent => &self.transfer.bytes_sent,
+          TransferDirection::Received => &self.transfer.bytes_received,
+        };
+        counter.fetch_add(data.len() as u64, Ordering::Relaxed);
+      }
+    }
+    result
+  }
+
+  #[inline]
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use http_body_util::{BodyExt, Full};
+  use hyper::{Request, Response};
+
+  use super::*;
+
+  #[test]
+  fn transferred_bytes_are_sent_as_metrics_after_bodies_are_dropped() {
+    let (metrics_tx, metrics_rx) = async_channel::unbounded();
+    let upstream = UpstreamInner {
+      proxy_to: "http://backend".to_string(),
+      proxy_unix: None,
+    };
+    let (request_parts, _) = Request::get("/").body(()).unwrap().into_parts();
+    let transfer = BackendTransfer::new(upstream, &request_parts, MetricsMultiSender::new(metrics_tx));
+    let (response_parts, _) = Response::new(()).into_parts();
+    transfer.record_response_head(&response_parts);
+
+    let request_body = TransferCountingBody::new(
+      Full::new(Bytes::from_static(b"Hello")),
+      transfer.clone(),
+      TransferDirection::Sent,
+    );
+    let response_body = TransferCountingBody::new(
+      Full::new(Bytes::from_static(b"Hello, world!")),
+      transfer,
+      TransferDirection::Received,
+    );
+    tokio::runtime::Builder::new_current_thread()
+      .build()
+      .expect("runtime should be created")
+      .block_on(async {
+        request_body.collect().await.unwrap();
+        // The metrics are sent only after both bodies are dropped
+        assert!(metrics_rx.is_empty());
+        response_body.collect().await.unwrap();
+      });
+
+    let values = std::iter::from_fn(|| metrics_rx.try_recv().ok())
+      .map(|metric| match metric.value {
+        MetricValue::U64(value) => (metric.name, value),
+        _ => panic!("unexpected metric value"),
+      })
+      .collect::<Vec<_>>();
+    // "GET / HTTP/1.1\r\n\r\n" + "Hello", and "HTTP/1.1 200 OK\r\n\r\n" + "Hello, world!"
+    assert_eq!(
+      values,
+      vec![
+        ("ferron.proxy.backend.bytes_sent", 23),
+        ("ferron.proxy.backend.bytes_received", 32),
+      ]
+    );
+  }
+}
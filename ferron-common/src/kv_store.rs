@@ -0,0 +1,639 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use tokio::sync::OnceCell;
+
+use crate::config::ServerConfigurationEntry;
+
+/// The interval of persisting the file-backed key-value store
+const FILE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The timeout for Redis operations
+const REDIS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The number of writes, after which the expired entries are removed from the in-memory key-value store
+const PRUNE_INTERVAL_WRITES: usize = 1024;
+
+/// A key-value store shared by modules (for example, for counters). Values are strings, and entries can expire.
+#[async_trait]
+pub trait KvStore: Send + Sync {
+  /// Obtains the value of the entry
+  async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>>;
+
+  /// Sets the value of the entry, optionally expiring after the TTL
+  async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /// Increments the integer value of the entry (missing entries are treated as zero), and returns the new value.
+  /// The TTL is set only when the entry is created.
+  async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>>;
+
+  /// Removes the entry
+  async fn delete(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+  /// Obtains all the entries, which keys start with the prefix
+  async fn scan(&self, prefix: &str) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>>;
+}
+
+/// An entry of the in-memory key-value store
+#[derive(Clone)]
+struct MemoryKvEntry {
+  value: String,
+  expires_at: Option<SystemTime>,
+}
+
+impl MemoryKvEntry {
+  /// Checks if the entry has expired
+  fn is_expired(&self, now: SystemTime) -> bool {
+    self.expires_at.is_some_and(|expires_at| expires_at <= now)
+  }
+}
+
+/// An in-memory key-value store
+#[derive(Default)]
+pub struct MemoryKvStore {
+  entries: Mutex<HashMap<String, MemoryKvEntry>>,
+  writes: AtomicUsize,
+}
+
+impl MemoryKvStore {
+  /// Counts a write, periodically removing the expired entries
+  fn register_write(&self, entries: &mut HashMap<String, MemoryKvEntry>, now: SystemTime) {
+    if self.writes.fetch_add(1, Ordering::Relaxed) % PRUNE_INTERVAL_WRITES == PRUNE_INTERVAL_WRITES - 1 {
+      entries.retain(|_, entry| !entry.is_expired(now));
+    }
+  }
+
+  fn get_sync(&self, key: &str) -> Option<String> {
+    let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    entries
+      .get(key)
+      .filter(|entry| !entry.is_expired(SystemTime::now()))
+      .map(|entry| entry.value.clone())
+  }
+
+  fn set_sync(&self, key: &str, value: &str, ttl: Option<Duration>) {
+    let now = SystemTime::now();
+    let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    self.register_write(&mut entries, now);
+    entries.insert(
+      key.to_string(),
+      MemoryKvEntry {
+        value: value.to_string(),
+        expires_at: ttl.and_then(|ttl| now.checked_add(ttl)),
+      },
+    );
+  }
+
+  fn increment_sync(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let now = SystemTime::now();
+    let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    self.register_write(&mut entries, now);
+    let entry = entries
+      .get_mut(key)
+      .filter(|entry| !entry.is_expired(now))
+      .map(|entry| {
+        let value = entry
+          .value
+          .parse::<i64>()
+          .map_err(|_| anyhow::anyhow!("The key-value store entry isn't an integer"))?
+          .saturating_add(delta);
+        entry.value = value.to_string();
+        Ok::<_, Box<dyn Error + Send + Sync>>(value)
+      })
+      .transpose()?;
+    match entry {
+      Some(value) => Ok(value),
+      None => {
+        entries.insert(
+          key.to_string(),
+          MemoryKvEntry {
+            value: delta.to_string(),
+            expires_at: ttl.and_then(|ttl| now.checked_add(ttl)),
+          },
+        );
+        Ok(delta)
+      }
+    }
+  }
+
+  fn delete_sync(&self, key: &str) {
+    self.entries.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+  }
+
+  fn scan_sync(&self, prefix: &str) -> Vec<(String, String)> {
+    let now = SystemTime::now();
+    let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    entries
+      .iter()
+      .filter(|(key, entry)| key.starts_with(prefix) && !entry.is_expired(now))
+      .map(|(key, entry)| (key.clone(), entry.value.clone()))
+      .collect()
+  }
+}
+
+#[async_trait]
+impl KvStore for MemoryKvStore {
+  async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    Ok(self.get_sync(key))
+  }
+
+  async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.set_sync(key, value, ttl);
+    Ok(())
+  }
+
+  async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    self.increment_sync(key, delta, ttl)
+  }
+
+  async fn delete(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.delete_sync(key);
+    Ok(())
+  }
+
+  async fn scan(&self, prefix: &str) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    Ok(self.scan_sync(prefix))
+  }
+}
+
+/// A key-value store persisted into a JSON file
+pub struct FileKvStore {
+  inner: MemoryKvStore,
+  path: PathBuf,
+  dirty: AtomicBool,
+}
+
+impl FileKvStore {
+  /// Creates a file-backed key-value store, loading the entries from the file if it exists
+  pub fn new(path: PathBuf) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let mut entries = HashMap::new();
+    match std::fs::read(&path) {
+      Ok(data) => {
+        let serialized_entries = serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&data)
+          .map_err(|e| anyhow::anyhow!("Can't parse the key-value store file: {e}"))?;
+        let now = SystemTime::now();
+        for (key, serialized_entry) in serialized_entries {
+          let Some(value) = serialized_entry.get("value").and_then(|v| v.as_str()) else {
+            continue;
+          };
+          let entry = MemoryKvEntry {
+            value: value.to_string(),
+            expires_at: serialized_entry
+              .get("expires_at")
+              .and_then(|v| v.as_u64())
+              .map(|expires_at| UNIX_EPOCH + Duration::from_secs(expires_at)),
+          };
+          if !entry.is_expired(now) {
+            entries.insert(key, entry);
+          }
+        }
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+      Err(err) => Err(anyhow::anyhow!("Can't read the key-value store file: {err}"))?,
+    }
+    Ok(Self {
+      inner: MemoryKvStore {
+        entries: Mutex::new(entries),
+        writes: AtomicUsize::new(0),
+      },
+      path,
+      dirty: AtomicBool::new(false),
+    })
+  }
+
+  /// Persists the entries into the file, if they have changed
+  pub fn flush(&self) -> Result<(), std::io::Error> {
+    if !self.dirty.swap(false, Ordering::Relaxed) {
+      return Ok(());
+    }
+    let now = SystemTime::now();
+    let serialized_entries = self
+      .inner
+      .entries
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .iter()
+      .filter(|(_, entry)| !entry.is_expired(now))
+      .map(|(key, entry)| {
+        (
+          key.clone(),
+          serde_json::json!({
+            "value": entry.value,
+            "expires_at": entry
+              .expires_at
+              .and_then(|expires_at| expires_at.duration_since(UNIX_EPOCH).ok())
+              .map(|expires_at| expires_at.as_secs()),
+          }),
+        )
+      })
+      .collect::<serde_json::Map<_, _>>();
+    let data = serde_json::to_vec(&serialized_entries)?;
+    let temporary_path = self.path.with_extension("tmp");
+    let result = std::fs::write(&temporary_path, data).and_then(|_| std::fs::rename(&temporary_path, &self.path));
+    if result.is_err() {
+      self.dirty.store(true, Ordering::Relaxed);
+    }
+    result
+  }
+
+  /// Spawns a task periodically persisting the entries, which ends when the store is dropped
+  fn spawn_flush_task(store: &Arc<Self>, runtime: &tokio::runtime::Handle) {
+    let store: Weak<Self> = Arc::downgrade(store);
+    runtime.spawn(async move {
+      loop {
+        tokio::time::sleep(FILE_FLUSH_INTERVAL).await;
+        let Some(store) = store.upgrade() else {
+          break;
+        };
+        let _ = tokio::task::spawn_blocking(move || store.flush()).await;
+      }
+    });
+  }
+}
+
+#[async_trait]
+impl KvStore for FileKvStore {
+  async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    Ok(self.inner.get_sync(key))
+  }
+
+  async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.dirty.store(true, Ordering::Relaxed);
+    self.inner.set_sync(key, value, ttl);
+    Ok(())
+  }
+
+  async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    self.dirty.store(true, Ordering::Relaxed);
+    self.inner.increment_sync(key, delta, ttl)
+  }
+
+  async fn delete(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.dirty.store(true, Ordering::Relaxed);
+    self.inner.delete_sync(key);
+    Ok(())
+  }
+
+  async fn scan(&self, prefix: &str) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    Ok(self.inner.scan_sync(prefix))
+  }
+}
+
+impl Drop for FileKvStore {
+  fn drop(&mut self) {
+    let _ = self.flush();
+  }
+}
+
+/// A lazily established connection to a Redis server
+struct RedisConnection {
+  client: redis::Client,
+  manager: OnceCell<ConnectionManager>,
+}
+
+/// A key-value store backed by a Redis server. The Redis operations are executed on the Tokio runtime.
+pub struct RedisKvStore {
+  connection: Arc<RedisConnection>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl RedisKvStore {
+  /// Creates a Redis-backed key-value store. The connection is established on the first operation.
+  pub fn new(url: &str, runtime: tokio::runtime::Handle) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(Self {
+      connection: Arc::new(RedisConnection {
+        client: redis::Client::open(url)?,
+        manager: OnceCell::new(),
+      }),
+      runtime,
+    })
+  }
+
+  /// Executes a Redis operation on the Tokio runtime
+  async fn execute<T, F, Fut>(&self, operation: F) -> Result<T, Box<dyn Error + Send + Sync>>
+  where
+    T: Send + 'static,
+    F: FnOnce(ConnectionManager) -> Fut + Send + 'static,
+    Fut: Future<Output = redis::RedisResult<T>> + Send + 'static,
+  {
+    let connection = self.connection.clone();
+    self
+      .runtime
+      .spawn(async move {
+        let result = tokio::time::timeout(REDIS_TIMEOUT, async move {
+          // The connection manager reconnects automatically, so it's established only once
+          let manager = connection
+            .manager
+            .get_or_try_init(|| connection.client.get_connection_manager())
+            .await?
+            .clone();
+          operation(manager).await
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("The key-value store operation has timed out"))?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(result?)
+      })
+      .await?
+  }
+}
+
+/// Escapes the glob pattern characters for the Redis `SCAN` command
+fn escape_redis_pattern(pattern: &str) -> String {
+  let mut escaped = String::with_capacity(pattern.len());
+  for c in pattern.chars() {
+    if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+#[async_trait]
+impl KvStore for RedisKvStore {
+  async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let key = key.to_string();
+    self
+      .execute(move |mut manager| async move {
+        redis::cmd("GET")
+          .arg(&key)
+          .query_async::<Option<String>>(&mut manager)
+          .await
+      })
+      .await
+  }
+
+  async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let key = key.to_string();
+    let value = value.to_string();
+    self
+      .execute(move |mut manager| async move {
+        let mut command = redis::cmd("SET");
+        command.arg(&key).arg(&value);
+        if let Some(ttl) = ttl {
+          command.arg("PX").arg(ttl.as_millis().max(1) as u64);
+        }
+        command.query_async::<()>(&mut manager).await
+      })
+      .await
+  }
+
+  async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let key = key.to_string();
+    self
+      .execute(move |mut manager| async move {
+        let (value, remaining_ttl) = redis::pipe()
+          .cmd("INCRBY")
+          .arg(&key)
+          .arg(delta)
+          .cmd("PTTL")
+          .arg(&key)
+          .query_async::<(i64, i64)>(&mut manager)
+          .await?;
+        // The entry without an expiration (PTTL returns -1) has just been created
+        if let (Some(ttl), -1) = (ttl, remaining_ttl) {
+          redis::cmd("PEXPIRE")
+            .arg(&key)
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async::<()>(&mut manager)
+            .await?;
+        }
+        Ok(value)
+      })
+      .await
+  }
+
+  async fn delete(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let key = key.to_string();
+    self
+      .execute(move |mut manager| async move { redis::cmd("DEL").arg(&key).query_async::<()>(&mut manager).await })
+      .await
+  }
+
+  async fn scan(&self, prefix: &str) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    let pattern = format!("{}*", escape_redis_pattern(prefix));
+    self
+      .execute(move |mut manager| async move {
+        let mut keys = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+          let (next_cursor, batch_keys) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(1000)
+            .query_async::<(u64, Vec<String>)>(&mut manager)
+            .await?;
+          keys.extend(batch_keys);
+          cursor = next_cursor;
+          if cursor == 0 {
+            break;
+          }
+        }
+        if keys.is_empty() {
+          return Ok(Vec::new());
+        }
+        let values = redis::cmd("MGET")
+          .arg(&keys)
+          .query_async::<Vec<Option<String>>>(&mut manager)
+          .await?;
+        // The entries could expire between the `SCAN` and `MGET` commands
+        Ok(
+          keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect(),
+        )
+      })
+      .await
+  }
+}
+
+/// A view of a key-value store, which prefixes the keys with a namespace, so that modules sharing a store
+/// don't overwrite each other's entries
+pub struct NamespacedKvStore {
+  inner: Arc<dyn KvStore>,
+  prefix: String,
+}
+
+impl NamespacedKvStore {
+  /// Creates a namespaced view of the key-value store
+  pub fn new(inner: Arc<dyn KvStore>, namespace: &str) -> Self {
+    Self {
+      inner,
+      prefix: format!("{namespace}:"),
+    }
+  }
+}
+
+#[async_trait]
+impl KvStore for NamespacedKvStore {
+  async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    self.inner.get(&format!("{}{key}", self.prefix)).await
+  }
+
+  async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.inner.set(&format!("{}{key}", self.prefix), value, ttl).await
+  }
+
+  async fn increment(&self, key: &str, delta: i64, ttl: Option<Duration>) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    self.inner.increment(&format!("{}{key}", self.prefix), delta, ttl).await
+  }
+
+  async fn delete(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self.inner.delete(&format!("{}{key}", self.prefix)).await
+  }
+
+  async fn scan(&self, prefix: &str) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .inner
+        .scan(&format!("{}{prefix}", self.prefix))
+        .await?
+        .into_iter()
+        .filter_map(|(key, value)| key.strip_prefix(&self.prefix).map(|key| (key.to_string(), value)))
+        .collect(),
+    )
+  }
+}
+
+/// A key-value store backend configuration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KvStoreConfig {
+  /// An in-memory store (the entries are lost when the server is restarted)
+  Memory,
+  /// A store persisted into a JSON file
+  File(PathBuf),
+  /// A Redis server
+  Redis(String),
+}
+
+impl KvStoreConfig {
+  /// Parses the key-value store configuration entry (like `store "memory"`, `store "file" path="/var/lib/ferron/store.json"`
+  /// or `store "redis://localhost:6379"`)
+  pub fn from_entry(entry: &ServerConfigurationEntry) -> Option<Self> {
+    match entry.values.first()?.as_str()? {
+      "memory" => Some(Self::Memory),
+      "file" => Some(Self::File(PathBuf::from(entry.props.get("path")?.as_str()?))),
+      url => redis::Client::open(url).ok().map(|_| Self::Redis(url.to_string())),
+    }
+  }
+
+  /// Obtains the identifier used for sharing the store instances
+  fn store_id(&self) -> String {
+    match self {
+      Self::Memory => "memory".to_string(),
+      Self::File(path) => format!("file:{}", path.display()),
+      Self::Redis(url) => url.clone(),
+    }
+  }
+}
+
+/// The key-value store instances, shared by modules using the same backend configuration
+static KV_STORES: LazyLock<Mutex<HashMap<String, Weak<dyn KvStore>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Obtains a key-value store for the backend configuration. The store instance is shared across the modules
+/// using the same backend, as long as any of the modules keeps a reference to it.
+pub fn obtain_kv_store(
+  config: &KvStoreConfig,
+  runtime: &tokio::runtime::Handle,
+) -> Result<Arc<dyn KvStore>, Box<dyn Error + Send + Sync>> {
+  let store_id = config.store_id();
+  let mut stores = KV_STORES.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some(store) = stores.get(&store_id).and_then(|store| store.upgrade()) {
+    return Ok(store);
+  }
+  let store: Arc<dyn KvStore> = match config {
+    KvStoreConfig::Memory => Arc::new(MemoryKvStore::default()),
+    KvStoreConfig::File(path) => {
+      let store = Arc::new(FileKvStore::new(path.clone())?);
+      FileKvStore::spawn_flush_task(&store, runtime);
+      store
+    }
+    KvStoreConfig::Redis(url) => Arc::new(RedisKvStore::new(url, runtime.clone())?),
+  };
+  stores.retain(|_, store| store.strong_count() > 0);
+  stores.insert(store_id, Arc::downgrade(&store));
+  Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap()
+      .block_on(future)
+  }
+
+  #[test]
+  fn test_memory_kv_store_increment_and_ttl() {
+    block_on(async {
+      let store = MemoryKvStore::default();
+      assert_eq!(store.increment("counter", 2, None).await.unwrap(), 2);
+      assert_eq!(store.increment("counter", 3, None).await.unwrap(), 5);
+      assert_eq!(store.get("counter").await.unwrap().as_deref(), Some("5"));
+
+      store.set("text", "value", None).await.unwrap();
+      assert!(store.increment("text", 1, None).await.is_err());
+
+      store.set("expired", "value", Some(Duration::ZERO)).await.unwrap();
+      assert_eq!(store.get("expired").await.unwrap(), None);
+      assert_eq!(store.increment("expired", 1, None).await.unwrap(), 1);
+
+      store.delete("counter").await.unwrap();
+      assert_eq!(store.get("counter").await.unwrap(), None);
+    });
+  }
+
+  #[test]
+  fn test_namespaced_kv_store() {
+    block_on(async {
+      let store: Arc<dyn KvStore> = Arc::new(MemoryKvStore::default());
+      let first = NamespacedKvStore::new(store.clone(), "first");
+      let second = NamespacedKvStore::new(store.clone(), "second");
+      first.set("key", "1", None).await.unwrap();
+      second.set("key", "2", None).await.unwrap();
+
+      assert_eq!(first.get("key").await.unwrap().as_deref(), Some("1"));
+      assert_eq!(
+        second.scan("").await.unwrap(),
+        vec![("key".to_string(), "2".to_string())]
+      );
+      assert_eq!(store.get("first:key").await.unwrap().as_deref(), Some("1"));
+    });
+  }
+
+  #[test]
+  fn test_file_kv_store_persistence() {
+    let path = std::env::temp_dir().join(format!("ferron-kv-store-test-{}.json", std::process::id()));
+    block_on(async {
+      let store = FileKvStore::new(path.clone()).unwrap();
+      store.set("key", "value", None).await.unwrap();
+      store.set("expired", "value", Some(Duration::ZERO)).await.unwrap();
+      store.flush().unwrap();
+      drop(store);
+
+      let store = FileKvStore::new(path.clone()).unwrap();
+      assert_eq!(store.get("key").await.unwrap().as_deref(), Some("value"));
+      assert_eq!(store.scan("").await.unwrap().len(), 1);
+    });
+    let _ = std::fs::remove_file(path);
+  }
+
+  #[test]
+  fn test_escape_redis_pattern() {
+    assert_eq!(escape_redis_pattern("quota:2026-10*"), "quota:2026-10\\*");
+  }
+}
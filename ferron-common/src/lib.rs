@@ -2,6 +2,8 @@ pub mod config;
 pub mod dns;
 #[cfg(feature = "http-proxy")]
 pub mod http_proxy;
+#[cfg(feature = "kv-store")]
+pub mod kv_store;
 pub mod logging;
 pub mod modules;
 pub mod observability;
@@ -1,10 +1,127 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
 use async_channel::Sender;
 
+/// Represents a structured access log record, used by observability backends that store access logs as records
+/// (for example, in databases) instead of formatted messages.
+#[derive(Clone, Debug)]
+pub struct AccessLogRecord {
+  /// The time the response was logged
+  pub timestamp: SystemTime,
+  /// The client's IP address
+  pub client_ip: IpAddr,
+  /// The name of the authenticated user
+  pub auth_user: Option<String>,
+  /// The request method
+  pub method: String,
+  /// The request path and query string
+  pub path_and_query: String,
+  /// The HTTP version of the request (like `HTTP/1.1`)
+  pub version: String,
+  /// The response status code
+  pub status_code: u16,
+  /// The response body length
+  pub content_length: Option<u64>,
+  /// The host name requested by the client
+  pub host: Option<String>,
+  /// The value of the `Referer` request header
+  pub referer: Option<String>,
+  /// The value of the `User-Agent` request header
+  pub user_agent: Option<String>,
+}
+
+/// Represents a severity level of a log message, ordered from the least to the most verbose.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+  /// Errors
+  Error,
+  /// Warnings
+  Warn,
+  /// Informational messages
+  Info,
+  /// Debugging messages
+  Debug,
+  /// Very verbose debugging messages
+  Trace,
+}
+
+impl LogLevel {
+  /// Parses the log level name (`error`, `warn`, `info`, `debug` or `trace`).
+  pub fn parse(name: &str) -> Option<Self> {
+    match name.to_ascii_lowercase().as_str() {
+      "error" => Some(Self::Error),
+      "warn" | "warning" => Some(Self::Warn),
+      "info" => Some(Self::Info),
+      "debug" => Some(Self::Debug),
+      "trace" => Some(Self::Trace),
+      _ => None,
+    }
+  }
+
+  /// Returns the name of the log level.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Error => "error",
+      Self::Warn => "warn",
+      Self::Info => "info",
+      Self::Debug => "debug",
+      Self::Trace => "trace",
+    }
+  }
+}
+
+impl Display for LogLevel {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Decides whether log messages of a specified level and target (module) are logged.
+/// The default maximum level can be overridden for specific modules.
+#[derive(Clone, Debug)]
+pub struct LogLevelFilter {
+  default_level: LogLevel,
+  module_levels: HashMap<String, LogLevel>,
+}
+
+impl Default for LogLevelFilter {
+  fn default() -> Self {
+    Self::new(LogLevel::Warn)
+  }
+}
+
+impl LogLevelFilter {
+  /// Creates a new log level filter with the specified default maximum level.
+  pub fn new(default_level: LogLevel) -> Self {
+    Self {
+      default_level,
+      module_levels: HashMap::new(),
+    }
+  }
+
+  /// Overrides the maximum log level for the specified module.
+  pub fn set_module_level(&mut self, module: &str, level: LogLevel) {
+    self.module_levels.insert(module.to_string(), level);
+  }
+
+  /// Checks whether the log messages of the specified level and target are logged.
+  pub fn is_enabled(&self, level: LogLevel, target: &str) -> bool {
+    level <= *self.module_levels.get(target).unwrap_or(&self.default_level)
+  }
+}
+
 /// Represents a log message with its content and error status.
 #[derive(Clone)]
 pub struct LogMessage {
   is_error: bool,
+  level: LogLevel,
+  target: Option<&'static str>,
   message: String,
+  access_log_record: Option<Arc<AccessLogRecord>>,
 }
 
 impl LogMessage {
@@ -19,7 +136,84 @@ impl LogMessage {
   ///
   /// A `LogMessage` object containing the specified message and error status.
   pub fn new(message: String, is_error: bool) -> Self {
-    Self { is_error, message }
+    Self {
+      is_error,
+      level: if is_error { LogLevel::Error } else { LogLevel::Info },
+      target: None,
+      message,
+      access_log_record: None,
+    }
+  }
+
+  /// Creates a new error log `LogMessage` instance with a specified level and target (module).
+  ///
+  /// # Parameters
+  ///
+  /// - `message`: The content of the log message.
+  /// - `level`: The severity level of the log message.
+  /// - `target`: The name of the module, which logged the message (like `http_proxy`).
+  ///
+  /// # Returns
+  ///
+  /// A `LogMessage` object, whose content is prefixed with the level and the target.
+  pub fn with_level(message: String, level: LogLevel, target: &'static str) -> Self {
+    Self {
+      is_error: true,
+      level,
+      target: Some(target),
+      message: format!("[{level}] [{target}] {message}"),
+      access_log_record: None,
+    }
+  }
+
+  /// Creates a new access log `LogMessage` instance with a structured access log record.
+  ///
+  /// # Parameters
+  ///
+  /// - `message`: The formatted access log message.
+  /// - `access_log_record`: The structured access log record.
+  ///
+  /// # Returns
+  ///
+  /// A `LogMessage` object containing the specified message and access log record.
+  pub fn new_access_log(message: String, access_log_record: Arc<AccessLogRecord>) -> Self {
+    Self {
+      is_error: false,
+      level: LogLevel::Info,
+      target: None,
+      message,
+      access_log_record: Some(access_log_record),
+    }
+  }
+
+  /// Returns the severity level of the log message.
+  pub fn get_level(&self) -> LogLevel {
+    self.level
+  }
+
+  /// Returns the name of the module, which logged the message, if specified.
+  pub fn get_target(&self) -> Option<&'static str> {
+    self.target
+  }
+
+  /// Returns the structured access log record, if the message is an access log message with a record.
+  ///
+  /// # Returns
+  ///
+  /// An `Option` containing the access log record.
+  pub fn get_access_log_record(&self) -> Option<Arc<AccessLogRecord>> {
+    self.access_log_record.clone()
+  }
+
+  /// Returns the components of the `LogMessage` without consuming it.
+  ///
+  /// # Returns
+  ///
+  /// A tuple containing:
+  /// - `&str`: The content of the log message.
+  /// - `bool`: A boolean indicating whether the message is an error.
+  pub fn peek_message(&self) -> (&str, bool) {
+    (&self.message, self.is_error)
   }
 
   /// Consumes the `LogMessage` and returns its components.
@@ -37,6 +231,7 @@ impl LogMessage {
 /// Facilitates logging of error messages through a provided logger sender.
 pub struct ErrorLogger {
   loggers: Vec<Sender<LogMessage>>,
+  level_filter: Arc<LogLevelFilter>,
 }
 
 impl ErrorLogger {
@@ -50,7 +245,10 @@ impl ErrorLogger {
   ///
   /// A new `ErrorLogger` instance associated with the provided logger.
   pub fn new(logger: Sender<LogMessage>) -> Self {
-    Self { loggers: vec![logger] }
+    Self {
+      loggers: vec![logger],
+      level_filter: Arc::new(LogLevelFilter::default()),
+    }
   }
 
   /// Creates a new `ErrorLogger` instance with multiple loggers.
@@ -63,7 +261,10 @@ impl ErrorLogger {
   ///
   /// A new `ErrorLogger` instance associated with multiple provided loggers.
   pub fn new_multiple(loggers: Vec<Sender<LogMessage>>) -> Self {
-    Self { loggers }
+    Self {
+      loggers,
+      level_filter: Arc::new(LogLevelFilter::default()),
+    }
   }
 
   /// Creates a new `ErrorLogger` instance without any underlying logger.
@@ -72,7 +273,10 @@ impl ErrorLogger {
   ///
   /// A new `ErrorLogger` instance not associated with any logger.
   pub fn without_logger() -> Self {
-    Self { loggers: vec![] }
+    Self {
+      loggers: vec![],
+      level_filter: Arc::new(LogLevelFilter::default()),
+    }
   }
 
   /// Logs an error message asynchronously.
@@ -100,6 +304,46 @@ impl ErrorLogger {
         .unwrap_or_default();
     }
   }
+
+  /// Sets the filter deciding which leveled log messages are logged.
+  ///
+  /// # Parameters
+  ///
+  /// - `level_filter`: The log level filter.
+  ///
+  /// # Returns
+  ///
+  /// The `ErrorLogger` instance using the specified filter.
+  pub fn with_level_filter(mut self, level_filter: Arc<LogLevelFilter>) -> Self {
+    self.level_filter = level_filter;
+    self
+  }
+
+  /// Checks whether the log messages of the specified level and target are logged.
+  /// This can be used to avoid formatting expensive debugging messages.
+  pub fn is_enabled(&self, level: LogLevel, target: &str) -> bool {
+    !self.loggers.is_empty() && self.level_filter.is_enabled(level, target)
+  }
+
+  /// Logs a message with a specified level and target (module) asynchronously,
+  /// if the level is enabled for the target.
+  ///
+  /// # Parameters
+  ///
+  /// - `level`: The severity level of the message.
+  /// - `target`: The name of the module logging the message (like `http_proxy`).
+  /// - `message`: A string slice containing the message to be logged.
+  pub async fn log_with_level(&self, level: LogLevel, target: &'static str, message: &str) {
+    if !self.is_enabled(level, target) {
+      return;
+    }
+    for logger in &self.loggers {
+      logger
+        .send(LogMessage::with_level(String::from(message), level, target))
+        .await
+        .unwrap_or_default();
+    }
+  }
 }
 
 impl Clone for ErrorLogger {
@@ -111,6 +355,7 @@ impl Clone for ErrorLogger {
   fn clone(&self) -> Self {
     Self {
       loggers: self.loggers.clone(),
+      level_filter: self.level_filter.clone(),
     }
   }
 }
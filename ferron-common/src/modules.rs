@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -12,6 +12,17 @@ use crate::config::ServerConfiguration;
 use crate::logging::ErrorLogger;
 use crate::observability::MetricsMultiSender;
 
+/// The scope of a configuration directive, which determines the configuration blocks the directive can be specified in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirectiveScope {
+  /// The directive can be specified only in the global configuration (`globals` and `*` blocks)
+  Global,
+
+  /// The directive can be specified in the global configuration and host blocks,
+  /// but not in locations, conditional blocks and error handler blocks
+  Host,
+}
+
 /// A trait that defines a module loader
 pub trait ModuleLoader {
   /// Loads a module according to specific configuration
@@ -27,6 +38,12 @@ pub trait ModuleLoader {
     vec![]
   }
 
+  /// Determines the scopes of configuration directives, which can't be specified in every configuration block.
+  /// Directives without a declared scope can be specified in any configuration block.
+  fn get_directive_scopes(&self) -> Vec<(&'static str, DirectiveScope)> {
+    vec![]
+  }
+
   /// Validates the server configuration
   #[allow(unused_variables)]
   fn validate_configuration(
@@ -95,6 +112,12 @@ pub struct SocketData {
 
   /// Indicates if the connection is encrypted.
   pub encrypted: bool,
+
+  /// The server name sent by the client in the TLS SNI extension, if the connection is encrypted.
+  pub tls_sni: Option<String>,
+
+  /// The application protocol negotiated using ALPN, if the connection is encrypted.
+  pub tls_alpn: Option<String>,
 }
 
 /// Data related to an HTTP request
@@ -109,6 +132,35 @@ pub struct RequestData {
   /// The error status code, when the error handler is executed
   #[allow(dead_code)]
   pub error_status_code: Option<StatusCode>,
+
+  /// The address of the directly connected client (before applying the PROXY protocol and the `X-Forwarded-For` header)
+  pub peer_address: Option<SocketAddr>,
+
+  /// The client address received via the PROXY protocol
+  pub proxy_protocol_address: Option<SocketAddr>,
+
+  /// The request variables set by modules, available via the `{var:NAME}` placeholder
+  pub variables: HashMap<String, String>,
+}
+
+/// The tags assigned to an HTTP request by the `classify` directives, stored in the request extensions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestTags(pub Vec<String>);
+
+impl RequestTags {
+  /// Checks if the request has the specified tag
+  pub fn contains(&self, tag: &str) -> bool {
+    self.0.iter().any(|request_tag| request_tag == tag)
+  }
+}
+
+/// The beginning of the HTTP request body buffered by the `body_match_buffer` directive, stored in the request extensions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BufferedRequestBody {
+  /// The buffered request body data
+  pub data: Bytes,
+  /// Whether the whole request body was buffered
+  pub complete: bool,
 }
 
 /// Data related to an HTTP response
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use async_channel::{Receiver, Sender};
 
 use crate::config::ServerConfiguration;
-use crate::logging::LogMessage;
+use crate::logging::{LogLevelFilter, LogMessage};
 
 /// A trait that defines an observability backend loader
 pub trait ObservabilityBackendLoader {
@@ -60,6 +60,8 @@ pub struct ObservabilityBackendChannels {
   pub metric_channels: Vec<Sender<Metric>>,
   /// Trace channels
   pub trace_channels: Vec<(Sender<()>, Receiver<Sender<TraceSignal>>)>,
+  /// Log level filter for leveled (for example, debugging) log messages
+  pub log_level_filter: Arc<LogLevelFilter>,
 }
 
 impl Default for ObservabilityBackendChannels {
@@ -75,6 +77,7 @@ impl ObservabilityBackendChannels {
       log_channels: Vec::new(),
       metric_channels: Vec::new(),
       trace_channels: Vec::new(),
+      log_level_filter: Arc::new(LogLevelFilter::default()),
     }
   }
 
@@ -174,6 +177,7 @@ pub enum MetricAttributeValue {
 }
 
 /// Facilitates logging of error messages through a provided sender.
+#[derive(Debug)]
 pub struct MetricsMultiSender {
   senders: Vec<Sender<Metric>>,
 }
@@ -225,6 +229,19 @@ impl MetricsMultiSender {
       sender.send(metric_data.clone()).await.unwrap_or_default();
     }
   }
+
+  /// Sends metric data without waiting for space in the channels (for example, when a response body is dropped).
+  /// The metric data is discarded for the channels that are full.
+  ///
+  /// # Parameters
+  ///
+  /// - `metric_data`: A `Metric` containing the metric data to be sent.
+  ///
+  pub fn try_send(&self, metric_data: Metric) {
+    for sender in &self.senders {
+      sender.try_send(metric_data.clone()).unwrap_or_default();
+    }
+  }
 }
 
 impl Clone for MetricsMultiSender {
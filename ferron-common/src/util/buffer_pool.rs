@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::BytesMut;
+
+/// The capacity of the pooled buffers
+pub const POOLED_BUFFER_CAPACITY: usize = 16384;
+/// The maximum number of buffers kept in the buffer pool of a single thread
+const MAX_POOLED_BUFFERS: usize = 128;
+
+thread_local! {
+  /// The buffer pool of the current thread. The request handlers run on single-threaded async runtimes,
+  /// so the buffers are pooled per thread without locking.
+  static BUFFER_POOL: RefCell<Vec<BytesMut>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The number of buffer acquisitions, for which a pooled buffer was reused
+static BUFFER_POOL_HITS: AtomicU64 = AtomicU64::new(0);
+/// The number of buffer acquisitions, for which a new buffer was allocated
+static BUFFER_POOL_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Buffer pool statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferPoolStatistics {
+  /// The number of buffer acquisitions, for which a pooled buffer was reused
+  pub hits: u64,
+  /// The number of buffer acquisitions, for which a new buffer was allocated
+  pub misses: u64,
+}
+
+/// Acquires an empty buffer with the capacity of `POOLED_BUFFER_CAPACITY` bytes from the buffer pool of the current thread.
+/// A pooled buffer is reused only if all the chunks split off it (for example, frozen into `Bytes` and sent to a client)
+/// were dropped, otherwise a new buffer is allocated.
+pub fn acquire_buffer() -> BytesMut {
+  let pooled_buffer = BUFFER_POOL.with_borrow_mut(|pool| {
+    let index = pool.iter_mut().position(|buffer| {
+      buffer.clear();
+      buffer.try_reclaim(POOLED_BUFFER_CAPACITY)
+    })?;
+    Some(pool.swap_remove(index))
+  });
+  match pooled_buffer {
+    Some(buffer) => {
+      BUFFER_POOL_HITS.fetch_add(1, Ordering::Relaxed);
+      buffer
+    }
+    None => {
+      BUFFER_POOL_MISSES.fetch_add(1, Ordering::Relaxed);
+      BytesMut::with_capacity(POOLED_BUFFER_CAPACITY)
+    }
+  }
+}
+
+/// Puts a buffer obtained with `acquire_buffer` back into the buffer pool of the current thread.
+/// The chunks split off the buffer can still be in use; the buffer is reused after all of them are dropped.
+pub fn release_buffer(buffer: BytesMut) {
+  BUFFER_POOL.with_borrow_mut(|pool| {
+    if pool.len() < MAX_POOLED_BUFFERS {
+      pool.push(buffer);
+    }
+  });
+}
+
+/// Takes the buffer pool statistics collected since the previous call
+pub fn take_buffer_pool_statistics() -> BufferPoolStatistics {
+  BufferPoolStatistics {
+    hits: BUFFER_POOL_HITS.swap(0, Ordering::Relaxed),
+    misses: BUFFER_POOL_MISSES.swap(0, Ordering::Relaxed),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_buffer_pool_reuse() {
+    let mut buffer = acquire_buffer();
+    let buffer_ptr = buffer.as_ptr();
+    buffer.extend_from_slice(b"Hello, world!");
+    let chunk = buffer.split().freeze();
+    release_buffer(buffer);
+
+    // The chunk split off the pooled buffer is still in use, so a new buffer is allocated
+    let other_buffer = acquire_buffer();
+    assert_ne!(other_buffer.as_ptr(), buffer_ptr);
+    release_buffer(other_buffer);
+
+    drop(chunk);
+    let reused_buffer = acquire_buffer();
+    assert_eq!(reused_buffer.as_ptr(), buffer_ptr);
+    assert!(reused_buffer.is_empty());
+    assert!(reused_buffer.capacity() >= POOLED_BUFFER_CAPACITY);
+  }
+}
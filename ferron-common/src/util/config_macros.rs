@@ -1,25 +1,25 @@
-/// Gets configuration entries for validation
+/// Gets configuration entries for validation. The property name is marked as used even if the property isn't specified,
+/// so that validating an empty configuration reveals all the properties validated by a module.
 #[macro_export]
 macro_rules! get_entries_for_validation {
   ($name:literal, $config:expr, $used:expr) => {{
-    $config.entries.get($name).and_then(|value| {
-      $used.insert($name.to_string());
-      value.get_value().map(|_| value)
-    })
+    $used.insert($name.to_string());
+    $config
+      .entries
+      .get($name)
+      .and_then(|value| value.get_value().map(|_| value))
   }};
 }
 
-/// Gets configuration values for validation
+/// Gets configuration values for validation. The property name is marked as used even if the property isn't specified.
 #[macro_export]
 macro_rules! get_values_for_validation {
   ($name:literal, $config:expr, $used:expr) => {{
+    $used.insert($name.to_string());
     $config
       .entries
       .get($name)
-      .and_then(|value| {
-        $used.insert($name.to_string());
-        value.get_value().map(|_| value)
-      })
+      .and_then(|value| value.get_value().map(|_| value))
       .map_or(Vec::new(), |value| value.get_values())
   }};
 }
@@ -0,0 +1,152 @@
+/// A cron-like schedule with five fields (minute, hour, day of month, month and day of week).
+/// Each field is stored as a bit set of matching values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+  minutes: u64,
+  hours: u64,
+  days_of_month: u64,
+  months: u64,
+  days_of_week: u64,
+  day_of_month_restricted: bool,
+  day_of_week_restricted: bool,
+}
+
+/// Month names accepted in the month field
+const MONTH_NAMES: [&str; 12] = [
+  "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Day names accepted in the day of week field
+const DAY_OF_WEEK_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Parses a single value of a cron field, either a number or a name (the names are mapped to values from `names_start`)
+fn parse_cron_value(value: &str, names: &[&str], names_start: u32) -> Option<u32> {
+  value.parse::<u32>().ok().or_else(|| {
+    names
+      .iter()
+      .position(|name| name.eq_ignore_ascii_case(value))
+      .map(|index| index as u32 + names_start)
+  })
+}
+
+/// Parses a cron field (like `*`, `*/15`, `1-5`, `0,30` or `mon-fri`) into a bit set of matching values
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str], names_start: u32) -> Option<u64> {
+  let mut bits = 0u64;
+  for part in field.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+      None => (part, 1),
+    };
+    let (start, end) = if range == "*" {
+      (min, max)
+    } else if let Some((start, end)) = range.split_once('-') {
+      (
+        parse_cron_value(start, names, names_start)?,
+        parse_cron_value(end, names, names_start)?,
+      )
+    } else {
+      let start = parse_cron_value(range, names, names_start)?;
+      // A single value with a step (like `5/10`) matches from the value to the end of the field range
+      (start, if part.contains('/') { max } else { start })
+    };
+    if start < min || end > max || start > end {
+      return None;
+    }
+    for value in (start..=end).step_by(step as usize) {
+      bits |= 1 << value;
+    }
+  }
+  Some(bits)
+}
+
+impl CronSchedule {
+  /// Parses a cron expression (like `0 3 * * *`), or one of the `@yearly`, `@annually`, `@monthly`, `@weekly`,
+  /// `@daily`, `@midnight` and `@hourly` shorthands
+  pub fn parse(expression: &str) -> Option<Self> {
+    let expression = match expression.trim() {
+      "@yearly" | "@annually" => "0 0 1 1 *",
+      "@monthly" => "0 0 1 * *",
+      "@weekly" => "0 0 * * 0",
+      "@daily" | "@midnight" => "0 0 * * *",
+      "@hourly" => "0 * * * *",
+      expression => expression,
+    };
+    let fields = expression.split_whitespace().collect::<Vec<_>>();
+    if fields.len() != 5 {
+      return None;
+    }
+    let mut days_of_week = parse_cron_field(fields[4], 0, 7, &DAY_OF_WEEK_NAMES, 0)?;
+    // Both 0 and 7 represent Sunday
+    if days_of_week & (1 << 7) != 0 {
+      days_of_week = (days_of_week & !(1 << 7)) | 1;
+    }
+    Some(Self {
+      minutes: parse_cron_field(fields[0], 0, 59, &[], 0)?,
+      hours: parse_cron_field(fields[1], 0, 23, &[], 0)?,
+      days_of_month: parse_cron_field(fields[2], 1, 31, &[], 0)?,
+      months: parse_cron_field(fields[3], 1, 12, &MONTH_NAMES, 1)?,
+      days_of_week,
+      day_of_month_restricted: !fields[2].starts_with('*'),
+      day_of_week_restricted: !fields[4].starts_with('*'),
+    })
+  }
+
+  /// Checks if the schedule matches the specified time. The day of week is counted from Sunday (0).
+  /// Like in cron, if both the day of month and the day of week are restricted, matching either of them is enough.
+  pub fn matches(&self, minute: u32, hour: u32, day_of_month: u32, month: u32, day_of_week: u32) -> bool {
+    let is_set = |bits: u64, value: u32| value < 64 && bits & (1 << value) != 0;
+    let day_of_month_matches = is_set(self.days_of_month, day_of_month);
+    let day_of_week_matches = is_set(self.days_of_week, day_of_week);
+    let day_matches = if self.day_of_month_restricted && self.day_of_week_restricted {
+      day_of_month_matches || day_of_week_matches
+    } else {
+      day_of_month_matches && day_of_week_matches
+    };
+    is_set(self.minutes, minute) && is_set(self.hours, hour) && is_set(self.months, month) && day_matches
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_and_match_cron_schedule() {
+    let schedule = CronSchedule::parse("0 3 * * *").unwrap();
+    assert!(schedule.matches(0, 3, 18, 10, 0));
+    assert!(!schedule.matches(1, 3, 18, 10, 0));
+    assert!(!schedule.matches(0, 4, 18, 10, 0));
+
+    let schedule = CronSchedule::parse("*/15 9-17 * * mon-fri").unwrap();
+    assert!(schedule.matches(45, 9, 19, 10, 1));
+    assert!(!schedule.matches(50, 9, 19, 10, 1));
+    assert!(!schedule.matches(45, 9, 18, 10, 6));
+
+    let schedule = CronSchedule::parse("30 2 1,15 jan,jul *").unwrap();
+    assert!(schedule.matches(30, 2, 15, 7, 3));
+    assert!(!schedule.matches(30, 2, 15, 8, 3));
+  }
+
+  #[test]
+  fn test_match_cron_schedule_days() {
+    // Either the day of month or the day of week has to match
+    let schedule = CronSchedule::parse("0 0 1 * 7").unwrap();
+    assert!(schedule.matches(0, 0, 1, 10, 4));
+    assert!(schedule.matches(0, 0, 18, 10, 0));
+    assert!(!schedule.matches(0, 0, 19, 10, 1));
+
+    let schedule = CronSchedule::parse("@weekly").unwrap();
+    assert!(schedule.matches(0, 0, 18, 10, 0));
+    assert!(!schedule.matches(0, 0, 19, 10, 1));
+  }
+
+  #[test]
+  fn test_parse_invalid_cron_schedule() {
+    assert_eq!(CronSchedule::parse("0 3 * *"), None);
+    assert_eq!(CronSchedule::parse("60 3 * * *"), None);
+    assert_eq!(CronSchedule::parse("0 3 0 * *"), None);
+    assert_eq!(CronSchedule::parse("*/0 * * * *"), None);
+    assert_eq!(CronSchedule::parse("5-1 * * * *"), None);
+    assert_eq!(CronSchedule::parse("@sometimes"), None);
+  }
+}
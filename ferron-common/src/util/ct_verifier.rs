@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::DigitallySignedStruct;
+use rustls::SignatureScheme;
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+
+use crate::util::parse_embedded_scts;
+
+/// A server certificate verifier that requires signed certificate timestamps (SCTs) from
+/// certificate transparency (CT) logs to be embedded in the server certificate,
+/// in addition to the verification performed by the inner verifier.
+#[derive(Debug)]
+pub struct CtEnforcingVerifier {
+  inner: Arc<dyn ServerCertVerifier>,
+  required_scts: usize,
+}
+
+impl CtEnforcingVerifier {
+  /// Creates a verifier requiring SCTs from at least `required_scts` distinct CT logs
+  pub fn new(inner: Arc<dyn ServerCertVerifier>, required_scts: usize) -> Self {
+    Self { inner, required_scts }
+  }
+}
+
+impl ServerCertVerifier for CtEnforcingVerifier {
+  fn verify_server_cert(
+    &self,
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    server_name: &ServerName<'_>,
+    ocsp_response: &[u8],
+    now: UnixTime,
+  ) -> Result<ServerCertVerified, rustls::Error> {
+    let verified = self
+      .inner
+      .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+
+    let scts = parse_embedded_scts(end_entity)
+      .ok_or_else(|| rustls::Error::General("Malformed certificate transparency SCT list".to_string()))?;
+    let now_millis = now.as_secs().saturating_mul(1000);
+    let sct_logs = scts
+      .iter()
+      .filter(|sct| sct.timestamp <= now_millis)
+      .map(|sct| sct.log_id)
+      .collect::<HashSet<_>>();
+    if sct_logs.len() < self.required_scts {
+      return Err(rustls::Error::General(format!(
+        "The certificate has SCTs from {} certificate transparency logs, while {} are required",
+        sct_logs.len(),
+        self.required_scts
+      )));
+    }
+
+    Ok(verified)
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.inner.verify_tls12_signature(message, cert, dss)
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.inner.verify_tls13_signature(message, cert, dss)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    self.inner.supported_verify_schemes()
+  }
+}
@@ -0,0 +1,49 @@
+use std::sync::OnceLock;
+
+/// The URL scheme of webroots with static assets embedded into the binary (like `embedded://site`)
+pub const EMBEDDED_ROOT_SCHEME: &str = "embedded://";
+
+/// Static assets of an embedded directory, sorted by URL paths (like `/index.html`)
+pub type EmbeddedAssetDirectory = &'static [(&'static str, &'static [u8])];
+
+/// Embedded directories, keyed by their names
+static EMBEDDED_ASSETS: OnceLock<&'static [(&'static str, EmbeddedAssetDirectory)]> = OnceLock::new();
+
+/// Registers the static assets embedded into the binary at compile time
+pub fn register_embedded_assets(assets: &'static [(&'static str, EmbeddedAssetDirectory)]) {
+  let _ = EMBEDDED_ASSETS.set(assets);
+}
+
+/// Obtains an embedded directory by its name
+pub fn get_embedded_directory(name: &str) -> Option<EmbeddedAssetDirectory> {
+  EMBEDDED_ASSETS
+    .get()?
+    .iter()
+    .find(|(directory_name, _)| *directory_name == name)
+    .map(|(_, directory)| *directory)
+}
+
+/// Obtains an embedded static asset by the embedded directory and the URL path
+pub fn get_embedded_asset(directory: EmbeddedAssetDirectory, path: &str) -> Option<&'static [u8]> {
+  directory
+    .binary_search_by(|(asset_path, _)| (*asset_path).cmp(path))
+    .ok()
+    .map(|index| directory[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_embedded_assets() {
+    const SITE: EmbeddedAssetDirectory = &[("/about/index.html", b"About"), ("/index.html", b"Home")];
+    register_embedded_assets(&[("site", SITE)]);
+
+    let directory = get_embedded_directory("site").unwrap();
+    assert_eq!(get_embedded_asset(directory, "/index.html"), Some(&b"Home"[..]));
+    assert_eq!(get_embedded_asset(directory, "/about/index.html"), Some(&b"About"[..]));
+    assert_eq!(get_embedded_asset(directory, "/missing.html"), None);
+    assert!(get_embedded_directory("other").is_none());
+  }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use hyper::header::{HeaderMap, HeaderValue};
+use hyper::StatusCode;
+
+/// The response header with the internal error code, attached to error responses generated by the server
+pub const ERROR_CODE_HEADER_NAME: &str = "x-ferron-error-code";
+
+/// An internal error code (like `FERRON-502-001`), identifying the cause of an error response generated by the server.
+/// The error code consists of the HTTP status code of the response and a number unique for the status code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorCode {
+  status_code: u16,
+  number: u16,
+}
+
+impl ErrorCode {
+  /// An unexpected error while executing the request handlers of the modules
+  pub const REQUEST_HANDLER_FAILED: Self = Self::new(500, 1);
+  /// An unexpected error while executing the response modifying handlers of the modules
+  pub const RESPONSE_HANDLER_FAILED: Self = Self::new(500, 2);
+  /// No server configuration matches the request
+  pub const CONFIGURATION_NOT_FOUND: Self = Self::new(500, 3);
+  /// The connection to the backend server failed
+  pub const BACKEND_CONNECTION_FAILED: Self = Self::new(502, 1);
+  /// The TLS handshake with the backend server failed
+  pub const BACKEND_TLS_HANDSHAKE_FAILED: Self = Self::new(502, 2);
+  /// The HTTP handshake with the backend server failed
+  pub const BACKEND_HTTP_HANDSHAKE_FAILED: Self = Self::new(502, 3);
+  /// The request to the backend server failed, or the backend server sent an invalid response
+  pub const BACKEND_REQUEST_FAILED: Self = Self::new(502, 4);
+  /// The integrity verification of the backend server response (the `Content-Digest` header) failed
+  pub const BACKEND_RESPONSE_INTEGRITY_FAILED: Self = Self::new(502, 5);
+  /// The backend server refused the connection, or is unreachable
+  pub const BACKEND_UNAVAILABLE: Self = Self::new(503, 1);
+  /// No backend servers are available (for example, all of them are marked as unhealthy)
+  pub const NO_BACKENDS_AVAILABLE: Self = Self::new(503, 2);
+  /// The dynamic host provider failed to resolve the host
+  pub const DYNAMIC_HOST_RESOLUTION_FAILED: Self = Self::new(503, 3);
+  /// The health check failed, because the server is draining before shutting down
+  pub const SHUTDOWN_DRAINING: Self = Self::new(503, 4);
+  /// The global memory budget for buffered bodies is exceeded, so the request isn't buffered
+  pub const MEMORY_BUDGET_EXCEEDED: Self = Self::new(503, 5);
+  /// The connection to the backend server timed out
+  pub const BACKEND_CONNECTION_TIMEOUT: Self = Self::new(504, 1);
+
+  /// Creates an error code from the HTTP status code and the number unique for the status code
+  pub const fn new(status_code: u16, number: u16) -> Self {
+    Self { status_code, number }
+  }
+
+  /// Parses the error code (like `FERRON-502-001`)
+  pub fn parse(error_code: &str) -> Option<Self> {
+    let (status_code, number) = error_code.strip_prefix("FERRON-")?.split_once('-')?;
+    if status_code.len() != 3 || number.len() != 3 {
+      return None;
+    }
+    Some(Self::new(status_code.parse().ok()?, number.parse().ok()?))
+  }
+
+  /// Obtains the HTTP status code of the error response
+  pub fn status_code(&self) -> StatusCode {
+    StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+  }
+
+  /// Obtains the error code from the response headers
+  pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+    headers
+      .get(ERROR_CODE_HEADER_NAME)
+      .and_then(|value| value.to_str().ok())
+      .and_then(Self::parse)
+  }
+
+  /// Creates response headers with the error code, used to pass the error code from a module to the request handler
+  pub fn to_headers(self) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&self.to_string()) {
+      headers.insert(ERROR_CODE_HEADER_NAME, value);
+    }
+    headers
+  }
+}
+
+impl fmt::Display for ErrorCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "FERRON-{:03}-{:03}", self.status_code, self.number)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_error_code_display() {
+    assert_eq!(ErrorCode::BACKEND_CONNECTION_FAILED.to_string(), "FERRON-502-001");
+    assert_eq!(ErrorCode::new(503, 12).to_string(), "FERRON-503-012");
+  }
+
+  #[test]
+  fn test_error_code_parse() {
+    assert_eq!(
+      ErrorCode::parse("FERRON-504-001"),
+      Some(ErrorCode::BACKEND_CONNECTION_TIMEOUT)
+    );
+    assert_eq!(ErrorCode::parse("FERRON-504-1"), None);
+    assert_eq!(ErrorCode::parse("APACHE-504-001"), None);
+    assert_eq!(ErrorCode::parse("FERRON-5x4-001"), None);
+  }
+
+  #[test]
+  fn test_error_code_headers() {
+    let headers = ErrorCode::NO_BACKENDS_AVAILABLE.to_headers();
+    assert_eq!(
+      headers.get(ERROR_CODE_HEADER_NAME).and_then(|v| v.to_str().ok()),
+      Some("FERRON-503-002")
+    );
+    assert_eq!(
+      ErrorCode::from_headers(&headers),
+      Some(ErrorCode::NO_BACKENDS_AVAILABLE)
+    );
+    assert_eq!(
+      ErrorCode::NO_BACKENDS_AVAILABLE.status_code(),
+      StatusCode::SERVICE_UNAVAILABLE
+    );
+  }
+}
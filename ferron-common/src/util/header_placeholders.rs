@@ -1,4 +1,4 @@
-use crate::modules::SocketData;
+use crate::modules::{RequestData, RequestTags, SocketData};
 
 pub fn replace_header_placeholders(
   input: &str,
@@ -40,12 +40,14 @@ pub fn replace_header_placeholders(
               output.push_str("{scheme}");
             }
           }
-          "client_ip" => {
+          "client_ip" | "remote_ip" => {
             if let Some(socket_data) = socket_data {
               output.push_str(&socket_data.remote_addr.ip().to_string());
             } else {
               // No socket data, leave it as is
-              output.push_str("{client_ip}");
+              output.push('{');
+              output.push_str(placeholder_value);
+              output.push('}');
             }
           }
           "client_port" => {
@@ -88,11 +90,49 @@ pub fn replace_header_placeholders(
               output.push_str("{server_ip_canonical}");
             }
           }
+          "tls.sni" => {
+            if let Some(socket_data) = socket_data {
+              output.push_str(socket_data.tls_sni.as_deref().unwrap_or(""));
+            } else {
+              // No socket data, leave it as is
+              output.push_str("{tls.sni}");
+            }
+          }
+          "tls.alpn" => {
+            if let Some(socket_data) = socket_data {
+              output.push_str(socket_data.tls_alpn.as_deref().unwrap_or(""));
+            } else {
+              // No socket data, leave it as is
+              output.push_str("{tls.alpn}");
+            }
+          }
+          "auth.user" => {
+            if let Some(auth_user) = request_parts
+              .extensions
+              .get::<RequestData>()
+              .and_then(|request_data| request_data.auth_user.as_deref())
+            {
+              output.push_str(auth_user);
+            }
+          }
+          "tags" => {
+            if let Some(request_tags) = request_parts.extensions.get::<RequestTags>() {
+              output.push_str(&request_tags.0.join(","));
+            }
+          }
           _ => {
             if let Some(header_name) = placeholder_value.strip_prefix("header:") {
               if let Some(header_value) = request_parts.headers.get(header_name) {
                 output.push_str(header_value.to_str().unwrap_or(""));
               }
+            } else if let Some(variable_name) = placeholder_value.strip_prefix("var:") {
+              if let Some(variable_value) = request_parts
+                .extensions
+                .get::<RequestData>()
+                .and_then(|request_data| request_data.variables.get(variable_name))
+              {
+                output.push_str(variable_value);
+              }
             } else {
               // Unknown placeholder, leave it as is
               output.push('{');
@@ -176,6 +216,34 @@ mod tests {
     assert_eq!(output, expected);
   }
 
+  #[test]
+  fn test_connection_placeholders() {
+    let mut parts = make_parts("/", Method::GET, Version::HTTP_2, None);
+    parts.extensions.insert(RequestData {
+      auth_user: Some("alice".to_string()),
+      original_url: None,
+      error_status_code: None,
+      peer_address: None,
+      proxy_protocol_address: None,
+      variables: Default::default(),
+    });
+    let socket_data = SocketData {
+      remote_addr: "192.0.2.1:12345".parse().unwrap(),
+      local_addr: "127.0.0.1:443".parse().unwrap(),
+      encrypted: true,
+      tls_sni: Some("example.com".to_string()),
+      tls_alpn: Some("h2".to_string()),
+    };
+    let input = "IP: {remote_ip}, SNI: {tls.sni}, ALPN: {tls.alpn}, User: {auth.user}";
+    let expected = "IP: 192.0.2.1, SNI: example.com, ALPN: h2, User: alice";
+    let output = replace_header_placeholders(input, &parts, Some(&socket_data));
+    assert_eq!(output, expected);
+
+    let parts = make_parts("/", Method::GET, Version::HTTP_2, None);
+    let output = replace_header_placeholders("User: {auth.user}", &parts, Some(&socket_data));
+    assert_eq!(output, "User: ");
+  }
+
   #[test]
   fn test_unknown_placeholder() {
     let parts = make_parts("/", Method::GET, Version::HTTP_11, None);
@@ -206,4 +274,34 @@ mod tests {
     let output = replace_header_placeholders(input, &parts, None);
     assert_eq!(output, expected);
   }
+
+  #[test]
+  fn test_variable_placeholder() {
+    let mut parts = make_parts("/", Method::GET, Version::HTTP_11, None);
+    parts.extensions.insert(RequestData {
+      auth_user: None,
+      original_url: None,
+      error_status_code: None,
+      peer_address: None,
+      proxy_protocol_address: None,
+      variables: std::collections::HashMap::from([("tenant".to_string(), "backend1".to_string())]),
+    });
+    let input = "Tenant: {var:tenant}, Missing: {var:missing}";
+    let expected = "Tenant: backend1, Missing: ";
+    let output = replace_header_placeholders(input, &parts, None);
+    assert_eq!(output, expected);
+  }
+
+  #[test]
+  fn test_tags_placeholder() {
+    let mut parts = make_parts("/", Method::GET, Version::HTTP_11, None);
+    let output = replace_header_placeholders("Tags: {tags}", &parts, None);
+    assert_eq!(output, "Tags: ");
+
+    parts
+      .extensions
+      .insert(RequestTags(vec!["api".to_string(), "bot".to_string()]));
+    let output = replace_header_placeholders("Tags: {tags}", &parts, None);
+    assert_eq!(output, "Tags: api,bot");
+  }
 }
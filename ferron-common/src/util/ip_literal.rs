@@ -0,0 +1,121 @@
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
+
+/// Parses an IP address literal, optionally enclosed in square brackets (like `[::1]`),
+/// with an optional IPv6 zone (like `fe80::1%eth0`, or `fe80::1%25eth0` as in URLs, RFC 6874).
+/// Returns the IP address and the zone, if it's specified.
+pub fn parse_ip_literal(literal: &str) -> Option<(IpAddr, Option<&str>)> {
+  let (literal, is_bracketed) = match literal.strip_prefix('[') {
+    Some(literal) => (literal.strip_suffix(']')?, true),
+    None => (literal, false),
+  };
+  match literal.split_once('%') {
+    Some((address, zone)) => {
+      // The "%" character in URLs is percent-encoded as "%25"
+      let zone = zone.strip_prefix("25").filter(|z| !z.is_empty()).unwrap_or(zone);
+      if zone.is_empty() {
+        return None;
+      }
+      Some((IpAddr::V6(address.parse::<Ipv6Addr>().ok()?), Some(zone)))
+    }
+    None => {
+      let ip = literal.parse::<IpAddr>().ok()?;
+      if is_bracketed && ip.is_ipv4() {
+        // IPv4 addresses aren't enclosed in square brackets
+        return None;
+      }
+      Some((ip, None))
+    }
+  }
+}
+
+/// Obtains the interface index (the scope ID) for an IPv6 zone, which is either a numeric index or an interface name.
+/// Interface names are supported only on Linux.
+pub fn ipv6_zone_index(zone: &str) -> Option<u32> {
+  if let Ok(index) = zone.parse::<u32>() {
+    return Some(index);
+  }
+  #[cfg(any(target_os = "linux", target_os = "android"))]
+  if !zone.contains(['/', '\0']) && zone != "." && zone != ".." {
+    return std::fs::read_to_string(format!("/sys/class/net/{zone}/ifindex"))
+      .ok()
+      .and_then(|index| index.trim().parse::<u32>().ok());
+  }
+  None
+}
+
+/// Creates a socket address from an IP address and a port, setting the scope ID for IPv6 addresses with a zone.
+pub fn socket_addr_with_zone(ip: IpAddr, zone: Option<&str>, port: u16) -> Option<SocketAddr> {
+  match (ip, zone) {
+    (IpAddr::V6(ip), Some(zone)) => Some(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, ipv6_zone_index(zone)?))),
+    (ip, None) => Some(SocketAddr::new(ip, port)),
+    _ => None,
+  }
+}
+
+/// Formats the address to connect to from the URL host (like `example.com`, `[::1]` or `[fe80::1%25eth0]`)
+/// and the port. IPv6 zones are replaced with the interface indexes, since the socket address parser accepts only them.
+pub fn format_connect_address(host: &str, port: u16) -> String {
+  if let Some((ip, Some(zone))) = parse_ip_literal(host) {
+    if let Some(socket_addr) = socket_addr_with_zone(ip, Some(zone), port) {
+      return socket_addr.to_string();
+    }
+  }
+  format!("{host}:{port}")
+}
+
+/// Removes the square brackets and the IPv6 zone from the URL host, for example for TLS server names.
+pub fn strip_ip_literal_host(host: &str) -> String {
+  match parse_ip_literal(host) {
+    Some((ip, _)) => ip.to_string(),
+    None => host.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_ip_literal() {
+    assert_eq!(
+      parse_ip_literal("192.0.2.1"),
+      Some(("192.0.2.1".parse().unwrap(), None))
+    );
+    assert_eq!(parse_ip_literal("[::1]"), Some(("::1".parse().unwrap(), None)));
+    assert_eq!(
+      parse_ip_literal("fe80::1%eth0"),
+      Some(("fe80::1".parse().unwrap(), Some("eth0")))
+    );
+    assert_eq!(
+      parse_ip_literal("[fe80::1%25eth0]"),
+      Some(("fe80::1".parse().unwrap(), Some("eth0")))
+    );
+    assert_eq!(
+      parse_ip_literal("[fe80::1%25]"),
+      Some(("fe80::1".parse().unwrap(), Some("25")))
+    );
+    assert_eq!(parse_ip_literal("[::1"), None);
+    assert_eq!(parse_ip_literal("[192.0.2.1]"), None);
+    assert_eq!(parse_ip_literal("192.0.2.1%eth0"), None);
+    assert_eq!(parse_ip_literal("fe80::1%"), None);
+    assert_eq!(parse_ip_literal("example.com"), None);
+  }
+
+  #[test]
+  fn test_format_connect_address() {
+    assert_eq!(format_connect_address("example.com", 80), "example.com:80");
+    assert_eq!(format_connect_address("[::1]", 8080), "[::1]:8080");
+    assert_eq!(format_connect_address("[fe80::1%253]", 8080), "[fe80::1%3]:8080");
+    assert_eq!(
+      format_connect_address("[fe80::1%25nonexistent-if]", 8080),
+      "[fe80::1%25nonexistent-if]:8080"
+    );
+  }
+
+  #[test]
+  fn test_strip_ip_literal_host() {
+    assert_eq!(strip_ip_literal_host("[fe80::1%25eth0]"), "fe80::1");
+    assert_eq!(strip_ip_literal_host("[::1]"), "::1");
+    assert_eq!(strip_ip_literal_host("example.com"), "example.com");
+  }
+}
@@ -0,0 +1,319 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// The global memory budget for buffered request bodies, buffered response bodies, and cached responses
+pub static MEMORY_BUDGET: MemoryBudget = MemoryBudget::new();
+
+/// The percentage of the memory budget, below which the shed mode is left
+const SHED_MODE_EXIT_PERCENTAGE: usize = 90;
+
+/// The percentage of the memory budget, which can be reserved by cached responses. Cached responses are kept
+/// until they're evicted, so they're capped below the low watermark to let the shed mode be left.
+const CACHE_SHARE_PERCENTAGE: usize = 50;
+
+/// Memory budget statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryBudgetStatistics {
+  /// The number of bytes currently reserved
+  pub used: usize,
+  /// Whether the memory budget is in the shed mode
+  pub shedding: bool,
+  /// The number of times the shed mode was entered since the previous call
+  pub shed_episodes: u64,
+  /// The number of reservations rejected since the previous call
+  pub rejections: u64,
+}
+
+/// A memory budget shared by all the request handler threads. When a reservation would exceed the budget,
+/// the budget switches into the shed mode, in which all further reservations are rejected,
+/// until the reserved memory falls below 90% of the budget. Cached responses can reserve at most half of the budget.
+pub struct MemoryBudget {
+  limit: AtomicUsize,
+  used: AtomicUsize,
+  cache_used: AtomicUsize,
+  shedding: AtomicBool,
+  shed_episodes: AtomicU64,
+  rejections: AtomicU64,
+}
+
+impl MemoryBudget {
+  /// Creates an unlimited memory budget
+  pub const fn new() -> Self {
+    Self {
+      limit: AtomicUsize::new(0),
+      used: AtomicUsize::new(0),
+      cache_used: AtomicUsize::new(0),
+      shedding: AtomicBool::new(false),
+      shed_episodes: AtomicU64::new(0),
+      rejections: AtomicU64::new(0),
+    }
+  }
+
+  /// Sets the memory budget in bytes. `None` means no memory budget.
+  pub fn set_limit(&self, limit: Option<usize>) {
+    self.limit.store(limit.unwrap_or(0), Ordering::Relaxed);
+    self.leave_shed_mode_if_below_watermark();
+  }
+
+  /// Obtains the memory budget in bytes, or `None` if there is no memory budget
+  pub fn limit(&self) -> Option<usize> {
+    match self.limit.load(Ordering::Relaxed) {
+      0 => None,
+      limit => Some(limit),
+    }
+  }
+
+  /// Obtains the number of bytes currently reserved
+  pub fn used(&self) -> usize {
+    self.used.load(Ordering::Relaxed)
+  }
+
+  /// Checks if the memory budget is in the shed mode
+  pub fn is_shedding(&self) -> bool {
+    self.shedding.load(Ordering::Relaxed)
+  }
+
+  /// Creates an empty memory reservation, which can be grown later
+  pub fn reserve(&'static self) -> MemoryReservation {
+    MemoryReservation {
+      budget: self,
+      size: 0,
+      cache: false,
+    }
+  }
+
+  /// Creates an empty memory reservation for a cached response, which can be grown later
+  /// only within the share of the memory budget for cached responses
+  pub fn reserve_for_cache(&'static self) -> MemoryReservation {
+    MemoryReservation {
+      budget: self,
+      size: 0,
+      cache: true,
+    }
+  }
+
+  /// Takes the memory budget statistics. The counters are collected since the previous call.
+  pub fn take_statistics(&self) -> MemoryBudgetStatistics {
+    MemoryBudgetStatistics {
+      used: self.used(),
+      shedding: self.is_shedding(),
+      shed_episodes: self.shed_episodes.swap(0, Ordering::Relaxed),
+      rejections: self.rejections.swap(0, Ordering::Relaxed),
+    }
+  }
+
+  /// Tries to reserve additional bytes
+  fn try_reserve(&self, size: usize, cache: bool) -> bool {
+    if self.is_shedding() {
+      self.rejections.fetch_add(1, Ordering::Relaxed);
+      return false;
+    }
+    if cache {
+      // Exceeding the share for cached responses doesn't switch into the shed mode,
+      // the response just isn't stored in the cache
+      let cache_used = self.cache_used.fetch_add(size, Ordering::Relaxed).saturating_add(size);
+      if self
+        .limit()
+        .is_some_and(|limit| cache_used > limit / 100 * CACHE_SHARE_PERCENTAGE)
+      {
+        self.cache_used.fetch_sub(size, Ordering::Relaxed);
+        return false;
+      }
+    }
+    let used = self.used.fetch_add(size, Ordering::Relaxed).saturating_add(size);
+    if let Some(limit) = self.limit() {
+      if used > limit {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+        if cache {
+          self.cache_used.fetch_sub(size, Ordering::Relaxed);
+        }
+        if !self.shedding.swap(true, Ordering::Relaxed) {
+          self.shed_episodes.fetch_add(1, Ordering::Relaxed);
+        }
+        self.rejections.fetch_add(1, Ordering::Relaxed);
+        return false;
+      }
+    }
+    true
+  }
+
+  /// Releases the reserved bytes
+  fn release(&self, size: usize, cache: bool) {
+    if cache {
+      self.cache_used.fetch_sub(size, Ordering::Relaxed);
+    }
+    self.used.fetch_sub(size, Ordering::Relaxed);
+    self.leave_shed_mode_if_below_watermark();
+  }
+
+  /// Leaves the shed mode, if the reserved memory is below the low watermark
+  fn leave_shed_mode_if_below_watermark(&self) {
+    if !self.is_shedding() {
+      return;
+    }
+    let below_watermark = match self.limit() {
+      Some(limit) => self.used() <= limit / 100 * SHED_MODE_EXIT_PERCENTAGE,
+      None => true,
+    };
+    if below_watermark {
+      self.shedding.store(false, Ordering::Relaxed);
+    }
+  }
+}
+
+impl Default for MemoryBudget {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// A memory reservation in the memory budget. The reserved bytes are released, when the reservation is dropped.
+pub struct MemoryReservation {
+  budget: &'static MemoryBudget,
+  size: usize,
+  cache: bool,
+}
+
+impl MemoryReservation {
+  /// Tries to grow the reservation. Returns `false`, if the memory budget is exceeded or in the shed mode.
+  pub fn try_grow(&mut self, additional: usize) -> bool {
+    if additional == 0 {
+      return true;
+    }
+    if !self.budget.try_reserve(additional, self.cache) {
+      return false;
+    }
+    self.size += additional;
+    true
+  }
+
+  /// Obtains the number of reserved bytes
+  pub fn size(&self) -> usize {
+    self.size
+  }
+}
+
+impl Drop for MemoryReservation {
+  fn drop(&mut self) {
+    if self.size > 0 {
+      self.budget.release(self.size, self.cache);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn budget(limit: Option<usize>) -> &'static MemoryBudget {
+    let budget = Box::leak(Box::new(MemoryBudget::new()));
+    budget.set_limit(limit);
+    budget
+  }
+
+  #[test]
+  fn test_memory_budget_unlimited() {
+    let budget = budget(None);
+    let mut reservation = budget.reserve();
+    assert!(reservation.try_grow(usize::MAX / 2));
+    assert!(!budget.is_shedding());
+    drop(reservation);
+    assert_eq!(budget.used(), 0);
+  }
+
+  #[test]
+  fn test_memory_budget_releases_on_drop() {
+    let budget = budget(Some(1000));
+    let mut reservation = budget.reserve();
+    assert!(reservation.try_grow(400));
+    assert!(reservation.try_grow(400));
+    assert_eq!(reservation.size(), 800);
+    assert_eq!(budget.used(), 800);
+    drop(reservation);
+    assert_eq!(budget.used(), 0);
+  }
+
+  #[test]
+  fn test_memory_budget_shed_mode() {
+    let budget = budget(Some(1000));
+    let mut first = budget.reserve();
+    assert!(first.try_grow(600));
+    let mut second = budget.reserve();
+    assert!(second.try_grow(300));
+    assert!(!second.try_grow(200));
+    assert!(budget.is_shedding());
+
+    // Reservations are rejected in the shed mode, even if they would fit into the budget
+    let mut third = budget.reserve();
+    assert!(!third.try_grow(10));
+
+    // The shed mode is left only below the low watermark
+    drop(second);
+    assert!(!budget.is_shedding());
+    assert!(third.try_grow(10));
+
+    let statistics = budget.take_statistics();
+    assert_eq!(statistics.used, 610);
+    assert!(!statistics.shedding);
+    assert_eq!(statistics.shed_episodes, 1);
+    assert_eq!(statistics.rejections, 2);
+    assert_eq!(budget.take_statistics().rejections, 0);
+  }
+
+  #[test]
+  fn test_memory_budget_shed_mode_watermark() {
+    let budget = budget(Some(1000));
+    let mut first = budget.reserve();
+    assert!(first.try_grow(500));
+    let mut second = budget.reserve();
+    assert!(second.try_grow(420));
+    let mut third = budget.reserve();
+    assert!(third.try_grow(50));
+    assert!(!third.try_grow(100));
+    assert!(budget.is_shedding());
+
+    // 920 bytes are still reserved, which is above the low watermark
+    drop(third);
+    assert!(budget.is_shedding());
+    drop(second);
+    assert!(!budget.is_shedding());
+    drop(first);
+    assert_eq!(budget.used(), 0);
+  }
+
+  #[test]
+  fn test_memory_budget_full_cache_leaves_shed_mode() {
+    let budget = budget(Some(1000));
+    let mut cached = budget.reserve_for_cache();
+    assert!(cached.try_grow(500));
+
+    // The cache share is full, which doesn't switch into the shed mode
+    let mut other_cached = budget.reserve_for_cache();
+    assert!(!other_cached.try_grow(10));
+    assert!(!budget.is_shedding());
+
+    let mut request = budget.reserve();
+    assert!(request.try_grow(450));
+    assert!(!request.try_grow(100));
+    assert!(budget.is_shedding());
+
+    // The shed mode is left after the requests are done, even though the cached responses are still kept
+    drop(request);
+    assert!(!budget.is_shedding());
+    assert_eq!(budget.used(), 500);
+    let mut request = budget.reserve();
+    assert!(request.try_grow(100));
+    drop(cached);
+    assert!(other_cached.try_grow(10));
+  }
+
+  #[test]
+  fn test_memory_budget_removed_limit_leaves_shed_mode() {
+    let budget = budget(Some(100));
+    let mut reservation = budget.reserve();
+    assert!(!reservation.try_grow(200));
+    assert!(budget.is_shedding());
+    budget.set_limit(None);
+    assert!(!budget.is_shedding());
+    assert!(reservation.try_grow(200));
+  }
+}
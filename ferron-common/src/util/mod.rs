@@ -1,48 +1,78 @@
 mod anti_xss;
+mod buffer_pool;
 mod config_macros;
+mod cron_schedule;
+mod ct_verifier;
 mod default_html_page;
+mod embedded_assets;
+mod error_codes;
 #[cfg(feature = "runtime-vibeio")]
 mod file_stream;
 mod header_placeholders;
 mod ip_blocklist;
+mod ip_literal;
 mod is_localhost;
 mod match_hostname;
 mod match_location;
+mod memory_budget;
 mod module_cache;
 #[cfg(feature = "runtime-monoio")]
 mod monoio_file_stream;
 #[cfg(feature = "runtime-monoio")]
 mod monoio_file_stream_no_spawn;
 mod no_server_verifier;
+mod parse_duration;
 mod parse_q_value_header;
+mod parse_size;
+mod real_ip;
+mod request_classifier;
+mod scheduled_tasks;
+mod sct;
 #[cfg(any(feature = "runtime-monoio", feature = "runtime-vibeio"))]
 mod send_async_io;
 #[cfg(feature = "runtime-monoio")]
 mod send_rw_stream;
 mod sizify;
+mod streaming_response;
 mod ttl_cache;
+mod warmup;
 
 pub use anti_xss::*;
+pub use buffer_pool::*;
+pub use cron_schedule::*;
+pub use ct_verifier::*;
+pub use embedded_assets::*;
+pub use error_codes::*;
 #[cfg(feature = "runtime-vibeio")]
 pub use file_stream::*;
 pub use header_placeholders::*;
 pub use ip_blocklist::*;
+pub use ip_literal::*;
 pub use is_localhost::*;
 pub use match_hostname::*;
 pub use match_location::*;
+pub use memory_budget::*;
 pub use module_cache::*;
 #[cfg(feature = "runtime-monoio")]
 pub use monoio_file_stream::*;
 #[cfg(feature = "runtime-monoio")]
 pub use monoio_file_stream_no_spawn::*;
 pub use no_server_verifier::*;
+pub use parse_duration::*;
 pub use parse_q_value_header::*;
+pub use parse_size::*;
+pub use real_ip::*;
+pub use request_classifier::*;
+pub use scheduled_tasks::*;
+pub use sct::*;
 #[cfg(any(feature = "runtime-monoio", feature = "runtime-vibeio"))]
 pub use send_async_io::*;
 #[cfg(feature = "runtime-monoio")]
 pub use send_rw_stream::*;
 pub use sizify::*;
+pub use streaming_response::*;
 pub use ttl_cache::*;
+pub use warmup::*;
 
 /// The web server software identifier
 pub const SERVER_SOFTWARE: &str = "Ferron";
@@ -7,7 +7,9 @@ use futures_util::Stream;
 use monoio::fs::File;
 use tokio_util::sync::CancellationToken;
 
-const MAX_BUFFER_SIZE: usize = 16384;
+use crate::util::{acquire_buffer, release_buffer, POOLED_BUFFER_CAPACITY};
+
+const MAX_BUFFER_SIZE: usize = POOLED_BUFFER_CAPACITY;
 const MAX_CHANNEL_CAPACITY: usize = 2;
 
 /// A wrapper over Monoio's `File` that implements a `Stream` trait.
@@ -30,7 +32,13 @@ impl MonoioFileStream {
         if buffer_sz == 0 {
           break;
         }
-        let buffer = BytesMut::with_capacity(buffer_sz);
+        // Full-sized chunks are read into pooled buffers, which are reused after the chunks are sent
+        let pooled = buffer_sz == POOLED_BUFFER_CAPACITY;
+        let buffer = if pooled {
+          acquire_buffer()
+        } else {
+          BytesMut::with_capacity(buffer_sz)
+        };
         let (io_result, mut buffer) = monoio::select! {
           biased;
 
@@ -51,7 +59,11 @@ impl MonoioFileStream {
         if tx
           .send(io_result.map(move |n| {
             buffer.truncate(n);
-            buffer.freeze()
+            let chunk = buffer.split().freeze();
+            if pooled {
+              release_buffer(buffer);
+            }
+            chunk
           }))
           .await
           .is_err()
@@ -3,12 +3,14 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_util::Stream;
 use monoio::fs::File;
 use send_wrapper::SendWrapper;
 
-const MAX_BUFFER_SIZE: usize = 16384;
+use crate::util::{acquire_buffer, release_buffer, POOLED_BUFFER_CAPACITY};
+
+const MAX_BUFFER_SIZE: usize = POOLED_BUFFER_CAPACITY;
 
 /// A wrapper over Monoio's `File` that implements a `Stream` trait and doesn't spawn a background task.
 pub struct MonoioFileStreamNoSpawn {
@@ -85,17 +87,24 @@ async fn read_chunk(file: Arc<SendWrapper<File>>, pos: u64, end: Option<u64>) ->
   if buffer_sz == 0 {
     return None;
   }
-  let buffer_uninit = Box::new_uninit_slice(buffer_sz);
-  // Safety: The buffer is a boxed slice of uninitialized `u8` values. `u8` is a primitive type.
-  let buffer: Box<[u8]> = unsafe { buffer_uninit.assume_init() };
+  // Full-sized chunks are read into pooled buffers, which are reused after the chunks are sent
+  let pooled = buffer_sz == POOLED_BUFFER_CAPACITY;
+  let buffer = if pooled {
+    acquire_buffer()
+  } else {
+    BytesMut::with_capacity(buffer_sz)
+  };
   let result = file.read_at(buffer, pos).await;
   match result {
-    (Ok(n), buffer) => {
+    (Ok(n), mut buffer) => {
       if n == 0 {
         None
       } else {
-        let mut bytes = Bytes::from_owner(buffer);
-        bytes.truncate(n);
+        buffer.truncate(n);
+        let bytes = buffer.split().freeze();
+        if pooled {
+          release_buffer(buffer);
+        }
         Some(Ok(bytes))
       }
     }
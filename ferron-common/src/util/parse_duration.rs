@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+/// Parses a human-readable duration (like `500ms`, `30s`, `5m`, `1h` or `2d`).
+/// A number without a unit is interpreted as milliseconds.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+  let duration = duration.trim();
+  let unit_index = duration.find(|c: char| !c.is_ascii_digit()).unwrap_or(duration.len());
+  let (value, unit) = duration.split_at(unit_index);
+  let value = value.parse::<u64>().ok()?;
+  let multiplier_ms: u64 = match unit.trim() {
+    "" | "ms" => 1,
+    "s" => 1000,
+    "m" => 60000,
+    "h" => 3600000,
+    "d" => 86400000,
+    _ => return None,
+  };
+  value.checked_mul(multiplier_ms).map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_duration_with_units() {
+    assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+    assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+    assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+    assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+    assert_eq!(parse_duration("2d"), Some(Duration::from_secs(172800)));
+  }
+
+  #[test]
+  fn test_parse_duration_without_unit() {
+    assert_eq!(parse_duration("1500"), Some(Duration::from_millis(1500)));
+  }
+
+  #[test]
+  fn test_parse_duration_invalid() {
+    assert_eq!(parse_duration(""), None);
+    assert_eq!(parse_duration("h"), None);
+    assert_eq!(parse_duration("1w"), None);
+    assert_eq!(parse_duration("-1s"), None);
+    assert_eq!(parse_duration("1.5h"), None);
+  }
+}
@@ -0,0 +1,52 @@
+/// Parses a human-readable size (like `512B`, `10KB`, `10MB`, `1GiB` or `2TB`) into a number of bytes.
+/// The `KB`, `MB`, `GB` and `TB` units are decimal (powers of 1000), while the `KiB`, `MiB`, `GiB` and `TiB` units
+/// are binary (powers of 1024). The units are case-insensitive. A number without a unit is interpreted as bytes.
+pub fn parse_size(size: &str) -> Option<u64> {
+  let size = size.trim();
+  let unit_index = size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len());
+  let (value, unit) = size.split_at(unit_index);
+  let value = value.parse::<u64>().ok()?;
+  let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+    "" | "b" => 1,
+    "kb" => 1000,
+    "mb" => 1000000,
+    "gb" => 1000000000,
+    "tb" => 1000000000000,
+    "kib" => 1 << 10,
+    "mib" => 1 << 20,
+    "gib" => 1 << 30,
+    "tib" => 1 << 40,
+    _ => return None,
+  };
+  value.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_size_with_units() {
+    assert_eq!(parse_size("512B"), Some(512));
+    assert_eq!(parse_size("10KB"), Some(10000));
+    assert_eq!(parse_size("10MB"), Some(10000000));
+    assert_eq!(parse_size("1GiB"), Some(1073741824));
+    assert_eq!(parse_size("64 KiB"), Some(65536));
+    assert_eq!(parse_size("2tb"), Some(2000000000000));
+  }
+
+  #[test]
+  fn test_parse_size_without_unit() {
+    assert_eq!(parse_size("1024"), Some(1024));
+  }
+
+  #[test]
+  fn test_parse_size_invalid() {
+    assert_eq!(parse_size(""), None);
+    assert_eq!(parse_size("MB"), None);
+    assert_eq!(parse_size("10PB"), None);
+    assert_eq!(parse_size("-1KB"), None);
+    assert_eq!(parse_size("1.5GB"), None);
+    assert_eq!(parse_size("99999999999TiB"), None);
+  }
+}
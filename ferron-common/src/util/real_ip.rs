@@ -0,0 +1,121 @@
+use std::net::{AddrParseError, IpAddr};
+
+use crate::util::IpBlockList;
+
+/// The resolver of the client IP address from the `X-Forwarded-For` header
+#[derive(Clone, Debug, Default)]
+pub struct RealIpResolver {
+  /// The IP addresses and CIDR ranges of trusted proxies. If not specified, all proxies are trusted.
+  pub trusted_proxies: Option<IpBlockList>,
+  /// Whether the trusted proxy addresses are skipped from the right of the `X-Forwarded-For` header
+  pub recursive: bool,
+  /// The number of hops from the right of the `X-Forwarded-For` header, at which the client IP address is selected
+  pub depth: Option<usize>,
+}
+
+impl RealIpResolver {
+  /// Checks if the IP address belongs to a trusted proxy
+  pub fn is_trusted(&self, ip: IpAddr) -> bool {
+    self.trusted_proxies.as_ref().is_none_or(|list| list.is_blocked(ip))
+  }
+
+  /// Resolves the client IP address from the `X-Forwarded-For` header sent by the directly connected client.
+  /// Returns `None`, if the directly connected client isn't a trusted proxy.
+  pub fn resolve(&self, peer_ip: IpAddr, x_forwarded_for: &str) -> Result<Option<IpAddr>, AddrParseError> {
+    if !self.is_trusted(peer_ip) {
+      return Ok(None);
+    }
+    let entries = x_forwarded_for.split(',').map(|entry| entry.trim()).collect::<Vec<_>>();
+
+    if !self.recursive {
+      // Without a depth, the leftmost address (the original client) is selected
+      let index = self.depth.map_or(0, |depth| entries.len().saturating_sub(depth.max(1)));
+      return entries[index].parse().map(Some);
+    }
+
+    let max_hops = self.depth.unwrap_or(usize::MAX).max(1);
+    let mut client_ip = None;
+    for (hop, entry) in entries.iter().rev().enumerate() {
+      let ip = entry.parse::<IpAddr>()?;
+      client_ip = Some(ip);
+      if !self.is_trusted(ip) || hop + 1 >= max_hops {
+        break;
+      }
+    }
+    Ok(client_ip)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn resolver(trusted_proxies: Option<Vec<&str>>, recursive: bool, depth: Option<usize>) -> RealIpResolver {
+    RealIpResolver {
+      trusted_proxies: trusted_proxies.map(|trusted_proxies| {
+        let mut list = IpBlockList::new();
+        list.load_from_vec(trusted_proxies);
+        list
+      }),
+      recursive,
+      depth,
+    }
+  }
+
+  #[test]
+  fn test_resolve_leftmost_and_depth() {
+    let peer_ip = "10.0.0.1".parse().unwrap();
+    let x_forwarded_for = "192.0.2.1, 198.51.100.1, 203.0.113.1";
+
+    assert_eq!(
+      resolver(None, false, None).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("192.0.2.1".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(None, false, Some(1)).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("203.0.113.1".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(None, false, Some(2)).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("198.51.100.1".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(None, false, Some(10)).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("192.0.2.1".parse().unwrap()))
+    );
+    assert!(resolver(None, false, None).resolve(peer_ip, "invalid").is_err());
+  }
+
+  #[test]
+  fn test_resolve_untrusted_peer() {
+    let resolver = resolver(Some(vec!["10.0.0.0/8"]), false, None);
+    assert_eq!(resolver.resolve("192.0.2.10".parse().unwrap(), "192.0.2.1"), Ok(None));
+    assert_eq!(
+      resolver.resolve("10.1.2.3".parse().unwrap(), "192.0.2.1"),
+      Ok(Some("192.0.2.1".parse().unwrap()))
+    );
+  }
+
+  #[test]
+  fn test_resolve_recursive() {
+    let peer_ip = "10.0.0.1".parse().unwrap();
+    let x_forwarded_for = "192.0.2.1, 198.51.100.1, 10.0.0.2, 10.0.0.3";
+
+    assert_eq!(
+      resolver(Some(vec!["10.0.0.0/8"]), true, None).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("198.51.100.1".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(Some(vec!["10.0.0.0/8"]), true, Some(1)).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("10.0.0.3".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(Some(vec!["10.0.0.0/8", "198.51.100.1"]), true, None).resolve(peer_ip, x_forwarded_for),
+      Ok(Some("192.0.2.1".parse().unwrap()))
+    );
+    assert_eq!(
+      resolver(Some(vec!["10.0.0.0/8"]), true, None).resolve(peer_ip, "10.0.0.2, 10.0.0.3"),
+      Ok(Some("10.0.0.2".parse().unwrap()))
+    );
+  }
+}
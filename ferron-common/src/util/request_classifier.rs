@@ -0,0 +1,168 @@
+use fancy_regex::Regex;
+
+use crate::config::{ServerConfiguration, ServerConfigurationEntries};
+use crate::modules::SocketData;
+use crate::util::replace_header_placeholders;
+
+/// The built-in request classification presets, as tags, placeholder values and regular expressions
+const REQUEST_CLASSIFICATION_PRESETS: [(&str, &str, &str); 4] = [
+  (
+    "static",
+    "{path}",
+    r"(?i)\.(css|js|mjs|map|ico|png|jpe?g|gif|webp|avif|svg|bmp|woff2?|ttf|otf|eot|mp3|mp4|ogg|webm|wasm|pdf)$",
+  ),
+  ("api", "{path}", r"^/api(/|$)"),
+  ("admin", "{path}", r"^/(admin|administrator|wp-admin)(/|$)"),
+  (
+    "bot",
+    "{header:User-Agent}",
+    r"(?i)(bot|crawl|spider|slurp|archiver|facebookexternalhit|headlesschrome)",
+  ),
+];
+
+/// Obtains the placeholder value and the regular expression of a built-in request classification preset
+pub fn request_classification_preset(tag: &str) -> Option<(&'static str, &'static str)> {
+  REQUEST_CLASSIFICATION_PRESETS
+    .iter()
+    .find(|(preset_tag, _, _)| *preset_tag == tag)
+    .map(|(_, value, regex)| (*value, *regex))
+}
+
+/// A request classification rule, which assigns the tag if the value (with replaced placeholders) matches the regex
+#[derive(Debug)]
+struct RequestClassificationRule {
+  tag: String,
+  value: String,
+  regex: Regex,
+}
+
+/// A request classifier, which assigns tags to requests based on the `classify` directives
+#[derive(Debug)]
+pub struct RequestClassifier {
+  rules: Vec<RequestClassificationRule>,
+}
+
+impl RequestClassifier {
+  /// Creates a request classifier from the `classify` directives of the server configuration.
+  /// Returns `None`, if there are no `classify` directives.
+  pub fn from_config(config: &ServerConfiguration) -> Option<Self> {
+    Self::from_entries(config.entries.get("classify")?)
+  }
+
+  fn from_entries(entries: &ServerConfigurationEntries) -> Option<Self> {
+    let mut rules = Vec::new();
+    for entry in &entries.inner {
+      let Some(tag) = entry.values.first().and_then(|v| v.as_str()) else {
+        continue;
+      };
+      let (value, regex) = match (
+        entry.values.get(1).and_then(|v| v.as_str()),
+        entry.values.get(2).and_then(|v| v.as_str()),
+      ) {
+        (Some(value), Some(regex)) => (value, regex),
+        _ => match request_classification_preset(tag) {
+          Some(preset) => preset,
+          None => continue,
+        },
+      };
+      // The regular expressions are validated when the configuration is loaded
+      let Ok(regex) = Regex::new(regex) else {
+        continue;
+      };
+      rules.push(RequestClassificationRule {
+        tag: tag.to_string(),
+        value: value.to_string(),
+        regex,
+      });
+    }
+    if rules.is_empty() {
+      None
+    } else {
+      Some(Self { rules })
+    }
+  }
+
+  /// Classifies the request, and returns the request tags. Each tag is returned once, in the configuration order.
+  pub fn classify(&self, request: &hyper::http::request::Parts, socket_data: &SocketData) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for rule in &self.rules {
+      if tags.contains(&rule.tag) {
+        continue;
+      }
+      let value = replace_header_placeholders(&rule.value, request, Some(socket_data));
+      if rule.regex.is_match(&value).unwrap_or(false) {
+        tags.push(rule.tag.clone());
+      }
+    }
+    tags
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::{ServerConfigurationEntry, ServerConfigurationValue};
+  use hyper::Request;
+  use std::collections::HashMap;
+
+  fn make_entries(entries: Vec<Vec<&str>>) -> ServerConfigurationEntries {
+    ServerConfigurationEntries {
+      inner: entries
+        .into_iter()
+        .map(|values| ServerConfigurationEntry {
+          values: values
+            .into_iter()
+            .map(|v| ServerConfigurationValue::String(v.to_string()))
+            .collect(),
+          props: HashMap::new(),
+        })
+        .collect(),
+    }
+  }
+
+  fn classify(classifier: &RequestClassifier, uri: &str, user_agent: &str) -> Vec<String> {
+    let (parts, _) = Request::builder()
+      .uri(uri)
+      .header("User-Agent", user_agent)
+      .body(())
+      .unwrap()
+      .into_parts();
+    let socket_data = SocketData {
+      remote_addr: "192.0.2.1:12345".parse().unwrap(),
+      local_addr: "127.0.0.1:80".parse().unwrap(),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+    classifier.classify(&parts, &socket_data)
+  }
+
+  #[test]
+  fn test_classify_with_presets() {
+    let classifier =
+      RequestClassifier::from_entries(&make_entries(vec![vec!["static"], vec!["api"], vec!["bot"]])).unwrap();
+    assert_eq!(classify(&classifier, "/assets/app.CSS", "Mozilla/5.0"), vec!["static"]);
+    assert_eq!(classify(&classifier, "/api/users", "Googlebot/2.1"), vec!["api", "bot"]);
+    assert!(classify(&classifier, "/apiary", "Mozilla/5.0").is_empty());
+  }
+
+  #[test]
+  fn test_classify_with_custom_rules() {
+    let classifier = RequestClassifier::from_entries(&make_entries(vec![
+      vec!["internal", "{path}", "^/internal/"],
+      vec!["internal", "{header:User-Agent}", "^HealthChecker/"],
+    ]))
+    .unwrap();
+    assert_eq!(
+      classify(&classifier, "/internal/status", "Mozilla/5.0"),
+      vec!["internal"]
+    );
+    assert_eq!(classify(&classifier, "/", "HealthChecker/1.0"), vec!["internal"]);
+    assert!(classify(&classifier, "/", "Mozilla/5.0").is_empty());
+  }
+
+  #[test]
+  fn test_classifier_without_valid_rules() {
+    assert!(RequestClassifier::from_entries(&make_entries(vec![vec!["unknown"]])).is_none());
+  }
+}
@@ -0,0 +1,60 @@
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+/// A handler of a scheduled maintenance task (like `cache_prune` or `log_rotate`)
+pub type ScheduledTaskHandler = dyn Fn() + Send + Sync;
+
+/// The registered scheduled task handlers. The handlers are referenced weakly,
+/// so they are unregistered when their owners (like module loaders) are dropped.
+static SCHEDULED_TASK_HANDLERS: LazyLock<Mutex<Vec<RegisteredScheduledTaskHandler>>> =
+  LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// A registered scheduled task handler, along with the name of the task
+type RegisteredScheduledTaskHandler = (&'static str, Weak<ScheduledTaskHandler>);
+
+/// Registers a handler of a scheduled maintenance task. The handler stays registered as long as it isn't dropped.
+pub fn register_scheduled_task(task: &'static str, handler: &Arc<ScheduledTaskHandler>) {
+  let mut handlers = SCHEDULED_TASK_HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+  handlers.retain(|(_, handler)| handler.strong_count() > 0);
+  handlers.push((task, Arc::downgrade(handler)));
+}
+
+/// Runs all the registered handlers of a scheduled maintenance task, and returns the number of executed handlers
+pub fn run_scheduled_task(task: &str) -> usize {
+  let handlers = SCHEDULED_TASK_HANDLERS
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .iter()
+    .filter(|(handler_task, _)| *handler_task == task)
+    .filter_map(|(_, handler)| handler.upgrade())
+    .collect::<Vec<_>>();
+  // The handlers are executed without holding the lock, so that they can register other handlers
+  for handler in &handlers {
+    handler();
+  }
+  handlers.len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  #[test]
+  fn test_run_scheduled_task() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter_clone = counter.clone();
+    let handler: Arc<ScheduledTaskHandler> = Arc::new(move || {
+      counter_clone.fetch_add(1, Ordering::Relaxed);
+    });
+    register_scheduled_task("test_task", &handler);
+
+    assert_eq!(run_scheduled_task("test_task"), 1);
+    assert_eq!(run_scheduled_task("other_test_task"), 0);
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    // Dropped handlers are unregistered
+    drop(handler);
+    assert_eq!(run_scheduled_task("test_task"), 0);
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+  }
+}
@@ -0,0 +1,263 @@
+/// The DER-encoded object identifier of the embedded SCT list X.509 extension (1.3.6.1.4.1.11129.2.4.2)
+const SCT_LIST_EXTENSION_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+/// The DER tag of the SEQUENCE type
+const DER_TAG_SEQUENCE: u8 = 0x30;
+/// The DER tag of the OBJECT IDENTIFIER type
+const DER_TAG_OBJECT_IDENTIFIER: u8 = 0x06;
+/// The DER tag of the BOOLEAN type
+const DER_TAG_BOOLEAN: u8 = 0x01;
+/// The DER tag of the OCTET STRING type
+const DER_TAG_OCTET_STRING: u8 = 0x04;
+/// The DER tag of the X.509 certificate extensions field (context-specific, constructed, 3)
+const DER_TAG_EXTENSIONS: u8 = 0xa3;
+/// The version of the SCTs defined in RFC 6962
+const SCT_VERSION_V1: u8 = 0;
+
+/// A signed certificate timestamp (SCT) embedded in an X.509 certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedCertificateTimestamp {
+  /// The SHA-256 hash of the public key of the CT log, which issued the SCT
+  pub log_id: [u8; 32],
+
+  /// The time, at which the certificate was submitted to the CT log, in milliseconds since the Unix epoch
+  pub timestamp: u64,
+}
+
+/// Reads a DER TLV (tag, length, value), and returns the tag, the value and the remaining data
+fn read_der_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+  let (&tag, data) = data.split_first()?;
+  let (&first_length_byte, mut data) = data.split_first()?;
+  let length = if first_length_byte < 0x80 {
+    first_length_byte as usize
+  } else {
+    let length_bytes = (first_length_byte & 0x7f) as usize;
+    if length_bytes == 0 || length_bytes > 4 || data.len() < length_bytes {
+      return None;
+    }
+    let mut length = 0usize;
+    for byte in &data[..length_bytes] {
+      length = (length << 8) | *byte as usize;
+    }
+    data = &data[length_bytes..];
+    length
+  };
+  if data.len() < length {
+    return None;
+  }
+  let (value, rest) = data.split_at(length);
+  Some((tag, value, rest))
+}
+
+/// Reads a DER TLV with the expected tag, and returns the value and the remaining data
+fn read_der_expected(data: &[u8], expected_tag: u8) -> Option<(&[u8], &[u8])> {
+  let (tag, value, rest) = read_der_tlv(data)?;
+  if tag == expected_tag {
+    Some((value, rest))
+  } else {
+    None
+  }
+}
+
+/// Reads a TLS vector with a 16-bit length prefix, and returns the vector and the remaining data
+fn read_tls_u16_prefixed(data: &[u8]) -> Option<(&[u8], &[u8])> {
+  if data.len() < 2 {
+    return None;
+  }
+  let length = u16::from_be_bytes([data[0], data[1]]) as usize;
+  let data = &data[2..];
+  if data.len() < length {
+    return None;
+  }
+  Some(data.split_at(length))
+}
+
+/// Parses the TLS-encoded SCT list (RFC 6962, section 3.3). SCTs with unknown versions are ignored.
+fn parse_sct_list(data: &[u8]) -> Option<Vec<SignedCertificateTimestamp>> {
+  let (mut list, rest) = read_tls_u16_prefixed(data)?;
+  if !rest.is_empty() {
+    return None;
+  }
+  let mut scts = Vec::new();
+  while !list.is_empty() {
+    let (sct, rest) = read_tls_u16_prefixed(list)?;
+    list = rest;
+    let (&version, sct) = sct.split_first()?;
+    if version != SCT_VERSION_V1 {
+      continue;
+    }
+    if sct.len() < 40 {
+      return None;
+    }
+    let mut log_id = [0u8; 32];
+    log_id.copy_from_slice(&sct[..32]);
+    let mut timestamp_bytes = [0u8; 8];
+    timestamp_bytes.copy_from_slice(&sct[32..40]);
+    // The SCT extensions and the signature (with the hash and signature algorithms) follow
+    let (_, sct) = read_tls_u16_prefixed(&sct[40..])?;
+    if sct.len() < 2 {
+      return None;
+    }
+    read_tls_u16_prefixed(&sct[2..])?;
+    scts.push(SignedCertificateTimestamp {
+      log_id,
+      timestamp: u64::from_be_bytes(timestamp_bytes),
+    });
+  }
+  Some(scts)
+}
+
+/// Obtains the signed certificate timestamps (SCTs) embedded in the DER-encoded X.509 certificate.
+/// Returns `None`, if the certificate or the embedded SCT list is malformed.
+///
+/// The SCT signatures aren't verified, since this requires the public keys of the CT logs.
+pub fn parse_embedded_scts(certificate: &[u8]) -> Option<Vec<SignedCertificateTimestamp>> {
+  let (certificate, _) = read_der_expected(certificate, DER_TAG_SEQUENCE)?;
+  let (mut tbs_certificate, _) = read_der_expected(certificate, DER_TAG_SEQUENCE)?;
+  while !tbs_certificate.is_empty() {
+    let (tag, value, rest) = read_der_tlv(tbs_certificate)?;
+    tbs_certificate = rest;
+    if tag != DER_TAG_EXTENSIONS {
+      continue;
+    }
+
+    let (mut extensions, _) = read_der_expected(value, DER_TAG_SEQUENCE)?;
+    while !extensions.is_empty() {
+      let (extension, rest) = read_der_expected(extensions, DER_TAG_SEQUENCE)?;
+      extensions = rest;
+      let (oid, mut extension) = read_der_expected(extension, DER_TAG_OBJECT_IDENTIFIER)?;
+      if oid != SCT_LIST_EXTENSION_OID {
+        continue;
+      }
+      if let Some((_, rest)) = read_der_expected(extension, DER_TAG_BOOLEAN) {
+        // The "critical" field
+        extension = rest;
+      }
+      // The extension value is an OCTET STRING wrapping another OCTET STRING with the SCT list
+      let (extension_value, _) = read_der_expected(extension, DER_TAG_OCTET_STRING)?;
+      let (sct_list, _) = read_der_expected(extension_value, DER_TAG_OCTET_STRING)?;
+      return parse_sct_list(sct_list);
+    }
+    return Some(Vec::new());
+  }
+  Some(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn der(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    if value.len() < 0x80 {
+      encoded.push(value.len() as u8);
+    } else {
+      encoded.push(0x82);
+      encoded.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    }
+    encoded.extend_from_slice(value);
+    encoded
+  }
+
+  fn tls_u16_prefixed(value: &[u8]) -> Vec<u8> {
+    let mut encoded = (value.len() as u16).to_be_bytes().to_vec();
+    encoded.extend_from_slice(value);
+    encoded
+  }
+
+  fn build_sct(version: u8, log_id_byte: u8, timestamp: u64) -> Vec<u8> {
+    let mut sct = vec![version];
+    sct.extend_from_slice(&[log_id_byte; 32]);
+    sct.extend_from_slice(&timestamp.to_be_bytes());
+    sct.extend_from_slice(&tls_u16_prefixed(&[]));
+    sct.extend_from_slice(&[0x04, 0x03]);
+    sct.extend_from_slice(&tls_u16_prefixed(&[0x42; 70]));
+    sct
+  }
+
+  fn build_certificate(scts: Option<Vec<Vec<u8>>>) -> Vec<u8> {
+    // A "basicConstraints" extension before the SCT list extension
+    let mut extensions = der(
+      DER_TAG_SEQUENCE,
+      &[
+        der(DER_TAG_OBJECT_IDENTIFIER, &[0x55, 0x1d, 0x13]),
+        der(DER_TAG_OCTET_STRING, &der(DER_TAG_SEQUENCE, &[])),
+      ]
+      .concat(),
+    );
+    if let Some(scts) = scts {
+      let sct_list: Vec<u8> = scts.iter().flat_map(|sct| tls_u16_prefixed(sct)).collect();
+      extensions.extend_from_slice(&der(
+        DER_TAG_SEQUENCE,
+        &[
+          der(DER_TAG_OBJECT_IDENTIFIER, SCT_LIST_EXTENSION_OID),
+          der(
+            DER_TAG_OCTET_STRING,
+            &der(DER_TAG_OCTET_STRING, &tls_u16_prefixed(&sct_list)),
+          ),
+        ]
+        .concat(),
+      ));
+    }
+
+    let tbs_certificate = der(
+      DER_TAG_SEQUENCE,
+      &[
+        der(0xa0, &der(0x02, &[0x02])),
+        der(0x02, &[0x01, 0x23]),
+        der(DER_TAG_SEQUENCE, &[]),
+        der(DER_TAG_SEQUENCE, &[]),
+        der(DER_TAG_SEQUENCE, &[]),
+        der(DER_TAG_SEQUENCE, &[]),
+        der(DER_TAG_SEQUENCE, &[]),
+        der(DER_TAG_EXTENSIONS, &der(DER_TAG_SEQUENCE, &extensions)),
+      ]
+      .concat(),
+    );
+    der(
+      DER_TAG_SEQUENCE,
+      &[tbs_certificate, der(DER_TAG_SEQUENCE, &[]), der(0x03, &[0x00])].concat(),
+    )
+  }
+
+  #[test]
+  fn test_parse_embedded_scts() {
+    let certificate = build_certificate(Some(vec![
+      build_sct(SCT_VERSION_V1, 0x01, 1_700_000_000_000),
+      build_sct(SCT_VERSION_V1, 0x02, 1_700_000_001_000),
+    ]));
+    assert_eq!(
+      parse_embedded_scts(&certificate),
+      Some(vec![
+        SignedCertificateTimestamp {
+          log_id: [0x01; 32],
+          timestamp: 1_700_000_000_000
+        },
+        SignedCertificateTimestamp {
+          log_id: [0x02; 32],
+          timestamp: 1_700_000_001_000
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn test_parse_embedded_scts_with_unknown_version() {
+    let certificate = build_certificate(Some(vec![
+      build_sct(1, 0x01, 1_700_000_000_000),
+      build_sct(SCT_VERSION_V1, 0x02, 1_700_000_001_000),
+    ]));
+    assert_eq!(parse_embedded_scts(&certificate).map(|scts| scts.len()), Some(1));
+  }
+
+  #[test]
+  fn test_parse_certificate_without_scts() {
+    assert_eq!(parse_embedded_scts(&build_certificate(None)), Some(Vec::new()));
+  }
+
+  #[test]
+  fn test_parse_malformed_certificate() {
+    let certificate = build_certificate(Some(vec![build_sct(SCT_VERSION_V1, 0x01, 1_700_000_000_000)]));
+    assert_eq!(parse_embedded_scts(&certificate[..certificate.len() - 10]), None);
+    assert_eq!(parse_embedded_scts(b"not a certificate"), None);
+  }
+}
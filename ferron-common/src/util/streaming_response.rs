@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use hyper::header;
+
+/// A response extension marking responses that are streamed to the client (for example, Server-Sent Events or long-polling responses),
+/// and which bodies shouldn't be buffered or compressed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StreamingResponse;
+
+/// A response extension specifying how often the response data held in buffers (for example, by compressors) is flushed to the client.
+/// A zero flush interval means that the data is flushed immediately after it's received.
+#[derive(Clone, Copy, Debug)]
+pub struct ResponseFlushInterval(pub Duration);
+
+/// Obtains the flush interval of the response, if it's specified
+pub fn response_flush_interval<B>(response: &hyper::Response<B>) -> Option<Duration> {
+  response
+    .extensions()
+    .get::<ResponseFlushInterval>()
+    .map(|flush_interval| flush_interval.0)
+}
+
+/// Determines if the response is a streaming response (a Server-Sent Events response, or a response marked as streaming
+/// or having a flush interval)
+pub fn is_streaming_response<B>(response: &hyper::Response<B>) -> bool {
+  response.extensions().get::<StreamingResponse>().is_some()
+    || response.extensions().get::<ResponseFlushInterval>().is_some()
+    || response
+      .headers()
+      .get(header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .is_some_and(|value| {
+        value
+          .split(';')
+          .next()
+          .is_some_and(|mime_type| mime_type.trim().eq_ignore_ascii_case("text/event-stream"))
+      })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_streaming_response() {
+    let response = hyper::Response::builder()
+      .header(header::CONTENT_TYPE, "Text/Event-Stream; charset=utf-8")
+      .body(())
+      .unwrap();
+    assert!(is_streaming_response(&response));
+
+    let mut response = hyper::Response::builder()
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(())
+      .unwrap();
+    assert!(!is_streaming_response(&response));
+    response.extensions_mut().insert(StreamingResponse);
+    assert!(is_streaming_response(&response));
+  }
+
+  #[test]
+  fn test_response_flush_interval() {
+    let mut response = hyper::Response::new(());
+    assert_eq!(response_flush_interval(&response), None);
+    response
+      .extensions_mut()
+      .insert(ResponseFlushInterval(Duration::from_millis(100)));
+    assert_eq!(response_flush_interval(&response), Some(Duration::from_millis(100)));
+    assert!(is_streaming_response(&response));
+  }
+}
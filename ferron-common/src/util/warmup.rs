@@ -0,0 +1,23 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+
+use async_channel::{Receiver, Sender};
+
+/// A warmup task (like pre-establishing connections to backend servers), run on a request handler thread
+pub type WarmupTask = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()>>> + Send>;
+
+/// The queue of warmup tasks. The tasks are queued by module loaders, and received by the request handler threads.
+static WARMUP_TASKS: LazyLock<(Sender<WarmupTask>, Receiver<WarmupTask>)> = LazyLock::new(async_channel::unbounded);
+
+/// Queues a warmup task to be run on one of the request handler threads.
+/// The warmup tasks are run on the request handler threads, because some resources
+/// (like connections to backend servers) are bound to the async runtimes of these threads.
+pub fn queue_warmup_task(task: WarmupTask) {
+  WARMUP_TASKS.0.try_send(task).unwrap_or_default();
+}
+
+/// Waits for a queued warmup task
+pub async fn receive_warmup_task() -> Option<WarmupTask> {
+  WARMUP_TASKS.1.recv().await.ok()
+}
@@ -1,11 +1,27 @@
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use toml::Table;
 use yaml_rust2::{YamlEmitter, YamlLoader};
 
+/// Collects the static assets to embed into the binary, with their URL paths
+fn collect_embedded_assets(directory: &Path, url_path: &str, assets: &mut Vec<(String, PathBuf)>) {
+  let entries = std::fs::read_dir(directory)
+    .unwrap_or_else(|e| panic!("Can't read the embedded directory \"{}\": {e}", directory.display()));
+  for entry in entries {
+    let entry = entry.unwrap();
+    let file_name = entry.file_name().to_string_lossy().to_string();
+    let path = entry.path();
+    if path.is_dir() {
+      collect_embedded_assets(&path, &format!("{url_path}/{file_name}"), assets);
+    } else {
+      assets.push((format!("{url_path}/{file_name}"), path));
+    }
+  }
+}
+
 fn main() {
   println!("cargo:rerun-if-changed=../ferron-build.yaml");
   println!("cargo:rerun-if-changed=../ferron-build-override.yaml");
@@ -65,11 +81,15 @@ fn main() {
       {
         let module_loader_name = module["loader"].as_str().unwrap();
         let module_loader = format!("ferron_modules_builtin::{module_loader_name}::new()");
-        modules_block_inside.push_str(&format!("register_module_loader!({module_loader});\n"));
+        modules_block_inside.push_str(&format!(
+          "register_module_loader!({module_loader_name:?}, {module_loader});\n"
+        ));
       } else if let Some(crate_name) = module["crate"].as_str() {
         let module_loader_name = module["loader"].as_str().unwrap();
         let module_loader = format!("{}::{}::new()", crate_name.replace("-", "_"), module_loader_name);
-        modules_block_inside.push_str(&format!("register_module_loader!({module_loader});\n"));
+        modules_block_inside.push_str(&format!(
+          "register_module_loader!({module_loader_name:?}, {module_loader});\n"
+        ));
       } else {
         println!(
           "cargo:warning=Module with \"{}\" loader is not built-in",
@@ -166,6 +186,33 @@ fn main() {
   )
   .unwrap();
 
+  let mut embedded_block_inside = String::new();
+  ferron_build_yaml["embedded"]
+    .as_vec()
+    .unwrap_or(&vec![])
+    .iter()
+    .for_each(|embedded_directory| {
+      let name = embedded_directory["name"].as_str().unwrap();
+      let path = embedded_directory["path"].as_str().unwrap();
+      let directory_path = Path::new(&crate_dir).join("..").join(path);
+      println!("cargo:rerun-if-changed={}", directory_path.display());
+      let mut assets = Vec::new();
+      collect_embedded_assets(&directory_path, "", &mut assets);
+      assets.sort();
+      embedded_block_inside.push_str(&format!("({name:?}, &[\n"));
+      for (asset_url_path, asset_path) in assets {
+        embedded_block_inside.push_str(&format!(
+          "({asset_url_path:?}, include_bytes!({:?})),\n",
+          asset_path.display().to_string()
+        ));
+      }
+      embedded_block_inside.push_str("]),\n");
+    });
+
+  let dest_path = Path::new(&out_dir).join("embedded_assets.rs");
+  let mut f = File::create(&dest_path).unwrap();
+  f.write_all(format!("&[{embedded_block_inside}]").as_bytes()).unwrap();
+
   let dest_path = Path::new(&out_dir).join("ferron-build.yaml");
   let mut f = File::create(&dest_path).unwrap();
   let mut ferron_build_yaml_normalized = String::new();
@@ -1,9 +1,14 @@
 use std::{collections::HashMap, error::Error, sync::Arc};
 
+use ferron_common::util::EmbeddedAssetDirectory;
 use ferron_common::{dns::DnsProvider, modules::ModuleLoader, observability::ObservabilityBackendLoader};
 
 pub const FERRON_BUILD_YAML: &str = include_str!(concat!(env!("OUT_DIR"), "/ferron-build.yaml"));
 
+/// Static assets embedded into the binary, keyed by the names of the embedded directories
+pub const EMBEDDED_ASSETS: &[(&str, EmbeddedAssetDirectory)] =
+  include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
 /// Obtains the module loaders
 pub fn obtain_module_loaders() -> Vec<Box<dyn ModuleLoader + Send + Sync>> {
   // Module loaders
@@ -11,7 +16,7 @@ pub fn obtain_module_loaders() -> Vec<Box<dyn ModuleLoader + Send + Sync>> {
 
   // Module loader registration macro
   macro_rules! register_module_loader {
-    ($moduleloader:expr) => {
+    ($name:expr, $moduleloader:expr) => {
       module_loaders.push(Box::new($moduleloader));
     };
   }
@@ -23,6 +28,25 @@ pub fn obtain_module_loaders() -> Vec<Box<dyn ModuleLoader + Send + Sync>> {
   module_loaders
 }
 
+/// Obtains the names of the module loaders, in the same order as the module loaders obtained with `obtain_module_loaders`
+pub fn obtain_module_loader_names() -> Vec<&'static str> {
+  // Module loader names
+  let mut module_loader_names: Vec<&'static str> = Vec::new();
+
+  // Module loader registration macro, which doesn't construct the module loaders
+  macro_rules! register_module_loader {
+    ($name:expr, $moduleloader:expr) => {
+      module_loader_names.push($name);
+    };
+  }
+
+  // Register module loader names
+  include!(concat!(env!("OUT_DIR"), "/register_module_loaders.rs"));
+
+  // Return the module loader names vector
+  module_loader_names
+}
+
 /// Obtains the observability backend loaders
 pub fn obtain_observability_backend_loaders() -> Vec<Box<dyn ObservabilityBackendLoader + Send + Sync>> {
   // Observability backend loaders
@@ -9,6 +9,7 @@ use http_body_util::{BodyExt, BodyStream, StreamBody};
 use hyper::{Request, Response};
 
 use ferron_common::logging::ErrorLogger;
+use ferron_common::util::{ErrorCode, MemoryReservation, MEMORY_BUDGET};
 use ferron_common::{config::ServerConfiguration, util::ModuleCache};
 use ferron_common::{get_entries_for_validation, get_value};
 
@@ -63,12 +64,8 @@ impl ModuleLoader for BufferModuleLoader {
           Err(anyhow::anyhow!(
             "The `buffer_request` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() && !entry.values[0].is_null() {
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid HTTP request body buffer size"))?
-        } else if let Some(value) = entry.values[0].as_i128() {
-          if value < 0 {
-            Err(anyhow::anyhow!("Invalid HTTP request body buffer size"))?
-          }
         }
       }
     }
@@ -79,12 +76,8 @@ impl ModuleLoader for BufferModuleLoader {
           Err(anyhow::anyhow!(
             "The `buffer_response` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() && !entry.values[0].is_null() {
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid HTTP response body buffer size"))?
-        } else if let Some(value) = entry.values[0].as_i128() {
-          if value < 0 {
-            Err(anyhow::anyhow!("Invalid HTTP response body buffer size"))?
-          }
         }
       }
     }
@@ -100,6 +93,7 @@ impl Module for BufferModule {
   fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
     Box::new(BufferModuleHandlers {
       response_buffer_size: None,
+      memory_reservation: MEMORY_BUDGET.reserve(),
     })
   }
 }
@@ -107,6 +101,8 @@ impl Module for BufferModule {
 /// Handlers for the buffering module
 struct BufferModuleHandlers {
   response_buffer_size: Option<usize>,
+  /// The reservation of the buffered bytes in the global memory budget, released after the request is handled
+  memory_reservation: MemoryReservation,
 }
 
 #[async_trait(?Send)]
@@ -118,7 +114,7 @@ impl ModuleHandlers for BufferModuleHandlers {
     _socket_data: &SocketData,
     _error_logger: &ErrorLogger,
   ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
-    if let Some(request_buffer_size) = get_value!("buffer_request", config).and_then(|v| v.as_i128()) {
+    if let Some(request_buffer_size) = get_value!("buffer_request", config).and_then(|v| v.as_size()) {
       let (request_parts, mut request_body) = request.into_parts();
       let mut request_body_buffer = Vec::new();
       let mut data_len: usize = 0;
@@ -126,9 +122,19 @@ impl ModuleHandlers for BufferModuleHandlers {
         let frame = frame?;
         match frame.into_data() {
           Ok(data) => {
+            if !self.memory_reservation.try_grow(data.len()) {
+              // The request body can't be buffered, because the global memory budget is exceeded
+              return Ok(ResponseData {
+                request: None,
+                response: None,
+                response_status: Some(ErrorCode::MEMORY_BUDGET_EXCEEDED.status_code()),
+                response_headers: Some(ErrorCode::MEMORY_BUDGET_EXCEEDED.to_headers()),
+                new_remote_address: None,
+              });
+            }
             data_len += data.len();
             request_body_buffer.push(hyper::body::Frame::data(data));
-            if data_len >= request_buffer_size as usize {
+            if data_len as u64 >= request_buffer_size {
               break;
             }
           }
@@ -149,7 +155,7 @@ impl ModuleHandlers for BufferModuleHandlers {
     }
 
     self.response_buffer_size = get_value!("buffer_response", config)
-      .and_then(|v| v.as_i128())
+      .and_then(|v| v.as_size())
       .map(|s| s as usize);
 
     Ok(ResponseData {
@@ -174,9 +180,11 @@ impl ModuleHandlers for BufferModuleHandlers {
         let frame = frame?;
         match frame.into_data() {
           Ok(data) => {
+            // If the global memory budget is exceeded, the rest of the response body is streamed without buffering
+            let memory_budget_exceeded = !self.memory_reservation.try_grow(data.len());
             data_len += data.len();
             response_body_buffer.push(hyper::body::Frame::data(data));
-            if data_len >= response_buffer_size {
+            if data_len >= response_buffer_size || memory_budget_exceeded {
               break;
             }
           }
@@ -5,17 +5,23 @@ use std::{collections::HashSet, error::Error};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use cidr::IpCidr;
 use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Empty};
 use hyper::header::HeaderName;
 use hyper::{header, Request, Response, StatusCode, Uri};
 
-use ferron_common::config::ServerConfiguration;
-use ferron_common::logging::ErrorLogger;
-use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::config::{ServerConfiguration, ServerConfigurationValue};
+use ferron_common::logging::{ErrorLogger, LogLevel};
+use ferron_common::modules::{
+  DirectiveScope, Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData,
+};
 use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
-use ferron_common::util::{is_localhost, ModuleCache};
-use ferron_common::{get_entries_for_validation, get_entry, get_value, get_values_for_validation};
+use ferron_common::util::{
+  ipv6_zone_index, is_localhost, parse_ip_literal, request_classification_preset, CronSchedule, IpBlockList,
+  ModuleCache, RealIpResolver,
+};
+use ferron_common::{get_entries, get_entries_for_validation, get_entry, get_value, get_values_for_validation};
 
 /// A core module loader
 pub struct CoreModuleLoader {
@@ -33,7 +39,12 @@ impl CoreModuleLoader {
   /// Creates a new module loader
   pub fn new() -> Self {
     Self {
-      cache: ModuleCache::new(vec![]),
+      cache: ModuleCache::new(vec![
+        "real_ip_depth",
+        "real_ip_header",
+        "real_ip_recursive",
+        "real_ip_trusted_proxies",
+      ]),
       has_https: Arc::new(AtomicBool::new(false)),
     }
   }
@@ -48,7 +59,7 @@ impl ModuleLoader for CoreModuleLoader {
   ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
     if !config.filters.is_global_non_host()
       && (get_value!("auto_tls", config).and_then(|v| v.as_bool()).unwrap_or(
-        (config.filters.hostname.is_some()
+        ((config.filters.hostname.is_some() && !config.filters.has_regex_hostname())
           || config.filters.ip.is_some()
           || get_value!("auto_tls_on_demand", config)
             .and_then(|v| v.as_bool())
@@ -85,6 +96,30 @@ impl ModuleLoader for CoreModuleLoader {
                 }
               }),
             has_https: self.has_https.clone(),
+            real_ip_header: get_value!("real_ip_header", config)
+              .and_then(|v| v.as_str())
+              .and_then(|v| HeaderName::from_bytes(v.as_bytes()).ok())
+              .unwrap_or(HeaderName::from_static("x-forwarded-for")),
+            real_ip: Arc::new(RealIpResolver {
+              trusted_proxies: get_entries!("real_ip_trusted_proxies", config).map(|entries| {
+                let mut list = IpBlockList::new();
+                list.load_from_vec(
+                  entries
+                    .inner
+                    .iter()
+                    .flat_map(|e| e.values.iter())
+                    .filter_map(|v| v.as_str())
+                    .collect(),
+                );
+                list
+              }),
+              recursive: get_value!("real_ip_recursive", config)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+              depth: get_value!("real_ip_depth", config)
+                .and_then(|v| v.as_i128())
+                .map(|v| v as usize),
+            }),
           }))
         })?,
     )
@@ -94,6 +129,86 @@ impl ModuleLoader for CoreModuleLoader {
     vec![]
   }
 
+  fn get_directive_scopes(&self) -> Vec<(&'static str, DirectiveScope)> {
+    let mut directive_scopes = [
+      "default_http_port",
+      "default_https_port",
+      "protocols",
+      "timeout",
+      "client_abort_grace",
+      "keepalive_timeout",
+      "keepalive_max_requests",
+      "graceful_shutdown_timeout",
+      "shutdown_hook",
+      "shutdown_drain_period",
+      "shutdown_drain_health_check",
+      "h2_initial_window_size",
+      "h2_max_frame_size",
+      "h2_max_concurrent_streams",
+      "h2_max_header_list_size",
+      "h2_enable_connect_protocol",
+      "max_request_header_size",
+      "max_header_count",
+      "protocol_proxy",
+      "listen_ip",
+      "io_uring",
+      "tcp_send_buffer",
+      "tcp_recv_buffer",
+      "tcp_accept_filter",
+      "dynamic_hosts",
+      "ocsp_stapling",
+      "ocsp_stapling_failure_policy",
+      "ocsp_stapling_max_age",
+      "auto_tls_on_demand_ask",
+      "auto_tls_on_demand_ask_no_verification",
+      "schedule",
+      "admin_listen",
+      "admin_token",
+      "memory_budget",
+      "sni_route",
+      "tls_default",
+      "strict_sni",
+    ]
+    .into_iter()
+    .map(|directive| (directive, DirectiveScope::Global))
+    .collect::<Vec<_>>();
+    // TLS certificates are selected for the whole host, so the TLS configuration can't vary by location
+    directive_scopes.extend(
+      [
+        "tls",
+        "auto_tls",
+        "auto_tls_contact",
+        "auto_tls_cache",
+        "auto_tls_letsencrypt_production",
+        "auto_tls_challenge",
+        "auto_tls_directory",
+        "auto_tls_directory_ca",
+        "auto_tls_no_verification",
+        "auto_tls_profile",
+        "auto_tls_issuer",
+        "auto_tls_issuer_directory",
+        "auto_tls_on_demand",
+        "auto_tls_eab",
+        "auto_tls_save_data",
+        "auto_tls_post_obtain_command",
+        "tls_policy",
+        "tls_cipher_suite",
+        "tls_ecdh_curve",
+        "tls_client_certificate",
+        "tls_min_version",
+        "tls_max_version",
+        "host_priority",
+        // Requests are classified before the location and conditional blocks are matched
+        "classify",
+        // Request bodies are buffered before the location and conditional blocks are matched
+        "body_match_buffer",
+      ]
+      .into_iter()
+      .map(|directive| (directive, DirectiveScope::Host)),
+    );
+    directive_scopes
+  }
+
   fn validate_configuration(
     &self,
     config: &ServerConfiguration,
@@ -149,6 +264,23 @@ impl ModuleLoader for CoreModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("tls_policy", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `tls_policy` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(
+          entry.values[0].as_str(),
+          Some("modern" | "intermediate" | "pq-hybrid" | "legacy")
+        ) {
+          Err(anyhow::anyhow!(
+            "The TLS policy must be \"modern\", \"intermediate\", \"pq-hybrid\" or \"legacy\""
+          ))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("auto_tls", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -199,7 +331,7 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `h2_initial_window_size` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() {
+        } else if entry.values[0].as_size().is_none_or(|v| v > u32::MAX as u64) {
           Err(anyhow::anyhow!("Invalid HTTP/2 initial window size"))?
         }
       }
@@ -211,7 +343,7 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `h2_max_frame_size` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() {
+        } else if entry.values[0].as_size().is_none_or(|v| v > u32::MAX as u64) {
           Err(anyhow::anyhow!("Invalid HTTP/2 maximum frame size"))?
         }
       }
@@ -235,12 +367,36 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `h2_max_header_list_size` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() {
+        } else if entry.values[0].as_size().is_none_or(|v| v > u32::MAX as u64) {
           Err(anyhow::anyhow!("Invalid HTTP/2 maximum header list size"))?
         }
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("max_request_header_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `max_request_header_size` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_size().is_none_or(|v| v == 0) {
+          Err(anyhow::anyhow!("Invalid maximum request header size"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("max_header_count", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `max_header_count` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_i128().is_none_or(|v| v <= 0) {
+          Err(anyhow::anyhow!("Invalid maximum request header count"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("h2_enable_connect_protocol", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -283,16 +439,94 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `timeout` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() && !entry.values[0].is_null() {
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
           Err(anyhow::anyhow!("Invalid HTTP server processing timeout"))?
-        } else if let Some(value) = entry.values[0].as_i128() {
-          if value < 0 {
-            Err(anyhow::anyhow!("Invalid HTTP server processing timeout"))?
-          }
         }
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("client_abort_grace", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `client_abort_grace` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid client disconnect grace period"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("keepalive_timeout", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `keepalive_timeout` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none_or(|d| d.is_zero()) {
+          Err(anyhow::anyhow!("Invalid keep-alive timeout"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("keepalive_max_requests", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `keepalive_max_requests` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 1) {
+          Err(anyhow::anyhow!(
+            "Invalid maximum number of requests per keep-alive connection"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("graceful_shutdown_timeout", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `graceful_shutdown_timeout` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid graceful shutdown timeout"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("shutdown_hook", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `shutdown_hook` configuration property must have at least one value"
+          ))?
+        } else if !entry.values.iter().all(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The shutdown hook command and its arguments must be strings"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("shutdown_drain_period", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `shutdown_drain_period` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid shutdown drain period"))?
+        }
+      }
+    };
+
+    for health_check_path in get_values_for_validation!("shutdown_drain_health_check", config, used_properties) {
+      if !health_check_path.as_str().is_some_and(|path| path.starts_with('/')) {
+        Err(anyhow::anyhow!("Invalid shutdown drain health check path"))?
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("allow_double_slashes", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -317,6 +551,42 @@ impl ModuleLoader for CoreModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("expose_error_codes", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `expose_error_codes` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid error code exposure enabling option"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("expect_continue", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `expect_continue` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(entry.values[0].as_str(), Some("deferred" | "reject")) {
+          Err(anyhow::anyhow!("Invalid \"Expect: 100-continue\" handling policy"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("max_request_body_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `max_request_body_size` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
+          Err(anyhow::anyhow!("Invalid maximum request body size"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("error_page", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 2 {
@@ -343,6 +613,70 @@ impl ModuleLoader for CoreModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("ocsp_stapling_failure_policy", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `ocsp_stapling_failure_policy` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(
+          entry.values[0].as_str(),
+          Some("serve") | Some("fail_must_staple") | Some("fail")
+        ) {
+          Err(anyhow::anyhow!("Invalid OCSP stapling failure policy"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("ocsp_stapling_max_age", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `ocsp_stapling_max_age` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid OCSP response maximum age"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("tls_default", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() == 1 {
+          if entry.values[0].as_str() != Some("self_signed") {
+            Err(anyhow::anyhow!(
+              "The `tls_default` configuration property must have either the certificate and private key paths, \
+               or the \"self_signed\" value"
+            ))?
+          }
+        } else if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `tls_default` configuration property must have either one or two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("Invalid path to the default TLS certificate"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("Invalid path to the default TLS private key"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("strict_sni", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `strict_sni` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid strict SNI enabling option"))?
+        } else if entry.values[0].as_bool() == Some(true) && config.entries.contains_key("tls_default") {
+          Err(anyhow::anyhow!(
+            "The `strict_sni` and `tls_default` configuration properties can't be used together"
+          ))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("trust_x_forwarded_for", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -357,6 +691,88 @@ impl ModuleLoader for CoreModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("real_ip_trusted_proxies", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() && !entry.props.contains_key("url") {
+          Err(anyhow::anyhow!(
+            "The `real_ip_trusted_proxies` configuration property must have at least one value or the `url` prop"
+          ))?
+        }
+        for value in &entry.values {
+          if !value
+            .as_str()
+            .is_some_and(|v| v.parse::<IpAddr>().is_ok() || v.parse::<IpCidr>().is_ok())
+          {
+            Err(anyhow::anyhow!("Invalid trusted proxy IP address"))?
+          }
+        }
+        if let Some(prop) = entry.props.get("url") {
+          if !prop
+            .as_str()
+            .and_then(|v| v.parse::<Uri>().ok())
+            .is_some_and(|uri| matches!(uri.scheme_str(), Some("http") | Some("https")))
+          {
+            Err(anyhow::anyhow!("Invalid trusted proxy IP address list URL"))?
+          }
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("real_ip_recursive", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `real_ip_recursive` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid recursive client IP address resolution enabling option"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("real_ip_depth", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `real_ip_depth` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_i128().unwrap_or(0) < 1 {
+          Err(anyhow::anyhow!("Invalid client IP address resolution depth"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("real_ip_header", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `real_ip_header` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0]
+          .as_str()
+          .is_none_or(|v| HeaderName::from_bytes(v.as_bytes()).is_err())
+        {
+          Err(anyhow::anyhow!("Invalid client IP address header name"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("cdn", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `cdn` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(entry.values[0].as_str(), Some("cloudflare") | Some("fastly")) {
+          Err(anyhow::anyhow!("Unsupported CDN preset"))?
+        } else if !entry.props.get("cache_control").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("Invalid CDN cache control header value"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("no_redirect_to_https", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -381,6 +797,18 @@ impl ModuleLoader for CoreModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("host_priority", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `host_priority` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_integer() {
+          Err(anyhow::anyhow!("Invalid host priority"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("listen_ip", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -390,7 +818,7 @@ impl ModuleLoader for CoreModuleLoader {
         } else if !entry.values[0].is_string() {
           Err(anyhow::anyhow!("Invalid IP address to listen to"))?
         } else if let Some(value) = entry.values[0].as_str() {
-          if value.parse::<IpAddr>().is_err() {
+          if parse_ip_literal(value).is_none_or(|(_, zone)| zone.is_some_and(|zone| ipv6_zone_index(zone).is_none())) {
             Err(anyhow::anyhow!("Invalid IP address to listen to"))?
           }
         }
@@ -477,7 +905,7 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `tcp_send_buffer` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() || entry.values[0].as_i128().is_some_and(|v| v < 0) {
+        } else if entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid TCP listener send buffer size"))?
         }
       }
@@ -489,12 +917,24 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `tcp_recv_buffer` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() || entry.values[0].as_i128().is_some_and(|v| v < 0) {
+        } else if entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid TCP listener receive buffer size"))?
         }
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("tcp_accept_filter", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `tcp_accept_filter` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid TCP accept filter enabling option"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("header_remove", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -513,12 +953,27 @@ impl ModuleLoader for CoreModuleLoader {
           Err(anyhow::anyhow!(
             "The `auto_tls_directory` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_string() {
+        } else if !entry.values[0].as_str().is_some_and(|v| {
+          v.parse::<hyper::Uri>()
+            .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http") | Some("https")) && uri.host().is_some())
+        }) {
           Err(anyhow::anyhow!("Invalid ACME directory URL"))?
         }
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("auto_tls_directory_ca", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `auto_tls_directory_ca` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!("Invalid ACME directory CA certificate path"))?
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("auto_tls_no_verification", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -545,6 +1000,45 @@ impl ModuleLoader for CoreModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("auto_tls_issuer", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `auto_tls_issuer` configuration property must have at least one value"
+          ))?
+        } else if !entry.values.iter().all(|value| value.is_string()) {
+          Err(anyhow::anyhow!("Invalid ACME issuer name"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("auto_tls_issuer_directory", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `auto_tls_issuer_directory` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("Invalid ACME issuer name"))?
+        } else if !entry.values[1].as_str().is_some_and(|v| {
+          v.parse::<hyper::Uri>()
+            .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http") | Some("https")) && uri.host().is_some())
+        }) {
+          Err(anyhow::anyhow!("Invalid ACME directory URL"))?
+        } else if entry.props.contains_key("eab_key_id") != entry.props.contains_key("eab_key_hmac") {
+          Err(anyhow::anyhow!(
+            "The ACME EAB key ID and the ACME EAB key must be specified together"
+          ))?
+        } else if entry.props.get("eab_key_id").is_some_and(|v| !v.is_string()) {
+          Err(anyhow::anyhow!("Invalid ACME EAB key ID"))?
+        } else if entry.props.get("eab_key_hmac").is_some_and(|v| !v.is_string()) {
+          Err(anyhow::anyhow!("Invalid ACME EAB key"))?
+        } else if entry.props.get("directory_ca").is_some_and(|v| !v.is_string()) {
+          Err(anyhow::anyhow!("Invalid ACME directory CA certificate path"))?
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("header_replace", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 2 {
@@ -595,6 +1089,35 @@ impl ModuleLoader for CoreModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("dynamic_hosts", config, used_properties) {
+      let is_valid_duration = |value: &ServerConfigurationValue| value.as_duration().is_some();
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `dynamic_hosts` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .is_some_and(|v| v.parse::<Uri>().is_ok_and(|uri| uri.host().is_some()))
+        {
+          Err(anyhow::anyhow!("Invalid dynamic host provider URL"))?
+        } else if !entry
+          .props
+          .get("ttl")
+          .is_none_or(|v| v.is_null() || is_valid_duration(v))
+        {
+          Err(anyhow::anyhow!("Invalid dynamic host provider cache TTL"))?
+        } else if !entry.props.get("timeout").is_none_or(is_valid_duration) {
+          Err(anyhow::anyhow!("Invalid dynamic host provider timeout"))?
+        } else if !entry.props.get("no_verification").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!(
+            "Invalid dynamic host provider certificate verification disabling option"
+          ))?
+        }
+      }
+    }
+
     if let Some(entries) =
       get_entries_for_validation!("auto_tls_on_demand_ask_no_verification", config, used_properties)
     {
@@ -611,6 +1134,139 @@ impl ModuleLoader for CoreModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("schedule", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `schedule` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0]
+          .as_str()
+          .is_none_or(|v| CronSchedule::parse(v).is_none())
+        {
+          Err(anyhow::anyhow!("Invalid schedule"))?
+        } else if !matches!(
+          entry.props.get("task").and_then(|v| v.as_str()),
+          Some("cache_prune")
+            | Some("log_rotate")
+            | Some("blocklist_refresh")
+            | Some("cert_cache_cleanup")
+            | Some("webhook")
+        ) {
+          Err(anyhow::anyhow!("Invalid scheduled task"))?
+        } else if entry.props.get("task").and_then(|v| v.as_str()) == Some("webhook")
+          && entry
+            .props
+            .get("url")
+            .and_then(|v| v.as_str())
+            .is_none_or(|v| v.parse::<hyper::Uri>().is_err())
+        {
+          Err(anyhow::anyhow!("Invalid scheduled webhook URL"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("admin_listen", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `admin_listen` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0].as_str().is_some_and(|v| match v.strip_prefix("unix:") {
+            Some(path) => !path.is_empty(),
+            None => v.parse::<SocketAddr>().is_ok_and(|address| address.ip().is_loopback()),
+          })
+        {
+          Err(anyhow::anyhow!(
+            "Invalid admin API listen address (it must be either a loopback address with a port, or a Unix socket path prefixed with \"unix:\")"
+          ))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("admin_token", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `admin_token` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_str().is_none_or(|v| v.trim().is_empty()) {
+          Err(anyhow::anyhow!("Invalid admin API bearer token"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("memory_budget", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `memory_budget` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none_or(|v| v == 0) {
+          Err(anyhow::anyhow!("Invalid memory budget"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("classify", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 && entry.values.len() != 3 {
+          Err(anyhow::anyhow!(
+            "The `classify` configuration property must have one (for built-in presets) or three values"
+          ))?
+        } else if !entry.values.iter().all(|v| v.is_string()) {
+          Err(anyhow::anyhow!("Invalid request classification"))?
+        } else if entry.values.len() == 1
+          && entry.values[0]
+            .as_str()
+            .and_then(request_classification_preset)
+            .is_none()
+        {
+          Err(anyhow::anyhow!("Unknown request classification preset"))?
+        } else if entry.values.len() == 3
+          && entry.values[2]
+            .as_str()
+            .is_none_or(|v| fancy_regex::Regex::new(v).is_err())
+        {
+          Err(anyhow::anyhow!("Invalid request classification regular expression"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("body_match_buffer", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `body_match_buffer` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
+          Err(anyhow::anyhow!("Invalid request body match buffer size"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("sni_route", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 && !(entry.values.len() == 3 && entry.values[1].as_str() == Some("->")) {
+          Err(anyhow::anyhow!(
+            "The `sni_route` configuration property must have two values (optionally separated with \"->\")"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("Invalid SNI passthrough route hostname"))?
+        } else if !entry.values[entry.values.len() - 1].as_str().is_some_and(|v| {
+          v.strip_prefix("tcp://")
+            .unwrap_or(v)
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        }) {
+          Err(anyhow::anyhow!("Invalid SNI passthrough backend server address"))?
+        } else if entry.props.get("alpn").is_some_and(|v| !v.is_string()) {
+          Err(anyhow::anyhow!("Invalid SNI passthrough route ALPN protocol"))?
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("auto_tls_eab", config, used_properties) {
       for entry in &entries.inner {
         if !(1..=2).contains(&entry.values.len()) {
@@ -631,6 +1287,45 @@ impl ModuleLoader for CoreModuleLoader {
       }
     }
 
+    if let Some(log_entries) = get_entries_for_validation!("log_level", config, used_properties) {
+      for log_entry in &log_entries.inner {
+        if log_entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `log_level` configuration property must have exactly one value"
+          ))?
+        } else if log_entry.values[0]
+          .as_str()
+          .is_none_or(|v| LogLevel::parse(v).is_none())
+        {
+          Err(anyhow::anyhow!(
+            "Invalid log level (the log level must be `error`, `warn`, `info`, `debug` or `trace`)"
+          ))?
+        } else if !log_entry.props.get("module").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("The module name for the log level must be a string"))?
+        }
+      }
+    };
+
+    if let Some(log_entries) = get_entries_for_validation!("error_log_dedup", config, used_properties) {
+      for log_entry in &log_entries.inner {
+        if log_entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `error_log_dedup` configuration property must have exactly one value"
+          ))?
+        } else if !log_entry.values[0].is_null() && log_entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid error log deduplication window"))?
+        } else if log_entry
+          .props
+          .get("max_messages")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid maximum number of error log messages in the deduplication window"
+          ))?
+        }
+      }
+    };
+
     if let Some(log_entries) = get_entries_for_validation!("log_date_format", config, used_properties) {
       for log_entry in &log_entries.inner {
         if log_entry.values.len() != 1 {
@@ -748,6 +1443,8 @@ struct CoreModule {
   default_http_port: Option<u16>,
   default_https_port: Option<u16>,
   has_https: Arc<AtomicBool>,
+  real_ip_header: HeaderName,
+  real_ip: Arc<RealIpResolver>,
 }
 
 impl Module for CoreModule {
@@ -756,6 +1453,8 @@ impl Module for CoreModule {
       default_http_port: self.default_http_port,
       default_https_port: self.default_https_port,
       has_https: self.has_https.load(Ordering::Relaxed),
+      real_ip_header: self.real_ip_header.clone(),
+      real_ip: self.real_ip.clone(),
       request_timer: None,
       metrics_attributes: None,
       response_status: None,
@@ -768,6 +1467,8 @@ struct CoreModuleHandlers {
   default_http_port: Option<u16>,
   default_https_port: Option<u16>,
   has_https: bool,
+  real_ip_header: HeaderName,
+  real_ip: Arc<RealIpResolver>,
   request_timer: Option<std::time::Instant>,
   metrics_attributes: Option<Vec<(&'static str, MetricAttributeValue)>>,
   response_status: Option<hyper::StatusCode>,
@@ -847,30 +1548,21 @@ impl ModuleHandlers for CoreModuleHandlers {
         }
       }
 
-      // Save the new socket address from X-Forwarded-For header
+      // Save the new socket address from X-Forwarded-For header (or the header specified by `real_ip_header`)
       let mut new_remote_address = None;
       if get_value!("trust_x_forwarded_for", config)
         .and_then(|v| v.as_bool())
         .unwrap_or(false)
       {
-        if let Some(x_forwarded_for_value) = request.headers().get(HeaderName::from_static("x-forwarded-for")) {
+        if let Some(x_forwarded_for_value) = request.headers().get(&self.real_ip_header) {
           let x_forwarded_for = x_forwarded_for_value.to_str()?;
 
-          let prepared_remote_ip_str = match x_forwarded_for.split(",").nth(0) {
-            Some(ip_address_str) => ip_address_str.replace(" ", ""),
-            None => {
-              return Ok(ResponseData {
-                request: Some(request),
-                response: None,
-                response_status: Some(StatusCode::BAD_REQUEST),
-                response_headers: None,
-                new_remote_address: None,
-              });
+          match self.real_ip.resolve(socket_data.remote_addr.ip(), x_forwarded_for) {
+            Ok(Some(client_ip)) => {
+              new_remote_address = Some(SocketAddr::new(client_ip, socket_data.remote_addr.port()));
             }
-          };
-
-          let prepared_remote_ip: IpAddr = match prepared_remote_ip_str.parse() {
-            Ok(ip_address) => ip_address,
+            // The directly connected client isn't a trusted proxy, so the header is ignored
+            Ok(None) => (),
             Err(_) => {
               return Ok(ResponseData {
                 request: Some(request),
@@ -880,9 +1572,7 @@ impl ModuleHandlers for CoreModuleHandlers {
                 new_remote_address: None,
               });
             }
-          };
-
-          new_remote_address = Some(SocketAddr::new(prepared_remote_ip, socket_data.remote_addr.port()));
+          }
         }
       }
 
@@ -893,7 +1583,7 @@ impl ModuleHandlers for CoreModuleHandlers {
         && self.has_https
         && config.filters.port.is_none()
         && (get_value!("auto_tls", config).and_then(|v| v.as_bool()).unwrap_or(
-          (config.filters.hostname.is_some()
+          ((config.filters.hostname.is_some() && !config.filters.has_regex_hostname())
             || config.filters.ip.is_some()
             || get_value!("auto_tls_on_demand", config)
               .and_then(|v| v.as_bool())
@@ -0,0 +1,463 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, HeaderMap, Request, Response, StatusCode};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+use crate::util::{CapturingBody, SharedBodyCapture};
+
+/// The interval of persisting transfer accounting counters into a file
+const ACCOUNTING_FILE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Transfer accounting counters, keyed by host names and counter names
+/// (`requests`, `bytes_received` and `bytes_sent`)
+type TransferCounters = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// A storage backend for transfer accounting counters
+pub trait TransferAccountingStore: Send + Sync {
+  /// Records a request with the specified numbers of received and sent bytes
+  fn record(&self, host: &str, bytes_received: u64, bytes_sent: u64);
+
+  /// Obtains the values of all the counters
+  fn usage(&self) -> TransferCounters;
+}
+
+/// An in-memory transfer accounting counter store
+#[derive(Default)]
+pub struct MemoryTransferAccountingStore {
+  counters: Mutex<TransferCounters>,
+}
+
+impl TransferAccountingStore for MemoryTransferAccountingStore {
+  fn record(&self, host: &str, bytes_received: u64, bytes_sent: u64) {
+    let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+    let host_counters = counters.entry(host.to_string()).or_default();
+    for (counter_name, value) in [
+      ("requests", 1),
+      ("bytes_received", bytes_received),
+      ("bytes_sent", bytes_sent),
+    ] {
+      let counter = host_counters.entry(counter_name.to_string()).or_insert(0);
+      *counter = counter.saturating_add(value);
+    }
+  }
+
+  fn usage(&self) -> TransferCounters {
+    self.counters.lock().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+}
+
+/// A transfer accounting counter store persisted into a JSON file
+pub struct FileTransferAccountingStore {
+  inner: MemoryTransferAccountingStore,
+  path: PathBuf,
+  dirty: AtomicBool,
+}
+
+impl FileTransferAccountingStore {
+  /// Creates a file-backed transfer accounting counter store, loading the counters from the file if it exists
+  pub fn new(path: PathBuf) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let counters = match std::fs::read(&path) {
+      Ok(data) => serde_json::from_slice::<TransferCounters>(&data)
+        .map_err(|e| anyhow::anyhow!("Can't parse the transfer accounting counters file: {e}"))?,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => TransferCounters::new(),
+      Err(err) => Err(anyhow::anyhow!(
+        "Can't read the transfer accounting counters file: {err}"
+      ))?,
+    };
+    Ok(Self {
+      inner: MemoryTransferAccountingStore {
+        counters: Mutex::new(counters),
+      },
+      path,
+      dirty: AtomicBool::new(false),
+    })
+  }
+
+  /// Persists the counters into the file, if they have changed
+  pub fn flush(&self) -> Result<(), std::io::Error> {
+    if !self.dirty.swap(false, Ordering::Relaxed) {
+      return Ok(());
+    }
+    let data = serde_json::to_vec(&self.inner.usage())?;
+    let temporary_path = self.path.with_extension("tmp");
+    let result = std::fs::write(&temporary_path, data).and_then(|_| std::fs::rename(&temporary_path, &self.path));
+    if result.is_err() {
+      self.dirty.store(true, Ordering::Relaxed);
+    }
+    result
+  }
+
+  /// Spawns a task periodically persisting the counters, which ends when the store is dropped
+  fn spawn_flush_task(store: &Arc<Self>, runtime: &tokio::runtime::Runtime) {
+    let store: Weak<Self> = Arc::downgrade(store);
+    runtime.spawn(async move {
+      loop {
+        tokio::time::sleep(ACCOUNTING_FILE_FLUSH_INTERVAL).await;
+        let Some(store) = store.upgrade() else {
+          break;
+        };
+        let _ = tokio::task::spawn_blocking(move || store.flush()).await;
+      }
+    });
+  }
+}
+
+impl TransferAccountingStore for FileTransferAccountingStore {
+  fn record(&self, host: &str, bytes_received: u64, bytes_sent: u64) {
+    self.dirty.store(true, Ordering::Relaxed);
+    self.inner.record(host, bytes_received, bytes_sent)
+  }
+
+  fn usage(&self) -> TransferCounters {
+    self.inner.usage()
+  }
+}
+
+impl Drop for FileTransferAccountingStore {
+  fn drop(&mut self) {
+    let _ = self.flush();
+  }
+}
+
+/// Estimates the size of the header fields, as if they were sent over HTTP/1.1
+fn estimate_header_fields_size(headers: &HeaderMap) -> u64 {
+  headers
+    .iter()
+    .map(|(name, value)| (name.as_str().len() + value.len() + 4) as u64)
+    .sum::<u64>()
+    + 2
+}
+
+/// Parses the transfer accounting counter store configuration
+fn parse_accounting_store_config(config: &ServerConfiguration) -> Option<PathBuf> {
+  get_entry!("transfer_accounting_store", config).and_then(|entry| {
+    if entry.values.first().and_then(|v| v.as_str()) == Some("file") {
+      entry.props.get("path").and_then(|v| v.as_str()).map(PathBuf::from)
+    } else {
+      None
+    }
+  })
+}
+
+/// A transfer accounting module loader
+pub struct TransferAccountingModuleLoader {
+  cache: ModuleCache<TransferAccountingModule>,
+  memory_store: Arc<MemoryTransferAccountingStore>,
+  file_stores: HashMap<PathBuf, Weak<FileTransferAccountingStore>>,
+}
+
+impl Default for TransferAccountingModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TransferAccountingModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["transfer_accounting_store"]),
+      memory_store: Arc::new(MemoryTransferAccountingStore::default()),
+      file_stores: HashMap::new(),
+    }
+  }
+
+  /// Obtains a file-backed transfer accounting counter store, sharing it across modules using the same file
+  fn get_file_store(
+    &mut self,
+    path: &Path,
+    runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<FileTransferAccountingStore>, Box<dyn Error + Send + Sync>> {
+    if let Some(store) = self.file_stores.get(path).and_then(|store| store.upgrade()) {
+      return Ok(store);
+    }
+    let store = Arc::new(FileTransferAccountingStore::new(path.to_path_buf())?);
+    FileTransferAccountingStore::spawn_flush_task(&store, runtime);
+    self.file_stores.retain(|_, store| store.strong_count() > 0);
+    self.file_stores.insert(path.to_path_buf(), Arc::downgrade(&store));
+    Ok(store)
+  }
+}
+
+impl ModuleLoader for TransferAccountingModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    let store: Arc<dyn TransferAccountingStore> = match parse_accounting_store_config(config) {
+      Some(path) => self.get_file_store(&path, secondary_runtime)?,
+      None => self.memory_store.clone(),
+    };
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| {
+          Ok(Arc::new(TransferAccountingModule { store }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["transfer_accounting", "transfer_accounting_usage"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("transfer_accounting", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `transfer_accounting` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid transfer accounting enabling option"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("transfer_accounting_store", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `transfer_accounting_store` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(entry.values[0].as_str(), Some("memory") | Some("file")) {
+          Err(anyhow::anyhow!("Invalid transfer accounting counter store"))?
+        } else if entry.values[0].as_str() == Some("file") && !entry.props.get("path").is_some_and(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The transfer accounting counter file store must have a path specified"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("transfer_accounting_usage", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `transfer_accounting_usage` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid transfer accounting usage export enabling option"
+          ))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// A transfer accounting module
+struct TransferAccountingModule {
+  store: Arc<dyn TransferAccountingStore>,
+}
+
+impl Module for TransferAccountingModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(TransferAccountingModuleHandlers {
+      store: self.store.clone(),
+      pending: None,
+      metrics_sender: None,
+    })
+  }
+}
+
+/// A request, for which the transferred bytes are being counted
+struct PendingTransfer {
+  host: String,
+  request_header_size: u64,
+  request_body: SharedBodyCapture,
+}
+
+/// Handlers for the transfer accounting module
+struct TransferAccountingModuleHandlers {
+  store: Arc<dyn TransferAccountingStore>,
+  pending: Option<PendingTransfer>,
+  metrics_sender: Option<MetricsMultiSender>,
+}
+
+/// A guard recording the transferred bytes, when the response body is dropped
+struct TransferAccountingGuard {
+  pending: PendingTransfer,
+  response_header_size: u64,
+  response_body: SharedBodyCapture,
+  store: Arc<dyn TransferAccountingStore>,
+  metrics_sender: Option<MetricsMultiSender>,
+}
+
+impl Drop for TransferAccountingGuard {
+  fn drop(&mut self) {
+    let body_size = |capture: &SharedBodyCapture| capture.lock().unwrap_or_else(|e| e.into_inner()).size;
+    let bytes_received = self.pending.request_header_size + body_size(&self.pending.request_body);
+    let bytes_sent = self.response_header_size + body_size(&self.response_body);
+    self.store.record(&self.pending.host, bytes_received, bytes_sent);
+
+    if let Some(metrics_sender) = &self.metrics_sender {
+      for (name, value, unit, description) in [
+        (
+          "ferron.transfer.requests",
+          1,
+          "{request}",
+          "Number of requests counted by the transfer accounting.",
+        ),
+        (
+          "ferron.transfer.bytes_received",
+          bytes_received,
+          "By",
+          "Number of bytes received from clients.",
+        ),
+        (
+          "ferron.transfer.bytes_sent",
+          bytes_sent,
+          "By",
+          "Number of bytes sent to clients.",
+        ),
+      ] {
+        metrics_sender.try_send(Metric::new(
+          name,
+          vec![(
+            "server.address",
+            MetricAttributeValue::String(self.pending.host.clone()),
+          )],
+          MetricType::Counter,
+          MetricValue::U64(value),
+          Some(unit),
+          Some(description),
+        ));
+      }
+    }
+  }
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for TransferAccountingModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    if get_value!("transfer_accounting_usage", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      let mut usage = self.store.usage();
+      // The usage can be narrowed down to a single host with the `host` query parameter
+      if let Some(host) = request.uri().query().and_then(|query| {
+        query.split('&').find_map(|parameter| {
+          let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+          (name == "host").then(|| urlencoding::decode(value).ok().map(|v| v.into_owned()))?
+        })
+      }) {
+        usage.retain(|usage_host, _| *usage_host == host);
+      }
+      let usage = serde_json::to_vec(&usage)?;
+      return Ok(ResponseData {
+        request: Some(request),
+        response: Some(
+          Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(Full::new(Bytes::from(usage)).map_err(|e| match e {}).boxed())?,
+        ),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    if !get_value!("transfer_accounting", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let (request_parts, request_body) = request.into_parts();
+    // The request line is estimated as if the request was sent over HTTP/1.1
+    let request_line_size = request_parts.method.as_str().len() + request_parts.uri.to_string().len() + 11;
+    let request_body_capture = SharedBodyCapture::default();
+    self.pending = Some(PendingTransfer {
+      host: config.filters.hostname.clone().unwrap_or_else(|| "*".to_string()),
+      request_header_size: request_line_size as u64 + estimate_header_fields_size(&request_parts.headers),
+      request_body: request_body_capture.clone(),
+    });
+    // The request body data isn't captured, only the body size is counted
+    let request_body = CapturingBody::new(request_body, request_body_capture, 0, None::<()>).boxed();
+
+    Ok(ResponseData {
+      request: Some(Request::from_parts(request_parts, request_body)),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    let Some(pending) = self.pending.take() else {
+      return Ok(response);
+    };
+
+    let (response_parts, response_body) = response.into_parts();
+    // The status line is estimated as if the response was sent over HTTP/1.1
+    let status_line_size = response_parts
+      .status
+      .canonical_reason()
+      .map_or(0, |reason| reason.len())
+      + 15;
+    let response_body_capture = SharedBodyCapture::default();
+    let guard = TransferAccountingGuard {
+      pending,
+      response_header_size: status_line_size as u64 + estimate_header_fields_size(&response_parts.headers),
+      response_body: response_body_capture.clone(),
+      store: self.store.clone(),
+      metrics_sender: self.metrics_sender.take(),
+    };
+    let response_body = CapturingBody::new(response_body, response_body_capture, 0, Some(guard)).boxed();
+
+    Ok(Response::from_parts(response_parts, response_body))
+  }
+
+  async fn metric_data_before_handler(
+    &mut self,
+    _request: &Request<BoxBody<Bytes, std::io::Error>>,
+    _socket_data: &SocketData,
+    metrics_sender: &MetricsMultiSender,
+  ) {
+    self.metrics_sender = Some(metrics_sender.clone());
+  }
+}
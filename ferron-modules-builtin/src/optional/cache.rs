@@ -1,16 +1,17 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, LazyLock};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::util::{Cachability, CacheControl};
+use crate::util::{normalize_vary_header_value, Cachability, CacheControl, TeeBody};
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::DateTime;
 use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue};
-use futures_util::stream::{StreamExt, TryStreamExt};
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Full, StreamBody};
+use http_body_util::{BodyExt, Empty, Full, StreamBody};
 use hyper::body::Frame;
 use hyper::header::{self, HeaderName, HeaderValue};
 use hyper::{HeaderMap, Method, Request, Response, StatusCode};
@@ -19,20 +20,36 @@ use smallvec::SmallVec;
 use ferron_common::logging::ErrorLogger;
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
 use ferron_common::observability::MetricsMultiSender;
+use ferron_common::util::{
+  is_streaming_response, register_scheduled_task, MemoryReservation, ScheduledTaskHandler, MEMORY_BUDGET,
+};
 use ferron_common::{config::ServerConfiguration, util::ModuleCache};
 use ferron_common::{get_entries_for_validation, get_entry, get_value, get_values};
 
 // Default cache size limits
 const DEFAULT_MAX_CACHE_RESPONSE_SIZE: u64 = 2097152; // 2 MB
 const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+const DEFAULT_MAX_CACHE_VARIANTS: usize = 16;
 
 // Constants for optimization
 const CACHE_HEADER_NAME: &str = "X-Ferron-Cache";
+const CACHE_DEBUG_HEADER_NAME: &str = "X-Cache";
 const DEFAULT_MAX_AGE: u64 = 300;
+const DEFAULT_STALE_ON_ERROR_MAX: u64 = 3600;
+const STALE_ON_ERROR_WARNING: &str = "111 - \"Revalidation Failed\"";
 const INITIAL_CACHE_KEY_CAPACITY: usize = 256;
-const INITIAL_RESPONSE_BUFFER_CAPACITY: usize = 16384; // Increased for better chunking
 const MAX_SMALL_HEADER_COUNT: usize = 16;
 
+// Headers sent in "304 Not Modified" responses generated from cached response metadata
+static NOT_MODIFIED_HEADERS: [HeaderName; 6] = [
+  header::CACHE_CONTROL,
+  header::CONTENT_LOCATION,
+  header::DATE,
+  header::ETAG,
+  header::EXPIRES,
+  header::VARY,
+];
+
 // Pre-computed header names for faster lookups
 static CACHE_CONTROL_HEADER: LazyLock<HeaderName> = LazyLock::new(|| header::CACHE_CONTROL);
 static HOST_HEADER: LazyLock<HeaderName> = LazyLock::new(|| header::HOST);
@@ -45,7 +62,17 @@ const HTTP_PREFIX: &str = "http://";
 const HTTPS_PREFIX: &str = "https://";
 
 type HeaderList = SmallVec<[String; MAX_SMALL_HEADER_COUNT]>;
-type CacheEntry = (StatusCode, HeaderMap, Vec<u8>, Instant, Option<Arc<CacheControl>>);
+type CacheEntry = (
+  StatusCode,
+  HeaderMap,
+  CachedBody,
+  Instant,
+  Option<Arc<CacheControl>>,
+  Duration,
+);
+/// The body of a cached response, stored as the data chunks received from the backend server
+type CachedBody = Arc<CachedChunks>;
+type VaryCache = quick_cache::sync::Cache<String, Arc<VaryEntry>>;
 type CacheInner = quick_cache::sync::Cache<
   String,
   CacheEntry,
@@ -54,6 +81,54 @@ type CacheInner = quick_cache::sync::Cache<
   CustomLifecycle<String, CacheEntry>,
 >;
 
+/// The result of a cache lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheResult {
+  /// The response was served from the cache
+  Hit,
+
+  /// The response wasn't found in the cache
+  Miss,
+
+  /// The response was found in the cache, but it was expired
+  Stale,
+
+  /// The cache was bypassed for the request
+  Bypass,
+
+  /// The expired response was served, because the backend servers were unavailable
+  StaleOnError,
+
+  /// The expired response was served, because the backend server confirmed it's still valid
+  Revalidated,
+}
+
+impl CacheResult {
+  /// Obtains the value of the metric attribute for the cache lookup result
+  fn as_metric_value(&self) -> &'static str {
+    match self {
+      Self::Hit => "hit",
+      Self::Miss => "miss",
+      Self::Stale => "stale",
+      Self::Bypass => "bypass",
+      Self::StaleOnError => "stale_on_error",
+      Self::Revalidated => "revalidated",
+    }
+  }
+
+  /// Obtains the value of the cache response header for the cache lookup result
+  fn as_header_value(&self) -> HeaderValue {
+    HeaderValue::from_static(match self {
+      Self::Hit => "HIT",
+      Self::Miss => "MISS",
+      Self::Stale => "STALE",
+      Self::Bypass => "BYPASS",
+      Self::StaleOnError => "STALE-ON-ERROR",
+      Self::Revalidated => "REVALIDATED",
+    })
+  }
+}
+
 /// Optimized cache decision with bitflags for faster comparisons
 #[derive(Debug, Clone, Copy)]
 struct CacheDecision {
@@ -127,52 +202,18 @@ impl CacheDecision {
   }
 }
 
-/// High-performance response body handler with memory pooling
-struct ResponseBodyHandler {
-  max_size: Option<u64>,
-  buffer: Vec<u8>,
-  total_size: usize,
+/// Request headers varying the cached responses for a URL, along with the cache keys of stored variants
+struct VaryEntry {
+  headers: HeaderList,
+  variants: Mutex<Vec<String>>,
 }
 
-impl ResponseBodyHandler {
-  fn new(max_size: Option<u64>) -> Self {
+impl VaryEntry {
+  fn new(headers: HeaderList) -> Self {
     Self {
-      max_size,
-      buffer: Vec::with_capacity(INITIAL_RESPONSE_BUFFER_CAPACITY),
-      total_size: 0,
-    }
-  }
-
-  #[inline]
-  async fn collect_body(&mut self, body: &mut BoxBody<Bytes, std::io::Error>) -> Result<bool, Box<dyn Error>> {
-    while let Some(frame) = body.frame().await {
-      let frame = frame?;
-      if let Some(bytes) = frame.data_ref() {
-        let chunk_size = bytes.len();
-
-        // Check size limit before allocation
-        if let Some(max_size) = self.max_size {
-          if self.total_size + chunk_size > max_size as usize {
-            return Ok(false);
-          }
-        }
-
-        // Reserve capacity in larger chunks to reduce allocations
-        if self.buffer.capacity() < self.total_size + chunk_size {
-          let new_capacity = (self.total_size + chunk_size).next_power_of_two();
-          self.buffer.reserve(new_capacity - self.buffer.capacity());
-        }
-
-        self.buffer.extend_from_slice(bytes);
-        self.total_size += chunk_size;
-      }
+      headers,
+      variants: Mutex::new(Vec::new()),
     }
-    Ok(true)
-  }
-
-  fn into_bytes(mut self) -> Vec<u8> {
-    self.buffer.shrink_to_fit(); // Reclaim unused capacity
-    self.buffer
   }
 }
 
@@ -188,7 +229,7 @@ impl VaryKeyBuilder {
     }
   }
 
-  fn build(&mut self, base_key: &str, vary_headers: &[String], request_headers: &HeaderMap) -> &str {
+  fn build(&mut self, base_key: &str, vary_headers: &[String], request_headers: &HeaderMap, normalize: bool) -> &str {
     self.buffer.clear();
     self.buffer.push_str(base_key);
     self.buffer.push('\n');
@@ -203,7 +244,13 @@ impl VaryKeyBuilder {
 
       if let Some(header_value) = request_headers.get(header_name) {
         if let Ok(str_val) = header_value.to_str() {
-          self.buffer.push_str(str_val);
+          match normalize
+            .then(|| normalize_vary_header_value(header_name, str_val))
+            .flatten()
+          {
+            Some(normalized_val) => self.buffer.push_str(&normalized_val),
+            None => self.buffer.push_str(str_val),
+          }
         } else {
           // Fallback to lossy conversion
           self.buffer.push_str(&String::from_utf8_lossy(header_value.as_bytes()));
@@ -266,6 +313,99 @@ fn build_cache_key(method: &Method, encrypted: bool, host: &str, path: &str, que
   cache_key
 }
 
+/// An expired cached response that is revalidated with the backend server
+struct RevalidationEntry {
+  cache_key: String,
+  status_code: StatusCode,
+  headers: HeaderMap,
+  body: CachedBody,
+}
+
+/// The data chunks of a cached response, reserved in the global memory budget until the cached response is evicted
+struct CachedChunks {
+  chunks: Box<[Bytes]>,
+  _reservation: MemoryReservation,
+}
+
+impl Deref for CachedChunks {
+  type Target = [Bytes];
+
+  fn deref(&self) -> &Self::Target {
+    &self.chunks
+  }
+}
+
+/// Creates a response body streaming the data chunks of a cached response, without concatenating them
+fn cached_response_body(body: CachedBody) -> BoxBody<Bytes, std::io::Error> {
+  match body.len() {
+    0 => Full::new(Bytes::new()).map_err(|e| match e {}).boxed(),
+    1 => Full::new(body[0].clone()).map_err(|e| match e {}).boxed(),
+    chunk_count => {
+      let chunks = (0..chunk_count).map(move |index| Ok::<_, std::io::Error>(Frame::data(body[index].clone())));
+      StreamBody::new(futures_util::stream::iter(chunks)).boxed()
+    }
+  }
+}
+
+/// Checks whether the conditional request headers match the cached response metadata,
+/// so that a "304 Not Modified" response can be sent without the response body
+fn is_not_modified(request_headers: &HeaderMap, cached_headers: &HeaderMap) -> bool {
+  if let Some(if_none_match) = request_headers.get(header::IF_NONE_MATCH) {
+    // If-None-Match takes precedence over If-Modified-Since, and uses the weak comparison
+    let Some(etag) = cached_headers.get(header::ETAG).and_then(|v| v.to_str().ok()) else {
+      return false;
+    };
+    let etag = etag.trim().trim_start_matches("W/");
+    return if_none_match.to_str().is_ok_and(|if_none_match| {
+      if_none_match
+        .split(',')
+        .map(|e| e.trim())
+        .any(|e| e == "*" || e.trim_start_matches("W/") == etag)
+    });
+  }
+
+  let parse_date = |value: &HeaderValue| value.to_str().ok().and_then(|v| DateTime::parse_from_rfc2822(v).ok());
+  match (
+    request_headers.get(header::IF_MODIFIED_SINCE).and_then(parse_date),
+    cached_headers.get(header::LAST_MODIFIED).and_then(parse_date),
+  ) {
+    (Some(if_modified_since), Some(last_modified)) => last_modified <= if_modified_since,
+    _ => false,
+  }
+}
+
+/// Builds a "304 Not Modified" response from the cached response metadata
+fn build_not_modified_response(
+  cached_headers: &HeaderMap,
+) -> Result<Response<BoxBody<Bytes, std::io::Error>>, hyper::http::Error> {
+  let mut response_builder = Response::builder().status(StatusCode::NOT_MODIFIED);
+  for header_name in &NOT_MODIFIED_HEADERS {
+    for header_value in cached_headers.get_all(header_name) {
+      response_builder = response_builder.header(header_name, header_value);
+    }
+  }
+  response_builder.body(Empty::new().map_err(|e| match e {}).boxed())
+}
+
+/// Updates the cached response headers with the headers from a "304 Not Modified" response
+fn update_cached_headers(cached_headers: &mut HeaderMap, not_modified_headers: &HeaderMap) {
+  for header_name in not_modified_headers.keys() {
+    if [
+      header::CONTENT_LENGTH,
+      header::TRANSFER_ENCODING,
+      header::CONTENT_ENCODING,
+    ]
+    .contains(header_name)
+    {
+      continue;
+    }
+    cached_headers.remove(header_name);
+    for header_value in not_modified_headers.get_all(header_name) {
+      cached_headers.append(header_name, header_value.clone());
+    }
+  }
+}
+
 /// Custom lifecycle for the cache module
 #[derive(Clone)]
 struct CustomLifecycle<Key, Val> {
@@ -308,8 +448,9 @@ impl<Key, Val> quick_cache::Lifecycle<Key, Val> for CustomLifecycle<Key, Val> {
 pub struct CacheModuleLoader {
   module_cache: ModuleCache<CacheModule>,
   caches_to_drop: Vec<Arc<CacheInner>>,
-  vary_caches_to_drop: Vec<Arc<quick_cache::sync::Cache<String, Arc<HeaderList>>>>,
+  vary_caches_to_drop: Vec<Arc<VaryCache>>,
   dont_register_evictions: Arc<AtomicBool>,
+  scheduled_task_handlers: Vec<Arc<ScheduledTaskHandler>>,
 }
 
 impl Default for CacheModuleLoader {
@@ -326,6 +467,7 @@ impl CacheModuleLoader {
       caches_to_drop: Vec::new(),
       vary_caches_to_drop: Vec::new(),
       dont_register_evictions: Arc::new(AtomicBool::new(false)),
+      scheduled_task_handlers: Vec::new(),
     }
   }
 }
@@ -378,10 +520,21 @@ impl ModuleLoader for CacheModuleLoader {
           self.caches_to_drop.push(cache.clone());
           self.vary_caches_to_drop.push(vary_cache.clone());
 
+          // The expired entries can be also removed by the `cache_prune` scheduled task
+          let cache_weak = Arc::downgrade(&cache);
+          let cache_prune_handler: Arc<ScheduledTaskHandler> = Arc::new(move || {
+            if let Some(cache) = cache_weak.upgrade() {
+              remove_expired_cache_entries(&cache);
+            }
+          });
+          register_scheduled_task("cache_prune", &cache_prune_handler);
+          self.scheduled_task_handlers.push(cache_prune_handler);
+
           Ok(Arc::new(CacheModule {
             cache,
             vary_cache,
             track_evictions,
+            track_stores: Arc::new(Mutex::new(HashMap::new())),
             dont_register_evictions: self.dont_register_evictions.clone(),
           }))
         })?,
@@ -428,9 +581,7 @@ impl ModuleLoader for CacheModuleLoader {
           return Err(
             anyhow::anyhow!("The `cache_max_response_size` configuration property must have exactly one value").into(),
           );
-        } else if (!entry.values[0].is_integer() && !entry.values[0].is_null())
-          || entry.values[0].as_i128().is_some_and(|v| v < 0)
-        {
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
           return Err(anyhow::anyhow!("Invalid maximum cache response size configuration").into());
         }
       }
@@ -446,6 +597,32 @@ impl ModuleLoader for CacheModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("cache_vary_normalize", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `cache_vary_normalize` configuration property must have exactly one value").into(),
+          );
+        } else if !entry.values[0].is_bool() {
+          return Err(anyhow::anyhow!("Invalid varying request header normalization enabling option").into());
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("cache_max_variants", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `cache_max_variants` configuration property must have exactly one value").into(),
+          );
+        } else if (!entry.values[0].is_integer() && !entry.values[0].is_null())
+          || entry.values[0].as_i128().is_some_and(|v| v < 1)
+        {
+          return Err(anyhow::anyhow!("Invalid maximum cached response variants configuration").into());
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("cache_ignore", config, used_properties) {
       for entry in &entries.inner {
         for value in &entry.values {
@@ -456,10 +633,84 @@ impl ModuleLoader for CacheModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("serve_stale_on_error", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `serve_stale_on_error` configuration property must have exactly one value").into(),
+          );
+        } else if !entry.values[0].is_bool() {
+          return Err(anyhow::anyhow!("Invalid stale response serving on errors enabling option").into());
+        } else if let Some(prop) = entry.props.get("max") {
+          if prop.as_duration().is_none() {
+            return Err(anyhow::anyhow!("Invalid maximum staleness of responses served on errors").into());
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("cache_revalidate", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `cache_revalidate` configuration property must have exactly one value").into(),
+          );
+        } else if !entry.values[0].is_bool() {
+          return Err(anyhow::anyhow!("Invalid cache revalidation enabling option").into());
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("cache_debug_header", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `cache_debug_header` configuration property must have exactly one value").into(),
+          );
+        } else if !entry.values[0].is_bool() {
+          return Err(anyhow::anyhow!("Invalid cache debug header enabling option").into());
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("strip_request_cookies", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `strip_request_cookies` configuration property must have exactly one value").into(),
+          );
+        } else if !entry.values[0].is_bool() {
+          return Err(anyhow::anyhow!("Invalid request cookie stripping enabling option").into());
+        }
+      }
+    }
+
     Ok(())
   }
 }
 
+/// Removes the expired entries from the cache, and returns the number of removed entries
+fn remove_expired_cache_entries(cache: &CacheInner) -> usize {
+  let default_max_age = Duration::from_secs(DEFAULT_MAX_AGE);
+  let now = Instant::now();
+
+  let evictions = AtomicUsize::new(0);
+  cache.retain(|_, (_, _, _, timestamp, cache_control, stale_on_error_max)| {
+    let max_age = cache_control
+      .as_ref()
+      .and_then(|cc| cc.s_max_age.or(cc.max_age))
+      .unwrap_or(default_max_age);
+
+    // Expired entries are kept for a while if they can be served when the backend servers are unavailable
+    let keep = now.duration_since(*timestamp) <= max_age.saturating_add(*stale_on_error_max);
+    if !keep {
+      evictions.fetch_add(1, Ordering::Relaxed);
+    }
+    keep
+  });
+  evictions.into_inner()
+}
+
 impl Drop for CacheModuleLoader {
   fn drop(&mut self) {
     self.dont_register_evictions.store(true, Ordering::Relaxed);
@@ -475,8 +726,9 @@ impl Drop for CacheModuleLoader {
 /// A cache module with optimized data structures
 struct CacheModule {
   cache: Arc<CacheInner>,
-  vary_cache: Arc<quick_cache::sync::Cache<String, Arc<HeaderList>>>,
+  vary_cache: Arc<VaryCache>,
   track_evictions: Arc<AtomicUsize>,
+  track_stores: Arc<Mutex<HashMap<String, u64>>>,
   dont_register_evictions: Arc<AtomicBool>,
 }
 
@@ -492,10 +744,22 @@ impl Module for CacheModule {
       request_headers: HeaderMap::new(),
       has_authorization: false,
       cached: false,
+      stale: false,
+      stale_response: None,
+      stale_on_error_max: None,
+      revalidate: true,
+      revalidation: None,
+      vary_normalize: true,
+      max_variants: Some(DEFAULT_MAX_CACHE_VARIANTS),
       no_store: false,
-      metric_cache_hit: None,
+      strip_request_cookies: false,
+      debug_header: false,
+      metric_host: None,
+      metric_cache_result: None,
       metric_cache_evictions_expired: None,
+      error_logger: ErrorLogger::without_logger(),
       track_evictions: self.track_evictions.clone(),
+      track_stores: self.track_stores.clone(),
       dont_register_evictions: self.dont_register_evictions.clone(),
     })
   }
@@ -504,7 +768,7 @@ impl Module for CacheModule {
 /// Optimized handlers for the cache module
 struct CacheModuleHandlers {
   cache: Arc<CacheInner>,
-  vary_cache: Arc<quick_cache::sync::Cache<String, Arc<HeaderList>>>,
+  vary_cache: Arc<VaryCache>,
   cache_vary_headers_configured: HeaderList,
   cache_ignore_headers_configured: HeaderList,
   maximum_cached_response_size: Option<u64>,
@@ -512,10 +776,22 @@ struct CacheModuleHandlers {
   request_headers: HeaderMap<HeaderValue>,
   has_authorization: bool,
   cached: bool,
+  stale: bool,
+  stale_response: Option<(StatusCode, HeaderMap, CachedBody)>,
+  stale_on_error_max: Option<Duration>,
+  revalidate: bool,
+  revalidation: Option<RevalidationEntry>,
+  vary_normalize: bool,
+  max_variants: Option<usize>,
   no_store: bool,
-  metric_cache_hit: Option<bool>,
+  strip_request_cookies: bool,
+  debug_header: bool,
+  metric_host: Option<String>,
+  metric_cache_result: Option<CacheResult>,
   metric_cache_evictions_expired: Option<usize>,
+  error_logger: ErrorLogger,
   track_evictions: Arc<AtomicUsize>,
+  track_stores: Arc<Mutex<HashMap<String, u64>>>,
   dont_register_evictions: Arc<AtomicBool>,
 }
 
@@ -543,30 +819,64 @@ impl CacheModuleHandlers {
       if v.is_null() {
         None
       } else {
-        Some(v.as_i128().map(|f| f as u64).unwrap_or(DEFAULT_MAX_CACHE_RESPONSE_SIZE))
+        Some(v.as_size().unwrap_or(DEFAULT_MAX_CACHE_RESPONSE_SIZE))
+      }
+    });
+
+    self.vary_normalize = get_value!("cache_vary_normalize", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(true);
+
+    self.max_variants = get_value!("cache_max_variants", config).map_or(Some(DEFAULT_MAX_CACHE_VARIANTS), |v| {
+      if v.is_null() {
+        None
+      } else {
+        Some(v.as_i128().map(|v| v as usize).unwrap_or(DEFAULT_MAX_CACHE_VARIANTS))
       }
     });
+
+    self.revalidate = get_value!("cache_revalidate", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(true);
+
+    self.debug_header = get_value!("cache_debug_header", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+
+    self.strip_request_cookies = get_value!("strip_request_cookies", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+
+    self.stale_on_error_max = get_entry!("serve_stale_on_error", config).and_then(|e| {
+      if e.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+        Some(
+          e.props
+            .get("max")
+            .and_then(|v| v.as_duration())
+            .unwrap_or(Duration::from_secs(DEFAULT_STALE_ON_ERROR_MAX)),
+        )
+      } else {
+        None
+      }
+    });
+
+    // Use the configured hostname (and not the "Host" header) to keep the metric cardinality bounded
+    self.metric_host = Some(config.filters.hostname.clone().unwrap_or_else(|| "*".to_string()));
+  }
+
+  /// Sets the cache response headers for the cache lookup result
+  #[inline]
+  fn set_cache_headers(&mut self, headers: &mut HeaderMap, cache_result: CacheResult) {
+    headers.insert(CACHE_HEADER_NAME, cache_result.as_header_value());
+    if self.debug_header {
+      headers.insert(CACHE_DEBUG_HEADER_NAME, cache_result.as_header_value());
+    }
+    self.metric_cache_result = Some(cache_result);
   }
 
   /// Optimized cache cleanup with batching (returns number of removed entries)
   fn cleanup_expired_entries(&self) -> usize {
-    let default_max_age = Duration::from_secs(DEFAULT_MAX_AGE);
-    let now = Instant::now();
-
-    let evictions = AtomicUsize::new(0);
-    self.cache.retain(|_, (_, _, _, timestamp, cache_control)| {
-      let max_age = cache_control
-        .as_ref()
-        .and_then(|cc| cc.s_max_age.or(cc.max_age))
-        .unwrap_or(default_max_age);
-
-      let keep = now.duration_since(*timestamp) <= max_age;
-      if !keep {
-        evictions.fetch_add(1, Ordering::Relaxed);
-      }
-      keep
-    });
-    evictions.into_inner()
+    remove_expired_cache_entries(&self.cache)
   }
 
   /// Fast cache control evaluation
@@ -593,13 +903,19 @@ impl CacheModuleHandlers {
 impl ModuleHandlers for CacheModuleHandlers {
   async fn request_handler(
     &mut self,
-    request: Request<BoxBody<Bytes, std::io::Error>>,
+    mut request: Request<BoxBody<Bytes, std::io::Error>>,
     config: &ServerConfiguration,
     socket_data: &SocketData,
-    _error_logger: &ErrorLogger,
+    error_logger: &ErrorLogger,
   ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
     // Extract configuration once per request
     self.extract_cache_config(config);
+    self.error_logger = error_logger.clone();
+
+    // Strip the cookies, so that backend servers produce responses that can be shared between clients
+    if self.strip_request_cookies {
+      request.headers_mut().remove(header::COOKIE);
+    }
 
     // Fast cache decision
     let cache_decision = CacheDecision::from_request(&request);
@@ -623,16 +939,16 @@ impl ModuleHandlers for CacheModuleHandlers {
 
     // Check cache only if not no-cache
     if !cache_decision.no_cache() {
-      if let Some(processed_vary) = self.vary_cache.get(&cache_key) {
+      if let Some(vary_entry) = self.vary_cache.get(&cache_key) {
         // Use thread-local builder for vary key
         let cache_key_with_vary = VARY_KEY_BUILDER.with(|builder| {
           builder
             .borrow_mut()
-            .build(&cache_key, &processed_vary, request.headers())
+            .build(&cache_key, &vary_entry.headers, request.headers(), self.vary_normalize)
             .to_string()
         });
 
-        if let Some((status_code, headers, body, timestamp, response_cache_control)) =
+        if let Some((status_code, headers, body, timestamp, response_cache_control, _)) =
           self.cache.get(&cache_key_with_vary)
         {
           let max_age = response_cache_control
@@ -640,16 +956,43 @@ impl ModuleHandlers for CacheModuleHandlers {
             .and_then(|cc| cc.s_max_age.or(cc.max_age))
             .unwrap_or(Duration::from_secs(DEFAULT_MAX_AGE));
 
-          if timestamp.elapsed() <= max_age {
+          if timestamp.elapsed() > max_age {
+            self.stale = true;
+            if let Some(stale_on_error_max) = self.stale_on_error_max {
+              if timestamp.elapsed() <= max_age.saturating_add(stale_on_error_max) {
+                // Keep the expired response in case the backend servers are unavailable
+                self.stale_response = Some((status_code, headers.clone(), body.clone()));
+              }
+            }
+            if self.revalidate && (headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED)) {
+              // Keep the expired response in case the backend server confirms it's still valid
+              self.revalidation = Some(RevalidationEntry {
+                cache_key: cache_key_with_vary,
+                status_code,
+                headers,
+                body,
+              });
+            }
+          } else {
             self.cached = true;
 
+            // Answer conditional requests from the cached response metadata
+            if status_code == StatusCode::OK && is_not_modified(request.headers(), &headers) {
+              return Ok(ResponseData {
+                request: Some(request),
+                response: Some(build_not_modified_response(&headers)?),
+                response_status: None,
+                response_headers: None,
+                new_remote_address: None,
+              });
+            }
+
             let mut hyper_response_builder = Response::builder().status(status_code);
             for (header_name, header_value) in headers.iter() {
               hyper_response_builder = hyper_response_builder.header(header_name, header_value);
             }
 
-            let hyper_response =
-              hyper_response_builder.body(Full::new(Bytes::from(body)).map_err(|e| match e {}).boxed())?;
+            let hyper_response = hyper_response_builder.body(cached_response_body(body))?;
 
             return Ok(ResponseData {
               request: Some(request),
@@ -668,6 +1011,19 @@ impl ModuleHandlers for CacheModuleHandlers {
     self.cache_key = Some(cache_key);
     self.has_authorization = request.headers().contains_key(&*AUTHORIZATION_HEADER);
 
+    if let Some(revalidation) = self.revalidation.as_ref() {
+      // Revalidate the expired response with the cached response validators instead of the client's ones
+      let request_headers = request.headers_mut();
+      request_headers.remove(header::IF_NONE_MATCH);
+      request_headers.remove(header::IF_MODIFIED_SINCE);
+      if let Some(etag) = revalidation.headers.get(header::ETAG) {
+        request_headers.insert(header::IF_NONE_MATCH, etag.clone());
+      }
+      if let Some(last_modified) = revalidation.headers.get(header::LAST_MODIFIED) {
+        request_headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+      }
+    }
+
     Ok(ResponseData {
       request: Some(request),
       response: None,
@@ -683,26 +1039,101 @@ impl ModuleHandlers for CacheModuleHandlers {
   ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
     // Fast path for common cases
     if self.no_store {
-      response
-        .headers_mut()
-        .insert(CACHE_HEADER_NAME, HeaderValue::from_static("BYPASS"));
+      self.set_cache_headers(response.headers_mut(), CacheResult::Bypass);
       return Ok(response);
     }
 
     if self.cached {
-      response
-        .headers_mut()
-        .insert(CACHE_HEADER_NAME, HeaderValue::from_static("HIT"));
-      self.metric_cache_hit = Some(true);
+      self.set_cache_headers(response.headers_mut(), CacheResult::Hit);
       return Ok(response);
-    } else {
-      self.metric_cache_hit = Some(false);
     }
 
-    let Some(cache_key) = &self.cache_key else {
+    if let Some((status_code, headers, body)) = self.stale_response.take() {
+      if matches!(
+        response.status(),
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+      ) {
+        self
+          .error_logger
+          .log(&format!(
+            "Backend servers are unavailable (status code {}), serving a stale cached response in degraded mode",
+            response.status().as_u16()
+          ))
+          .await;
+
+        let mut hyper_response = Response::new(cached_response_body(body));
+        *hyper_response.status_mut() = status_code;
+        *hyper_response.headers_mut() = headers;
+        hyper_response
+          .headers_mut()
+          .append(header::WARNING, HeaderValue::from_static(STALE_ON_ERROR_WARNING));
+        self.set_cache_headers(hyper_response.headers_mut(), CacheResult::StaleOnError);
+        return Ok(hyper_response);
+      }
+    }
+
+    if let Some(revalidation) = self.revalidation.take() {
+      if response.status() == StatusCode::NOT_MODIFIED {
+        // The backend server confirmed the expired response is still valid, so refresh the cached response
+        let RevalidationEntry {
+          cache_key,
+          status_code,
+          mut headers,
+          body,
+        } = revalidation;
+        update_cached_headers(&mut headers, response.headers());
+        for header in &self.cache_ignore_headers_configured {
+          headers.remove(header);
+        }
+        headers.remove(CACHE_HEADER_NAME);
+        headers.remove(CACHE_DEBUG_HEADER_NAME);
+        let response_cache_control = headers
+          .get(&*CACHE_CONTROL_HEADER)
+          .and_then(|value| value.to_str().ok())
+          .and_then(CacheControl::from_value);
+        if self.should_cache_response(&response_cache_control, self.has_authorization) {
+          self.cache.insert(
+            cache_key,
+            (
+              status_code,
+              headers.clone(),
+              body.clone(),
+              Instant::now(),
+              response_cache_control.map(Arc::new),
+              self.stale_on_error_max.unwrap_or_default(),
+            ),
+          );
+        }
+
+        let mut hyper_response = if status_code == StatusCode::OK && is_not_modified(&self.request_headers, &headers) {
+          build_not_modified_response(&headers)?
+        } else {
+          let mut hyper_response = Response::new(cached_response_body(body));
+          *hyper_response.status_mut() = status_code;
+          *hyper_response.headers_mut() = headers;
+          hyper_response
+        };
+        self.set_cache_headers(hyper_response.headers_mut(), CacheResult::Revalidated);
+        return Ok(hyper_response);
+      }
+    }
+
+    let cache_result = if self.stale {
+      CacheResult::Stale
+    } else {
+      CacheResult::Miss
+    };
+
+    let Some(cache_key) = self.cache_key.clone() else {
       return Ok(response);
     };
 
+    if is_streaming_response(&response) {
+      // Streaming responses (like Server-Sent Events) might never end, so they can't be cached
+      self.set_cache_headers(response.headers_mut(), CacheResult::Bypass);
+      return Ok(response);
+    }
+
     let (mut response_parts, mut response_body) = response.into_parts();
 
     // Fast cache control parsing
@@ -712,29 +1143,11 @@ impl ModuleHandlers for CacheModuleHandlers {
       .and_then(|value| value.to_str().ok())
       .and_then(CacheControl::from_value);
 
-    let should_cache = self.should_cache_response(&response_cache_control, self.has_authorization);
+    // "304 Not Modified" responses for the client's conditional requests don't have a response body to cache
+    let should_cache = response_parts.status != StatusCode::NOT_MODIFIED
+      && self.should_cache_response(&response_cache_control, self.has_authorization);
 
     if should_cache {
-      let mut body_handler = ResponseBodyHandler::new(self.maximum_cached_response_size);
-      let body_collected = body_handler.collect_body(&mut response_body).await?;
-
-      if !body_collected {
-        // Size exceeded, stream response without caching
-        let cached_stream = futures_util::stream::once(async move { Ok(Bytes::from(body_handler.into_bytes())) });
-        let response_stream = response_body.into_data_stream();
-        let chained_stream = cached_stream.chain(response_stream);
-        let stream_body = StreamBody::new(chained_stream.map_ok(Frame::data));
-        let response_body = BodyExt::boxed(stream_body);
-
-        response_parts
-          .headers
-          .insert(CACHE_HEADER_NAME, HeaderValue::from_static("MISS"));
-
-        return Ok(Response::from_parts(response_parts, response_body));
-      }
-
-      let response_body_buffer = body_handler.into_bytes();
-
       // Optimized vary header processing
       let mut processed_vary = self.cache_vary_headers_configured.clone();
 
@@ -743,6 +1156,10 @@ impl ModuleHandlers for CacheModuleHandlers {
           processed_vary.extend(vary_str.split(',').map(|s| s.trim().to_string()));
         }
       }
+      if self.vary_normalize {
+        // Header names are case-insensitive
+        processed_vary.iter_mut().for_each(|h| h.make_ascii_lowercase());
+      }
 
       // Remove duplicates efficiently
       processed_vary.sort_unstable();
@@ -753,13 +1170,10 @@ impl ModuleHandlers for CacheModuleHandlers {
         let cache_key_with_vary = VARY_KEY_BUILDER.with(|builder| {
           builder
             .borrow_mut()
-            .build(cache_key, &processed_vary, &self.request_headers)
+            .build(&cache_key, &processed_vary, &self.request_headers, self.vary_normalize)
             .to_string()
         });
 
-        // Update vary cache
-        self.vary_cache.insert(cache_key.clone(), Arc::new(processed_vary));
-
         // Prepare headers for caching (remove ignored headers)
         let mut written_headers = response_parts.headers.clone();
         for header in &self.cache_ignore_headers_configured {
@@ -772,42 +1186,81 @@ impl ModuleHandlers for CacheModuleHandlers {
           *evictions_expired_set += evictions_expired;
         }
 
-        // Store in cache
-        self.cache.insert(
-          cache_key_with_vary,
-          (
-            response_parts.status,
-            written_headers,
-            response_body_buffer.clone(),
-            Instant::now(),
-            response_cache_control.map(Arc::new),
-          ),
-        );
-        if self.metric_cache_evictions_expired.is_none() {
-          self.track_evictions.store(0, Ordering::Relaxed);
-        }
-      }
+        // The response body is streamed to the client, and the response is stored in the cache
+        // after the whole response body is received from the backend server
+        let cache = self.cache.clone();
+        let vary_cache = self.vary_cache.clone();
+        let max_variants = self.max_variants;
+        let status_code = response_parts.status;
+        let response_cache_control = response_cache_control.map(Arc::new);
+        let stale_on_error_max = self.stale_on_error_max.unwrap_or_default();
+        let track_evictions = self.track_evictions.clone();
+        let track_stores = self.track_stores.clone();
+        let metrics_enabled = self.metric_cache_evictions_expired.is_some();
+        let metric_host = self.metric_host.clone().unwrap_or_else(|| "*".to_string());
+        let store_response = move |chunks: Vec<Bytes>, reservation: MemoryReservation| {
+          // Update vary cache, keeping the stored variants if the varying request headers didn't change
+          let vary_entry = match vary_cache.get(&cache_key) {
+            Some(vary_entry) if vary_entry.headers == processed_vary => vary_entry,
+            _ => {
+              let vary_entry = Arc::new(VaryEntry::new(processed_vary));
+              vary_cache.insert(cache_key, vary_entry.clone());
+              vary_entry
+            }
+          };
+
+          // Bound the number of stored variants per URL, evicting the oldest variants
+          if let Ok(mut variants) = vary_entry.variants.lock() {
+            if !variants.contains(&cache_key_with_vary) {
+              if let Some(max_variants) = max_variants {
+                while variants.len() >= max_variants.max(1) {
+                  let oldest_variant = variants.remove(0);
+                  cache.remove(&oldest_variant);
+                }
+              }
+              variants.push(cache_key_with_vary.clone());
+            }
+          }
 
-      // Create response stream efficiently
-      let cached_stream = futures_util::stream::once(async move { Ok(Bytes::from(response_body_buffer)) });
-      let stream_body = StreamBody::new(cached_stream.map_ok(Frame::data));
-      let response_body = BodyExt::boxed(stream_body);
+          // Store in cache
+          cache.insert(
+            cache_key_with_vary,
+            (
+              status_code,
+              written_headers,
+              Arc::new(CachedChunks {
+                chunks: chunks.into(),
+                _reservation: reservation,
+              }),
+              Instant::now(),
+              response_cache_control,
+              stale_on_error_max,
+            ),
+          );
+          if metrics_enabled {
+            if let Ok(mut stores) = track_stores.lock() {
+              *stores.entry(metric_host).or_default() += 1;
+            }
+          } else {
+            track_evictions.store(0, Ordering::Relaxed);
+          }
+        };
+        response_body = TeeBody::new(
+          response_body,
+          self.maximum_cached_response_size,
+          MEMORY_BUDGET.reserve_for_cache(),
+          store_response,
+        )
+        .boxed();
+      }
 
       // Remove extensions from response parts (to prevent zerocopy from interfering with the cache)
       response_parts.extensions.clear();
+    }
 
-      response_parts
-        .headers
-        .insert(CACHE_HEADER_NAME, HeaderValue::from_static("MISS"));
-
-      Ok(Response::from_parts(response_parts, response_body))
-    } else {
-      response_parts
-        .headers
-        .insert(CACHE_HEADER_NAME, HeaderValue::from_static("MISS"));
+    self.set_cache_headers(&mut response_parts.headers, cache_result);
 
-      Ok(Response::from_parts(response_parts, response_body))
-    }
+    Ok(Response::from_parts(response_parts, response_body))
   }
 
   async fn metric_data_before_handler(
@@ -820,19 +1273,20 @@ impl ModuleHandlers for CacheModuleHandlers {
   }
 
   async fn metric_data_after_handler(&mut self, metrics_sender: &MetricsMultiSender) {
-    if let Some(cache_hit) = self.metric_cache_hit.take() {
+    let metric_host = self.metric_host.take().unwrap_or_else(|| "*".to_string());
+
+    if let Some(cache_result) = self.metric_cache_result.take() {
       // Cache lookups
       metrics_sender
         .send(Metric::new(
           "ferron.cache.lookups",
-          vec![(
-            "ferron.cache.result",
-            MetricAttributeValue::String(if cache_hit {
-              "hit".to_string()
-            } else {
-              "miss".to_string()
-            }),
-          )],
+          vec![
+            (
+              "ferron.cache.result",
+              MetricAttributeValue::String(cache_result.as_metric_value().to_string()),
+            ),
+            ("server.address", MetricAttributeValue::String(metric_host)),
+          ],
           MetricType::Counter,
           MetricValue::U64(1),
           Some("{lookup}"),
@@ -841,6 +1295,25 @@ impl ModuleHandlers for CacheModuleHandlers {
         .await;
     }
 
+    // Cache stores (the responses are stored after their bodies are fully received, so the stores are tracked by the module)
+    let stores = self
+      .track_stores
+      .lock()
+      .map(|mut stores| std::mem::take(&mut *stores))
+      .unwrap_or_default();
+    for (store_host, store_count) in stores {
+      metrics_sender
+        .send(Metric::new(
+          "ferron.cache.stores",
+          vec![("server.address", MetricAttributeValue::String(store_host))],
+          MetricType::Counter,
+          MetricValue::U64(store_count),
+          Some("{item}"),
+          Some("Number of responses stored in the cache."),
+        ))
+        .await;
+    }
+
     // Items in cache
     metrics_sender
       .send(Metric::new(
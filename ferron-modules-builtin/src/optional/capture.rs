@@ -0,0 +1,556 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use async_trait::async_trait;
+use base64::Engine;
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::header::HeaderName;
+use hyper::{header, HeaderMap, Method, Request, Response, StatusCode, Version};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+use crate::util::{CapturingBody, SharedBodyCapture};
+
+/// The default number of request/response exchanges kept in the capture buffer
+const DEFAULT_CAPTURE_BUFFER_SIZE: usize = 100;
+
+/// The default maximum size of captured request and response bodies (in bytes)
+const DEFAULT_MAX_CAPTURED_BODY_SIZE: usize = 65536;
+
+/// A captured request/response exchange
+struct CapturedExchange {
+  started: SystemTime,
+  client_ip: IpAddr,
+  method: Method,
+  url: String,
+  version: Version,
+  request_headers: Vec<(String, String)>,
+  request_body: BodyCaptureSnapshot,
+  status: StatusCode,
+  response_headers: Vec<(String, String)>,
+  response_body: BodyCaptureSnapshot,
+  wait_time_ms: f64,
+  receive_time_ms: f64,
+}
+
+/// A snapshot of a captured body
+struct BodyCaptureSnapshot {
+  data: Vec<u8>,
+  size: u64,
+  truncated: bool,
+}
+
+impl BodyCaptureSnapshot {
+  /// Takes a snapshot of a captured body
+  fn take(capture: &SharedBodyCapture) -> Self {
+    let mut capture = capture.lock().unwrap_or_else(|e| e.into_inner());
+    Self {
+      truncated: capture.is_truncated(),
+      size: capture.size,
+      data: std::mem::take(&mut capture.data),
+    }
+  }
+}
+
+/// A ring buffer holding the most recent captured exchanges
+struct CaptureBuffer {
+  entries: Mutex<VecDeque<CapturedExchange>>,
+  capacity: AtomicUsize,
+}
+
+impl CaptureBuffer {
+  /// Creates an empty capture buffer
+  fn new() -> Self {
+    Self {
+      entries: Mutex::new(VecDeque::new()),
+      capacity: AtomicUsize::new(DEFAULT_CAPTURE_BUFFER_SIZE),
+    }
+  }
+
+  /// Changes the maximum number of captured exchanges, discarding the oldest ones if needed
+  fn set_capacity(&self, capacity: usize) {
+    self.capacity.store(capacity, Ordering::Relaxed);
+    let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    while entries.len() > capacity {
+      entries.pop_front();
+    }
+  }
+
+  /// Adds a captured exchange, discarding the oldest one if the buffer is full
+  fn push(&self, exchange: CapturedExchange) {
+    let capacity = self.capacity.load(Ordering::Relaxed);
+    if capacity == 0 {
+      return;
+    }
+    let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    while entries.len() >= capacity {
+      entries.pop_front();
+    }
+    entries.push_back(exchange);
+  }
+
+  /// Exports the captured exchanges as a HAR (HTTP Archive) document
+  fn to_har(&self) -> serde_json::Value {
+    let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    serde_json::json!({
+      "log": {
+        "version": "1.2",
+        "creator": {
+          "name": "Ferron",
+          "version": env!("CARGO_PKG_VERSION"),
+        },
+        "entries": entries.iter().map(exchange_to_har_entry).collect::<Vec<_>>(),
+      }
+    })
+  }
+}
+
+/// Converts the HTTP version into a string used in HAR documents
+fn version_to_string(version: Version) -> &'static str {
+  match version {
+    Version::HTTP_09 => "HTTP/0.9",
+    Version::HTTP_10 => "HTTP/1.0",
+    Version::HTTP_2 => "HTTP/2",
+    Version::HTTP_3 => "HTTP/3",
+    _ => "HTTP/1.1",
+  }
+}
+
+/// Determines if the header value is redacted in captured exchanges, because the header contains credentials
+fn is_redacted_header(name: &HeaderName) -> bool {
+  name == header::AUTHORIZATION
+    || name == header::COOKIE
+    || name == header::PROXY_AUTHORIZATION
+    || name == header::SET_COOKIE
+}
+
+/// Captures the headers, redacting values of headers containing credentials
+fn capture_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+  headers
+    .iter()
+    .map(|(name, value)| {
+      let value = if is_redacted_header(name) {
+        "[REDACTED]".to_string()
+      } else {
+        String::from_utf8_lossy(value.as_bytes()).into_owned()
+      };
+      (name.to_string(), value)
+    })
+    .collect()
+}
+
+/// Obtains the MIME type from the captured headers
+fn captured_mime_type(headers: &[(String, String)]) -> &str {
+  headers
+    .iter()
+    .find(|(name, _)| name == header::CONTENT_TYPE.as_str())
+    .map_or("", |(_, value)| value.as_str())
+}
+
+/// Converts captured headers into HAR name-value pairs
+fn headers_to_har(headers: &[(String, String)]) -> Vec<serde_json::Value> {
+  headers
+    .iter()
+    .map(|(name, value)| serde_json::json!({ "name": name, "value": value }))
+    .collect()
+}
+
+/// Converts a captured exchange into a HAR entry
+fn exchange_to_har_entry(exchange: &CapturedExchange) -> serde_json::Value {
+  let query_string = exchange
+    .url
+    .split_once('?')
+    .map(|(_, query)| query)
+    .unwrap_or("")
+    .split('&')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+      serde_json::json!({
+        "name": urlencoding::decode(name).map_or_else(|_| name.to_string(), |v| v.into_owned()),
+        "value": urlencoding::decode(value).map_or_else(|_| value.to_string(), |v| v.into_owned()),
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let mut request = serde_json::json!({
+    "method": exchange.method.as_str(),
+    "url": exchange.url,
+    "httpVersion": version_to_string(exchange.version),
+    "cookies": [],
+    "headers": headers_to_har(&exchange.request_headers),
+    "queryString": query_string,
+    "headersSize": -1,
+    "bodySize": exchange.request_body.size,
+  });
+  if exchange.request_body.size > 0 {
+    request["postData"] = serde_json::json!({
+      "mimeType": captured_mime_type(&exchange.request_headers),
+      "text": String::from_utf8_lossy(&exchange.request_body.data),
+    });
+    if exchange.request_body.truncated {
+      request["postData"]["comment"] = "truncated".into();
+    }
+  }
+
+  let mut content = serde_json::json!({
+    "size": exchange.response_body.size,
+    "mimeType": captured_mime_type(&exchange.response_headers),
+  });
+  match std::str::from_utf8(&exchange.response_body.data) {
+    Ok(text) => content["text"] = text.into(),
+    Err(_) => {
+      content["text"] = base64::engine::general_purpose::STANDARD
+        .encode(&exchange.response_body.data)
+        .into();
+      content["encoding"] = "base64".into();
+    }
+  }
+  if exchange.response_body.truncated {
+    content["comment"] = "truncated".into();
+  }
+
+  let redirect_url = exchange
+    .response_headers
+    .iter()
+    .find(|(name, _)| name == header::LOCATION.as_str())
+    .map_or("", |(_, value)| value.as_str());
+
+  serde_json::json!({
+    "startedDateTime": DateTime::<Utc>::from(exchange.started).to_rfc3339_opts(SecondsFormat::Millis, true),
+    "time": exchange.wait_time_ms + exchange.receive_time_ms,
+    "serverIPAddress": "",
+    "request": request,
+    "response": {
+      "status": exchange.status.as_u16(),
+      "statusText": exchange.status.canonical_reason().unwrap_or(""),
+      "httpVersion": version_to_string(exchange.version),
+      "cookies": [],
+      "headers": headers_to_har(&exchange.response_headers),
+      "content": content,
+      "redirectURL": redirect_url,
+      "headersSize": -1,
+      "bodySize": exchange.response_body.size,
+    },
+    "cache": {},
+    "timings": {
+      "send": 0,
+      "wait": exchange.wait_time_ms,
+      "receive": exchange.receive_time_ms,
+    },
+    "_clientIPAddress": exchange.client_ip.to_canonical().to_string(),
+  })
+}
+
+/// A request captured before the response is generated
+struct PendingCapture {
+  started: SystemTime,
+  started_instant: Instant,
+  client_ip: IpAddr,
+  method: Method,
+  url: String,
+  version: Version,
+  request_headers: Vec<(String, String)>,
+  request_body: SharedBodyCapture,
+  max_body_size: usize,
+  min_status: u16,
+}
+
+/// A guard adding the captured exchange into the capture buffer after the response body ends or is dropped
+struct CaptureGuard {
+  pending: PendingCapture,
+  status: StatusCode,
+  response_headers: Vec<(String, String)>,
+  response_body: SharedBodyCapture,
+  wait_time_ms: f64,
+  buffer: Arc<CaptureBuffer>,
+}
+
+impl Drop for CaptureGuard {
+  fn drop(&mut self) {
+    let total_time_ms = self.pending.started_instant.elapsed().as_secs_f64() * 1000.0;
+    self.buffer.push(CapturedExchange {
+      started: self.pending.started,
+      client_ip: self.pending.client_ip,
+      method: self.pending.method.clone(),
+      url: std::mem::take(&mut self.pending.url),
+      version: self.pending.version,
+      request_headers: std::mem::take(&mut self.pending.request_headers),
+      request_body: BodyCaptureSnapshot::take(&self.pending.request_body),
+      status: self.status,
+      response_headers: std::mem::take(&mut self.response_headers),
+      response_body: BodyCaptureSnapshot::take(&self.response_body),
+      wait_time_ms: self.wait_time_ms,
+      receive_time_ms: (total_time_ms - self.wait_time_ms).max(0.0),
+    });
+  }
+}
+
+/// A request/response capture module loader
+pub struct CaptureModuleLoader {
+  cache: ModuleCache<CaptureModule>,
+  buffer: Arc<CaptureBuffer>,
+}
+
+impl Default for CaptureModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl CaptureModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![]),
+      buffer: Arc::new(CaptureBuffer::new()),
+    }
+  }
+}
+
+impl ModuleLoader for CaptureModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    // The capture buffer is shared across all the hosts and locations, so its size is a global setting
+    self.buffer.set_capacity(
+      global_config
+        .and_then(|c| get_value!("capture_buffer_size", c))
+        .and_then(|v| v.as_i128())
+        .map_or(DEFAULT_CAPTURE_BUFFER_SIZE, |v| v as usize),
+    );
+    let buffer = self.buffer.clone();
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| {
+          Ok(Arc::new(CaptureModule { buffer }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["capture", "capture_export"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("capture", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `capture` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid request capture enabling option"))?
+        } else if entry.props.get("max_body_size").is_some_and(|v| v.as_size().is_none()) {
+          Err(anyhow::anyhow!("Invalid maximum captured body size"))?
+        } else if !entry
+          .props
+          .get("min_status")
+          .is_none_or(|v| v.as_i128().is_some_and(|v| (100..=599).contains(&v)))
+        {
+          Err(anyhow::anyhow!(
+            "The minimum status code of captured responses must be between 100 and 599"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("capture_buffer_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `capture_buffer_size` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_i128().is_none_or(|v| v < 0) {
+          Err(anyhow::anyhow!("Invalid request capture buffer size"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("capture_export", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `capture_export` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid captured requests export enabling option"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// A request/response capture module
+struct CaptureModule {
+  buffer: Arc<CaptureBuffer>,
+}
+
+impl Module for CaptureModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(CaptureModuleHandlers {
+      buffer: self.buffer.clone(),
+      pending: None,
+    })
+  }
+}
+
+/// Handlers for the request/response capture module
+struct CaptureModuleHandlers {
+  buffer: Arc<CaptureBuffer>,
+  pending: Option<PendingCapture>,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for CaptureModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    if get_value!("capture_export", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      if request.method() == Method::DELETE {
+        self.buffer.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::NO_CONTENT),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+      let har = serde_json::to_vec(&self.buffer.to_har())?;
+      return Ok(ResponseData {
+        request: Some(request),
+        response: Some(
+          Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .header(
+              header::CONTENT_DISPOSITION,
+              "attachment; filename=\"ferron-capture.har\"",
+            )
+            .body(Full::new(Bytes::from(har)).map_err(|e| match e {}).boxed())?,
+        ),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let Some(capture_entry) =
+      get_entry!("capture", config).filter(|entry| entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false))
+    else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+    let max_body_size = capture_entry
+      .props
+      .get("max_body_size")
+      .and_then(|v| v.as_size())
+      .map_or(DEFAULT_MAX_CAPTURED_BODY_SIZE, |v| v as usize);
+    let min_status = capture_entry
+      .props
+      .get("min_status")
+      .and_then(|v| v.as_i128())
+      .map_or(0, |v| v as u16);
+
+    let (request_parts, request_body) = request.into_parts();
+    let host = request_parts
+      .headers
+      .get(header::HOST)
+      .and_then(|v| v.to_str().ok())
+      .or_else(|| request_parts.uri.authority().map(|authority| authority.as_str()))
+      .unwrap_or("");
+    let url = format!(
+      "{}://{}{}",
+      if socket_data.encrypted { "https" } else { "http" },
+      host,
+      request_parts
+        .uri
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str())
+    );
+    let request_body_capture = SharedBodyCapture::default();
+    self.pending = Some(PendingCapture {
+      started: SystemTime::now(),
+      started_instant: Instant::now(),
+      client_ip: socket_data.remote_addr.ip(),
+      method: request_parts.method.clone(),
+      url,
+      version: request_parts.version,
+      request_headers: capture_headers(&request_parts.headers),
+      request_body: request_body_capture.clone(),
+      max_body_size,
+      min_status,
+    });
+    let request_body = CapturingBody::new(request_body, request_body_capture, max_body_size, None::<()>).boxed();
+
+    Ok(ResponseData {
+      request: Some(Request::from_parts(request_parts, request_body)),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    let Some(pending) = self
+      .pending
+      .take()
+      .filter(|pending| response.status().as_u16() >= pending.min_status)
+    else {
+      return Ok(response);
+    };
+
+    let (response_parts, response_body) = response.into_parts();
+    let response_body_capture = SharedBodyCapture::default();
+    let max_body_size = pending.max_body_size;
+    let guard = CaptureGuard {
+      wait_time_ms: pending.started_instant.elapsed().as_secs_f64() * 1000.0,
+      pending,
+      status: response_parts.status,
+      response_headers: capture_headers(&response_parts.headers),
+      response_body: response_body_capture.clone(),
+      buffer: self.buffer.clone(),
+    };
+    let response_body = CapturingBody::new(response_body, response_body_capture, max_body_size, Some(guard)).boxed();
+
+    Ok(Response::from_parts(response_parts, response_body))
+  }
+}
@@ -65,7 +65,9 @@ impl cegla_cgi::client::SendRuntime for CustomCgiRuntime {
       .stdout(Stdio::piped())
       .stderr(Stdio::piped())
       .envs(env)
-      .args(args);
+      .args(args)
+      // Kill the CGI program, if the request is cancelled (for example, when the client disconnects)
+      .kill_on_drop(true);
     if let Some(cwd) = cwd {
       command.current_dir(cwd);
     }
@@ -98,6 +100,74 @@ impl cegla_cgi::client::SendChild for CustomCgiChild {
   }
 }
 
+/// Custom runtime for `cegla-cgi`
+#[cfg(feature = "runtime-tokio")]
+pub struct CustomCgiRuntime;
+
+/// Custom child process for `cegla-cgi`
+#[cfg(feature = "runtime-tokio")]
+pub struct CustomCgiChild {
+  inner: tokio::process::Child,
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl cegla_cgi::client::SendRuntime for CustomCgiRuntime {
+  type Child = CustomCgiChild;
+
+  fn spawn(&self, future: impl std::future::Future + Send + 'static) {
+    tokio::spawn(async move {
+      future.await;
+    });
+  }
+
+  fn start_child(
+    &self,
+    cmd: &std::ffi::OsStr,
+    args: &[&std::ffi::OsStr],
+    env: CgiEnvironment,
+    cwd: Option<PathBuf>,
+  ) -> Result<Self::Child, std::io::Error> {
+    let mut command = Command::new(cmd);
+    command
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .envs(env)
+      .args(args)
+      // Kill the CGI program, if the request is cancelled (for example, when the client disconnects)
+      .kill_on_drop(true);
+    if let Some(cwd) = cwd {
+      command.current_dir(cwd);
+    }
+    Ok(CustomCgiChild {
+      inner: command.spawn()?,
+    })
+  }
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl cegla_cgi::client::SendChild for CustomCgiChild {
+  type Stdin = tokio::process::ChildStdin;
+  type Stdout = tokio::process::ChildStdout;
+  type Stderr = tokio::process::ChildStderr;
+
+  fn stdin(&mut self) -> Option<Self::Stdin> {
+    self.inner.stdin.take()
+  }
+
+  fn stdout(&mut self) -> Option<Self::Stdout> {
+    self.inner.stdout.take()
+  }
+
+  fn stderr(&mut self) -> Option<Self::Stderr> {
+    self.inner.stderr.take()
+  }
+
+  fn try_status(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+    self.inner.try_wait()
+  }
+}
+
 /// Custom runtime for `cegla-cgi`
 #[cfg(feature = "runtime-vibeio")]
 pub struct CustomCgiRuntime;
@@ -164,6 +234,17 @@ impl cegla_cgi::client::Child for CustomCgiChild {
   }
 }
 
+#[cfg(feature = "runtime-vibeio")]
+impl Drop for CustomCgiChild {
+  fn drop(&mut self) {
+    // Kill the CGI program, if the request is cancelled (for example, when the client disconnects),
+    // since vibeio doesn't kill child processes on drop
+    if matches!(self.inner.try_wait(), Ok(None)) {
+      let _ = self.inner.kill();
+    }
+  }
+}
+
 #[cfg(feature = "runtime-vibeio")]
 struct SendWrapBody<B> {
   inner: send_wrapper::SendWrapper<std::pin::Pin<Box<B>>>,
@@ -845,11 +926,6 @@ async fn execute_cgi(
   });
   let args: Vec<_> = executable_params_iter.map(std::ffi::OsStr::new).collect();
 
-  #[cfg(feature = "runtime-tokio")]
-  let runtime = tokio_cegla::TokioCgiRuntime;
-  #[cfg(feature = "runtime-monoio")]
-  let runtime = CustomCgiRuntime;
-  #[cfg(feature = "runtime-vibeio")]
   let runtime = CustomCgiRuntime;
 
   #[cfg(not(feature = "runtime-vibeio"))]
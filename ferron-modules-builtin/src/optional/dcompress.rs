@@ -4,6 +4,10 @@ use std::sync::{Arc, LazyLock};
 
 use async_compression::brotli::EncoderParams;
 use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder};
+use async_compression::tokio::write::{
+  BrotliEncoder as BrotliWriteEncoder, DeflateEncoder as DeflateWriteEncoder, GzipEncoder as GzipWriteEncoder,
+  ZstdEncoder as ZstdWriteEncoder,
+};
 use async_compression::zstd::CParameter;
 use async_compression::Level;
 use async_trait::async_trait;
@@ -11,20 +15,27 @@ use bytes::Bytes;
 use futures_util::future::Either;
 use futures_util::{StreamExt, TryStreamExt};
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, BodyStream, StreamBody};
+use http_body_util::{BodyExt, BodyStream, Empty, StreamBody};
 use hyper::body::Frame;
-use hyper::{header, Request, Response};
+use hyper::{header, Request, Response, StatusCode};
 
 use ferron_common::config::ServerConfiguration;
 use ferron_common::logging::ErrorLogger;
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
-use ferron_common::util::{parse_q_value_header, ModuleCache};
-use ferron_common::{get_entries_for_validation, get_value};
+use ferron_common::util::{
+  is_streaming_response, parse_q_value_header, response_flush_interval, ModuleCache, ResponseFlushInterval,
+};
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
 use tokio_util::io::{ReaderStream, StreamReader};
 
-use crate::util::SplitStreamByMapExt;
+use crate::util::{
+  decompress_body_with_ratio_limit, is_decodable_content_coding, FlushingCompressedBody, SplitStreamByMapExt,
+  DECODABLE_CONTENT_CODINGS,
+};
 
 const COMPRESSED_STREAM_READER_BUFFER_SIZE: usize = 16384;
+/// The default maximum expansion ratio of decompressed request bodies
+const DEFAULT_REQUEST_DECOMPRESSION_MAX_RATIO: u64 = 100;
 
 /// A hard-coded list of non-compressible MIME types
 static NON_COMPRESSIBLE_MIME_TYPES: LazyLock<BTreeSet<&'static str>> = LazyLock::new(|| {
@@ -117,6 +128,21 @@ enum Compression {
   Identity,
 }
 
+/// Removes the suffixes added to the entity tags of compressed responses from the `If-None-Match` header value
+fn strip_dynamic_etag_suffixes(if_none_match: &str) -> String {
+  if_none_match
+    .split(',')
+    .map(|etag| {
+      let etag = etag.trim();
+      ["br", "zstd", "deflate", "gzip"]
+        .into_iter()
+        .find_map(|algorithm| etag.strip_suffix(&format!("-dynamic-{algorithm}\"")))
+        .map_or_else(|| etag.to_string(), |etag| format!("{etag}\""))
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
 /// A dynamic content compression module loader
 pub struct DynamicCompressionModuleLoader {
   cache: ModuleCache<DynamicCompressionModule>,
@@ -154,7 +180,7 @@ impl ModuleLoader for DynamicCompressionModuleLoader {
   }
 
   fn get_requirements(&self) -> Vec<&'static str> {
-    vec!["dynamic_compressed"]
+    vec!["dynamic_compressed", "request_decompress"]
   }
 
   fn validate_configuration(
@@ -174,6 +200,26 @@ impl ModuleLoader for DynamicCompressionModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("request_decompress", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `request_decompress` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid request body decompression enabling option"))?
+        } else if entry
+          .props
+          .get("max_ratio")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid maximum expansion ratio of decompressed request bodies"
+          ))?
+        }
+      }
+    }
+
     Ok(())
   }
 }
@@ -202,7 +248,7 @@ struct DynamicCompressionModuleHandlers {
 impl ModuleHandlers for DynamicCompressionModuleHandlers {
   async fn request_handler(
     &mut self,
-    request: Request<BoxBody<Bytes, std::io::Error>>,
+    mut request: Request<BoxBody<Bytes, std::io::Error>>,
     config: &ServerConfiguration,
     _socket_data: &SocketData,
     _error_logger: &ErrorLogger,
@@ -221,6 +267,68 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
       .and_then(|v| v.as_bool())
       .unwrap_or(false);
 
+    let request_decompression_max_ratio = get_entry!("request_decompress", config).and_then(|e| {
+      if e.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+        Some(
+          e.props
+            .get("max_ratio")
+            .and_then(|v| v.as_i128())
+            .map_or(DEFAULT_REQUEST_DECOMPRESSION_MAX_RATIO, |v| v as u64),
+        )
+      } else {
+        None
+      }
+    });
+
+    if let Some(max_ratio) = request_decompression_max_ratio {
+      // Decompress the request body, since backend servers (like CGI, FastCGI or SCGI applications) might not support
+      // compressed request bodies
+      if let Some(content_coding) = request
+        .headers()
+        .get(header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| v != "identity")
+      {
+        if !is_decodable_content_coding(&content_coding) {
+          return Ok(ResponseData {
+            request: Some(request),
+            response: Some(
+              Response::builder()
+                .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .header(header::ACCEPT_ENCODING, DECODABLE_CONTENT_CODINGS)
+                .body(Empty::new().map_err(|e| match e {}).boxed())?,
+            ),
+            response_status: None,
+            response_headers: None,
+            new_remote_address: None,
+          });
+        }
+
+        let (mut request_parts, request_body) = request.into_parts();
+        while request_parts.headers.remove(header::CONTENT_ENCODING).is_some() {}
+        while request_parts.headers.remove(header::CONTENT_LENGTH).is_some() {}
+        request = Request::from_parts(
+          request_parts,
+          decompress_body_with_ratio_limit(request_body, &content_coding, max_ratio),
+        );
+      }
+    }
+
+    if self.compression_enabled {
+      // Conditional requests are evaluated (for example, by the cache or backend servers) against the uncompressed responses
+      if let Some(if_none_match) = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(strip_dynamic_etag_suffixes)
+      {
+        request
+          .headers_mut()
+          .insert(header::IF_NONE_MATCH, if_none_match.parse()?);
+      }
+    }
+
     Ok(ResponseData {
       request: Some(request),
       response: None,
@@ -234,6 +342,11 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
     &mut self,
     mut response: Response<BoxBody<Bytes, std::io::Error>>,
   ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    if !self.compression_enabled {
+      // Only the request body decompression is enabled
+      return Ok(response);
+    }
+
     // Initialize compression flags
     let mut used_compression = Compression::Identity;
 
@@ -244,8 +357,12 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
       .and_then(|v| v.to_str().ok())
       .map(|v| v.split_once(';').map_or(v, |s| s.0).trim());
 
+    // Streaming responses (like Server-Sent Events) aren't compressed, since the compressor would buffer the events,
+    // unless the response has a flush interval, in which case the compressor is flushed periodically
+    let flush_interval = response_flush_interval(&response);
     let compressible = self.compression_enabled
       && !response.headers().contains_key(header::CONTENT_ENCODING)
+      && (flush_interval.is_some() || !is_streaming_response(&response))
       && content_type_option.is_none_or(|t| !NON_COMPRESSIBLE_MIME_TYPES.contains(&t));
 
     // Determine the appropriate compression algorithm based on Accept-Encoding
@@ -355,7 +472,10 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
       }
     }
 
-    if let Some(algorithm_str) = &algorithm_str {
+    // "304 Not Modified" responses get the same entity tag as the compressed response, but aren't compressed
+    let has_body = !matches!(response.status(), StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED);
+
+    if let Some(algorithm_str) = algorithm_str.as_ref().filter(|_| has_body) {
       // Add Content-Encoding header
       response
         .headers_mut()
@@ -365,8 +485,42 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
       let mut remove_extensions = true;
 
       // Create the appropriate response body based on compression method
-      let boxed_body = match used_compression {
-        Compression::Brotli => {
+      let boxed_body = match (used_compression, flush_interval) {
+        (Compression::Brotli, Some(flush_interval)) => FlushingCompressedBody::new(
+          response_body,
+          BrotliWriteEncoder::with_params(
+            Vec::new(),
+            EncoderParams::default()
+              .quality(Level::Precise(4))
+              .window_size(17)
+              .block_size(18),
+          ),
+          flush_interval,
+        )
+        .boxed(),
+        (Compression::Zstd, Some(flush_interval)) => FlushingCompressedBody::new(
+          response_body,
+          ZstdWriteEncoder::with_quality_and_params(
+            Vec::new(),
+            Level::Default,
+            &[CParameter::window_log(17), CParameter::hash_log(10)],
+          ),
+          flush_interval,
+        )
+        .boxed(),
+        (Compression::Deflate, Some(flush_interval)) => FlushingCompressedBody::new(
+          response_body,
+          DeflateWriteEncoder::with_quality(Vec::new(), Level::Precise(4)),
+          flush_interval,
+        )
+        .boxed(),
+        (Compression::Gzip, Some(flush_interval)) => FlushingCompressedBody::new(
+          response_body,
+          GzipWriteEncoder::with_quality(Vec::new(), Level::Precise(4)),
+          flush_interval,
+        )
+        .boxed(),
+        (Compression::Brotli, None) => {
           let (data_stream, trailer_stream) = BodyStream::new(response_body).split_by_map(|f| match f {
             Ok(frame) if frame.is_trailers() => Either::Right(Ok::<_, std::io::Error>(frame)),
             Ok(frame) => match frame.into_data() {
@@ -392,7 +546,7 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
           let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data).chain(trailer_stream));
           BodyExt::boxed(stream_body)
         }
-        Compression::Zstd => {
+        (Compression::Zstd, None) => {
           let (data_stream, trailer_stream) = BodyStream::new(response_body).split_by_map(|f| match f {
             Ok(frame) if frame.is_trailers() => Either::Right(Ok::<_, std::io::Error>(frame)),
             Ok(frame) => match frame.into_data() {
@@ -416,7 +570,7 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
           let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data).chain(trailer_stream));
           BodyExt::boxed(stream_body)
         }
-        Compression::Deflate => {
+        (Compression::Deflate, None) => {
           let (data_stream, trailer_stream) = BodyStream::new(response_body).split_by_map(|f| match f {
             Ok(frame) if frame.is_trailers() => Either::Right(Ok::<_, std::io::Error>(frame)),
             Ok(frame) => match frame.into_data() {
@@ -434,7 +588,7 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
           let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data).chain(trailer_stream));
           BodyExt::boxed(stream_body)
         }
-        Compression::Gzip => {
+        (Compression::Gzip, None) => {
           let (data_stream, trailer_stream) = BodyStream::new(response_body).split_by_map(|f| match f {
             Ok(frame) if frame.is_trailers() => Either::Right(Ok::<_, std::io::Error>(frame)),
             Ok(frame) => match frame.into_data() {
@@ -462,6 +616,10 @@ impl ModuleHandlers for DynamicCompressionModuleHandlers {
       if remove_extensions {
         // Remove extensions from response parts (to prevent zerocopy from interfering with the dynamic compression)
         response_parts.extensions.clear();
+        if let Some(flush_interval) = flush_interval {
+          // Keep the flush interval, so that the compressed response isn't buffered by other modules
+          response_parts.extensions.insert(ResponseFlushInterval(flush_interval));
+        }
       }
 
       response = Response::from_parts(response_parts, boxed_body);
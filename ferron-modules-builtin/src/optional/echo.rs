@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::header::HeaderValue;
+use hyper::{header, Request, Response, StatusCode};
+
+use ferron_common::config::{ErrorHandlerStatus, ServerConfiguration};
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::{replace_header_placeholders, ModuleCache};
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+/// Placeholders, which resolved values are reflected by the debug echo endpoint
+const ECHOED_PLACEHOLDERS: [&str; 12] = [
+  "path",
+  "path_and_query",
+  "method",
+  "version",
+  "scheme",
+  "client_ip",
+  "client_port",
+  "client_ip_canonical",
+  "server_ip",
+  "server_port",
+  "server_ip_canonical",
+  "header:host",
+];
+
+/// An echo module loader
+pub struct EchoModuleLoader {
+  cache: ModuleCache<EchoModule>,
+}
+
+impl Default for EchoModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl EchoModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![]),
+    }
+  }
+}
+
+impl ModuleLoader for EchoModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| Ok(Arc::new(EchoModule)))?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["respond", "debug_echo"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("respond", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `respond` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && !entry.values[0].as_i128().is_some_and(|v| (200..=599).contains(&v)) {
+          Err(anyhow::anyhow!("The response status code must be between 200 and 599"))?
+        } else if !entry.props.get("body").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("The response body must be a string"))?
+        } else if entry
+          .props
+          .get("content_type")
+          .is_some_and(|v| v.as_str().is_none_or(|v| HeaderValue::from_str(v).is_err()))
+        {
+          Err(anyhow::anyhow!("Invalid response content type"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("debug_echo", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `debug_echo` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid debug echo endpoint enabling option"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// An echo module
+struct EchoModule;
+
+impl Module for EchoModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(EchoModuleHandlers)
+  }
+}
+
+/// Handlers for the echo module
+struct EchoModuleHandlers;
+
+/// Describes the server configuration block matched for the request
+fn describe_matched_configuration(config: &ServerConfiguration) -> serde_json::Value {
+  let filters = &config.filters;
+  serde_json::json!({
+    "description": filters.to_string(),
+    "hostname": filters.hostname,
+    "ip": filters.ip.map(|ip| ip.to_string()),
+    "port": filters.port,
+    "location": filters.condition.as_ref().map(|condition| condition.location_prefix.as_str()),
+    "conditionals": filters.condition.as_ref().map_or(0, |condition| condition.conditionals.len()),
+    "error_status": filters.error_handler_status.as_ref().map(|status| match status {
+      ErrorHandlerStatus::Any => "any".to_string(),
+      ErrorHandlerStatus::Status(status) => status.to_string(),
+    }),
+  })
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for EchoModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    if get_value!("debug_echo", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      let (request_parts, request_body) = request.into_parts();
+
+      let mut headers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+      for (name, value) in &request_parts.headers {
+        headers
+          .entry(name.as_str())
+          .or_default()
+          .push(String::from_utf8_lossy(value.as_bytes()).into_owned());
+      }
+
+      let placeholders = ECHOED_PLACEHOLDERS
+        .iter()
+        .map(|placeholder| {
+          (
+            *placeholder,
+            replace_header_placeholders(&format!("{{{placeholder}}}"), &request_parts, Some(socket_data)),
+          )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+      let request_data = request_parts.extensions.get::<RequestData>();
+      let x_forwarded_for = request_parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        .collect::<Vec<_>>();
+
+      let echo = serde_json::json!({
+        "request": {
+          "method": request_parts.method.as_str(),
+          "uri": request_parts.uri.to_string(),
+          "original_uri": request_data.and_then(|data| data.original_url.as_ref()).map(|uri| uri.to_string()),
+          "version": format!("{:?}", request_parts.version),
+          "headers": headers,
+        },
+        "placeholders": placeholders,
+        "matched_configuration": describe_matched_configuration(config),
+        "client_address": {
+          "peer_address": request_data.and_then(|data| data.peer_address).map(|address| address.to_string()),
+          "proxy_protocol_address": request_data
+            .and_then(|data| data.proxy_protocol_address)
+            .map(|address| address.to_string()),
+          "x_forwarded_for": x_forwarded_for,
+          "trust_x_forwarded_for": get_value!("trust_x_forwarded_for", config)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+          "client_address": socket_data.remote_addr.to_string(),
+        },
+        "server_address": socket_data.local_addr.to_string(),
+        "encrypted": socket_data.encrypted,
+        "auth_user": request_data.and_then(|data| data.auth_user.as_deref()),
+      });
+
+      return Ok(ResponseData {
+        request: Some(Request::from_parts(request_parts, request_body)),
+        response: Some(
+          Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(
+              Full::new(Bytes::from(serde_json::to_vec_pretty(&echo)?))
+                .map_err(|e| match e {})
+                .boxed(),
+            )?,
+        ),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    if let Some(entry) = get_entry!("respond", config) {
+      if let Some(status_code) = entry
+        .values
+        .first()
+        .and_then(|v| v.as_i128())
+        .and_then(|v| StatusCode::from_u16(v as u16).ok())
+      {
+        let (request_parts, request_body) = request.into_parts();
+        let body = entry
+          .props
+          .get("body")
+          .and_then(|v| v.as_str())
+          .map(|body| replace_header_placeholders(body, &request_parts, Some(socket_data)));
+        let mut response_builder = Response::builder().status(status_code);
+        if let Some(content_type) = entry.props.get("content_type").and_then(|v| v.as_str()) {
+          response_builder = response_builder.header(header::CONTENT_TYPE, content_type);
+        } else if body.is_some() {
+          response_builder = response_builder.header(header::CONTENT_TYPE, "text/plain; charset=utf-8");
+        }
+        let response = response_builder.body(
+          Full::new(Bytes::from(body.unwrap_or_default()))
+            .map_err(|e| match e {})
+            .boxed(),
+        )?;
+
+        return Ok(ResponseData {
+          request: Some(Request::from_parts(request_parts, request_body)),
+          response: Some(response),
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    }
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
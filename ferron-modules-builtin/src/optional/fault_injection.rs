@@ -0,0 +1,260 @@
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Frame;
+use hyper::header::HeaderName;
+use hyper::{Request, Response, StatusCode};
+
+use ferron_common::config::{ServerConfiguration, ServerConfigurationEntry, ServerConfigurationValue};
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry};
+
+/// Obtains the percentage of requests a fault is injected into from the `percentage` property of the configuration entry
+fn get_percentage(entry: &ServerConfigurationEntry) -> f64 {
+  entry
+    .props
+    .get("percentage")
+    .and_then(|v| {
+      if v.is_float() {
+        v.as_f64()
+      } else if v.is_integer() {
+        v.as_i128().map(|v| v as f64)
+      } else {
+        None
+      }
+    })
+    .unwrap_or(100.0)
+}
+
+/// Determines if a percentage property is valid
+fn is_valid_percentage(value: Option<&ServerConfigurationValue>) -> bool {
+  value.is_none_or(|v| {
+    let percentage = if v.is_float() {
+      v.as_f64()
+    } else if v.is_integer() {
+      v.as_i128().map(|v| v as f64)
+    } else {
+      None
+    };
+    percentage.is_some_and(|percentage| (0.0..=100.0).contains(&percentage))
+  })
+}
+
+/// Determines randomly if a fault is injected into the request, based on the percentage of requests
+fn should_inject(entry: &ServerConfigurationEntry) -> bool {
+  let percentage = get_percentage(entry);
+  percentage >= 100.0 || rand::random::<f64>() * 100.0 < percentage
+}
+
+/// A fault injection module loader
+pub struct FaultInjectionModuleLoader {
+  cache: ModuleCache<FaultInjectionModule>,
+}
+
+impl Default for FaultInjectionModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl FaultInjectionModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![]),
+    }
+  }
+}
+
+impl ModuleLoader for FaultInjectionModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| Ok(Arc::new(FaultInjectionModule)))?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["fault_delay", "fault_abort", "fault_reset"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("fault_delay", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `fault_delay` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid injected delay"))?
+        } else if !is_valid_percentage(entry.props.get("percentage")) {
+          Err(anyhow::anyhow!(
+            "The percentage of requests with injected delay must be a number between 0 and 100"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("fault_abort", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `fault_abort` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && !entry.values[0].as_i128().is_some_and(|v| (400..=599).contains(&v)) {
+          Err(anyhow::anyhow!(
+            "The injected abort status code must be an error status code (between 400 and 599)"
+          ))?
+        } else if !is_valid_percentage(entry.props.get("percentage")) {
+          Err(anyhow::anyhow!(
+            "The percentage of aborted requests must be a number between 0 and 100"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("fault_reset", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `fault_reset` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid injected connection reset enabling option"))?
+        } else if !is_valid_percentage(entry.props.get("percentage")) {
+          Err(anyhow::anyhow!(
+            "The percentage of requests with reset connections must be a number between 0 and 100"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("fault_header", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `fault_header` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && entry.values[0]
+            .as_str()
+            .is_none_or(|v| HeaderName::from_str(v).is_err())
+        {
+          Err(anyhow::anyhow!("Invalid fault injection header name"))?
+        } else if !entry.props.get("value").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("The fault injection header value must be a string"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// A fault injection module
+struct FaultInjectionModule;
+
+impl Module for FaultInjectionModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(FaultInjectionModuleHandlers)
+  }
+}
+
+/// Handlers for the fault injection module
+struct FaultInjectionModuleHandlers;
+
+#[async_trait(?Send)]
+impl ModuleHandlers for FaultInjectionModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    // If the fault injection header is specified, faults are injected only into requests having that header
+    let header_matches =
+      get_entry!("fault_header", config).is_none_or(|entry| match entry.values.first().and_then(|v| v.as_str()) {
+        Some(header_name) => {
+          let expected_value = entry.props.get("value").and_then(|v| v.as_str());
+          request
+            .headers()
+            .get_all(header_name)
+            .iter()
+            .any(|value| expected_value.is_none_or(|expected_value| value.as_bytes() == expected_value.as_bytes()))
+        }
+        None => true,
+      });
+
+    if header_matches {
+      if let Some(delay) = get_entry!("fault_delay", config)
+        .filter(|entry| should_inject(entry))
+        .and_then(|entry| entry.values.first())
+        .and_then(|v| v.as_duration())
+      {
+        ferron_common::runtime::sleep(delay).await;
+      }
+
+      if get_entry!("fault_reset", config)
+        .filter(|entry| entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false))
+        .is_some_and(should_inject)
+      {
+        // The response body fails immediately, which makes the server reset the connection (for HTTP/1.x) or the stream
+        // (for HTTP/2 and HTTP/3)
+        let body = StreamBody::new(futures_util::stream::once(async {
+          Err::<Frame<Bytes>, _>(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "The connection is reset by fault injection",
+          ))
+        }));
+        return Ok(ResponseData {
+          request: Some(request),
+          response: Some(Response::builder().body(body.boxed())?),
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+
+      if let Some(status_code) = get_entry!("fault_abort", config)
+        .filter(|entry| should_inject(entry))
+        .and_then(|entry| entry.values.first())
+        .and_then(|v| v.as_i128())
+        .and_then(|v| StatusCode::from_u16(v as u16).ok())
+      {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(status_code),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    }
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
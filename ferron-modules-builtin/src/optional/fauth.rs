@@ -10,14 +10,14 @@ use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Empty};
 use hyper::{header, Request};
 
-use ferron_common::http_proxy::{Connections, ReverseProxy, ReverseProxyHandler};
+use ferron_common::http_proxy::{Connections, ProxyHostHeader, ReverseProxy, ReverseProxyHandler};
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
 use ferron_common::{config::ServerConfiguration, util::ModuleCache};
 use ferron_common::{get_entries_for_validation, get_entry, get_value, get_values};
 use hyper::header::HeaderName;
 
 const DEFAULT_CONCURRENT_CONNECTIONS: usize = 16384;
-const DEFAULT_KEEPALIVE_IDLE_TIMEOUT: u64 = 60000;
+const DEFAULT_KEEPALIVE_IDLE_TIMEOUT: Duration = Duration::from_millis(60000);
 
 /// A forwarded authentication module loader
 pub struct ForwardedAuthenticationModuleLoader {
@@ -85,10 +85,9 @@ impl ModuleLoader for ForwardedAuthenticationModuleLoader {
                       if v.is_null() {
                         None
                       } else {
-                        Some(v.as_i128().map(|v| v as u64).unwrap_or(DEFAULT_KEEPALIVE_IDLE_TIMEOUT))
+                        Some(v.as_duration().unwrap_or(DEFAULT_KEEPALIVE_IDLE_TIMEOUT))
                       }
-                    })
-                    .map(Duration::from_millis),
+                    }),
                 )
               })
           });
@@ -96,7 +95,7 @@ impl ModuleLoader for ForwardedAuthenticationModuleLoader {
           if let Some((proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout)) = proxy_to_raw {
             proxy_builder = proxy_builder.upstream(proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout);
           }
-          let proxy = proxy_builder.rewrite_host(true).build();
+          let proxy = proxy_builder.proxy_host_header(Some(ProxyHostHeader::Upstream)).build();
 
           Ok(Arc::new(ForwardedAuthenticationModule { proxy }))
         })?,
@@ -129,7 +128,7 @@ impl ModuleLoader for ForwardedAuthenticationModuleLoader {
           }
         }
         if let Some(prop) = entry.props.get("idle_timeout") {
-          if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
+          if !prop.is_null() && prop.as_duration().is_none_or(|v| v.is_zero()) {
             Err(anyhow::anyhow!(
               "Invalid forwarded authentication idle keep-alive connection timeout for a backend server"
             ))?
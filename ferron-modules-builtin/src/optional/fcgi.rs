@@ -833,13 +833,26 @@ async fn execute_fastcgi(
   let stdout = StreamReader::new(stdout_stream);
   let stderr = StreamReader::new(stderr_stream);
 
+  // The FastCGI request is cancelled (and the connection is closed) when either the request handler
+  // or the response body is dropped, for example when the client disconnects
+  let fastcgi_cancel = CancellationToken::new();
+  let fastcgi_cancel_guard = fastcgi_cancel.clone().drop_guard();
+
+  let stdin_cancel = fastcgi_cancel.clone();
   ferron_common::runtime::spawn(async move {
     let (mut cgi_stdin_reader, mut stdin) = (cgi_stdin_reader, stdin);
-    let _ = tokio::io::copy(&mut cgi_stdin_reader, &mut stdin).await;
+    ferron_common::runtime::select! {
+      biased;
 
-    // Send terminating STDIN packet
-    let _ = stdin.write(&[]).await;
-    let _ = stdin.flush().await;
+      _ = stdin_cancel.cancelled() => {}
+      _ = async {
+        let _ = tokio::io::copy(&mut cgi_stdin_reader, &mut stdin).await;
+
+        // Send terminating STDIN packet
+        let _ = stdin.write(&[]).await;
+        let _ = stdin.flush().await;
+      } => {}
+    }
   });
 
   let stderr_read_future = async move {
@@ -881,7 +894,9 @@ async fn execute_fastcgi(
     }
   };
 
-  let stderr_cancel = CancellationToken::new();
+  // From now on, the FastCGI request is cancelled when the response body is dropped
+  fastcgi_cancel_guard.disarm();
+  let stderr_cancel = fastcgi_cancel;
   let (parts, body) = response.into_parts();
   let response = Response::from_parts(parts, FcgiProcessedBody::new(body, stderr_cancel.clone()).boxed());
 
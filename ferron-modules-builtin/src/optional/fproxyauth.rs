@@ -17,7 +17,7 @@ use ferron_common::util::TtlCache;
 use ferron_common::{config::ServerConfiguration, util::ModuleCache};
 use ferron_common::{get_entries, get_entries_for_validation, get_entry};
 
-use crate::util::parse_basic_auth;
+use crate::util::{constant_time_eq, parse_basic_auth};
 
 /// A forward proxy authentication module loader
 pub struct ForwardProxyAuthenticationModuleLoader {
@@ -215,7 +215,7 @@ impl ModuleHandlers for ForwardProxyAuthenticationModuleHandlers {
           let mut authorized_user = None;
           for user_config in &users_vec_config.inner {
             if let Some(username_db) = user_config.values.first().and_then(|v| v.as_str()) {
-              if username_db != username {
+              if !constant_time_eq(username_db.as_bytes(), username.as_bytes()) {
                 continue;
               }
               if let Some(user_list) = &self.allowed_users {
@@ -0,0 +1,536 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{header, Method, Request, Response, StatusCode};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::{ErrorLogger, LogLevel};
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+use crate::util::{parse_persisted_query_manifest, GraphqlDocument};
+
+/// The maximum size of a GraphQL request body inspected by the GraphQL guard
+const MAX_REQUEST_BODY_SIZE: usize = 1048576;
+
+/// The target (module name) of leveled log messages logged by the GraphQL guard module
+const LOG_TARGET: &str = "graphql";
+
+/// A GraphQL guard module loader
+pub struct GraphqlGuardModuleLoader {
+  cache: ModuleCache<GraphqlGuardModule>,
+}
+
+impl Default for GraphqlGuardModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GraphqlGuardModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![
+        "graphql_max_depth",
+        "graphql_max_complexity",
+        "graphql_persisted_queries",
+      ]),
+    }
+  }
+}
+
+impl ModuleLoader for GraphqlGuardModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let persisted_queries_entry = get_entry!("graphql_persisted_queries", config);
+          let persisted_queries = match persisted_queries_entry
+            .and_then(|e| e.values.first())
+            .and_then(|v| v.as_str())
+          {
+            Some(manifest_path) => {
+              let manifest = std::fs::read_to_string(manifest_path).map_err(|err| {
+                anyhow::anyhow!("Cannot read the persisted query manifest \"{manifest_path}\": {err}")
+              })?;
+              let queries = parse_persisted_query_manifest(&manifest)
+                .map_err(|err| anyhow::anyhow!("{err} (in \"{manifest_path}\")"))?;
+              Some(Arc::new(PersistedQueries {
+                query_hashes: queries.values().map(|query| sha256_hex(query)).collect(),
+                queries,
+                only: persisted_queries_entry
+                  .and_then(|e| e.props.get("only"))
+                  .and_then(|v| v.as_bool())
+                  .unwrap_or(false),
+              }))
+            }
+            None => None,
+          };
+          Ok(Arc::new(GraphqlGuardModule {
+            max_depth: get_value!("graphql_max_depth", config)
+              .and_then(|v| v.as_i128())
+              .map(|v| v as u64),
+            max_complexity: get_value!("graphql_max_complexity", config)
+              .and_then(|v| v.as_i128())
+              .map(|v| v as u64),
+            persisted_queries,
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec![
+      "graphql_max_depth",
+      "graphql_max_complexity",
+      "graphql_persisted_queries",
+    ]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("graphql_max_depth", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `graphql_max_depth` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v <= 0) {
+          Err(anyhow::anyhow!("Invalid maximum GraphQL query depth"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("graphql_max_complexity", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `graphql_max_complexity` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v <= 0) {
+          Err(anyhow::anyhow!("Invalid maximum GraphQL query complexity"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("graphql_persisted_queries", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `graphql_persisted_queries` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!(
+            "The path to the persisted query manifest must be a string"
+          ))?
+        } else if !entry.props.get("only").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!("Invalid persisted query enforcement enabling option"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// Persisted GraphQL queries loaded from a manifest
+struct PersistedQueries {
+  /// The map of the persisted query IDs (hashes) to the queries
+  queries: HashMap<String, String>,
+  /// The SHA-256 hashes of the persisted queries
+  query_hashes: HashSet<String>,
+  /// Whether only the persisted queries are allowed
+  only: bool,
+}
+
+/// Computes the hex-encoded SHA-256 hash of the GraphQL query
+fn sha256_hex(query: &str) -> String {
+  Sha256::digest(query.as_bytes())
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect()
+}
+
+/// A GraphQL guard module
+struct GraphqlGuardModule {
+  max_depth: Option<u64>,
+  max_complexity: Option<u64>,
+  persisted_queries: Option<Arc<PersistedQueries>>,
+}
+
+impl Module for GraphqlGuardModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(GraphqlGuardModuleHandlers {
+      max_depth: self.max_depth,
+      max_complexity: self.max_complexity,
+      persisted_queries: self.persisted_queries.clone(),
+      metric_operations: Vec::new(),
+    })
+  }
+}
+
+/// A GraphQL request (a single operation of a possibly batched request)
+struct GraphqlRequest {
+  query: Option<String>,
+  operation_name: Option<String>,
+  variables: Value,
+  persisted_query_hash: Option<String>,
+}
+
+impl GraphqlRequest {
+  /// Creates a GraphQL request from the JSON object of the GraphQL over HTTP request
+  fn from_json(request: &Value) -> Result<Self, GraphqlGuardError> {
+    if !request.is_object() {
+      return Err(GraphqlGuardError::bad_request(
+        "The GraphQL request must be a JSON object",
+      ));
+    }
+    Ok(Self {
+      query: request.get("query").and_then(|v| v.as_str()).map(|v| v.to_string()),
+      operation_name: request
+        .get("operationName")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string()),
+      variables: request.get("variables").cloned().unwrap_or(Value::Null),
+      persisted_query_hash: request
+        .pointer("/extensions/persistedQuery/sha256Hash")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string()),
+    })
+  }
+
+  /// Creates a GraphQL request from the query string of the GraphQL over HTTP `GET` request
+  fn from_query_string(query_string: &str) -> Result<Self, GraphqlGuardError> {
+    let mut request = serde_json::Map::new();
+    for pair in query_string.split('&').filter(|pair| !pair.is_empty()) {
+      let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+      let value = value.replace('+', " ");
+      let value = urlencoding::decode(&value).map_or_else(|_| value.clone(), |v| v.into_owned());
+      let value = match name {
+        "query" | "operationName" => Value::String(value),
+        "variables" | "extensions" => serde_json::from_str(&value).map_err(|_| {
+          GraphqlGuardError::bad_request(&format!("The `{name}` query parameter must be a JSON object"))
+        })?,
+        _ => continue,
+      };
+      request.insert(name.to_string(), value);
+    }
+    Self::from_json(&Value::Object(request))
+  }
+}
+
+/// An error causing the GraphQL request to be rejected
+struct GraphqlGuardError {
+  code: &'static str,
+  message: String,
+}
+
+impl GraphqlGuardError {
+  fn bad_request(message: &str) -> Self {
+    Self {
+      code: "BAD_REQUEST",
+      message: message.to_string(),
+    }
+  }
+}
+
+/// GraphQL guard module handlers
+struct GraphqlGuardModuleHandlers {
+  max_depth: Option<u64>,
+  max_complexity: Option<u64>,
+  persisted_queries: Option<Arc<PersistedQueries>>,
+  /// The names and types of the inspected operations, and whether they were accepted
+  metric_operations: Vec<(Option<String>, Option<&'static str>, bool)>,
+}
+
+impl GraphqlGuardModuleHandlers {
+  /// Checks the GraphQL request against the persisted queries and the depth and complexity limits.
+  /// Returns the operation name and type (if known), and the check result.
+  fn check_request(
+    &self,
+    request: &GraphqlRequest,
+  ) -> (Option<String>, Option<&'static str>, Result<(), GraphqlGuardError>) {
+    let query = match (&request.query, &request.persisted_query_hash, &self.persisted_queries) {
+      (Some(query), _, Some(persisted_queries))
+        if persisted_queries.only && !persisted_queries.query_hashes.contains(&sha256_hex(query)) =>
+      {
+        return (
+          request.operation_name.clone(),
+          None,
+          Err(GraphqlGuardError {
+            code: "PERSISTED_QUERY_NOT_ALLOWED",
+            message: "Only persisted queries are allowed".to_string(),
+          }),
+        );
+      }
+      (Some(query), _, _) => query,
+      (None, Some(hash), persisted_queries) => {
+        match persisted_queries.as_ref().and_then(|p| p.queries.get(hash)) {
+          Some(query) => query,
+          None if persisted_queries.as_ref().is_some_and(|p| p.only) => {
+            return (
+              request.operation_name.clone(),
+              None,
+              Err(GraphqlGuardError {
+                code: "PERSISTED_QUERY_NOT_FOUND",
+                message: "PersistedQueryNotFound".to_string(),
+              }),
+            );
+          }
+          // The query might be an automatic persisted query registered in the backend server.
+          // Such queries are checked when they're registered, since they're sent along with their hashes then.
+          None => return (request.operation_name.clone(), None, Ok(())),
+        }
+      }
+      (None, None, _) => {
+        return (
+          request.operation_name.clone(),
+          None,
+          Err(GraphqlGuardError::bad_request("The GraphQL query is missing")),
+        );
+      }
+    };
+
+    let analysis = match GraphqlDocument::parse(query) {
+      Ok(document) => match document.analyze(request.operation_name.as_deref(), &request.variables) {
+        Ok(analysis) => analysis,
+        Err(message) => {
+          return (
+            request.operation_name.clone(),
+            None,
+            Err(GraphqlGuardError {
+              code: "GRAPHQL_VALIDATION_FAILED",
+              message,
+            }),
+          )
+        }
+      },
+      Err(message) => {
+        return (
+          request.operation_name.clone(),
+          None,
+          Err(GraphqlGuardError {
+            code: "GRAPHQL_PARSE_FAILED",
+            message,
+          }),
+        )
+      }
+    };
+
+    let result = if let Some(max_depth) = self.max_depth.filter(|max_depth| analysis.depth > *max_depth) {
+      Err(GraphqlGuardError {
+        code: "MAX_DEPTH_EXCEEDED",
+        message: format!(
+          "The query depth ({}) exceeds the maximum allowed depth ({max_depth})",
+          analysis.depth
+        ),
+      })
+    } else if let Some(max_complexity) = self
+      .max_complexity
+      .filter(|max_complexity| analysis.complexity > *max_complexity)
+    {
+      Err(GraphqlGuardError {
+        code: "MAX_COMPLEXITY_EXCEEDED",
+        message: format!(
+          "The query complexity ({}) exceeds the maximum allowed complexity ({max_complexity})",
+          analysis.complexity
+        ),
+      })
+    } else {
+      Ok(())
+    };
+    (analysis.name, Some(analysis.operation_type.as_str()), result)
+  }
+}
+
+/// Creates a response with the GraphQL error
+fn graphql_error_response(
+  request: Request<BoxBody<Bytes, std::io::Error>>,
+  error: &GraphqlGuardError,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let body = serde_json::json!({
+    "errors": [{
+      "message": error.message,
+      "extensions": { "code": error.code }
+    }]
+  });
+  Ok(ResponseData {
+    request: Some(request),
+    response: Some(
+      Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(
+          Full::new(Bytes::from(serde_json::to_vec(&body)?))
+            .map_err(|e| match e {})
+            .boxed(),
+        )?,
+    ),
+    response_status: None,
+    response_headers: None,
+    new_remote_address: None,
+  })
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for GraphqlGuardModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    _config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let (request_parts, request_body) = request.into_parts();
+    let content_type = request_parts
+      .headers
+      .get(header::CONTENT_TYPE)
+      .and_then(|v| v.to_str().ok())
+      .and_then(|v| v.split(';').next())
+      .map(|v| v.trim().to_lowercase());
+
+    let (graphql_requests, request_body) = match request_parts.method {
+      Method::GET => match request_parts.uri.query() {
+        Some(query_string)
+          if query_string
+            .split('&')
+            .any(|pair| pair.starts_with("query=") || pair.starts_with("extensions=")) =>
+        {
+          (vec![GraphqlRequest::from_query_string(query_string)], request_body)
+        }
+        // Not a GraphQL request (for example, a request for a GraphQL IDE page)
+        _ => (vec![], request_body),
+      },
+      Method::POST
+        if matches!(
+          content_type.as_deref(),
+          Some("application/json") | Some("application/graphql")
+        ) =>
+      {
+        let body = match Limited::new(request_body, MAX_REQUEST_BODY_SIZE).collect().await {
+          Ok(body) => body.to_bytes(),
+          Err(err) if err.is::<LengthLimitError>() => {
+            return Ok(ResponseData {
+              request: Some(Request::from_parts(
+                request_parts,
+                Full::new(Bytes::new()).map_err(|e| match e {}).boxed(),
+              )),
+              response: None,
+              response_status: Some(StatusCode::PAYLOAD_TOO_LARGE),
+              response_headers: None,
+              new_remote_address: None,
+            });
+          }
+          Err(err) => Err(err)?,
+        };
+        let graphql_requests: Vec<Result<GraphqlRequest, GraphqlGuardError>> =
+          if content_type.as_deref() == Some("application/graphql") {
+            vec![Ok(GraphqlRequest {
+              query: Some(String::from_utf8_lossy(&body).into_owned()),
+              operation_name: None,
+              variables: Value::Null,
+              persisted_query_hash: None,
+            })]
+          } else {
+            match serde_json::from_slice::<Value>(&body) {
+              // Batched GraphQL requests
+              Ok(Value::Array(requests)) => requests.iter().map(GraphqlRequest::from_json).collect(),
+              Ok(request) => vec![GraphqlRequest::from_json(&request)],
+              Err(_) => vec![Err(GraphqlGuardError::bad_request(
+                "The GraphQL request body must be valid JSON",
+              ))],
+            }
+          };
+        (graphql_requests, Full::new(body).map_err(|e| match e {}).boxed())
+      }
+      _ => (vec![], request_body),
+    };
+
+    let request = Request::from_parts(request_parts, request_body);
+    let mut rejection = None;
+    for graphql_request in graphql_requests {
+      let (operation_name, operation_type, result) = match graphql_request {
+        Ok(graphql_request) => self.check_request(&graphql_request),
+        Err(error) => (None, None, Err(error)),
+      };
+      self
+        .metric_operations
+        .push((operation_name.clone(), operation_type, result.is_ok()));
+      if let Err(error) = result {
+        if rejection.is_none() {
+          error_logger
+            .log_with_level(
+              LogLevel::Debug,
+              LOG_TARGET,
+              &format!(
+                "GraphQL operation{} rejected ({}): {}",
+                operation_name.map_or_else(String::new, |name| format!(" \"{name}\"")),
+                error.code,
+                error.message
+              ),
+            )
+            .await;
+          rejection = Some(error);
+        }
+      }
+    }
+
+    match rejection {
+      Some(error) => graphql_error_response(request, &error),
+      None => Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      }),
+    }
+  }
+
+  async fn metric_data_after_handler(&mut self, metrics_sender: &MetricsMultiSender) {
+    for (operation_name, operation_type, accepted) in self.metric_operations.drain(..) {
+      let mut attributes = vec![(
+        "ferron.graphql.result",
+        MetricAttributeValue::String(if accepted { "accepted" } else { "rejected" }.to_string()),
+      )];
+      if let Some(operation_name) = operation_name {
+        attributes.push(("graphql.operation.name", MetricAttributeValue::String(operation_name)));
+      }
+      if let Some(operation_type) = operation_type {
+        attributes.push((
+          "graphql.operation.type",
+          MetricAttributeValue::String(operation_type.to_string()),
+        ));
+      }
+      metrics_sender
+        .send(Metric::new(
+          "ferron.graphql.operations",
+          attributes,
+          MetricType::Counter,
+          MetricValue::U64(1),
+          Some("{operation}"),
+          Some("Number of GraphQL operations inspected by the GraphQL guard."),
+        ))
+        .await;
+    }
+  }
+}
@@ -0,0 +1,618 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use hyper::header::HeaderValue;
+use hyper::{header, HeaderMap, Request, StatusCode};
+use ldap3::{dn_escape, ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, LdapError, Scope, SearchEntry};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::{ModuleCache, TtlCache};
+use ferron_common::{get_entries_for_validation, get_entry, get_value, get_values};
+
+use crate::util::parse_basic_auth;
+
+const DEFAULT_USER_FILTER: &str = "(uid={username})";
+const DEFAULT_POOL_SIZE: usize = 8;
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The LDAP result code for invalid credentials
+const LDAP_INVALID_CREDENTIALS: u32 = 49;
+
+/// LDAP server and directory layout settings
+struct LdapSettings {
+  url: String,
+  starttls: bool,
+  no_verification: bool,
+  timeout: Duration,
+  user_dn: Option<String>,
+  bind_dn: Option<String>,
+  bind_password: Option<String>,
+  base_dn: Option<String>,
+  filter: String,
+  group_base_dn: Option<String>,
+}
+
+/// The result of an LDAP authentication attempt
+enum LdapAuthResult {
+  /// The user is authenticated, with distinguished names of the groups the user is a member of
+  Authenticated(Vec<String>),
+  /// The credentials are invalid
+  InvalidCredentials,
+}
+
+/// A pool of idle LDAP connections
+struct LdapConnectionPool {
+  idle: Mutex<Vec<Ldap>>,
+  max_idle: usize,
+}
+
+impl LdapConnectionPool {
+  /// Obtains an idle LDAP connection, or establishes a new one
+  async fn get(&self, settings: &LdapSettings) -> Result<Ldap, LdapError> {
+    loop {
+      let ldap = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+      let Some(mut ldap) = ldap else {
+        break;
+      };
+      if !ldap.is_closed() {
+        return Ok(ldap);
+      }
+    }
+
+    let connection_settings = LdapConnSettings::new()
+      .set_conn_timeout(settings.timeout)
+      .set_starttls(settings.starttls)
+      .set_no_tls_verify(settings.no_verification);
+    let (connection, ldap) = LdapConnAsync::with_settings(connection_settings, &settings.url).await?;
+    tokio::spawn(async move {
+      let _ = connection.drive().await;
+    });
+    Ok(ldap)
+  }
+
+  /// Returns the LDAP connection back into the pool
+  fn put(&self, mut ldap: Ldap) {
+    if ldap.is_closed() {
+      return;
+    }
+    let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+    if idle.len() < self.max_idle {
+      idle.push(ldap);
+    }
+  }
+}
+
+/// Authenticates the user against the LDAP server, and obtains the groups the user is a member of
+async fn ldap_authenticate(
+  settings: Arc<LdapSettings>,
+  pool: Arc<LdapConnectionPool>,
+  username: String,
+  password: String,
+) -> Result<LdapAuthResult, LdapError> {
+  let mut ldap = pool.get(&settings).await?;
+  let result = ldap_authenticate_with_connection(&mut ldap, &settings, &username, &password).await;
+  if result.is_ok() {
+    pool.put(ldap);
+  }
+  result
+}
+
+/// Authenticates the user using an established LDAP connection
+async fn ldap_authenticate_with_connection(
+  ldap: &mut Ldap,
+  settings: &LdapSettings,
+  username: &str,
+  password: &str,
+) -> Result<LdapAuthResult, LdapError> {
+  let user_dn = if let Some(user_dn_template) = &settings.user_dn {
+    user_dn_template.replace("{username}", &dn_escape(username))
+  } else {
+    if let (Some(bind_dn), Some(bind_password)) = (&settings.bind_dn, &settings.bind_password) {
+      ldap
+        .with_timeout(settings.timeout)
+        .simple_bind(bind_dn, bind_password)
+        .await?
+        .success()?;
+    }
+    let filter = settings.filter.replace("{username}", &ldap_escape(username));
+    let (entries, _) = ldap
+      .with_timeout(settings.timeout)
+      .search(
+        settings.base_dn.as_deref().unwrap_or_default(),
+        Scope::Subtree,
+        &filter,
+        vec!["1.1"],
+      )
+      .await?
+      .success()?;
+    if entries.len() != 1 {
+      // The user doesn't exist, or the filter is ambiguous
+      return Ok(LdapAuthResult::InvalidCredentials);
+    }
+    let Some(entry) = entries.into_iter().next() else {
+      return Ok(LdapAuthResult::InvalidCredentials);
+    };
+    SearchEntry::construct(entry).dn
+  };
+
+  let bind_result = ldap
+    .with_timeout(settings.timeout)
+    .simple_bind(&user_dn, password)
+    .await?;
+  if bind_result.rc == LDAP_INVALID_CREDENTIALS {
+    return Ok(LdapAuthResult::InvalidCredentials);
+  }
+  bind_result.success()?;
+
+  let groups = if let Some(group_base_dn) = &settings.group_base_dn {
+    let filter = format!("(|(member={0})(uniqueMember={0}))", ldap_escape(&user_dn));
+    let (entries, _) = ldap
+      .with_timeout(settings.timeout)
+      .search(group_base_dn, Scope::Subtree, &filter, vec!["1.1"])
+      .await?
+      .success()?;
+    entries
+      .into_iter()
+      .map(|entry| SearchEntry::construct(entry).dn)
+      .collect()
+  } else {
+    let (entries, _) = ldap
+      .with_timeout(settings.timeout)
+      .search(&user_dn, Scope::Base, "(objectClass=*)", vec!["memberOf"])
+      .await?
+      .success()?;
+    entries
+      .into_iter()
+      .flat_map(|entry| {
+        SearchEntry::construct(entry)
+          .attrs
+          .into_iter()
+          .filter(|(name, _)| name.eq_ignore_ascii_case("memberOf"))
+          .flat_map(|(_, values)| values)
+      })
+      .collect()
+  };
+
+  Ok(LdapAuthResult::Authenticated(groups))
+}
+
+/// An LDAP authentication module loader
+pub struct LdapAuthenticationModuleLoader {
+  cache: ModuleCache<LdapAuthenticationModule>,
+  brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+}
+
+impl Default for LdapAuthenticationModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl LdapAuthenticationModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["auth_ldap", "auth_ldap_cache_ttl"]),
+      brute_force_db: Arc::new(RwLock::new(TtlCache::new(Duration::new(300, 0)))),
+    }
+  }
+}
+
+impl ModuleLoader for LdapAuthenticationModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let entry =
+            get_entry!("auth_ldap", config).ok_or_else(|| anyhow::anyhow!("The LDAP server URL must be specified"))?;
+          let prop_string = |name: &str| entry.props.get(name).and_then(|v| v.as_str()).map(|v| v.to_string());
+          let settings = LdapSettings {
+            url: entry
+              .values
+              .first()
+              .and_then(|v| v.as_str())
+              .ok_or_else(|| anyhow::anyhow!("The LDAP server URL must be specified"))?
+              .to_string(),
+            starttls: entry.props.get("starttls").and_then(|v| v.as_bool()).unwrap_or(false),
+            no_verification: entry
+              .props
+              .get("no_verification")
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false),
+            timeout: entry
+              .props
+              .get("timeout")
+              .and_then(|v| v.as_duration())
+              .unwrap_or(DEFAULT_TIMEOUT),
+            user_dn: prop_string("user_dn"),
+            bind_dn: prop_string("bind_dn"),
+            bind_password: prop_string("bind_password"),
+            base_dn: prop_string("base_dn"),
+            filter: prop_string("filter").unwrap_or(DEFAULT_USER_FILTER.to_string()),
+            group_base_dn: prop_string("group_base_dn"),
+          };
+          let pool = LdapConnectionPool {
+            idle: Mutex::new(Vec::new()),
+            max_idle: entry
+              .props
+              .get("pool_size")
+              .and_then(|v| v.as_i128())
+              .map(|v| v as usize)
+              .unwrap_or(DEFAULT_POOL_SIZE),
+          };
+          let auth_cache = get_value!("auth_ldap_cache_ttl", config)
+            .map_or(Some(DEFAULT_CACHE_TTL), |v| {
+              if v.is_null() {
+                None
+              } else {
+                Some(v.as_duration().unwrap_or(DEFAULT_CACHE_TTL))
+              }
+            })
+            .filter(|ttl| !ttl.is_zero())
+            .map(|ttl| Arc::new(RwLock::new(TtlCache::new(ttl))));
+
+          Ok(Arc::new(LdapAuthenticationModule {
+            settings: Arc::new(settings),
+            pool: Arc::new(pool),
+            realm: entry
+              .props
+              .get("realm")
+              .and_then(|v| v.as_str())
+              .map(|v| Arc::new(v.to_string())),
+            brute_force_protection: entry
+              .props
+              .get("brute_protection")
+              .and_then(|v| v.as_bool())
+              .unwrap_or(true),
+            brute_force_db: self.brute_force_db.clone(),
+            auth_cache,
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["auth_ldap"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("auth_ldap", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `auth_ldap` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .is_some_and(|v| v.starts_with("ldap://") || v.starts_with("ldaps://") || v.starts_with("ldapi://"))
+        {
+          Err(anyhow::anyhow!("Invalid LDAP server URL"))?
+        }
+        for prop_name in [
+          "user_dn",
+          "bind_dn",
+          "bind_password",
+          "base_dn",
+          "filter",
+          "group_base_dn",
+          "realm",
+        ] {
+          if !entry.props.get(prop_name).is_none_or(|v| v.is_string()) {
+            Err(anyhow::anyhow!(
+              "The LDAP authentication `{prop_name}` prop must be a string"
+            ))?
+          }
+        }
+        for prop_name in ["starttls", "no_verification", "brute_protection"] {
+          if !entry.props.get(prop_name).is_none_or(|v| v.is_bool()) {
+            Err(anyhow::anyhow!(
+              "The LDAP authentication `{prop_name}` prop must be boolean"
+            ))?
+          }
+        }
+        if entry.values[0].is_string() {
+          let user_dn = entry.props.get("user_dn").and_then(|v| v.as_str());
+          if user_dn.is_some_and(|v| !v.contains("{username}")) {
+            Err(anyhow::anyhow!(
+              "The LDAP user DN template must contain the `{{username}}` placeholder"
+            ))?
+          } else if user_dn.is_none() && !entry.props.contains_key("base_dn") {
+            Err(anyhow::anyhow!(
+              "Either the LDAP user DN template or the LDAP search base DN must be specified"
+            ))?
+          } else if entry.props.contains_key("bind_dn") != entry.props.contains_key("bind_password") {
+            Err(anyhow::anyhow!(
+              "Both the LDAP bind DN and the LDAP bind password must be specified"
+            ))?
+          } else if entry.values[0].as_str().is_some_and(|v| v.starts_with("ldaps://"))
+            && entry.props.get("starttls").and_then(|v| v.as_bool()).unwrap_or(false)
+          {
+            Err(anyhow::anyhow!("StartTLS can't be used with LDAPS"))?
+          }
+        }
+        if let Some(prop) = entry.props.get("pool_size") {
+          if !prop.is_integer() || prop.as_i128().is_some_and(|v| v < 0) {
+            Err(anyhow::anyhow!("Invalid LDAP connection pool size"))?
+          }
+        }
+        if let Some(prop) = entry.props.get("timeout") {
+          if prop.as_duration().is_none_or(|v| v.is_zero()) {
+            Err(anyhow::anyhow!("Invalid LDAP operation timeout"))?
+          }
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("auth_ldap_require_group", config, used_properties) {
+      for entry in &entries.inner {
+        for value in &entry.values {
+          if !value.is_string() {
+            Err(anyhow::anyhow!("Invalid required LDAP group configuration"))?
+          }
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("auth_ldap_cache_ttl", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `auth_ldap_cache_ttl` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid LDAP authentication cache TTL"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// A cache of successful LDAP authentications, keyed by the hash of the credentials, with the groups of the users
+type LdapAuthCache = RwLock<TtlCache<Vec<u8>, Arc<Vec<String>>>>;
+
+/// An LDAP authentication module
+struct LdapAuthenticationModule {
+  settings: Arc<LdapSettings>,
+  pool: Arc<LdapConnectionPool>,
+  realm: Option<Arc<String>>,
+  brute_force_protection: bool,
+  brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+  auth_cache: Option<Arc<LdapAuthCache>>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for LdapAuthenticationModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(LdapAuthenticationModuleHandlers {
+      settings: self.settings.clone(),
+      pool: self.pool.clone(),
+      realm: self.realm.clone(),
+      brute_force_protection: self.brute_force_protection,
+      brute_force_db: self.brute_force_db.clone(),
+      auth_cache: self.auth_cache.clone(),
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the LDAP authentication module
+struct LdapAuthenticationModuleHandlers {
+  settings: Arc<LdapSettings>,
+  pool: Arc<LdapConnectionPool>,
+  realm: Option<Arc<String>>,
+  brute_force_protection: bool,
+  brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+  auth_cache: Option<Arc<LdapAuthCache>>,
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for LdapAuthenticationModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    mut request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let brute_force_db_key = socket_data.remote_addr.ip().to_string();
+    if self.brute_force_protection {
+      let rwlock_read = self.brute_force_db.read().await;
+      let current_attempts = rwlock_read.get(&brute_force_db_key).unwrap_or(0);
+      if current_attempts >= 10 {
+        error_logger
+          .log(&format!(
+            "Too many failed authorization attempts for client \"{}\"",
+            socket_data.remote_addr.ip()
+          ))
+          .await;
+
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::TOO_MANY_REQUESTS),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    }
+    let mut header_map = HeaderMap::new();
+    header_map.insert(
+      header::WWW_AUTHENTICATE,
+      HeaderValue::from_str(&format!(
+        "Basic realm=\"{}\", charset=\"UTF-8\"",
+        self
+          .realm
+          .as_deref()
+          .cloned()
+          .unwrap_or("Ferron HTTP Basic Authorization".to_string())
+          .replace("\\", "\\\\")
+          .replace("\"", "\\\"")
+      ))?,
+    );
+
+    let Some(authorization_header_value) = request.headers().get(header::AUTHORIZATION) else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::UNAUTHORIZED),
+        response_headers: Some(header_map),
+        new_remote_address: None,
+      });
+    };
+    let Ok(authorization_str) = authorization_header_value.to_str() else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::BAD_REQUEST),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+
+    if let Some((username, password)) = parse_basic_auth(authorization_str) {
+      // Empty passwords would result in unauthenticated binds, which always succeed
+      let auth_result = if username.is_empty() || password.is_empty() {
+        LdapAuthResult::InvalidCredentials
+      } else {
+        let auth_cache_key = {
+          let mut hasher = Sha256::new();
+          hasher.update(username.as_bytes());
+          hasher.update([0]);
+          hasher.update(password.as_bytes());
+          hasher.finalize().to_vec()
+        };
+        let cached_groups = match &self.auth_cache {
+          Some(auth_cache) => auth_cache.read().await.get(&auth_cache_key),
+          None => None,
+        };
+        if let Some(cached_groups) = cached_groups {
+          LdapAuthResult::Authenticated(cached_groups.to_vec())
+        } else {
+          // LDAP connections are driven by the Tokio runtime
+          let auth_result = self
+            .runtime
+            .spawn(ldap_authenticate(
+              self.settings.clone(),
+              self.pool.clone(),
+              username.clone(),
+              password,
+            ))
+            .await
+            .map_err(|_| anyhow::anyhow!("Can't spawn a task to authenticate against the LDAP server"))?;
+          match auth_result {
+            Ok(auth_result) => {
+              if let (Some(auth_cache), LdapAuthResult::Authenticated(groups)) = (&self.auth_cache, &auth_result) {
+                let mut rwlock_write = auth_cache.write().await;
+                rwlock_write.cleanup();
+                rwlock_write.insert(auth_cache_key, Arc::new(groups.clone()));
+              }
+              auth_result
+            }
+            Err(err) => {
+              error_logger
+                .log(&format!(
+                  "Can't authenticate the user \"{username}\" against the LDAP server: {err}"
+                ))
+                .await;
+              return Ok(ResponseData {
+                request: Some(request),
+                response: None,
+                response_status: Some(StatusCode::SERVICE_UNAVAILABLE),
+                response_headers: None,
+                new_remote_address: None,
+              });
+            }
+          }
+        }
+      };
+
+      if let LdapAuthResult::Authenticated(groups) = auth_result {
+        let required_groups = get_values!("auth_ldap_require_group", config)
+          .into_iter()
+          .filter_map(|v| v.as_str())
+          .collect::<Vec<_>>();
+        if !required_groups.is_empty()
+          && !required_groups
+            .iter()
+            .any(|required_group| groups.iter().any(|group| group.eq_ignore_ascii_case(required_group)))
+        {
+          error_logger
+            .log(&format!(
+              "User \"{}\" isn't a member of any required LDAP group (client \"{}\")",
+              username,
+              socket_data.remote_addr.ip()
+            ))
+            .await;
+
+          return Ok(ResponseData {
+            request: Some(request),
+            response: None,
+            response_status: Some(StatusCode::FORBIDDEN),
+            response_headers: None,
+            new_remote_address: None,
+          });
+        }
+
+        let request_data = request.extensions_mut().get_mut::<RequestData>();
+        if let Some(request_data) = request_data {
+          request_data.auth_user = Some(username);
+        }
+
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+
+      if self.brute_force_protection {
+        let mut rwlock_write = self.brute_force_db.write().await;
+        rwlock_write.cleanup();
+        let current_attempts = rwlock_write.get(&brute_force_db_key).unwrap_or(0);
+        rwlock_write.insert(brute_force_db_key, current_attempts + 1);
+      }
+
+      error_logger
+        .log(&format!(
+          "Authorization failed for user \"{}\" and client \"{}\"",
+          username,
+          socket_data.remote_addr.ip()
+        ))
+        .await;
+    }
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: Some(StatusCode::UNAUTHORIZED),
+      response_headers: Some(header_map),
+      new_remote_address: None,
+    })
+  }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use hyper::Request;
+use redis::aio::ConnectionManager;
+use tokio::sync::{OnceCell, RwLock};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::{replace_header_placeholders, ModuleCache, TtlCache};
+use ferron_common::{get_entries, get_entries_for_validation};
+
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A lazily established connection to a Redis server
+struct RedisConnection {
+  client: redis::Client,
+  manager: OnceCell<ConnectionManager>,
+}
+
+/// Obtains a value from a Redis server. This function is executed on the Tokio runtime.
+async fn redis_get(
+  connection: Arc<RedisConnection>,
+  key: String,
+  timeout: Duration,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+  let result = tokio::time::timeout(timeout, async {
+    // The connection manager reconnects automatically, so it's established only once
+    let mut manager = connection
+      .manager
+      .get_or_try_init(|| connection.client.get_connection_manager())
+      .await?
+      .clone();
+    redis::cmd("GET")
+      .arg(&key)
+      .query_async::<Option<String>>(&mut manager)
+      .await
+  })
+  .await
+  .map_err(|_| anyhow::anyhow!("The lookup has timed out"))?;
+  Ok(result?)
+}
+
+/// A cache of external lookup results, keyed by the looked up keys. Missing keys are cached as `None`.
+type ExternalLookupCache = RwLock<TtlCache<String, Option<String>>>;
+
+/// An external key-value lookup, which result is stored in a request variable
+struct ExternalLookup {
+  connection: Arc<RedisConnection>,
+  key: String,
+  variable: String,
+  default: Option<String>,
+  timeout: Duration,
+  cache: Option<Arc<ExternalLookupCache>>,
+}
+
+/// An external key-value lookup module loader
+pub struct ExternalMapModuleLoader {
+  cache: ModuleCache<ExternalMapModule>,
+  connections: HashMap<String, Arc<RedisConnection>>,
+}
+
+impl Default for ExternalMapModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ExternalMapModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["map_external"]),
+      connections: HashMap::new(),
+    }
+  }
+}
+
+impl ModuleLoader for ExternalMapModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    let connections = &mut self.connections;
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let mut lookups = Vec::new();
+          for entry in get_entries!("map_external", config).map_or(vec![], |e| e.inner.iter().collect()) {
+            let Some(url) = entry.values.first().and_then(|v| v.as_str()) else {
+              continue;
+            };
+            // Connections are shared between lookups using the same key-value store
+            let connection = match connections.get(url) {
+              Some(connection) => connection.clone(),
+              None => {
+                let connection = Arc::new(RedisConnection {
+                  client: redis::Client::open(url)?,
+                  manager: OnceCell::new(),
+                });
+                connections.insert(url.to_string(), connection.clone());
+                connection
+              }
+            };
+            let cache_ttl = entry.props.get("ttl").map_or(Some(DEFAULT_CACHE_TTL), |v| {
+              if v.is_null() {
+                None
+              } else {
+                Some(v.as_duration().unwrap_or(DEFAULT_CACHE_TTL))
+              }
+            });
+            lookups.push(ExternalLookup {
+              connection,
+              key: entry
+                .props
+                .get("key")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow::anyhow!("The external lookup key must be specified"))?
+                .to_string(),
+              variable: entry
+                .props
+                .get("var")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow::anyhow!("The external lookup variable name must be specified"))?
+                .to_string(),
+              default: entry
+                .props
+                .get("default")
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+              timeout: entry
+                .props
+                .get("timeout")
+                .and_then(|v| v.as_duration())
+                .unwrap_or(DEFAULT_TIMEOUT),
+              cache: cache_ttl
+                .filter(|ttl| !ttl.is_zero())
+                .map(|ttl| Arc::new(RwLock::new(TtlCache::new(ttl)))),
+            });
+          }
+
+          Ok(Arc::new(ExternalMapModule {
+            lookups: Arc::new(lookups),
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["map_external"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("map_external", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `map_external` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_str().is_none_or(|v| redis::Client::open(v).is_err()) {
+          Err(anyhow::anyhow!("Invalid external key-value store URL"))?
+        } else if !entry.props.get("key").is_some_and(|v| v.is_string()) {
+          Err(anyhow::anyhow!("The external lookup key must be a string"))?
+        } else if entry
+          .props
+          .get("var")
+          .is_none_or(|v| v.as_str().is_none_or(|v| v.is_empty()))
+        {
+          Err(anyhow::anyhow!(
+            "The external lookup variable name must be a non-empty string"
+          ))?
+        } else if !entry.props.get("default").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("The external lookup default value must be a string"))?
+        } else if !entry
+          .props
+          .get("ttl")
+          .is_none_or(|v| v.is_null() || v.as_duration().is_some())
+        {
+          Err(anyhow::anyhow!("Invalid external lookup cache TTL"))?
+        } else if entry
+          .props
+          .get("timeout")
+          .is_some_and(|v| v.as_duration().is_none_or(|v| v.is_zero()))
+        {
+          Err(anyhow::anyhow!("Invalid external lookup timeout"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// An external key-value lookup module
+struct ExternalMapModule {
+  lookups: Arc<Vec<ExternalLookup>>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for ExternalMapModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(ExternalMapModuleHandlers {
+      lookups: self.lookups.clone(),
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the external key-value lookup module
+struct ExternalMapModuleHandlers {
+  lookups: Arc<Vec<ExternalLookup>>,
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for ExternalMapModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    _config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let (mut request_parts, request_body) = request.into_parts();
+
+    // Lookups are performed in order, so the lookup keys can refer to variables set by previous lookups
+    for lookup in self.lookups.iter() {
+      let key = replace_header_placeholders(&lookup.key, &request_parts, Some(socket_data));
+      let cached_value = match &lookup.cache {
+        Some(cache) => cache.read().await.get(&key),
+        None => None,
+      };
+      let value = if let Some(cached_value) = cached_value {
+        cached_value
+      } else {
+        // Redis connections are driven by the Tokio runtime
+        let lookup_result = self
+          .runtime
+          .spawn(redis_get(lookup.connection.clone(), key.clone(), lookup.timeout))
+          .await
+          .map_err(|_| anyhow::anyhow!("Can't spawn a task to look up the value in the key-value store"))?;
+        match lookup_result {
+          Ok(value) => {
+            if let Some(cache) = &lookup.cache {
+              let mut rwlock_write = cache.write().await;
+              rwlock_write.cleanup();
+              rwlock_write.insert(key, value.clone());
+            }
+            value
+          }
+          Err(err) => {
+            error_logger
+              .log(&format!(
+                "Can't look up the key \"{key}\" in the external key-value store: {err}"
+              ))
+              .await;
+            None
+          }
+        }
+      };
+
+      if let Some(value) = value.or_else(|| lookup.default.clone()) {
+        if let Some(request_data) = request_parts.extensions.get_mut::<RequestData>() {
+          request_data.variables.insert(lookup.variable.clone(), value);
+        }
+      }
+    }
+
+    Ok(ResponseData {
+      request: Some(Request::from_parts(request_parts, request_body)),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
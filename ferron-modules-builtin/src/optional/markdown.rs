@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Method, Request, Response};
+
+use crate::util::{fill_markdown_template, render_markdown, split_front_matter, DEFAULT_MARKDOWN_TEMPLATE};
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+/// The maximum number of rendered Markdown documents cached per configuration
+const MARKDOWN_CACHE_MAX_ENTRIES: usize = 1024;
+
+/// The maximum size of a Markdown document to be rendered
+const MARKDOWN_MAX_FILE_SIZE: u64 = 4194304;
+
+/// The name of the Markdown index file for directories
+const MARKDOWN_INDEX_FILE: &str = "index.md";
+
+/// A rendered Markdown document
+struct RenderedMarkdown {
+  modified: SystemTime,
+  html: Bytes,
+}
+
+/// A cache of rendered Markdown documents, keyed by paths, and validated against modification times
+type MarkdownCache = quick_cache::sync::Cache<PathBuf, Arc<RenderedMarkdown>>;
+
+/// Resolves the path of the Markdown document for the request path. Returns `None` if the request isn't for a Markdown document.
+fn resolve_markdown_path(root: &Path, request_path: &str, indexes: &[String]) -> Option<PathBuf> {
+  let decoded_path = urlencoding::decode(request_path.trim_start_matches('/')).ok()?;
+  let path = root.join(decoded_path.as_ref());
+  let path = if request_path.ends_with('/') {
+    // Regular index files take precedence over the Markdown index file
+    if indexes.iter().any(|index| path.join(index).is_file()) {
+      return None;
+    }
+    path.join(MARKDOWN_INDEX_FILE)
+  } else if path
+    .extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| e.eq_ignore_ascii_case("md"))
+  {
+    path
+  } else {
+    return None;
+  };
+
+  // Prevent path traversal
+  let canonical_path = path.canonicalize().ok()?;
+  if !canonical_path.starts_with(root.canonicalize().ok()?) || !canonical_path.is_file() {
+    return None;
+  }
+  Some(canonical_path)
+}
+
+/// Renders the Markdown document into an HTML page, using the template
+fn render_markdown_page(path: &Path, template: &str) -> Result<String, std::io::Error> {
+  let document = std::fs::read_to_string(path)?;
+  let (mut front_matter, markdown) = split_front_matter(&document);
+  if !front_matter.iter().any(|(key, _)| key == "title") {
+    // Fall back to the file name for the page title
+    let title = path
+      .file_stem()
+      .and_then(|s| s.to_str())
+      .unwrap_or_default()
+      .to_string();
+    front_matter.push(("title".to_string(), title));
+  }
+  Ok(fill_markdown_template(
+    template,
+    &render_markdown(markdown),
+    &front_matter,
+  ))
+}
+
+/// A Markdown rendering module loader
+pub struct MarkdownModuleLoader {
+  cache: ModuleCache<MarkdownModule>,
+}
+
+impl Default for MarkdownModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MarkdownModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["markdown_template"]),
+    }
+  }
+}
+
+impl ModuleLoader for MarkdownModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let template = match get_value!("markdown_template", config).and_then(|v| v.as_str()) {
+            Some(template_path) => std::fs::read_to_string(template_path)
+              .map_err(|e| anyhow::anyhow!("Can't read the Markdown template \"{template_path}\": {e}"))?,
+            None => DEFAULT_MARKDOWN_TEMPLATE.to_string(),
+          };
+          Ok(Arc::new(MarkdownModule {
+            template: Arc::new(template),
+            rendered_cache: Arc::new(MarkdownCache::new(MARKDOWN_CACHE_MAX_ENTRIES)),
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["markdown"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("markdown", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `markdown` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid Markdown rendering enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("markdown_template", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `markdown_template` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!("The path to the Markdown template must be a string"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A Markdown rendering module
+struct MarkdownModule {
+  template: Arc<String>,
+  rendered_cache: Arc<MarkdownCache>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for MarkdownModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(MarkdownModuleHandlers {
+      template: self.template.clone(),
+      rendered_cache: self.rendered_cache.clone(),
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the Markdown rendering module
+struct MarkdownModuleHandlers {
+  template: Arc<String>,
+  rendered_cache: Arc<MarkdownCache>,
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for MarkdownModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let root = get_entry!("root", config)
+      .and_then(|e| e.values.first())
+      .and_then(|v| v.as_str())
+      .map(PathBuf::from);
+    let Some(root) = root.filter(|_| {
+      get_value!("markdown", config)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        && matches!(request.method(), &Method::GET | &Method::HEAD)
+    }) else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+
+    let request_path = request.uri().path().to_string();
+    let indexes = get_entry!("index", config)
+      .map(|e| e.values.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+      .unwrap_or(vec![
+        "index.html".to_string(),
+        "index.htm".to_string(),
+        "index.xhtml".to_string(),
+      ]);
+    let template = self.template.clone();
+    let rendered_cache = self.rendered_cache.clone();
+
+    // File system operations and rendering are performed on the secondary runtime's blocking threads
+    let rendered = self
+      .runtime
+      .spawn_blocking(move || -> Result<Option<Arc<RenderedMarkdown>>, std::io::Error> {
+        let Some(path) = resolve_markdown_path(&root, &request_path, &indexes) else {
+          return Ok(None);
+        };
+        let metadata = std::fs::metadata(&path)?;
+        if metadata.len() > MARKDOWN_MAX_FILE_SIZE {
+          return Ok(None);
+        }
+        let modified = metadata.modified()?;
+        if let Some(rendered) = rendered_cache.get(&path).filter(|r| r.modified == modified) {
+          return Ok(Some(rendered));
+        }
+        let rendered = Arc::new(RenderedMarkdown {
+          modified,
+          html: Bytes::from(render_markdown_page(&path, &template)?),
+        });
+        rendered_cache.insert(path, rendered.clone());
+        Ok(Some(rendered))
+      })
+      .await
+      .map_err(|_| anyhow::anyhow!("Can't spawn a task to render the Markdown document"))?;
+
+    let rendered = match rendered {
+      Ok(Some(rendered)) => rendered,
+      Ok(None) => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        })
+      }
+      Err(err) => {
+        // Let the static file serving module handle the errors
+        error_logger
+          .log(&format!("Can't render the Markdown document: {err}"))
+          .await;
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    };
+
+    let last_modified = DateTime::<Utc>::from(rendered.modified)
+      .format("%a, %d %b %Y %H:%M:%S GMT")
+      .to_string();
+    let response = Response::builder()
+      .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+      .header(header::CONTENT_LENGTH, rendered.html.len())
+      .header(header::LAST_MODIFIED, last_modified)
+      .body(
+        Full::new(if request.method() == Method::HEAD {
+          Bytes::new()
+        } else {
+          rendered.html.clone()
+        })
+        .map_err(|e| match e {})
+        .boxed(),
+      )?;
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: Some(response),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
@@ -0,0 +1,420 @@
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+
+use crate::util::HlsPlaylistState;
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::{ModuleCache, EMBEDDED_ROOT_SCHEME};
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+/// The default maximum age of playlists and manifests (in seconds)
+const DEFAULT_PLAYLIST_MAX_AGE: u64 = 1;
+
+/// The default maximum age of media segments (in seconds)
+const DEFAULT_SEGMENT_MAX_AGE: u64 = 86400;
+
+/// The default timeout of blocking playlist reloads and blocking segment requests (in milliseconds)
+const DEFAULT_BLOCKING_TIMEOUT: u64 = 6000;
+
+/// The interval of polling for playlist and segment updates
+const BLOCKING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A kind of the media origin resource
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MediaKind {
+  HlsPlaylist,
+  DashManifest,
+  Segment,
+}
+
+impl MediaKind {
+  /// Determines the kind of the media origin resource and its MIME type from the file extension
+  fn from_path(path: &str) -> Option<(Self, &'static str)> {
+    let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match extension.as_str() {
+      "m3u8" => Some((Self::HlsPlaylist, "application/vnd.apple.mpegurl")),
+      "mpd" => Some((Self::DashManifest, "application/dash+xml")),
+      "ts" => Some((Self::Segment, "video/mp2t")),
+      "m4s" => Some((Self::Segment, "video/iso.segment")),
+      "mp4" | "m4v" | "cmfv" => Some((Self::Segment, "video/mp4")),
+      "m4a" | "cmfa" => Some((Self::Segment, "audio/mp4")),
+      "aac" => Some((Self::Segment, "audio/aac")),
+      "vtt" | "webvtt" => Some((Self::Segment, "text/vtt")),
+      _ => None,
+    }
+  }
+}
+
+/// Resolves the path of the media origin resource. Returns `None` for paths escaping the webroot.
+fn resolve_media_path(root: &str, request_path: &str) -> Option<PathBuf> {
+  let decoded_path = urlencoding::decode(request_path.trim_start_matches('/')).ok()?;
+  let relative_path = Path::new(decoded_path.as_ref());
+  if relative_path
+    .components()
+    .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+  {
+    return None;
+  }
+  Some(Path::new(root).join(relative_path))
+}
+
+/// Parses the query parameter from the query string
+fn parse_query_parameter<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+  query?.split('&').find_map(|pair| {
+    pair
+      .split_once('=')
+      .filter(|(key, _)| *key == name)
+      .map(|(_, value)| value)
+  })
+}
+
+/// A media origin module loader
+pub struct MediaOriginModuleLoader {
+  cache: ModuleCache<MediaOriginModule>,
+}
+
+impl Default for MediaOriginModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MediaOriginModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![]),
+    }
+  }
+}
+
+impl ModuleLoader for MediaOriginModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| Ok(Arc::new(MediaOriginModule)))?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["media_origin"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("media_origin", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `media_origin` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid media origin mode enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("media_origin_playlist_max_age", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `media_origin_playlist_max_age` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_duration_in(Duration::from_secs(1)).is_none() {
+          Err(anyhow::anyhow!("Invalid maximum age of playlists and manifests"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("media_origin_segment_max_age", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `media_origin_segment_max_age` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_duration_in(Duration::from_secs(1)).is_none() {
+          Err(anyhow::anyhow!("Invalid maximum age of media segments"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("media_origin_cors_origin", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `media_origin_cors_origin` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && entry.values[0]
+            .as_str()
+            .is_none_or(|v| HeaderValue::from_str(v).is_err())
+        {
+          Err(anyhow::anyhow!("Invalid allowed origin for media origin CORS headers"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("media_origin_blocking_timeout", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `media_origin_blocking_timeout` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!(
+            "Invalid timeout of blocking playlist reloads and segment requests"
+          ))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A media origin module
+struct MediaOriginModule;
+
+impl Module for MediaOriginModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(MediaOriginModuleHandlers {
+      media_kind: None,
+      cache_control: None,
+      cors_origin: None,
+    })
+  }
+}
+
+/// Handlers for the media origin module
+struct MediaOriginModuleHandlers {
+  media_kind: Option<(MediaKind, &'static str)>,
+  cache_control: Option<String>,
+  cors_origin: Option<String>,
+}
+
+impl MediaOriginModuleHandlers {
+  /// Waits for the playlist to contain the requested segment (LL-HLS blocking playlist reload).
+  /// Returns the error status code, if the playlist reload can't be blocked.
+  async fn block_playlist_reload(
+    &self,
+    path: PathBuf,
+    media_sequence: u64,
+    part: Option<u64>,
+    timeout: Duration,
+  ) -> Option<StatusCode> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      let playlist_path = path.clone();
+      let playlist = ferron_common::runtime::spawn_blocking(move || std::fs::read_to_string(playlist_path))
+        .await
+        .unwrap_or(Err(std::io::Error::other(
+          "Can't spawn a blocking task to read the playlist",
+        )));
+      let Ok(playlist) = playlist else {
+        // Let the static file serving module handle the errors
+        return None;
+      };
+      let state = HlsPlaylistState::parse(&playlist);
+      if state.contains(media_sequence, part) {
+        return None;
+      } else if state.is_too_far_ahead(media_sequence) {
+        return Some(StatusCode::BAD_REQUEST);
+      } else if Instant::now() >= deadline {
+        return Some(StatusCode::SERVICE_UNAVAILABLE);
+      }
+      ferron_common::runtime::sleep(BLOCKING_POLL_INTERVAL).await;
+    }
+  }
+
+  /// Waits for the media segment to be created (for example, a partial segment advertised with a preload hint)
+  async fn block_segment_request(&self, path: PathBuf, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+      let segment_path = path.clone();
+      if ferron_common::runtime::spawn_blocking(move || segment_path.exists())
+        .await
+        .unwrap_or(true)
+      {
+        return;
+      }
+      ferron_common::runtime::sleep(BLOCKING_POLL_INTERVAL).await;
+    }
+  }
+
+  /// Adds the CORS headers to the response headers
+  fn add_cors_headers(&self, headers: &mut hyper::HeaderMap) -> Result<(), header::InvalidHeaderValue> {
+    if let Some(cors_origin) = &self.cors_origin {
+      headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_str(cors_origin)?);
+      headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("Content-Length, Content-Range, Date"),
+      );
+    }
+    Ok(())
+  }
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for MediaOriginModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    if !get_value!("media_origin", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    self.cors_origin = match get_entry!("media_origin_cors_origin", config).and_then(|e| e.values.first()) {
+      Some(value) => value.as_str().map(String::from),
+      None => Some("*".to_string()),
+    };
+
+    // Answer CORS preflight requests
+    if request.method() == Method::OPTIONS && self.cors_origin.is_some() {
+      let mut response = Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, OPTIONS")
+        .header(
+          header::ACCESS_CONTROL_ALLOW_HEADERS,
+          request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned()
+            .unwrap_or(HeaderValue::from_static("Range")),
+        )
+        .header(header::ACCESS_CONTROL_MAX_AGE, "86400")
+        .body(Empty::new().map_err(|e| match e {}).boxed())?;
+      self.add_cors_headers(response.headers_mut())?;
+      return Ok(ResponseData {
+        request: Some(request),
+        response: Some(response),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    self.media_kind = MediaKind::from_path(request.uri().path());
+    self.cache_control = self.media_kind.map(|(media_kind, _)| match media_kind {
+      MediaKind::HlsPlaylist | MediaKind::DashManifest => format!(
+        "public, max-age={}",
+        get_value!("media_origin_playlist_max_age", config)
+          .and_then(|v| v.as_duration_in(Duration::from_secs(1)))
+          .map_or(DEFAULT_PLAYLIST_MAX_AGE, |v| v.as_secs())
+      ),
+      MediaKind::Segment => format!(
+        "public, max-age={}, immutable",
+        get_value!("media_origin_segment_max_age", config)
+          .and_then(|v| v.as_duration_in(Duration::from_secs(1)))
+          .map_or(DEFAULT_SEGMENT_MAX_AGE, |v| v.as_secs())
+      ),
+    });
+
+    let timeout = get_value!("media_origin_blocking_timeout", config)
+      .and_then(|v| v.as_duration())
+      .unwrap_or(Duration::from_millis(DEFAULT_BLOCKING_TIMEOUT));
+    let path = get_entry!("root", config)
+      .and_then(|e| e.values.first())
+      .and_then(|v| v.as_str())
+      .filter(|root| !root.starts_with(EMBEDDED_ROOT_SCHEME))
+      .and_then(|root| resolve_media_path(root, request.uri().path()));
+    if let (Some(path), Some((media_kind, _)), false) = (path, self.media_kind, timeout.is_zero()) {
+      match media_kind {
+        MediaKind::HlsPlaylist => {
+          let query = request.uri().query();
+          if let Some(media_sequence) = parse_query_parameter(query, "_HLS_msn") {
+            let media_sequence = media_sequence.parse::<u64>().ok();
+            let part = parse_query_parameter(query, "_HLS_part").map(|part| part.parse::<u64>().ok());
+            let status = match (media_sequence, part) {
+              (Some(media_sequence), None) => self.block_playlist_reload(path, media_sequence, None, timeout).await,
+              (Some(media_sequence), Some(Some(part))) => {
+                self
+                  .block_playlist_reload(path, media_sequence, Some(part), timeout)
+                  .await
+              }
+              _ => Some(StatusCode::BAD_REQUEST),
+            };
+            if let Some(status) = status {
+              let mut header_map = hyper::HeaderMap::new();
+              self.add_cors_headers(&mut header_map)?;
+              header_map.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+              return Ok(ResponseData {
+                request: Some(request),
+                response: None,
+                response_status: Some(status),
+                response_headers: Some(header_map),
+                new_remote_address: None,
+              });
+            }
+          }
+        }
+        MediaKind::Segment => self.block_segment_request(path, timeout).await,
+        MediaKind::DashManifest => (),
+      }
+    }
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    mut response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    self.add_cors_headers(response.headers_mut())?;
+
+    if let Some((_, content_type)) = self.media_kind {
+      let status = response.status();
+      let headers = response.headers_mut();
+      if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        if let Some(cache_control) = &self.cache_control {
+          headers.insert(header::CACHE_CONTROL, HeaderValue::from_str(cache_control)?);
+        }
+        if status != StatusCode::NOT_MODIFIED {
+          headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        }
+      } else {
+        // Don't let caches keep errors for media that can appear later
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+      }
+    }
+
+    Ok(response)
+  }
+}
@@ -1,9 +1,17 @@
+#[cfg(feature = "accounting")]
+mod accounting;
 #[cfg(feature = "cache")]
 mod cache;
+#[cfg(feature = "capture")]
+mod capture;
 #[cfg(feature = "cgi")]
 mod cgi;
 #[cfg(feature = "dcompress")]
 mod dcompress;
+#[cfg(feature = "echo")]
+mod echo;
+#[cfg(feature = "fault_injection")]
+mod fault_injection;
 #[cfg(feature = "fauth")]
 mod fauth;
 #[cfg(feature = "fcgi")]
@@ -12,23 +20,61 @@ mod fcgi;
 mod fproxy;
 #[cfg(feature = "fproxyauth")]
 mod fproxyauth;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "ldap")]
+mod ldap;
 #[cfg(feature = "limit")]
 mod limit;
+#[cfg(feature = "map_external")]
+mod map_external;
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "mediaorigin")]
+mod mediaorigin;
+#[cfg(feature = "opa")]
+mod opa;
+#[cfg(feature = "openapi")]
+mod openapi;
+#[cfg(feature = "profiling")]
+mod profiling;
+#[cfg(feature = "pseudostream")]
+mod pseudostream;
+#[cfg(feature = "quota")]
+mod quota;
+#[cfg(feature = "redact")]
+mod redact;
 #[cfg(feature = "replace")]
 mod replace;
 #[cfg(feature = "rproxy")]
 mod rproxy;
 #[cfg(feature = "scgi")]
 mod scgi;
+#[cfg(feature = "sitemap")]
+mod sitemap;
 #[cfg(feature = "static")]
 mod r#static;
+#[cfg(feature = "templates")]
+mod templates;
+#[cfg(feature = "transform")]
+mod transform;
+#[cfg(feature = "try_backends")]
+mod try_backends;
 
+#[cfg(feature = "accounting")]
+pub use accounting::*;
 #[cfg(feature = "cache")]
 pub use cache::*;
+#[cfg(feature = "capture")]
+pub use capture::*;
 #[cfg(feature = "cgi")]
 pub use cgi::*;
 #[cfg(feature = "dcompress")]
 pub use dcompress::*;
+#[cfg(feature = "echo")]
+pub use echo::*;
+#[cfg(feature = "fault_injection")]
+pub use fault_injection::*;
 #[cfg(feature = "fauth")]
 pub use fauth::*;
 #[cfg(feature = "fcgi")]
@@ -37,13 +83,43 @@ pub use fcgi::*;
 pub use fproxy::*;
 #[cfg(feature = "fproxyauth")]
 pub use fproxyauth::*;
+#[cfg(feature = "graphql")]
+pub use graphql::*;
+#[cfg(feature = "ldap")]
+pub use ldap::*;
 #[cfg(feature = "limit")]
 pub use limit::*;
+#[cfg(feature = "map_external")]
+pub use map_external::*;
+#[cfg(feature = "markdown")]
+pub use markdown::*;
+#[cfg(feature = "mediaorigin")]
+pub use mediaorigin::*;
+#[cfg(feature = "opa")]
+pub use opa::*;
+#[cfg(feature = "openapi")]
+pub use openapi::*;
+#[cfg(feature = "profiling")]
+pub use profiling::*;
+#[cfg(feature = "pseudostream")]
+pub use pseudostream::*;
+#[cfg(feature = "quota")]
+pub use quota::*;
 #[cfg(feature = "static")]
 pub use r#static::*;
+#[cfg(feature = "redact")]
+pub use redact::*;
 #[cfg(feature = "replace")]
 pub use replace::*;
 #[cfg(feature = "rproxy")]
 pub use rproxy::*;
 #[cfg(feature = "scgi")]
 pub use scgi::*;
+#[cfg(feature = "sitemap")]
+pub use sitemap::*;
+#[cfg(feature = "templates")]
+pub use templates::*;
+#[cfg(feature = "transform")]
+pub use transform::*;
+#[cfg(feature = "try_backends")]
+pub use try_backends::*;
@@ -0,0 +1,432 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use ferron_common::logging::ErrorLogger;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Method, Request, StatusCode, Uri};
+use tokio::sync::RwLock;
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::http_proxy::{Connections, ProxyHostHeader, ReverseProxy, ReverseProxyHandler};
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::{ModuleCache, TtlCache};
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+use crate::util::decode_bearer_claims;
+
+const DEFAULT_CONCURRENT_CONNECTIONS: usize = 16384;
+const DEFAULT_KEEPALIVE_IDLE_TIMEOUT: Duration = Duration::from_millis(60000);
+
+/// Obtains the authorization decision from the Open Policy Agent response body.
+/// Both boolean results and objects with an `allow` boolean field are supported.
+fn parse_opa_decision(body: &[u8]) -> Option<bool> {
+  let response = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+  match response.get("result") {
+    Some(serde_json::Value::Bool(allow)) => Some(*allow),
+    Some(serde_json::Value::Object(result)) => result.get("allow").and_then(|v| v.as_bool()),
+    // An undefined decision (for example, when no rule matched) denies the request
+    None => Some(false),
+    _ => None,
+  }
+}
+
+/// An Open Policy Agent authorization module loader
+pub struct OpaAuthorizationModuleLoader {
+  cache: ModuleCache<OpaAuthorizationModule>,
+  connections: Option<Connections>,
+}
+
+impl Default for OpaAuthorizationModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl OpaAuthorizationModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![
+        "authz_opa",
+        "authz_opa_cache_ttl",
+        "authz_opa_concurrent_conns",
+        "authz_opa_fail_open",
+        "authz_opa_no_verification",
+      ]),
+      connections: None,
+    }
+  }
+}
+
+impl ModuleLoader for OpaAuthorizationModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    let concurrency_limit = global_config
+      .and_then(|c| get_value!("authz_opa_concurrent_conns", c))
+      .map_or(Some(DEFAULT_CONCURRENT_CONNECTIONS), |v| {
+        if v.is_null() {
+          None
+        } else {
+          Some(
+            v.as_i128()
+              .map(|v| v as usize)
+              .unwrap_or(DEFAULT_CONCURRENT_CONNECTIONS),
+          )
+        }
+      });
+    let connections = self.connections.get_or_insert(if let Some(limit) = concurrency_limit {
+      Connections::with_global_limit(limit)
+    } else {
+      Connections::new()
+    });
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let mut policy_path = "/".to_string();
+          let mut proxy_builder = connections.get_builder();
+          if let Some(entry) = get_entry!("authz_opa", config) {
+            if let Some(policy_url) = entry.values.first().and_then(|v| v.as_str()) {
+              // The policy path is sent as the request path, since the reverse proxy prepends the URL path
+              let policy_url = policy_url.parse::<Uri>()?;
+              let (Some(scheme), Some(authority)) = (policy_url.scheme_str(), policy_url.authority()) else {
+                return Err(anyhow::anyhow!("Invalid Open Policy Agent policy URL").into());
+              };
+              if let Some(path_and_query) = policy_url.path_and_query() {
+                policy_path = path_and_query.to_string();
+              }
+              proxy_builder = proxy_builder.upstream(
+                format!("{scheme}://{authority}"),
+                entry.props.get("unix").and_then(|v| v.as_str()).map(|s| s.to_owned()),
+                entry.props.get("limit").and_then(|v| v.as_i128()).map(|v| v as usize),
+                entry
+                  .props
+                  .get("idle_timeout")
+                  .map_or(Some(DEFAULT_KEEPALIVE_IDLE_TIMEOUT), |v| {
+                    if v.is_null() {
+                      None
+                    } else {
+                      Some(v.as_duration().unwrap_or(DEFAULT_KEEPALIVE_IDLE_TIMEOUT))
+                    }
+                  }),
+              );
+            }
+          }
+          let proxy = proxy_builder
+            .proxy_no_verification(
+              get_value!("authz_opa_no_verification", config)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            )
+            .proxy_host_header(Some(ProxyHostHeader::Upstream))
+            .build();
+
+          let decision_cache = get_value!("authz_opa_cache_ttl", config)
+            .and_then(|v| v.as_duration())
+            .filter(|ttl| !ttl.is_zero())
+            .map(|ttl| Arc::new(RwLock::new(TtlCache::new(ttl))));
+
+          Ok(Arc::new(OpaAuthorizationModule {
+            proxy,
+            policy_path,
+            decision_cache,
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["authz_opa"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("authz_opa", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `authz_opa` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .and_then(|v| v.parse::<Uri>().ok())
+            .is_some_and(|v| v.scheme().is_some() && v.authority().is_some())
+        {
+          Err(anyhow::anyhow!("Invalid Open Policy Agent policy URL"))?
+        }
+        if let Some(prop) = entry.props.get("limit") {
+          if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
+            Err(anyhow::anyhow!(
+              "Invalid Open Policy Agent connection limit for a policy server"
+            ))?
+          }
+        }
+        if let Some(prop) = entry.props.get("idle_timeout") {
+          if !prop.is_null() && prop.as_duration().is_none_or(|v| v.is_zero()) {
+            Err(anyhow::anyhow!(
+              "Invalid Open Policy Agent idle keep-alive connection timeout for a policy server"
+            ))?
+          }
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("authz_opa_no_verification", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `authz_opa_no_verification` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid Open Policy Agent server certificate verification option"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("authz_opa_fail_open", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `authz_opa_fail_open` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid Open Policy Agent fail-open option"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("authz_opa_cache_ttl", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `authz_opa_cache_ttl` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid Open Policy Agent decision cache TTL"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("authz_opa_concurrent_conns", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          return Err(
+            anyhow::anyhow!("The `authz_opa_concurrent_conns` configuration property must have exactly one value")
+              .into(),
+          );
+        } else if (!entry.values[0].is_integer() && !entry.values[0].is_null())
+          || entry.values[0].as_i128().is_some_and(|v| v < 0)
+        {
+          return Err(
+            anyhow::anyhow!("Invalid global maximum concurrent connections for Open Policy Agent configuration").into(),
+          );
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// An Open Policy Agent authorization module
+struct OpaAuthorizationModule {
+  proxy: ReverseProxy,
+  policy_path: String,
+  decision_cache: Option<Arc<RwLock<TtlCache<String, bool>>>>,
+}
+
+impl Module for OpaAuthorizationModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(OpaAuthorizationModuleHandlers {
+      inner: self.proxy.get_handler(),
+      policy_path: self.policy_path.clone(),
+      decision_cache: self.decision_cache.clone(),
+    })
+  }
+}
+
+/// Open Policy Agent authorization module handlers
+struct OpaAuthorizationModuleHandlers {
+  inner: ReverseProxyHandler,
+  policy_path: String,
+  decision_cache: Option<Arc<RwLock<TtlCache<String, bool>>>>,
+}
+
+impl OpaAuthorizationModuleHandlers {
+  /// Queries the Open Policy Agent for an authorization decision.
+  /// Returns `None` if the decision couldn't be obtained.
+  async fn query_decision(
+    &mut self,
+    input: String,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<Option<bool>, Box<dyn Error + Send + Sync>> {
+    let opa_request = Request::builder()
+      .method(Method::POST)
+      .uri(&self.policy_path)
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::ACCEPT, "application/json")
+      .body(Full::new(Bytes::from(input)).map_err(|e| match e {}).boxed())?;
+
+    let opa_response = match self
+      .inner
+      .request_handler(opa_request, config, socket_data, error_logger)
+      .await
+    {
+      Ok(opa_response) => opa_response,
+      Err(err) => {
+        error_logger
+          .log(&format!("Can't connect to Open Policy Agent: {err}"))
+          .await;
+        return Ok(None);
+      }
+    };
+
+    let Some(opa_response) = opa_response.response else {
+      error_logger
+        .log(&format!(
+          "Can't obtain the authorization decision from Open Policy Agent (status code {})",
+          opa_response.response_status.unwrap_or(StatusCode::BAD_GATEWAY).as_u16()
+        ))
+        .await;
+      return Ok(None);
+    };
+
+    if !opa_response.status().is_success() {
+      error_logger
+        .log(&format!(
+          "Open Policy Agent responded with an unexpected status code {}",
+          opa_response.status().as_u16()
+        ))
+        .await;
+      return Ok(None);
+    }
+
+    let body = match opa_response.into_body().collect().await {
+      Ok(body) => body.to_bytes(),
+      Err(err) => {
+        error_logger
+          .log(&format!("Can't read the Open Policy Agent response: {err}"))
+          .await;
+        return Ok(None);
+      }
+    };
+
+    let decision = parse_opa_decision(&body);
+    if decision.is_none() {
+      error_logger
+        .log("Open Policy Agent returned an invalid authorization decision")
+        .await;
+    }
+    Ok(decision)
+  }
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for OpaAuthorizationModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let fail_open = get_value!("authz_opa_fail_open", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in request.headers() {
+      let value = String::from_utf8_lossy(value.as_bytes());
+      headers
+        .entry(name.as_str().to_string())
+        .and_modify(|v| {
+          v.push_str(", ");
+          v.push_str(&value);
+        })
+        .or_insert_with(|| value.to_string());
+    }
+
+    let claims = request
+      .headers()
+      .get(header::AUTHORIZATION)
+      .and_then(|v| v.to_str().ok())
+      .and_then(decode_bearer_claims);
+
+    let user = request
+      .extensions()
+      .get::<RequestData>()
+      .and_then(|d| d.auth_user.clone());
+
+    let input = serde_json::json!({
+      "input": {
+        "method": request.method().as_str(),
+        "path": request.uri().path(),
+        "query": request.uri().query(),
+        "headers": headers,
+        "client_ip": socket_data.remote_addr.ip().to_canonical().to_string(),
+        "encrypted": socket_data.encrypted,
+        "user": user,
+        "claims": claims,
+      }
+    })
+    .to_string();
+
+    let cached_decision = match &self.decision_cache {
+      Some(decision_cache) => decision_cache.read().await.get(&input),
+      None => None,
+    };
+
+    let allowed = match cached_decision {
+      Some(allowed) => allowed,
+      None => match self
+        .query_decision(input.clone(), config, socket_data, error_logger)
+        .await?
+      {
+        Some(allowed) => {
+          if let Some(decision_cache) = &self.decision_cache {
+            let mut write_rwlock = decision_cache.write().await;
+            write_rwlock.cleanup();
+            write_rwlock.insert(input, allowed);
+          }
+          allowed
+        }
+        None => fail_open,
+      },
+    };
+
+    if allowed {
+      Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      })
+    } else {
+      Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::FORBIDDEN),
+        response_headers: None,
+        new_remote_address: None,
+      })
+    }
+  }
+}
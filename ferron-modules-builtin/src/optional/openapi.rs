@@ -0,0 +1,261 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{header, Request, Response, StatusCode};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::{ErrorLogger, LogLevel};
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry};
+
+use crate::util::{OpenApiSpec, OpenApiValidationError};
+
+/// The default maximum size of a request body validated against the OpenAPI specification
+const DEFAULT_MAX_BODY_SIZE: usize = 1048576;
+
+/// The target (module name) of leveled log messages logged by the OpenAPI validation module
+const LOG_TARGET: &str = "openapi";
+
+/// An OpenAPI request validation module loader
+pub struct OpenApiValidationModuleLoader {
+  cache: ModuleCache<OpenApiValidationModule>,
+}
+
+impl Default for OpenApiValidationModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl OpenApiValidationModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["openapi"]),
+    }
+  }
+}
+
+impl ModuleLoader for OpenApiValidationModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let entry = get_entry!("openapi", config);
+          let spec = match entry.and_then(|e| e.values.first()).and_then(|v| v.as_str()) {
+            Some(spec_path) => {
+              let spec_content = std::fs::read_to_string(spec_path)
+                .map_err(|err| anyhow::anyhow!("Cannot read the OpenAPI specification \"{spec_path}\": {err}"))?;
+              let base_path = entry.and_then(|e| e.props.get("base_path")).and_then(|v| v.as_str());
+              Some(Arc::new(
+                OpenApiSpec::parse(&spec_content, base_path)
+                  .map_err(|err| anyhow::anyhow!("{err} (in \"{spec_path}\")"))?,
+              ))
+            }
+            None => None,
+          };
+          Ok(Arc::new(OpenApiValidationModule {
+            spec,
+            validate_body: entry
+              .and_then(|e| e.props.get("body"))
+              .and_then(|v| v.as_bool())
+              .unwrap_or(true),
+            max_body_size: entry
+              .and_then(|e| e.props.get("max_body_size"))
+              .and_then(|v| v.as_size())
+              .map_or(DEFAULT_MAX_BODY_SIZE, |v| v as usize),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["openapi"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("openapi", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `openapi` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!(
+            "The path to the OpenAPI specification must be a string"
+          ))?
+        } else if !entry
+          .props
+          .get("base_path")
+          .is_none_or(|v| v.as_str().is_some_and(|v| v.is_empty() || v.starts_with('/')))
+        {
+          Err(anyhow::anyhow!("Invalid OpenAPI base path"))?
+        } else if !entry.props.get("body").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!(
+            "Invalid OpenAPI request body validation enabling option"
+          ))?
+        } else if entry
+          .props
+          .get("max_body_size")
+          .is_some_and(|v| v.as_size().is_none_or(|v| v == 0))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid maximum size of a request body validated against the OpenAPI specification"
+          ))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// An OpenAPI request validation module
+struct OpenApiValidationModule {
+  spec: Option<Arc<OpenApiSpec>>,
+  validate_body: bool,
+  max_body_size: usize,
+}
+
+impl Module for OpenApiValidationModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(OpenApiValidationModuleHandlers {
+      spec: self.spec.clone(),
+      validate_body: self.validate_body,
+      max_body_size: self.max_body_size,
+    })
+  }
+}
+
+/// OpenAPI request validation module handlers
+struct OpenApiValidationModuleHandlers {
+  spec: Option<Arc<OpenApiSpec>>,
+  validate_body: bool,
+  max_body_size: usize,
+}
+
+/// Creates a response with the validation error serialized as problem details (RFC 9457)
+fn validation_error_response(
+  request: Request<BoxBody<Bytes, std::io::Error>>,
+  error: &OpenApiValidationError,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let mut response_builder = Response::builder()
+    .status(error.status)
+    .header(header::CONTENT_TYPE, "application/problem+json");
+  if !error.allowed_methods.is_empty() {
+    response_builder = response_builder.header(header::ALLOW, error.allowed_methods.join(", "));
+  }
+  Ok(ResponseData {
+    request: Some(request),
+    response: Some(
+      response_builder.body(
+        Full::new(Bytes::from(serde_json::to_vec(&error.to_problem_details())?))
+          .map_err(|e| match e {})
+          .boxed(),
+      )?,
+    ),
+    response_status: None,
+    response_headers: None,
+    new_remote_address: None,
+  })
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for OpenApiValidationModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    _config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let Some(spec) = self.spec.clone() else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+
+    let (request_parts, request_body) = request.into_parts();
+    let validation_result = spec
+      .match_operation(&request_parts.method, request_parts.uri.path())
+      .and_then(|operation| operation.validate_parameters(&request_parts).map(|_| operation));
+
+    let (validation_result, request_body) = match validation_result {
+      Ok(operation) if self.validate_body && operation.has_request_body() => {
+        let body = match Limited::new(request_body, self.max_body_size).collect().await {
+          Ok(body) => body.to_bytes(),
+          Err(err) if err.is::<LengthLimitError>() => {
+            return Ok(ResponseData {
+              request: Some(Request::from_parts(
+                request_parts,
+                Full::new(Bytes::new()).map_err(|e| match e {}).boxed(),
+              )),
+              response: None,
+              response_status: Some(StatusCode::PAYLOAD_TOO_LARGE),
+              response_headers: None,
+              new_remote_address: None,
+            });
+          }
+          Err(err) => Err(err)?,
+        };
+        let validation_result = operation.validate_body(
+          request_parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok()),
+          &body,
+        );
+        (validation_result, Full::new(body).map_err(|e| match e {}).boxed())
+      }
+      validation_result => (validation_result.map(|_| ()), request_body),
+    };
+
+    let request = Request::from_parts(request_parts, request_body);
+    match validation_result {
+      Ok(()) => Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      }),
+      Err(error) => {
+        error_logger
+          .log_with_level(
+            LogLevel::Debug,
+            LOG_TARGET,
+            &format!(
+              "Request rejected by OpenAPI validation ({}{}): {}",
+              error.status.as_u16(),
+              error
+                .location
+                .as_ref()
+                .map_or_else(String::new, |location| format!(", at {location}")),
+              error.message
+            ),
+          )
+          .await;
+        validation_error_response(request, &error)
+      }
+    }
+  }
+}
@@ -0,0 +1,519 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Method, Request, Response, StatusCode};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+/// The path prefix of the profiling endpoints
+const PPROF_PATH_PREFIX: &str = "/debug/pprof/";
+
+/// The default duration of CPU profiles (in seconds)
+const DEFAULT_CPU_PROFILE_SECONDS: u64 = 30;
+
+/// The maximum duration of CPU profiles (in seconds)
+const MAX_CPU_PROFILE_SECONDS: u64 = 300;
+
+/// The default sampling frequency of CPU profiles (in hertz)
+const DEFAULT_CPU_PROFILE_FREQUENCY: i32 = 99;
+
+/// The maximum sampling frequency of CPU profiles (in hertz)
+const MAX_CPU_PROFILE_FREQUENCY: i32 = 1000;
+
+/// Whether a CPU profile is being collected. Only one CPU profile can be collected at once.
+static CPU_PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// A guard marking that a CPU profile is being collected, until it's dropped
+struct CpuProfilingGuard;
+
+impl CpuProfilingGuard {
+  /// Marks that a CPU profile is being collected. Returns `None` if another CPU profile is being collected.
+  fn acquire() -> Option<Self> {
+    if CPU_PROFILING.swap(true, Ordering::AcqRel) {
+      None
+    } else {
+      Some(Self)
+    }
+  }
+}
+
+impl Drop for CpuProfilingGuard {
+  fn drop(&mut self) {
+    CPU_PROFILING.store(false, Ordering::Release);
+  }
+}
+
+/// The output format of CPU profiles
+#[derive(Clone, Copy)]
+enum CpuProfileFormat {
+  /// Protocol Buffers-encoded profile, which can be read by `go tool pprof`
+  Pprof,
+  /// Flame graph as an SVG image
+  Flamegraph,
+}
+
+/// Collects a CPU profile of the whole process. This function blocks for the profile duration.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn collect_cpu_profile(
+  duration: Duration,
+  frequency: i32,
+  format: CpuProfileFormat,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+  use pprof::protos::Message;
+
+  let guard = pprof::ProfilerGuardBuilder::default()
+    .frequency(frequency)
+    .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+    .build()?;
+  std::thread::sleep(duration);
+  let report = guard.report().build()?;
+
+  let mut output = Vec::new();
+  match format {
+    CpuProfileFormat::Pprof => report.pprof()?.encode(&mut output)?,
+    CpuProfileFormat::Flamegraph => report.flamegraph(&mut output)?,
+  }
+  Ok(output)
+}
+
+/// Collects a CPU profile of the whole process. CPU profiling isn't supported on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn collect_cpu_profile(
+  _duration: Duration,
+  _frequency: i32,
+  _format: CpuProfileFormat,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+  Err(anyhow::anyhow!("CPU profiling isn't supported on this platform"))?
+}
+
+/// Obtains the task metrics of a Tokio runtime
+fn runtime_task_metrics(handle: &tokio::runtime::Handle) -> serde_json::Value {
+  let metrics = handle.metrics();
+  serde_json::json!({
+    "workers": metrics.num_workers(),
+    "alive_tasks": metrics.num_alive_tasks(),
+    "global_queue_depth": metrics.global_queue_depth(),
+  })
+}
+
+/// Obtains a query parameter from the query string
+fn get_query_parameter<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+  query?.split('&').find_map(|pair| {
+    pair
+      .split_once('=')
+      .filter(|(key, _)| *key == name)
+      .map(|(_, value)| value)
+  })
+}
+
+/// Builds a response with the specified status code, content type and body
+fn build_response(
+  status: StatusCode,
+  content_type: &'static str,
+  body: impl Into<Bytes>,
+) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error + Send + Sync>> {
+  Ok(
+    Response::builder()
+      .status(status)
+      .header(header::CONTENT_TYPE, content_type)
+      .header(header::CACHE_CONTROL, "no-store")
+      .body(Full::new(body.into()).map_err(|e| match e {}).boxed())?,
+  )
+}
+
+/// A profiling module loader
+pub struct ProfilingModuleLoader {
+  cache: ModuleCache<ProfilingModule>,
+}
+
+impl Default for ProfilingModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ProfilingModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["pprof"]),
+    }
+  }
+}
+
+impl ModuleLoader for ProfilingModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          Ok(Arc::new(ProfilingModule {
+            allowed_users: Arc::new(
+              get_entry!("pprof", config)
+                .and_then(|e| e.props.get("users"))
+                .and_then(|v| v.as_str())
+                .map(|v| v.split(',').map(ToString::to_string).collect::<Vec<_>>())
+                .unwrap_or_default(),
+            ),
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["pprof"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("pprof", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `pprof` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid profiling endpoint enabling option"))?
+        } else if !entry.props.get("users").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The profiling endpoint allowed users list must be a string"
+          ))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A profiling module
+struct ProfilingModule {
+  allowed_users: Arc<Vec<String>>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for ProfilingModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(ProfilingModuleHandlers {
+      allowed_users: self.allowed_users.clone(),
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the profiling module
+struct ProfilingModuleHandlers {
+  allowed_users: Arc<Vec<String>>,
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for ProfilingModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let enabled = get_value!("pprof", config).and_then(|v| v.as_bool()).unwrap_or(false);
+    let Some(endpoint) = request
+      .uri()
+      .path()
+      .strip_prefix(PPROF_PATH_PREFIX)
+      .filter(|_| enabled && request.method() == Method::GET)
+      .map(|endpoint| endpoint.to_string())
+    else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+
+    // The profiling endpoints are available only for the allowed authenticated users
+    if request
+      .extensions()
+      .get::<RequestData>()
+      .and_then(|request_data| request_data.auth_user.as_ref())
+      .is_none_or(|auth_user| !self.allowed_users.contains(auth_user))
+    {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::FORBIDDEN),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let query = request.uri().query().map(|query| query.to_string());
+    let query = query.as_deref();
+    let response = match endpoint.as_str() {
+      "" => build_response(
+        StatusCode::OK,
+        "text/plain; charset=utf-8",
+        "Profiling endpoints:\n\
+         profile - CPU profile (parameters: seconds, frequency, format=pprof|flamegraph)\n\
+         tasks - asynchronous runtime task metrics\n",
+      )?,
+      "profile" => {
+        let Some(seconds) = get_query_parameter(query, "seconds")
+          .map_or(Some(DEFAULT_CPU_PROFILE_SECONDS), |v| v.parse::<u64>().ok())
+          .filter(|v| (1..=MAX_CPU_PROFILE_SECONDS).contains(v))
+        else {
+          return Ok(ResponseData {
+            request: Some(request),
+            response: None,
+            response_status: Some(StatusCode::BAD_REQUEST),
+            response_headers: None,
+            new_remote_address: None,
+          });
+        };
+        let Some(frequency) = get_query_parameter(query, "frequency")
+          .map_or(Some(DEFAULT_CPU_PROFILE_FREQUENCY), |v| v.parse::<i32>().ok())
+          .filter(|v| (1..=MAX_CPU_PROFILE_FREQUENCY).contains(v))
+        else {
+          return Ok(ResponseData {
+            request: Some(request),
+            response: None,
+            response_status: Some(StatusCode::BAD_REQUEST),
+            response_headers: None,
+            new_remote_address: None,
+          });
+        };
+        let format = match get_query_parameter(query, "format") {
+          None | Some("pprof") => CpuProfileFormat::Pprof,
+          Some("flamegraph") => CpuProfileFormat::Flamegraph,
+          Some(_) => {
+            return Ok(ResponseData {
+              request: Some(request),
+              response: None,
+              response_status: Some(StatusCode::BAD_REQUEST),
+              response_headers: None,
+              new_remote_address: None,
+            })
+          }
+        };
+
+        if let Some(guard) = CpuProfilingGuard::acquire() {
+          // The profile is collected on the secondary runtime's blocking threads
+          let profile = self
+            .runtime
+            .spawn_blocking(move || {
+              let _guard = guard;
+              collect_cpu_profile(Duration::from_secs(seconds), frequency, format)
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Can't spawn a task to collect the CPU profile"))?;
+          match profile {
+            Ok(profile) => match format {
+              CpuProfileFormat::Pprof => build_response(StatusCode::OK, "application/octet-stream", profile)?,
+              CpuProfileFormat::Flamegraph => build_response(StatusCode::OK, "image/svg+xml", profile)?,
+            },
+            Err(err) => {
+              error_logger.log(&format!("Can't collect the CPU profile: {err}")).await;
+              build_response(
+                StatusCode::NOT_IMPLEMENTED,
+                "text/plain; charset=utf-8",
+                format!("Can't collect the CPU profile: {err}\n"),
+              )?
+            }
+          }
+        } else {
+          build_response(
+            StatusCode::CONFLICT,
+            "text/plain; charset=utf-8",
+            "Another CPU profile is being collected\n",
+          )?
+        }
+      }
+      "tasks" => {
+        let mut task_metrics = serde_json::json!({
+          "secondary_runtime": runtime_task_metrics(&self.runtime),
+        });
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+          task_metrics["request_handler_runtime"] = runtime_task_metrics(&handle);
+        }
+        build_response(StatusCode::OK, "application/json", task_metrics.to_string())?
+      }
+      _ => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::NOT_FOUND),
+          response_headers: None,
+          new_remote_address: None,
+        })
+      }
+    };
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: Some(response),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::future::Future;
+  use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+  use ferron_common::config::{
+    ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationFilters, ServerConfigurationValue,
+  };
+  use ferron_common::observability::ObservabilityBackendChannels;
+  use http_body_util::Empty;
+
+  use super::*;
+
+  fn run_async<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()
+      .expect("runtime should be created")
+      .block_on(future)
+  }
+
+  /// Creates a configuration enabling the profiling endpoints for the "admin" user
+  fn config() -> ServerConfiguration {
+    let pprof = ServerConfigurationEntry {
+      values: vec![ServerConfigurationValue::Bool(true)],
+      props: HashMap::from([(
+        "users".to_string(),
+        ServerConfigurationValue::String("admin".to_string()),
+      )]),
+    };
+    ServerConfiguration {
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: None,
+        ip: None,
+        port: None,
+        condition: None,
+        error_handler_status: None,
+      },
+      entries: HashMap::from([("pprof".to_string(), ServerConfigurationEntries { inner: vec![pprof] })]),
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  /// Sends a GET request to the profiling module, and returns the response status code
+  async fn get(runtime: &tokio::runtime::Runtime, uri: &str, auth_user: Option<&str>) -> StatusCode {
+    let config = config();
+    let module = ProfilingModuleLoader::new()
+      .load_module(&config, None, runtime)
+      .unwrap();
+    let mut request = Request::builder()
+      .uri(uri)
+      .body(Empty::new().map_err(|e| match e {}).boxed())
+      .unwrap();
+    request.extensions_mut().insert(RequestData {
+      auth_user: auth_user.map(|auth_user| auth_user.to_string()),
+      original_url: None,
+      error_status_code: None,
+      peer_address: None,
+      proxy_protocol_address: None,
+      variables: HashMap::new(),
+    });
+    let socket_data = SocketData {
+      remote_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 50000),
+      local_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+    let response_data = module
+      .get_module_handlers()
+      .request_handler(request, &config, &socket_data, &ErrorLogger::without_logger())
+      .await
+      .unwrap();
+    match response_data.response {
+      Some(response) => response.status(),
+      None => response_data.response_status.unwrap_or(StatusCode::OK),
+    }
+  }
+
+  #[test]
+  fn test_get_query_parameter() {
+    let query = Some("seconds=10&format=flamegraph&empty=&flag");
+    assert_eq!(get_query_parameter(query, "seconds"), Some("10"));
+    assert_eq!(get_query_parameter(query, "format"), Some("flamegraph"));
+    assert_eq!(get_query_parameter(query, "empty"), Some(""));
+    assert_eq!(get_query_parameter(query, "flag"), None);
+    assert_eq!(get_query_parameter(query, "frequency"), None);
+    assert_eq!(get_query_parameter(None, "seconds"), None);
+  }
+
+  #[test]
+  fn test_unauthorized_users_are_forbidden() {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    run_async(async {
+      assert_eq!(get(&runtime, "/debug/pprof/tasks", None).await, StatusCode::FORBIDDEN);
+      assert_eq!(
+        get(&runtime, "/debug/pprof/tasks", Some("guest")).await,
+        StatusCode::FORBIDDEN
+      );
+      assert_eq!(get(&runtime, "/debug/pprof/tasks", Some("admin")).await, StatusCode::OK);
+    });
+  }
+
+  #[test]
+  fn test_out_of_range_parameters() {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    run_async(async {
+      for query in [
+        "seconds=0",
+        "seconds=301",
+        "seconds=invalid",
+        "frequency=0",
+        "frequency=1001",
+        "format=invalid",
+      ] {
+        assert_eq!(
+          get(&runtime, &format!("/debug/pprof/profile?{query}"), Some("admin")).await,
+          StatusCode::BAD_REQUEST
+        );
+      }
+    });
+  }
+
+  #[test]
+  fn test_concurrent_profiles() {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let _guard = CpuProfilingGuard::acquire().unwrap();
+    run_async(async {
+      assert_eq!(
+        get(&runtime, "/debug/pprof/profile?seconds=1", Some("admin")).await,
+        StatusCode::CONFLICT
+      );
+    });
+  }
+}
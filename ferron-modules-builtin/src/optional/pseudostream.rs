@@ -0,0 +1,380 @@
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty, StreamBody};
+use hyper::body::Frame;
+use hyper::{header, Method, Request, Response, StatusCode};
+
+use crate::util::{read_mp4_layout, remux_mp4, ChannelWriter, FLV_HEADER};
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::{ModuleCache, EMBEDDED_ROOT_SCHEME};
+use ferron_common::{get_entries_for_validation, get_entry, get_value};
+
+/// The default maximum size of the "moov" box of MP4 files to be remuxed
+const DEFAULT_MP4_MAX_MOOV_SIZE: u64 = 10485760;
+
+/// A format of pseudo-streamed media files
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PseudoStreamingFormat {
+  Mp4,
+  Flv,
+}
+
+/// A plan of the pseudo-streaming response
+struct PseudoStreamingPlan {
+  header: Vec<u8>,
+  data_offset: u64,
+  data_length: u64,
+  modified: SystemTime,
+}
+
+/// Resolves the path of the media file for the request path. Returns `None` if the request isn't for a supported media file.
+fn resolve_media_path(
+  root: &Path,
+  request_path: &str,
+  enable_mp4: bool,
+  enable_flv: bool,
+) -> Option<(PathBuf, PseudoStreamingFormat, &'static str)> {
+  let decoded_path = urlencoding::decode(request_path.trim_start_matches('/')).ok()?;
+  let path = root.join(decoded_path.as_ref());
+  let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+  let (format, content_type) = match extension.as_str() {
+    "mp4" if enable_mp4 => (PseudoStreamingFormat::Mp4, "video/mp4"),
+    "m4v" if enable_mp4 => (PseudoStreamingFormat::Mp4, "video/x-m4v"),
+    "m4a" if enable_mp4 => (PseudoStreamingFormat::Mp4, "audio/mp4"),
+    "flv" if enable_flv => (PseudoStreamingFormat::Flv, "video/x-flv"),
+    _ => return None,
+  };
+
+  // Prevent path traversal
+  let canonical_path = path.canonicalize().ok()?;
+  if !canonical_path.starts_with(root.canonicalize().ok()?) || !canonical_path.is_file() {
+    return None;
+  }
+  Some((canonical_path, format, content_type))
+}
+
+/// Plans the pseudo-streaming response for the media file. Returns `None` if the whole file should be served instead.
+fn plan_pseudo_streaming(
+  path: &Path,
+  format: PseudoStreamingFormat,
+  start: f64,
+  max_moov_size: u64,
+) -> Result<Option<PseudoStreamingPlan>, anyhow::Error> {
+  let mut file = std::fs::File::open(path)?;
+  let metadata = file.metadata()?;
+  let file_size = metadata.len();
+  let modified = metadata.modified()?;
+
+  match format {
+    PseudoStreamingFormat::Mp4 => {
+      let Some(layout) = read_mp4_layout(&mut file, file_size)? else {
+        return Ok(None);
+      };
+      if layout.moov.1 > max_moov_size {
+        Err(anyhow::anyhow!(
+          "The \"moov\" box of the MP4 file is larger than the maximum size"
+        ))?
+      }
+      let mut ftyp = Vec::new();
+      if let Some((ftyp_offset, ftyp_size)) = layout.ftyp {
+        file.seek(SeekFrom::Start(ftyp_offset))?;
+        (&mut file).take(ftyp_size).read_to_end(&mut ftyp)?;
+      }
+      let mut moov = Vec::new();
+      file.seek(SeekFrom::Start(layout.moov.0))?;
+      (&mut file).take(layout.moov.1).read_to_end(&mut moov)?;
+
+      let remuxed = remux_mp4(&ftyp, &moov, layout.mdat_data, (start * 1000.0) as u64)?;
+      Ok(Some(PseudoStreamingPlan {
+        header: remuxed.header,
+        data_offset: remuxed.data_offset,
+        data_length: remuxed.data_length,
+        modified,
+      }))
+    }
+    PseudoStreamingFormat::Flv => {
+      // The start parameter of FLV files is a byte offset of the key frame
+      let data_offset = start as u64;
+      if data_offset == 0 || data_offset >= file_size {
+        return Ok(None);
+      }
+      Ok(Some(PseudoStreamingPlan {
+        header: FLV_HEADER.to_vec(),
+        data_offset,
+        data_length: file_size - data_offset,
+        modified,
+      }))
+    }
+  }
+}
+
+/// Writes the pseudo-streaming response body (the header followed by the part of the media file) into the writer
+fn write_pseudo_streaming_response(
+  path: &Path,
+  plan: &PseudoStreamingPlan,
+  writer: &mut impl Write,
+) -> Result<(), std::io::Error> {
+  writer.write_all(&plan.header)?;
+  let mut file = std::fs::File::open(path)?;
+  file.seek(SeekFrom::Start(plan.data_offset))?;
+  std::io::copy(&mut file.take(plan.data_length), writer)?;
+  writer.flush()
+}
+
+/// A media pseudo-streaming module loader
+pub struct PseudoStreamingModuleLoader {
+  cache: ModuleCache<PseudoStreamingModule>,
+}
+
+impl Default for PseudoStreamingModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl PseudoStreamingModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![]),
+    }
+  }
+}
+
+impl ModuleLoader for PseudoStreamingModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| {
+          Ok(Arc::new(PseudoStreamingModule {
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["mp4", "flv"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("mp4", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `mp4` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid MP4 pseudo-streaming enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("flv", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `flv` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid FLV pseudo-streaming enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("mp4_max_moov_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `mp4_max_moov_size` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_size().is_none_or(|v| v == 0) {
+          Err(anyhow::anyhow!("Invalid maximum size of the \"moov\" box of MP4 files"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A media pseudo-streaming module
+struct PseudoStreamingModule {
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for PseudoStreamingModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(PseudoStreamingModuleHandlers {
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the media pseudo-streaming module
+struct PseudoStreamingModuleHandlers {
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for PseudoStreamingModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let enable_mp4 = get_value!("mp4", config).and_then(|v| v.as_bool()).unwrap_or(false);
+    let enable_flv = get_value!("flv", config).and_then(|v| v.as_bool()).unwrap_or(false);
+    let root = get_entry!("root", config)
+      .and_then(|e| e.values.first())
+      .and_then(|v| v.as_str())
+      .filter(|root| !root.starts_with(EMBEDDED_ROOT_SCHEME))
+      .map(PathBuf::from);
+    let start = request.uri().query().and_then(|query| {
+      query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("start="))
+        .map(|start| start.to_string())
+    });
+    let (Some(root), Some(start)) = (root, start) else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+    if !(enable_mp4 || enable_flv) || !matches!(request.method(), &Method::GET | &Method::HEAD) {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let Some(start) = start
+      .parse::<f64>()
+      .ok()
+      .filter(|start| start.is_finite() && *start >= 0.0)
+    else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::BAD_REQUEST),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+    let max_moov_size = get_value!("mp4_max_moov_size", config)
+      .and_then(|v| v.as_size())
+      .unwrap_or(DEFAULT_MP4_MAX_MOOV_SIZE);
+    let request_path = request.uri().path().to_string();
+
+    // File system operations and remuxing are performed on the secondary runtime's blocking threads
+    let planned = self
+      .runtime
+      .spawn_blocking(move || {
+        let Some((path, format, content_type)) = resolve_media_path(&root, &request_path, enable_mp4, enable_flv)
+        else {
+          return Ok(None);
+        };
+        plan_pseudo_streaming(&path, format, start, max_moov_size)
+          .map(|plan| plan.map(|plan| (path, content_type, plan)))
+      })
+      .await
+      .map_err(|_| anyhow::anyhow!("Can't spawn a task to prepare the pseudo-streamed media file"))?;
+
+    let (path, content_type, plan) = match planned {
+      Ok(Some(planned)) => planned,
+      Ok(None) => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        })
+      }
+      Err(err) => {
+        // Let the static file serving module serve the whole file
+        error_logger
+          .log(&format!("Can't prepare the pseudo-streamed media file: {err}"))
+          .await;
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    };
+
+    let last_modified = DateTime::<Utc>::from(plan.modified)
+      .format("%a, %d %b %Y %H:%M:%S GMT")
+      .to_string();
+    let mut response_builder = Response::builder()
+      .header(header::CONTENT_TYPE, content_type)
+      .header(header::CONTENT_LENGTH, plan.header.len() as u64 + plan.data_length)
+      .header(header::LAST_MODIFIED, last_modified);
+    if let Some(cache_control) = get_value!("file_cache_control", config).and_then(|v| v.as_str()) {
+      response_builder = response_builder.header(header::CACHE_CONTROL, cache_control);
+    }
+
+    let response = if request.method() == Method::HEAD {
+      response_builder.body(Empty::new().map_err(|e| match e {}).boxed())?
+    } else {
+      // The media file is read on the secondary runtime's blocking threads, and sent in chunks through a bounded channel
+      let (sender, mut receiver) = tokio::sync::mpsc::channel(4);
+      self.runtime.spawn_blocking(move || {
+        let mut writer = ChannelWriter::new(sender.clone());
+        if let Err(err) = write_pseudo_streaming_response(&path, &plan, &mut writer) {
+          if err.kind() != std::io::ErrorKind::BrokenPipe {
+            sender.blocking_send(Err(err)).unwrap_or_default();
+          }
+        }
+      });
+      response_builder.body(
+        StreamBody::new(futures_util::stream::poll_fn(move |cx| {
+          receiver
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+        }))
+        .boxed(),
+      )?
+    };
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: Some(response),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
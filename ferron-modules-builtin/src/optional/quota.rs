@@ -0,0 +1,515 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeDelta, Utc};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{header, HeaderMap, Request, Response, StatusCode};
+use sha2::{Digest, Sha256};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::kv_store::{obtain_kv_store, KvStore, KvStoreConfig, NamespacedKvStore};
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{DirectiveScope, Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries, get_entries_for_validation, get_entry, get_value};
+
+use crate::util::decode_bearer_claims;
+
+/// The time, for which daily API quota counters are kept after their period ends
+const DAILY_COUNTERS_RETENTION: Duration = Duration::from_secs(31 * 86400);
+
+/// The time, for which monthly API quota counters are kept after their period ends
+const MONTHLY_COUNTERS_RETENTION: Duration = Duration::from_secs(366 * 86400);
+
+/// The namespace of API quota counters in the key-value store
+const QUOTA_KV_NAMESPACE: &str = "quota";
+
+/// API quota counters, keyed by period identifiers (like `2026-10-18` for days or `2026-10` for months) and quota keys
+type QuotaCounters = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// Obtains the values of all the API quota counters from the key-value store.
+/// The counters are stored under `<period ID>:<quota key>` keys.
+async fn obtain_quota_usage(store: &dyn KvStore) -> Result<QuotaCounters, Box<dyn Error + Send + Sync>> {
+  let mut counters = QuotaCounters::new();
+  for (key, value) in store.scan("").await? {
+    let (Some((period_id, key)), Ok(value)) = (key.split_once(':'), value.parse::<u64>()) else {
+      continue;
+    };
+    counters
+      .entry(period_id.to_string())
+      .or_default()
+      .insert(key.to_string(), value);
+  }
+  Ok(counters)
+}
+
+/// An API quota period
+#[derive(Clone, Copy)]
+enum QuotaPeriod {
+  Daily,
+  Monthly,
+}
+
+impl QuotaPeriod {
+  /// Parses the API quota period name
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "daily" => Some(Self::Daily),
+      "monthly" => Some(Self::Monthly),
+      _ => None,
+    }
+  }
+
+  /// Obtains the identifier of the period containing the specified time
+  fn period_id(&self, time: DateTime<Utc>) -> String {
+    match self {
+      Self::Daily => time.format("%Y-%m-%d").to_string(),
+      Self::Monthly => time.format("%Y-%m").to_string(),
+    }
+  }
+
+  /// Obtains the time, for which the counters are kept after the period ends
+  fn retention(&self) -> Duration {
+    match self {
+      Self::Daily => DAILY_COUNTERS_RETENTION,
+      Self::Monthly => MONTHLY_COUNTERS_RETENTION,
+    }
+  }
+
+  /// Obtains the number of seconds until the period containing the specified time ends
+  fn seconds_until_reset(&self, time: DateTime<Utc>) -> u64 {
+    let period_start = match self {
+      Self::Daily => time.date_naive(),
+      Self::Monthly => NaiveDate::from_ymd_opt(time.year(), time.month(), 1).unwrap_or(time.date_naive()),
+    };
+    let next_period_start = match self {
+      Self::Daily => period_start.succ_opt(),
+      Self::Monthly => period_start.checked_add_months(Months::new(1)),
+    };
+    next_period_start
+      .and_then(|date| date.and_hms_opt(0, 0, 0))
+      .map(|start| (start.and_utc() - time).max(TimeDelta::zero()).num_seconds() as u64)
+      .unwrap_or(0)
+  }
+}
+
+/// A source of the key, by which API quotas are tracked
+#[derive(Clone)]
+enum QuotaKeySource {
+  /// A request header containing an API key
+  Header(HeaderName),
+  /// A query parameter containing an API key
+  Query(String),
+  /// A claim from the JWT bearer token
+  JwtClaim(String),
+}
+
+impl QuotaKeySource {
+  /// Parses the API quota key source (like `header:X-API-Key`, `query:api_key` or `jwt:sub`)
+  fn parse(source: &str) -> Option<Self> {
+    let (source_type, name) = source.split_once(':')?;
+    if name.is_empty() {
+      return None;
+    }
+    match source_type {
+      "header" => HeaderName::from_bytes(name.as_bytes()).ok().map(Self::Header),
+      "query" => Some(Self::Query(name.to_string())),
+      "jwt" => Some(Self::JwtClaim(name.to_string())),
+      _ => None,
+    }
+  }
+
+  /// Obtains the API quota key from the request. API keys are hashed, so that they aren't stored in plain text.
+  fn obtain_key(&self, request: &Request<BoxBody<Bytes, std::io::Error>>) -> Option<String> {
+    let hash_key = |key: &[u8]| {
+      Sha256::digest(key)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>()
+    };
+    match self {
+      Self::Header(header_name) => request
+        .headers()
+        .get(header_name)
+        .map(|v| v.as_bytes())
+        .filter(|v| !v.is_empty())
+        .map(hash_key),
+      Self::Query(parameter_name) => request.uri().query().and_then(|query| {
+        query.split('&').find_map(|parameter| {
+          let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+          if urlencoding::decode(name).ok()?.as_ref() != parameter_name || value.is_empty() {
+            return None;
+          }
+          urlencoding::decode(value).ok().map(|value| hash_key(value.as_bytes()))
+        })
+      }),
+      Self::JwtClaim(claim_name) => request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(decode_bearer_claims)
+        .and_then(|claims| match claims.get(claim_name)? {
+          serde_json::Value::String(claim) => Some(claim.clone()),
+          serde_json::Value::Number(claim) => Some(claim.to_string()),
+          _ => None,
+        }),
+    }
+  }
+}
+
+/// An API quota
+struct Quota {
+  max_requests: u64,
+  period: QuotaPeriod,
+  key_source: QuotaKeySource,
+}
+
+/// Parses the API quota counter store configuration. If the counter store isn't configured,
+/// the shared key-value store (the `store` global directive) is used, falling back to an in-memory store.
+fn parse_quota_store_config(
+  config: &ServerConfiguration,
+  global_config: Option<&ServerConfiguration>,
+) -> Result<KvStoreConfig, Box<dyn Error + Send + Sync>> {
+  let shared_store_config = || {
+    global_config
+      .and_then(|c| get_entry!("store", c))
+      .map_or(Some(KvStoreConfig::Memory), KvStoreConfig::from_entry)
+      .ok_or_else(|| anyhow::anyhow!("Invalid shared key-value store"))
+  };
+  match get_entry!("api_quota_store", config) {
+    Some(entry) if entry.values.first().and_then(|v| v.as_str()) == Some("shared") => Ok(shared_store_config()?),
+    Some(entry) => {
+      Ok(KvStoreConfig::from_entry(entry).ok_or_else(|| anyhow::anyhow!("Invalid API quota counter store"))?)
+    }
+    None => Ok(shared_store_config()?),
+  }
+}
+
+/// An API quota module loader
+pub struct ApiQuotaModuleLoader {
+  cache: ModuleCache<ApiQuotaModule>,
+}
+
+impl Default for ApiQuotaModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ApiQuotaModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["api_quota", "api_quota_store"]),
+    }
+  }
+}
+
+impl ModuleLoader for ApiQuotaModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    let store: Arc<dyn KvStore> = Arc::new(NamespacedKvStore::new(
+      obtain_kv_store(
+        &parse_quota_store_config(config, global_config)?,
+        secondary_runtime.handle(),
+      )?,
+      QUOTA_KV_NAMESPACE,
+    ));
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let mut quotas = Vec::new();
+          if let Some(entries) = get_entries!("api_quota", config) {
+            for entry in &entries.inner {
+              let Some(max_requests) = entry.values.first().and_then(|v| v.as_i128()) else {
+                continue;
+              };
+              quotas.push(Quota {
+                max_requests: max_requests.try_into()?,
+                period: entry
+                  .props
+                  .get("period")
+                  .and_then(|v| v.as_str())
+                  .map_or(Some(QuotaPeriod::Daily), QuotaPeriod::from_name)
+                  .ok_or_else(|| anyhow::anyhow!("Invalid API quota period"))?,
+                key_source: QuotaKeySource::parse(
+                  entry
+                    .props
+                    .get("key")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("header:X-API-Key"),
+                )
+                .ok_or_else(|| anyhow::anyhow!("Invalid API quota key source"))?,
+              });
+            }
+          }
+          Ok(Arc::new(ApiQuotaModule {
+            quotas: Arc::new(quotas),
+            store,
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["api_quota", "api_quota_usage"]
+  }
+
+  fn get_directive_scopes(&self) -> Vec<(&'static str, DirectiveScope)> {
+    vec![("store", DirectiveScope::Global)]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("api_quota", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `api_quota` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 0) {
+          Err(anyhow::anyhow!("Invalid API quota maximum number of requests"))?
+        } else if entry
+          .props
+          .get("period")
+          .is_some_and(|v| v.as_str().and_then(QuotaPeriod::from_name).is_none())
+        {
+          Err(anyhow::anyhow!("Invalid API quota period"))?
+        } else if entry
+          .props
+          .get("key")
+          .is_some_and(|v| v.as_str().and_then(QuotaKeySource::parse).is_none())
+        {
+          Err(anyhow::anyhow!("Invalid API quota key source"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("api_quota_store", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `api_quota_store` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_str() == Some("file") && !entry.props.get("path").is_some_and(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The API quota counter file store must have a path specified"
+          ))?
+        } else if entry.values[0].as_str() != Some("shared") && KvStoreConfig::from_entry(entry).is_none() {
+          Err(anyhow::anyhow!("Invalid API quota counter store"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("store", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `store` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_str() == Some("file") && !entry.props.get("path").is_some_and(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The shared key-value file store must have a path specified"
+          ))?
+        } else if KvStoreConfig::from_entry(entry).is_none() {
+          Err(anyhow::anyhow!("Invalid shared key-value store"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("api_quota_usage", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `api_quota_usage` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid API quota usage export enabling option"))?
+        }
+      }
+    };
+
+    Ok(())
+  }
+}
+
+/// An API quota module
+struct ApiQuotaModule {
+  quotas: Arc<Vec<Quota>>,
+  store: Arc<dyn KvStore>,
+}
+
+impl Module for ApiQuotaModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(ApiQuotaModuleHandlers {
+      quotas: self.quotas.clone(),
+      store: self.store.clone(),
+      quota_headers: None,
+    })
+  }
+}
+
+/// Handlers for the API quota module
+struct ApiQuotaModuleHandlers {
+  quotas: Arc<Vec<Quota>>,
+  store: Arc<dyn KvStore>,
+  quota_headers: Option<HeaderMap>,
+}
+
+/// Constructs the API quota response headers
+fn construct_quota_headers(
+  max_requests: u64,
+  remaining: u64,
+  reset: u64,
+) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
+  let mut headers = HeaderMap::new();
+  headers.insert(
+    HeaderName::from_static("x-ratelimit-limit"),
+    HeaderValue::from_str(&max_requests.to_string())?,
+  );
+  headers.insert(
+    HeaderName::from_static("x-ratelimit-remaining"),
+    HeaderValue::from_str(&remaining.to_string())?,
+  );
+  headers.insert(
+    HeaderName::from_static("x-ratelimit-reset"),
+    HeaderValue::from_str(&reset.to_string())?,
+  );
+  Ok(headers)
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for ApiQuotaModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    if get_value!("api_quota_usage", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false)
+    {
+      let usage = serde_json::to_vec(&obtain_quota_usage(&*self.store).await?)?;
+      return Ok(ResponseData {
+        request: Some(request),
+        response: Some(
+          Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(Full::new(Bytes::from(usage)).map_err(|e| match e {}).boxed())?,
+        ),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let now = Utc::now();
+    let applicable_quotas = self
+      .quotas
+      .iter()
+      .filter_map(|quota| {
+        quota
+          .key_source
+          .obtain_key(&request)
+          .map(|key| (quota, quota.period.period_id(now), key))
+      })
+      .collect::<Vec<_>>();
+
+    // Check all the quotas first, so that rejected requests don't count towards other quotas
+    for (quota, period_id, key) in &applicable_quotas {
+      let used_requests = match self.store.get(&format!("{period_id}:{key}")).await {
+        Ok(used_requests) => used_requests.and_then(|v| v.parse::<u64>().ok()).unwrap_or(0),
+        Err(err) => {
+          // If the counter store is unavailable, the request is allowed
+          error_logger
+            .log(&format!("Can't obtain the API quota counter: {err}"))
+            .await;
+          continue;
+        }
+      };
+      if used_requests >= quota.max_requests {
+        let reset = quota.period.seconds_until_reset(now);
+        let mut headers = construct_quota_headers(quota.max_requests, 0, reset)?;
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_str(&reset.to_string())?);
+        error_logger
+          .log(&format!(
+            "API quota of {} requests per {} period exceeded",
+            quota.max_requests, period_id
+          ))
+          .await;
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::TOO_MANY_REQUESTS),
+          response_headers: Some(headers),
+          new_remote_address: None,
+        });
+      }
+    }
+
+    // The quota with the fewest remaining requests is reported in the response headers
+    let mut reported_quota = None;
+    for (quota, period_id, key) in &applicable_quotas {
+      let ttl = Duration::from_secs(quota.period.seconds_until_reset(now)) + quota.period.retention();
+      let used_requests = match self.store.increment(&format!("{period_id}:{key}"), 1, Some(ttl)).await {
+        Ok(used_requests) => used_requests.max(0) as u64,
+        Err(err) => {
+          error_logger
+            .log(&format!("Can't increment the API quota counter: {err}"))
+            .await;
+          continue;
+        }
+      };
+      let remaining = quota.max_requests.saturating_sub(used_requests);
+      if reported_quota.is_none_or(|(_, reported_remaining)| remaining < reported_remaining) {
+        reported_quota = Some((quota, remaining));
+      }
+    }
+    if let Some((quota, remaining)) = reported_quota {
+      self.quota_headers = Some(construct_quota_headers(
+        quota.max_requests,
+        remaining,
+        quota.period.seconds_until_reset(now),
+      )?);
+    }
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    mut response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    if let Some(quota_headers) = self.quota_headers.take() {
+      for (header_name, header_value) in quota_headers.iter() {
+        if !response.headers().contains_key(header_name) {
+          response.headers_mut().insert(header_name, header_value.clone());
+        }
+      }
+    }
+    Ok(response)
+  }
+}
@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::BodyExt;
+use hyper::{header, Request, Response, StatusCode};
+
+use crate::util::{JsonRedactor, RedactionAction, RedactionRule, ResponseRedactor, XmlRedactor};
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries, get_entries_for_validation};
+
+/// The default replacement of the masked response fields
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// A response field redaction module loader
+pub struct ResponseRedactionModuleLoader {
+  cache: ModuleCache<ResponseRedactionModule>,
+}
+
+impl Default for ResponseRedactionModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ResponseRedactionModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["response_redact"]),
+    }
+  }
+}
+
+/// Parses the redaction action from the `action` and `replacement` props
+fn parse_redaction_action(action: Option<&str>, replacement: Option<&str>) -> Option<RedactionAction> {
+  match action {
+    None | Some("mask") => Some(RedactionAction::Mask(
+      replacement.unwrap_or(DEFAULT_REPLACEMENT).to_string(),
+    )),
+    Some("remove") => Some(RedactionAction::Remove),
+    _ => None,
+  }
+}
+
+impl ModuleLoader for ResponseRedactionModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let mut rules = Vec::new();
+          if let Some(entries) = get_entries!("response_redact", config) {
+            for entry in &entries.inner {
+              let Some(path) = entry.values.first().and_then(|v| v.as_str()) else {
+                continue;
+              };
+              let action = parse_redaction_action(
+                entry.props.get("action").and_then(|v| v.as_str()),
+                entry.props.get("replacement").and_then(|v| v.as_str()),
+              )
+              .ok_or_else(|| anyhow::anyhow!("Invalid response field redaction action"))?;
+              rules.push(RedactionRule::parse(path, action).map_err(|err| anyhow::anyhow!(err))?);
+            }
+          }
+          Ok(Arc::new(ResponseRedactionModule { rules: Arc::new(rules) }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["response_redact"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("response_redact", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `response_redact` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!("The redacted response field path must be a string"))?
+        } else if !entry.props.get("action").is_none_or(|v| {
+          v.as_str()
+            .is_some_and(|v| parse_redaction_action(Some(v), None).is_some())
+        }) {
+          Err(anyhow::anyhow!(
+            "Invalid response field redaction action (must be `mask` or `remove`)"
+          ))?
+        } else if !entry.props.get("replacement").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!(
+            "The redacted response field replacement must be a string"
+          ))?
+        } else if let Some(path) = entry.values[0].as_str() {
+          RedactionRule::parse(path, RedactionAction::Remove).map_err(|err| anyhow::anyhow!(err))?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A response field redaction module
+struct ResponseRedactionModule {
+  rules: Arc<Vec<RedactionRule>>,
+}
+
+impl Module for ResponseRedactionModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(ResponseRedactionModuleHandlers {
+      rules: self.rules.clone(),
+    })
+  }
+}
+
+/// Handlers for the response field redaction module
+struct ResponseRedactionModuleHandlers {
+  rules: Arc<Vec<RedactionRule>>,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for ResponseRedactionModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    _config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let (mut request_parts, request_body) = request.into_parts();
+    if !self.rules.is_empty() {
+      // Request uncompressed responses, since compressed responses can't be redacted
+      while request_parts.headers.remove(header::ACCEPT_ENCODING).is_some() {}
+    }
+    Ok(ResponseData {
+      request: Some(Request::from_parts(request_parts, request_body)),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    if self.rules.is_empty()
+      || response.headers().contains_key(header::CONTENT_ENCODING) // Don't corrupt compressed data
+      || response.status() == StatusCode::PARTIAL_CONTENT
+    {
+      return Ok(response);
+    }
+
+    let response_mime_type = response
+      .headers()
+      .get(header::CONTENT_TYPE)
+      .and_then(|h| h.to_str().ok())
+      .map(|t| t.split(';').next().unwrap_or(t).trim().to_lowercase());
+    let redactor = match response_mime_type.as_deref() {
+      Some(mime_type) if mime_type == "application/json" || mime_type.ends_with("+json") => {
+        ResponseRedactor::Json(JsonRedactor::new(self.rules.clone()))
+      }
+      Some(mime_type) if mime_type == "application/xml" || mime_type == "text/xml" || mime_type.ends_with("+xml") => {
+        ResponseRedactor::Xml(XmlRedactor::new(self.rules.clone()))
+      }
+      _ => return Ok(response),
+    };
+
+    let (mut response_parts, response_body) = response.into_parts();
+    while response_parts.headers.remove(header::CONTENT_LENGTH).is_some() {}
+    Ok(Response::from_parts(
+      response_parts,
+      redactor.wrap(response_body).boxed(),
+    ))
+  }
+}
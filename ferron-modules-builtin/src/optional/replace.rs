@@ -11,7 +11,7 @@ use crate::util::BodyReplacer;
 use ferron_common::config::ServerConfiguration;
 use ferron_common::logging::ErrorLogger;
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
-use ferron_common::util::ModuleCache;
+use ferron_common::util::{is_streaming_response, ModuleCache};
 use ferron_common::{get_entries, get_entries_for_validation, get_value, get_values};
 
 /// A response replacement module loader
@@ -181,7 +181,8 @@ impl ModuleHandlers for ReplaceModuleHandlers {
     &mut self,
     response: Response<BoxBody<Bytes, std::io::Error>>,
   ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
-    let mut can_replace = !response.headers().contains_key(header::CONTENT_ENCODING); // Don't corrupt compressed data
+    let mut can_replace = !response.headers().contains_key(header::CONTENT_ENCODING) // Don't corrupt compressed data
+      && !is_streaming_response(&response);
     let response_mime_type = response
       .headers()
       .get(header::CONTENT_TYPE)
@@ -197,7 +198,7 @@ impl ModuleHandlers for ReplaceModuleHandlers {
             } else {
               t.trim()
             }
-          }) == Some(filter_type)
+          }) == Some(filter_type.as_str())
         {
           can_replace = true;
           break;
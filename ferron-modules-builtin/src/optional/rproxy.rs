@@ -7,17 +7,170 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use ferron_common::logging::ErrorLogger;
 use http_body_util::combinators::BoxBody;
-use hyper::header::HeaderName;
-use hyper::Request;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
 
-use ferron_common::http_proxy::{Connections, LoadBalancerAlgorithm, ProxyHeader, ReverseProxy, ReverseProxyHandler};
+use ferron_common::config::ServerConfigurationEntry;
+use ferron_common::http_proxy::{
+  AwsCredentials, CircuitBreaker, Connections, Http2Keepalive, LoadBalancerAlgorithm, OutlierDetection,
+  ProxyCookieFlags, ProxyHeader, ProxyHostHeader, ProxyNextUpstream, ProxyRequestSigning, ReverseProxy,
+  ReverseProxyHandler, StickySessions, TcpKeepalive,
+};
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
 use ferron_common::observability::MetricsMultiSender;
+use ferron_common::util::{parse_ip_literal, queue_warmup_task, ErrorCode};
 use ferron_common::{config::ServerConfiguration, util::ModuleCache};
-use ferron_common::{get_entries, get_entries_for_validation, get_value};
+use ferron_common::{get_entries, get_entries_for_validation, get_entry, get_value, get_values};
+
+use crate::util::{decompress_body, is_decodable_content_coding, verify_integrity_digest, DECODABLE_CONTENT_CODINGS};
 
 const DEFAULT_CONCURRENT_CONNECTIONS: usize = 16384;
 const DEFAULT_KEEPALIVE_IDLE_TIMEOUT: u64 = 60000;
+const DEFAULT_TCP_KEEPALIVE_TIME: Duration = Duration::from_secs(60);
+const DEFAULT_TCP_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_HTTP2_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+const DEFAULT_REQUIRED_SCTS: usize = 2;
+const MAX_VERIFIED_RESPONSE_SIZE: usize = 67108864;
+
+/// Parses the outlier detection configuration from the `proxy_outlier_detection` configuration entry
+fn parse_outlier_detection(entry: &ServerConfigurationEntry) -> Option<OutlierDetection> {
+  if !entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+    return None;
+  }
+  let default = OutlierDetection::default();
+  let optional_prop = |name: &str, default: Option<u64>| {
+    entry.props.get(name).map_or(default, |v| {
+      if v.is_null() {
+        None
+      } else {
+        v.as_i128().map(|v| v as u64).or(default)
+      }
+    })
+  };
+  let duration_prop =
+    |name: &str, default: Duration| entry.props.get(name).and_then(|v| v.as_duration()).unwrap_or(default);
+  Some(OutlierDetection {
+    consecutive_5xx: optional_prop("consecutive_5xx", default.consecutive_5xx),
+    success_rate_stdev_factor: optional_prop("success_rate_stdev_factor", default.success_rate_stdev_factor),
+    latency_factor: optional_prop("latency_factor", default.latency_factor),
+    minimum_hosts: entry
+      .props
+      .get("minimum_hosts")
+      .and_then(|v| v.as_i128())
+      .map_or(default.minimum_hosts, |v| v as usize),
+    request_volume: entry
+      .props
+      .get("request_volume")
+      .and_then(|v| v.as_i128())
+      .map_or(default.request_volume, |v| v as u64),
+    interval: duration_prop("interval", default.interval),
+    base_ejection_time: duration_prop("base_ejection_time", default.base_ejection_time),
+    max_ejection_time: duration_prop("max_ejection_time", default.max_ejection_time),
+    max_ejection_percent: entry
+      .props
+      .get("max_ejection_percent")
+      .and_then(|v| v.as_i128())
+      .map_or(default.max_ejection_percent, |v| v as u64),
+  })
+}
+
+/// Parses the circuit breaker configuration from the `proxy_circuit_breaker` configuration entry
+fn parse_circuit_breaker(entry: &ServerConfigurationEntry) -> Option<CircuitBreaker> {
+  if !entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+    return None;
+  }
+  let default = CircuitBreaker::default();
+  let count_prop = |name: &str, default: u64| {
+    entry
+      .props
+      .get(name)
+      .and_then(|v| v.as_i128())
+      .map_or(default, |v| v as u64)
+  };
+  Some(CircuitBreaker {
+    failure_threshold: count_prop("failures", default.failure_threshold),
+    cooldown: entry
+      .props
+      .get("cooldown")
+      .and_then(|v| v.as_duration())
+      .unwrap_or(default.cooldown),
+    half_open_requests: count_prop("half_open_requests", default.half_open_requests),
+    success_threshold: count_prop("successes", default.success_threshold),
+  })
+}
+
+/// Parses the sticky session configuration from the `lb_sticky_sessions` configuration entry
+fn parse_sticky_sessions(entry: &ServerConfigurationEntry) -> Option<StickySessions> {
+  if !entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+    return None;
+  }
+  let default = StickySessions::default();
+  let duration_prop =
+    |name: &str, default: Duration| entry.props.get(name).and_then(|v| v.as_duration()).unwrap_or(default);
+  Some(StickySessions {
+    cookie_name: entry
+      .props
+      .get("cookie")
+      .and_then(|v| v.as_str())
+      .map_or(default.cookie_name, |v| v.to_string()),
+    ttl: duration_prop("ttl", default.ttl),
+    drain_timeout: duration_prop("drain_timeout", default.drain_timeout),
+  })
+}
+
+/// Parses the TCP keepalive configuration from the `proxy_tcp_keepalive` configuration entry
+fn parse_tcp_keepalive(entry: &ServerConfigurationEntry) -> Option<TcpKeepalive> {
+  if !entry.values.first().and_then(|v| v.as_bool()).unwrap_or(false) {
+    return None;
+  }
+  Some(TcpKeepalive {
+    time: entry
+      .props
+      .get("time")
+      .and_then(|v| v.as_duration())
+      .unwrap_or(DEFAULT_TCP_KEEPALIVE_TIME),
+    interval: entry
+      .props
+      .get("interval")
+      .and_then(|v| v.as_duration())
+      .unwrap_or(DEFAULT_TCP_KEEPALIVE_INTERVAL),
+  })
+}
+
+/// Parses the cookie attribute changes from the `proxy_cookie_flags` configuration entry
+fn parse_proxy_cookie_flags(entry: &ServerConfigurationEntry) -> Option<ProxyCookieFlags> {
+  Some(ProxyCookieFlags {
+    cookie_name: entry.values.first().and_then(|v| v.as_str())?.to_string(),
+    secure: entry.props.get("secure").and_then(|v| v.as_bool()),
+    http_only: entry.props.get("httponly").and_then(|v| v.as_bool()),
+    same_site: entry
+      .props
+      .get("samesite")
+      .and_then(|v| v.as_str())
+      .and_then(normalize_same_site),
+  })
+}
+
+/// Normalizes the `SameSite` cookie attribute value, returning `None` for invalid values
+fn normalize_same_site(same_site: &str) -> Option<String> {
+  ["Strict", "Lax", "None"]
+    .into_iter()
+    .find(|value| value.eq_ignore_ascii_case(same_site))
+    .map(String::from)
+}
+
+/// Parses the HTTP/2 PING configuration from the `proxy_http2_keepalive` configuration entry
+fn parse_http2_keepalive(entry: &ServerConfigurationEntry) -> Option<Http2Keepalive> {
+  Some(Http2Keepalive {
+    interval: entry.values.first().and_then(|v| v.as_duration())?,
+    timeout: entry
+      .props
+      .get("timeout")
+      .and_then(|v| v.as_duration())
+      .unwrap_or(DEFAULT_HTTP2_KEEPALIVE_TIMEOUT),
+  })
+}
 
 /// A reverse proxy module loader
 pub struct ReverseProxyModuleLoader {
@@ -37,22 +190,50 @@ impl ReverseProxyModuleLoader {
     Self {
       cache: ModuleCache::new(vec![
         "lb_algorithm",
+        "lb_failback_delay",
         "lb_health_check",
         "lb_health_check_max_fails",
         "lb_health_check_window",
         "lb_retry_connection",
+        "lb_sticky_sessions",
         "proxy",
+        "proxy_circuit_breaker",
         "proxy_concurrent_conns",
+        "proxy_cookie_domain",
+        "proxy_cookie_flags",
+        "proxy_cookie_path",
+        "proxy_flush_interval",
+        "proxy_fold_headers",
+        "proxy_hide_headers",
+        "proxy_host_header",
         "proxy_http2",
+        "proxy_http2_keepalive",
         "proxy_http2_only",
+        "proxy_http3",
         "proxy_intercept_errors",
         "proxy_keepalive",
+        "proxy_max_requests_per_connection",
+        "proxy_next_upstream",
         "proxy_no_verification",
+        "proxy_outlier_detection",
+        "proxy_pass_headers",
+        "proxy_pool_idle_timeout",
+        "proxy_pool_max_idle_per_host",
         "proxy_proxy_header",
         "proxy_request_header",
         "proxy_request_header_remove",
         "proxy_request_header_replace",
+        "proxy_require_ct",
+        "proxy_response_header",
+        "proxy_response_header_remove",
+        "proxy_response_header_replace",
+        "proxy_sign",
         "proxy_srv",
+        "proxy_sse",
+        "proxy_tcp_keepalive",
+        "proxy_warmup_connections",
+        "proxy_x_forwarded_for",
+        "proxy_x_forwarded_for_limit",
       ]),
       connections: None,
     }
@@ -88,6 +269,20 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       self
         .cache
         .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          // The `proxy_pool_idle_timeout` directive specifies the default idle timeout for backend servers
+          let default_keepalive_idle_timeout = get_value!("proxy_pool_idle_timeout", config).map_or(
+            Some(Duration::from_millis(DEFAULT_KEEPALIVE_IDLE_TIMEOUT)),
+            |v| {
+              if v.is_null() {
+                None
+              } else {
+                Some(
+                  v.as_duration()
+                    .unwrap_or(Duration::from_millis(DEFAULT_KEEPALIVE_IDLE_TIMEOUT)),
+                )
+              }
+            },
+          );
           let proxy_to_raw = get_entries!("proxy", config).map_or(vec![], |e| {
             e.inner
               .iter()
@@ -100,16 +295,18 @@ impl ModuleLoader for ReverseProxyModuleLoader {
                       v,
                       e.props.get("unix").and_then(|v| v.as_str()).map(|s| s.to_owned()),
                       e.props.get("limit").and_then(|v| v.as_i128()).map(|v| v as usize),
-                      e.props
-                        .get("idle_timeout")
-                        .map_or(Some(DEFAULT_KEEPALIVE_IDLE_TIMEOUT), |v| {
-                          if v.is_null() {
-                            None
-                          } else {
-                            Some(v.as_i128().map(|v| v as u64).unwrap_or(DEFAULT_KEEPALIVE_IDLE_TIMEOUT))
-                          }
-                        })
-                        .map(Duration::from_millis),
+                      e.props.get("idle_timeout").map_or(default_keepalive_idle_timeout, |v| {
+                        if v.is_null() {
+                          None
+                        } else {
+                          Some(
+                            v.as_duration()
+                              .unwrap_or(Duration::from_millis(DEFAULT_KEEPALIVE_IDLE_TIMEOUT)),
+                          )
+                        }
+                      }),
+                      e.props.get("backup").and_then(|v| v.as_bool()).unwrap_or(false),
+                      e.props.get("drain").and_then(|v| v.as_bool()).unwrap_or(false),
                     )
                   })
               })
@@ -126,16 +323,16 @@ impl ModuleLoader for ReverseProxyModuleLoader {
                     (
                       v,
                       e.props.get("limit").and_then(|v| v.as_i128()).map(|v| v as usize),
-                      e.props
-                        .get("idle_timeout")
-                        .map_or(Some(DEFAULT_KEEPALIVE_IDLE_TIMEOUT), |v| {
-                          if v.is_null() {
-                            None
-                          } else {
-                            Some(v.as_i128().map(|v| v as u64).unwrap_or(DEFAULT_KEEPALIVE_IDLE_TIMEOUT))
-                          }
-                        })
-                        .map(Duration::from_millis),
+                      e.props.get("idle_timeout").map_or(default_keepalive_idle_timeout, |v| {
+                        if v.is_null() {
+                          None
+                        } else {
+                          Some(
+                            v.as_duration()
+                              .unwrap_or(Duration::from_millis(DEFAULT_KEEPALIVE_IDLE_TIMEOUT)),
+                          )
+                        }
+                      }),
                       e.props
                         .get("dns_servers")
                         .and_then(|v| v.as_str())
@@ -148,9 +345,58 @@ impl ModuleLoader for ReverseProxyModuleLoader {
               })
               .collect()
           });
+          let proxy_sign = match get_entry!("proxy_sign", config) {
+            Some(entry) => match entry.values.first().and_then(|v| v.as_str()) {
+              Some("aws-sigv4") => Some(ProxyRequestSigning::AwsSigV4 {
+                region: entry
+                  .props
+                  .get("region")
+                  .and_then(|v| v.as_str())
+                  .unwrap_or_default()
+                  .to_string(),
+                service: entry
+                  .props
+                  .get("service")
+                  .and_then(|v| v.as_str())
+                  .unwrap_or_default()
+                  .to_string(),
+                // The credentials are obtained when the configuration is loaded
+                credentials: AwsCredentials::from_env().ok_or(anyhow::anyhow!(
+                  "The AWS credentials for reverse proxy request signing aren't set \
+                   (the `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` environment variables are required)"
+                ))?,
+              }),
+              Some("hmac") => Some(ProxyRequestSigning::Hmac {
+                secret: entry
+                  .props
+                  .get("secret")
+                  .and_then(|v| v.as_str())
+                  .unwrap_or_default()
+                  .to_string(),
+                key_id: entry
+                  .props
+                  .get("key_id")
+                  .and_then(|v| v.as_str())
+                  .map(|v| v.to_string()),
+                header: match entry.props.get("header").and_then(|v| v.as_str()) {
+                  Some(header) => HeaderName::from_str(header)?,
+                  None => HeaderName::from_static("x-signature"),
+                },
+              }),
+              _ => None,
+            },
+            None => None,
+          };
           let mut proxy_builder = connections.get_builder();
-          for (proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout) in proxy_to_raw {
-            proxy_builder = proxy_builder.upstream(proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout);
+          for (proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout, backup, drain) in proxy_to_raw {
+            if drain {
+              proxy_builder = proxy_builder.drain_upstream(proxy_to.clone(), proxy_unix.clone());
+            }
+            proxy_builder = if backup {
+              proxy_builder.backup_upstream(proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout)
+            } else {
+              proxy_builder.upstream(proxy_to, proxy_unix, keepalive_limit, keepalive_idle_timeout)
+            };
           }
           for (to, keepalive_limit, keepalive_idle_timeout, dns_servers) in proxy_to_srv_raw {
             proxy_builder = proxy_builder.upstream_srv(
@@ -162,7 +408,7 @@ impl ModuleLoader for ReverseProxyModuleLoader {
             );
           }
           if let Some(custom_headers) = get_entries!("proxy_request_header", config) {
-            for custom_header in custom_headers.inner.iter().rev() {
+            for custom_header in custom_headers.inner.iter() {
               if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
                 if let Some(header_value) = custom_header.values.get(1).and_then(|v| v.as_str()) {
                   if let Ok(header_name) = HeaderName::from_str(header_name) {
@@ -173,7 +419,7 @@ impl ModuleLoader for ReverseProxyModuleLoader {
             }
           }
           if let Some(custom_headers) = get_entries!("proxy_request_header_replace", config) {
-            for custom_header in custom_headers.inner.iter().rev() {
+            for custom_header in custom_headers.inner.iter() {
               if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
                 if let Some(header_value) = custom_header.values.get(1).and_then(|v| v.as_str()) {
                   if let Ok(header_name) = HeaderName::from_str(header_name) {
@@ -184,7 +430,7 @@ impl ModuleLoader for ReverseProxyModuleLoader {
             }
           }
           if let Some(custom_headers_to_remove) = get_entries!("proxy_request_header_remove", config) {
-            for custom_header in custom_headers_to_remove.inner.iter().rev() {
+            for custom_header in custom_headers_to_remove.inner.iter() {
               if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
                 if let Ok(header_name) = HeaderName::from_str(header_name) {
                   proxy_builder = proxy_builder.proxy_request_header_remove(header_name);
@@ -192,6 +438,109 @@ impl ModuleLoader for ReverseProxyModuleLoader {
               }
             }
           }
+          if let Some(custom_headers) = get_entries!("proxy_response_header", config) {
+            for custom_header in custom_headers.inner.iter() {
+              if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
+                if let Some(header_value) = custom_header.values.get(1).and_then(|v| v.as_str()) {
+                  if let Ok(header_name) = HeaderName::from_str(header_name) {
+                    proxy_builder = proxy_builder.proxy_response_header(header_name, header_value.to_string());
+                  }
+                }
+              }
+            }
+          }
+          if let Some(custom_headers) = get_entries!("proxy_response_header_replace", config) {
+            for custom_header in custom_headers.inner.iter() {
+              if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
+                if let Some(header_value) = custom_header.values.get(1).and_then(|v| v.as_str()) {
+                  if let Ok(header_name) = HeaderName::from_str(header_name) {
+                    proxy_builder = proxy_builder.proxy_response_header_replace(header_name, header_value.to_string());
+                  }
+                }
+              }
+            }
+          }
+          if let Some(custom_headers_to_remove) = get_entries!("proxy_response_header_remove", config) {
+            for custom_header in custom_headers_to_remove.inner.iter() {
+              if let Some(header_name) = custom_header.values.first().and_then(|v| v.as_str()) {
+                if let Ok(header_name) = HeaderName::from_str(header_name) {
+                  proxy_builder = proxy_builder.proxy_response_header_remove(header_name);
+                }
+              }
+            }
+          }
+          for header_name in get_values!("proxy_pass_headers", config)
+            .into_iter()
+            .filter_map(|v| v.as_str().and_then(|v| HeaderName::from_str(v).ok()))
+          {
+            proxy_builder = proxy_builder.proxy_pass_header(header_name);
+          }
+          for header_name in get_values!("proxy_hide_headers", config)
+            .into_iter()
+            .filter_map(|v| v.as_str().and_then(|v| HeaderName::from_str(v).ok()))
+          {
+            proxy_builder = proxy_builder.proxy_hide_header(header_name);
+          }
+          proxy_builder = proxy_builder.proxy_fold_headers(
+            get_value!("proxy_fold_headers", config)
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false),
+          );
+          if let Some(entries) = get_entries!("proxy_cookie_domain", config) {
+            for entry in entries.inner.iter() {
+              if let Some(domain) = entry.values.first().and_then(|v| v.as_str()) {
+                if let Some(replacement) = entry.values.get(1).and_then(|v| v.as_str()) {
+                  proxy_builder = proxy_builder.proxy_cookie_domain(domain.to_string(), replacement.to_string());
+                }
+              }
+            }
+          }
+          if let Some(entries) = get_entries!("proxy_cookie_path", config) {
+            for entry in entries.inner.iter() {
+              if let Some(path) = entry.values.first().and_then(|v| v.as_str()) {
+                if let Some(replacement) = entry.values.get(1).and_then(|v| v.as_str()) {
+                  proxy_builder = proxy_builder.proxy_cookie_path(path.to_string(), replacement.to_string());
+                }
+              }
+            }
+          }
+          if let Some(entries) = get_entries!("proxy_cookie_flags", config) {
+            for flags in entries.inner.iter().filter_map(parse_proxy_cookie_flags) {
+              proxy_builder = proxy_builder.proxy_cookie_flags(flags);
+            }
+          }
+          proxy_builder = proxy_builder.proxy_x_forwarded_for_append(
+            get_value!("proxy_x_forwarded_for", config).and_then(|v| v.as_str()) != Some("overwrite"),
+          );
+          if let Some(entry) = get_entry!("proxy_x_forwarded_for_limit", config) {
+            if let Some(max_entries) = entry.values.first().and_then(|v| v.as_i128()) {
+              proxy_builder = proxy_builder.proxy_x_forwarded_for_max_entries(max_entries as usize);
+            }
+            if let Some(max_size) = entry.props.get("max_size").and_then(|v| v.as_size()) {
+              proxy_builder = proxy_builder.proxy_x_forwarded_for_max_size(max_size as usize);
+            }
+          }
+          proxy_builder = proxy_builder.lb_retry_connection(
+            get_value!("lb_retry_connection", config)
+              .and_then(|v| v.as_bool())
+              .unwrap_or(true),
+          );
+          if let Some(entries) = get_entries!("proxy_next_upstream", config) {
+            // The `proxy_next_upstream` directive takes precedence over the `lb_retry_connection` directive
+            let mut proxy_next_upstream = ProxyNextUpstream::off();
+            for entry in &entries.inner {
+              for condition in entry.values.iter().filter_map(|v| v.as_str()) {
+                proxy_next_upstream.add_condition(condition)?;
+              }
+            }
+            proxy_builder = proxy_builder.proxy_next_upstream(proxy_next_upstream);
+          }
+          proxy_builder = proxy_builder
+            .outlier_detection(get_entry!("proxy_outlier_detection", config).and_then(parse_outlier_detection))
+            .circuit_breaker(get_entry!("proxy_circuit_breaker", config).and_then(parse_circuit_breaker))
+            .sticky_sessions(get_entry!("lb_sticky_sessions", config).and_then(parse_sticky_sessions))
+            .proxy_tcp_keepalive(get_entry!("proxy_tcp_keepalive", config).and_then(parse_tcp_keepalive))
+            .proxy_http2_keepalive(get_entry!("proxy_http2_keepalive", config).and_then(parse_http2_keepalive));
           let proxy = proxy_builder
             .lb_algorithm({
               let algorithm_name = get_value!("lb_algorithm", config)
@@ -207,26 +556,26 @@ impl ModuleLoader for ReverseProxyModuleLoader {
                 ))?,
               }
             })
+            .lb_failback_delay(
+              get_value!("lb_failback_delay", config)
+                .and_then(|v| v.as_duration())
+                .unwrap_or(Duration::from_secs(30)),
+            )
             .lb_health_check(
               get_value!("lb_health_check", config)
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false),
             )
-            .lb_health_check_window(Duration::from_millis(
+            .lb_health_check_window(
               get_value!("lb_health_check_window", config)
-                .and_then(|v| v.as_i128())
-                .unwrap_or(5000) as u64,
-            ))
+                .and_then(|v| v.as_duration())
+                .unwrap_or(Duration::from_millis(5000)),
+            )
             .lb_health_check_max_fails(
               get_value!("lb_health_check_max_fails", config)
                 .and_then(|v| v.as_i128())
                 .unwrap_or(3) as u64,
             )
-            .lb_retry_connection(
-              get_value!("lb_retry_connection", config)
-                .and_then(|v| v.as_bool())
-                .unwrap_or(true),
-            )
             .proxy_http2(
               get_value!("proxy_http2", config)
                 .and_then(|v| v.as_bool())
@@ -237,21 +586,52 @@ impl ModuleLoader for ReverseProxyModuleLoader {
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false),
             )
+            .proxy_http3(
+              get_value!("proxy_http3", config)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            )
+            .http3_runtime(secondary_runtime.handle().to_owned())
             .proxy_intercept_errors(
               get_value!("proxy_intercept_errors", config)
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false),
             )
+            .proxy_sse(
+              get_value!("proxy_sse", config)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            )
+            .proxy_flush_interval(get_value!("proxy_flush_interval", config).and_then(|v| v.as_duration()))
             .proxy_keepalive(
               get_value!("proxy_keepalive", config)
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true),
             )
+            .proxy_pool_max_idle_per_host(
+              get_value!("proxy_pool_max_idle_per_host", config)
+                .and_then(|v| v.as_i128())
+                .map(|v| v as usize),
+            )
+            .proxy_max_requests_per_connection(
+              get_value!("proxy_max_requests_per_connection", config)
+                .and_then(|v| v.as_i128())
+                .map(|v| v as u64),
+            )
             .proxy_no_verification(
               get_value!("proxy_no_verification", config)
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false),
             )
+            .proxy_require_ct(get_value!("proxy_require_ct", config).and_then(|v| {
+              if let Some(required_scts) = v.as_i128() {
+                Some(required_scts as usize)
+              } else if v.as_bool() == Some(true) {
+                Some(DEFAULT_REQUIRED_SCTS)
+              } else {
+                None
+              }
+            }))
             .proxy_proxy_header(
               get_value!("proxy_proxy_header", config)
                 .and_then(|v| v.as_str())
@@ -261,7 +641,34 @@ impl ModuleLoader for ReverseProxyModuleLoader {
                   _ => None,
                 }),
             )
+            .proxy_host_header(
+              get_value!("proxy_host_header", config)
+                .and_then(|v| v.as_str())
+                .map(|v| match v {
+                  "preserve" => ProxyHostHeader::Preserve,
+                  "upstream" => ProxyHostHeader::Upstream,
+                  _ => ProxyHostHeader::Custom(v.to_string()),
+                }),
+            )
+            .proxy_sign(proxy_sign)
             .build();
+          let proxy = Arc::new(proxy);
+
+          // The `proxy_warmup_connections` directive specifies the number of connections
+          // pre-established to each backend server after the configuration is loaded
+          if let Some(warmup_connections) = get_value!("proxy_warmup_connections", config)
+            .and_then(|v| v.as_i128())
+            .filter(|v| *v > 0)
+          {
+            let proxy = proxy.clone();
+            let error_logger = ErrorLogger::new_multiple(config.observability.log_channels.clone())
+              .with_level_filter(config.observability.log_level_filter.clone());
+            queue_warmup_task(Box::new(move || {
+              Box::pin(async move {
+                proxy.warm_up(warmup_connections as usize, &error_logger).await;
+              })
+            }));
+          }
 
           Ok(Arc::new(ReverseProxyModule { proxy }))
         })?,
@@ -277,6 +684,18 @@ impl ModuleLoader for ReverseProxyModuleLoader {
     config: &ServerConfiguration,
     used_properties: &mut std::collections::HashSet<String>,
   ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("lb_failback_delay", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `lb_failback_delay` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid load balancer failback delay"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("lb_health_check", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -311,12 +730,8 @@ impl ModuleLoader for ReverseProxyModuleLoader {
           Err(anyhow::anyhow!(
             "The `lb_health_check_window` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_integer() && !entry.values[0].is_null() {
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
           Err(anyhow::anyhow!("Invalid load balancer health check window"))?
-        } else if let Some(value) = entry.values[0].as_i128() {
-          if value < 0 {
-            Err(anyhow::anyhow!("Invalid load balancer health check window"))?
-          }
         }
       }
     };
@@ -331,6 +746,16 @@ impl ModuleLoader for ReverseProxyModuleLoader {
           Err(anyhow::anyhow!("Invalid proxy backend server"))?
         } else if !entry.props.get("unix").is_none_or(|v| v.is_string()) {
           Err(anyhow::anyhow!("Invalid proxy Unix socket path"))?
+        } else if let Some(url) = entry.values[0].as_str().filter(|url| !url.contains('{')) {
+          // URLs with placeholders are validated after the placeholders are replaced
+          let is_valid_url = url.parse::<hyper::Uri>().is_ok_and(|uri| {
+            uri
+              .host()
+              .is_some_and(|host| !host.starts_with('[') || parse_ip_literal(host).is_some())
+          });
+          if !is_valid_url {
+            Err(anyhow::anyhow!("Invalid proxy backend server URL: {}", url))?
+          }
         }
         if let Some(prop) = entry.props.get("limit") {
           if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
@@ -338,12 +763,18 @@ impl ModuleLoader for ReverseProxyModuleLoader {
           }
         }
         if let Some(prop) = entry.props.get("idle_timeout") {
-          if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
+          if !prop.is_null() && prop.as_duration().is_none_or(|v| v.is_zero()) {
             Err(anyhow::anyhow!(
               "Invalid proxy idle keep-alive connection timeout for a backend server"
             ))?
           }
         }
+        if !entry.props.get("backup").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!("Invalid proxy backup backend server option"))?
+        }
+        if !entry.props.get("drain").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!("Invalid proxy backend server draining option"))?
+        }
 
         #[cfg(not(unix))]
         if entry.props.get("unix").is_some() {
@@ -367,7 +798,7 @@ impl ModuleLoader for ReverseProxyModuleLoader {
           }
         }
         if let Some(prop) = entry.props.get("idle_timeout") {
-          if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
+          if !prop.is_null() && prop.as_duration().is_none_or(|v| v.is_zero()) {
             Err(anyhow::anyhow!(
               "Invalid proxy idle keep-alive connection timeout for a backend server"
             ))?
@@ -397,6 +828,32 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("proxy_sse", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_sse` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid reverse proxy streaming responses enabling option"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("proxy_flush_interval", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_flush_interval` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none() {
+          Err(anyhow::anyhow!("Invalid reverse proxy flush interval"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("proxy_no_verification", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -411,6 +868,20 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("proxy_require_ct", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_require_ct` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() && entry.values[0].as_i128().is_none_or(|v| v < 1) {
+          Err(anyhow::anyhow!(
+            "Invalid proxy backend server certificate transparency requirement"
+          ))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("proxy_request_header", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 2 {
@@ -449,6 +920,60 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("proxy_pool_max_idle_per_host", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_pool_max_idle_per_host` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 0) {
+          Err(anyhow::anyhow!(
+            "Invalid maximum number of idle connections for a backend server"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("proxy_warmup_connections", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_warmup_connections` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_i128().is_none_or(|v| v < 0) {
+          Err(anyhow::anyhow!(
+            "Invalid number of pre-established connections for a backend server"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("proxy_pool_idle_timeout", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_pool_idle_timeout` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none_or(|v| v.is_zero()) {
+          Err(anyhow::anyhow!("Invalid proxy idle keep-alive connection timeout"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("proxy_max_requests_per_connection", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_max_requests_per_connection` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 1) {
+          Err(anyhow::anyhow!(
+            "Invalid maximum number of requests per backend server connection"
+          ))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("proxy_request_header_replace", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 2 {
@@ -463,6 +988,170 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("proxy_response_header", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `proxy_response_header` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The header name must be a string"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("The header value must be a string"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_response_header_remove", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_response_header_remove` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The header name must be a string"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_response_header_replace", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `proxy_response_header_replace` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The header name must be a string"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("The header value must be a string"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_pass_headers", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `proxy_pass_headers` configuration property must have at least one value"
+          ))?
+        }
+        for value in &entry.values {
+          if value.as_str().is_none_or(|v| HeaderName::from_str(v).is_err()) {
+            Err(anyhow::anyhow!("Invalid passed hop-by-hop header name"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_hide_headers", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `proxy_hide_headers` configuration property must have at least one value"
+          ))?
+        }
+        for value in &entry.values {
+          if value.as_str().is_none_or(|v| HeaderName::from_str(v).is_err()) {
+            Err(anyhow::anyhow!("Invalid hidden header name"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_x_forwarded_for", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_x_forwarded_for` configuration property must have exactly one value"
+          ))?
+        } else if !matches!(entry.values[0].as_str(), Some("append") | Some("overwrite")) {
+          Err(anyhow::anyhow!("Invalid `X-Forwarded-For` header handling mode"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_x_forwarded_for_limit", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_x_forwarded_for_limit` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_i128().unwrap_or(0) < 1 {
+          Err(anyhow::anyhow!(
+            "Invalid maximum number of `X-Forwarded-For` header entries"
+          ))?
+        } else if let Some(prop) = entry.props.get("max_size") {
+          if prop.as_size().is_none_or(|v| v == 0) {
+            Err(anyhow::anyhow!("Invalid maximum `X-Forwarded-For` header size"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_fold_headers", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_fold_headers` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid reverse proxy header folding enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_cookie_domain", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `proxy_cookie_domain` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The cookie domain must be a string"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("The cookie domain replacement must be a string"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_cookie_path", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `proxy_cookie_path` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The cookie path must be a string"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("The cookie path replacement must be a string"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_cookie_flags", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_cookie_flags` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The cookie name must be a string"))?
+        }
+        for prop_name in ["secure", "httponly"] {
+          if entry.props.get(prop_name).is_some_and(|v| !v.is_bool()) {
+            Err(anyhow::anyhow!("Invalid `{prop_name}` cookie attribute option"))?
+          }
+        }
+        if let Some(same_site) = entry.props.get("samesite") {
+          if same_site.as_str().and_then(normalize_same_site).is_none() {
+            Err(anyhow::anyhow!(
+              "Invalid `samesite` cookie attribute value (must be `strict`, `lax` or `none`)"
+            ))?
+          }
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("proxy_http2", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -475,6 +1164,56 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("proxy_http2_keepalive", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_http2_keepalive` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_duration().is_none_or(|v| v.is_zero()) {
+          Err(anyhow::anyhow!("Invalid reverse proxy HTTP/2 PING interval"))?
+        }
+        if let Some(prop) = entry.props.get("timeout") {
+          if prop.as_duration().is_none_or(|v| v.is_zero()) {
+            Err(anyhow::anyhow!("Invalid reverse proxy HTTP/2 PING timeout"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_tcp_keepalive", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_tcp_keepalive` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid reverse proxy TCP keepalive enabling option"))?
+        }
+        for prop_name in ["time", "interval"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if prop.as_duration().is_none_or(|v| v.is_zero()) {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` duration for reverse proxy TCP keepalive"
+              ))?
+            }
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_http3", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_http3` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid reverse proxy HTTP/3 enabling option"))?
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("lb_retry_connection", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -489,6 +1228,138 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("proxy_next_upstream", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `proxy_next_upstream` configuration property must have at least one value"
+          ))?
+        }
+        let mut proxy_next_upstream = ProxyNextUpstream::off();
+        for value in &entry.values {
+          if let Some(condition) = value.as_str() {
+            if condition == "off" && entry.values.len() > 1 {
+              Err(anyhow::anyhow!(
+                "The `off` condition for passing a request to the next backend server can't be combined with other conditions"
+              ))?
+            }
+            proxy_next_upstream.add_condition(condition)?;
+          } else {
+            Err(anyhow::anyhow!(
+              "Invalid condition for passing a request to the next backend server"
+            ))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_outlier_detection", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_outlier_detection` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid reverse proxy outlier detection enabling option"
+          ))?
+        }
+        for prop_name in ["consecutive_5xx", "success_rate_stdev_factor", "latency_factor"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if !prop.is_null() && prop.as_i128().unwrap_or(0) < 1 {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` value for reverse proxy outlier detection"
+              ))?
+            }
+          }
+        }
+        for prop_name in ["minimum_hosts", "request_volume"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if prop.as_i128().is_none_or(|v| v < 0) {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` value for reverse proxy outlier detection"
+              ))?
+            }
+          }
+        }
+        if let Some(prop) = entry.props.get("max_ejection_percent") {
+          if prop.as_i128().is_none_or(|v| !(0..=100).contains(&v)) {
+            Err(anyhow::anyhow!(
+              "Invalid maximum ejection percentage for reverse proxy outlier detection"
+            ))?
+          }
+        }
+        for prop_name in ["interval", "base_ejection_time", "max_ejection_time"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if prop.as_duration().is_none() {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` duration for reverse proxy outlier detection"
+              ))?
+            }
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_circuit_breaker", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_circuit_breaker` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid reverse proxy circuit breaker enabling option"))?
+        }
+        for prop_name in ["failures", "half_open_requests", "successes"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if prop.as_i128().is_none_or(|v| v < 1) {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` value for reverse proxy circuit breaker"
+              ))?
+            }
+          }
+        }
+        if let Some(prop) = entry.props.get("cooldown") {
+          if prop.as_duration().is_none() {
+            Err(anyhow::anyhow!(
+              "Invalid cooldown duration for reverse proxy circuit breaker"
+            ))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("lb_sticky_sessions", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `lb_sticky_sessions` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid load balancer sticky sessions enabling option"))?
+        }
+        if let Some(prop) = entry.props.get("cookie") {
+          if prop.as_str().is_none_or(|name| {
+            name.is_empty()
+              || !name
+                .bytes()
+                .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+          }) {
+            Err(anyhow::anyhow!("Invalid cookie name for load balancer sticky sessions"))?
+          }
+        }
+        for prop_name in ["ttl", "drain_timeout"] {
+          if let Some(prop) = entry.props.get(prop_name) {
+            if prop.as_duration().is_none_or(|v| v.is_zero()) {
+              Err(anyhow::anyhow!(
+                "Invalid `{prop_name}` duration for load balancer sticky sessions"
+              ))?
+            }
+          }
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("lb_algorithm", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -525,6 +1396,87 @@ impl ModuleLoader for ReverseProxyModuleLoader {
       }
     }
 
+    if let Some(entries) = get_entries_for_validation!("proxy_host_header", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_host_header` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_str().is_none_or(|v| v.is_empty()) {
+          Err(anyhow::anyhow!("Invalid reverse proxy `Host` header"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_sign", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_sign` configuration property must have exactly one value"
+          ))?
+        }
+        let is_non_empty_prop = |name: &str| {
+          entry
+            .props
+            .get(name)
+            .and_then(|v| v.as_str())
+            .is_some_and(|v| !v.is_empty())
+        };
+        match entry.values[0].as_str() {
+          Some("aws-sigv4") => {
+            if !is_non_empty_prop("region") || !is_non_empty_prop("service") {
+              Err(anyhow::anyhow!(
+                "The AWS Signature Version 4 request signing requires the `region` and `service` props"
+              ))?
+            }
+          }
+          Some("hmac") => {
+            if !is_non_empty_prop("secret") {
+              Err(anyhow::anyhow!("The HMAC request signing requires the `secret` prop"))?
+            } else if entry.props.get("key_id").is_some_and(|v| !v.is_string()) {
+              Err(anyhow::anyhow!("Invalid HMAC request signing key ID"))?
+            } else if entry
+              .props
+              .get("header")
+              .is_some_and(|v| v.as_str().is_none_or(|v| HeaderName::from_str(v).is_err()))
+            {
+              Err(anyhow::anyhow!("Invalid HMAC request signature header name"))?
+            }
+          }
+          None if entry.values[0].is_null() => (),
+          _ => Err(anyhow::anyhow!("Unsupported reverse proxy request signing method"))?,
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_verify_digest", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_verify_digest` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid reverse proxy response integrity verification enabling option"
+          ))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("proxy_decompress", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `proxy_decompress` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!(
+            "Invalid reverse proxy response decompression enabling option"
+          ))?
+        }
+      }
+    }
+
     if let Some(entries) = get_entries_for_validation!("proxy_concurrent_conns", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -545,9 +1497,120 @@ impl ModuleLoader for ReverseProxyModuleLoader {
   }
 }
 
+/// Decodes the backend server response body (as for the `proxy_decompress` directive),
+/// so that the response can be cached and compressed for the client independently of the backend server.
+fn decompress_response(
+  response: Response<BoxBody<Bytes, std::io::Error>>,
+  is_head_request: bool,
+) -> Response<BoxBody<Bytes, std::io::Error>> {
+  let (mut response_parts, response_body) = response.into_parts();
+
+  // The backend server response doesn't vary by the `Accept-Encoding` header, since it's fixed by the reverse proxy
+  if let Some(vary) = response_parts.headers.get(header::VARY) {
+    let vary = vary
+      .to_str()
+      .map(|v| {
+        v.split(',')
+          .map(|v| v.trim())
+          .filter(|v| !v.eq_ignore_ascii_case("accept-encoding"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      })
+      .ok();
+    match vary.as_deref() {
+      Some("") => {
+        response_parts.headers.remove(header::VARY);
+      }
+      Some(vary) => {
+        if let Ok(vary) = HeaderValue::from_str(vary) {
+          response_parts.headers.insert(header::VARY, vary);
+        }
+      }
+      None => (),
+    }
+  }
+
+  let Some(content_coding) = response_parts
+    .headers
+    .get(header::CONTENT_ENCODING)
+    .and_then(|v| v.to_str().ok())
+    .filter(|v| is_decodable_content_coding(v))
+    .map(String::from)
+  else {
+    return Response::from_parts(response_parts, response_body);
+  };
+
+  response_parts.headers.remove(header::CONTENT_ENCODING);
+  response_parts.headers.remove(header::CONTENT_LENGTH);
+
+  // The decoded response is a different representation, so the entity tag is only weakly equal
+  if let Some(etag) = response_parts.headers.get_mut(header::ETAG) {
+    if !etag.as_bytes().starts_with(b"W/") {
+      let mut weak_etag = b"W/".to_vec();
+      weak_etag.extend_from_slice(etag.as_bytes());
+      if let Ok(weak_etag) = HeaderValue::from_bytes(&weak_etag) {
+        *etag = weak_etag;
+      }
+    }
+  }
+
+  let has_body =
+    !is_head_request && !matches!(response_parts.status, StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED);
+  let response_body = if has_body {
+    decompress_body(response_body, &content_coding)
+  } else {
+    response_body
+  };
+  Response::from_parts(response_parts, response_body)
+}
+
+/// Verifies the integrity digests (RFC 9530) of the backend server response (as for the `proxy_verify_digest` directive).
+/// The response body is buffered, so that the response isn't sent to the client if the digests don't match.
+async fn verify_response_digest(
+  response: Response<BoxBody<Bytes, std::io::Error>>,
+  is_head_request: bool,
+) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error + Send + Sync>> {
+  let (response_parts, response_body) = response.into_parts();
+
+  let has_body =
+    !is_head_request && !matches!(response_parts.status, StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED);
+  let content_digest = response_parts
+    .headers
+    .get("content-digest")
+    .and_then(|v| v.to_str().ok())
+    .map(String::from);
+  // The representation data is the response content, unless the response contains only a part of the representation
+  let repr_digest = response_parts
+    .headers
+    .get("repr-digest")
+    .filter(|_| response_parts.status != StatusCode::PARTIAL_CONTENT)
+    .and_then(|v| v.to_str().ok())
+    .map(String::from);
+  if !has_body || (content_digest.is_none() && repr_digest.is_none()) {
+    return Ok(Response::from_parts(response_parts, response_body));
+  }
+
+  let response_body = Limited::new(response_body, MAX_VERIFIED_RESPONSE_SIZE)
+    .collect()
+    .await?
+    .to_bytes();
+  for digest in [content_digest, repr_digest].into_iter().flatten() {
+    if verify_integrity_digest(&digest, &response_body) == Some(false) {
+      Err(anyhow::anyhow!(
+        "The backend server response doesn't match the integrity digest"
+      ))?
+    }
+  }
+
+  Ok(Response::from_parts(
+    response_parts,
+    Full::new(response_body).map_err(|e| match e {}).boxed(),
+  ))
+}
+
 /// A reverse proxy module
 struct ReverseProxyModule {
-  proxy: ReverseProxy,
+  proxy: Arc<ReverseProxy>,
 }
 
 impl Module for ReverseProxyModule {
@@ -567,15 +1630,66 @@ struct ReverseProxyModuleHandlers {
 impl ModuleHandlers for ReverseProxyModuleHandlers {
   async fn request_handler(
     &mut self,
-    request: Request<BoxBody<Bytes, std::io::Error>>,
+    mut request: Request<BoxBody<Bytes, std::io::Error>>,
     config: &ServerConfiguration,
     socket_data: &SocketData,
     error_logger: &ErrorLogger,
   ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
-    self
+    let proxy_decompress = get_value!("proxy_decompress", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+    let proxy_verify_digest = get_value!("proxy_verify_digest", config)
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+    if !proxy_decompress && !proxy_verify_digest {
+      return self
+        .inner
+        .request_handler(request, config, socket_data, error_logger)
+        .await;
+    }
+
+    if proxy_decompress {
+      // Request the content codings that can be decoded, regardless of the content codings accepted by the client,
+      // so that the backend server responses don't vary by the client
+      request.headers_mut().insert(
+        header::ACCEPT_ENCODING,
+        HeaderValue::from_static(DECODABLE_CONTENT_CODINGS),
+      );
+    }
+    let is_head_request = request.method() == Method::HEAD;
+    let mut response_data = self
       .inner
       .request_handler(request, config, socket_data, error_logger)
-      .await
+      .await?;
+    if proxy_verify_digest {
+      // The digests are verified before decoding the response, since they cover the encoded content
+      if let Some(response) = response_data.response.take() {
+        match verify_response_digest(response, is_head_request).await {
+          Ok(response) => response_data.response = Some(response),
+          Err(err) => {
+            error_logger
+              .log(&format!(
+                "Failed to verify the backend server response integrity ({}): {err}",
+                ErrorCode::BACKEND_RESPONSE_INTEGRITY_FAILED
+              ))
+              .await;
+            return Ok(ResponseData {
+              request: None,
+              response: None,
+              response_status: Some(ErrorCode::BACKEND_RESPONSE_INTEGRITY_FAILED.status_code()),
+              response_headers: Some(ErrorCode::BACKEND_RESPONSE_INTEGRITY_FAILED.to_headers()),
+              new_remote_address: None,
+            });
+          }
+        }
+      }
+    }
+    if proxy_decompress {
+      response_data.response = response_data
+        .response
+        .map(|response| decompress_response(response, is_head_request));
+    }
+    Ok(response_data)
   }
 
   async fn metric_data_before_handler(
@@ -0,0 +1,512 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Method, Request, Response, StatusCode};
+use tokio::sync::RwLock;
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::ModuleCache;
+use ferron_common::{get_entries, get_entries_for_validation, get_entry, get_value};
+
+/// The default interval of regenerating the site map
+const DEFAULT_SITEMAP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The maximum number of URLs in a site map, as specified by the sitemaps protocol
+const MAX_SITEMAP_URLS: usize = 50000;
+
+/// The maximum depth of directories scanned when generating the site map
+const MAX_SCAN_DEPTH: usize = 32;
+
+/// The file extensions of HTML documents listed in the site map
+const HTML_EXTENSIONS: [&str; 3] = ["html", "htm", "xhtml"];
+
+/// Escapes the text for use in XML documents
+fn escape_xml(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// Converts a glob pattern for URL paths into a "robots.txt" path pattern
+fn glob_to_robots_pattern(glob: &str) -> String {
+  let mut pattern = String::with_capacity(glob.len() + 2);
+  let mut chars = glob.strip_prefix("**/").map_or(glob.chars(), |glob| glob.chars());
+  if !glob.starts_with('/') {
+    pattern.push_str("/*");
+  }
+  while let Some(c) = chars.next() {
+    match c {
+      '*' | '?' => {
+        if !pattern.ends_with('*') {
+          pattern.push('*');
+        }
+      }
+      '[' => {
+        // Character classes can't be expressed in "robots.txt" patterns
+        for c in chars.by_ref() {
+          if c == ']' {
+            break;
+          }
+        }
+        if !pattern.ends_with('*') {
+          pattern.push('*');
+        }
+      }
+      _ => pattern.push(c),
+    }
+  }
+  if !pattern.ends_with('*') {
+    pattern.push('$');
+  }
+  pattern
+}
+
+/// A site map entry
+struct SitemapEntry {
+  /// The URL path (percent-encoded)
+  path: String,
+  /// The last modification time of the page
+  last_modified: Option<DateTime<Utc>>,
+}
+
+/// Settings of site map generation
+struct SitemapSettings {
+  root: PathBuf,
+  indexes: Vec<String>,
+  directory_listing: bool,
+  exclusions: Vec<glob::Pattern>,
+}
+
+impl SitemapSettings {
+  /// Checks whether the URL path is excluded from the site map
+  fn is_excluded(&self, url_path: &str) -> bool {
+    let options = glob::MatchOptions {
+      case_sensitive: true,
+      require_literal_separator: true,
+      require_literal_leading_dot: false,
+    };
+    self
+      .exclusions
+      .iter()
+      .any(|exclusion| exclusion.matches_with(url_path, options))
+  }
+
+  /// Scans the webroot for pages to be listed in the site map
+  fn scan(&self) -> Vec<SitemapEntry> {
+    let mut entries = Vec::new();
+    self.scan_directory(&self.root, "/", 0, &mut entries);
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+  }
+
+  /// Scans the directory for pages to be listed in the site map
+  fn scan_directory(&self, directory: &Path, url_path: &str, depth: usize, entries: &mut Vec<SitemapEntry>) {
+    if depth > MAX_SCAN_DEPTH || entries.len() >= MAX_SITEMAP_URLS {
+      return;
+    }
+    let Ok(directory_entries) = std::fs::read_dir(directory) else {
+      return;
+    };
+    let mut files = Vec::new();
+    let mut subdirectories = Vec::new();
+    for directory_entry in directory_entries.flatten() {
+      let Ok(name) = directory_entry.file_name().into_string() else {
+        continue;
+      };
+      // Hidden files (like ".git" or ".htaccess") aren't listed in the site map
+      if name.starts_with('.') {
+        continue;
+      }
+      let Ok(metadata) = std::fs::metadata(directory_entry.path()) else {
+        continue;
+      };
+      if metadata.is_dir() {
+        subdirectories.push(name);
+      } else if metadata.is_file() {
+        files.push((name, metadata.modified().ok()));
+      }
+    }
+
+    // A directory is listed, if it has an index file or directory listings are enabled
+    let index_file = self
+      .indexes
+      .iter()
+      .find_map(|index| files.iter().find(|(name, _)| name == index));
+    if (index_file.is_some() || self.directory_listing) && !self.is_excluded(url_path) {
+      let last_modified = match index_file {
+        Some((_, modified)) => *modified,
+        None => std::fs::metadata(directory).and_then(|m| m.modified()).ok(),
+      };
+      entries.push(SitemapEntry {
+        path: url_path.to_string(),
+        last_modified: last_modified.map(DateTime::<Utc>::from),
+      });
+    }
+
+    for (name, modified) in &files {
+      if entries.len() >= MAX_SITEMAP_URLS {
+        return;
+      }
+      let is_html = Path::new(name).extension().and_then(|e| e.to_str()).is_some_and(|e| {
+        HTML_EXTENSIONS
+          .iter()
+          .any(|html_extension| e.eq_ignore_ascii_case(html_extension))
+      });
+      let is_index = index_file.is_some_and(|(index_name, _)| index_name == name);
+      if !is_html || is_index {
+        continue;
+      }
+      let file_url_path = format!("{url_path}{}", urlencoding::encode(name));
+      if !self.is_excluded(&file_url_path) {
+        entries.push(SitemapEntry {
+          path: file_url_path,
+          last_modified: modified.map(DateTime::<Utc>::from),
+        });
+      }
+    }
+
+    for name in &subdirectories {
+      self.scan_directory(
+        &directory.join(name),
+        &format!("{url_path}{}/", urlencoding::encode(name)),
+        depth + 1,
+        entries,
+      );
+    }
+  }
+}
+
+/// A site map generation module loader
+pub struct SitemapModuleLoader {
+  cache: ModuleCache<SitemapModule>,
+}
+
+impl Default for SitemapModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl SitemapModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec![
+        "sitemap",
+        "sitemap_exclude",
+        "sitemap_interval",
+        "robots_txt",
+        "root",
+        "index",
+        "directory_listing",
+      ]),
+    }
+  }
+}
+
+impl ModuleLoader for SitemapModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let sitemap_enabled = get_value!("sitemap", config).and_then(|v| v.as_bool()).unwrap_or(false);
+          let robots_txt_enabled = get_value!("robots_txt", config)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+          let mut exclusions = Vec::new();
+          if let Some(entries) = get_entries!("sitemap_exclude", config) {
+            for entry in &entries.inner {
+              for value in &entry.values {
+                if let Some(exclusion) = value.as_str() {
+                  exclusions.push(glob::Pattern::new(exclusion)?);
+                }
+              }
+            }
+          }
+          let robots_disallow = exclusions
+            .iter()
+            .map(|exclusion| glob_to_robots_pattern(exclusion.as_str()))
+            .collect();
+          let interval = get_value!("sitemap_interval", config)
+            .and_then(|v| v.as_duration())
+            .unwrap_or(DEFAULT_SITEMAP_INTERVAL);
+
+          let entries = Arc::new(RwLock::new(None));
+          if let Some(root) = get_value!("root", config)
+            .and_then(|v| v.as_str())
+            .filter(|_| sitemap_enabled)
+          {
+            let settings = Arc::new(SitemapSettings {
+              root: PathBuf::from(root),
+              indexes: get_entry!("index", config)
+                .map(|e| e.values.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                .unwrap_or(vec![
+                  "index.html".to_string(),
+                  "index.htm".to_string(),
+                  "index.xhtml".to_string(),
+                ]),
+              directory_listing: get_value!("directory_listing", config)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+              exclusions,
+            });
+
+            // The site map is regenerated periodically, until the module is dropped
+            let entries_weak: Weak<RwLock<Option<Arc<Vec<SitemapEntry>>>>> = Arc::downgrade(&entries);
+            secondary_runtime.spawn(async move {
+              loop {
+                let settings = settings.clone();
+                let Ok(scanned_entries) = tokio::task::spawn_blocking(move || settings.scan()).await else {
+                  break;
+                };
+                let Some(entries) = entries_weak.upgrade() else {
+                  break;
+                };
+                entries.write().await.replace(Arc::new(scanned_entries));
+                drop(entries);
+                tokio::time::sleep(interval).await;
+              }
+            });
+          }
+
+          Ok(Arc::new(SitemapModule {
+            entries,
+            robots_disallow: Arc::new(robots_disallow),
+            sitemap_enabled,
+            robots_txt_enabled,
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["sitemap", "robots_txt"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("sitemap", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `sitemap` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid site map generation enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("robots_txt", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `robots_txt` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid \"robots.txt\" generation enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("sitemap_exclude", config, used_properties) {
+      for entry in &entries.inner {
+        for value in &entry.values {
+          if value.as_str().is_none_or(|v| glob::Pattern::new(v).is_err()) {
+            Err(anyhow::anyhow!("Invalid site map exclusion glob pattern"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("sitemap_interval", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `sitemap_interval` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0].as_duration().is_none_or(|v| v.is_zero()) {
+          Err(anyhow::anyhow!("Invalid site map regeneration interval"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("sitemap_base_url", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `sitemap_base_url` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].as_str().is_some_and(|v| {
+          v.parse::<hyper::Uri>()
+            .is_ok_and(|uri| uri.scheme().is_some() && uri.authority().is_some())
+        }) {
+          Err(anyhow::anyhow!("Invalid site map base URL"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A site map generation module
+struct SitemapModule {
+  entries: Arc<RwLock<Option<Arc<Vec<SitemapEntry>>>>>,
+  robots_disallow: Arc<Vec<String>>,
+  sitemap_enabled: bool,
+  robots_txt_enabled: bool,
+}
+
+impl Module for SitemapModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(SitemapModuleHandlers {
+      entries: self.entries.clone(),
+      robots_disallow: self.robots_disallow.clone(),
+      sitemap_enabled: self.sitemap_enabled,
+      robots_txt_enabled: self.robots_txt_enabled,
+    })
+  }
+}
+
+/// Handlers for the site map generation module
+struct SitemapModuleHandlers {
+  entries: Arc<RwLock<Option<Arc<Vec<SitemapEntry>>>>>,
+  robots_disallow: Arc<Vec<String>>,
+  sitemap_enabled: bool,
+  robots_txt_enabled: bool,
+}
+
+/// Constructs a response with a generated document
+fn generated_document_response(
+  content_type: &'static str,
+  document: String,
+  is_head: bool,
+) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error + Send + Sync>> {
+  Ok(
+    Response::builder()
+      .header(header::CONTENT_TYPE, content_type)
+      .header(header::CONTENT_LENGTH, document.len())
+      .body(
+        Full::new(if is_head { Bytes::new() } else { Bytes::from(document) })
+          .map_err(|e| match e {})
+          .boxed(),
+      )?,
+  )
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for SitemapModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let is_sitemap = self.sitemap_enabled && request.uri().path() == "/sitemap.xml";
+    let is_robots_txt = self.robots_txt_enabled && request.uri().path() == "/robots.txt";
+    if !(is_sitemap || is_robots_txt) || !matches!(request.method(), &Method::GET | &Method::HEAD) {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+    let is_head = request.method() == Method::HEAD;
+
+    // The URLs in the site map must be absolute
+    let base_url = match get_value!("sitemap_base_url", config).and_then(|v| v.as_str()) {
+      Some(base_url) => base_url.trim_end_matches('/').to_string(),
+      None => format!(
+        "{}://{}",
+        if socket_data.encrypted { "https" } else { "http" },
+        request
+          .headers()
+          .get(header::HOST)
+          .and_then(|h| h.to_str().ok())
+          .or_else(|| request.uri().authority().map(|a| a.as_str()))
+          .unwrap_or("localhost")
+      ),
+    };
+
+    let response = if is_sitemap {
+      let Some(entries) = self.entries.read().await.clone() else {
+        // The site map hasn't been generated yet
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::SERVICE_UNAVAILABLE),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      };
+      let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+      );
+      for entry in entries.iter() {
+        sitemap.push_str("  <url>\n    <loc>");
+        sitemap.push_str(&escape_xml(&format!("{base_url}{}", entry.path)));
+        sitemap.push_str("</loc>\n");
+        if let Some(last_modified) = entry.last_modified {
+          sitemap.push_str(&format!(
+            "    <lastmod>{}</lastmod>\n",
+            last_modified.format("%Y-%m-%dT%H:%M:%SZ")
+          ));
+        }
+        sitemap.push_str("  </url>\n");
+      }
+      sitemap.push_str("</urlset>\n");
+      generated_document_response("application/xml", sitemap, is_head)?
+    } else {
+      let mut robots_txt = String::from("User-agent: *\n");
+      if self.robots_disallow.is_empty() {
+        robots_txt.push_str("Disallow:\n");
+      }
+      for disallowed in self.robots_disallow.iter() {
+        robots_txt.push_str(&format!("Disallow: {disallowed}\n"));
+      }
+      if self.sitemap_enabled {
+        robots_txt.push_str(&format!("\nSitemap: {base_url}/sitemap.xml\n"));
+      }
+      generated_document_response("text/plain; charset=utf-8", robots_txt, is_head)?
+    };
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: Some(response),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
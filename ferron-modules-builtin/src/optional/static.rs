@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::ffi::OsStr;
 #[cfg(any(feature = "runtime-monoio", feature = "runtime-vibeio"))]
@@ -15,6 +15,7 @@ use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncod
 use async_compression::zstd::CParameter;
 use async_compression::Level;
 use async_trait::async_trait;
+use base64::Engine;
 use chrono::{DateTime, Local};
 use futures_util::TryStreamExt;
 use hashlink::LruCache;
@@ -25,6 +26,7 @@ use hyper::header::{self, HeaderValue};
 use hyper::{HeaderMap, Method, Request, Response, StatusCode};
 #[cfg(feature = "runtime-monoio")]
 use monoio::fs;
+use sha2::{Digest, Sha256};
 #[cfg(feature = "runtime-tokio")]
 use tokio::fs::{self, ReadDir};
 #[cfg(feature = "runtime-tokio")]
@@ -34,18 +36,30 @@ use tokio_util::io::{ReaderStream, StreamReader};
 #[cfg(feature = "runtime-vibeio")]
 use vibeio::fs;
 
-use ferron_common::config::ServerConfiguration;
+use crate::util::{
+  collect_archive_entries, format_integrity_digest, write_archive, ArchiveFormat, ChannelWriter, ZIP_ARCHIVE_MAX_SIZE,
+};
+use ferron_common::config::{ServerConfiguration, ServerConfigurationEntries};
 use ferron_common::logging::ErrorLogger;
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
 #[cfg(feature = "runtime-vibeio")]
 use ferron_common::util::FileStream;
 #[cfg(feature = "runtime-monoio")]
 use ferron_common::util::MonoioFileStreamNoSpawn;
-use ferron_common::util::{anti_xss, parse_q_value_header, sizify, ModuleCache, TtlCache};
-use ferron_common::{format_page, get_entries, get_entries_for_validation, get_entry, get_value};
+use ferron_common::util::{
+  anti_xss, get_embedded_asset, get_embedded_directory, parse_q_value_header, sizify, ModuleCache, TtlCache,
+  EMBEDDED_ROOT_SCHEME,
+};
+use ferron_common::{format_page, get_entries, get_entries_for_validation, get_entry, get_value, get_values};
 
 const COMPRESSED_STREAM_READER_BUFFER_SIZE: usize = 16384;
 
+/// The default maximum total size of files in a directory archive
+const DEFAULT_DIRECTORY_ARCHIVE_MAX_SIZE: u64 = 1073741824;
+
+/// The magic number preceding the dictionary hash in dictionary-compressed Zstandard ("dcz") responses (RFC 9842)
+const DCZ_MAGIC_NUMBER: [u8; 8] = [0x5e, 0x2a, 0x4d, 0x18, 0x20, 0x00, 0x00, 0x00];
+
 /// A hard-coded list of non-compressible file extensions
 static NON_COMPRESSIBLE_FILE_EXTENSIONS: LazyLock<BTreeSet<&'static str>> = LazyLock::new(|| {
   BTreeSet::from_iter(vec![
@@ -182,9 +196,225 @@ enum Compression {
   Brotli,
   Deflate,
   Zstd,
+  DictionaryZstd,
   Identity,
 }
 
+/// An ETag generation algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EtagAlgorithm {
+  /// A hash of the file path, size and modification time
+  MtimeSize,
+  /// An XXH3 hash of the file contents
+  Xxhash,
+  /// A SHA-256 hash of the file contents
+  Sha256,
+}
+
+impl EtagAlgorithm {
+  /// Parses the ETag algorithm name
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "mtime-size" => Some(Self::MtimeSize),
+      "xxhash" => Some(Self::Xxhash),
+      "sha256" => Some(Self::Sha256),
+      _ => None,
+    }
+  }
+
+  /// Obtains the extension of checksum sidecar files, if the algorithm is based on file contents
+  fn sidecar_extension(&self) -> Option<&'static str> {
+    match self {
+      Self::MtimeSize => None,
+      Self::Xxhash => Some("xxh3"),
+      Self::Sha256 => Some("sha256"),
+    }
+  }
+
+  /// Calculates the checksum of file contents as a hexadecimal string
+  fn checksum(&self, data: &[u8]) -> String {
+    match self {
+      Self::Sha256 => Sha256::digest(data).iter().map(|byte| format!("{byte:02x}")).collect(),
+      _ => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(data)),
+    }
+  }
+
+  /// Checks whether a precomputed checksum is valid for the algorithm
+  fn is_valid_checksum(&self, checksum: &str) -> bool {
+    checksum.len()
+      == match self {
+        Self::Sha256 => 64,
+        _ => 16,
+      }
+      && checksum.bytes().all(|byte| byte.is_ascii_hexdigit())
+  }
+}
+
+/// Creates a checksum cache key based on the ETag algorithm, file path, size, and modification time
+fn checksum_cache_key(
+  algorithm: EtagAlgorithm,
+  path: &Path,
+  file_length: u64,
+  file_modified: std::io::Result<SystemTime>,
+) -> String {
+  format!(
+    "{}{}-{}-{}",
+    algorithm
+      .sidecar_extension()
+      .map_or("".to_string(), |ext| format!("{ext}:")),
+    path.to_string_lossy(),
+    file_length,
+    match file_modified {
+      Ok(mtime) => {
+        (match mtime.duration_since(SystemTime::UNIX_EPOCH) {
+          Ok(duration) => duration.as_secs() as i128,
+          Err(error) => -(error.duration().as_secs() as i128),
+        })
+        .to_string()
+      }
+      Err(_) => String::from(""),
+    }
+  )
+}
+
+/// Obtains the path of the checksum sidecar file (like `app.js.sha256`)
+fn checksum_sidecar_path(path: &Path, algorithm: EtagAlgorithm) -> Option<PathBuf> {
+  let mut sidecar_pathbuf = path.to_path_buf();
+  sidecar_pathbuf.set_extension(
+    format!(
+      "{}.{}",
+      path.extension().map_or(OsStr::new(""), |ext| ext).to_string_lossy(),
+      algorithm.sidecar_extension()?
+    )
+    .trim_matches('.'),
+  );
+  Some(sidecar_pathbuf)
+}
+
+/// Parses the contents of a checksum sidecar file
+fn parse_checksum_sidecar(sidecar: Vec<u8>, algorithm: EtagAlgorithm) -> Option<String> {
+  // The sidecar file can be in the format used by `sha256sum` and similar tools
+  let sidecar = String::from_utf8(sidecar).ok()?;
+  let checksum = sidecar.split_whitespace().next()?.to_ascii_lowercase();
+  algorithm.is_valid_checksum(&checksum).then_some(checksum)
+}
+
+/// Reads a precomputed checksum from a sidecar file (like `app.js.sha256`), if it's not older than the file itself
+async fn read_checksum_sidecar(
+  path: &Path,
+  algorithm: EtagAlgorithm,
+  file_modified: Option<SystemTime>,
+) -> Option<String> {
+  let sidecar_pathbuf = checksum_sidecar_path(path, algorithm)?;
+
+  // Monoio's `fs` doesn't expose `metadata()` on Windows, so we have to spawn a blocking task to obtain the metadata on this platform
+  #[cfg(any(
+    feature = "runtime-tokio",
+    all(feature = "runtime-monoio", unix),
+    feature = "runtime-vibeio"
+  ))]
+  let sidecar_metadata = fs::metadata(&sidecar_pathbuf).await.ok()?;
+  #[cfg(all(feature = "runtime-monoio", windows))]
+  let sidecar_metadata = {
+    let sidecar_pathbuf = sidecar_pathbuf.clone();
+    monoio::spawn_blocking(move || std::fs::metadata(sidecar_pathbuf))
+      .await
+      .ok()?
+      .ok()?
+  };
+  if let (Some(file_modified), Ok(sidecar_modified)) = (file_modified, sidecar_metadata.modified()) {
+    if sidecar_modified < file_modified {
+      // The sidecar file is stale
+      return None;
+    }
+  }
+
+  parse_checksum_sidecar(fs::read(sidecar_pathbuf).await.ok()?, algorithm)
+}
+
+/// Pre-reads a static file (specified with the `warmup_path` directive) into the OS page cache,
+/// and precomputes its ETag the same way as when the file is served. This function is blocking.
+/// Returns the ETag cache entry, if ETags are enabled.
+fn warm_up_static_file(
+  wwwroot: &Path,
+  request_path: &str,
+  etag_algorithm: Option<EtagAlgorithm>,
+  etag_precomputed: bool,
+) -> std::io::Result<Option<(String, String)>> {
+  let decoded_relative_path =
+    urlencoding::decode(request_path.trim_start_matches('/')).map_err(std::io::Error::other)?;
+  let joined_pathbuf = wwwroot.join(decoded_relative_path.as_ref());
+  let metadata = std::fs::metadata(&joined_pathbuf)?;
+  if !metadata.is_file() {
+    Err(std::io::Error::other("The path doesn't point to a file"))?
+  }
+  // Reading the file loads its contents into the OS page cache
+  let contents = std::fs::read(&joined_pathbuf)?;
+
+  let Some(etag_algorithm) = etag_algorithm else {
+    return Ok(None);
+  };
+  let etag_cache_key = checksum_cache_key(etag_algorithm, &joined_pathbuf, metadata.len(), metadata.modified());
+  let etag = match etag_algorithm {
+    EtagAlgorithm::MtimeSize => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(etag_cache_key.as_bytes())),
+    _ => {
+      let precomputed_checksum = checksum_sidecar_path(&joined_pathbuf, etag_algorithm)
+        .filter(|_| etag_precomputed)
+        .and_then(|sidecar_pathbuf| {
+          let sidecar_modified = std::fs::metadata(&sidecar_pathbuf).ok()?.modified();
+          if let (Ok(file_modified), Ok(sidecar_modified)) = (metadata.modified(), sidecar_modified) {
+            if sidecar_modified < file_modified {
+              // The sidecar file is stale
+              return None;
+            }
+          }
+          parse_checksum_sidecar(std::fs::read(sidecar_pathbuf).ok()?, etag_algorithm)
+        });
+      precomputed_checksum.unwrap_or_else(|| etag_algorithm.checksum(&contents))
+    }
+  };
+  Ok(Some((etag_cache_key, etag)))
+}
+
+/// Compression dictionaries, keyed by their SHA-256 hashes
+type CompressionDictionaries = HashMap<Vec<u8>, Arc<Vec<u8>>>;
+
+/// Loads the compression dictionaries specified in the server configuration
+fn load_compression_dictionaries(
+  config: &ServerConfiguration,
+) -> Result<CompressionDictionaries, Box<dyn Error + Send + Sync>> {
+  let mut dictionaries = HashMap::new();
+  if let Some(entries) = get_entries!("compression_dictionary", config) {
+    for dictionary_path in entries
+      .inner
+      .iter()
+      .flat_map(|e| e.values.iter().filter_map(|v| v.as_str()))
+    {
+      let dictionary = std::fs::read(dictionary_path)
+        .map_err(|err| anyhow::anyhow!("Can't read the compression dictionary \"{dictionary_path}\": {err}"))?;
+      dictionaries.insert(Sha256::digest(&dictionary).to_vec(), Arc::new(dictionary));
+    }
+  }
+  Ok(dictionaries)
+}
+
+/// Parses the HTTP "Available-Dictionary" header value (a structured field byte sequence) into a dictionary hash
+#[inline]
+fn parse_available_dictionary(value: &str) -> Option<Vec<u8>> {
+  let encoded_hash = value.trim().strip_prefix(':')?.strip_suffix(':')?;
+  base64::engine::general_purpose::STANDARD.decode(encoded_hash).ok()
+}
+
+/// Constructs the HTTP "Use-As-Dictionary" header value
+#[inline]
+fn construct_use_as_dictionary(match_pattern: &str, id: Option<&str>) -> String {
+  let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+  match id {
+    Some(id) => format!("match=\"{}\", id=\"{}\"", escape(match_pattern), escape(id)),
+    None => format!("match=\"{}\"", escape(match_pattern)),
+  }
+}
+
 /// Split an ETag request header into individual ETags.
 #[inline]
 fn split_etag_request_header(etag: &str) -> Vec<String> {
@@ -492,6 +722,318 @@ fn construct_etag(input: &str, weak: bool) -> String {
   }
 }
 
+/// Serves a static asset embedded into the binary
+fn serve_embedded_asset(
+  request: Request<BoxBody<Bytes, std::io::Error>>,
+  config: &ServerConfiguration,
+  embedded_name: &str,
+  indexes: &[&str],
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let Some(directory) = get_embedded_directory(embedded_name) else {
+    return Err(
+      anyhow::anyhow!("The \"{embedded_name}\" embedded directory doesn't exist in this build of Ferron").into(),
+    );
+  };
+
+  let request_path = request.uri().path();
+  let decoded_path = match urlencoding::decode(request_path) {
+    Ok(path) => path,
+    Err(_) => {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::BAD_REQUEST),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+  };
+
+  // Normalize the path, so that it doesn't escape the embedded directory
+  let mut path_segments = Vec::new();
+  for segment in decoded_path.split('/') {
+    match segment {
+      "" | "." => (),
+      ".." => {
+        path_segments.pop();
+      }
+      _ => path_segments.push(segment),
+    }
+  }
+  let normalized_path = format!("/{}", path_segments.join("/"));
+
+  let asset = if decoded_path.ends_with('/') {
+    // Try the index files for directories
+    indexes.iter().find_map(|index| {
+      let index_path = if normalized_path == "/" {
+        format!("/{index}")
+      } else {
+        format!("{normalized_path}/{index}")
+      };
+      get_embedded_asset(directory, &index_path).map(|asset| (index_path, asset))
+    })
+  } else {
+    match get_embedded_asset(directory, &normalized_path) {
+      Some(asset) => Some((normalized_path.clone(), asset)),
+      None => {
+        // Redirect to the URL with a trailing slash, if the path is an embedded directory with an index file
+        if indexes
+          .iter()
+          .any(|index| get_embedded_asset(directory, &format!("{normalized_path}/{index}")).is_some())
+        {
+          let location = match request.uri().query() {
+            Some(query) => format!("{request_path}/?{query}"),
+            None => format!("{request_path}/"),
+          };
+          return Ok(ResponseData {
+            request: Some(request),
+            response: Some(
+              Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, location)
+                .body(Empty::new().map_err(|e| match e {}).boxed())?,
+            ),
+            response_status: None,
+            response_headers: None,
+            new_remote_address: None,
+          });
+        }
+        None
+      }
+    }
+  };
+
+  let Some((asset_path, asset)) = asset else {
+    return Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: Some(StatusCode::NOT_FOUND),
+      response_headers: None,
+      new_remote_address: None,
+    });
+  };
+
+  let cache_control = get_value!("file_cache_control", config).and_then(|v| v.as_str());
+  let etag = format!("{:016x}", xxhash_rust::xxh3::xxh3_64(asset));
+
+  // Handle If-None-Match header for conditional requests
+  if let Some(if_none_match) = request
+    .headers()
+    .get(header::IF_NONE_MATCH)
+    .and_then(|v| v.to_str().ok())
+  {
+    if split_etag_request_header(if_none_match).iter().any(|if_none_match| {
+      extract_etag_inner(if_none_match, true).is_some_and(|(etag_extracted, _, _)| etag_extracted == etag)
+    }) {
+      let mut not_modified_response = Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(header::ETAG, construct_etag(&etag, false))
+        .body(Empty::new().map_err(|e| match e {}).boxed())?;
+      if let Some(cache_control) = cache_control {
+        not_modified_response
+          .headers_mut()
+          .insert(header::CACHE_CONTROL, HeaderValue::from_str(cache_control)?);
+      }
+      return Ok(ResponseData {
+        request: Some(request),
+        response: Some(not_modified_response),
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+  }
+
+  let asset_path = Path::new(&asset_path);
+  let custom_content_type_option = {
+    let mut custom_content_type = None;
+    if let Some(mime_types_entries) = get_entries!("mime_type", config) {
+      if let Some(extension) = asset_path.extension().map(|a| format!(".{}", a.to_string_lossy())) {
+        for entry in mime_types_entries.inner.iter() {
+          if let Some(key) = entry.values.first().and_then(|v| v.as_str()) {
+            if key == extension {
+              if let Some(value) = entry.values.get(1).and_then(|v| v.as_str()) {
+                custom_content_type = Some(value.to_string());
+                break;
+              }
+            }
+          }
+        }
+      }
+    }
+    custom_content_type
+  };
+  let content_type_option = custom_content_type_option.or_else(|| {
+    new_mime_guess::from_path(asset_path)
+      .first()
+      .map(|mime_type| mime_type.to_string())
+  });
+
+  let mut response_builder = Response::builder()
+    .header(header::CONTENT_LENGTH, asset.len())
+    .header(header::ETAG, construct_etag(&etag, false));
+  if let Some(content_type) = content_type_option {
+    response_builder = response_builder.header(header::CONTENT_TYPE, content_type);
+  }
+  if let Some(cache_control) = cache_control {
+    response_builder = response_builder.header(header::CACHE_CONTROL, cache_control);
+  }
+
+  let response = response_builder.body(
+    Full::new(if request.method() == Method::HEAD {
+      Bytes::new()
+    } else {
+      Bytes::from_static(asset)
+    })
+    .map_err(|e| match e {})
+    .boxed(),
+  )?;
+
+  Ok(ResponseData {
+    request: Some(request),
+    response: Some(response),
+    response_status: None,
+    response_headers: None,
+    new_remote_address: None,
+  })
+}
+
+/// Streams an archive of the directory, without creating temporary files
+async fn serve_directory_archive(
+  request: Request<BoxBody<Bytes, std::io::Error>>,
+  config: &ServerConfiguration,
+  directory: PathBuf,
+  archive_format: ArchiveFormat,
+  runtime: &tokio::runtime::Handle,
+  error_logger: &ErrorLogger,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let parse_patterns = |entries: Option<&ServerConfigurationEntries>| {
+    entries
+      .map_or(vec![].as_ref(), |e| &e.inner)
+      .iter()
+      .filter_map(|e| e.values.first().and_then(|v| v.as_str()))
+      .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+      .collect::<Vec<_>>()
+  };
+  let include = parse_patterns(get_entries!("directory_archive_include", config));
+  let exclude = parse_patterns(get_entries!("directory_archive_exclude", config));
+  let mut max_size = get_entry!("directory_archive_max_size", config)
+    .and_then(|e| e.values.first())
+    .map_or(Some(DEFAULT_DIRECTORY_ARCHIVE_MAX_SIZE), |v| {
+      if v.is_null() {
+        None
+      } else {
+        Some(v.as_size().unwrap_or(DEFAULT_DIRECTORY_ARCHIVE_MAX_SIZE))
+      }
+    });
+  if archive_format == ArchiveFormat::Zip {
+    max_size = Some(max_size.map_or(ZIP_ARCHIVE_MAX_SIZE, |v| v.min(ZIP_ARCHIVE_MAX_SIZE)));
+  }
+
+  let archive_name = directory
+    .file_name()
+    .map(|name| {
+      name
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+          if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            c
+          } else {
+            '_'
+          }
+        })
+        .collect::<String>()
+    })
+    .filter(|name| !name.is_empty())
+    .unwrap_or_else(|| "archive".to_string());
+
+  // Directory traversal is performed on the secondary runtime's blocking threads
+  let entries_directory = directory.clone();
+  let entries = match runtime
+    .spawn_blocking(move || collect_archive_entries(&entries_directory, &include, &exclude, max_size))
+    .await
+    .map_err(|_| anyhow::anyhow!("Can't spawn a task to read the directory"))?
+  {
+    Ok(Some(entries)) => entries,
+    Ok(None) => {
+      error_logger
+        .log(&format!(
+          "The archive of the \"{}\" directory would exceed the size or entry limit",
+          directory.display()
+        ))
+        .await;
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::FORBIDDEN),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+    Err(err) => match err.kind() {
+      std::io::ErrorKind::NotFound => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::NOT_FOUND),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+      std::io::ErrorKind::PermissionDenied => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::FORBIDDEN),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+      _ => Err(err)?,
+    },
+  };
+
+  let response_builder = Response::builder()
+    .status(StatusCode::OK)
+    .header(header::CONTENT_TYPE, archive_format.content_type())
+    .header(
+      header::CONTENT_DISPOSITION,
+      format!("attachment; filename=\"{archive_name}.{}\"", archive_format.extension()),
+    );
+
+  let response = if request.method() == Method::HEAD {
+    response_builder.body(Empty::new().map_err(|e| match e {}).boxed())?
+  } else {
+    // The archive is written on the secondary runtime's blocking threads, and sent in chunks through a bounded channel
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(4);
+    runtime.spawn_blocking(move || {
+      let mut writer = ChannelWriter::new(sender.clone());
+      if let Err(err) = write_archive(archive_format, &entries, &mut writer) {
+        if err.kind() != std::io::ErrorKind::BrokenPipe {
+          sender.blocking_send(Err(err)).unwrap_or_default();
+        }
+      }
+    });
+    response_builder.body(
+      StreamBody::new(futures_util::stream::poll_fn(move |cx| {
+        receiver
+          .poll_recv(cx)
+          .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+      }))
+      .boxed(),
+    )?
+  };
+
+  Ok(ResponseData {
+    request: Some(request),
+    response: Some(response),
+    response_status: None,
+    response_headers: None,
+    new_remote_address: None,
+  })
+}
+
 /// A static file serving module loader
 pub struct StaticFileServingModuleLoader {
   cache: ModuleCache<StaticFileServingModule>,
@@ -510,7 +1052,7 @@ impl StaticFileServingModuleLoader {
   /// Creates a new module loader
   pub fn new() -> Self {
     Self {
-      cache: ModuleCache::new(vec![]),
+      cache: ModuleCache::new(vec!["compression_dictionary"]),
       pathbuf_cache: Arc::new(RwLock::new(TtlCache::new(Duration::from_millis(100)))),
       path_traversal_check_cache: Arc::new(RwLock::new(TtlCache::new(Duration::from_millis(100)))),
       etag_cache: Arc::new(RwLock::new(LruCache::new(1000))),
@@ -523,16 +1065,73 @@ impl ModuleLoader for StaticFileServingModuleLoader {
     &mut self,
     config: &ServerConfiguration,
     _global_config: Option<&ServerConfiguration>,
-    _secondary_runtime: &tokio::runtime::Runtime,
+    secondary_runtime: &tokio::runtime::Runtime,
   ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
     Ok(
       self
         .cache
-        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |_| {
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          // The `warmup_path` directives specify the static files, which are pre-read into the OS page cache
+          // (with precomputed ETags) after the configuration is loaded
+          let warmup_paths = get_values!("warmup_path", config)
+            .into_iter()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect::<Vec<_>>();
+          if let Some(wwwroot) = get_value!("root", config)
+            .and_then(|v| v.as_str())
+            .filter(|wwwroot| !warmup_paths.is_empty() && !wwwroot.starts_with(EMBEDDED_ROOT_SCHEME))
+          {
+            let wwwroot = PathBuf::from(wwwroot);
+            let etag_algorithm = get_value!("etag", config)
+              .and_then(|v| v.as_bool())
+              .unwrap_or(true)
+              .then(|| {
+                get_value!("etag_algorithm", config)
+                  .and_then(|v| v.as_str())
+                  .and_then(EtagAlgorithm::from_name)
+                  .unwrap_or(EtagAlgorithm::MtimeSize)
+              });
+            let etag_precomputed = get_value!("etag_precomputed", config)
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false);
+            let etag_cache = self.etag_cache.clone();
+            let error_logger = ErrorLogger::new_multiple(config.observability.log_channels.clone());
+            secondary_runtime.spawn(async move {
+              let warmup_results = tokio::task::spawn_blocking(move || {
+                warmup_paths
+                  .into_iter()
+                  .map(|request_path| {
+                    let result = warm_up_static_file(&wwwroot, &request_path, etag_algorithm, etag_precomputed);
+                    (request_path, result)
+                  })
+                  .collect::<Vec<_>>()
+              })
+              .await
+              .unwrap_or_default();
+              for (request_path, result) in warmup_results {
+                match result {
+                  Ok(Some((etag_cache_key, etag))) => {
+                    etag_cache.write().await.insert(etag_cache_key, etag);
+                  }
+                  Ok(None) => (),
+                  Err(err) => {
+                    error_logger
+                      .log(&format!(
+                        "Failed to warm up the static file at \"{request_path}\": {err}"
+                      ))
+                      .await
+                  }
+                }
+              }
+            });
+          }
+
           Ok(Arc::new(StaticFileServingModule {
             pathbuf_cache: self.pathbuf_cache.clone(),
             path_traversal_check_cache: self.path_traversal_check_cache.clone(),
             etag_cache: self.etag_cache.clone(),
+            compression_dictionaries: Arc::new(load_compression_dictionaries(config)?),
+            runtime: secondary_runtime.handle().clone(),
           }))
         })?,
     )
@@ -559,6 +1158,18 @@ impl ModuleLoader for StaticFileServingModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("compression_dictionary", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `compression_dictionary` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The compression dictionary path must be a string"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("directory_listing", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -571,6 +1182,72 @@ impl ModuleLoader for StaticFileServingModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("directory_archive", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `directory_archive` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid directory archive downloads enabling option"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("directory_archive_max_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `directory_archive_max_size` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
+          Err(anyhow::anyhow!(
+            "Invalid maximum total size of files in a directory archive"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("directory_archive_include", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `directory_archive_include` configuration property must have exactly one value"
+          ))?
+        } else if let Some(pattern) = entry.values[0].as_str() {
+          if let Err(err) = glob::Pattern::new(pattern) {
+            Err(anyhow::anyhow!(
+              "Invalid directory archive inclusion pattern \"{pattern}\": {err}"
+            ))?
+          }
+        } else {
+          Err(anyhow::anyhow!(
+            "The directory archive inclusion pattern must be a string"
+          ))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("directory_archive_exclude", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `directory_archive_exclude` configuration property must have exactly one value"
+          ))?
+        } else if let Some(pattern) = entry.values[0].as_str() {
+          if let Err(err) = glob::Pattern::new(pattern) {
+            Err(anyhow::anyhow!(
+              "Invalid directory archive exclusion pattern \"{pattern}\": {err}"
+            ))?
+          }
+        } else {
+          Err(anyhow::anyhow!(
+            "The directory archive exclusion pattern must be a string"
+          ))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("etag", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -583,6 +1260,57 @@ impl ModuleLoader for StaticFileServingModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("etag_algorithm", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `etag_algorithm` configuration property must have exactly one value"
+          ))?
+        } else if entry.values[0]
+          .as_str()
+          .is_none_or(|v| EtagAlgorithm::from_name(v).is_none())
+        {
+          Err(anyhow::anyhow!("Invalid ETag algorithm"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("etag_precomputed", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `etag_precomputed` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid precomputed ETag checksums enabling option"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("warmup_path", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `warmup_path` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].as_str().is_some_and(|v| v.starts_with('/')) {
+          Err(anyhow::anyhow!("Invalid static file warmup path"))?
+        }
+      }
+    };
+
+    if let Some(entries) = get_entries_for_validation!("repr_digest", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `repr_digest` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid integrity digest enabling option"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("file_cache_control", config, used_properties) {
       for entry in &entries.inner {
         if entry.values.len() != 1 {
@@ -621,6 +1349,20 @@ impl ModuleLoader for StaticFileServingModuleLoader {
       }
     };
 
+    if let Some(entries) = get_entries_for_validation!("use_as_dictionary", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `use_as_dictionary` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!("Invalid dictionary URL pattern"))?
+        } else if !entry.props.get("id").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("Invalid dictionary ID"))?
+        }
+      }
+    };
+
     if let Some(entries) = get_entries_for_validation!("index", config, used_properties) {
       for entry in &entries.inner {
         if !entry.values.iter().all(|v| v.is_string()) {
@@ -638,6 +1380,8 @@ struct StaticFileServingModule {
   pathbuf_cache: Arc<RwLock<TtlCache<String, PathBuf>>>,
   path_traversal_check_cache: Arc<RwLock<TtlCache<PathBuf, bool>>>,
   etag_cache: Arc<RwLock<LruCache<String, String>>>,
+  compression_dictionaries: Arc<CompressionDictionaries>,
+  runtime: tokio::runtime::Handle,
 }
 
 impl Module for StaticFileServingModule {
@@ -646,6 +1390,8 @@ impl Module for StaticFileServingModule {
       pathbuf_cache: self.pathbuf_cache.clone(),
       path_traversal_check_cache: self.path_traversal_check_cache.clone(),
       etag_cache: self.etag_cache.clone(),
+      compression_dictionaries: self.compression_dictionaries.clone(),
+      runtime: self.runtime.clone(),
     })
   }
 }
@@ -655,6 +1401,8 @@ struct StaticFileServingModuleHandlers {
   pathbuf_cache: Arc<RwLock<TtlCache<String, PathBuf>>>,
   path_traversal_check_cache: Arc<RwLock<TtlCache<PathBuf, bool>>>,
   etag_cache: Arc<RwLock<LruCache<String, String>>>,
+  compression_dictionaries: Arc<CompressionDictionaries>,
+  runtime: tokio::runtime::Handle,
 }
 
 #[async_trait(?Send)]
@@ -672,7 +1420,7 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
     request: Request<BoxBody<Bytes, std::io::Error>>,
     config: &ServerConfiguration,
     _socket_data: &SocketData,
-    _error_logger: &ErrorLogger,
+    error_logger: &ErrorLogger,
   ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
     // Handle different HTTP methods
     match request.method() {
@@ -739,6 +1487,11 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
         .map(|e| e.values.iter().filter_map(|v| v.as_str()).collect::<Vec<&str>>())
         .unwrap_or(vec!["index.html", "index.htm", "index.xhtml"]);
 
+      // Serve the static assets embedded into the binary
+      if let Some(embedded_name) = wwwroot.strip_prefix(EMBEDDED_ROOT_SCHEME) {
+        return serve_embedded_asset(request, config, embedded_name, &indexes);
+      }
+
       // Create a cache key that includes IP and hostname filters if present
       let cache_key = format!(
         "{}{}{}",
@@ -984,24 +1737,16 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
 
             // Generate and handle ETags for caching
             let mut etag_option = None;
+            // ETags based on file contents are strong, since they don't depend on the server instance
+            let etag_algorithm = get_value!("etag_algorithm", config)
+              .and_then(|v| v.as_str())
+              .and_then(EtagAlgorithm::from_name)
+              .unwrap_or(EtagAlgorithm::MtimeSize);
+            let etag_strong = etag_algorithm != EtagAlgorithm::MtimeSize;
             // Check if ETags are enabled in config (defaults to true)
             if get_value!("etag", config).and_then(|v| v.as_bool()).unwrap_or(true) {
-              // Create ETag cache key based on file path, size, and modification time
-              let etag_cache_key = format!(
-                "{}-{}-{}",
-                joined_pathbuf.to_string_lossy(),
-                metadata.len(),
-                match metadata.modified() {
-                  Ok(mtime) => {
-                    (match mtime.duration_since(SystemTime::UNIX_EPOCH) {
-                      Ok(duration) => duration.as_secs() as i128,
-                      Err(error) => -(error.duration().as_secs() as i128),
-                    })
-                    .to_string()
-                  }
-                  Err(_) => String::from(""),
-                }
-              );
+              let etag_cache_key =
+                checksum_cache_key(etag_algorithm, &joined_pathbuf, metadata.len(), metadata.modified());
               let rwlock_read = self.etag_cache.read().await;
               // Had to use "peek", since "get" would mutate the LRU cache
               let etag_locked_option = rwlock_read.peek(&etag_cache_key).cloned();
@@ -1009,7 +1754,26 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
               let etag = match etag_locked_option {
                 Some(etag) => etag,
                 None => {
-                  let etag = format!("{:016x}", xxhash_rust::xxh3::xxh3_64(etag_cache_key.as_bytes()));
+                  let etag = match etag_algorithm {
+                    EtagAlgorithm::MtimeSize => {
+                      format!("{:016x}", xxhash_rust::xxh3::xxh3_64(etag_cache_key.as_bytes()))
+                    }
+                    _ => {
+                      // Prefer precomputed checksums (for example, generated by `ferron-precompress`)
+                      let precomputed_checksum = if get_value!("etag_precomputed", config)
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false)
+                      {
+                        read_checksum_sidecar(&joined_pathbuf, etag_algorithm, metadata.modified().ok()).await
+                      } else {
+                        None
+                      };
+                      match precomputed_checksum {
+                        Some(checksum) => checksum,
+                        None => etag_algorithm.checksum(&fs::read(&joined_pathbuf).await?),
+                      }
+                    }
+                  };
 
                   let mut rwlock_write = self.etag_cache.write().await;
                   rwlock_write.insert(etag_cache_key, etag.clone());
@@ -1021,7 +1785,9 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
 
               // Set Vary header based on available features
               // Include Accept-Encoding if compression is possible
-              vary = if compression_possible {
+              vary = if compression_possible && !self.compression_dictionaries.is_empty() {
+                "Accept-Encoding, Available-Dictionary, If-Match, If-None-Match, Range"
+              } else if compression_possible {
                 "Accept-Encoding, If-Match, If-None-Match, Range"
               } else {
                 "If-Match, If-None-Match, Range"
@@ -1050,21 +1816,23 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                         // Client's cached version matches our current version
                         if etag_extracted == etag {
                           let mut etag_new_inner = String::new();
+                          let mut etag_new_weak = !etag_strong;
                           etag_new_inner.push_str(&etag);
                           if let Some(suffix) = suffix_option {
                             match &*suffix {
                               // These suffixes are supported by Ferron
-                              "gzip" | "deflate" | "br" | "zstd" => {
+                              "gzip" | "deflate" | "br" | "zstd" | "dcz" => {
                                 etag_new_inner.push('-');
                                 etag_new_inner.push_str(&suffix);
+                                etag_new_weak = true;
                               }
                               _ => {}
                             }
                           }
-                          // Ferron's static file serving functionality would also emit weak ETags,
-                          // so for RFC 7232 compliance, weak ETags are sent in 304 responses as well.
-                          // Therefore, we construct a weak ETag here.
-                          let constructed_etag = construct_etag(&etag_new_inner, true);
+                          // Ferron's static file serving functionality would emit weak ETags for compressed responses
+                          // and for ETags not based on file contents, so for RFC 7232 compliance,
+                          // the same kind of ETags are sent in 304 responses as well.
+                          let constructed_etag = construct_etag(&etag_new_inner, etag_new_weak);
                           let mut not_modified_response = Response::builder()
                             .status(StatusCode::NOT_MODIFIED)
                             .header(header::ETAG, &constructed_etag)
@@ -1119,11 +1887,11 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                     }
 
                     // "*" means any version is acceptable
-                    // Ferron only emits weak ETags, and comparing a strong ETag with it would not match
+                    // Weak ETags (emitted when ETags aren't based on file contents) would not match
                     // for strong comparsions, for more details see RFC 7232
                     if !split_etag_request_header(if_match)
                       .into_iter()
-                      .any(|if_match| if_match == "*")
+                      .any(|if_match| if_match == "*" || (etag_strong && if_match == etag))
                     {
                       let mut header_map = HeaderMap::new();
                       header_map.insert(header::VARY, HeaderValue::from_static(vary));
@@ -1151,13 +1919,57 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
               }
               etag_option = Some(etag);
             } else {
-              vary = if compression_possible {
+              vary = if compression_possible && !self.compression_dictionaries.is_empty() {
+                "Accept-Encoding, Available-Dictionary, Range"
+              } else if compression_possible {
                 "Accept-Encoding, Range"
               } else {
                 "Range"
               };
             }
 
+            // Generate the integrity digest (RFC 9530) of the file contents.
+            // The SHA-256 hash is cached along with the SHA-256 ETags.
+            let mut repr_digest_option = None;
+            if get_value!("repr_digest", config)
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false)
+            {
+              let checksum_cache_key = checksum_cache_key(
+                EtagAlgorithm::Sha256,
+                &joined_pathbuf,
+                metadata.len(),
+                metadata.modified(),
+              );
+              let rwlock_read = self.etag_cache.read().await;
+              let checksum_option = rwlock_read.peek(&checksum_cache_key).cloned();
+              drop(rwlock_read);
+              let checksum = match checksum_option {
+                Some(checksum) => checksum,
+                None => {
+                  let precomputed_checksum = if get_value!("etag_precomputed", config)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+                  {
+                    read_checksum_sidecar(&joined_pathbuf, EtagAlgorithm::Sha256, metadata.modified().ok()).await
+                  } else {
+                    None
+                  };
+                  let checksum = match precomputed_checksum {
+                    Some(checksum) => checksum,
+                    None => EtagAlgorithm::Sha256.checksum(&fs::read(&joined_pathbuf).await?),
+                  };
+
+                  let mut rwlock_write = self.etag_cache.write().await;
+                  rwlock_write.insert(checksum_cache_key, checksum.clone());
+                  drop(rwlock_write);
+
+                  checksum
+                }
+              };
+              repr_digest_option = format_integrity_digest(&checksum);
+            }
+
             let custom_content_type_option = {
               let mut custom_content_type = None;
               if let Some(mime_types_entries) = get_entries!("mime_type", config) {
@@ -1257,7 +2069,12 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                   );
 
                 if let Some(etag) = etag_option {
-                  response_builder = response_builder.header(header::ETAG, format!("W/\"{etag}\""));
+                  response_builder = response_builder.header(header::ETAG, construct_etag(&etag, !etag_strong));
+                }
+
+                // The representation digest covers the whole file, not only the requested range
+                if let Some(repr_digest) = repr_digest_option {
+                  response_builder = response_builder.header("Repr-Digest", repr_digest);
                 }
 
                 if let Some(content_type) = content_type_option {
@@ -1353,6 +2170,7 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
               // Initialize compression flags
               let mut precompressed_extensions = Vec::new();
               let mut used_compression = Compression::Identity;
+              let mut used_dictionary = None;
 
               // Determine the appropriate compression algorithm based on Accept-Encoding
               if compression_possible {
@@ -1391,9 +2209,28 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                     .get(header::ACCEPT_ENCODING)
                     .map(|header_value| header_value.to_str().unwrap_or_default())
                   {
+                    // Prefer delta compression against a compression dictionary available to the client
+                    let accepted_encodings = parse_q_value_header(accept_encoding);
+                    if accepted_encodings.iter().any(|e| e == "dcz") {
+                      used_dictionary = request
+                        .headers()
+                        .get("Available-Dictionary")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_available_dictionary)
+                        .and_then(|hash| {
+                          self
+                            .compression_dictionaries
+                            .get(&hash)
+                            .map(|dictionary| (hash, dictionary.clone()))
+                        });
+                      if used_dictionary.is_some() {
+                        used_compression = Compression::DictionaryZstd;
+                      }
+                    }
+
                     // Parse Accept-Encoding header to select the best compression method
                     // Check for supported compression algorithms in order of preference
-                    for accepted_encoding in parse_q_value_header(accept_encoding) {
+                    for accepted_encoding in accepted_encodings.into_iter().filter(|_| used_dictionary.is_none()) {
                       match &*accepted_encoding {
                         "br" => {
                           if enable_precompression {
@@ -1518,15 +2355,36 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                   match used_compression {
                     Compression::Brotli => format!("W/\"{etag}-br\""),
                     Compression::Zstd => format!("W/\"{etag}-zstd\""),
+                    Compression::DictionaryZstd => format!("W/\"{etag}-dcz\""),
                     Compression::Deflate => format!("W/\"{etag}-deflate\""),
                     Compression::Gzip => format!("W/\"{etag}-gzip\""),
-                    _ => format!("W/\"{etag}\""),
+                    _ => construct_etag(&etag, !etag_strong),
                   },
                 );
               }
 
               response_builder = response_builder.header(header::VARY, vary);
 
+              // The digest is computed from the uncompressed file, so it's sent only for uncompressed responses
+              if let Some(repr_digest) = repr_digest_option.filter(|_| used_compression == Compression::Identity) {
+                response_builder = response_builder
+                  .header("Repr-Digest", &repr_digest)
+                  .header("Content-Digest", repr_digest);
+              }
+
+              // Allow the response to be used as a compression dictionary for future requests
+              if let Some(use_as_dictionary_entry) = get_entry!("use_as_dictionary", config) {
+                if let Some(match_pattern) = use_as_dictionary_entry.values.first().and_then(|v| v.as_str()) {
+                  response_builder = response_builder.header(
+                    "Use-As-Dictionary",
+                    construct_use_as_dictionary(
+                      match_pattern,
+                      use_as_dictionary_entry.props.get("id").and_then(|v| v.as_str()),
+                    ),
+                  );
+                }
+              }
+
               if let Some(content_type) = content_type_option {
                 response_builder = response_builder.header(header::CONTENT_TYPE, content_type);
               }
@@ -1544,6 +2402,9 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                   response_builder =
                     response_builder.header(header::CONTENT_ENCODING, HeaderValue::from_static("zstd"));
                 }
+                Compression::DictionaryZstd => {
+                  response_builder = response_builder.header(header::CONTENT_ENCODING, HeaderValue::from_static("dcz"));
+                }
                 Compression::Deflate => {
                   response_builder =
                     response_builder.header(header::CONTENT_ENCODING, HeaderValue::from_static("deflate"));
@@ -1644,6 +2505,26 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
                       let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data));
                       stream_body.boxed()
                     }
+                    (_, Compression::DictionaryZstd) => {
+                      // Wrap the stream as a `AsyncRead`
+                      let file_bufreader = StreamReader::new(file_stream);
+
+                      // The dictionary-compressed response starts with the magic number and the dictionary hash
+                      let (dictionary_hash, dictionary) = used_dictionary.unwrap_or_default();
+                      let mut dcz_header = Vec::with_capacity(DCZ_MAGIC_NUMBER.len() + dictionary_hash.len());
+                      dcz_header.extend_from_slice(&DCZ_MAGIC_NUMBER);
+                      dcz_header.extend_from_slice(&dictionary_hash);
+
+                      let reader_stream = ReaderStream::with_capacity(
+                        tokio::io::AsyncReadExt::chain(
+                          std::io::Cursor::new(dcz_header),
+                          ZstdEncoder::with_dict(file_bufreader, Level::Default, &dictionary)?,
+                        ),
+                        COMPRESSED_STREAM_READER_BUFFER_SIZE,
+                      );
+                      let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data));
+                      stream_body.boxed()
+                    }
                     (false, Compression::Deflate) => {
                       // Wrap the stream as a `AsyncRead`
                       let file_bufreader = StreamReader::new(file_stream);
@@ -1705,6 +2586,33 @@ impl ModuleHandlers for StaticFileServingModuleHandlers {
               .and_then(|v| v.as_bool())
               .unwrap_or(false)
             {
+              // Stream an archive of the directory, if requested and enabled
+              let archive_format = request
+                .uri()
+                .query()
+                .and_then(|query| {
+                  query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("download="))
+                    .and_then(ArchiveFormat::from_name)
+                })
+                .filter(|_| {
+                  get_value!("directory_archive", config)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+                });
+              if let Some(archive_format) = archive_format {
+                return serve_directory_archive(
+                  request,
+                  config,
+                  joined_pathbuf,
+                  archive_format,
+                  &self.runtime,
+                  error_logger,
+                )
+                .await;
+              }
+
               // Look for a description file in the directory
               let joined_maindesc_pathbuf = joined_pathbuf.join(".maindesc");
               // Read the directory contents (using blocking task on Windows and with Monoio)
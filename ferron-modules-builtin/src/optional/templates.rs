@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::{header, Method, Request, Response, StatusCode};
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
+use ferron_common::util::{replace_header_placeholders, ModuleCache};
+use ferron_common::{get_entries, get_entries_for_validation, get_value};
+
+/// The file extension of templates
+const TEMPLATE_EXTENSION: &str = "tera";
+
+/// The minimum interval between checks, whether the templates were modified
+const TEMPLATE_RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The placeholders, which values are available in templates as `request.<placeholder name>`
+const TEMPLATE_PLACEHOLDERS: [&str; 11] = [
+  "path",
+  "path_and_query",
+  "method",
+  "version",
+  "scheme",
+  "client_ip",
+  "client_port",
+  "client_ip_canonical",
+  "server_ip",
+  "server_port",
+  "server_ip_canonical",
+];
+
+/// A set of compiled templates from the webroot
+struct TemplateSet {
+  tera: tera::Tera,
+  loaded: SystemTime,
+  last_reload_check: Instant,
+}
+
+impl TemplateSet {
+  /// Compiles the templates from the webroot
+  fn load(root: &Path) -> Result<Self, tera::Error> {
+    let loaded = SystemTime::now();
+    let mut tera = tera::Tera::new(&format!("{}/**/*.{TEMPLATE_EXTENSION}", root.to_string_lossy()))?;
+    tera.autoescape_on(vec![".html.tera", ".htm.tera", ".xhtml.tera", ".xml.tera"]);
+    Ok(Self {
+      tera,
+      loaded,
+      last_reload_check: Instant::now(),
+    })
+  }
+
+  /// Determines if the templates need to be recompiled, because any of them were modified, or the template isn't compiled yet
+  fn needs_reload(&self, template_name: &str) -> bool {
+    self.tera.get_template(template_name).is_err()
+      || self.tera.get_template_names().any(|name| {
+        self
+          .tera
+          .get_template(name)
+          .ok()
+          .and_then(|template| template.path.as_ref())
+          .and_then(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+          .is_none_or(|modified| modified >= self.loaded)
+      })
+  }
+
+  /// Recompiles the templates, if they were modified since the last check
+  fn reload_if_modified(&mut self, template_name: &str) -> Result<(), tera::Error> {
+    if self.last_reload_check.elapsed() < TEMPLATE_RELOAD_CHECK_INTERVAL
+      && self.tera.get_template(template_name).is_ok()
+    {
+      return Ok(());
+    }
+    self.last_reload_check = Instant::now();
+    if self.needs_reload(template_name) {
+      let loaded = SystemTime::now();
+      self.tera.full_reload()?;
+      self.loaded = loaded;
+    }
+    Ok(())
+  }
+}
+
+/// Resolves the path of the template for the request path. Returns `None` if there is no template for the request.
+fn resolve_template_path(root: &Path, request_path: &str) -> Option<PathBuf> {
+  let decoded_path = urlencoding::decode(request_path.trim_start_matches('/')).ok()?;
+  let mut path = root.join(decoded_path.as_ref());
+  if request_path.ends_with('/') {
+    path.push("index.html");
+  }
+  let mut template_path = path.into_os_string();
+  template_path.push(".");
+  template_path.push(TEMPLATE_EXTENSION);
+  let template_path = PathBuf::from(template_path);
+
+  // Prevent path traversal
+  let canonical_path = template_path.canonicalize().ok()?;
+  if !canonical_path.starts_with(root.canonicalize().ok()?) || !canonical_path.is_file() {
+    return None;
+  }
+  Some(canonical_path)
+}
+
+/// Obtains the name of the template (the path relative to the webroot with forward slashes)
+fn template_name(root: &Path, template_path: &Path) -> Option<String> {
+  let relative_path = template_path.strip_prefix(root.canonicalize().ok()?).ok()?;
+  Some(
+    relative_path
+      .components()
+      .map(|component| component.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/"),
+  )
+}
+
+/// Determines the content type of the rendered template from the extension preceding the template extension
+fn rendered_content_type(template_name: &str) -> &'static str {
+  let extension = template_name
+    .strip_suffix(TEMPLATE_EXTENSION)
+    .and_then(|name| name.strip_suffix('.'))
+    .and_then(|name| name.rsplit_once('.'))
+    .map(|(_, extension)| extension.to_ascii_lowercase());
+  match extension.as_deref() {
+    Some("txt") => "text/plain; charset=utf-8",
+    Some("css") => "text/css; charset=utf-8",
+    Some("js") => "text/javascript; charset=utf-8",
+    Some("json") => "application/json",
+    Some("xml") => "application/xml",
+    Some("svg") => "image/svg+xml",
+    Some("xhtml") => "application/xhtml+xml",
+    _ => "text/html; charset=utf-8",
+  }
+}
+
+/// Loads the JSON data files specified in the `template_data` configuration entries
+fn load_template_data(config: &ServerConfiguration) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+  let mut data = serde_json::Map::new();
+  if let Some(entries) = get_entries!("template_data", config) {
+    for entry in &entries.inner {
+      let (Some(name), Some(path)) = (
+        entry.values.first().and_then(|v| v.as_str()),
+        entry.values.get(1).and_then(|v| v.as_str()),
+      ) else {
+        continue;
+      };
+      let contents =
+        std::fs::read(path).map_err(|e| anyhow::anyhow!("Can't read the template data file \"{path}\": {e}"))?;
+      let value = serde_json::from_slice::<serde_json::Value>(&contents)
+        .map_err(|e| anyhow::anyhow!("Can't parse the template data file \"{path}\": {e}"))?;
+      data.insert(name.to_string(), value);
+    }
+  }
+  Ok(serde_json::Value::Object(data))
+}
+
+/// A templates module loader
+pub struct TemplatesModuleLoader {
+  cache: ModuleCache<TemplatesModule>,
+}
+
+impl Default for TemplatesModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TemplatesModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["root", "template_data"]),
+    }
+  }
+}
+
+impl ModuleLoader for TemplatesModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let root = get_value!("root", config).and_then(|v| v.as_str()).map(PathBuf::from);
+          let templates = match &root {
+            Some(root) => Some(Arc::new(RwLock::new(TemplateSet::load(root).map_err(|e| {
+              anyhow::anyhow!(
+                "Can't load the templates from \"{}\": {}",
+                root.display(),
+                e.source()
+                  .map_or_else(|| e.to_string(), |source| format!("{e}: {source}"))
+              )
+            })?))),
+            None => None,
+          };
+          Ok(Arc::new(TemplatesModule {
+            root,
+            templates,
+            data: Arc::new(load_template_data(config)?),
+            runtime: secondary_runtime.handle().clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["templates"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("templates", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `templates` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid template rendering enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("template_data", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 2 {
+          Err(anyhow::anyhow!(
+            "The `template_data` configuration property must have exactly two values"
+          ))?
+        } else if !entry.values[0].is_string() {
+          Err(anyhow::anyhow!("The template data name must be a string"))?
+        } else if !entry.values[1].is_string() {
+          Err(anyhow::anyhow!("The path to the template data file must be a string"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A templates module
+struct TemplatesModule {
+  root: Option<PathBuf>,
+  templates: Option<Arc<RwLock<TemplateSet>>>,
+  data: Arc<serde_json::Value>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Module for TemplatesModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(TemplatesModuleHandlers {
+      root: self.root.clone(),
+      templates: self.templates.clone(),
+      data: self.data.clone(),
+      runtime: self.runtime.clone(),
+    })
+  }
+}
+
+/// Handlers for the templates module
+struct TemplatesModuleHandlers {
+  root: Option<PathBuf>,
+  templates: Option<Arc<RwLock<TemplateSet>>>,
+  data: Arc<serde_json::Value>,
+  runtime: tokio::runtime::Handle,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for TemplatesModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let Some((root, templates)) = self.root.clone().zip(self.templates.clone()).filter(|_| {
+      get_value!("templates", config)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        && matches!(request.method(), &Method::GET | &Method::HEAD)
+    }) else {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: None,
+        response_headers: None,
+        new_remote_address: None,
+      });
+    };
+
+    // The template sources aren't served directly
+    if request
+      .uri()
+      .path()
+      .rsplit_once('.')
+      .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case(TEMPLATE_EXTENSION))
+    {
+      return Ok(ResponseData {
+        request: Some(request),
+        response: None,
+        response_status: Some(StatusCode::NOT_FOUND),
+        response_headers: None,
+        new_remote_address: None,
+      });
+    }
+
+    let (request_parts, request_body) = request.into_parts();
+    let mut request_context = TEMPLATE_PLACEHOLDERS
+      .iter()
+      .map(|placeholder| {
+        (
+          placeholder.to_string(),
+          serde_json::Value::String(replace_header_placeholders(
+            &format!("{{{placeholder}}}"),
+            &request_parts,
+            Some(socket_data),
+          )),
+        )
+      })
+      .collect::<serde_json::Map<_, _>>();
+    request_context.insert(
+      "query".to_string(),
+      request_parts.uri.query().unwrap_or_default().into(),
+    );
+    let mut headers = BTreeMap::new();
+    for (name, value) in &request_parts.headers {
+      if let Ok(value) = value.to_str() {
+        headers.entry(name.to_string()).or_insert_with(|| value.to_string());
+      }
+    }
+    request_context.insert("headers".to_string(), serde_json::to_value(headers)?);
+    request_context.insert(
+      "auth_user".to_string(),
+      request_parts
+        .extensions
+        .get::<RequestData>()
+        .and_then(|data| data.auth_user.clone())
+        .into(),
+    );
+    let request = Request::from_parts(request_parts, request_body);
+
+    let request_path = request.uri().path().to_string();
+    let data = self.data.clone();
+
+    // File system operations and rendering are performed on the secondary runtime's blocking threads
+    let rendered = self
+      .runtime
+      .spawn_blocking(move || -> Result<Option<(String, String)>, tera::Error> {
+        let Some(name) =
+          resolve_template_path(&root, &request_path).and_then(|template_path| template_name(&root, &template_path))
+        else {
+          return Ok(None);
+        };
+        templates
+          .write()
+          .unwrap_or_else(|e| e.into_inner())
+          .reload_if_modified(&name)?;
+        let mut context = tera::Context::new();
+        context.insert("request", &request_context);
+        context.insert("data", data.as_ref());
+        let rendered = templates
+          .read()
+          .unwrap_or_else(|e| e.into_inner())
+          .tera
+          .render(&name, &context)?;
+        Ok(Some((name, rendered)))
+      })
+      .await
+      .map_err(|_| anyhow::anyhow!("Can't spawn a task to render the template"))?;
+
+    let (name, rendered) = match rendered {
+      Ok(Some(rendered)) => rendered,
+      Ok(None) => {
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: None,
+          response_headers: None,
+          new_remote_address: None,
+        })
+      }
+      Err(err) => {
+        error_logger
+          .log(&format!(
+            "Can't render the template: {}",
+            err
+              .source()
+              .map_or_else(|| err.to_string(), |source| format!("{err}: {source}"))
+          ))
+          .await;
+        return Ok(ResponseData {
+          request: Some(request),
+          response: None,
+          response_status: Some(StatusCode::INTERNAL_SERVER_ERROR),
+          response_headers: None,
+          new_remote_address: None,
+        });
+      }
+    };
+
+    let response = Response::builder()
+      .header(header::CONTENT_TYPE, rendered_content_type(&name))
+      .header(header::CONTENT_LENGTH, rendered.len())
+      .body(
+        Full::new(if request.method() == Method::HEAD {
+          Bytes::new()
+        } else {
+          Bytes::from(rendered)
+        })
+        .map_err(|e| match e {})
+        .boxed(),
+      )?;
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: Some(response),
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+}
@@ -0,0 +1,349 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::BodyExt;
+use hyper::{header, Request, Response, StatusCode};
+use sha2::{Digest, Sha256};
+
+use crate::util::{BodyTransformFn, BufferedTransformBody, HtmlInjector};
+use ferron_common::config::{ServerConfiguration, ServerConfigurationEntries};
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::util::{is_streaming_response, ModuleCache};
+use ferron_common::{get_entries, get_entries_for_validation, get_entry, get_value};
+
+/// The default maximum size of a response body to be minified
+const DEFAULT_MINIFY_MAX_SIZE: u64 = 2097152;
+
+/// The maximum total size of cached minified response bodies
+const MINIFIED_CACHE_MAX_WEIGHT: u64 = 67108864;
+
+/// The estimated number of cached minified response bodies
+const MINIFIED_CACHE_ESTIMATED_ENTRIES: usize = 1024;
+
+/// A type of the minified content
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum MinifyKind {
+  Html,
+  Css,
+  JavaScript,
+}
+
+impl MinifyKind {
+  /// Determines the type of the minified content from the MIME type
+  fn from_mime_type(mime_type: &str) -> Option<Self> {
+    match mime_type {
+      "text/html" => Some(Self::Html),
+      "text/css" => Some(Self::Css),
+      "text/javascript" | "application/javascript" | "application/x-javascript" => Some(Self::JavaScript),
+      _ => None,
+    }
+  }
+
+  /// Minifies the content. Returns `None` if the content can't be minified.
+  fn minify(&self, data: &[u8]) -> Option<Vec<u8>> {
+    match self {
+      Self::Html => Some(minify_html::minify(
+        data,
+        &minify_html::Cfg {
+          minify_css: true,
+          minify_js: true,
+          ..Default::default()
+        },
+      )),
+      Self::Css => minifier::css::minify(std::str::from_utf8(data).ok()?)
+        .ok()
+        .map(|minified| minified.to_string().into_bytes()),
+      Self::JavaScript => Some(
+        minifier::js::minify(std::str::from_utf8(data).ok()?)
+          .to_string()
+          .into_bytes(),
+      ),
+    }
+  }
+}
+
+/// A weighter for the cache of minified response bodies, based on the sizes of the bodies
+#[derive(Clone)]
+struct MinifiedWeighter;
+
+impl quick_cache::Weighter<(MinifyKind, [u8; 32]), Bytes> for MinifiedWeighter {
+  fn weight(&self, _key: &(MinifyKind, [u8; 32]), val: &Bytes) -> u64 {
+    val.len() as u64 + 1
+  }
+}
+
+/// A cache of minified response bodies, keyed by the type and the SHA-256 hash of the original content
+type MinifiedCache = quick_cache::sync::Cache<(MinifyKind, [u8; 32]), Bytes, MinifiedWeighter>;
+
+/// A response transformation module loader
+pub struct ResponseTransformModuleLoader {
+  cache: ModuleCache<ResponseTransformModule>,
+  minified_cache: Arc<MinifiedCache>,
+}
+
+impl Default for ResponseTransformModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ResponseTransformModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["inject_head", "inject_body"]),
+      minified_cache: Arc::new(MinifiedCache::with_weighter(
+        MINIFIED_CACHE_ESTIMATED_ENTRIES,
+        MINIFIED_CACHE_MAX_WEIGHT,
+        MinifiedWeighter,
+      )),
+    }
+  }
+}
+
+impl ModuleLoader for ResponseTransformModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    _secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, |config| {
+          let concatenate_snippets = |entries: Option<&ServerConfigurationEntries>| {
+            entries
+              .map_or(vec![].as_ref(), |e| &e.inner)
+              .iter()
+              .filter_map(|e| e.values.first().and_then(|v| v.as_str()))
+              .collect::<String>()
+          };
+          let head_snippet = concatenate_snippets(get_entries!("inject_head", config));
+          let body_snippet = concatenate_snippets(get_entries!("inject_body", config));
+          let injector = if head_snippet.is_empty() && body_snippet.is_empty() {
+            None
+          } else {
+            Some(Arc::new(HtmlInjector::new(
+              head_snippet.as_bytes(),
+              body_snippet.as_bytes(),
+            )))
+          };
+          Ok(Arc::new(ResponseTransformModule {
+            injector,
+            minified_cache: self.minified_cache.clone(),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["inject_head", "inject_body", "minify"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("inject_head", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `inject_head` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!(
+            "The snippet injected before `</head>` must be a string"
+          ))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("inject_body", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `inject_body` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_string() && !entry.values[0].is_null() {
+          Err(anyhow::anyhow!(
+            "The snippet injected before `</body>` must be a string"
+          ))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("minify", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `minify` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_bool() {
+          Err(anyhow::anyhow!("Invalid response minification enabling option"))?
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("minify_max_size", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `minify_max_size` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
+          Err(anyhow::anyhow!(
+            "Invalid maximum size of a response body to be minified"
+          ))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A response transformation module
+struct ResponseTransformModule {
+  injector: Option<Arc<HtmlInjector>>,
+  minified_cache: Arc<MinifiedCache>,
+}
+
+impl Module for ResponseTransformModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(ResponseTransformModuleHandlers {
+      injector: self.injector.clone(),
+      minified_cache: self.minified_cache.clone(),
+      minify: false,
+      minify_max_size: Some(DEFAULT_MINIFY_MAX_SIZE),
+    })
+  }
+}
+
+/// Handlers for the response transformation module
+struct ResponseTransformModuleHandlers {
+  injector: Option<Arc<HtmlInjector>>,
+  minified_cache: Arc<MinifiedCache>,
+  minify: bool,
+  minify_max_size: Option<u64>,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for ResponseTransformModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    config: &ServerConfiguration,
+    _socket_data: &SocketData,
+    _error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    self.minify = get_value!("minify", config).and_then(|v| v.as_bool()).unwrap_or(false);
+    self.minify_max_size = get_entry!("minify_max_size", config)
+      .and_then(|e| e.values.first())
+      .map_or(Some(DEFAULT_MINIFY_MAX_SIZE), |v| {
+        if v.is_null() {
+          None
+        } else {
+          Some(v.as_size().unwrap_or(DEFAULT_MINIFY_MAX_SIZE))
+        }
+      });
+
+    Ok(ResponseData {
+      request: Some(request),
+      response: None,
+      response_status: None,
+      response_headers: None,
+      new_remote_address: None,
+    })
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    if response.headers().contains_key(header::CONTENT_ENCODING) // Don't corrupt compressed data
+      || is_streaming_response(&response)
+      || matches!(
+        response.status(),
+        StatusCode::PARTIAL_CONTENT | StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED
+      )
+    {
+      return Ok(response);
+    }
+
+    let response_mime_type = response
+      .headers()
+      .get(header::CONTENT_TYPE)
+      .and_then(|h| h.to_str().ok())
+      .map(|t| {
+        t.split_once(';')
+          .map_or(t, |(mime_type, _)| mime_type)
+          .trim()
+          .to_ascii_lowercase()
+      });
+    let is_html = response_mime_type.as_deref() == Some("text/html");
+    let injector = self.injector.as_ref().filter(|_| is_html);
+    let minify_kind = response_mime_type
+      .as_deref()
+      .and_then(MinifyKind::from_mime_type)
+      .filter(|_| self.minify);
+
+    if injector.is_none() && minify_kind.is_none() {
+      return Ok(response);
+    }
+
+    let (mut response_parts, mut response_body) = response.into_parts();
+    while response_parts.headers.remove(header::CONTENT_LENGTH).is_some() {}
+    while response_parts.headers.remove(header::ACCEPT_RANGES).is_some() {}
+
+    // Add ETag suffix, since the response body is transformed
+    if let Some(etag) = response_parts.headers.get_mut(header::ETAG) {
+      if let Ok(etag_str) = etag.to_str() {
+        if let Some(etag_str) = etag_str.strip_suffix('"') {
+          *etag = format!("{etag_str}-transformed\"").try_into()?;
+        } else {
+          *etag = format!("{etag_str}-transformed").try_into()?;
+        }
+      }
+    }
+
+    // Snippets are injected before minification, since minification can omit optional closing tags
+    if let Some(injector) = injector {
+      response_body = injector.wrap(response_body).boxed();
+    }
+
+    if let Some(minify_kind) = minify_kind {
+      let minified_cache = self.minified_cache.clone();
+      let transform: BodyTransformFn = Arc::new(move |data: Bytes| {
+        let cache_key = (minify_kind, Sha256::digest(&data).into());
+        if let Some(minified) = minified_cache.get(&cache_key) {
+          return minified;
+        }
+        match minify_kind.minify(&data) {
+          Some(minified) => {
+            let minified = Bytes::from(minified);
+            minified_cache.insert(cache_key, minified.clone());
+            minified
+          }
+          None => data,
+        }
+      });
+      response_body = BufferedTransformBody::new(
+        response_body,
+        self
+          .minify_max_size
+          .map_or(usize::MAX, |v| v.try_into().unwrap_or(usize::MAX)),
+        transform,
+      )
+      .boxed();
+    }
+
+    response_parts.extensions.clear();
+    Ok(Response::from_parts(response_parts, response_body))
+  }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::Body;
+use hyper::{header, Request, Response, StatusCode};
+
+use ferron_common::config::{
+  ServerConfiguration, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationValue,
+};
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, ResponseData, SocketData};
+use ferron_common::observability::MetricsMultiSender;
+use ferron_common::{get_entries, get_entries_for_validation};
+
+use crate::{FcgiModuleLoader, ReverseProxyModuleLoader};
+
+/// The maximum size of request bodies buffered, so that the requests can be passed to the next backend in the chain
+const MAX_REPLAYABLE_REQUEST_BODY_SIZE: u64 = 65536;
+
+/// The directives specifying the backends, which are replaced in the configurations of the backends in the chain
+const BACKEND_DIRECTIVES: [&str; 5] = ["fcgi", "fcgi_php", "proxy", "proxy_srv", "try_backends"];
+
+/// A backend target parsed from the backend URL
+enum BackendTarget {
+  /// A FastCGI server (the URL is a FastCGI module URL, like `tcp://localhost:9000/`)
+  FastCgi(String),
+  /// An HTTP backend server
+  Http(String),
+  /// A static file
+  File(PathBuf),
+}
+
+/// Parses the backend URL (like `fcgi://localhost:9000`, `fcgi+unix:///run/php-fpm.sock`, `http://localhost:3000`
+/// or `file:///var/www/maintenance.html`)
+fn parse_backend_url(url: &str) -> Option<BackendTarget> {
+  if let Some(address) = url.strip_prefix("fcgi://") {
+    Some(BackendTarget::FastCgi(format!("tcp://{address}")))
+  } else if let Some(path) = url.strip_prefix("fcgi+unix://") {
+    Some(BackendTarget::FastCgi(format!("unix://{path}")))
+  } else if url.starts_with("http://") || url.starts_with("https://") {
+    Some(BackendTarget::Http(url.to_string()))
+  } else {
+    url
+      .strip_prefix("file://")
+      .filter(|path| !path.is_empty())
+      .map(|path| BackendTarget::File(PathBuf::from(path)))
+  }
+}
+
+/// The conditions, under which a request is passed to the next backend in the chain
+#[derive(Clone, Debug, Default)]
+struct FallbackConditions {
+  error: bool,
+  timeout: bool,
+  status_codes: Vec<StatusCode>,
+}
+
+impl FallbackConditions {
+  /// Adds a condition (`error`, `timeout` or `http_<status_code>`)
+  fn add_condition(&mut self, condition: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match condition {
+      "error" => self.error = true,
+      "timeout" => self.timeout = true,
+      _ => {
+        let status_code = condition
+          .strip_prefix("http_")
+          .and_then(|status_code| status_code.parse::<u16>().ok())
+          .and_then(|status_code| StatusCode::from_u16(status_code).ok())
+          .filter(|status_code| status_code.is_client_error() || status_code.is_server_error())
+          .ok_or(anyhow::anyhow!(
+            "Invalid condition for passing a request to the next backend in the chain: {condition}"
+          ))?;
+        if !self.status_codes.contains(&status_code) {
+          self.status_codes.push(status_code);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks whether the result of the backend triggers passing the request to the next backend in the chain
+  fn matches(&self, result: &Result<ResponseData, Box<dyn Error + Send + Sync>>) -> bool {
+    match result {
+      Err(_) => self.error,
+      Ok(ResponseData {
+        response: Some(response),
+        ..
+      }) => self.status_codes.contains(&response.status()),
+      Ok(ResponseData {
+        response: None,
+        response_status: Some(status),
+        ..
+      }) => match *status {
+        StatusCode::GATEWAY_TIMEOUT => self.timeout,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => self.error,
+        _ => false,
+      },
+      Ok(_) => false,
+    }
+  }
+}
+
+/// A backend in the chain
+enum Backend {
+  /// A backend served by another module (FastCGI or reverse proxy) with the configuration specific to the backend
+  Module {
+    url: String,
+    module: Arc<dyn Module + Send + Sync>,
+    config: Box<ServerConfiguration>,
+  },
+  /// A static file
+  File(PathBuf),
+}
+
+impl Backend {
+  /// Obtains the backend URL or path for the logs
+  fn url(&self) -> String {
+    match self {
+      Self::Module { url, .. } => url.clone(),
+      Self::File(path) => format!("file://{}", path.display()),
+    }
+  }
+}
+
+/// A backend fallback chain module loader
+pub struct TryBackendsModuleLoader {
+  fcgi_loader: FcgiModuleLoader,
+  proxy_loader: ReverseProxyModuleLoader,
+}
+
+impl Default for TryBackendsModuleLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TryBackendsModuleLoader {
+  /// Creates a new module loader
+  pub fn new() -> Self {
+    Self {
+      fcgi_loader: FcgiModuleLoader::new(),
+      proxy_loader: ReverseProxyModuleLoader::new(),
+    }
+  }
+}
+
+impl ModuleLoader for TryBackendsModuleLoader {
+  fn load_module(
+    &mut self,
+    config: &ServerConfiguration,
+    global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    // The modules for the backends are cached by the FastCGI and reverse proxy module loaders
+    let mut backends = Vec::new();
+    for url in get_entries!("try_backends", config)
+      .iter()
+      .flat_map(|e| e.inner.iter())
+      .flat_map(|e| e.values.iter())
+      .filter_map(|v| v.as_str())
+    {
+      let (directive, backend_to) = match parse_backend_url(url) {
+        Some(BackendTarget::FastCgi(fastcgi_to)) => ("fcgi", fastcgi_to),
+        Some(BackendTarget::Http(proxy_to)) => ("proxy", proxy_to),
+        Some(BackendTarget::File(path)) => {
+          backends.push(Backend::File(path));
+          continue;
+        }
+        None => Err(anyhow::anyhow!("Unsupported backend URL: {url}"))?,
+      };
+
+      let mut backend_config = config.clone();
+      backend_config
+        .entries
+        .retain(|name, _| !BACKEND_DIRECTIVES.contains(&name.as_str()));
+      backend_config.entries.insert(
+        directive.to_string(),
+        ServerConfigurationEntries {
+          inner: vec![ServerConfigurationEntry {
+            values: vec![ServerConfigurationValue::String(backend_to)],
+            props: HashMap::new(),
+          }],
+        },
+      );
+      let module = if directive == "fcgi" {
+        self
+          .fcgi_loader
+          .load_module(&backend_config, global_config, secondary_runtime)?
+      } else {
+        self
+          .proxy_loader
+          .load_module(&backend_config, global_config, secondary_runtime)?
+      };
+      backends.push(Backend::Module {
+        url: url.to_string(),
+        module,
+        config: Box::new(backend_config),
+      });
+    }
+
+    let mut conditions = FallbackConditions::default();
+    if let Some(entries) = get_entries!("try_backends_on", config) {
+      for entry in &entries.inner {
+        for condition in entry.values.iter().filter_map(|v| v.as_str()) {
+          conditions.add_condition(condition)?;
+        }
+      }
+    } else {
+      conditions.error = true;
+      conditions.timeout = true;
+    }
+
+    Ok(Arc::new(TryBackendsModule {
+      chain: Arc::new(BackendChain { backends, conditions }),
+    }))
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["try_backends"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("try_backends", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `try_backends` configuration property must have at least one value"
+          ))?
+        }
+        for value in &entry.values {
+          if value.as_str().is_none_or(|url| parse_backend_url(url).is_none()) {
+            Err(anyhow::anyhow!("Invalid backend URL in the backend fallback chain"))?
+          }
+        }
+      }
+    }
+
+    if let Some(entries) = get_entries_for_validation!("try_backends_on", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.is_empty() {
+          Err(anyhow::anyhow!(
+            "The `try_backends_on` configuration property must have at least one value"
+          ))?
+        }
+        let mut conditions = FallbackConditions::default();
+        for value in &entry.values {
+          if let Some(condition) = value.as_str() {
+            conditions.add_condition(condition)?;
+          } else {
+            Err(anyhow::anyhow!(
+              "Invalid condition for passing a request to the next backend in the chain"
+            ))?
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A backend fallback chain
+struct BackendChain {
+  backends: Vec<Backend>,
+  conditions: FallbackConditions,
+}
+
+/// A backend fallback chain module
+struct TryBackendsModule {
+  chain: Arc<BackendChain>,
+}
+
+impl Module for TryBackendsModule {
+  fn get_module_handlers(&self) -> Box<dyn ModuleHandlers> {
+    Box::new(TryBackendsModuleHandlers {
+      chain: self.chain.clone(),
+      metrics_sender: None,
+      executed_handlers: Vec::new(),
+      response_handler: None,
+    })
+  }
+}
+
+/// Handlers for the backend fallback chain module
+struct TryBackendsModuleHandlers {
+  chain: Arc<BackendChain>,
+  metrics_sender: Option<MetricsMultiSender>,
+  executed_handlers: Vec<Box<dyn ModuleHandlers>>,
+  response_handler: Option<usize>,
+}
+
+#[async_trait(?Send)]
+impl ModuleHandlers for TryBackendsModuleHandlers {
+  async fn request_handler(
+    &mut self,
+    request: Request<BoxBody<Bytes, std::io::Error>>,
+    _config: &ServerConfiguration,
+    socket_data: &SocketData,
+    error_logger: &ErrorLogger,
+  ) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    // Small request bodies are buffered, so that the request can be passed to the next backend in the chain.
+    // Larger request bodies are passed only to the first backend (and static files are served without request bodies).
+    let (request_parts, request_body) = request.into_parts();
+    let (mut request_body, replayable_body) = if request_body
+      .size_hint()
+      .upper()
+      .is_some_and(|size| size <= MAX_REPLAYABLE_REQUEST_BODY_SIZE)
+    {
+      (None, Some(request_body.collect().await?.to_bytes()))
+    } else {
+      (Some(request_body), None)
+    };
+
+    let chain = self.chain.clone();
+    let mut last_result = None;
+    for (index, backend) in chain.backends.iter().enumerate() {
+      let is_fallback = last_result.is_some();
+      let result = match backend {
+        Backend::Module { module, config, .. } => {
+          let request_body = match (&replayable_body, request_body.take()) {
+            (Some(body), _) => Full::new(body.clone()).map_err(|e| match e {}).boxed(),
+            (None, Some(body)) => body,
+            (None, None) => continue,
+          };
+          let request = Request::from_parts(request_parts.clone(), request_body);
+
+          let mut handlers = module.get_module_handlers();
+          if let Some(metrics_sender) = &self.metrics_sender {
+            handlers
+              .metric_data_before_handler(&request, socket_data, metrics_sender)
+              .await;
+          }
+          let result = handlers
+            .request_handler(request, config, socket_data, error_logger)
+            .await;
+          self.executed_handlers.push(handlers);
+          self.response_handler = Some(self.executed_handlers.len() - 1);
+          result
+        }
+        Backend::File(path) => {
+          self.response_handler = None;
+          serve_file(path.clone(), is_fallback).await
+        }
+      };
+
+      if index + 1 == chain.backends.len() || !chain.conditions.matches(&result) {
+        last_result = Some(result);
+        break;
+      }
+      error_logger
+        .log(&format!(
+          "The backend \"{}\" failed, passing the request to the next backend in the chain",
+          backend.url()
+        ))
+        .await;
+      last_result = Some(result);
+    }
+
+    last_result.unwrap_or(Ok(ResponseData {
+      request: None,
+      response: None,
+      response_status: Some(StatusCode::SERVICE_UNAVAILABLE),
+      response_headers: None,
+      new_remote_address: None,
+    }))
+  }
+
+  async fn response_modifying_handler(
+    &mut self,
+    response: Response<BoxBody<Bytes, std::io::Error>>,
+  ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, Box<dyn Error>> {
+    match self
+      .response_handler
+      .and_then(|index| self.executed_handlers.get_mut(index))
+    {
+      Some(handlers) => handlers.response_modifying_handler(response).await,
+      None => Ok(response),
+    }
+  }
+
+  async fn metric_data_before_handler(
+    &mut self,
+    _request: &Request<BoxBody<Bytes, std::io::Error>>,
+    _socket_data: &SocketData,
+    metrics_sender: &MetricsMultiSender,
+  ) {
+    // The metric data handlers of the backends are executed when the backends are tried
+    self.metrics_sender = Some(metrics_sender.clone());
+  }
+
+  async fn metric_data_after_handler(&mut self, metrics_sender: &MetricsMultiSender) {
+    for handlers in &mut self.executed_handlers {
+      handlers.metric_data_after_handler(metrics_sender).await;
+    }
+  }
+}
+
+/// Serves a static file from the chain. The file is served with a "503 Service Unavailable" status,
+/// if the previous backends in the chain failed
+async fn serve_file(path: PathBuf, is_fallback: bool) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+  let content_type = new_mime_guess::from_path(&path)
+    .first()
+    .map(|mime_type| mime_type.to_string());
+  let contents = ferron_common::runtime::spawn_blocking(move || std::fs::read(path))
+    .await
+    .map_err(|_| anyhow::anyhow!("Can't spawn a blocking task to read the file"))??;
+
+  let mut response_builder = Response::builder().status(if is_fallback {
+    StatusCode::SERVICE_UNAVAILABLE
+  } else {
+    StatusCode::OK
+  });
+  if let Some(content_type) = content_type {
+    response_builder = response_builder.header(header::CONTENT_TYPE, content_type);
+  }
+  let response_body = if contents.is_empty() {
+    Empty::new().map_err(|e| match e {}).boxed()
+  } else {
+    Full::new(Bytes::from(contents)).map_err(|e| match e {}).boxed()
+  };
+
+  Ok(ResponseData {
+    request: None,
+    response: Some(response_builder.body(response_body)?),
+    response_status: None,
+    response_headers: None,
+    new_remote_address: None,
+  })
+}
@@ -1,5 +1,5 @@
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -9,7 +9,7 @@ use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Empty, Full};
 use hyper::header::HeaderValue;
 use hyper::{header, HeaderMap, Request, Response, StatusCode};
-use password_auth::verify_password;
+use password_auth::{generate_hash, verify_password};
 use tokio::sync::RwLock;
 
 use ferron_common::logging::ErrorLogger;
@@ -19,7 +19,16 @@ use ferron_common::{get_entries, get_entries_for_validation};
 
 use ferron_common::modules::{Module, ModuleHandlers, ModuleLoader, RequestData, ResponseData, SocketData};
 
-use crate::util::parse_basic_auth;
+use crate::util::{
+  compute_digest_response, constant_time_eq, parse_basic_auth, parse_digest_auth, sha256_hex, DigestCredentials,
+  DigestNonceValidity, DigestNonces,
+};
+
+/// The maximum age of HTTP Digest authentication nonces
+const DIGEST_NONCE_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// A password hash verified when the user doesn't exist, so that existing users can't be discovered via response timing
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| generate_hash("ferron-dummy-password"));
 
 /// A non-standard status code configuration
 struct NonStandardCode {
@@ -29,16 +38,144 @@ struct NonStandardCode {
   location: Option<String>,
   realm: Option<String>,
   disable_brute_force_protection: bool,
+  max_user_attempts: Option<u32>,
+  digest: bool,
   user_list: Option<Vec<String>>,
   users: Option<IpBlockList>,
   body: Option<String>,
   not_allowed: Option<IpBlockList>,
 }
 
+/// HTTP authentication credentials
+enum HttpCredentials {
+  /// HTTP Basic authentication credentials (username and password)
+  Basic(String, String),
+  /// HTTP Digest authentication credentials
+  Digest(DigestCredentials),
+}
+
+/// Verifies the HTTP Basic authentication credentials against the configured users
+async fn verify_basic_credentials(
+  config: &ServerConfiguration,
+  user_list: Option<&[String]>,
+  username: &str,
+  password: String,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+  let mut password_hashes = Vec::new();
+  if let Some(users_vec_config) = get_entries!("user", config) {
+    for user_config in &users_vec_config.inner {
+      if let Some(username_db) = user_config.values.first().and_then(|v| v.as_str()) {
+        if !constant_time_eq(username_db.as_bytes(), username.as_bytes()) {
+          continue;
+        }
+        if let Some(user_list) = user_list {
+          if !user_list.iter().any(|allowed_user| allowed_user == username) {
+            continue;
+          }
+        }
+        if let Some(password_hash_db) = user_config.values.get(1).and_then(|v| v.as_str()) {
+          password_hashes.push(password_hash_db.to_string());
+        }
+      }
+    }
+  }
+
+  // Offload verifying the hash into a separate blocking thread.
+  Ok(
+    ferron_common::runtime::spawn_blocking(move || {
+      if password_hashes.is_empty() {
+        let _ = verify_password(&password, &DUMMY_PASSWORD_HASH);
+        false
+      } else {
+        password_hashes
+          .iter()
+          .any(|password_hash_db| verify_password(&password, password_hash_db).is_ok())
+      }
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("Can't spawn a blocking task to verify the password"))?,
+  )
+}
+
+/// Resolves the username from the HTTP Digest authentication credentials, which may contain a hashed username
+fn resolve_digest_username(config: &ServerConfiguration, credentials: &DigestCredentials, realm: &str) -> String {
+  if credentials.userhash {
+    let username_hash = credentials.username.to_ascii_lowercase();
+    if let Some(users_vec_config) = get_entries!("user", config) {
+      for user_config in &users_vec_config.inner {
+        if let Some(username_db) = user_config.values.first().and_then(|v| v.as_str()) {
+          if constant_time_eq(
+            sha256_hex(&format!("{username_db}:{realm}")).as_bytes(),
+            username_hash.as_bytes(),
+          ) {
+            return username_db.to_string();
+          }
+        }
+      }
+    }
+  }
+  credentials.username.clone()
+}
+
+/// Verifies the HTTP Digest authentication credentials against the configured users
+fn verify_digest_credentials(
+  config: &ServerConfiguration,
+  user_list: Option<&[String]>,
+  username: &str,
+  credentials: &DigestCredentials,
+  realm: &str,
+  method: &str,
+  request_url: &str,
+) -> bool {
+  if credentials.realm != realm
+    || credentials.uri != request_url
+    || !credentials
+      .algorithm
+      .as_deref()
+      .is_some_and(|algorithm| algorithm.eq_ignore_ascii_case("SHA-256"))
+    || credentials.qop.as_deref() != Some("auth")
+  {
+    return false;
+  }
+  let (Some(nc), Some(cnonce)) = (&credentials.nc, &credentials.cnonce) else {
+    return false;
+  };
+  if let Some(user_list) = user_list {
+    if !user_list.iter().any(|allowed_user| allowed_user == username) {
+      return false;
+    }
+  }
+  let response = credentials.response.to_ascii_lowercase();
+  let mut credentials_valid = false;
+  if let Some(users_vec_config) = get_entries!("user", config) {
+    for user_config in &users_vec_config.inner {
+      if let Some(username_db) = user_config.values.first().and_then(|v| v.as_str()) {
+        if !constant_time_eq(username_db.as_bytes(), username.as_bytes()) {
+          continue;
+        }
+        if let Some(digest_ha1) = user_config.props.get("digest").and_then(|v| v.as_str()) {
+          let expected_response = compute_digest_response(
+            &digest_ha1.to_ascii_lowercase(),
+            &credentials.nonce,
+            nc,
+            cnonce,
+            method,
+            request_url,
+          );
+          credentials_valid |= constant_time_eq(expected_response.as_bytes(), response.as_bytes());
+        }
+      }
+    }
+  }
+  credentials_valid
+}
+
 /// A status codes module loader
 pub struct StatusCodesModuleLoader {
   cache: ModuleCache<StatusCodesModule>,
   brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+  user_brute_force_db: Arc<RwLock<TtlCache<String, u32>>>,
+  digest_nonces: Arc<DigestNonces>,
 }
 
 impl Default for StatusCodesModuleLoader {
@@ -53,6 +190,8 @@ impl StatusCodesModuleLoader {
     Self {
       cache: ModuleCache::new(vec!["status"]),
       brute_force_db: Arc::new(RwLock::new(TtlCache::new(Duration::new(300, 0)))),
+      user_brute_force_db: Arc::new(RwLock::new(TtlCache::new(Duration::new(300, 0)))),
+      digest_nonces: Arc::new(DigestNonces::new(DIGEST_NONCE_MAX_AGE)),
     }
   }
 }
@@ -106,6 +245,18 @@ impl ModuleLoader for StatusCodesModuleLoader {
                 .get("brute_protection")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true);
+              // Failed attempts per user are limited only when configured, since they are counted regardless of
+              // the client IP address, so any client could lock out the user
+              let max_user_attempts = non_standard_code_config_entry
+                .props
+                .get("max_user_attempts")
+                .and_then(|v| v.as_i128())
+                .and_then(|v| v.try_into().ok());
+              let digest = non_standard_code_config_entry
+                .props
+                .get("digest")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
               let user_list = non_standard_code_config_entry
                 .props
                 .get("users")
@@ -151,6 +302,8 @@ impl ModuleLoader for StatusCodesModuleLoader {
                 location,
                 realm,
                 disable_brute_force_protection,
+                max_user_attempts,
+                digest,
                 user_list,
                 users,
                 body,
@@ -161,6 +314,8 @@ impl ModuleLoader for StatusCodesModuleLoader {
           Ok(Arc::new(StatusCodesModule {
             non_standard_codes_list: Arc::new(non_standard_codes_list),
             brute_force_db: self.brute_force_db.clone(),
+            user_brute_force_db: self.user_brute_force_db.clone(),
+            digest_nonces: self.digest_nonces.clone(),
           }))
         })?,
     )
@@ -211,6 +366,18 @@ impl ModuleLoader for StatusCodesModuleLoader {
           ))?
         } else if !entry.props.get("body").is_none_or(|v| v.is_string()) {
           Err(anyhow::anyhow!("The custom status code response body must be a string"))?
+        } else if !entry.props.get("digest").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!(
+            "The custom status code HTTP Digest authentication option must be boolean"
+          ))?
+        } else if !entry
+          .props
+          .get("max_user_attempts")
+          .is_none_or(|v| v.is_null() || v.as_i128().is_some_and(|v| (1..=u32::MAX as i128).contains(&v)))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid custom status code HTTP authentication maximum failed attempts per user"
+          ))?
         }
       }
     }
@@ -225,6 +392,11 @@ impl ModuleLoader for StatusCodesModuleLoader {
           Err(anyhow::anyhow!("Invalid username"))?
         } else if !entry.values[1].is_string() {
           Err(anyhow::anyhow!("Invalid password hash"))?
+        } else if !entry.props.get("digest").is_none_or(|v| {
+          v.as_str()
+            .is_some_and(|v| v.len() == 64 && v.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        }) {
+          Err(anyhow::anyhow!("Invalid HTTP Digest authentication hash"))?
         }
       }
     }
@@ -237,6 +409,8 @@ impl ModuleLoader for StatusCodesModuleLoader {
 struct StatusCodesModule {
   non_standard_codes_list: Arc<Vec<NonStandardCode>>,
   brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+  user_brute_force_db: Arc<RwLock<TtlCache<String, u32>>>,
+  digest_nonces: Arc<DigestNonces>,
 }
 
 impl Module for StatusCodesModule {
@@ -244,6 +418,8 @@ impl Module for StatusCodesModule {
     Box::new(StatusCodesModuleHandlers {
       non_standard_codes_list: self.non_standard_codes_list.clone(),
       brute_force_db: self.brute_force_db.clone(),
+      user_brute_force_db: self.user_brute_force_db.clone(),
+      digest_nonces: self.digest_nonces.clone(),
     })
   }
 }
@@ -252,6 +428,8 @@ impl Module for StatusCodesModule {
 struct StatusCodesModuleHandlers {
   non_standard_codes_list: Arc<Vec<NonStandardCode>>,
   brute_force_db: Arc<RwLock<TtlCache<String, u8>>>,
+  user_brute_force_db: Arc<RwLock<TtlCache<String, u32>>>,
+  digest_nonces: Arc<DigestNonces>,
 }
 
 #[async_trait(?Send)]
@@ -395,19 +573,11 @@ impl ModuleHandlers for StatusCodesModuleHandlers {
                 });
               }
             }
-            let mut header_map = HeaderMap::new();
-            header_map.insert(
-              header::WWW_AUTHENTICATE,
-              HeaderValue::from_str(&format!(
-                "Basic realm=\"{}\", charset=\"UTF-8\"",
-                non_standard_code
-                  .realm
-                  .clone()
-                  .unwrap_or("Ferron HTTP Basic Authorization".to_string())
-                  .replace("\\", "\\\\")
-                  .replace("\"", "\\\"")
-              ))?,
-            );
+            let realm = non_standard_code
+              .realm
+              .clone()
+              .unwrap_or("Ferron HTTP Basic Authorization".to_string());
+            let mut digest_stale = false;
 
             if let Some(authorization_header_value) = request_parts.headers.get(header::AUTHORIZATION) {
               let authorization_str = match authorization_header_value.to_str() {
@@ -423,58 +593,134 @@ impl ModuleHandlers for StatusCodesModuleHandlers {
                 }
               };
 
-              if let Some((username, password)) = parse_basic_auth(authorization_str) {
-                if let Some(users_vec_config) = get_entries!("user", config) {
-                  let mut authorized_user = None;
-                  for user_config in &users_vec_config.inner {
-                    if let Some(username_db) = user_config.values.first().and_then(|v| v.as_str()) {
-                      if username_db != username {
-                        continue;
-                      }
-                      if let Some(user_list) = &non_standard_code.user_list {
-                        if !user_list.contains(&username) {
-                          continue;
-                        }
-                      }
-                      if let Some(password_hash_db) = user_config.values.get(1).and_then(|v| v.as_str()) {
-                        let password_cloned = password.clone();
-                        let password_hash_db_cloned = password_hash_db.to_string();
-                        // Offload verifying the hash into a separate blocking thread.
-                        let password_valid = ferron_common::runtime::spawn_blocking(move || {
-                          verify_password(password_cloned, &password_hash_db_cloned).is_ok()
-                        })
-                        .await
-                        .map_err(|_| anyhow::anyhow!("Can't spawn a blocking task to verify the password"))?;
-                        if password_valid {
-                          authorized_user = Some(&username);
-                          break;
+              let credentials = if let Some((username, password)) = parse_basic_auth(authorization_str) {
+                Some(HttpCredentials::Basic(username, password))
+              } else if non_standard_code.digest {
+                parse_digest_auth(authorization_str).map(HttpCredentials::Digest)
+              } else {
+                None
+              };
+
+              if let Some(credentials) = credentials {
+                let username = match &credentials {
+                  HttpCredentials::Basic(username, _) => username.clone(),
+                  HttpCredentials::Digest(digest_credentials) => {
+                    resolve_digest_username(config, digest_credentials, &realm)
+                  }
+                };
+
+                if let Some(max_user_attempts) = non_standard_code.max_user_attempts {
+                  let rwlock_read = self.user_brute_force_db.read().await;
+                  let current_attempts = rwlock_read.get(&username).unwrap_or(0);
+                  if current_attempts >= max_user_attempts {
+                    error_logger
+                      .log(&format!(
+                        "Too many failed authorization attempts for user \"{}\" (client \"{}\")",
+                        username,
+                        socket_data.remote_addr.ip()
+                      ))
+                      .await;
+
+                    return Ok(ResponseData {
+                      request: Some(Request::from_parts(request_parts, request_body)),
+                      response: None,
+                      response_status: Some(StatusCode::TOO_MANY_REQUESTS),
+                      response_headers: None,
+                      new_remote_address: None,
+                    });
+                  }
+                }
+
+                let credentials_valid = match credentials {
+                  HttpCredentials::Basic(_, password) => {
+                    verify_basic_credentials(config, non_standard_code.user_list.as_deref(), &username, password)
+                      .await?
+                  }
+                  HttpCredentials::Digest(digest_credentials) => {
+                    match self.digest_nonces.validate(&digest_credentials.nonce, &realm) {
+                      DigestNonceValidity::Invalid => false,
+                      nonce_validity => {
+                        let credentials_valid = verify_digest_credentials(
+                          config,
+                          non_standard_code.user_list.as_deref(),
+                          &username,
+                          &digest_credentials,
+                          &realm,
+                          request_parts.method.as_str(),
+                          &request_url,
+                        );
+                        let nonce_validity = if credentials_valid && nonce_validity == DigestNonceValidity::Valid {
+                          // Reject replayed requests, whose nonce count was already used with the nonce
+                          self.digest_nonces.use_nonce_count(
+                            &digest_credentials.nonce,
+                            digest_credentials.nc.as_deref().unwrap_or_default(),
+                          )
+                        } else {
+                          nonce_validity
+                        };
+                        match nonce_validity {
+                          DigestNonceValidity::Valid => credentials_valid,
+                          DigestNonceValidity::Stale if credentials_valid => {
+                            // Valid credentials with an expired nonce; the client should retry with a new nonce
+                            digest_stale = true;
+                            false
+                          }
+                          _ => false,
                         }
                       }
                     }
                   }
-                  if let Some(authorized_user) = authorized_user {
-                    auth_user = Some(authorized_user.to_owned());
-                    continue;
-                  }
-                }
+                };
 
-                if !non_standard_code.disable_brute_force_protection {
-                  let mut rwlock_write = self.brute_force_db.write().await;
-                  rwlock_write.cleanup();
-                  let current_attempts = rwlock_write.get(&brute_force_db_key).unwrap_or(0);
-                  rwlock_write.insert(brute_force_db_key, current_attempts + 1);
+                if credentials_valid {
+                  auth_user = Some(username);
+                  continue;
                 }
 
-                error_logger
-                  .log(&format!(
-                    "Authorization failed for user \"{}\" and client \"{}\"",
-                    username,
-                    socket_data.remote_addr.ip()
-                  ))
-                  .await;
+                if !digest_stale {
+                  if !non_standard_code.disable_brute_force_protection {
+                    let mut rwlock_write = self.brute_force_db.write().await;
+                    rwlock_write.cleanup();
+                    let current_attempts = rwlock_write.get(&brute_force_db_key).unwrap_or(0);
+                    rwlock_write.insert(brute_force_db_key, current_attempts + 1);
+                  }
+
+                  if non_standard_code.max_user_attempts.is_some() {
+                    let mut rwlock_write = self.user_brute_force_db.write().await;
+                    rwlock_write.cleanup();
+                    let current_attempts = rwlock_write.get(&username).unwrap_or(0);
+                    rwlock_write.insert(username.clone(), current_attempts.saturating_add(1));
+                  }
+
+                  error_logger
+                    .log(&format!(
+                      "Authorization failed for user \"{}\" and client \"{}\"",
+                      username,
+                      socket_data.remote_addr.ip()
+                    ))
+                    .await;
+                }
               }
             }
 
+            let escaped_realm = realm.replace("\\", "\\\\").replace("\"", "\\\"");
+            let mut header_map = HeaderMap::new();
+            if non_standard_code.digest {
+              header_map.append(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_str(&format!(
+                  "Digest realm=\"{}\", qop=\"auth\", algorithm=SHA-256, nonce=\"{}\", charset=UTF-8, userhash=true{}",
+                  escaped_realm,
+                  self.digest_nonces.generate(&realm),
+                  if digest_stale { ", stale=true" } else { "" }
+                ))?,
+              );
+            }
+            header_map.append(
+              header::WWW_AUTHENTICATE,
+              HeaderValue::from_str(&format!("Basic realm=\"{}\", charset=\"UTF-8\"", escaped_realm))?,
+            );
+
             if let Some(body) = &non_standard_code.body {
               let mut response_builder = Response::builder().status(StatusCode::UNAUTHORIZED);
               if let Some(headers) = response_builder.headers_mut() {
@@ -550,3 +796,209 @@ impl ModuleHandlers for StatusCodesModuleHandlers {
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::future::Future;
+  use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+  use ferron_common::config::{
+    ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationFilters, ServerConfigurationValue,
+  };
+  use ferron_common::observability::ObservabilityBackendChannels;
+
+  use super::*;
+
+  const REALM: &str = "Restricted";
+
+  fn run_async<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()
+      .expect("runtime should be created")
+      .block_on(future)
+  }
+
+  fn entry(
+    values: Vec<ServerConfigurationValue>,
+    props: Vec<(&str, ServerConfigurationValue)>,
+  ) -> ServerConfigurationEntry {
+    ServerConfigurationEntry {
+      values,
+      props: props
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect::<HashMap<_, _>>(),
+    }
+  }
+
+  fn string(value: &str) -> ServerConfigurationValue {
+    ServerConfigurationValue::String(value.to_string())
+  }
+
+  /// Creates a configuration requiring HTTP Digest authentication of the "alice" user with the "password" password
+  fn config(max_user_attempts: Option<i128>) -> ServerConfiguration {
+    let mut status_props = vec![
+      ("realm", string(REALM)),
+      ("digest", ServerConfigurationValue::Bool(true)),
+    ];
+    if let Some(max_user_attempts) = max_user_attempts {
+      status_props.push((
+        "max_user_attempts",
+        ServerConfigurationValue::Integer(max_user_attempts),
+      ));
+    }
+    let status = entry(vec![ServerConfigurationValue::Integer(401)], status_props);
+    let user = entry(
+      vec![
+        string("alice"),
+        string("$pbkdf2-sha256$i=1000,l=32$ZmVycm9uLXRlc3Qtc2FsdA$D5TnTAPVGAtDZUjvS4w229SGTtTfzlIvWcbS2BhFoX8"),
+      ],
+      vec![("digest", string(&sha256_hex(&format!("alice:{REALM}:password"))))],
+    );
+    ServerConfiguration {
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: None,
+        ip: None,
+        port: None,
+        condition: None,
+        error_handler_status: None,
+      },
+      entries: HashMap::from([
+        ("status".to_string(), ServerConfigurationEntries { inner: vec![status] }),
+        ("user".to_string(), ServerConfigurationEntries { inner: vec![user] }),
+      ]),
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  fn digest_authorization(nonce: &str, password: &str, nc: &str) -> String {
+    let ha1 = sha256_hex(&format!("alice:{REALM}:{password}"));
+    let response = compute_digest_response(&ha1, nonce, nc, "0a4f113b", "GET", "/");
+    format!(
+      "Digest username=\"alice\", realm=\"{REALM}\", uri=\"/\", algorithm=SHA-256, nonce=\"{nonce}\", \
+       nc={nc}, cnonce=\"0a4f113b\", qop=auth, response=\"{response}\""
+    )
+  }
+
+  /// Sends a request with the "Authorization" header from the client, and returns the response status code
+  async fn authenticate(
+    module: &Arc<dyn Module + Send + Sync>,
+    config: &ServerConfiguration,
+    client_ip: Ipv4Addr,
+    authorization: &str,
+  ) -> StatusCode {
+    let request = Request::builder()
+      .uri("/")
+      .header(header::AUTHORIZATION, authorization)
+      .body(Empty::new().map_err(|e| match e {}).boxed())
+      .unwrap();
+    let socket_data = SocketData {
+      remote_addr: SocketAddr::new(IpAddr::V4(client_ip), 50000),
+      local_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+    let response_data = module
+      .get_module_handlers()
+      .request_handler(request, config, &socket_data, &ErrorLogger::without_logger())
+      .await
+      .unwrap();
+    response_data.response_status.unwrap_or(StatusCode::OK)
+  }
+
+  #[test]
+  fn test_failed_attempts_dont_lock_out_other_clients() {
+    let config = config(None);
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut loader = StatusCodesModuleLoader::new();
+    let module = loader.load_module(&config, None, &runtime).unwrap();
+    let nonce = loader.digest_nonces.generate(REALM);
+
+    run_async(async {
+      let attacker = Ipv4Addr::new(192, 0, 2, 1);
+      for _ in 0..10 {
+        let authorization = digest_authorization(&nonce, "wrong password", "00000001");
+        assert_eq!(
+          authenticate(&module, &config, attacker, &authorization).await,
+          StatusCode::UNAUTHORIZED
+        );
+      }
+      let authorization = digest_authorization(&nonce, "wrong password", "00000001");
+      assert_eq!(
+        authenticate(&module, &config, attacker, &authorization).await,
+        StatusCode::TOO_MANY_REQUESTS
+      );
+
+      let authorization = digest_authorization(&nonce, "password", "00000001");
+      assert_eq!(
+        authenticate(&module, &config, Ipv4Addr::new(198, 51, 100, 1), &authorization).await,
+        StatusCode::OK
+      );
+    });
+  }
+
+  #[test]
+  fn test_digest_replay() {
+    let config = config(None);
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut loader = StatusCodesModuleLoader::new();
+    let module = loader.load_module(&config, None, &runtime).unwrap();
+    let nonce = loader.digest_nonces.generate(REALM);
+    let client = Ipv4Addr::new(198, 51, 100, 1);
+
+    run_async(async {
+      let authorization = digest_authorization(&nonce, "password", "00000002");
+      assert_eq!(
+        authenticate(&module, &config, client, &authorization).await,
+        StatusCode::OK
+      );
+
+      // The same request replayed, and a request with a lower nonce count
+      assert_eq!(
+        authenticate(&module, &config, client, &authorization).await,
+        StatusCode::UNAUTHORIZED
+      );
+      let authorization = digest_authorization(&nonce, "password", "00000001");
+      assert_eq!(
+        authenticate(&module, &config, client, &authorization).await,
+        StatusCode::UNAUTHORIZED
+      );
+
+      let authorization = digest_authorization(&nonce, "password", "00000003");
+      assert_eq!(
+        authenticate(&module, &config, client, &authorization).await,
+        StatusCode::OK
+      );
+    });
+  }
+
+  #[test]
+  fn test_max_user_attempts() {
+    let config = config(Some(3));
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut loader = StatusCodesModuleLoader::new();
+    let module = loader.load_module(&config, None, &runtime).unwrap();
+    let nonce = loader.digest_nonces.generate(REALM);
+
+    run_async(async {
+      for client in 1..=3 {
+        let authorization = digest_authorization(&nonce, "wrong password", "00000001");
+        assert_eq!(
+          authenticate(&module, &config, Ipv4Addr::new(192, 0, 2, client), &authorization).await,
+          StatusCode::UNAUTHORIZED
+        );
+      }
+
+      let authorization = digest_authorization(&nonce, "password", "00000001");
+      assert_eq!(
+        authenticate(&module, &config, Ipv4Addr::new(198, 51, 100, 1), &authorization).await,
+        StatusCode::TOO_MANY_REQUESTS
+      );
+    });
+  }
+}
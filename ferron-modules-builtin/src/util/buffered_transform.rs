@@ -0,0 +1,176 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hyper::body::{Body, Frame};
+use hyper::HeaderMap;
+use pin_project_lite::pin_project;
+
+/// A function transforming a complete response body
+pub type BodyTransformFn = Arc<dyn Fn(Bytes) -> Bytes + Send + Sync>;
+
+pin_project! {
+  /// A `Body` that is buffered in full and transformed. If the body exceeds the maximum size, it's passed through unchanged.
+  pub struct BufferedTransformBody<B> {
+    transform: BodyTransformFn,
+    max_size: usize,
+    buffer: Option<BytesMut>,
+    trailers: Option<HeaderMap>,
+    finished: bool,
+    #[pin]
+    inner: B,
+  }
+}
+
+impl<B> BufferedTransformBody<B>
+where
+  B: Body,
+{
+  /// Wraps a `Body` to transform it once it's received in full
+  pub fn new(inner: B, max_size: usize, transform: BodyTransformFn) -> Self {
+    Self {
+      transform,
+      max_size,
+      buffer: Some(BytesMut::new()),
+      trailers: None,
+      finished: false,
+      inner,
+    }
+  }
+}
+
+impl<B> Body for BufferedTransformBody<B>
+where
+  B: Body,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let mut this = self.project();
+    loop {
+      if this.buffer.is_none() {
+        if let Some(trailers) = this.trailers.take() {
+          return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+        } else if *this.finished {
+          return Poll::Ready(None);
+        }
+        // The body exceeded the maximum size, so it's passed through
+        return match this.inner.as_mut().poll_frame(cx) {
+          Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+            Ok(mut data) => Poll::Ready(Some(Ok(Frame::data(data.copy_to_bytes(data.remaining()))))),
+            Err(frame) => Poll::Ready(Some(Ok(frame.map_data(|_| Bytes::new())))),
+          },
+          Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
+          Poll::Ready(None) => Poll::Ready(None),
+          Poll::Pending => Poll::Pending,
+        };
+      }
+
+      match this.inner.as_mut().poll_frame(cx) {
+        Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+          Ok(mut data) => {
+            let buffer = this.buffer.as_mut().expect("the body should be buffered");
+            buffer.put(data.copy_to_bytes(data.remaining()));
+            if buffer.len() > *this.max_size {
+              let buffer = this.buffer.take().unwrap_or_default();
+              return Poll::Ready(Some(Ok(Frame::data(buffer.freeze()))));
+            }
+          }
+          Err(frame) => {
+            if let Ok(trailers) = frame.into_trailers() {
+              *this.trailers = Some(trailers);
+            }
+            *this.finished = true;
+            let buffer = this.buffer.take().unwrap_or_default();
+            return Poll::Ready(Some(Ok(Frame::data((this.transform)(buffer.freeze())))));
+          }
+        },
+        Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => {
+          *this.finished = true;
+          let buffer = this.buffer.take().unwrap_or_default();
+          return Poll::Ready(Some(Ok(Frame::data((this.transform)(buffer.freeze())))));
+        }
+        Poll::Pending => return Poll::Pending,
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Helper function to collect all data from a body
+  async fn collect_body_bytes<B>(body: B) -> Result<Vec<u8>, B::Error>
+  where
+    B: Body,
+    B::Data: AsRef<[u8]>,
+  {
+    let mut result = Vec::new();
+    let mut body = std::pin::pin!(body);
+    while let Some(frame) = futures_util::future::poll_fn(|cx| body.as_mut().poll_frame(cx)).await {
+      if let Ok(data) = frame?.into_data() {
+        result.extend_from_slice(data.as_ref());
+      }
+    }
+    Ok(result)
+  }
+
+  // Simple test body that yields data in chunks
+  struct TestBody {
+    chunks: Vec<Bytes>,
+    current: usize,
+  }
+
+  impl TestBody {
+    fn new(chunks: &[&[u8]]) -> Self {
+      Self {
+        chunks: chunks.iter().map(|chunk| Bytes::copy_from_slice(chunk)).collect(),
+        current: 0,
+      }
+    }
+  }
+
+  impl Body for TestBody {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+      mut self: Pin<&mut Self>,
+      _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+      if self.current < self.chunks.len() {
+        let chunk = self.chunks[self.current].clone();
+        self.current += 1;
+        Poll::Ready(Some(Ok(Frame::data(chunk))))
+      } else {
+        Poll::Ready(None)
+      }
+    }
+  }
+
+  fn uppercase_transform() -> BodyTransformFn {
+    Arc::new(|data: Bytes| Bytes::from(data.to_ascii_uppercase()))
+  }
+
+  #[tokio::test]
+  async fn test_buffered_transform() {
+    let body = BufferedTransformBody::new(TestBody::new(&[b"hello ", b"world"]), 64, uppercase_transform());
+    let result = collect_body_bytes(body).await.unwrap();
+    assert_eq!(result, b"HELLO WORLD");
+  }
+
+  #[tokio::test]
+  async fn test_buffered_transform_exceeding_max_size() {
+    let body = BufferedTransformBody::new(
+      TestBody::new(&[b"hello ", b"world", b" again"]),
+      8,
+      uppercase_transform(),
+    );
+    let result = collect_body_bytes(body).await.unwrap();
+    assert_eq!(result, b"hello world again");
+  }
+}
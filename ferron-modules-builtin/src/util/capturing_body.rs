@@ -0,0 +1,124 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+
+/// A captured prefix of a body
+#[derive(Default)]
+pub struct BodyCapture {
+  /// The captured data, truncated to the maximum captured body size
+  pub data: Vec<u8>,
+  /// The total size of the body received so far
+  pub size: u64,
+}
+
+impl BodyCapture {
+  /// Determines if the captured data is truncated
+  pub fn is_truncated(&self) -> bool {
+    self.size > self.data.len() as u64
+  }
+}
+
+/// A shared handle to a captured body prefix
+pub type SharedBodyCapture = Arc<Mutex<BodyCapture>>;
+
+pin_project! {
+  /// A `Body` that copies the data passing through it (up to the maximum captured body size) into a body capture.
+  /// The guard is dropped together with the body, which allows finalizing the capture after the body ends.
+  pub struct CapturingBody<B, G> {
+    #[pin]
+    inner: B,
+    capture: SharedBodyCapture,
+    max_size: usize,
+    guard: Option<G>,
+  }
+}
+
+impl<B, G> CapturingBody<B, G> {
+  /// Creates a capturing `Body`
+  pub fn new(inner: B, capture: SharedBodyCapture, max_size: usize, guard: Option<G>) -> Self {
+    Self {
+      inner,
+      capture,
+      max_size,
+      guard,
+    }
+  }
+}
+
+impl<B, G> Body for CapturingBody<B, G>
+where
+  B: Body<Data = Bytes>,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    let result = this.inner.poll_frame(cx);
+    match &result {
+      Poll::Ready(Some(Ok(frame))) => {
+        if let Some(data) = frame.data_ref() {
+          let mut capture = this.capture.lock().unwrap_or_else(|e| e.into_inner());
+          let remaining = this.max_size.saturating_sub(capture.data.len());
+          capture.data.extend_from_slice(&data[..data.len().min(remaining)]);
+          capture.size += data.len() as u64;
+        }
+      }
+      Poll::Ready(None) | Poll::Ready(Some(Err(_))) => {
+        // The body has ended, so the capture can be finalized
+        this.guard.take();
+      }
+      Poll::Pending => (),
+    }
+    result
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use http_body_util::{BodyExt, Full};
+
+  struct FlagGuard(Arc<Mutex<bool>>);
+
+  impl Drop for FlagGuard {
+    fn drop(&mut self) {
+      *self.0.lock().unwrap() = true;
+    }
+  }
+
+  #[tokio::test]
+  async fn test_capturing_body_truncates_captured_data() {
+    let capture = SharedBodyCapture::default();
+    let dropped = Arc::new(Mutex::new(false));
+    let body = CapturingBody::new(
+      Full::new(Bytes::from_static(b"Hello, world!")),
+      capture.clone(),
+      5,
+      Some(FlagGuard(dropped.clone())),
+    );
+
+    // The data passes through the body unchanged
+    let collected = body.collect().await.unwrap().to_bytes();
+    assert_eq!(collected.as_ref(), b"Hello, world!");
+
+    let capture = capture.lock().unwrap();
+    assert_eq!(capture.data, b"Hello");
+    assert_eq!(capture.size, 13);
+    assert!(capture.is_truncated());
+    assert!(*dropped.lock().unwrap());
+  }
+}
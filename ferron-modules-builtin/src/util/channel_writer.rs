@@ -0,0 +1,48 @@
+use std::io::{self, Write};
+
+use bytes::{Bytes, BytesMut};
+
+/// The size of chunks sent from the channel writer
+const CHANNEL_WRITER_CHUNK_SIZE: usize = 65536;
+
+/// A writer that sends the written data in chunks through a channel, blocking when the channel is full
+pub struct ChannelWriter {
+  sender: tokio::sync::mpsc::Sender<Result<Bytes, io::Error>>,
+  buffer: BytesMut,
+}
+
+impl ChannelWriter {
+  /// Creates a new channel writer
+  pub fn new(sender: tokio::sync::mpsc::Sender<Result<Bytes, io::Error>>) -> Self {
+    Self {
+      sender,
+      buffer: BytesMut::with_capacity(CHANNEL_WRITER_CHUNK_SIZE),
+    }
+  }
+
+  /// Sends the buffered data through the channel
+  fn send_buffer(&mut self) -> io::Result<()> {
+    if self.buffer.is_empty() {
+      return Ok(());
+    }
+    let chunk = self.buffer.split().freeze();
+    self
+      .sender
+      .blocking_send(Ok(chunk))
+      .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The channel receiver was closed"))
+  }
+}
+
+impl Write for ChannelWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(buf);
+    if self.buffer.len() >= CHANNEL_WRITER_CHUNK_SIZE {
+      self.send_buffer()?;
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.send_buffer()
+  }
+}
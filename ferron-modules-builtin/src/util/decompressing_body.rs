@@ -0,0 +1,152 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
+use bytes::Bytes;
+use futures_util::future::Either;
+use futures_util::{StreamExt, TryStreamExt};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use hyper::body::Frame;
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::util::SplitStreamByMapExt;
+
+const DECOMPRESSED_STREAM_READER_BUFFER_SIZE: usize = 16384;
+/// The minimum compressed body size used to calculate the expansion ratio, so that small bodies aren't rejected
+const MIN_EXPANSION_RATIO_BASE_SIZE: u64 = 1024;
+
+/// The content codings that can be decoded
+pub const DECODABLE_CONTENT_CODINGS: &str = "gzip, deflate, br, zstd";
+
+/// Checks if the content coding (from the `Content-Encoding` header) can be decoded.
+pub fn is_decodable_content_coding(content_coding: &str) -> bool {
+  matches!(
+    &*content_coding.trim().to_lowercase(),
+    "gzip" | "x-gzip" | "deflate" | "br" | "zstd"
+  )
+}
+
+/// Wraps the response body in a decoder for the specified content coding (from the `Content-Encoding` header).
+/// Returns the original body, if the content coding can't be decoded.
+pub fn decompress_body(body: BoxBody<Bytes, std::io::Error>, content_coding: &str) -> BoxBody<Bytes, std::io::Error> {
+  let content_coding = content_coding.trim().to_lowercase();
+  if !is_decodable_content_coding(&content_coding) {
+    return body;
+  }
+
+  let (data_stream, trailer_stream) = BodyStream::new(body).split_by_map(|f| match f {
+    Ok(frame) if frame.is_trailers() => Either::Right(Ok::<_, std::io::Error>(frame)),
+    Ok(frame) => match frame.into_data() {
+      Ok(data) => Either::Left(Ok(data)),
+      Err(frame) => Either::Right(Ok(frame)),
+    },
+    Err(err) => Either::Left(Err(err)),
+  });
+  let body_reader = StreamReader::new(data_stream);
+
+  let decoder: Pin<Box<dyn AsyncRead + Send + Sync>> = match &*content_coding {
+    "gzip" | "x-gzip" => Box::pin(GzipDecoder::new(body_reader)),
+    // The "deflate" content coding is the zlib format (RFC 9110, section 8.4.1.2)
+    "deflate" => Box::pin(ZlibDecoder::new(body_reader)),
+    "br" => Box::pin(BrotliDecoder::new(body_reader)),
+    _ => Box::pin(ZstdDecoder::new(body_reader)),
+  };
+  let reader_stream = ReaderStream::with_capacity(decoder, DECOMPRESSED_STREAM_READER_BUFFER_SIZE);
+  let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data).chain(trailer_stream));
+  BodyExt::boxed(stream_body)
+}
+
+/// Wraps the body in a decoder for the specified content coding (from the `Content-Encoding` header), like `decompress_body`.
+/// The decompressed body fails with an error, if its size exceeds the size of the compressed body multiplied by the maximum
+/// expansion ratio, which protects against decompression bombs.
+pub fn decompress_body_with_ratio_limit(
+  body: BoxBody<Bytes, std::io::Error>,
+  content_coding: &str,
+  max_expansion_ratio: u64,
+) -> BoxBody<Bytes, std::io::Error> {
+  let compressed_size = Arc::new(AtomicU64::new(0));
+  let compressed_size_clone = compressed_size.clone();
+  let body = body
+    .map_frame(move |frame| {
+      if let Some(data) = frame.data_ref() {
+        compressed_size_clone.fetch_add(data.len() as u64, Ordering::Relaxed);
+      }
+      frame
+    })
+    .boxed();
+
+  let mut decompressed_size: u64 = 0;
+  let decompressed_stream = BodyStream::new(decompress_body(body, content_coding)).map(move |frame| {
+    let frame = frame?;
+    if let Some(data) = frame.data_ref() {
+      decompressed_size += data.len() as u64;
+      let compressed_size = compressed_size
+        .load(Ordering::Relaxed)
+        .max(MIN_EXPANSION_RATIO_BASE_SIZE);
+      if decompressed_size > compressed_size.saturating_mul(max_expansion_ratio) {
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::InvalidData,
+          "The decompressed body exceeds the maximum expansion ratio",
+        ));
+      }
+    }
+    Ok(frame)
+  });
+  BodyExt::boxed(StreamBody::new(decompressed_stream))
+}
+
+#[cfg(test)]
+mod tests {
+  use async_compression::tokio::bufread::{GzipEncoder, ZstdEncoder};
+  use http_body_util::Full;
+  use tokio::io::AsyncReadExt;
+
+  use super::*;
+
+  async fn decompress_bytes(data: Vec<u8>, content_coding: &str) -> Bytes {
+    let body = Full::new(Bytes::from(data)).map_err(|e| match e {}).boxed();
+    decompress_body(body, content_coding)
+      .collect()
+      .await
+      .unwrap()
+      .to_bytes()
+  }
+
+  #[tokio::test]
+  async fn test_decompress_body() {
+    let data = b"Hello, world! Hello, world! Hello, world!";
+
+    let mut gzip_data = Vec::new();
+    GzipEncoder::new(&data[..]).read_to_end(&mut gzip_data).await.unwrap();
+    assert_eq!(decompress_bytes(gzip_data, "gzip").await, &data[..]);
+
+    let mut zstd_data = Vec::new();
+    ZstdEncoder::new(&data[..]).read_to_end(&mut zstd_data).await.unwrap();
+    assert_eq!(decompress_bytes(zstd_data, " ZSTD ").await, &data[..]);
+  }
+
+  #[tokio::test]
+  async fn test_decompress_body_with_ratio_limit() {
+    let data = vec![b'a'; 1048576];
+    let mut gzip_data = Vec::new();
+    GzipEncoder::new(&data[..]).read_to_end(&mut gzip_data).await.unwrap();
+
+    let decompress = |max_expansion_ratio| {
+      let body = Full::new(Bytes::from(gzip_data.clone()))
+        .map_err(|e| match e {})
+        .boxed();
+      decompress_body_with_ratio_limit(body, "gzip", max_expansion_ratio).collect()
+    };
+    assert_eq!(decompress(1024).await.unwrap().to_bytes(), &data[..]);
+    assert!(decompress(100).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_decompress_body_unsupported_coding() {
+    assert!(!is_decodable_content_coding("gzip, br"));
+    assert_eq!(decompress_bytes(b"data".to_vec(), "compress").await, &b"data"[..]);
+  }
+}
@@ -0,0 +1,374 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+/// The maximum number of HTTP Digest authentication nonces, for which the highest used nonce count is tracked
+const MAX_TRACKED_NONCES: usize = 65536;
+
+/// Credentials from the HTTP "Authorization" header for HTTP Digest authentication
+pub struct DigestCredentials {
+  pub username: String,
+  pub realm: String,
+  pub nonce: String,
+  pub uri: String,
+  pub response: String,
+  pub algorithm: Option<String>,
+  pub qop: Option<String>,
+  pub nc: Option<String>,
+  pub cnonce: Option<String>,
+  pub userhash: bool,
+}
+
+/// The validity of a HTTP Digest authentication nonce
+#[derive(Debug, PartialEq, Eq)]
+pub enum DigestNonceValidity {
+  /// The nonce was generated by the server and isn't expired
+  Valid,
+  /// The nonce was generated by the server, but it's expired
+  Stale,
+  /// The nonce wasn't generated by the server
+  Invalid,
+}
+
+/// A generator of HTTP Digest authentication nonces, bound to a secret and a realm.
+/// The nonces are stateless, while the highest nonce counts of the used nonces are tracked to reject replayed requests.
+pub struct DigestNonces {
+  secret: [u8; 32],
+  max_age: Duration,
+  max_tracked_nonces: usize,
+  nonce_counts: Mutex<NonceCounts>,
+}
+
+/// The highest nonce counts of the used nonces
+#[derive(Default)]
+struct NonceCounts {
+  /// The highest nonce counts, keyed by the nonces
+  counts: HashMap<String, u32>,
+  /// The timestamps and the nonces, in the order of the first use
+  order: VecDeque<(u64, String)>,
+  /// The timestamp, at and before which the nonces aren't tracked anymore, since they were evicted
+  evicted_until: u64,
+}
+
+impl DigestNonces {
+  /// Creates a new nonce generator with a random secret
+  pub fn new(max_age: Duration) -> Self {
+    Self {
+      secret: rand::random(),
+      max_age,
+      max_tracked_nonces: MAX_TRACKED_NONCES,
+      nonce_counts: Mutex::new(NonceCounts::default()),
+    }
+  }
+
+  /// Calculates the nonce signature for a timestamp, a random value and a realm
+  fn signature(&self, timestamp: u64, random: u64, realm: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(self.secret);
+    hasher.update(timestamp.to_be_bytes());
+    hasher.update(random.to_be_bytes());
+    hasher.update(realm.as_bytes());
+    hex_encode(&hasher.finalize())
+  }
+
+  /// Generates a new nonce for the realm
+  pub fn generate(&self, realm: &str) -> String {
+    let timestamp = unix_timestamp();
+    let random: u64 = rand::random();
+    format!(
+      "{timestamp:016x}{random:016x}{}",
+      self.signature(timestamp, random, realm)
+    )
+  }
+
+  /// Validates the nonce for the realm
+  pub fn validate(&self, nonce: &str, realm: &str) -> DigestNonceValidity {
+    let Some(timestamp) = nonce_timestamp(nonce) else {
+      return DigestNonceValidity::Invalid;
+    };
+    let Some(random) = nonce
+      .get(16..32)
+      .and_then(|random_hex| u64::from_str_radix(random_hex, 16).ok())
+    else {
+      return DigestNonceValidity::Invalid;
+    };
+    let signature = &nonce[32..];
+    if !constant_time_eq(
+      signature.as_bytes(),
+      self.signature(timestamp, random, realm).as_bytes(),
+    ) {
+      return DigestNonceValidity::Invalid;
+    }
+    if unix_timestamp().saturating_sub(timestamp) > self.max_age.as_secs() {
+      DigestNonceValidity::Stale
+    } else {
+      DigestNonceValidity::Valid
+    }
+  }
+
+  /// Records the nonce count of a verified request with a valid nonce. The nonce count is invalid,
+  /// if it isn't greater than the highest nonce count already used with the nonce (the request was replayed).
+  /// The nonce is stale, if it was evicted from the tracked nonces, so the client should retry with a new nonce.
+  pub fn use_nonce_count(&self, nonce: &str, nc: &str) -> DigestNonceValidity {
+    let (Some(timestamp), Ok(nc)) = (nonce_timestamp(nonce), u32::from_str_radix(nc, 16)) else {
+      return DigestNonceValidity::Invalid;
+    };
+    let mut nonce_counts = self.nonce_counts.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Stop tracking the expired nonces
+    let expired_until = unix_timestamp().saturating_sub(self.max_age.as_secs());
+    while nonce_counts
+      .order
+      .front()
+      .is_some_and(|(timestamp, _)| *timestamp < expired_until)
+    {
+      if let Some((_, nonce)) = nonce_counts.order.pop_front() {
+        nonce_counts.counts.remove(&nonce);
+      }
+    }
+
+    if let Some(highest_nc) = nonce_counts.counts.get_mut(nonce) {
+      if nc <= *highest_nc {
+        return DigestNonceValidity::Invalid;
+      }
+      *highest_nc = nc;
+      return DigestNonceValidity::Valid;
+    } else if timestamp <= nonce_counts.evicted_until {
+      return DigestNonceValidity::Stale;
+    }
+
+    nonce_counts.counts.insert(nonce.to_string(), nc);
+    nonce_counts.order.push_back((timestamp, nonce.to_string()));
+    while nonce_counts.order.len() > self.max_tracked_nonces {
+      if let Some((timestamp, nonce)) = nonce_counts.order.pop_front() {
+        nonce_counts.counts.remove(&nonce);
+        nonce_counts.evicted_until = nonce_counts.evicted_until.max(timestamp);
+      }
+    }
+    DigestNonceValidity::Valid
+  }
+}
+
+/// Obtains the current UNIX timestamp in seconds
+fn unix_timestamp() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+/// Parses the timestamp, at which the nonce was generated
+fn nonce_timestamp(nonce: &str) -> Option<u64> {
+  nonce
+    .get(..16)
+    .and_then(|timestamp_hex| u64::from_str_radix(timestamp_hex, 16).ok())
+}
+
+/// Compares two byte strings in time independent of their contents
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  let difference = a.iter().zip(b).fold(0u8, |difference, (x, y)| difference | (x ^ y));
+  std::hint::black_box(difference) == 0
+}
+
+/// Encodes bytes as a lowercase hexadecimal string
+fn hex_encode(data: &[u8]) -> String {
+  data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Calculates the SHA-256 hash of the data as a lowercase hexadecimal string
+pub fn sha256_hex(data: &str) -> String {
+  hex_encode(&Sha256::digest(data.as_bytes()))
+}
+
+/// Calculates the expected HTTP Digest authentication response (RFC 7616, SHA-256 algorithm, "auth" QoP)
+pub fn compute_digest_response(ha1: &str, nonce: &str, nc: &str, cnonce: &str, method: &str, uri: &str) -> String {
+  let ha2 = sha256_hex(&format!("{method}:{uri}"));
+  sha256_hex(&format!("{ha1}:{nonce}:{nc}:{cnonce}:auth:{ha2}"))
+}
+
+/// Parses the HTTP "Authorization" header for HTTP Digest authentication
+pub fn parse_digest_auth(auth_str: &str) -> Option<DigestCredentials> {
+  let (scheme, params_str) = auth_str.trim().split_once(' ')?;
+  if !scheme.eq_ignore_ascii_case("digest") {
+    return None;
+  }
+
+  let mut username = None;
+  let mut realm = None;
+  let mut nonce = None;
+  let mut uri = None;
+  let mut response = None;
+  let mut algorithm = None;
+  let mut qop = None;
+  let mut nc = None;
+  let mut cnonce = None;
+  let mut userhash = false;
+
+  let mut chars = params_str.chars().peekable();
+  loop {
+    while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+    if chars.peek().is_none() {
+      break;
+    }
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| *c != '=' && *c != ',') {
+      name.push(c);
+    }
+    if chars.next() != Some('=') {
+      return None;
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    let mut value = String::new();
+    if chars.next_if_eq(&'"').is_some() {
+      loop {
+        match chars.next()? {
+          '"' => break,
+          '\\' => value.push(chars.next()?),
+          c => value.push(c),
+        }
+      }
+    } else {
+      while let Some(c) = chars.next_if(|c| *c != ',') {
+        value.push(c);
+      }
+      value = value.trim_end().to_string();
+    }
+
+    match name.trim().to_ascii_lowercase().as_str() {
+      "username" => username = Some(value),
+      "realm" => realm = Some(value),
+      "nonce" => nonce = Some(value),
+      "uri" => uri = Some(value),
+      "response" => response = Some(value),
+      "algorithm" => algorithm = Some(value),
+      "qop" => qop = Some(value),
+      "nc" => nc = Some(value),
+      "cnonce" => cnonce = Some(value),
+      "userhash" => userhash = value.eq_ignore_ascii_case("true"),
+      _ => (),
+    }
+  }
+
+  Some(DigestCredentials {
+    username: username?,
+    realm: realm?,
+    nonce: nonce?,
+    uri: uri?,
+    response: response?,
+    algorithm,
+    qop,
+    nc,
+    cnonce,
+    userhash,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_digest_auth() {
+    let credentials = parse_digest_auth(
+      "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", uri=\"/dir/index.html\", \
+       algorithm=SHA-256, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", nc=00000001, \
+       cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", qop=auth, \
+       response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\", \
+       opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+    )
+    .unwrap();
+    assert_eq!(credentials.username, "Mufasa");
+    assert_eq!(credentials.realm, "http-auth@example.org");
+    assert_eq!(credentials.uri, "/dir/index.html");
+    assert_eq!(credentials.algorithm.as_deref(), Some("SHA-256"));
+    assert_eq!(credentials.nc.as_deref(), Some("00000001"));
+    assert_eq!(credentials.qop.as_deref(), Some("auth"));
+    assert!(!credentials.userhash);
+
+    assert!(parse_digest_auth("Basic dXNlcjpwYXNz").is_none());
+    assert!(parse_digest_auth("Digest username=\"unterminated").is_none());
+  }
+
+  #[test]
+  fn test_compute_digest_response() {
+    // Example from RFC 7616, section 3.9.1
+    let ha1 = sha256_hex("Mufasa:http-auth@example.org:Circle of Life");
+    assert_eq!(
+      compute_digest_response(
+        &ha1,
+        "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v",
+        "00000001",
+        "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        "GET",
+        "/dir/index.html"
+      ),
+      "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+    );
+  }
+
+  #[test]
+  fn test_digest_nonces() {
+    let nonces = DigestNonces::new(Duration::from_secs(300));
+    let nonce = nonces.generate("realm");
+    assert_eq!(nonces.validate(&nonce, "realm"), DigestNonceValidity::Valid);
+    assert_eq!(nonces.validate(&nonce, "another realm"), DigestNonceValidity::Invalid);
+    assert_eq!(
+      nonces.validate("0000000000000000abc", "realm"),
+      DigestNonceValidity::Invalid
+    );
+
+    let expired_nonces = DigestNonces::new(Duration::ZERO);
+    let expired_nonce = format!("{:016x}{:016x}{}", 1, 2, expired_nonces.signature(1, 2, "realm"));
+    assert_eq!(
+      expired_nonces.validate(&expired_nonce, "realm"),
+      DigestNonceValidity::Stale
+    );
+  }
+
+  #[test]
+  fn test_digest_nonce_counts() {
+    let nonces = DigestNonces::new(Duration::from_secs(300));
+    let nonce = nonces.generate("realm");
+    assert_ne!(nonces.generate("realm"), nonce);
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000001"), DigestNonceValidity::Valid);
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000001"), DigestNonceValidity::Invalid);
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000003"), DigestNonceValidity::Valid);
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000002"), DigestNonceValidity::Invalid);
+    assert_eq!(nonces.use_nonce_count(&nonce, "0000000x"), DigestNonceValidity::Invalid);
+
+    // Another nonce has its own nonce count
+    let another_nonce = nonces.generate("realm");
+    assert_eq!(
+      nonces.use_nonce_count(&another_nonce, "00000001"),
+      DigestNonceValidity::Valid
+    );
+  }
+
+  #[test]
+  fn test_digest_nonce_counts_eviction() {
+    let mut nonces = DigestNonces::new(Duration::from_secs(300));
+    nonces.max_tracked_nonces = 1;
+    let nonce = nonces.generate("realm");
+    let another_nonce = nonces.generate("realm");
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000001"), DigestNonceValidity::Valid);
+    assert_eq!(
+      nonces.use_nonce_count(&another_nonce, "00000001"),
+      DigestNonceValidity::Valid
+    );
+
+    // The evicted nonce can't be replayed
+    assert_eq!(nonces.use_nonce_count(&nonce, "00000001"), DigestNonceValidity::Stale);
+  }
+
+  #[test]
+  fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"password", b"password"));
+    assert!(!constant_time_eq(b"password", b"passwore"));
+    assert!(!constant_time_eq(b"password", b"pass"));
+  }
+}
@@ -0,0 +1,421 @@
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+
+/// The maximum number of entries in a directory archive (the ZIP format without ZIP64 extensions is limited to 65535 entries)
+pub const DIRECTORY_ARCHIVE_MAX_ENTRIES: usize = 65535;
+
+/// The maximum total size of files in a ZIP archive (the ZIP format without ZIP64 extensions is limited to 4 GiB)
+pub const ZIP_ARCHIVE_MAX_SIZE: u64 = 0xF000_0000;
+
+/// The maximum depth of directories included in a directory archive
+const DIRECTORY_ARCHIVE_MAX_DEPTH: usize = 32;
+
+/// The size of the buffer used to copy file contents into the archive
+const ARCHIVE_CHUNK_SIZE: usize = 65536;
+
+/// A directory archive format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+  Zip,
+  TarGz,
+}
+
+impl ArchiveFormat {
+  /// Parses the archive format from the `download` query parameter value
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "zip" => Some(Self::Zip),
+      "tar.gz" | "tgz" => Some(Self::TarGz),
+      _ => None,
+    }
+  }
+
+  /// Obtains the MIME type of the archive
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      Self::Zip => "application/zip",
+      Self::TarGz => "application/gzip",
+    }
+  }
+
+  /// Obtains the file extension of the archive
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Self::Zip => "zip",
+      Self::TarGz => "tar.gz",
+    }
+  }
+}
+
+/// An entry of a directory archive
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+  /// The path inside the archive, with directories ending with `/`
+  pub archive_path: String,
+  /// The path of the file in the file system, or `None` for directories
+  pub file_path: Option<PathBuf>,
+  /// The size of the file
+  pub size: u64,
+  /// The modification time of the file
+  pub modified: SystemTime,
+}
+
+/// Collects entries of a directory archive. Symbolic links are skipped, and the paths inside the archive
+/// are matched against the include and exclude glob patterns. Returns `None` if the archive would exceed the size limit.
+pub fn collect_archive_entries(
+  root: &Path,
+  include: &[glob::Pattern],
+  exclude: &[glob::Pattern],
+  max_size: Option<u64>,
+) -> Result<Option<Vec<ArchiveEntry>>, io::Error> {
+  let mut entries = Vec::new();
+  let mut total_size = 0;
+  let mut directories = vec![(root.to_path_buf(), String::new(), 0)];
+  while let Some((directory, archive_directory, depth)) = directories.pop() {
+    let mut directory_entries = std::fs::read_dir(&directory)?.collect::<Result<Vec<_>, _>>()?;
+    directory_entries.sort_by_key(|entry| entry.file_name());
+    for entry in directory_entries {
+      let file_type = entry.file_type()?;
+      let Ok(file_name) = entry.file_name().into_string() else {
+        continue;
+      };
+      let archive_path = format!("{archive_directory}{file_name}");
+      if exclude.iter().any(|pattern| pattern.matches(&archive_path)) {
+        continue;
+      }
+      if file_type.is_dir() {
+        if depth < DIRECTORY_ARCHIVE_MAX_DEPTH {
+          let metadata = entry.metadata()?;
+          entries.push(ArchiveEntry {
+            archive_path: format!("{archive_path}/"),
+            file_path: None,
+            size: 0,
+            modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+          });
+          directories.push((entry.path(), format!("{archive_path}/"), depth + 1));
+        }
+      } else if file_type.is_file() {
+        if !include.is_empty() && !include.iter().any(|pattern| pattern.matches(&archive_path)) {
+          continue;
+        }
+        let metadata = entry.metadata()?;
+        total_size += metadata.len();
+        if max_size.is_some_and(|max_size| total_size > max_size) {
+          return Ok(None);
+        }
+        entries.push(ArchiveEntry {
+          archive_path,
+          file_path: Some(entry.path()),
+          size: metadata.len(),
+          modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+        });
+      }
+      if entries.len() > DIRECTORY_ARCHIVE_MAX_ENTRIES {
+        return Ok(None);
+      }
+    }
+  }
+  Ok(Some(entries))
+}
+
+/// Writes a directory archive with the entries into the writer
+pub fn write_archive(format: ArchiveFormat, entries: &[ArchiveEntry], writer: impl Write) -> Result<(), io::Error> {
+  match format {
+    ArchiveFormat::Zip => write_zip_archive(entries, writer),
+    ArchiveFormat::TarGz => {
+      let mut encoder = GzEncoder::new(writer, Compression::fast());
+      write_tar_archive(entries, &mut encoder)?;
+      encoder.finish()?.flush()
+    }
+  }
+}
+
+/// Copies exactly `size` bytes of the file into the writer, padding with zeros if the file was truncated in the meantime
+fn copy_file_contents(
+  file_path: &Path,
+  size: u64,
+  writer: &mut impl Write,
+  mut crc: Option<&mut Crc>,
+) -> io::Result<()> {
+  let file = std::fs::File::open(file_path)?;
+  let mut reader = file.take(size);
+  let mut buffer = vec![0u8; ARCHIVE_CHUNK_SIZE];
+  let mut copied = 0;
+  loop {
+    let read = match reader.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(read) => read,
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(err),
+    };
+    if let Some(crc) = crc.as_deref_mut() {
+      crc.update(&buffer[..read]);
+    }
+    writer.write_all(&buffer[..read])?;
+    copied += read as u64;
+  }
+  let zeros = [0u8; 512];
+  while copied < size {
+    let padding = (size - copied).min(zeros.len() as u64) as usize;
+    if let Some(crc) = crc.as_deref_mut() {
+      crc.update(&zeros[..padding]);
+    }
+    writer.write_all(&zeros[..padding])?;
+    copied += padding as u64;
+  }
+  Ok(())
+}
+
+/// Writes an octal number into the tar header field, terminated with a NUL byte
+fn write_tar_octal(field: &mut [u8], value: u64) {
+  let digits = field.len() - 1;
+  let formatted = format!("{value:0digits$o}");
+  field[..digits].copy_from_slice(&formatted.as_bytes()[formatted.len() - digits..]);
+  field[digits] = 0;
+}
+
+/// Constructs a ustar header block
+pub fn construct_tar_header(name: &[u8], size: u64, modified: u64, type_flag: u8) -> [u8; 512] {
+  let mut header = [0u8; 512];
+  let name_length = name.len().min(100);
+  header[..name_length].copy_from_slice(&name[..name_length]);
+  write_tar_octal(&mut header[100..108], if type_flag == b'5' { 0o755 } else { 0o644 });
+  write_tar_octal(&mut header[108..116], 0);
+  write_tar_octal(&mut header[116..124], 0);
+  if size < 0o77777777777 {
+    write_tar_octal(&mut header[124..136], size);
+  } else {
+    // GNU base-256 encoding for large files
+    header[124] = 0x80;
+    header[128..136].copy_from_slice(&size.to_be_bytes());
+  }
+  write_tar_octal(&mut header[136..148], modified.min(0o77777777777));
+  header[148..156].fill(b' ');
+  header[156] = type_flag;
+  header[257..263].copy_from_slice(b"ustar\0");
+  header[263..265].copy_from_slice(b"00");
+  let checksum = header.iter().map(|byte| *byte as u64).sum::<u64>();
+  write_tar_octal(&mut header[148..155], checksum);
+  header
+}
+
+/// Writes the padding after the tar entry data
+fn write_tar_padding(size: u64, writer: &mut impl Write) -> io::Result<()> {
+  let remainder = (size % 512) as usize;
+  if remainder != 0 {
+    writer.write_all(&[0u8; 512][..512 - remainder])?;
+  }
+  Ok(())
+}
+
+/// Writes a tar archive with the entries into the writer
+fn write_tar_archive(entries: &[ArchiveEntry], writer: &mut impl Write) -> io::Result<()> {
+  for entry in entries {
+    let name = entry.archive_path.as_bytes();
+    let modified = entry
+      .modified
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_secs());
+    let type_flag = if entry.file_path.is_some() { b'0' } else { b'5' };
+    if name.len() > 100 {
+      // GNU long name extension for paths longer than 100 bytes
+      let long_name_size = name.len() as u64 + 1;
+      writer.write_all(&construct_tar_header(b"././@LongLink", long_name_size, 0, b'L'))?;
+      writer.write_all(name)?;
+      writer.write_all(&[0])?;
+      write_tar_padding(long_name_size, writer)?;
+    }
+    writer.write_all(&construct_tar_header(name, entry.size, modified, type_flag))?;
+    if let Some(file_path) = &entry.file_path {
+      copy_file_contents(file_path, entry.size, writer, None)?;
+      write_tar_padding(entry.size, writer)?;
+    }
+  }
+  // The end of the archive is marked with two zero blocks
+  writer.write_all(&[0u8; 1024])
+}
+
+/// A writer that counts the written bytes
+struct CountingWriter<W: Write> {
+  inner: W,
+  count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let written = self.inner.write(buf)?;
+    self.count += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+/// Converts the modification time into the MS-DOS time and date
+fn to_dos_time_date(modified: SystemTime) -> (u16, u16) {
+  let modified = DateTime::<Local>::from(modified);
+  if modified.year() < 1980 {
+    // The MS-DOS date can't represent dates before 1980
+    return (0, (1 << 5) | 1);
+  }
+  let time = ((modified.hour() as u16) << 11) | ((modified.minute() as u16) << 5) | (modified.second() as u16 / 2);
+  let date =
+    (((modified.year() - 1980).min(127) as u16) << 9) | ((modified.month() as u16) << 5) | (modified.day() as u16);
+  (time, date)
+}
+
+/// Writes a ZIP archive with the entries into the writer. File contents are compressed using Deflate,
+/// and the CRC-32 checksums and sizes are written in data descriptors after the file contents.
+fn write_zip_archive(entries: &[ArchiveEntry], writer: impl Write) -> io::Result<()> {
+  let mut writer = CountingWriter {
+    inner: writer,
+    count: 0,
+  };
+  let mut central_directory = Vec::new();
+  for entry in entries {
+    let name = entry.archive_path.as_bytes();
+    let (time, date) = to_dos_time_date(entry.modified);
+    let is_file = entry.file_path.is_some();
+    // Bit 3 - data descriptor, bit 11 - UTF-8 file names
+    let flags: u16 = if is_file { 0x0808 } else { 0x0800 };
+    let method: u16 = if is_file { 8 } else { 0 };
+    let local_header_offset =
+      u32::try_from(writer.count).map_err(|_| io::Error::other("The ZIP archive exceeds the maximum size"))?;
+
+    let mut local_header = Vec::with_capacity(30 + name.len());
+    local_header.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    local_header.extend_from_slice(&20u16.to_le_bytes());
+    local_header.extend_from_slice(&flags.to_le_bytes());
+    local_header.extend_from_slice(&method.to_le_bytes());
+    local_header.extend_from_slice(&time.to_le_bytes());
+    local_header.extend_from_slice(&date.to_le_bytes());
+    local_header.extend_from_slice(&[0u8; 12]); // CRC-32 and sizes are in the data descriptor
+    local_header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    local_header.extend_from_slice(&0u16.to_le_bytes());
+    local_header.extend_from_slice(name);
+    writer.write_all(&local_header)?;
+
+    let (crc_sum, compressed_size, uncompressed_size) = match &entry.file_path {
+      Some(file_path) => {
+        let mut crc = Crc::new();
+        let data_start = writer.count;
+        let mut encoder = DeflateEncoder::new(&mut writer, Compression::fast());
+        copy_file_contents(file_path, entry.size, &mut encoder, Some(&mut crc))?;
+        encoder.finish()?;
+        let compressed_size = writer.count - data_start;
+        let compressed_size =
+          u32::try_from(compressed_size).map_err(|_| io::Error::other("The ZIP archive exceeds the maximum size"))?;
+        let uncompressed_size =
+          u32::try_from(entry.size).map_err(|_| io::Error::other("The ZIP archive exceeds the maximum size"))?;
+
+        let mut data_descriptor = Vec::with_capacity(16);
+        data_descriptor.extend_from_slice(&0x08074b50u32.to_le_bytes());
+        data_descriptor.extend_from_slice(&crc.sum().to_le_bytes());
+        data_descriptor.extend_from_slice(&compressed_size.to_le_bytes());
+        data_descriptor.extend_from_slice(&uncompressed_size.to_le_bytes());
+        writer.write_all(&data_descriptor)?;
+
+        (crc.sum(), compressed_size, uncompressed_size)
+      }
+      None => (0, 0, 0),
+    };
+
+    let external_attributes: u32 = if is_file {
+      0o100644 << 16
+    } else {
+      (0o40755 << 16) | 0x10
+    };
+    central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    central_directory.extend_from_slice(&0x0314u16.to_le_bytes()); // Made by Unix, version 2.0
+    central_directory.extend_from_slice(&20u16.to_le_bytes());
+    central_directory.extend_from_slice(&flags.to_le_bytes());
+    central_directory.extend_from_slice(&method.to_le_bytes());
+    central_directory.extend_from_slice(&time.to_le_bytes());
+    central_directory.extend_from_slice(&date.to_le_bytes());
+    central_directory.extend_from_slice(&crc_sum.to_le_bytes());
+    central_directory.extend_from_slice(&compressed_size.to_le_bytes());
+    central_directory.extend_from_slice(&uncompressed_size.to_le_bytes());
+    central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    central_directory.extend_from_slice(&[0u8; 8]); // Extra field length, comment length, disk number, internal attributes
+    central_directory.extend_from_slice(&external_attributes.to_le_bytes());
+    central_directory.extend_from_slice(&local_header_offset.to_le_bytes());
+    central_directory.extend_from_slice(name);
+  }
+
+  let central_directory_offset =
+    u32::try_from(writer.count).map_err(|_| io::Error::other("The ZIP archive exceeds the maximum size"))?;
+  let entry_count =
+    u16::try_from(entries.len()).map_err(|_| io::Error::other("The ZIP archive has too many entries"))?;
+  writer.write_all(&central_directory)?;
+
+  let mut end_of_central_directory = Vec::with_capacity(22);
+  end_of_central_directory.extend_from_slice(&0x06054b50u32.to_le_bytes());
+  end_of_central_directory.extend_from_slice(&[0u8; 4]); // Disk numbers
+  end_of_central_directory.extend_from_slice(&entry_count.to_le_bytes());
+  end_of_central_directory.extend_from_slice(&entry_count.to_le_bytes());
+  end_of_central_directory.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+  end_of_central_directory.extend_from_slice(&central_directory_offset.to_le_bytes());
+  end_of_central_directory.extend_from_slice(&0u16.to_le_bytes());
+  writer.write_all(&end_of_central_directory)?;
+  writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_construct_tar_header() {
+    let header = construct_tar_header(b"docs/readme.txt", 1234, 1700000000, b'0');
+    assert_eq!(&header[..15], b"docs/readme.txt");
+    assert_eq!(&header[124..136], b"00000002322\0");
+    assert_eq!(&header[257..263], b"ustar\0");
+
+    // The checksum is calculated with the checksum field filled with spaces
+    let mut header_for_checksum = header;
+    header_for_checksum[148..156].fill(b' ');
+    let checksum = header_for_checksum.iter().map(|byte| *byte as u64).sum::<u64>();
+    assert_eq!(&header[148..155], format!("{checksum:06o}\0").as_bytes());
+  }
+
+  #[test]
+  fn test_write_tar_archive() {
+    let entries = vec![ArchiveEntry {
+      archive_path: "empty/".to_string(),
+      file_path: None,
+      size: 0,
+      modified: UNIX_EPOCH,
+    }];
+    let mut archive = Vec::new();
+    write_tar_archive(&entries, &mut archive).unwrap();
+    assert_eq!(archive.len(), 512 + 1024);
+    assert_eq!(&archive[..6], b"empty/");
+    assert_eq!(archive[156], b'5');
+    assert!(archive[512..].iter().all(|byte| *byte == 0));
+  }
+
+  #[test]
+  fn test_write_zip_archive() {
+    let entries = vec![ArchiveEntry {
+      archive_path: "empty/".to_string(),
+      file_path: None,
+      size: 0,
+      modified: UNIX_EPOCH,
+    }];
+    let mut archive = Vec::new();
+    write_zip_archive(&entries, &mut archive).unwrap();
+    assert_eq!(&archive[..4], &0x04034b50u32.to_le_bytes());
+    let end_of_central_directory = &archive[archive.len() - 22..];
+    assert_eq!(&end_of_central_directory[..4], &0x06054b50u32.to_le_bytes());
+    assert_eq!(&end_of_central_directory[8..10], &1u16.to_le_bytes());
+    // The central directory starts right after the local header
+    assert_eq!(&end_of_central_directory[16..20], &(30u32 + 6).to_le_bytes());
+  }
+}
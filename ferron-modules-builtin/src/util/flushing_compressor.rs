@@ -0,0 +1,204 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_compression::tokio::write::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder};
+use bytes::Bytes;
+use futures_util::FutureExt;
+use hyper::body::{Body, Frame};
+use hyper::HeaderMap;
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// A timer for flushing the compressor
+#[cfg(feature = "runtime-tokio")]
+type FlushTimer = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
+
+/// A timer for flushing the compressor
+#[cfg(not(feature = "runtime-tokio"))]
+type FlushTimer = send_wrapper::SendWrapper<Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Creates a timer for flushing the compressor
+#[cfg(feature = "runtime-tokio")]
+fn new_flush_timer(flush_interval: Duration) -> FlushTimer {
+  Box::pin(ferron_common::runtime::sleep(flush_interval))
+}
+
+/// Creates a timer for flushing the compressor
+#[cfg(not(feature = "runtime-tokio"))]
+fn new_flush_timer(flush_interval: Duration) -> FlushTimer {
+  send_wrapper::SendWrapper::new(
+    Box::pin(ferron_common::runtime::sleep(flush_interval)) as Pin<Box<dyn Future<Output = ()>>>
+  )
+}
+
+/// A compressor writing the compressed data into memory, which can be flushed in the middle of the stream
+pub trait FlushableEncoder: AsyncWrite + Unpin {
+  /// Takes the compressed data written so far
+  fn take_output(&mut self) -> Bytes;
+}
+
+macro_rules! impl_flushable_encoder {
+  ($($encoder:ident),*) => {
+    $(
+      impl FlushableEncoder for $encoder<Vec<u8>> {
+        fn take_output(&mut self) -> Bytes {
+          Bytes::from(std::mem::take(self.get_mut()))
+        }
+      }
+    )*
+  };
+}
+
+impl_flushable_encoder!(BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder);
+
+/// Runs a compressor operation. The compressor writes into memory, so the operation completes without waiting.
+fn run_encoder_operation(operation: impl Future<Output = Result<(), std::io::Error>>) -> Result<(), std::io::Error> {
+  operation
+    .now_or_never()
+    .unwrap_or_else(|| Err(std::io::Error::other("The compressor operation didn't complete")))
+}
+
+pin_project! {
+  /// A compressed `Body`, which compressor is flushed after the flush interval elapses since the data was received,
+  /// so that the data isn't held in the compressor while waiting for more data
+  pub struct FlushingCompressedBody<B, E> {
+    #[pin]
+    inner: B,
+    encoder: E,
+    flush_interval: Duration,
+    flush_timer: Option<FlushTimer>,
+    trailers: Option<HeaderMap>,
+    finished: bool,
+  }
+}
+
+impl<B, E> FlushingCompressedBody<B, E>
+where
+  B: Body<Data = Bytes, Error = std::io::Error>,
+  E: FlushableEncoder,
+{
+  /// Creates a compressed `Body`. A zero flush interval means that the compressor is flushed after every received data frame.
+  pub fn new(inner: B, encoder: E, flush_interval: Duration) -> Self {
+    Self {
+      inner,
+      encoder,
+      flush_interval,
+      flush_timer: None,
+      trailers: None,
+      finished: false,
+    }
+  }
+}
+
+impl<B, E> Body for FlushingCompressedBody<B, E>
+where
+  B: Body<Data = Bytes, Error = std::io::Error>,
+  E: FlushableEncoder,
+{
+  type Data = Bytes;
+  type Error = std::io::Error;
+
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let mut this = self.project();
+    loop {
+      if *this.finished {
+        return Poll::Ready(this.trailers.take().map(|trailers| Ok(Frame::trailers(trailers))));
+      }
+
+      let inner_pending = match this.inner.as_mut().poll_frame(cx) {
+        Poll::Ready(Some(Ok(frame))) => {
+          match frame.into_data() {
+            Ok(data) => {
+              if let Err(err) = run_encoder_operation(this.encoder.write_all(&data)) {
+                return Poll::Ready(Some(Err(err)));
+              }
+              if this.flush_interval.is_zero() {
+                if let Err(err) = run_encoder_operation(this.encoder.flush()) {
+                  return Poll::Ready(Some(Err(err)));
+                }
+              } else if this.flush_timer.is_none() {
+                *this.flush_timer = Some(new_flush_timer(*this.flush_interval));
+              }
+            }
+            Err(frame) => {
+              // Trailers end the response body
+              *this.trailers = frame.into_trailers().ok();
+              *this.finished = true;
+            }
+          }
+          false
+        }
+        Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => {
+          *this.finished = true;
+          false
+        }
+        Poll::Pending => true,
+      };
+
+      if *this.finished {
+        *this.flush_timer = None;
+        if let Err(err) = run_encoder_operation(this.encoder.shutdown()) {
+          return Poll::Ready(Some(Err(err)));
+        }
+      } else if this
+        .flush_timer
+        .as_mut()
+        .is_some_and(|flush_timer| flush_timer.as_mut().poll(cx).is_ready())
+      {
+        *this.flush_timer = None;
+        if let Err(err) = run_encoder_operation(this.encoder.flush()) {
+          return Poll::Ready(Some(Err(err)));
+        }
+      }
+
+      let output = this.encoder.take_output();
+      if !output.is_empty() {
+        return Poll::Ready(Some(Ok(Frame::data(output))));
+      } else if inner_pending {
+        return Poll::Pending;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::io::Write;
+
+  use http_body_util::{BodyExt, StreamBody};
+
+  #[tokio::test]
+  async fn test_immediate_flush() {
+    let chunks: Vec<Result<Frame<Bytes>, std::io::Error>> = vec![
+      Ok(Frame::data(Bytes::from_static(b"data: first\n\n"))),
+      Ok(Frame::data(Bytes::from_static(b"data: second\n\n"))),
+    ];
+    let mut body = FlushingCompressedBody::new(
+      StreamBody::new(futures_util::stream::iter(chunks)),
+      GzipEncoder::new(Vec::new()),
+      Duration::ZERO,
+    );
+
+    // Every received data frame can be decompressed without waiting for the end of the response body
+    let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+    let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    decoder.write_all(&frame).unwrap();
+    decoder.flush().unwrap();
+    assert_eq!(decoder.get_ref().as_slice(), b"data: first\n\n");
+
+    let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    decoder.write_all(&frame).unwrap();
+    decoder.flush().unwrap();
+    assert_eq!(decoder.get_ref().as_slice(), b"data: first\n\ndata: second\n\n");
+
+    while let Some(frame) = body.frame().await {
+      decoder.write_all(&frame.unwrap().into_data().unwrap()).unwrap();
+    }
+    assert_eq!(decoder.finish().unwrap(), b"data: first\n\ndata: second\n\n");
+  }
+}
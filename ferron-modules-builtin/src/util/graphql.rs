@@ -0,0 +1,612 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+/// The maximum nesting of selection sets, lists and objects accepted by the GraphQL parser,
+/// protecting the server against stack overflows
+const MAX_PARSER_NESTING: usize = 256;
+
+/// The arguments, whose integer values multiply the complexity of the selected fields (for paginated lists)
+const MULTIPLIER_ARGUMENTS: [&str; 3] = ["first", "last", "limit"];
+
+/// A token of a GraphQL document
+#[derive(Debug, PartialEq)]
+enum Token {
+  Punctuator(char),
+  Spread,
+  Name(String),
+  Int(i64),
+  Float,
+  String,
+}
+
+/// Splits the GraphQL document into tokens
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = source.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      // Whitespace, commas and the byte order mark are insignificant
+      ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => (),
+      '#' => while chars.next_if(|c| *c != '\n' && *c != '\r').is_some() {},
+      '!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => tokens.push(Token::Punctuator(c)),
+      '.' => {
+        if chars.next() == Some('.') && chars.next() == Some('.') {
+          tokens.push(Token::Spread);
+        } else {
+          return Err("Unexpected character \".\"".to_string());
+        }
+      }
+      '"' => {
+        if chars.next_if_eq(&'"').is_some() {
+          if chars.next_if_eq(&'"').is_some() {
+            // A block string, terminated by three quotes (escaped as `\"""`)
+            let mut quotes = 0;
+            loop {
+              match chars.next() {
+                Some('"') => {
+                  quotes += 1;
+                  if quotes == 3 {
+                    break;
+                  }
+                }
+                Some('\\') => {
+                  quotes = 0;
+                  for _ in 0..3 {
+                    chars.next_if_eq(&'"');
+                  }
+                }
+                Some(_) => quotes = 0,
+                None => return Err("Unterminated block string".to_string()),
+              }
+            }
+          }
+          // Otherwise, it's an empty string
+        } else {
+          loop {
+            match chars.next() {
+              Some('"') => break,
+              Some('\\') => {
+                chars.next();
+              }
+              Some('\n') | Some('\r') | None => return Err("Unterminated string".to_string()),
+              Some(_) => (),
+            }
+          }
+        }
+        tokens.push(Token::String);
+      }
+      c if c == '-' || c.is_ascii_digit() => {
+        let mut number = String::from(c);
+        let mut is_float = false;
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '+' || *c == '-') {
+          if c == '.' || c == 'e' || c == 'E' {
+            is_float = true;
+          }
+          number.push(c);
+        }
+        if is_float {
+          number
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid number \"{number}\""))?;
+          tokens.push(Token::Float);
+        } else {
+          tokens.push(Token::Int(
+            number
+              .parse::<i64>()
+              .map_err(|_| format!("Invalid number \"{number}\""))?,
+          ));
+        }
+      }
+      c if c == '_' || c.is_ascii_alphabetic() => {
+        let mut name = String::from(c);
+        while let Some(c) = chars.next_if(|c| *c == '_' || c.is_ascii_alphanumeric()) {
+          name.push(c);
+        }
+        tokens.push(Token::Name(name));
+      }
+      c => return Err(format!("Unexpected character \"{}\"", c.escape_default())),
+    }
+  }
+  Ok(tokens)
+}
+
+/// A value of a field argument affecting the complexity
+#[derive(Debug)]
+enum MultiplierValue {
+  Int(i64),
+  Variable(String),
+}
+
+/// A selection in a GraphQL selection set
+#[derive(Debug)]
+enum Selection {
+  Field {
+    multiplier: Option<MultiplierValue>,
+    selections: Vec<Selection>,
+  },
+  FragmentSpread(String),
+  InlineFragment(Vec<Selection>),
+}
+
+/// A GraphQL operation type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphqlOperationType {
+  Query,
+  Mutation,
+  Subscription,
+}
+
+impl GraphqlOperationType {
+  /// Returns the name of the operation type
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Query => "query",
+      Self::Mutation => "mutation",
+      Self::Subscription => "subscription",
+    }
+  }
+}
+
+/// A GraphQL operation definition
+#[derive(Debug)]
+struct Operation {
+  operation_type: GraphqlOperationType,
+  name: Option<String>,
+  selections: Vec<Selection>,
+}
+
+/// A recursive descent parser of GraphQL executable documents
+struct Parser {
+  tokens: Vec<Token>,
+  position: usize,
+  nesting: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position)
+  }
+
+  fn next(&mut self) -> Option<&Token> {
+    let token = self.tokens.get(self.position);
+    self.position += 1;
+    token
+  }
+
+  fn is_punctuator(&self, punctuator: char) -> bool {
+    self.peek() == Some(&Token::Punctuator(punctuator))
+  }
+
+  fn expect_punctuator(&mut self, punctuator: char) -> Result<(), String> {
+    match self.next() {
+      Some(Token::Punctuator(c)) if *c == punctuator => Ok(()),
+      _ => Err(format!("Expected \"{punctuator}\"")),
+    }
+  }
+
+  fn expect_name(&mut self) -> Result<String, String> {
+    match self.next() {
+      Some(Token::Name(name)) => Ok(name.clone()),
+      _ => Err("Expected a name".to_string()),
+    }
+  }
+
+  fn enter(&mut self) -> Result<(), String> {
+    self.nesting += 1;
+    if self.nesting > MAX_PARSER_NESTING {
+      Err("The document is nested too deeply".to_string())
+    } else {
+      Ok(())
+    }
+  }
+
+  fn leave(&mut self) {
+    self.nesting -= 1;
+  }
+
+  /// Parses a value, returning the multiplier value, if it's an integer or a variable
+  fn parse_value(&mut self) -> Result<Option<MultiplierValue>, String> {
+    match self.next() {
+      Some(Token::Int(value)) => Ok(Some(MultiplierValue::Int(*value))),
+      Some(Token::Float) | Some(Token::String) | Some(Token::Name(_)) => Ok(None),
+      Some(Token::Punctuator('$')) => Ok(Some(MultiplierValue::Variable(self.expect_name()?))),
+      Some(Token::Punctuator('[')) => {
+        self.enter()?;
+        while !self.is_punctuator(']') {
+          self.parse_value()?;
+        }
+        self.position += 1;
+        self.leave();
+        Ok(None)
+      }
+      Some(Token::Punctuator('{')) => {
+        self.enter()?;
+        while !self.is_punctuator('}') {
+          self.expect_name()?;
+          self.expect_punctuator(':')?;
+          self.parse_value()?;
+        }
+        self.position += 1;
+        self.leave();
+        Ok(None)
+      }
+      _ => Err("Expected a value".to_string()),
+    }
+  }
+
+  /// Parses the arguments, returning the complexity multiplier
+  fn parse_arguments(&mut self) -> Result<Option<MultiplierValue>, String> {
+    let mut multiplier = None;
+    if self.is_punctuator('(') {
+      self.position += 1;
+      while !self.is_punctuator(')') {
+        let name = self.expect_name()?;
+        self.expect_punctuator(':')?;
+        let value = self.parse_value()?;
+        if MULTIPLIER_ARGUMENTS.contains(&name.as_str()) && value.is_some() {
+          multiplier = value;
+        }
+      }
+      self.position += 1;
+    }
+    Ok(multiplier)
+  }
+
+  fn parse_directives(&mut self) -> Result<(), String> {
+    while self.is_punctuator('@') {
+      self.position += 1;
+      self.expect_name()?;
+      self.parse_arguments()?;
+    }
+    Ok(())
+  }
+
+  fn parse_type(&mut self) -> Result<(), String> {
+    if self.is_punctuator('[') {
+      self.position += 1;
+      self.enter()?;
+      self.parse_type()?;
+      self.leave();
+      self.expect_punctuator(']')?;
+    } else {
+      self.expect_name()?;
+    }
+    if self.is_punctuator('!') {
+      self.position += 1;
+    }
+    Ok(())
+  }
+
+  fn parse_variable_definitions(&mut self) -> Result<(), String> {
+    if self.is_punctuator('(') {
+      self.position += 1;
+      while !self.is_punctuator(')') {
+        self.expect_punctuator('$')?;
+        self.expect_name()?;
+        self.expect_punctuator(':')?;
+        self.parse_type()?;
+        if self.is_punctuator('=') {
+          self.position += 1;
+          self.parse_value()?;
+        }
+        self.parse_directives()?;
+      }
+      self.position += 1;
+    }
+    Ok(())
+  }
+
+  fn parse_selection_set(&mut self) -> Result<Vec<Selection>, String> {
+    self.expect_punctuator('{')?;
+    self.enter()?;
+    let mut selections = Vec::new();
+    while !self.is_punctuator('}') {
+      if self.peek() == Some(&Token::Spread) {
+        self.position += 1;
+        match self.peek() {
+          Some(Token::Name(name)) if name != "on" => {
+            let name = name.clone();
+            self.position += 1;
+            self.parse_directives()?;
+            selections.push(Selection::FragmentSpread(name));
+          }
+          _ => {
+            if self.peek() == Some(&Token::Name("on".to_string())) {
+              self.position += 1;
+              self.expect_name()?;
+            }
+            self.parse_directives()?;
+            selections.push(Selection::InlineFragment(self.parse_selection_set()?));
+          }
+        }
+      } else {
+        self.expect_name()?;
+        if self.is_punctuator(':') {
+          // The first name is an alias
+          self.position += 1;
+          self.expect_name()?;
+        }
+        let multiplier = self.parse_arguments()?;
+        self.parse_directives()?;
+        let selections_inner = if self.is_punctuator('{') {
+          self.parse_selection_set()?
+        } else {
+          Vec::new()
+        };
+        selections.push(Selection::Field {
+          multiplier,
+          selections: selections_inner,
+        });
+      }
+    }
+    self.position += 1;
+    self.leave();
+    Ok(selections)
+  }
+}
+
+/// Analysis results of a GraphQL operation
+#[derive(Debug, PartialEq)]
+pub struct GraphqlOperationAnalysis {
+  /// The operation name, if specified
+  pub name: Option<String>,
+  /// The operation type
+  pub operation_type: GraphqlOperationType,
+  /// The maximum depth of the selection sets
+  pub depth: u64,
+  /// The complexity (the number of the selected fields, multiplied by pagination arguments)
+  pub complexity: u64,
+}
+
+/// A parsed GraphQL executable document
+#[derive(Debug)]
+pub struct GraphqlDocument {
+  operations: Vec<Operation>,
+  fragments: HashMap<String, Vec<Selection>>,
+}
+
+impl GraphqlDocument {
+  /// Parses the GraphQL executable document
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let mut parser = Parser {
+      tokens: tokenize(source)?,
+      position: 0,
+      nesting: 0,
+    };
+    let mut operations = Vec::new();
+    let mut fragments = HashMap::new();
+    while let Some(token) = parser.peek() {
+      match token {
+        Token::Punctuator('{') => operations.push(Operation {
+          operation_type: GraphqlOperationType::Query,
+          name: None,
+          selections: parser.parse_selection_set()?,
+        }),
+        Token::Name(keyword) if keyword == "fragment" => {
+          parser.position += 1;
+          let name = parser.expect_name()?;
+          if parser.expect_name()? != "on" {
+            return Err("Expected \"on\"".to_string());
+          }
+          parser.expect_name()?;
+          parser.parse_directives()?;
+          fragments.insert(name, parser.parse_selection_set()?);
+        }
+        Token::Name(keyword) => {
+          let operation_type = match keyword.as_str() {
+            "query" => GraphqlOperationType::Query,
+            "mutation" => GraphqlOperationType::Mutation,
+            "subscription" => GraphqlOperationType::Subscription,
+            _ => return Err(format!("Unexpected \"{keyword}\"")),
+          };
+          parser.position += 1;
+          let name = match parser.peek() {
+            Some(Token::Name(name)) => {
+              let name = name.clone();
+              parser.position += 1;
+              Some(name)
+            }
+            _ => None,
+          };
+          parser.parse_variable_definitions()?;
+          parser.parse_directives()?;
+          operations.push(Operation {
+            operation_type,
+            name,
+            selections: parser.parse_selection_set()?,
+          });
+        }
+        _ => return Err("Expected an operation or a fragment definition".to_string()),
+      }
+    }
+    if operations.is_empty() {
+      return Err("The document doesn't contain any operations".to_string());
+    }
+    Ok(Self { operations, fragments })
+  }
+
+  /// Computes the depth and the complexity of the selections.
+  /// The results for fragments are memoized, so documents with many fragment spreads are analyzed quickly.
+  fn measure(
+    &self,
+    selections: &[Selection],
+    variables: &Value,
+    fragment_results: &mut HashMap<String, (u64, u64)>,
+    visiting: &mut HashSet<String>,
+  ) -> Result<(u64, u64), String> {
+    let mut depth = 0;
+    let mut complexity: u64 = 0;
+    for selection in selections {
+      let (selection_depth, selection_complexity) = match selection {
+        Selection::Field { multiplier, selections } => {
+          let (children_depth, children_complexity) =
+            self.measure(selections, variables, fragment_results, visiting)?;
+          let multiplier = match multiplier {
+            Some(MultiplierValue::Int(value)) => Some(*value),
+            Some(MultiplierValue::Variable(variable)) => variables.get(variable).and_then(|v| v.as_i64()),
+            None => None,
+          }
+          .map_or(1, |value| value.max(1) as u64);
+          (
+            children_depth + 1,
+            children_complexity.saturating_mul(multiplier).saturating_add(1),
+          )
+        }
+        Selection::InlineFragment(selections) => self.measure(selections, variables, fragment_results, visiting)?,
+        Selection::FragmentSpread(name) => match fragment_results.get(name) {
+          Some(result) => *result,
+          None => {
+            let fragment = self
+              .fragments
+              .get(name)
+              .ok_or_else(|| format!("Unknown fragment \"{name}\""))?;
+            if !visiting.insert(name.clone()) {
+              return Err(format!("The fragment \"{name}\" spreads itself"));
+            }
+            let result = self.measure(fragment, variables, fragment_results, visiting)?;
+            visiting.remove(name);
+            fragment_results.insert(name.clone(), result);
+            result
+          }
+        },
+      };
+      depth = depth.max(selection_depth);
+      complexity = complexity.saturating_add(selection_complexity);
+    }
+    Ok((depth, complexity))
+  }
+
+  /// Analyzes the operation with the specified name (or the only operation in the document)
+  pub fn analyze(&self, operation_name: Option<&str>, variables: &Value) -> Result<GraphqlOperationAnalysis, String> {
+    let operation = match operation_name {
+      Some(operation_name) => self
+        .operations
+        .iter()
+        .find(|operation| operation.name.as_deref() == Some(operation_name))
+        .ok_or_else(|| format!("Unknown operation \"{operation_name}\""))?,
+      None if self.operations.len() == 1 => &self.operations[0],
+      None => return Err("The operation name is required for documents with multiple operations".to_string()),
+    };
+    let (depth, complexity) = self.measure(
+      &operation.selections,
+      variables,
+      &mut HashMap::new(),
+      &mut HashSet::new(),
+    )?;
+    Ok(GraphqlOperationAnalysis {
+      name: operation.name.clone(),
+      operation_type: operation.operation_type,
+      depth,
+      complexity,
+    })
+  }
+}
+
+/// Parses the persisted query manifest, returning the map of the persisted query IDs (hashes) to the queries.
+/// Both Apollo persisted query manifests (with the `operations` array) and JSON objects mapping the hashes
+/// to the queries are supported.
+pub fn parse_persisted_query_manifest(content: &str) -> Result<HashMap<String, String>, String> {
+  let manifest: Value =
+    serde_json::from_str(content).map_err(|err| format!("Invalid persisted query manifest: {err}"))?;
+  let mut queries = HashMap::new();
+  if let Some(operations) = manifest.get("operations") {
+    for operation in operations
+      .as_array()
+      .ok_or("The persisted operations must be an array")?
+    {
+      match (
+        operation.get("id").and_then(|v| v.as_str()),
+        operation.get("body").and_then(|v| v.as_str()),
+      ) {
+        (Some(id), Some(body)) => queries.insert(id.to_string(), body.to_string()),
+        _ => return Err("A persisted operation must have the `id` and `body` strings".to_string()),
+      };
+    }
+  } else if let Some(manifest) = manifest.as_object() {
+    for (hash, query) in manifest {
+      let query = query
+        .as_str()
+        .ok_or_else(|| format!("The persisted query \"{hash}\" must be a string"))?;
+      queries.insert(hash.clone(), query.to_string());
+    }
+  } else {
+    return Err("The persisted query manifest must be a JSON object".to_string());
+  }
+  Ok(queries)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn analyze(source: &str) -> GraphqlOperationAnalysis {
+    GraphqlDocument::parse(source)
+      .unwrap()
+      .analyze(None, &Value::Null)
+      .unwrap()
+  }
+
+  #[test]
+  fn measures_depth_and_complexity() {
+    let analysis = analyze("{ me { name friends { name } } }");
+    assert_eq!(analysis.operation_type, GraphqlOperationType::Query);
+    assert_eq!(analysis.depth, 3);
+    assert_eq!(analysis.complexity, 4);
+  }
+
+  #[test]
+  fn expands_fragments_and_multiplies_paginated_fields() {
+    let source = r#"
+      # Fetch the posts
+      query Posts($count: Int = 10) {
+        posts(first: $count, filter: { tags: ["a", "b"], title: """block "string" """ }) {
+          ...PostFields
+          ... on Post @include(if: true) { id }
+        }
+      }
+      fragment PostFields on Post { id title author { name } }
+    "#;
+    let document = GraphqlDocument::parse(source).unwrap();
+    let analysis = document
+      .analyze(Some("Posts"), &serde_json::json!({ "count": 20 }))
+      .unwrap();
+    assert_eq!(analysis.name.as_deref(), Some("Posts"));
+    assert_eq!(analysis.depth, 3);
+    // posts (1) + 20 * (id + title + author + author.name + id)
+    assert_eq!(analysis.complexity, 101);
+  }
+
+  #[test]
+  fn selects_operations_by_name() {
+    let document = GraphqlDocument::parse("query A { a } mutation B { b { c } }").unwrap();
+    assert!(document.analyze(None, &Value::Null).is_err());
+    assert!(document.analyze(Some("C"), &Value::Null).is_err());
+    let analysis = document.analyze(Some("B"), &Value::Null).unwrap();
+    assert_eq!(analysis.operation_type, GraphqlOperationType::Mutation);
+    assert_eq!(analysis.depth, 2);
+  }
+
+  #[test]
+  fn rejects_invalid_documents() {
+    assert!(GraphqlDocument::parse("{ a ").is_err());
+    assert!(GraphqlDocument::parse("type Query { a: Int }").is_err());
+    assert!(GraphqlDocument::parse("fragment F on T { a }").is_err());
+    assert!(GraphqlDocument::parse(&"{ a ".repeat(1000)).is_err());
+    let document = GraphqlDocument::parse("{ ...F } fragment F on T { ...G } fragment G on T { ...F }").unwrap();
+    assert!(document.analyze(None, &Value::Null).is_err());
+  }
+
+  #[test]
+  fn parses_persisted_query_manifests() {
+    let queries = parse_persisted_query_manifest(
+      r#"{"format":"apollo-persisted-query-manifest","version":1,"operations":[{"id":"abc","name":"Me","type":"query","body":"query Me { me { id } }"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(queries.get("abc").map(|v| v.as_str()), Some("query Me { me { id } }"));
+    let queries = parse_persisted_query_manifest(r#"{"def": "{ a }"}"#).unwrap();
+    assert_eq!(queries.get("def").map(|v| v.as_str()), Some("{ a }"));
+    assert!(parse_persisted_query_manifest(r#"{"def": 1}"#).is_err());
+    assert!(parse_persisted_query_manifest("[]").is_err());
+  }
+}
@@ -0,0 +1,88 @@
+/// The state of an HLS media playlist, used for blocking playlist reloads (LL-HLS)
+#[derive(Debug, PartialEq, Eq)]
+pub struct HlsPlaylistState {
+  /// The media sequence number of the segment following the last complete segment
+  pub next_media_sequence: u64,
+  /// The number of partial segments of the segment following the last complete segment
+  pub next_segment_parts: u64,
+  /// Whether the playlist has the `EXT-X-ENDLIST` tag
+  pub ended: bool,
+}
+
+impl HlsPlaylistState {
+  /// Parses the state of an HLS media playlist
+  pub fn parse(playlist: &str) -> Self {
+    let mut media_sequence = 0;
+    let mut segments = 0;
+    let mut next_segment_parts = 0;
+    let mut ended = false;
+    for line in playlist.lines() {
+      let line = line.trim();
+      if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+        media_sequence = value.trim().parse().unwrap_or(0);
+      } else if line.starts_with("#EXTINF:") {
+        segments += 1;
+        next_segment_parts = 0;
+      } else if line.starts_with("#EXT-X-PART:") {
+        next_segment_parts += 1;
+      } else if line == "#EXT-X-ENDLIST" {
+        ended = true;
+      }
+    }
+    Self {
+      next_media_sequence: media_sequence + segments,
+      next_segment_parts,
+      ended,
+    }
+  }
+
+  /// Checks if the playlist contains the segment (and the partial segment, if specified) requested for blocking playlist reload
+  pub fn contains(&self, media_sequence: u64, part: Option<u64>) -> bool {
+    self.ended
+      || media_sequence < self.next_media_sequence
+      || (media_sequence == self.next_media_sequence && part.is_some_and(|part| part < self.next_segment_parts))
+  }
+
+  /// Checks if the requested segment is more than two segments beyond the last segment in the playlist
+  pub fn is_too_far_ahead(&self, media_sequence: u64) -> bool {
+    !self.ended && media_sequence > self.next_media_sequence + 1
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_hls_playlist_state() {
+    let state = HlsPlaylistState::parse(
+      "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:100\n\
+       #EXT-X-PART:DURATION=1,URI=\"s100.0.m4s\"\n#EXTINF:4,\ns100.m4s\n\
+       #EXTINF:4,\ns101.m4s\n#EXT-X-PART:DURATION=1,URI=\"s102.0.m4s\"\n\
+       #EXT-X-PART:DURATION=1,URI=\"s102.1.m4s\"\n#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"s102.2.m4s\"\n",
+    );
+    assert_eq!(
+      state,
+      HlsPlaylistState {
+        next_media_sequence: 102,
+        next_segment_parts: 2,
+        ended: false,
+      }
+    );
+  }
+
+  #[test]
+  fn test_hls_playlist_state_contains() {
+    let state = HlsPlaylistState {
+      next_media_sequence: 102,
+      next_segment_parts: 2,
+      ended: false,
+    };
+    assert!(state.contains(101, None));
+    assert!(state.contains(102, Some(1)));
+    assert!(!state.contains(102, Some(2)));
+    assert!(!state.contains(102, None));
+    assert!(!state.is_too_far_ahead(103));
+    assert!(state.is_too_far_ahead(104));
+  }
+}
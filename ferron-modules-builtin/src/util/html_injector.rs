@@ -0,0 +1,224 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, Bytes};
+use hyper::body::{Body, Frame};
+use pin_project_lite::pin_project;
+
+/// The closing tags, before which the snippets are injected
+const INJECTION_TAGS: [&[u8]; 2] = [b"</head>", b"</body>"];
+
+/// Finds the first case-insensitive occurrence of an ASCII needle in a haystack
+fn find_ascii_case_insensitive(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  if needle.is_empty() || haystack.len() < needle.len() {
+    return None;
+  }
+  haystack
+    .windows(needle.len())
+    .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// A struct that can wrap a `Body` to inject snippets into HTML documents
+pub struct HtmlInjector {
+  snippets: [Arc<Vec<u8>>; 2],
+}
+
+impl HtmlInjector {
+  /// Creates a struct that can wrap a `Body` to inject snippets before `</head>` and `</body>` closing tags
+  pub fn new(head_snippet: &[u8], body_snippet: &[u8]) -> Self {
+    Self {
+      snippets: [Arc::new(head_snippet.to_vec()), Arc::new(body_snippet.to_vec())],
+    }
+  }
+
+  /// Wraps a `Body` to inject snippets
+  pub fn wrap<B>(&self, body: B) -> InjectBody<B>
+  where
+    B: Body,
+  {
+    InjectBody {
+      snippets: self.snippets.clone(),
+      pending: [!self.snippets[0].is_empty(), !self.snippets[1].is_empty()],
+      buffer: Vec::new(),
+      inner: body,
+    }
+  }
+}
+
+pin_project! {
+  /// A `Body` with injected snippets
+  pub struct InjectBody<B> {
+    snippets: [Arc<Vec<u8>>; 2],
+    pending: [bool; 2],
+    buffer: Vec<u8>,
+    #[pin]
+    inner: B,
+  }
+}
+
+impl<B> Body for InjectBody<B>
+where
+  B: Body,
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  #[inline]
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    let frame_raw = match this.inner.poll_frame(cx) {
+      Poll::Ready(Some(Ok(frame))) => frame,
+      Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+      Poll::Ready(None) => {
+        if this.buffer.is_empty() {
+          return Poll::Ready(None);
+        } else {
+          return Poll::Ready(Some(Ok(Frame::data(Bytes::from(std::mem::take(this.buffer))))));
+        }
+      }
+      Poll::Pending => return Poll::Pending,
+    };
+    match frame_raw.into_data() {
+      Ok(mut data) => {
+        let data_len = data.remaining();
+        let data_bytes = data.copy_to_bytes(data_len);
+        if !this.pending.iter().any(|pending| *pending) && this.buffer.is_empty() {
+          return Poll::Ready(Some(Ok(Frame::data(data_bytes))));
+        }
+        let mut combined_bytes = std::mem::take(this.buffer);
+        combined_bytes.extend_from_slice(&data_bytes);
+
+        let mut injected = Vec::with_capacity(combined_bytes.len());
+        let mut last_beg_index = 0;
+        loop {
+          // Inject the snippet before the earliest pending closing tag
+          let earliest_match = INJECTION_TAGS
+            .iter()
+            .enumerate()
+            .filter(|(tag_index, _)| this.pending[*tag_index])
+            .filter_map(|(tag_index, tag)| {
+              find_ascii_case_insensitive(&combined_bytes[last_beg_index..], tag).map(|index| (index, tag_index))
+            })
+            .min();
+          let Some((index, tag_index)) = earliest_match else {
+            break;
+          };
+          injected.extend_from_slice(&combined_bytes[last_beg_index..last_beg_index + index]);
+          injected.extend_from_slice(&this.snippets[tag_index]);
+          last_beg_index += index;
+          this.pending[tag_index] = false;
+        }
+
+        // Keep the bytes, which might be a start of a pending closing tag split across chunks
+        let kept_length = INJECTION_TAGS
+          .iter()
+          .enumerate()
+          .filter(|(tag_index, _)| this.pending[*tag_index])
+          .map(|(_, tag)| tag.len() - 1)
+          .max()
+          .unwrap_or(0)
+          .min(combined_bytes.len() - last_beg_index);
+        let split_index = combined_bytes.len() - kept_length;
+        injected.extend_from_slice(&combined_bytes[last_beg_index..split_index]);
+        *this.buffer = combined_bytes[split_index..].to_vec();
+
+        Poll::Ready(Some(Ok(Frame::data(Bytes::from(injected)))))
+      }
+      Err(frame_raw) => {
+        if let Ok(trailers) = frame_raw.into_trailers() {
+          Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+        } else {
+          unreachable!()
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Helper function to collect all data from a body
+  async fn collect_body_bytes<B>(body: B) -> Result<Vec<u8>, B::Error>
+  where
+    B: Body,
+    B::Data: AsRef<[u8]>,
+  {
+    let mut result = Vec::new();
+    let mut body = std::pin::pin!(body);
+    while let Some(frame) = futures_util::future::poll_fn(|cx| body.as_mut().poll_frame(cx)).await {
+      if let Ok(data) = frame?.into_data() {
+        result.extend_from_slice(data.as_ref());
+      }
+    }
+    Ok(result)
+  }
+
+  // Simple test body that yields data in chunks
+  struct TestBody {
+    chunks: Vec<Bytes>,
+    current: usize,
+  }
+
+  impl TestBody {
+    fn new(chunks: &[&[u8]]) -> Self {
+      Self {
+        chunks: chunks.iter().map(|chunk| Bytes::copy_from_slice(chunk)).collect(),
+        current: 0,
+      }
+    }
+  }
+
+  impl Body for TestBody {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+      mut self: Pin<&mut Self>,
+      _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+      if self.current < self.chunks.len() {
+        let chunk = self.chunks[self.current].clone();
+        self.current += 1;
+        Poll::Ready(Some(Ok(Frame::data(chunk))))
+      } else {
+        Poll::Ready(None)
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_inject_head_and_body() {
+    let injector = HtmlInjector::new(b"<link>", b"<script></script>");
+    let body = TestBody::new(&[b"<html><head></head><body><p>Hi</p></body></html>"]);
+
+    let result = collect_body_bytes(injector.wrap(body)).await.unwrap();
+    assert_eq!(
+      result,
+      b"<html><head><link></head><body><p>Hi</p><script></script></body></html>"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_inject_tags_spanning_chunks() {
+    let injector = HtmlInjector::new(b"<link>", b"<script></script>");
+    let body = TestBody::new(&[b"<head></HE", b"AD><body>text</b", b"o", b"dy>"]);
+
+    let result = collect_body_bytes(injector.wrap(body)).await.unwrap();
+    assert_eq!(result, b"<head><link></HEAD><body>text<script></script></body>");
+  }
+
+  #[tokio::test]
+  async fn test_inject_once_and_without_tags() {
+    let injector = HtmlInjector::new(b"", b"<p>Banner</p>");
+    let body = TestBody::new(&[b"<body></body></body>"]);
+    let result = collect_body_bytes(injector.wrap(body)).await.unwrap();
+    assert_eq!(result, b"<body><p>Banner</p></body></body>");
+
+    let body = TestBody::new(&[b"plain ", b"text"]);
+    let result = collect_body_bytes(injector.wrap(body)).await.unwrap();
+    assert_eq!(result, b"plain text");
+  }
+}
@@ -0,0 +1,86 @@
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Formats an RFC 9530 integrity field value (for the `Content-Digest` and `Repr-Digest` headers)
+/// from a hexadecimal SHA-256 hash
+pub fn format_integrity_digest(sha256_hex: &str) -> Option<String> {
+  if sha256_hex.len() != 64 || !sha256_hex.is_ascii() {
+    return None;
+  }
+  let hash = (0..sha256_hex.len())
+    .step_by(2)
+    .map(|index| u8::from_str_radix(&sha256_hex[index..index + 2], 16).ok())
+    .collect::<Option<Vec<_>>>()?;
+  Some(format!(
+    "sha-256=:{}:",
+    base64::engine::general_purpose::STANDARD.encode(hash)
+  ))
+}
+
+/// Verifies the data against an RFC 9530 integrity field value. Only the `sha-256` and `sha-512` algorithms
+/// are supported, and all the supported hashes in the field value have to match.
+/// Returns `None`, if the field value doesn't contain any supported hashes.
+pub fn verify_integrity_digest(field_value: &str, data: &[u8]) -> Option<bool> {
+  let mut verified = None;
+  for member in field_value.split(',') {
+    let Some((algorithm, hash)) = member.split_once('=') else {
+      continue;
+    };
+    // The byte sequence can be followed by parameters, which are ignored
+    let Some(hash) = hash
+      .split(';')
+      .next()
+      .and_then(|hash| hash.trim().strip_prefix(':'))
+      .and_then(|hash| hash.strip_suffix(':'))
+    else {
+      continue;
+    };
+    let expected_hash = match algorithm.trim().to_ascii_lowercase().as_str() {
+      "sha-256" => Sha256::digest(data).to_vec(),
+      "sha-512" => Sha512::digest(data).to_vec(),
+      _ => continue,
+    };
+    if !base64::engine::general_purpose::STANDARD
+      .decode(hash)
+      .is_ok_and(|hash| hash == expected_hash)
+    {
+      return Some(false);
+    }
+    verified = Some(true);
+  }
+  verified
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_format_integrity_digest() {
+    assert_eq!(
+      format_integrity_digest("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824").as_deref(),
+      Some("sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:")
+    );
+    assert_eq!(format_integrity_digest("invalid"), None);
+  }
+
+  #[test]
+  fn test_verify_integrity_digest() {
+    assert_eq!(
+      verify_integrity_digest("sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:", b"hello"),
+      Some(true)
+    );
+    assert_eq!(
+      verify_integrity_digest("sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:", b"hello!"),
+      Some(false)
+    );
+    assert_eq!(
+      verify_integrity_digest(
+        "unixsum=:MzAy:, sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:",
+        b"hello"
+      ),
+      Some(true)
+    );
+    assert_eq!(verify_integrity_digest("unixsum=:MzAy:", b"hello"), None);
+  }
+}
@@ -0,0 +1,43 @@
+use base64::Engine;
+
+/// Decodes the claims from the JWT bearer token in the "Authorization" header, without verifying the token
+pub fn decode_bearer_claims(authorization: &str) -> Option<serde_json::Value> {
+  let (scheme, token) = authorization.trim().split_once(' ')?;
+  if !scheme.eq_ignore_ascii_case("bearer") {
+    return None;
+  }
+  let mut token_parts = token.trim().split('.');
+  let (Some(_), Some(payload), Some(_), None) = (
+    token_parts.next(),
+    token_parts.next(),
+    token_parts.next(),
+    token_parts.next(),
+  ) else {
+    return None;
+  };
+  let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+    .decode(payload.trim_end_matches('='))
+    .ok()?;
+  serde_json::from_slice::<serde_json::Value>(&payload)
+    .ok()
+    .filter(|claims| claims.is_object())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode_bearer_claims() {
+    // {"alg":"HS256","typ":"JWT"}.{"sub":"1234567890","name":"John Doe"}
+    let claims = decode_bearer_claims(
+      "Bearer eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIn0.signature",
+    )
+    .unwrap();
+    assert_eq!(claims["sub"], "1234567890");
+    assert_eq!(claims["name"], "John Doe");
+
+    assert!(decode_bearer_claims("Basic dXNlcjpwYXNz").is_none());
+    assert!(decode_bearer_claims("Bearer not-a-jwt").is_none());
+  }
+}
@@ -0,0 +1,138 @@
+use ferron_common::util::anti_xss;
+use pulldown_cmark::{html, Options, Parser};
+
+/// The default template for Markdown documents rendered into HTML
+pub const DEFAULT_MARKDOWN_TEMPLATE: &str = "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"UTF-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">
+<title>{{title}}</title>
+<style>body{max-width:50em;margin:0 auto;padding:1em;font-family:sans-serif;line-height:1.5}\
+pre{overflow-x:auto}table{border-collapse:collapse}th,td{border:1px solid #ccc;padding:0.25em 0.5em}</style>
+</head>
+<body>
+{{content}}
+</body>
+</html>
+";
+
+/// Splits the YAML front matter (delimited by `---` lines) from the Markdown document.
+/// Only `key: value` pairs are supported in the front matter.
+pub fn split_front_matter(document: &str) -> (Vec<(String, String)>, &str) {
+  let document = document.strip_prefix('\u{feff}').unwrap_or(document);
+  let Some(rest) = document
+    .strip_prefix("---\n")
+    .or_else(|| document.strip_prefix("---\r\n"))
+  else {
+    return (Vec::new(), document);
+  };
+
+  let mut front_matter = Vec::new();
+  let mut offset = 0;
+  for line in rest.split_inclusive('\n') {
+    offset += line.len();
+    let line = line.trim_end();
+    if line == "---" || line == "..." {
+      return (front_matter, &rest[offset..]);
+    }
+    if line.starts_with('#') {
+      continue;
+    }
+    if let Some((key, value)) = line.split_once(':') {
+      let value = value.trim();
+      let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+      front_matter.push((key.trim().to_string(), value.to_string()));
+    }
+  }
+
+  // The front matter isn't terminated, so the document doesn't have a front matter
+  (Vec::new(), document)
+}
+
+/// Renders the Markdown document into HTML
+pub fn render_markdown(markdown: &str) -> String {
+  let options = Options::ENABLE_TABLES
+    | Options::ENABLE_FOOTNOTES
+    | Options::ENABLE_STRIKETHROUGH
+    | Options::ENABLE_TASKLISTS
+    | Options::ENABLE_HEADING_ATTRIBUTES;
+  let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+  html::push_html(&mut rendered, Parser::new_ext(markdown, options));
+  rendered
+}
+
+/// Fills the template with the rendered Markdown content and the front matter values.
+/// The `{{content}}` placeholder is replaced with the rendered HTML, while other placeholders
+/// (like `{{title}}`) are replaced with escaped front matter values.
+pub fn fill_markdown_template(template: &str, content: &str, front_matter: &[(String, String)]) -> String {
+  let mut filled = String::with_capacity(template.len() + content.len());
+  let mut rest = template;
+  while let Some(start_index) = rest.find("{{") {
+    let Some(end_index) = rest[start_index + 2..].find("}}") else {
+      break;
+    };
+    filled.push_str(&rest[..start_index]);
+    let placeholder = rest[start_index + 2..start_index + 2 + end_index].trim();
+    if placeholder == "content" {
+      filled.push_str(content);
+    } else if let Some((_, value)) = front_matter.iter().find(|(key, _)| key == placeholder) {
+      filled.push_str(&anti_xss(value));
+    }
+    rest = &rest[start_index + 2 + end_index + 2..];
+  }
+  filled.push_str(rest);
+  filled
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_split_front_matter() {
+    let (front_matter, markdown) =
+      split_front_matter("---\ntitle: \"Hello: world\"\n# comment\nauthor: Jane\n---\n# Heading\n");
+    assert_eq!(
+      front_matter,
+      vec![
+        ("title".to_string(), "Hello: world".to_string()),
+        ("author".to_string(), "Jane".to_string())
+      ]
+    );
+    assert_eq!(markdown, "# Heading\n");
+
+    let (front_matter, markdown) = split_front_matter("# Heading\n---\n");
+    assert!(front_matter.is_empty());
+    assert_eq!(markdown, "# Heading\n---\n");
+
+    let (front_matter, markdown) = split_front_matter("---\nunterminated: true\n");
+    assert!(front_matter.is_empty());
+    assert_eq!(markdown, "---\nunterminated: true\n");
+  }
+
+  #[test]
+  fn test_render_markdown() {
+    assert_eq!(
+      render_markdown("# Title\n\n~~old~~"),
+      "<h1>Title</h1>\n<p><del>old</del></p>\n"
+    );
+  }
+
+  #[test]
+  fn test_fill_markdown_template() {
+    let front_matter = vec![("title".to_string(), "A <b>title</b>".to_string())];
+    assert_eq!(
+      fill_markdown_template(
+        "<title>{{ title }}</title>{{content}}{{missing}}{{",
+        "<p>Hi</p>",
+        &front_matter
+      ),
+      "<title>A &lt;b&gt;title&lt;/b&gt;</title><p>Hi</p>{{"
+    );
+  }
+}
@@ -1,17 +1,87 @@
 mod basic_auth;
 #[cfg(feature = "replace")]
 mod body_replacer;
+#[cfg(feature = "transform")]
+mod buffered_transform;
 #[cfg(feature = "cache")]
 pub mod cache_control;
+#[cfg(any(feature = "accounting", feature = "capture"))]
+mod capturing_body;
+#[cfg(any(feature = "pseudostream", feature = "static"))]
+mod channel_writer;
+#[cfg(any(feature = "dcompress", feature = "rproxy"))]
+mod decompressing_body;
+mod digest_auth;
+#[cfg(feature = "static")]
+mod directory_archive;
 #[cfg(feature = "fcgi")]
 pub mod fcgi;
-#[cfg(any(feature = "dcompress", feature = "fcgi"))]
+#[cfg(feature = "dcompress")]
+mod flushing_compressor;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "mediaorigin")]
+mod hls_playlist;
+#[cfg(feature = "transform")]
+mod html_injector;
+#[cfg(any(feature = "rproxy", feature = "static"))]
+mod integrity_digest;
+#[cfg(any(feature = "opa", feature = "quota"))]
+mod jwt_claims;
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "pseudostream")]
+mod mp4;
+#[cfg(feature = "openapi")]
+mod openapi;
+#[cfg(feature = "redact")]
+mod response_redactor;
+#[cfg(any(feature = "dcompress", feature = "fcgi", feature = "rproxy"))]
 mod split_stream_by_map;
+#[cfg(feature = "cache")]
+mod tee_body;
+#[cfg(feature = "cache")]
+mod vary_normalize;
 
 pub use basic_auth::*;
 #[cfg(feature = "replace")]
 pub use body_replacer::*;
+#[cfg(feature = "transform")]
+pub use buffered_transform::*;
 #[cfg(feature = "cache")]
 pub use cache_control::*;
-#[cfg(any(feature = "dcompress", feature = "fcgi"))]
+#[cfg(any(feature = "accounting", feature = "capture"))]
+pub use capturing_body::*;
+#[cfg(any(feature = "pseudostream", feature = "static"))]
+pub use channel_writer::*;
+#[cfg(any(feature = "dcompress", feature = "rproxy"))]
+pub use decompressing_body::*;
+pub use digest_auth::*;
+#[cfg(feature = "static")]
+pub use directory_archive::*;
+#[cfg(feature = "dcompress")]
+pub use flushing_compressor::*;
+#[cfg(feature = "graphql")]
+pub use graphql::*;
+#[cfg(feature = "mediaorigin")]
+pub use hls_playlist::*;
+#[cfg(feature = "transform")]
+pub use html_injector::*;
+#[cfg(any(feature = "rproxy", feature = "static"))]
+pub use integrity_digest::*;
+#[cfg(any(feature = "opa", feature = "quota"))]
+pub use jwt_claims::*;
+#[cfg(feature = "markdown")]
+pub use markdown::*;
+#[cfg(feature = "pseudostream")]
+pub use mp4::*;
+#[cfg(feature = "openapi")]
+pub use openapi::*;
+#[cfg(feature = "redact")]
+pub use response_redactor::*;
+#[cfg(any(feature = "dcompress", feature = "fcgi", feature = "rproxy"))]
 pub use split_stream_by_map::*;
+#[cfg(feature = "cache")]
+pub use tee_body::*;
+#[cfg(feature = "cache")]
+pub use vary_normalize::*;
@@ -0,0 +1,798 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The locations of the top-level boxes of an MP4 file
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mp4Layout {
+  /// The offset and the size of the `ftyp` box
+  pub ftyp: Option<(u64, u64)>,
+  /// The offset and the size of the `moov` box
+  pub moov: (u64, u64),
+  /// The offset and the size of the `mdat` box contents
+  pub mdat_data: (u64, u64),
+}
+
+/// An MP4 file remuxed for pseudo-streaming, consisting of the new header (`ftyp`, `moov` and `mdat` box header)
+/// followed by the part of the original `mdat` box contents
+#[derive(Debug)]
+pub struct Mp4PseudoStream {
+  pub header: Vec<u8>,
+  pub data_offset: u64,
+  pub data_length: u64,
+}
+
+/// Reads the locations of the top-level boxes of an MP4 file. Returns `None` if the file isn't a non-fragmented MP4 file.
+pub fn read_mp4_layout(reader: &mut (impl Read + Seek), file_size: u64) -> io::Result<Option<Mp4Layout>> {
+  let mut offset = 0;
+  let mut ftyp = None;
+  let mut moov = None;
+  let mut mdat_data = None;
+  while offset + 8 <= file_size {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut header = [0u8; 16];
+    reader.read_exact(&mut header[..8])?;
+    let (header_length, size) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+      0 => (8, file_size - offset),
+      1 => {
+        reader.read_exact(&mut header[8..])?;
+        (16, u64::from_be_bytes(header[8..].try_into().unwrap_or_default()))
+      }
+      size => (8, size as u64),
+    };
+    if size < header_length || size > file_size - offset {
+      return Ok(None);
+    }
+    match &header[4..8] {
+      b"ftyp" => ftyp = Some((offset, size)),
+      b"moov" => moov = Some((offset, size)),
+      b"mdat" if mdat_data.is_none() => mdat_data = Some((offset + header_length, size - header_length)),
+      b"moof" => return Ok(None), // Fragmented MP4 files aren't supported
+      _ => (),
+    }
+    offset += size;
+  }
+  Ok(match (moov, mdat_data) {
+    (Some(moov), Some(mdat_data)) => Some(Mp4Layout { ftyp, moov, mdat_data }),
+    _ => None,
+  })
+}
+
+/// Reads a big-endian 32-bit integer from the data
+fn read_u32(data: &[u8], position: usize) -> Result<u32, anyhow::Error> {
+  data
+    .get(position..position + 4)
+    .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    .ok_or_else(|| anyhow::anyhow!("Unexpected end of the MP4 box"))
+}
+
+/// Reads a big-endian 64-bit integer from the data
+fn read_u64(data: &[u8], position: usize) -> Result<u64, anyhow::Error> {
+  data
+    .get(position..position + 8)
+    .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap_or_default()))
+    .ok_or_else(|| anyhow::anyhow!("Unexpected end of the MP4 box"))
+}
+
+/// An MP4 box: the box type, the whole box and the box contents
+type Mp4Box<'a> = ([u8; 4], &'a [u8], &'a [u8]);
+
+/// Splits the data into boxes. Returns the box types, the whole boxes and the box contents.
+fn split_boxes(data: &[u8]) -> Result<Vec<Mp4Box<'_>>, anyhow::Error> {
+  let mut boxes = Vec::new();
+  let mut position = 0;
+  while position < data.len() {
+    let size = read_u32(data, position)? as u64;
+    let kind: [u8; 4] = data
+      .get(position + 4..position + 8)
+      .and_then(|kind| kind.try_into().ok())
+      .ok_or_else(|| anyhow::anyhow!("Unexpected end of the MP4 box"))?;
+    let (header_length, size): (usize, u64) = match size {
+      0 => (8, (data.len() - position) as u64),
+      1 => (16, read_u64(data, position + 8)?),
+      size => (8, size),
+    };
+    if size < header_length as u64 || size > (data.len() - position) as u64 {
+      Err(anyhow::anyhow!("Invalid MP4 box size"))?
+    }
+    let size = size as usize;
+    boxes.push((
+      kind,
+      &data[position..position + size],
+      &data[position + header_length..position + size],
+    ));
+    position += size;
+  }
+  Ok(boxes)
+}
+
+/// Finds the contents of the child box with the specified type
+fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Result<Option<&'a [u8]>, anyhow::Error> {
+  Ok(
+    split_boxes(data)?
+      .into_iter()
+      .find(|(box_kind, _, _)| box_kind == kind)
+      .map(|(_, _, contents)| contents),
+  )
+}
+
+/// Writes an MP4 box with the contents
+fn write_box(output: &mut Vec<u8>, kind: &[u8; 4], contents: &[u8]) {
+  output.extend_from_slice(&(contents.len() as u32 + 8).to_be_bytes());
+  output.extend_from_slice(kind);
+  output.extend_from_slice(contents);
+}
+
+/// Reads the timescale and the duration from the `mvhd` or `mdhd` box contents
+fn read_timescale_and_duration(contents: &[u8]) -> Result<(u32, u64), anyhow::Error> {
+  if contents.first() == Some(&1) {
+    Ok((read_u32(contents, 20)?, read_u64(contents, 24)?))
+  } else {
+    Ok((read_u32(contents, 12)?, read_u32(contents, 16)? as u64))
+  }
+}
+
+/// Writes the `mvhd`, `tkhd` or `mdhd` box with the duration replaced
+fn write_header_box_with_duration(output: &mut Vec<u8>, kind: &[u8; 4], contents: &[u8], duration: u64) {
+  let mut contents = contents.to_vec();
+  let is_version_1 = contents.first() == Some(&1);
+  let duration_position = match (kind, is_version_1) {
+    (b"tkhd", true) => 28,
+    (b"tkhd", false) => 20,
+    (_, true) => 24,
+    (_, false) => 16,
+  };
+  if is_version_1 {
+    if let Some(field) = contents.get_mut(duration_position..duration_position + 8) {
+      field.copy_from_slice(&duration.to_be_bytes());
+    }
+  } else if let Some(field) = contents.get_mut(duration_position..duration_position + 4) {
+    field.copy_from_slice(&(duration.min(u32::MAX as u64) as u32).to_be_bytes());
+  }
+  write_box(output, kind, &contents);
+}
+
+/// Sample tables of an MP4 track
+#[derive(Clone, Default)]
+struct SampleTables {
+  timescale: u32,
+  time_to_sample: Vec<(u32, u32)>,
+  composition_offsets: Option<(u8, Vec<(u32, u32)>)>,
+  sync_samples: Option<Vec<u32>>,
+  sample_to_chunk: Vec<(u32, u32, u32)>,
+  sample_size: u32,
+  sample_sizes: Vec<u32>,
+  sample_count: u32,
+  chunk_offsets: Vec<u64>,
+  uses_co64: bool,
+}
+
+impl SampleTables {
+  /// Parses the sample tables from the `trak` box contents
+  fn parse(trak: &[u8]) -> Result<Self, anyhow::Error> {
+    let mdia = find_box(trak, b"mdia")?.ok_or_else(|| anyhow::anyhow!("The MP4 track has no media box"))?;
+    let mdhd = find_box(mdia, b"mdhd")?.ok_or_else(|| anyhow::anyhow!("The MP4 track has no media header"))?;
+    let minf = find_box(mdia, b"minf")?.ok_or_else(|| anyhow::anyhow!("The MP4 track has no media information"))?;
+    let stbl = find_box(minf, b"stbl")?.ok_or_else(|| anyhow::anyhow!("The MP4 track has no sample table"))?;
+    let (timescale, _) = read_timescale_and_duration(mdhd)?;
+
+    let mut tables = Self {
+      timescale,
+      ..Default::default()
+    };
+    for (kind, _, contents) in split_boxes(stbl)? {
+      match &kind {
+        b"stts" => {
+          tables.time_to_sample = read_table(contents, 4, 8, |entry| Ok((read_u32(entry, 0)?, read_u32(entry, 4)?)))?
+        }
+        b"ctts" => {
+          tables.composition_offsets = Some((
+            contents.first().copied().unwrap_or(0),
+            read_table(contents, 4, 8, |entry| Ok((read_u32(entry, 0)?, read_u32(entry, 4)?)))?,
+          ))
+        }
+        b"stss" => tables.sync_samples = Some(read_table(contents, 4, 4, |entry| read_u32(entry, 0))?),
+        b"stsc" => {
+          tables.sample_to_chunk = read_table(contents, 4, 12, |entry| {
+            Ok((read_u32(entry, 0)?, read_u32(entry, 4)?, read_u32(entry, 8)?))
+          })?
+        }
+        b"stsz" => {
+          tables.sample_size = read_u32(contents, 4)?;
+          tables.sample_count = read_u32(contents, 8)?;
+          if tables.sample_size == 0 {
+            tables.sample_sizes = read_table(contents, 8, 4, |entry| read_u32(entry, 0))?;
+            if tables.sample_sizes.len() != tables.sample_count as usize {
+              Err(anyhow::anyhow!("Invalid MP4 sample size table"))?
+            }
+          }
+        }
+        b"stz2" => Err(anyhow::anyhow!("Compact MP4 sample size tables aren't supported"))?,
+        b"stco" => tables.chunk_offsets = read_table(contents, 4, 4, |entry| Ok(read_u32(entry, 0)? as u64))?,
+        b"co64" => {
+          tables.chunk_offsets = read_table(contents, 4, 8, |entry| read_u64(entry, 0))?;
+          tables.uses_co64 = true;
+        }
+        _ => (),
+      }
+    }
+    Ok(tables)
+  }
+
+  /// Obtains the size of the sample
+  fn size_of(&self, sample: u32) -> u64 {
+    if self.sample_size != 0 {
+      self.sample_size as u64
+    } else {
+      self.sample_sizes.get(sample as usize).copied().unwrap_or(0) as u64
+    }
+  }
+
+  /// Obtains the first sample with decoding time not earlier than the specified time (in the track timescale)
+  fn sample_at_time(&self, time: u64) -> u32 {
+    let mut sample = 0u32;
+    let mut sample_time = 0u64;
+    for &(count, delta) in &self.time_to_sample {
+      let entry_duration = count as u64 * delta as u64;
+      if time <= sample_time + entry_duration {
+        let samples = if delta == 0 {
+          0
+        } else {
+          (time - sample_time).div_ceil(delta as u64) as u32
+        };
+        return sample.saturating_add(samples.min(count));
+      }
+      sample = sample.saturating_add(count);
+      sample_time += entry_duration;
+    }
+    sample
+  }
+
+  /// Obtains the decoding time of the sample (in the track timescale)
+  fn time_of_sample(&self, sample: u32) -> u64 {
+    let mut remaining = sample;
+    let mut sample_time = 0u64;
+    for &(count, delta) in &self.time_to_sample {
+      if remaining < count {
+        return sample_time + remaining as u64 * delta as u64;
+      }
+      remaining -= count;
+      sample_time += count as u64 * delta as u64;
+    }
+    sample_time
+  }
+
+  /// Obtains the duration of the track (in the track timescale)
+  fn duration(&self) -> u64 {
+    self
+      .time_to_sample
+      .iter()
+      .map(|&(count, delta)| count as u64 * delta as u64)
+      .sum()
+  }
+
+  /// Removes the samples preceding the start sample from the sample tables
+  fn trim(&self, start: u32) -> Result<Self, anyhow::Error> {
+    if start >= self.sample_count {
+      // The track ends before the start time
+      return Ok(Self {
+        timescale: self.timescale,
+        composition_offsets: self
+          .composition_offsets
+          .as_ref()
+          .map(|(version, _)| (*version, Vec::new())),
+        sync_samples: self.sync_samples.as_ref().map(|_| Vec::new()),
+        sample_size: self.sample_size,
+        uses_co64: self.uses_co64,
+        ..Default::default()
+      });
+    }
+
+    if self.sample_to_chunk.first().is_some_and(|entry| entry.0 != 1)
+      || self
+        .sample_to_chunk
+        .windows(2)
+        .any(|entries| entries[1].0 <= entries[0].0)
+      || self.sample_to_chunk.iter().any(|entry| entry.1 == 0)
+    {
+      Err(anyhow::anyhow!("Invalid MP4 sample-to-chunk table"))?
+    }
+
+    // Locate the chunk containing the start sample
+    let chunk_count = self.chunk_offsets.len() as u64;
+    let mut first_sample_in_run = 0u64;
+    let mut located = None;
+    for (index, &(first_chunk, samples_per_chunk, _)) in self.sample_to_chunk.iter().enumerate() {
+      let next_first_chunk = self
+        .sample_to_chunk
+        .get(index + 1)
+        .map_or(chunk_count + 1, |entry| entry.0 as u64);
+      if next_first_chunk <= first_chunk as u64 {
+        Err(anyhow::anyhow!("Invalid MP4 sample-to-chunk table"))?
+      }
+      let samples_in_run = (next_first_chunk - first_chunk as u64) * samples_per_chunk as u64;
+      if (start as u64) < first_sample_in_run + samples_in_run {
+        let samples_into_run = start as u64 - first_sample_in_run;
+        let chunk = first_chunk as u64 - 1 + samples_into_run / samples_per_chunk as u64;
+        let samples_into_chunk = (samples_into_run % samples_per_chunk as u64) as u32;
+        located = Some((index, chunk, samples_into_chunk, next_first_chunk));
+        break;
+      }
+      first_sample_in_run += samples_in_run;
+    }
+    let (run_index, chunk, samples_into_chunk, next_first_chunk) =
+      located.ok_or_else(|| anyhow::anyhow!("The MP4 sample-to-chunk table doesn't cover all samples"))?;
+    let (_, samples_per_chunk, description_index) = self.sample_to_chunk[run_index];
+
+    let mut chunk_offsets = self
+      .chunk_offsets
+      .get(chunk as usize..)
+      .ok_or_else(|| anyhow::anyhow!("Invalid MP4 chunk offset table"))?
+      .to_vec();
+    let skipped_in_chunk = (start - samples_into_chunk..start)
+      .map(|sample| self.size_of(sample))
+      .sum::<u64>();
+    chunk_offsets[0] += skipped_in_chunk;
+
+    let mut sample_to_chunk = vec![(1, samples_per_chunk - samples_into_chunk, description_index)];
+    if chunk + 2 < next_first_chunk {
+      sample_to_chunk.push((2, samples_per_chunk, description_index));
+    }
+    for &(first_chunk, samples_per_chunk, description_index) in &self.sample_to_chunk[run_index + 1..] {
+      sample_to_chunk.push((
+        (first_chunk as u64 - chunk) as u32,
+        samples_per_chunk,
+        description_index,
+      ));
+    }
+
+    Ok(Self {
+      timescale: self.timescale,
+      time_to_sample: skip_run_length_entries(&self.time_to_sample, start),
+      composition_offsets: self
+        .composition_offsets
+        .as_ref()
+        .map(|(version, entries)| (*version, skip_run_length_entries(entries, start))),
+      sync_samples: self.sync_samples.as_ref().map(|sync_samples| {
+        sync_samples
+          .iter()
+          .filter(|&&sample| sample > start)
+          .map(|sample| sample - start)
+          .collect()
+      }),
+      sample_to_chunk,
+      sample_size: self.sample_size,
+      sample_sizes: self.sample_sizes.get(start as usize..).unwrap_or_default().to_vec(),
+      sample_count: self.sample_count - start,
+      chunk_offsets,
+      uses_co64: self.uses_co64,
+    })
+  }
+
+  /// Writes the sample tables, with the chunk offsets shifted
+  fn write(&self, output: &mut Vec<u8>, offset_shift: i128, force_co64: bool) -> Result<(), anyhow::Error> {
+    let mut contents = Vec::new();
+    write_table(&mut contents, &[0; 4], &self.time_to_sample, |c, &(count, delta)| {
+      c.extend_from_slice(&count.to_be_bytes());
+      c.extend_from_slice(&delta.to_be_bytes());
+    });
+    write_box(output, b"stts", &contents);
+
+    if let Some((version, entries)) = &self.composition_offsets {
+      contents.clear();
+      write_table(&mut contents, &[*version, 0, 0, 0], entries, |c, &(count, offset)| {
+        c.extend_from_slice(&count.to_be_bytes());
+        c.extend_from_slice(&offset.to_be_bytes());
+      });
+      write_box(output, b"ctts", &contents);
+    }
+
+    if let Some(sync_samples) = &self.sync_samples {
+      contents.clear();
+      write_table(&mut contents, &[0; 4], sync_samples, |c, sample| {
+        c.extend_from_slice(&sample.to_be_bytes())
+      });
+      write_box(output, b"stss", &contents);
+    }
+
+    contents.clear();
+    write_table(
+      &mut contents,
+      &[0; 4],
+      &self.sample_to_chunk,
+      |c, &(first_chunk, samples_per_chunk, description_index)| {
+        c.extend_from_slice(&first_chunk.to_be_bytes());
+        c.extend_from_slice(&samples_per_chunk.to_be_bytes());
+        c.extend_from_slice(&description_index.to_be_bytes());
+      },
+    );
+    write_box(output, b"stsc", &contents);
+
+    contents.clear();
+    contents.extend_from_slice(&[0; 4]);
+    contents.extend_from_slice(&self.sample_size.to_be_bytes());
+    contents.extend_from_slice(&self.sample_count.to_be_bytes());
+    if self.sample_size == 0 {
+      for sample_size in &self.sample_sizes {
+        contents.extend_from_slice(&sample_size.to_be_bytes());
+      }
+    }
+    write_box(output, b"stsz", &contents);
+
+    let shifted_offsets = self
+      .chunk_offsets
+      .iter()
+      .map(|&offset| {
+        u64::try_from(offset as i128 + offset_shift).map_err(|_| anyhow::anyhow!("Invalid MP4 chunk offset"))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    contents.clear();
+    if self.uses_co64 || force_co64 {
+      write_table(&mut contents, &[0; 4], &shifted_offsets, |c, offset| {
+        c.extend_from_slice(&offset.to_be_bytes())
+      });
+      write_box(output, b"co64", &contents);
+    } else {
+      write_table(&mut contents, &[0; 4], &shifted_offsets, |c, offset| {
+        c.extend_from_slice(&(*offset as u32).to_be_bytes())
+      });
+      write_box(output, b"stco", &contents);
+    }
+    Ok(())
+  }
+}
+
+/// Reads the table entries from the full box contents, with the entry count following the version and flags
+fn read_table<T>(
+  contents: &[u8],
+  count_position: usize,
+  entry_size: usize,
+  read_entry: impl Fn(&[u8]) -> Result<T, anyhow::Error>,
+) -> Result<Vec<T>, anyhow::Error> {
+  let count = read_u32(contents, count_position)? as usize;
+  let entries = &contents[(count_position + 4).min(contents.len())..];
+  if entries.len() / entry_size < count {
+    Err(anyhow::anyhow!("Unexpected end of the MP4 box"))?
+  }
+  entries.chunks_exact(entry_size).take(count).map(read_entry).collect()
+}
+
+/// Writes the table entries into the full box contents
+fn write_table<T>(
+  contents: &mut Vec<u8>,
+  version_and_flags: &[u8; 4],
+  entries: &[T],
+  write_entry: impl Fn(&mut Vec<u8>, &T),
+) {
+  contents.extend_from_slice(version_and_flags);
+  contents.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+  for entry in entries {
+    write_entry(contents, entry);
+  }
+}
+
+/// Skips the samples in the run-length encoded table (like `stts` or `ctts`)
+fn skip_run_length_entries(entries: &[(u32, u32)], samples: u32) -> Vec<(u32, u32)> {
+  let mut remaining = samples;
+  let mut skipped_entries = Vec::new();
+  for &(count, value) in entries {
+    if remaining >= count {
+      remaining -= count;
+    } else {
+      skipped_entries.push((count - remaining, value));
+      remaining = 0;
+    }
+  }
+  skipped_entries
+}
+
+/// Remuxes the MP4 file for pseudo-streaming from the start time (in milliseconds). The start time is adjusted to
+/// the preceding key frame of the video track, and the `moov` box is placed before the media data.
+pub fn remux_mp4(
+  ftyp: &[u8],
+  moov: &[u8],
+  mdat_data: (u64, u64),
+  start_milliseconds: u64,
+) -> Result<Mp4PseudoStream, anyhow::Error> {
+  let (_, _, moov_contents) = split_boxes(moov)?
+    .into_iter()
+    .next()
+    .ok_or_else(|| anyhow::anyhow!("Invalid MP4 movie box"))?;
+  let moov_children = split_boxes(moov_contents)?;
+  if moov_children.iter().any(|(kind, _, _)| kind == b"mvex") {
+    Err(anyhow::anyhow!("Fragmented MP4 files aren't supported"))?
+  }
+  let mvhd = moov_children
+    .iter()
+    .find(|(kind, _, _)| kind == b"mvhd")
+    .map(|(_, _, contents)| *contents)
+    .ok_or_else(|| anyhow::anyhow!("The MP4 file has no movie header"))?;
+  let (movie_timescale, _) = read_timescale_and_duration(mvhd)?;
+
+  let tracks = moov_children
+    .iter()
+    .filter(|(kind, _, _)| kind == b"trak")
+    .map(|(_, _, contents)| -> Result<_, anyhow::Error> { Ok((*contents, SampleTables::parse(contents)?)) })
+    .collect::<Result<Vec<_>, anyhow::Error>>()?;
+  if tracks.is_empty() {
+    Err(anyhow::anyhow!("The MP4 file has no tracks"))?
+  }
+
+  // The start time is adjusted to the key frame of the first track with sync samples (usually the video track)
+  let (reference_time, reference_timescale) = match tracks
+    .iter()
+    .map(|(_, tables)| tables)
+    .find(|tables| tables.sync_samples.is_some())
+  {
+    Some(tables) => {
+      let start_time = start_milliseconds as u128 * tables.timescale as u128 / 1000;
+      let start_sample = tables.sample_at_time(start_time.min(u64::MAX as u128) as u64);
+      if start_sample >= tables.sample_count {
+        Err(anyhow::anyhow!("The start time exceeds the duration of the MP4 file"))?
+      }
+      let key_frame = tables
+        .sync_samples
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|&sample| sample > 0 && sample <= start_sample + 1)
+        .max()
+        .unwrap_or(1);
+      (tables.time_of_sample(key_frame - 1), tables.timescale.max(1))
+    }
+    None => (start_milliseconds, 1000),
+  };
+
+  let trimmed_tracks = tracks
+    .iter()
+    .map(|(trak, tables)| -> Result<_, anyhow::Error> {
+      let start_time = reference_time as u128 * tables.timescale as u128 / reference_timescale as u128;
+      let start_sample = tables.sample_at_time(start_time.min(u64::MAX as u128) as u64);
+      Ok((*trak, tables.trim(start_sample)?))
+    })
+    .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+  // Determine the part of the media data to send
+  let (mdat_data_offset, mdat_data_size) = mdat_data;
+  let mdat_data_end = mdat_data_offset + mdat_data_size;
+  let mut data_offset = mdat_data_end;
+  for (_, tables) in &trimmed_tracks {
+    for &chunk_offset in &tables.chunk_offsets {
+      if chunk_offset < mdat_data_offset || chunk_offset > mdat_data_end {
+        Err(anyhow::anyhow!("The MP4 chunk is outside the media data box"))?
+      }
+    }
+    if let Some(&first_chunk_offset) = tables.chunk_offsets.first() {
+      data_offset = data_offset.min(first_chunk_offset);
+    }
+  }
+  let data_length = mdat_data_end - data_offset;
+  let mdat_header_length = if data_length + 8 > u32::MAX as u64 { 16 } else { 8 };
+
+  let movie_duration = trimmed_tracks
+    .iter()
+    .map(|(_, tables)| {
+      (tables.duration() as u128 * movie_timescale as u128 / tables.timescale.max(1) as u128).min(u64::MAX as u128)
+        as u64
+    })
+    .max()
+    .unwrap_or(0);
+  let build_moov = |offset_shift: i128, force_co64: bool| -> Result<Vec<u8>, anyhow::Error> {
+    let mut contents = Vec::new();
+    let mut trimmed_tracks_iter = trimmed_tracks.iter();
+    for (kind, whole_box, child_contents) in &moov_children {
+      match kind {
+        b"mvhd" => write_header_box_with_duration(&mut contents, b"mvhd", child_contents, movie_duration),
+        b"trak" => {
+          let (trak, tables) = trimmed_tracks_iter
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Invalid MP4 track"))?;
+          let media_duration = tables.duration();
+          let track_duration = (media_duration as u128 * movie_timescale as u128 / tables.timescale.max(1) as u128)
+            .min(u64::MAX as u128) as u64;
+          let mut trak_contents = Vec::new();
+          write_trak(
+            &mut trak_contents,
+            trak,
+            tables,
+            media_duration,
+            track_duration,
+            offset_shift,
+            force_co64,
+          )?;
+          write_box(&mut contents, b"trak", &trak_contents);
+        }
+        _ => contents.extend_from_slice(whole_box),
+      }
+    }
+    let mut moov = Vec::new();
+    write_box(&mut moov, b"moov", &contents);
+    Ok(moov)
+  };
+
+  // The size of the "moov" box doesn't depend on the chunk offsets, so it's determined before the offsets are shifted
+  let header_length = |moov_length: usize| (ftyp.len() + moov_length) as u64 + mdat_header_length;
+  let mut force_co64 = false;
+  let mut new_header_length = header_length(build_moov(0, false)?.len());
+  if new_header_length + data_length > u32::MAX as u64 {
+    force_co64 = true;
+    new_header_length = header_length(build_moov(0, true)?.len());
+  }
+  let moov = build_moov(new_header_length as i128 - data_offset as i128, force_co64)?;
+
+  let mut header = Vec::with_capacity(new_header_length as usize);
+  header.extend_from_slice(ftyp);
+  header.extend_from_slice(&moov);
+  if mdat_header_length == 16 {
+    header.extend_from_slice(&1u32.to_be_bytes());
+    header.extend_from_slice(b"mdat");
+    header.extend_from_slice(&(data_length + 16).to_be_bytes());
+  } else {
+    header.extend_from_slice(&(data_length as u32 + 8).to_be_bytes());
+    header.extend_from_slice(b"mdat");
+  }
+
+  Ok(Mp4PseudoStream {
+    header,
+    data_offset,
+    data_length,
+  })
+}
+
+/// Writes the `trak` box contents with the trimmed sample tables. Edit lists are removed, since they would no longer match.
+fn write_trak(
+  output: &mut Vec<u8>,
+  trak: &[u8],
+  tables: &SampleTables,
+  media_duration: u64,
+  track_duration: u64,
+  offset_shift: i128,
+  force_co64: bool,
+) -> Result<(), anyhow::Error> {
+  for (kind, whole_box, contents) in split_boxes(trak)? {
+    match &kind {
+      b"tkhd" => write_header_box_with_duration(output, b"tkhd", contents, track_duration),
+      b"edts" => (),
+      b"mdia" => {
+        let mut mdia_contents = Vec::new();
+        for (kind, whole_box, contents) in split_boxes(contents)? {
+          match &kind {
+            b"mdhd" => write_header_box_with_duration(&mut mdia_contents, b"mdhd", contents, media_duration),
+            b"minf" => {
+              let mut minf_contents = Vec::new();
+              for (kind, whole_box, contents) in split_boxes(contents)? {
+                if &kind == b"stbl" {
+                  let mut stbl_contents = Vec::new();
+                  // The sample description is kept, while other per-sample tables (like sample groups) are dropped
+                  if let Some((_, stsd, _)) = split_boxes(contents)?.into_iter().find(|(kind, _, _)| kind == b"stsd") {
+                    stbl_contents.extend_from_slice(stsd);
+                  }
+                  tables.write(&mut stbl_contents, offset_shift, force_co64)?;
+                  write_box(&mut minf_contents, b"stbl", &stbl_contents);
+                } else {
+                  minf_contents.extend_from_slice(whole_box);
+                }
+              }
+              write_box(&mut mdia_contents, b"minf", &minf_contents);
+            }
+            _ => mdia_contents.extend_from_slice(whole_box),
+          }
+        }
+        write_box(output, b"mdia", &mdia_contents);
+      }
+      _ => output.extend_from_slice(whole_box),
+    }
+  }
+  Ok(())
+}
+
+/// The header of an FLV file, prepended to the FLV pseudo-streaming responses
+pub const FLV_HEADER: &[u8] = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00";
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn mp4_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    write_box(&mut output, kind, contents);
+    output
+  }
+
+  fn full_box_table(entries: &[u32]) -> Vec<u8> {
+    let mut contents = vec![0; 4];
+    for entry in entries {
+      contents.extend_from_slice(&entry.to_be_bytes());
+    }
+    contents
+  }
+
+  #[test]
+  fn test_remux_mp4() {
+    let mut mvhd = vec![0; 4];
+    mvhd.extend_from_slice(&[0; 8]);
+    mvhd.extend_from_slice(&1000u32.to_be_bytes());
+    mvhd.extend_from_slice(&4000u32.to_be_bytes());
+    let mut tkhd = vec![0; 4];
+    tkhd.extend_from_slice(&[0; 16]);
+    tkhd.extend_from_slice(&4000u32.to_be_bytes());
+    let stbl = [
+      mp4_box(b"stsd", &[0; 8]),
+      mp4_box(b"stts", &full_box_table(&[1, 4, 1000])),
+      mp4_box(b"stss", &full_box_table(&[2, 1, 3])),
+      mp4_box(b"stsc", &full_box_table(&[1, 1, 2, 1])),
+      mp4_box(b"stsz", &full_box_table(&[0, 4, 10, 20, 30, 40])),
+      mp4_box(b"stco", &full_box_table(&[2, 100, 130])),
+    ]
+    .concat();
+    let trak = [
+      mp4_box(b"tkhd", &tkhd),
+      mp4_box(b"edts", &[0; 8]),
+      mp4_box(
+        b"mdia",
+        &[mp4_box(b"mdhd", &mvhd), mp4_box(b"minf", &mp4_box(b"stbl", &stbl))].concat(),
+      ),
+    ]
+    .concat();
+    let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &mvhd), mp4_box(b"trak", &trak)].concat());
+    let ftyp = mp4_box(b"ftyp", b"isom\x00\x00\x02\x00");
+
+    // The start time of 2.5 seconds is adjusted to the key frame at 2 seconds
+    let remuxed = remux_mp4(&ftyp, &moov, (100, 100), 2500).unwrap();
+    assert_eq!(remuxed.data_offset, 130);
+    assert_eq!(remuxed.data_length, 70);
+    assert!(remuxed.header.starts_with(&ftyp));
+    assert!(remuxed.header.ends_with(&[0, 0, 0, 78, b'm', b'd', b'a', b't']));
+
+    let (_, _, moov_contents) = split_boxes(&remuxed.header[ftyp.len()..remuxed.header.len() - 8]).unwrap()[0];
+    let (movie_timescale, movie_duration) =
+      read_timescale_and_duration(find_box(moov_contents, b"mvhd").unwrap().unwrap()).unwrap();
+    assert_eq!((movie_timescale, movie_duration), (1000, 2000));
+    let trak = find_box(moov_contents, b"trak").unwrap().unwrap();
+    assert!(find_box(trak, b"edts").unwrap().is_none());
+    let tables = SampleTables::parse(trak).unwrap();
+    assert_eq!(tables.time_to_sample, vec![(2, 1000)]);
+    assert_eq!(tables.sync_samples, Some(vec![1]));
+    assert_eq!(tables.sample_sizes, vec![30, 40]);
+    assert_eq!(tables.sample_to_chunk, vec![(1, 2, 1)]);
+    assert_eq!(tables.chunk_offsets, vec![remuxed.header.len() as u64]);
+  }
+
+  #[test]
+  fn test_trim_sample_tables_within_chunk() {
+    let tables = SampleTables {
+      timescale: 1000,
+      time_to_sample: vec![(6, 100)],
+      sample_to_chunk: vec![(1, 3, 1)],
+      sample_size: 10,
+      sample_count: 6,
+      chunk_offsets: vec![1000, 2000],
+      ..Default::default()
+    };
+    assert_eq!(tables.sample_at_time(150), 2);
+    let trimmed = tables.trim(1).unwrap();
+    assert_eq!(trimmed.sample_to_chunk, vec![(1, 2, 1), (2, 3, 1)]);
+    assert_eq!(trimmed.chunk_offsets, vec![1010, 2000]);
+    assert_eq!(trimmed.sample_count, 5);
+    assert_eq!(trimmed.time_to_sample, vec![(5, 100)]);
+  }
+
+  #[test]
+  fn test_read_mp4_layout() {
+    let file = [
+      mp4_box(b"ftyp", b"isom"),
+      mp4_box(b"mdat", &[0; 16]),
+      mp4_box(b"moov", &[0; 4]),
+    ]
+    .concat();
+    let layout = read_mp4_layout(&mut io::Cursor::new(&file), file.len() as u64)
+      .unwrap()
+      .unwrap();
+    assert_eq!(
+      layout,
+      Mp4Layout {
+        ftyp: Some((0, 12)),
+        moov: (36, 12),
+        mdat_data: (20, 16),
+      }
+    );
+  }
+}
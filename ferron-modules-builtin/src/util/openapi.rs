@@ -0,0 +1,980 @@
+use std::collections::HashMap;
+
+use fancy_regex::Regex;
+use hyper::{header, Method, StatusCode};
+use serde_json::Value;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// The maximum depth of nested schemas and references, protecting against reference cycles
+const MAX_SCHEMA_DEPTH: usize = 64;
+
+/// The HTTP methods, for which OpenAPI operations can be defined
+const OPERATION_METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// An error of the request validation against an OpenAPI specification
+#[derive(Debug, PartialEq)]
+pub struct OpenApiValidationError {
+  /// The response status code
+  pub status: StatusCode,
+  /// The location of the invalid part of the request (like `query.limit` or `body.name`)
+  pub location: Option<String>,
+  /// The description of the error
+  pub message: String,
+  /// The methods allowed for the request path (for "405 Method Not Allowed" responses)
+  pub allowed_methods: Vec<String>,
+}
+
+impl OpenApiValidationError {
+  /// Creates a new "400 Bad Request" validation error
+  fn bad_request(location: impl Into<String>, message: impl Into<String>) -> Self {
+    Self {
+      status: StatusCode::BAD_REQUEST,
+      location: Some(location.into()),
+      message: message.into(),
+      allowed_methods: Vec::new(),
+    }
+  }
+
+  /// Serializes the validation error as a problem details (RFC 9457) JSON object
+  pub fn to_problem_details(&self) -> Value {
+    let mut problem_details = serde_json::json!({
+      "type": "about:blank",
+      "title": self.status.canonical_reason().unwrap_or("Bad Request"),
+      "status": self.status.as_u16(),
+      "detail": self.message,
+    });
+    if let Some(location) = &self.location {
+      problem_details["location"] = Value::String(location.clone());
+    }
+    problem_details
+  }
+}
+
+/// Converts a YAML value into a JSON value
+fn yaml_to_json(yaml: Yaml) -> Value {
+  match yaml {
+    Yaml::Real(value) => value
+      .parse::<f64>()
+      .ok()
+      .and_then(serde_json::Number::from_f64)
+      .map_or(Value::Null, Value::Number),
+    Yaml::Integer(value) => Value::from(value),
+    Yaml::String(value) => Value::String(value),
+    Yaml::Boolean(value) => Value::Bool(value),
+    Yaml::Array(values) => Value::Array(values.into_iter().map(yaml_to_json).collect()),
+    Yaml::Hash(values) => Value::Object(
+      values
+        .into_iter()
+        .map(|(key, value)| {
+          let key = match key {
+            Yaml::String(key) | Yaml::Real(key) => key,
+            Yaml::Integer(key) => key.to_string(),
+            Yaml::Boolean(key) => key.to_string(),
+            _ => String::new(),
+          };
+          (key, yaml_to_json(value))
+        })
+        .collect(),
+    ),
+    _ => Value::Null,
+  }
+}
+
+/// Collects and compiles the regular expressions from the `pattern` keywords of the schemas
+fn collect_patterns(value: &Value, patterns: &mut HashMap<String, Regex>) {
+  match value {
+    Value::Object(object) => {
+      for (key, value) in object {
+        match (key.as_str(), value) {
+          ("pattern", Value::String(pattern)) => {
+            if !patterns.contains_key(pattern) {
+              if let Ok(regex) = Regex::new(pattern) {
+                patterns.insert(pattern.clone(), regex);
+              }
+            }
+          }
+          _ => collect_patterns(value, patterns),
+        }
+      }
+    }
+    Value::Array(values) => {
+      for value in values {
+        collect_patterns(value, patterns);
+      }
+    }
+    _ => (),
+  }
+}
+
+/// A segment of an OpenAPI path template
+enum PathSegment {
+  /// A literal segment
+  Literal(String),
+  /// A segment with a path parameter, optionally surrounded by literal text (like `{id}.json`)
+  Parameter {
+    prefix: String,
+    name: String,
+    suffix: String,
+  },
+}
+
+/// Parses the OpenAPI path template into segments
+fn parse_path_template(template: &str) -> Vec<PathSegment> {
+  template
+    .trim_matches('/')
+    .split('/')
+    .map(|segment| {
+      if let (Some(start), Some(end)) = (segment.find('{'), segment.rfind('}')) {
+        if start < end {
+          return PathSegment::Parameter {
+            prefix: segment[..start].to_string(),
+            name: segment[start + 1..end].to_string(),
+            suffix: segment[end + 1..].to_string(),
+          };
+        }
+      }
+      PathSegment::Literal(segment.to_string())
+    })
+    .collect()
+}
+
+/// Matches the request path segments against the path template.
+/// Returns the number of literal segments (for preferring concrete paths) and the path parameters.
+fn match_path_template(template: &[PathSegment], segments: &[&str]) -> Option<(usize, Vec<(String, String)>)> {
+  if template.len() != segments.len() {
+    return None;
+  }
+  let mut literal_segments = 0;
+  let mut parameters = Vec::new();
+  for (template_segment, segment) in template.iter().zip(segments) {
+    match template_segment {
+      PathSegment::Literal(literal) => {
+        if literal != segment {
+          return None;
+        }
+        literal_segments += 1;
+      }
+      PathSegment::Parameter { prefix, name, suffix } => {
+        let value = segment.strip_prefix(prefix.as_str())?.strip_suffix(suffix.as_str())?;
+        if value.is_empty() {
+          return None;
+        }
+        let value = urlencoding::decode(value).map_or_else(|_| value.to_string(), |v| v.into_owned());
+        parameters.push((name.clone(), value));
+      }
+    }
+  }
+  Some((literal_segments, parameters))
+}
+
+/// Obtains the media type essence (without parameters, in lowercase) from the `Content-Type` header value
+fn media_type_essence(content_type: &str) -> String {
+  content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+/// Checks whether the media type is a JSON media type
+fn is_json_media_type(media_type: &str) -> bool {
+  media_type == "application/json" || media_type.ends_with("+json")
+}
+
+/// An OpenAPI 3 specification, against which requests are validated
+pub struct OpenApiSpec {
+  document: Value,
+  base_path: String,
+  paths: Vec<(Vec<PathSegment>, String)>,
+  patterns: HashMap<String, Regex>,
+}
+
+impl OpenApiSpec {
+  /// Parses the OpenAPI 3 specification in the JSON or YAML format.
+  /// If the base path isn't specified, it's determined from the first server URL in the specification.
+  pub fn parse(content: &str, base_path: Option<&str>) -> Result<Self, anyhow::Error> {
+    let document = match serde_json::from_str::<Value>(content) {
+      Ok(document) => document,
+      Err(_) => yaml_to_json(
+        YamlLoader::load_from_str(content)
+          .map_err(|err| anyhow::anyhow!("Invalid OpenAPI specification: {err}"))?
+          .into_iter()
+          .next()
+          .unwrap_or(Yaml::Null),
+      ),
+    };
+    // The version can be parsed as a number from YAML (like `openapi: 3.1`)
+    if !document
+      .get("openapi")
+      .map(|v| v.as_str().map_or_else(|| v.to_string(), |v| v.to_string()))
+      .is_some_and(|v| v.starts_with("3."))
+    {
+      Err(anyhow::anyhow!("Only OpenAPI 3 specifications are supported"))?
+    }
+    let Some(paths_object) = document.get("paths").and_then(|v| v.as_object()) else {
+      return Err(anyhow::anyhow!("The OpenAPI specification doesn't define any paths"));
+    };
+    let paths = paths_object
+      .keys()
+      .map(|template| (parse_path_template(template), template.clone()))
+      .collect();
+
+    let base_path = match base_path {
+      Some(base_path) => base_path.to_string(),
+      None => document
+        .get("servers")
+        .and_then(|v| v.get(0))
+        .and_then(|v| v.get("url"))
+        .and_then(|v| v.as_str())
+        .filter(|url| !url.contains('{'))
+        .map(|url| match url.split_once("://") {
+          Some((_, rest)) => rest.find('/').map_or("", |index| &rest[index..]).to_string(),
+          None => url.to_string(),
+        })
+        .unwrap_or_default(),
+    };
+
+    let mut patterns = HashMap::new();
+    collect_patterns(&document, &mut patterns);
+
+    Ok(Self {
+      base_path: base_path.trim_end_matches('/').to_string(),
+      document,
+      paths,
+      patterns,
+    })
+  }
+
+  /// Resolves the local reference (like `#/components/schemas/Pet`) in the value, if there is any
+  fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+    for _ in 0..MAX_SCHEMA_DEPTH {
+      match value
+        .get("$ref")
+        .and_then(|v| v.as_str())
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| self.document.pointer(pointer))
+      {
+        Some(resolved) => value = resolved,
+        None => break,
+      }
+    }
+    value
+  }
+
+  /// Finds the OpenAPI operation matching the request method and path
+  pub fn match_operation(&self, method: &Method, path: &str) -> Result<OpenApiOperation<'_>, OpenApiValidationError> {
+    let not_found = || OpenApiValidationError {
+      status: StatusCode::NOT_FOUND,
+      location: Some("path".to_string()),
+      message: format!("The path `{path}` isn't defined in the API specification"),
+      allowed_methods: Vec::new(),
+    };
+    let relative_path = if self.base_path.is_empty() {
+      path
+    } else {
+      match path.strip_prefix(self.base_path.as_str()) {
+        Some(relative_path) if relative_path.is_empty() || relative_path.starts_with('/') => relative_path,
+        _ => return Err(not_found()),
+      }
+    };
+    let segments = relative_path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+    let (template, path_parameters) = self
+      .paths
+      .iter()
+      .filter_map(|(template, name)| {
+        match_path_template(template, &segments)
+          .map(|(literal_segments, parameters)| (literal_segments, name, parameters))
+      })
+      .max_by_key(|(literal_segments, _, _)| *literal_segments)
+      .map(|(_, name, parameters)| (name, parameters))
+      .ok_or_else(not_found)?;
+    let path_item = self.resolve(&self.document["paths"][template.as_str()]);
+
+    let method_name = method.as_str().to_ascii_lowercase();
+    let operation = path_item
+      .get(method_name.as_str())
+      .or_else(|| (method == Method::HEAD).then(|| path_item.get("get")).flatten())
+      .ok_or_else(|| OpenApiValidationError {
+        status: StatusCode::METHOD_NOT_ALLOWED,
+        location: Some("method".to_string()),
+        message: format!("The `{method}` method isn't allowed for the path `{path}`"),
+        allowed_methods: OPERATION_METHODS
+          .iter()
+          .filter(|method| path_item.get(**method).is_some())
+          .map(|method| method.to_ascii_uppercase())
+          .collect(),
+      })?;
+    let operation = self.resolve(operation);
+
+    // The operation parameters override the path item parameters with the same name and location
+    let mut parameters: Vec<&Value> = Vec::new();
+    for parameter in path_item
+      .get("parameters")
+      .and_then(|v| v.as_array())
+      .into_iter()
+      .flatten()
+      .chain(
+        operation
+          .get("parameters")
+          .and_then(|v| v.as_array())
+          .into_iter()
+          .flatten(),
+      )
+    {
+      let parameter = self.resolve(parameter);
+      parameters
+        .retain(|existing| existing.get("name") != parameter.get("name") || existing.get("in") != parameter.get("in"));
+      parameters.push(parameter);
+    }
+
+    Ok(OpenApiOperation {
+      spec: self,
+      path_parameters,
+      parameters,
+      request_body: operation.get("requestBody").map(|v| self.resolve(v)),
+    })
+  }
+
+  /// Validates the JSON value against the JSON schema
+  fn validate_value(
+    &self,
+    value: &Value,
+    schema: &Value,
+    location: &str,
+    depth: usize,
+  ) -> Result<(), OpenApiValidationError> {
+    if depth > MAX_SCHEMA_DEPTH {
+      return Ok(());
+    }
+    let schema = self.resolve(schema);
+    let schema_object = match schema {
+      Value::Bool(true) => return Ok(()),
+      Value::Bool(false) => Err(OpenApiValidationError::bad_request(location, "The value isn't allowed"))?,
+      Value::Object(schema_object) => schema_object,
+      _ => return Ok(()),
+    };
+
+    if value.is_null() && schema_object.get("nullable").and_then(|v| v.as_bool()) == Some(true) {
+      return Ok(());
+    }
+
+    if let Some(schema_type) = schema_object.get("type") {
+      let types = match schema_type {
+        Value::String(schema_type) => vec![schema_type.as_str()],
+        Value::Array(types) => types.iter().filter_map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+      };
+      let type_matches = types.iter().any(|schema_type| match *schema_type {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|v| v.fract() == 0.0),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+      });
+      if !types.is_empty() && !type_matches {
+        Err(OpenApiValidationError::bad_request(
+          location,
+          format!("The value must be of the `{}` type", types.join("` or `")),
+        ))?
+      }
+    }
+
+    if let Some(allowed_values) = schema_object.get("enum").and_then(|v| v.as_array()) {
+      if !allowed_values.contains(value) {
+        Err(OpenApiValidationError::bad_request(
+          location,
+          "The value isn't one of the allowed values",
+        ))?
+      }
+    }
+    if let Some(constant) = schema_object.get("const") {
+      if constant != value {
+        Err(OpenApiValidationError::bad_request(
+          location,
+          "The value isn't equal to the allowed value",
+        ))?
+      }
+    }
+
+    match value {
+      Value::String(string) => {
+        let length = string.chars().count() as u64;
+        if schema_object
+          .get("minLength")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|min_length| length < min_length)
+        {
+          Err(OpenApiValidationError::bad_request(location, "The string is too short"))?
+        }
+        if schema_object
+          .get("maxLength")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|max_length| length > max_length)
+        {
+          Err(OpenApiValidationError::bad_request(location, "The string is too long"))?
+        }
+        if let Some(pattern) = schema_object
+          .get("pattern")
+          .and_then(|v| v.as_str())
+          .and_then(|pattern| self.patterns.get(pattern))
+        {
+          if !pattern.is_match(string).unwrap_or(true) {
+            Err(OpenApiValidationError::bad_request(
+              location,
+              format!("The string doesn't match the `{}` pattern", pattern.as_str()),
+            ))?
+          }
+        }
+      }
+      Value::Number(number) => {
+        let number = number.as_f64().unwrap_or(0.0);
+        let minimum = schema_object.get("minimum").and_then(|v| v.as_f64());
+        let maximum = schema_object.get("maximum").and_then(|v| v.as_f64());
+        // OpenAPI 3.0 uses boolean exclusive limits, while OpenAPI 3.1 uses numeric exclusive limits
+        let (minimum, exclusive_minimum) = match schema_object.get("exclusiveMinimum") {
+          Some(Value::Bool(exclusive)) => (minimum, *exclusive),
+          Some(Value::Number(exclusive_minimum)) => (exclusive_minimum.as_f64(), true),
+          _ => (minimum, false),
+        };
+        let (maximum, exclusive_maximum) = match schema_object.get("exclusiveMaximum") {
+          Some(Value::Bool(exclusive)) => (maximum, *exclusive),
+          Some(Value::Number(exclusive_maximum)) => (exclusive_maximum.as_f64(), true),
+          _ => (maximum, false),
+        };
+        if minimum.is_some_and(|minimum| number < minimum || (exclusive_minimum && number == minimum)) {
+          Err(OpenApiValidationError::bad_request(location, "The number is too small"))?
+        }
+        if maximum.is_some_and(|maximum| number > maximum || (exclusive_maximum && number == maximum)) {
+          Err(OpenApiValidationError::bad_request(location, "The number is too large"))?
+        }
+        if let Some(multiple_of) = schema_object
+          .get("multipleOf")
+          .and_then(|v| v.as_f64())
+          .filter(|v| *v > 0.0)
+        {
+          let quotient = number / multiple_of;
+          if (quotient - quotient.round()).abs() > 1e-9 {
+            Err(OpenApiValidationError::bad_request(
+              location,
+              format!("The number must be a multiple of {multiple_of}"),
+            ))?
+          }
+        }
+      }
+      Value::Array(items) => {
+        let length = items.len() as u64;
+        if schema_object
+          .get("minItems")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|min_items| length < min_items)
+        {
+          Err(OpenApiValidationError::bad_request(
+            location,
+            "The array has too few items",
+          ))?
+        }
+        if schema_object
+          .get("maxItems")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|max_items| length > max_items)
+        {
+          Err(OpenApiValidationError::bad_request(
+            location,
+            "The array has too many items",
+          ))?
+        }
+        if schema_object.get("uniqueItems").and_then(|v| v.as_bool()) == Some(true) {
+          for (index, item) in items.iter().enumerate() {
+            if items[..index].contains(item) {
+              Err(OpenApiValidationError::bad_request(
+                location,
+                "The array items must be unique",
+              ))?
+            }
+          }
+        }
+        if let Some(items_schema) = schema_object.get("items") {
+          for (index, item) in items.iter().enumerate() {
+            self.validate_value(item, items_schema, &format!("{location}[{index}]"), depth + 1)?;
+          }
+        }
+      }
+      Value::Object(object) => {
+        let properties = schema_object.get("properties").and_then(|v| v.as_object());
+        if let Some(required) = schema_object.get("required").and_then(|v| v.as_array()) {
+          for name in required.iter().filter_map(|v| v.as_str()) {
+            // Read-only properties aren't sent in requests
+            let read_only = properties
+              .and_then(|properties| properties.get(name))
+              .and_then(|property| self.resolve(property).get("readOnly"))
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false);
+            if !read_only && !object.contains_key(name) {
+              Err(OpenApiValidationError::bad_request(
+                format!("{location}.{name}"),
+                format!("The required property `{name}` is missing"),
+              ))?
+            }
+          }
+        }
+        let length = object.len() as u64;
+        if schema_object
+          .get("minProperties")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|min_properties| length < min_properties)
+        {
+          Err(OpenApiValidationError::bad_request(
+            location,
+            "The object has too few properties",
+          ))?
+        }
+        if schema_object
+          .get("maxProperties")
+          .and_then(|v| v.as_u64())
+          .is_some_and(|max_properties| length > max_properties)
+        {
+          Err(OpenApiValidationError::bad_request(
+            location,
+            "The object has too many properties",
+          ))?
+        }
+        for (name, property_value) in object {
+          let property_location = format!("{location}.{name}");
+          match properties.and_then(|properties| properties.get(name)) {
+            Some(property_schema) => {
+              self.validate_value(property_value, property_schema, &property_location, depth + 1)?
+            }
+            None => match schema_object.get("additionalProperties") {
+              Some(Value::Bool(false)) => Err(OpenApiValidationError::bad_request(
+                property_location,
+                format!("The property `{name}` isn't allowed"),
+              ))?,
+              Some(additional_properties) => {
+                self.validate_value(property_value, additional_properties, &property_location, depth + 1)?
+              }
+              None => (),
+            },
+          }
+        }
+      }
+      _ => (),
+    }
+
+    if let Some(schemas) = schema_object.get("allOf").and_then(|v| v.as_array()) {
+      for schema in schemas {
+        self.validate_value(value, schema, location, depth + 1)?;
+      }
+    }
+    if let Some(schemas) = schema_object.get("anyOf").and_then(|v| v.as_array()) {
+      if !schemas
+        .iter()
+        .any(|schema| self.validate_value(value, schema, location, depth + 1).is_ok())
+      {
+        Err(OpenApiValidationError::bad_request(
+          location,
+          "The value doesn't match any of the allowed schemas",
+        ))?
+      }
+    }
+    if let Some(schemas) = schema_object.get("oneOf").and_then(|v| v.as_array()) {
+      let matching_schemas = schemas
+        .iter()
+        .filter(|schema| self.validate_value(value, schema, location, depth + 1).is_ok())
+        .count();
+      if matching_schemas != 1 {
+        Err(OpenApiValidationError::bad_request(
+          location,
+          "The value must match exactly one of the allowed schemas",
+        ))?
+      }
+    }
+    if let Some(schema) = schema_object.get("not") {
+      if self.validate_value(value, schema, location, depth + 1).is_ok() {
+        Err(OpenApiValidationError::bad_request(location, "The value isn't allowed"))?
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Converts the raw parameter value into a JSON value according to the parameter schema
+fn coerce_parameter_value(spec: &OpenApiSpec, values: &[String], schema: &Value) -> Value {
+  let schema = spec.resolve(schema);
+  let schema_type = match schema.get("type") {
+    Some(Value::String(schema_type)) => schema_type.as_str(),
+    Some(Value::Array(types)) => types
+      .iter()
+      .filter_map(|v| v.as_str())
+      .find(|v| *v != "null")
+      .unwrap_or(""),
+    _ => "",
+  };
+  if schema_type == "array" {
+    let items_schema = schema.get("items").unwrap_or(&Value::Null);
+    let items: Vec<String> = if values.len() == 1 {
+      values[0].split(',').map(|v| v.to_string()).collect()
+    } else {
+      values.to_vec()
+    };
+    return Value::Array(
+      items
+        .iter()
+        .map(|item| coerce_parameter_value(spec, std::slice::from_ref(item), items_schema))
+        .collect(),
+    );
+  }
+  let value = values.first().map_or("", |v| v.as_str());
+  match schema_type {
+    "integer" => value
+      .parse::<i64>()
+      .map_or_else(|_| Value::String(value.to_string()), Value::from),
+    "number" => value
+      .parse::<f64>()
+      .ok()
+      .and_then(serde_json::Number::from_f64)
+      .map_or_else(|| Value::String(value.to_string()), Value::Number),
+    "boolean" => match value {
+      "true" => Value::Bool(true),
+      "false" => Value::Bool(false),
+      _ => Value::String(value.to_string()),
+    },
+    _ => Value::String(value.to_string()),
+  }
+}
+
+/// An OpenAPI operation matching the request
+pub struct OpenApiOperation<'a> {
+  spec: &'a OpenApiSpec,
+  path_parameters: Vec<(String, String)>,
+  parameters: Vec<&'a Value>,
+  request_body: Option<&'a Value>,
+}
+
+impl OpenApiOperation<'_> {
+  /// Checks whether the operation defines a request body, so the request body needs to be read for validation
+  pub fn has_request_body(&self) -> bool {
+    self.request_body.is_some()
+  }
+
+  /// Validates the path, query, header and cookie parameters of the request
+  pub fn validate_parameters(&self, request_parts: &hyper::http::request::Parts) -> Result<(), OpenApiValidationError> {
+    let query_pairs = request_parts
+      .uri
+      .query()
+      .unwrap_or("")
+      .split('&')
+      .filter(|pair| !pair.is_empty())
+      .map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let decode = |v: &str| {
+          let v = v.replace('+', " ");
+          urlencoding::decode(&v).map_or_else(|_| v.clone(), |v| v.into_owned())
+        };
+        (decode(name), decode(value))
+      })
+      .collect::<Vec<_>>();
+    let cookies = request_parts
+      .headers
+      .get_all(header::COOKIE)
+      .iter()
+      .filter_map(|v| v.to_str().ok())
+      .flat_map(|v| v.split(';'))
+      .filter_map(|cookie| cookie.trim().split_once('='))
+      .collect::<Vec<_>>();
+
+    for parameter in &self.parameters {
+      let (Some(name), Some(location)) = (
+        parameter.get("name").and_then(|v| v.as_str()),
+        parameter.get("in").and_then(|v| v.as_str()),
+      ) else {
+        continue;
+      };
+      let values: Vec<String> = match location {
+        "path" => self
+          .path_parameters
+          .iter()
+          .filter(|(parameter_name, _)| parameter_name == name)
+          .map(|(_, value)| value.clone())
+          .collect(),
+        "query" => query_pairs
+          .iter()
+          .filter(|(parameter_name, _)| parameter_name == name)
+          .map(|(_, value)| value.clone())
+          .collect(),
+        "header" => request_parts
+          .headers
+          .get_all(name)
+          .iter()
+          .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+          .collect(),
+        "cookie" => cookies
+          .iter()
+          .filter(|(cookie_name, _)| *cookie_name == name)
+          .map(|(_, value)| value.to_string())
+          .collect(),
+        _ => continue,
+      };
+      let parameter_location = format!("{location}.{name}");
+      if values.is_empty() {
+        if location == "path" || parameter.get("required").and_then(|v| v.as_bool()) == Some(true) {
+          Err(OpenApiValidationError::bad_request(
+            parameter_location,
+            format!("The required {location} parameter `{name}` is missing"),
+          ))?
+        }
+        continue;
+      }
+
+      if let Some(schema) = parameter.get("schema") {
+        let value = coerce_parameter_value(self.spec, &values, schema);
+        self.spec.validate_value(&value, schema, &parameter_location, 0)?;
+      } else if let Some((media_type, media_type_object)) = parameter
+        .get("content")
+        .and_then(|v| v.as_object())
+        .and_then(|content| content.iter().next())
+      {
+        // Parameters with a JSON media type contain JSON-serialized values
+        if let (true, Some(schema)) = (is_json_media_type(media_type), media_type_object.get("schema")) {
+          let value = serde_json::from_str::<Value>(&values[0]).map_err(|_| {
+            OpenApiValidationError::bad_request(parameter_location.clone(), "The parameter value isn't valid JSON")
+          })?;
+          self.spec.validate_value(&value, schema, &parameter_location, 0)?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Validates the request body with the specified `Content-Type` header value
+  pub fn validate_body(&self, content_type: Option<&str>, body: &[u8]) -> Result<(), OpenApiValidationError> {
+    let Some(request_body) = self.request_body else {
+      return Ok(());
+    };
+    if body.is_empty() {
+      if request_body.get("required").and_then(|v| v.as_bool()) == Some(true) {
+        Err(OpenApiValidationError::bad_request(
+          "body",
+          "The required request body is missing",
+        ))?
+      }
+      return Ok(());
+    }
+
+    let media_type = media_type_essence(content_type.unwrap_or(""));
+    let Some(content) = request_body.get("content").and_then(|v| v.as_object()) else {
+      return Ok(());
+    };
+    let media_type_object = content
+      .iter()
+      .find(|(content_media_type, _)| media_type_essence(content_media_type) == media_type)
+      .or_else(|| {
+        // Media type ranges (like `application/*` or `*/*`) are matched after exact media types
+        content.iter().find(|(content_media_type, _)| {
+          let content_media_type = media_type_essence(content_media_type);
+          content_media_type == "*/*"
+            || content_media_type
+              .strip_suffix("/*")
+              .is_some_and(|main_type| media_type.split('/').next() == Some(main_type))
+        })
+      })
+      .map(|(_, media_type_object)| media_type_object);
+    let Some(media_type_object) = media_type_object else {
+      return Err(OpenApiValidationError {
+        status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        location: Some("header.content-type".to_string()),
+        message: format!(
+          "The `{}` content type isn't supported for the request body",
+          if media_type.is_empty() {
+            "(none)"
+          } else {
+            media_type.as_str()
+          }
+        ),
+        allowed_methods: Vec::new(),
+      });
+    };
+
+    if let (true, Some(schema)) = (is_json_media_type(&media_type), media_type_object.get("schema")) {
+      let value = serde_json::from_slice::<Value>(body).map_err(|err| {
+        OpenApiValidationError::bad_request("body", format!("The request body isn't valid JSON: {err}"))
+      })?;
+      self.spec.validate_value(&value, schema, "body", 0)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::Request;
+
+  use super::*;
+
+  const SPEC: &str = r##"
+openapi: 3.0.3
+info:
+  title: Pet store
+  version: 1.0.0
+servers:
+  - url: https://api.example.com/v1
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+            maximum: 100
+        - name: tags
+          in: query
+          schema:
+            type: array
+            items:
+              type: string
+    post:
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema:
+          type: integer
+    get: {}
+  /pets/mine:
+    get: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [id, name]
+      additionalProperties: false
+      properties:
+        id:
+          type: integer
+          readOnly: true
+        name:
+          type: string
+          minLength: 1
+          pattern: "^[A-Za-z ]+$"
+        tag:
+          type: string
+          nullable: true
+"##;
+
+  fn validate(spec: &OpenApiSpec, method: Method, uri: &str) -> Result<(), OpenApiValidationError> {
+    let (request_parts, _) = Request::builder()
+      .method(method.clone())
+      .uri(uri)
+      .body(())
+      .unwrap()
+      .into_parts();
+    spec
+      .match_operation(&method, request_parts.uri.path())?
+      .validate_parameters(&request_parts)
+  }
+
+  fn validate_body(spec: &OpenApiSpec, body: &str) -> Result<(), OpenApiValidationError> {
+    spec
+      .match_operation(&Method::POST, "/v1/pets")?
+      .validate_body(Some("application/json; charset=utf-8"), body.as_bytes())
+  }
+
+  #[test]
+  fn matches_paths_and_methods() {
+    let spec = OpenApiSpec::parse(SPEC, None).unwrap();
+    assert!(validate(&spec, Method::GET, "/v1/pets").is_ok());
+    assert!(validate(&spec, Method::GET, "/v1/pets/mine").is_ok());
+    assert!(validate(&spec, Method::HEAD, "/v1/pets/42").is_ok());
+    assert_eq!(
+      validate(&spec, Method::GET, "/v1/owners").unwrap_err().status,
+      StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+      validate(&spec, Method::GET, "/pets").unwrap_err().status,
+      StatusCode::NOT_FOUND
+    );
+    let error = validate(&spec, Method::DELETE, "/v1/pets").unwrap_err();
+    assert_eq!(error.status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(error.allowed_methods, vec!["GET", "POST"]);
+  }
+
+  #[test]
+  fn validates_parameters() {
+    let spec = OpenApiSpec::parse(SPEC, None).unwrap();
+    assert!(validate(&spec, Method::GET, "/v1/pets?limit=10&tags=a,b").is_ok());
+    let error = validate(&spec, Method::GET, "/v1/pets?limit=1000").unwrap_err();
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.location.as_deref(), Some("query.limit"));
+    assert_eq!(
+      validate(&spec, Method::GET, "/v1/pets?limit=ten")
+        .unwrap_err()
+        .location
+        .as_deref(),
+      Some("query.limit")
+    );
+    assert_eq!(
+      validate(&spec, Method::GET, "/v1/pets/abc")
+        .unwrap_err()
+        .location
+        .as_deref(),
+      Some("path.petId")
+    );
+  }
+
+  #[test]
+  fn validates_json_bodies() {
+    let spec = OpenApiSpec::parse(SPEC, None).unwrap();
+    assert!(validate_body(&spec, r#"{"name": "Rex", "tag": null}"#).is_ok());
+    assert_eq!(
+      validate_body(&spec, "").unwrap_err().message,
+      "The required request body is missing"
+    );
+    assert_eq!(
+      validate_body(&spec, r#"{"tag": "dog"}"#)
+        .unwrap_err()
+        .location
+        .as_deref(),
+      Some("body.name")
+    );
+    assert_eq!(
+      validate_body(&spec, r#"{"name": "Rex1"}"#)
+        .unwrap_err()
+        .location
+        .as_deref(),
+      Some("body.name")
+    );
+    assert_eq!(
+      validate_body(&spec, r#"{"name": "Rex", "owner": "me"}"#)
+        .unwrap_err()
+        .location
+        .as_deref(),
+      Some("body.owner")
+    );
+    assert_eq!(validate_body(&spec, "{").unwrap_err().location.as_deref(), Some("body"));
+    assert_eq!(
+      spec
+        .match_operation(&Method::POST, "/v1/pets")
+        .unwrap()
+        .validate_body(Some("text/plain"), b"Rex")
+        .unwrap_err()
+        .status,
+      StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+  }
+
+  #[test]
+  fn rejects_non_openapi_3_specifications() {
+    assert!(OpenApiSpec::parse(r#"{"swagger": "2.0", "paths": {}}"#, None).is_err());
+    assert!(OpenApiSpec::parse(r#"{"openapi": "3.1.0", "paths": {}}"#, Some("/api")).is_ok());
+  }
+}
@@ -0,0 +1,811 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame};
+use hyper::HeaderMap;
+use pin_project_lite::pin_project;
+
+/// The maximum nesting of JSON values or XML elements in a redacted response body
+const MAX_NESTING: usize = 512;
+
+/// The maximum size of a JSON object key or an XML tag in a redacted response body
+const MAX_TOKEN_SIZE: usize = 65536;
+
+/// A segment of a redacted field path
+#[derive(Debug, PartialEq)]
+enum FieldPathSegment {
+  /// An object key or an XML element name
+  Key(String),
+  /// An array index
+  Index(usize),
+  /// Any object key, array index or XML element name (`*`)
+  Wildcard,
+  /// Any number of nested keys, indices or elements (`..`)
+  Descendant,
+}
+
+/// An element of the location of a value in a JSON document (or an element in an XML document)
+#[derive(Debug)]
+enum LocationElement {
+  Key(String),
+  Index(usize),
+}
+
+/// An action performed on a redacted field
+#[derive(Clone, Debug, PartialEq)]
+pub enum RedactionAction {
+  /// Removes the field
+  Remove,
+  /// Replaces the field value with the specified string
+  Mask(String),
+}
+
+/// A response field redaction rule
+#[derive(Debug)]
+pub struct RedactionRule {
+  path: Vec<FieldPathSegment>,
+  action: RedactionAction,
+}
+
+impl RedactionRule {
+  /// Parses the field path (like `$.user.email`, `$.items[*].ssn` or `$..password`) of the redaction rule
+  pub fn parse(path: &str, action: RedactionAction) -> Result<Self, String> {
+    let mut rest = path
+      .strip_prefix('$')
+      .ok_or_else(|| format!("The field path \"{path}\" must start with \"$\""))?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+      if let Some(after_dots) = rest.strip_prefix("..") {
+        segments.push(FieldPathSegment::Descendant);
+        rest = after_dots;
+        if rest.starts_with('[') {
+          continue;
+        }
+      } else if let Some(after_dot) = rest.strip_prefix('.') {
+        rest = after_dot;
+      } else if let Some(after_bracket) = rest.strip_prefix('[') {
+        let (selector, after_selector) = after_bracket
+          .split_once(']')
+          .ok_or_else(|| format!("Unterminated bracket in the field path \"{path}\""))?;
+        rest = after_selector;
+        let selector = selector.trim();
+        segments.push(if selector == "*" {
+          FieldPathSegment::Wildcard
+        } else if let Some(key) = selector
+          .strip_prefix('\'')
+          .and_then(|s| s.strip_suffix('\''))
+          .or_else(|| selector.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+        {
+          FieldPathSegment::Key(key.to_string())
+        } else {
+          FieldPathSegment::Index(
+            selector
+              .parse()
+              .map_err(|_| format!("Invalid array index \"{selector}\" in the field path \"{path}\""))?,
+          )
+        });
+        continue;
+      } else {
+        return Err(format!("Invalid field path \"{path}\""));
+      }
+      let key_length = rest.find(['.', '[']).unwrap_or(rest.len());
+      let key = &rest[..key_length];
+      rest = &rest[key_length..];
+      segments.push(match key {
+        "" => return Err(format!("Empty key in the field path \"{path}\"")),
+        "*" => FieldPathSegment::Wildcard,
+        key => FieldPathSegment::Key(key.to_string()),
+      });
+    }
+    if segments.is_empty() || segments.last() == Some(&FieldPathSegment::Descendant) {
+      return Err(format!("The field path \"{path}\" doesn't select any fields"));
+    }
+    Ok(Self { path: segments, action })
+  }
+
+  /// Checks whether the location matches the field path of the rule
+  fn matches(&self, location: &[LocationElement]) -> bool {
+    fn matches_segments(segments: &[FieldPathSegment], location: &[LocationElement]) -> bool {
+      match segments.split_first() {
+        None => location.is_empty(),
+        Some((FieldPathSegment::Descendant, segments)) => {
+          (0..=location.len()).any(|skipped| matches_segments(segments, &location[skipped..]))
+        }
+        Some((segment, segments)) => match (segment, location.split_first()) {
+          (FieldPathSegment::Wildcard, Some((_, location))) => matches_segments(segments, location),
+          (FieldPathSegment::Key(key), Some((LocationElement::Key(location_key), location))) => {
+            key == location_key && matches_segments(segments, location)
+          }
+          (FieldPathSegment::Index(index), Some((LocationElement::Index(location_index), location))) => {
+            index == location_index && matches_segments(segments, location)
+          }
+          _ => false,
+        },
+      }
+    }
+
+    matches_segments(&self.path, location)
+  }
+}
+
+/// Finds the action of the first redaction rule matching the location
+fn find_action(rules: &[RedactionRule], location: &[LocationElement]) -> Option<RedactionAction> {
+  rules
+    .iter()
+    .find(|rule| rule.matches(location))
+    .map(|rule| rule.action.clone())
+}
+
+/// A JSON object or array, in which the redactor currently is
+struct JsonContainer {
+  is_object: bool,
+  /// The key of the current object member
+  key: String,
+  /// The number of the members or elements read (the index of the next array element)
+  index: usize,
+  /// The number of the members or elements written to the output
+  written: usize,
+}
+
+/// A state of the streaming JSON redactor
+#[derive(Clone, Copy, PartialEq)]
+enum JsonState {
+  Value,
+  String { escape: bool },
+  Literal,
+  KeyOrEnd,
+  Key,
+  KeyString { escape: bool },
+  Colon,
+  ElementOrEnd,
+  Element,
+  AfterValue,
+}
+
+/// A streaming JSON redactor, which removes or masks the fields selected by the redaction rules
+/// without buffering the whole document
+pub struct JsonRedactor {
+  rules: Arc<Vec<RedactionRule>>,
+  state: JsonState,
+  stack: Vec<JsonContainer>,
+  /// The raw object key (including the quotes) being read
+  key: Vec<u8>,
+  /// The whitespace before the next member or element, written only if the member or element isn't removed
+  pending: Vec<u8>,
+  /// The nesting level, at which the currently suppressed (removed or masked) value ends
+  suppress: Option<usize>,
+  /// The replacement of the next value
+  mask: Option<String>,
+}
+
+impl JsonRedactor {
+  /// Creates a streaming JSON redactor with the specified redaction rules
+  pub fn new(rules: Arc<Vec<RedactionRule>>) -> Self {
+    Self {
+      rules,
+      state: JsonState::Value,
+      stack: Vec::new(),
+      key: Vec::new(),
+      pending: Vec::new(),
+      suppress: None,
+      mask: None,
+    }
+  }
+
+  fn write(&self, output: &mut Vec<u8>, byte: u8) {
+    if self.suppress.is_none() {
+      output.push(byte);
+    }
+  }
+
+  /// Decides whether to keep, remove or mask the next member or element of the current container
+  fn begin_member(&mut self, element: LocationElement, output: &mut Vec<u8>) {
+    if self.suppress.is_some() {
+      return;
+    }
+    let mut location = self
+      .stack
+      .iter()
+      .take(self.stack.len() - 1)
+      .map(|container| {
+        if container.is_object {
+          LocationElement::Key(container.key.clone())
+        } else {
+          LocationElement::Index(container.index - 1)
+        }
+      })
+      .collect::<Vec<_>>();
+    location.push(element);
+    match find_action(&self.rules, &location) {
+      Some(RedactionAction::Remove) => {
+        self.pending.clear();
+        self.suppress = Some(self.stack.len());
+      }
+      action => {
+        if let Some(container) = self.stack.last_mut() {
+          if container.written > 0 {
+            output.push(b',');
+          } else if container.index > 1 {
+            // The previous members or elements were removed, so the whitespace after their separator is dropped
+            self.pending.clear();
+          }
+          container.written += 1;
+        }
+        output.append(&mut self.pending);
+        if let Some(RedactionAction::Mask(replacement)) = action {
+          self.mask = Some(replacement);
+        }
+      }
+    }
+  }
+
+  fn begin_value(&mut self, byte: u8, output: &mut Vec<u8>) -> Result<(), String> {
+    if self.suppress.is_none() {
+      if let Some(replacement) = self.mask.take() {
+        output.extend_from_slice(
+          serde_json::to_string(&replacement)
+            .map_err(|err| err.to_string())?
+            .as_bytes(),
+        );
+        self.suppress = Some(self.stack.len());
+      }
+    }
+    self.write(output, byte);
+    self.state = match byte {
+      b'{' | b'[' => {
+        if self.stack.len() >= MAX_NESTING {
+          return Err("The JSON document is nested too deeply".to_string());
+        }
+        self.stack.push(JsonContainer {
+          is_object: byte == b'{',
+          key: String::new(),
+          index: 0,
+          written: 0,
+        });
+        if byte == b'{' {
+          JsonState::KeyOrEnd
+        } else {
+          JsonState::ElementOrEnd
+        }
+      }
+      b'"' => JsonState::String { escape: false },
+      b'-' | b'0'..=b'9' | b'a'..=b'z' => JsonState::Literal,
+      _ => return Err(format!("Unexpected character \"{}\" in JSON", byte.escape_ascii())),
+    };
+    Ok(())
+  }
+
+  fn end_value(&mut self) {
+    self.state = JsonState::AfterValue;
+    if self.suppress == Some(self.stack.len()) {
+      self.suppress = None;
+    }
+  }
+
+  fn end_container(&mut self, byte: u8, output: &mut Vec<u8>) {
+    if self.suppress.is_none() {
+      output.append(&mut self.pending);
+    }
+    self.write(output, byte);
+    self.stack.pop();
+    self.end_value();
+  }
+
+  /// Processes a chunk of the JSON document, writing the redacted JSON into the output
+  pub fn process(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
+    let mut position = 0;
+    while let Some(&byte) = input.get(position) {
+      let is_whitespace = matches!(byte, b' ' | b'\t' | b'\n' | b'\r');
+      let is_object = self.stack.last().map(|container| container.is_object);
+      match self.state {
+        JsonState::Value if is_whitespace => self.write(output, byte),
+        JsonState::Value => self.begin_value(byte, output)?,
+        JsonState::String { escape } => {
+          self.write(output, byte);
+          if escape {
+            self.state = JsonState::String { escape: false };
+          } else if byte == b'\\' {
+            self.state = JsonState::String { escape: true };
+          } else if byte == b'"' {
+            self.end_value();
+          }
+        }
+        JsonState::Literal if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'+' | b'.') => {
+          self.write(output, byte)
+        }
+        JsonState::Literal => {
+          // The literal ended; the current character is processed again in the next state
+          self.end_value();
+          continue;
+        }
+        JsonState::KeyOrEnd | JsonState::Key | JsonState::ElementOrEnd | JsonState::Element if is_whitespace => {
+          if self.suppress.is_none() {
+            self.pending.push(byte);
+          }
+        }
+        JsonState::KeyOrEnd if byte == b'}' => self.end_container(byte, output),
+        JsonState::KeyOrEnd | JsonState::Key if byte == b'"' => {
+          self.key.clear();
+          self.key.push(byte);
+          self.state = JsonState::KeyString { escape: false };
+        }
+        JsonState::KeyString { escape } => {
+          if self.key.len() >= MAX_TOKEN_SIZE {
+            return Err("A JSON object key is too long".to_string());
+          }
+          self.key.push(byte);
+          if escape {
+            self.state = JsonState::KeyString { escape: false };
+          } else if byte == b'\\' {
+            self.state = JsonState::KeyString { escape: true };
+          } else if byte == b'"' {
+            let key = serde_json::from_slice::<String>(&self.key).map_err(|err| err.to_string())?;
+            if let Some(container) = self.stack.last_mut() {
+              container.key = key.clone();
+              container.index += 1;
+            }
+            self.begin_member(LocationElement::Key(key), output);
+            if self.suppress.is_none() {
+              output.extend_from_slice(&self.key);
+            }
+            self.state = JsonState::Colon;
+          }
+        }
+        JsonState::Colon if is_whitespace => self.write(output, byte),
+        JsonState::Colon if byte == b':' => {
+          self.write(output, byte);
+          self.state = JsonState::Value;
+        }
+        JsonState::ElementOrEnd if byte == b']' => self.end_container(byte, output),
+        JsonState::ElementOrEnd | JsonState::Element => {
+          let index = match self.stack.last_mut() {
+            Some(container) => {
+              container.index += 1;
+              container.index - 1
+            }
+            None => 0,
+          };
+          self.begin_member(LocationElement::Index(index), output);
+          self.begin_value(byte, output)?;
+        }
+        JsonState::AfterValue if is_whitespace => self.write(output, byte),
+        // Multiple top-level values (like in JSON Lines documents)
+        JsonState::AfterValue if is_object.is_none() => self.begin_value(byte, output)?,
+        JsonState::AfterValue if byte == b',' => {
+          self.state = if is_object == Some(true) {
+            JsonState::Key
+          } else {
+            JsonState::Element
+          };
+        }
+        JsonState::AfterValue if byte == b'}' && is_object == Some(true) => self.end_container(byte, output),
+        JsonState::AfterValue if byte == b']' && is_object == Some(false) => self.end_container(byte, output),
+        _ => return Err(format!("Unexpected character \"{}\" in JSON", byte.escape_ascii())),
+      }
+      position += 1;
+    }
+    Ok(())
+  }
+
+  /// Finishes the processing of the JSON document
+  pub fn finish(&mut self, _output: &mut Vec<u8>) -> Result<(), String> {
+    if self.state == JsonState::Literal {
+      self.end_value();
+    }
+    if !self.stack.is_empty() || !matches!(self.state, JsonState::Value | JsonState::AfterValue) {
+      return Err("The JSON document is truncated".to_string());
+    }
+    Ok(())
+  }
+}
+
+/// A state of the streaming XML redactor
+enum XmlState {
+  Text,
+  /// A tag or a declaration, buffered until it ends
+  Markup {
+    quote: Option<u8>,
+    brackets: usize,
+  },
+  /// A comment, a CDATA section or a processing instruction, ending with the terminator
+  Special {
+    terminator: &'static [u8],
+  },
+}
+
+/// A streaming XML redactor, which removes the elements or masks the element contents selected by the redaction rules
+/// without buffering the whole document
+pub struct XmlRedactor {
+  rules: Arc<Vec<RedactionRule>>,
+  state: XmlState,
+  markup: Vec<u8>,
+  stack: Vec<String>,
+  /// The nesting level, at which the currently suppressed (removed or masked) element ends
+  suppress: Option<usize>,
+  /// Whether the currently suppressed element is masked (so its end tag is written)
+  masking: bool,
+}
+
+impl XmlRedactor {
+  /// Creates a streaming XML redactor with the specified redaction rules
+  pub fn new(rules: Arc<Vec<RedactionRule>>) -> Self {
+    Self {
+      rules,
+      state: XmlState::Text,
+      markup: Vec::new(),
+      stack: Vec::new(),
+      suppress: None,
+      masking: false,
+    }
+  }
+
+  /// Obtains the local name (without the namespace prefix) of the element from the tag
+  fn tag_name(tag: &[u8]) -> String {
+    let name = tag
+      .iter()
+      .position(|byte| byte.is_ascii_whitespace() || *byte == b'/' || *byte == b'>')
+      .map_or(tag, |end| &tag[..end]);
+    let name = String::from_utf8_lossy(name);
+    name.rsplit(':').next().unwrap_or_default().to_string()
+  }
+
+  /// Processes a complete tag or declaration
+  fn process_markup(&mut self, output: &mut Vec<u8>) -> Result<(), String> {
+    let markup = std::mem::take(&mut self.markup);
+    if let Some(tag) = markup.strip_prefix(b"</") {
+      if self.stack.pop().is_none_or(|name| name != Self::tag_name(tag)) {
+        return Err("Mismatched XML end tag".to_string());
+      }
+      if self.suppress == Some(self.stack.len()) {
+        self.suppress = None;
+        if self.masking {
+          self.masking = false;
+          output.extend_from_slice(&markup);
+        }
+      } else if self.suppress.is_none() {
+        output.extend_from_slice(&markup);
+      }
+    } else if markup.starts_with(b"<!") || markup.starts_with(b"<?") {
+      if self.suppress.is_none() {
+        output.extend_from_slice(&markup);
+      }
+    } else {
+      let name = Self::tag_name(&markup[1..]);
+      let self_closing = markup.ends_with(b"/>");
+      let action = if self.suppress.is_none() {
+        let location = self
+          .stack
+          .iter()
+          .chain(std::iter::once(&name))
+          .map(|name| LocationElement::Key(name.clone()))
+          .collect::<Vec<_>>();
+        find_action(&self.rules, &location)
+      } else {
+        None
+      };
+      if self.suppress.is_none() && action != Some(RedactionAction::Remove) {
+        output.extend_from_slice(&markup);
+      }
+      if !self_closing {
+        if self.stack.len() >= MAX_NESTING {
+          return Err("The XML document is nested too deeply".to_string());
+        }
+        self.stack.push(name);
+        match action {
+          Some(RedactionAction::Remove) => {
+            self.suppress = Some(self.stack.len() - 1);
+          }
+          Some(RedactionAction::Mask(replacement)) => {
+            for character in replacement.chars() {
+              match character {
+                '&' => output.extend_from_slice(b"&amp;"),
+                '<' => output.extend_from_slice(b"&lt;"),
+                '>' => output.extend_from_slice(b"&gt;"),
+                character => output.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes()),
+              }
+            }
+            self.suppress = Some(self.stack.len() - 1);
+            self.masking = true;
+          }
+          None => (),
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Processes a chunk of the XML document, writing the redacted XML into the output
+  pub fn process(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
+    for &byte in input {
+      match &mut self.state {
+        XmlState::Text => {
+          if byte == b'<' {
+            self.markup.push(byte);
+            self.state = XmlState::Markup {
+              quote: None,
+              brackets: 0,
+            };
+          } else if self.suppress.is_none() {
+            output.push(byte);
+          }
+        }
+        XmlState::Markup { quote, brackets } => {
+          if self.markup.len() >= MAX_TOKEN_SIZE {
+            return Err("An XML tag is too long".to_string());
+          }
+          self.markup.push(byte);
+          let terminator: Option<&'static [u8]> = if self.markup == b"<!--" {
+            Some(b"-->")
+          } else if self.markup == b"<![CDATA[" {
+            Some(b"]]>")
+          } else if self.markup == b"<?" {
+            Some(b"?>")
+          } else {
+            None
+          };
+          if let Some(terminator) = terminator {
+            if self.suppress.is_none() {
+              output.append(&mut self.markup);
+            }
+            self.markup.clear();
+            self.state = XmlState::Special { terminator };
+            continue;
+          }
+          match (*quote, byte) {
+            (Some(quote_byte), byte) if quote_byte == byte => *quote = None,
+            (Some(_), _) => (),
+            (None, b'"' | b'\'') => *quote = Some(byte),
+            (None, b'[') => *brackets += 1,
+            (None, b']') => *brackets = brackets.saturating_sub(1),
+            (None, b'>') if *brackets == 0 => {
+              self.state = XmlState::Text;
+              self.process_markup(output)?;
+            }
+            _ => (),
+          }
+        }
+        XmlState::Special { terminator } => {
+          let terminator = *terminator;
+          if self.suppress.is_none() {
+            output.push(byte);
+          }
+          // Only the last bytes are kept to detect the terminator
+          self.markup.push(byte);
+          if self.markup.len() > terminator.len() {
+            self.markup.remove(0);
+          }
+          if self.markup == terminator {
+            self.markup.clear();
+            self.state = XmlState::Text;
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Finishes the processing of the XML document
+  pub fn finish(&mut self, _output: &mut Vec<u8>) -> Result<(), String> {
+    if !self.stack.is_empty() || !matches!(self.state, XmlState::Text) {
+      return Err("The XML document is truncated".to_string());
+    }
+    Ok(())
+  }
+}
+
+/// A streaming redactor of JSON or XML response bodies
+pub enum ResponseRedactor {
+  Json(JsonRedactor),
+  Xml(XmlRedactor),
+}
+
+impl ResponseRedactor {
+  fn process(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
+    match self {
+      Self::Json(redactor) => redactor.process(input, output),
+      Self::Xml(redactor) => redactor.process(input, output),
+    }
+  }
+
+  fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), String> {
+    match self {
+      Self::Json(redactor) => redactor.finish(output),
+      Self::Xml(redactor) => redactor.finish(output),
+    }
+  }
+
+  /// Wraps a `Body` to redact its contents
+  pub fn wrap<B>(self, body: B) -> RedactBody<B>
+  where
+    B: Body<Data = Bytes, Error = std::io::Error>,
+  {
+    RedactBody {
+      redactor: self,
+      trailers: None,
+      finished: false,
+      inner: body,
+    }
+  }
+}
+
+pin_project! {
+  /// A `Body` with redacted JSON fields or XML elements.
+  /// If the body isn't a valid JSON or XML document, the body ends with an error, so no unredacted data is sent.
+  pub struct RedactBody<B> {
+    redactor: ResponseRedactor,
+    trailers: Option<HeaderMap>,
+    finished: bool,
+    #[pin]
+    inner: B,
+  }
+}
+
+impl<B> Body for RedactBody<B>
+where
+  B: Body<Data = Bytes, Error = std::io::Error>,
+{
+  type Data = Bytes;
+  type Error = std::io::Error;
+
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let mut this = self.project();
+    loop {
+      if *this.finished {
+        return Poll::Ready(this.trailers.take().map(|trailers| Ok(Frame::trailers(trailers))));
+      }
+      let mut output = Vec::new();
+      let result = match this.inner.as_mut().poll_frame(cx) {
+        Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+          Ok(data) => this.redactor.process(&data, &mut output),
+          Err(frame) => {
+            *this.trailers = frame.into_trailers().ok();
+            *this.finished = true;
+            this.redactor.finish(&mut output)
+          }
+        },
+        Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+        Poll::Ready(None) => {
+          *this.finished = true;
+          this.redactor.finish(&mut output)
+        }
+        Poll::Pending => return Poll::Pending,
+      };
+      if let Err(err) = result {
+        *this.finished = true;
+        *this.trailers = None;
+        return Poll::Ready(Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err))));
+      }
+      if !output.is_empty() {
+        return Poll::Ready(Some(Ok(Frame::data(Bytes::from(output)))));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rules(rules: &[(&str, RedactionAction)]) -> Arc<Vec<RedactionRule>> {
+    Arc::new(
+      rules
+        .iter()
+        .map(|(path, action)| RedactionRule::parse(path, action.clone()).unwrap())
+        .collect(),
+    )
+  }
+
+  /// Redacts the document, feeding it in chunks of the specified size
+  fn redact(mut redactor: ResponseRedactor, document: &str, chunk_size: usize) -> Result<String, String> {
+    let mut output = Vec::new();
+    for chunk in document.as_bytes().chunks(chunk_size) {
+      redactor.process(chunk, &mut output)?;
+    }
+    redactor.finish(&mut output)?;
+    Ok(String::from_utf8(output).unwrap())
+  }
+
+  #[test]
+  fn parses_field_paths() {
+    assert_eq!(
+      RedactionRule::parse("$.items[*].ssn", RedactionAction::Remove)
+        .unwrap()
+        .path,
+      vec![
+        FieldPathSegment::Key("items".to_string()),
+        FieldPathSegment::Wildcard,
+        FieldPathSegment::Key("ssn".to_string())
+      ]
+    );
+    assert_eq!(
+      RedactionRule::parse("$..['a.b'][0]", RedactionAction::Remove)
+        .unwrap()
+        .path,
+      vec![
+        FieldPathSegment::Descendant,
+        FieldPathSegment::Key("a.b".to_string()),
+        FieldPathSegment::Index(0)
+      ]
+    );
+    assert!(RedactionRule::parse("user.email", RedactionAction::Remove).is_err());
+    assert!(RedactionRule::parse("$", RedactionAction::Remove).is_err());
+    assert!(RedactionRule::parse("$.a..", RedactionAction::Remove).is_err());
+    assert!(RedactionRule::parse("$.a[x]", RedactionAction::Remove).is_err());
+  }
+
+  #[test]
+  fn redacts_json_fields() {
+    let rules = rules(&[
+      ("$.debug", RedactionAction::Remove),
+      ("$.user.email", RedactionAction::Mask("[REDACTED]".to_string())),
+      ("$..password", RedactionAction::Remove),
+      ("$.items[0]", RedactionAction::Remove),
+    ]);
+    let document = r#"{"debug": {"trace": [1, "}"]}, "user": {"name": "A \"B\"", "email": "a@example.com", "password": "x"},
+      "items": [{"password": 1}, 2, true], "n": -1.5e3}"#;
+    let expected = r#"{"user": {"name": "A \"B\"", "email": "[REDACTED]"},
+      "items": [2, true], "n": -1.5e3}"#;
+    for chunk_size in 1..=document.len() {
+      assert_eq!(
+        redact(
+          ResponseRedactor::Json(JsonRedactor::new(rules.clone())),
+          document,
+          chunk_size
+        )
+        .as_deref(),
+        Ok(expected)
+      );
+    }
+  }
+
+  #[test]
+  fn redacts_json_lines() {
+    let rules = rules(&[("$.secret", RedactionAction::Remove)]);
+    assert_eq!(
+      redact(
+        ResponseRedactor::Json(JsonRedactor::new(rules)),
+        "{\"secret\":1,\"a\":2}\n{\"a\":3,\"secret\":4}\n",
+        7
+      )
+      .as_deref(),
+      Ok("{\"a\":2}\n{\"a\":3}\n")
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_json() {
+    let rules = rules(&[("$.a", RedactionAction::Remove)]);
+    assert!(redact(
+      ResponseRedactor::Json(JsonRedactor::new(rules.clone())),
+      "{\"a\":1,}",
+      4
+    )
+    .is_err());
+    assert!(redact(ResponseRedactor::Json(JsonRedactor::new(rules.clone())), "{\"a\":[1", 4).is_err());
+    assert!(redact(ResponseRedactor::Json(JsonRedactor::new(rules)), "<html>", 4).is_err());
+  }
+
+  #[test]
+  fn redacts_xml_elements() {
+    let rules = rules(&[
+      ("$.user.ssn", RedactionAction::Remove),
+      ("$..email", RedactionAction::Mask("<hidden>".to_string())),
+    ]);
+    let document = r#"<?xml version="1.0"?><!-- <user> --><user id="a>b"><name><![CDATA[<ssn>]]></name><ssn>123</ssn><x:email>a@example.com</x:email><email/></user>"#;
+    let expected = r#"<?xml version="1.0"?><!-- <user> --><user id="a>b"><name><![CDATA[<ssn>]]></name><x:email>&lt;hidden&gt;</x:email><email/></user>"#;
+    for chunk_size in 1..=document.len() {
+      assert_eq!(
+        redact(
+          ResponseRedactor::Xml(XmlRedactor::new(rules.clone())),
+          document,
+          chunk_size
+        )
+        .as_deref(),
+        Ok(expected)
+      );
+    }
+    assert!(redact(ResponseRedactor::Xml(XmlRedactor::new(rules)), "<user><ssn>1</user>", 4).is_err());
+  }
+}
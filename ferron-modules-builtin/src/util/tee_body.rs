@@ -0,0 +1,184 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use ferron_common::util::MemoryReservation;
+use hyper::body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+
+pin_project! {
+  /// A `Body` that forwards the data passing through it, while keeping the data chunks (up to the maximum body size).
+  /// The kept data chunks are reserved in the memory reservation. After the body ends, the kept data chunks
+  /// and the memory reservation are passed to the completion callback. The callback isn't called, if the body fails,
+  /// is dropped before it ends, exceeds the maximum body size, or can't be reserved in the global memory budget.
+  pub struct TeeBody<B, F> {
+    #[pin]
+    inner: B,
+    chunks: Vec<Bytes>,
+    size: u64,
+    max_size: Option<u64>,
+    on_complete: Option<F>,
+    reservation: Option<MemoryReservation>,
+  }
+}
+
+impl<B, F> TeeBody<B, F>
+where
+  F: FnOnce(Vec<Bytes>, MemoryReservation),
+{
+  /// Creates a tee `Body`
+  pub fn new(inner: B, max_size: Option<u64>, reservation: MemoryReservation, on_complete: F) -> Self {
+    Self {
+      inner,
+      chunks: Vec::new(),
+      size: 0,
+      max_size,
+      on_complete: Some(on_complete),
+      reservation: Some(reservation),
+    }
+  }
+}
+
+impl<B, F> Body for TeeBody<B, F>
+where
+  B: Body<Data = Bytes>,
+  F: FnOnce(Vec<Bytes>, MemoryReservation),
+{
+  type Data = Bytes;
+  type Error = B::Error;
+
+  fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let this = self.project();
+    let result = this.inner.poll_frame(cx);
+    match &result {
+      Poll::Ready(Some(Ok(frame))) => {
+        if let Some(data) = frame.data_ref() {
+          if this.on_complete.is_some() {
+            *this.size += data.len() as u64;
+            if this.max_size.is_some_and(|max_size| *this.size > max_size)
+              || !this
+                .reservation
+                .as_mut()
+                .is_some_and(|reservation| reservation.try_grow(data.len()))
+            {
+              // The body is too large, or the memory budget is exceeded, so the data chunks aren't kept anymore
+              this.on_complete.take();
+              this.reservation.take();
+              *this.chunks = Vec::new();
+            } else if !data.is_empty() {
+              this.chunks.push(data.clone());
+            }
+          }
+        }
+      }
+      Poll::Ready(None) => {
+        if let (Some(on_complete), Some(reservation)) = (this.on_complete.take(), this.reservation.take()) {
+          on_complete(std::mem::take(this.chunks), reservation);
+        }
+      }
+      Poll::Ready(Some(Err(_))) => {
+        this.on_complete.take();
+        this.reservation.take();
+        *this.chunks = Vec::new();
+      }
+      Poll::Pending => (),
+    }
+    result
+  }
+
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::{Arc, Mutex};
+
+  use ferron_common::util::{MemoryBudget, MEMORY_BUDGET};
+  use futures_util::stream;
+  use http_body_util::{BodyExt, StreamBody};
+
+  fn test_body(
+    chunks: &[&'static [u8]],
+  ) -> StreamBody<impl futures_util::Stream<Item = Result<Frame<Bytes>, std::io::Error>>> {
+    StreamBody::new(stream::iter(
+      chunks
+        .iter()
+        .map(|chunk| Ok(Frame::data(Bytes::from_static(chunk))))
+        .collect::<Vec<_>>(),
+    ))
+  }
+
+  #[tokio::test]
+  async fn test_tee_body_keeps_chunks() {
+    let kept_chunks = Arc::new(Mutex::new(None));
+    let kept_chunks_clone = kept_chunks.clone();
+    let body = TeeBody::new(
+      test_body(&[b"Hello, ", b"world!"]),
+      Some(13),
+      MEMORY_BUDGET.reserve(),
+      move |chunks, reservation| {
+        assert_eq!(reservation.size(), 13);
+        *kept_chunks_clone.lock().unwrap() = Some(chunks);
+      },
+    );
+
+    // The data passes through the body unchanged
+    let collected = body.collect().await.unwrap().to_bytes();
+    assert_eq!(collected.as_ref(), b"Hello, world!");
+    assert_eq!(
+      kept_chunks.lock().unwrap().as_deref(),
+      Some(&[Bytes::from_static(b"Hello, "), Bytes::from_static(b"world!")][..])
+    );
+  }
+
+  #[tokio::test]
+  async fn test_tee_body_exceeding_maximum_size() {
+    let completed = Arc::new(Mutex::new(false));
+    let completed_clone = completed.clone();
+    let body = TeeBody::new(
+      test_body(&[b"Hello, ", b"world!"]),
+      Some(12),
+      MEMORY_BUDGET.reserve(),
+      move |_, _| {
+        *completed_clone.lock().unwrap() = true;
+      },
+    );
+
+    let collected = body.collect().await.unwrap().to_bytes();
+    assert_eq!(collected.as_ref(), b"Hello, world!");
+    assert!(!*completed.lock().unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_tee_body_exceeding_memory_budget() {
+    let memory_budget: &'static MemoryBudget = Box::leak(Box::new(MemoryBudget::new()));
+    memory_budget.set_limit(Some(10));
+    let completed = Arc::new(Mutex::new(false));
+    let completed_clone = completed.clone();
+    let body = TeeBody::new(
+      test_body(&[b"Hello, ", b"world!"]),
+      None,
+      memory_budget.reserve(),
+      move |_, _| {
+        *completed_clone.lock().unwrap() = true;
+      },
+    );
+
+    let collected = body.collect().await.unwrap().to_bytes();
+    assert_eq!(collected.as_ref(), b"Hello, world!");
+    assert!(!*completed.lock().unwrap());
+    // The reserved bytes are released after the data chunks are dropped, leaving the shed mode
+    let statistics = memory_budget.take_statistics();
+    assert_eq!(statistics.shed_episodes, 1);
+    assert!(!statistics.shedding);
+    assert_eq!(statistics.used, 0);
+  }
+}
@@ -0,0 +1,91 @@
+/// Normalizes a value of a request header used to vary cache entries, to reduce cache fragmentation.
+/// Returns `None` if the header isn't normalized.
+pub fn normalize_vary_header_value(header_name: &str, value: &str) -> Option<String> {
+  if header_name.eq_ignore_ascii_case("accept-encoding") {
+    Some(normalize_accept_encoding(value))
+  } else if header_name.eq_ignore_ascii_case("accept-language") {
+    Some(normalize_accept_language(value))
+  } else {
+    None
+  }
+}
+
+/// Normalizes the "Accept-Encoding" header value into a sorted list of lowercase accepted content codings
+fn normalize_accept_encoding(value: &str) -> String {
+  let mut codings = value
+    .split(',')
+    .filter_map(|part| {
+      let mut params = part.split(';');
+      let coding = params.next()?.trim().to_ascii_lowercase();
+      let rejected = params.any(|param| {
+        param
+          .trim()
+          .strip_prefix("q=")
+          .and_then(|q| q.trim().parse::<f32>().ok())
+          .is_some_and(|q| q <= 0.0)
+      });
+      (!coding.is_empty() && !rejected).then_some(coding)
+    })
+    .collect::<Vec<_>>();
+  codings.sort_unstable();
+  codings.dedup();
+  codings.join(",")
+}
+
+/// Normalizes the "Accept-Language" header value into a list of lowercase primary language subtags,
+/// preserving the client's order of preference
+fn normalize_accept_language(value: &str) -> String {
+  let mut languages: Vec<String> = Vec::new();
+  for part in value.split(',') {
+    let mut params = part.split(';');
+    let Some(language_range) = params.next().map(|l| l.trim()) else {
+      continue;
+    };
+    let rejected = params.any(|param| {
+      param
+        .trim()
+        .strip_prefix("q=")
+        .and_then(|q| q.trim().parse::<f32>().ok())
+        .is_some_and(|q| q <= 0.0)
+    });
+    let primary_tag = language_range
+      .split('-')
+      .next()
+      .unwrap_or_default()
+      .to_ascii_lowercase();
+    if !primary_tag.is_empty() && !rejected && !languages.contains(&primary_tag) {
+      languages.push(primary_tag);
+    }
+  }
+  languages.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_normalize_accept_encoding() {
+    assert_eq!(
+      normalize_vary_header_value("Accept-Encoding", "GZIP, br;q=0.9, gzip, identity;q=0"),
+      Some("br,gzip".to_string())
+    );
+    assert_eq!(
+      normalize_vary_header_value("accept-encoding", "br, gzip"),
+      normalize_vary_header_value("accept-encoding", "gzip,BR")
+    );
+  }
+
+  #[test]
+  fn test_normalize_accept_language() {
+    assert_eq!(
+      normalize_vary_header_value("Accept-Language", "en-US,en;q=0.9,fr-FR;q=0.8,de;q=0"),
+      Some("en,fr".to_string())
+    );
+  }
+
+  #[test]
+  fn test_other_headers_not_normalized() {
+    assert_eq!(normalize_vary_header_value("User-Agent", "Mozilla/5.0"), None);
+  }
+}
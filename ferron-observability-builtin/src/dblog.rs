@@ -0,0 +1,546 @@
+use std::{
+  error::Error,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use async_channel::Sender;
+use chrono::{DateTime, Utc};
+use ferron_common::{
+  config::ServerConfiguration,
+  get_entries_for_validation, get_entry,
+  logging::{AccessLogRecord, LogMessage},
+  observability::{ObservabilityBackend, ObservabilityBackendLoader},
+  util::ModuleCache,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, Request, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use rustls::ClientConfig;
+use tokio::sync::Notify;
+use tokio_postgres::{binary_copy::BinaryCopyInWriter, types::Type};
+use tokio_postgres_rustls::MakeRustlsConnect;
+use tokio_util::sync::CancellationToken;
+
+use crate::util::{build_tls_client_config, LogBuffer};
+
+/// The default name of the table with access logs
+const DEFAULT_TABLE: &str = "ferron_access_log";
+
+/// The default maximum number of access log records inserted at once
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// The default interval (in milliseconds) of inserting buffered access log records
+const DEFAULT_FLUSH_INTERVAL: u64 = 1000;
+
+/// The default maximum number of buffered access log records
+const DEFAULT_BUFFER_SIZE: usize = 100000;
+
+/// The maximum delay between retries of failed inserts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// The access log table columns with their ClickHouse types
+const CLICKHOUSE_COLUMNS: &[(&str, &str)] = &[
+  ("timestamp", "DateTime64(3, 'UTC')"),
+  ("client_ip", "String"),
+  ("auth_user", "Nullable(String)"),
+  ("method", "LowCardinality(String)"),
+  ("path_and_query", "String"),
+  ("version", "LowCardinality(String)"),
+  ("status_code", "UInt16"),
+  ("content_length", "Nullable(UInt64)"),
+  ("host", "Nullable(String)"),
+  ("referer", "Nullable(String)"),
+  ("user_agent", "Nullable(String)"),
+];
+
+/// The access log table columns with their PostgreSQL types
+const POSTGRES_COLUMNS: &[(&str, &str, Type)] = &[
+  ("timestamp", "TIMESTAMPTZ NOT NULL", Type::TIMESTAMPTZ),
+  ("client_ip", "INET NOT NULL", Type::INET),
+  ("auth_user", "TEXT", Type::TEXT),
+  ("method", "TEXT NOT NULL", Type::TEXT),
+  ("path_and_query", "TEXT NOT NULL", Type::TEXT),
+  ("version", "TEXT NOT NULL", Type::TEXT),
+  ("status_code", "SMALLINT NOT NULL", Type::INT2),
+  ("content_length", "BIGINT", Type::INT8),
+  ("host", "TEXT", Type::TEXT),
+  ("referer", "TEXT", Type::TEXT),
+  ("user_agent", "TEXT", Type::TEXT),
+];
+
+/// Checks if the table name is valid (an SQL identifier, optionally qualified with a database or a schema name)
+fn is_valid_table_name(table: &str) -> bool {
+  table.split('.').count() <= 2
+    && table.split('.').all(|part| {
+      let mut chars = part.chars();
+      chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Checks if the database URL is a PostgreSQL connection URL
+fn is_postgres_url(url: &str) -> bool {
+  url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+/// Checks if the database URL is a ClickHouse HTTP interface URL
+fn is_clickhouse_url(url: &str) -> bool {
+  (url.starts_with("http://") || url.starts_with("https://")) && url.parse::<Uri>().is_ok()
+}
+
+/// A ClickHouse database, into which access log records are inserted via the HTTP interface
+struct ClickHouseDatabase {
+  client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+  url: Uri,
+  table: String,
+  schema_created: bool,
+}
+
+impl ClickHouseDatabase {
+  fn new(url: Uri, table: String, tls_config: ClientConfig) -> Self {
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+      .with_tls_config(tls_config)
+      .https_or_http()
+      .enable_http1()
+      .build();
+    Self {
+      client: Client::builder(hyper_util::rt::TokioExecutor::new()).build(connector),
+      url,
+      table,
+      schema_created: false,
+    }
+  }
+
+  /// Executes a ClickHouse query
+  async fn query(&self, query: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request = Request::post(self.url.clone()).body(Full::new(Bytes::from(query)))?;
+    let response = self.client.request(request).await?;
+    let status = response.status();
+    if !status.is_success() {
+      let body = response.into_body().collect().await?.to_bytes();
+      Err(anyhow::anyhow!(
+        "ClickHouse responded with {status}: {}",
+        String::from_utf8_lossy(&body).trim()
+      ))?
+    }
+    Ok(())
+  }
+
+  /// Creates the access log table, and adds the columns missing in the table
+  async fn create_schema(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    self
+      .query(format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}) ENGINE = MergeTree PARTITION BY toYYYYMM(timestamp) ORDER BY timestamp",
+        self.table,
+        CLICKHOUSE_COLUMNS
+          .iter()
+          .map(|(name, column_type)| format!("{name} {column_type}"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ))
+      .await?;
+    self
+      .query(format!(
+        "ALTER TABLE {} {}",
+        self.table,
+        CLICKHOUSE_COLUMNS
+          .iter()
+          .map(|(name, column_type)| format!("ADD COLUMN IF NOT EXISTS {name} {column_type}"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ))
+      .await
+  }
+
+  /// Inserts access log records into the database
+  async fn insert(&mut self, records: &[Arc<AccessLogRecord>]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !self.schema_created {
+      self.create_schema().await?;
+      self.schema_created = true;
+    }
+
+    let mut query = format!("INSERT INTO {} FORMAT JSONEachRow\n", self.table);
+    for record in records {
+      query.push_str(
+        &serde_json::json!({
+          "timestamp": DateTime::<Utc>::from(record.timestamp).format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+          "client_ip": record.client_ip.to_string(),
+          "auth_user": record.auth_user,
+          "method": record.method,
+          "path_and_query": record.path_and_query,
+          "version": record.version,
+          "status_code": record.status_code,
+          "content_length": record.content_length,
+          "host": record.host,
+          "referer": record.referer,
+          "user_agent": record.user_agent,
+        })
+        .to_string(),
+      );
+      query.push('\n');
+    }
+    self.query(query).await
+  }
+}
+
+/// A PostgreSQL database, into which access log records are copied
+struct PostgresDatabase {
+  url: String,
+  tls: MakeRustlsConnect,
+  table: String,
+  client: Option<tokio_postgres::Client>,
+}
+
+impl PostgresDatabase {
+  fn new(url: String, table: String, tls_config: ClientConfig) -> Self {
+    Self {
+      url,
+      tls: MakeRustlsConnect::new(tls_config),
+      table,
+      client: None,
+    }
+  }
+
+  /// Connects to the database and creates the access log table, if there is no open connection
+  async fn connect(&mut self) -> Result<&tokio_postgres::Client, Box<dyn Error + Send + Sync>> {
+    if let Some(client) = self.client.take().filter(|client| !client.is_closed()) {
+      return Ok(self.client.insert(client));
+    }
+
+    let (client, connection) = tokio_postgres::connect(&self.url, self.tls.clone()).await?;
+    tokio::spawn(async move {
+      if let Err(err) = connection.await {
+        eprintln!("Database access log connection error: {err}");
+      }
+    });
+
+    let index_name = format!("{}_timestamp_idx", self.table.rsplit('.').next().unwrap_or(&self.table));
+    client
+      .batch_execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} ({columns}); ALTER TABLE {table} {add_columns}; \
+         CREATE INDEX IF NOT EXISTS {index_name} ON {table} (timestamp);",
+        table = self.table,
+        columns = POSTGRES_COLUMNS
+          .iter()
+          .map(|(name, column_type, _)| format!("{name} {column_type}"))
+          .collect::<Vec<_>>()
+          .join(", "),
+        add_columns = POSTGRES_COLUMNS
+          .iter()
+          .map(|(name, column_type, _)| format!("ADD COLUMN IF NOT EXISTS {name} {column_type}"))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ))
+      .await?;
+
+    Ok(self.client.insert(client))
+  }
+
+  /// Inserts access log records into the database
+  async fn insert(&mut self, records: &[Arc<AccessLogRecord>]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let statement = format!(
+      "COPY {} ({}) FROM STDIN BINARY",
+      self.table,
+      POSTGRES_COLUMNS
+        .iter()
+        .map(|(name, _, _)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+    );
+    let column_types = POSTGRES_COLUMNS
+      .iter()
+      .map(|(_, _, column_type)| column_type.clone())
+      .collect::<Vec<_>>();
+
+    let client = self.connect().await?;
+    let sink = client.copy_in(&statement).await?;
+    let mut writer = std::pin::pin!(BinaryCopyInWriter::new(sink, &column_types));
+    for record in records {
+      let status_code = record.status_code as i16;
+      let content_length = record
+        .content_length
+        .map(|content_length| i64::try_from(content_length).unwrap_or(i64::MAX));
+      writer
+        .as_mut()
+        .write(&[
+          &record.timestamp,
+          &record.client_ip,
+          &record.auth_user,
+          &record.method,
+          &record.path_and_query,
+          &record.version,
+          &status_code,
+          &content_length,
+          &record.host,
+          &record.referer,
+          &record.user_agent,
+        ])
+        .await?;
+    }
+    writer.finish().await?;
+
+    Ok(())
+  }
+}
+
+/// A database, into which access log records are inserted
+enum LogDatabase {
+  ClickHouse(Box<ClickHouseDatabase>),
+  Postgres(Box<PostgresDatabase>),
+}
+
+impl LogDatabase {
+  /// Inserts access log records into the database
+  async fn insert(&mut self, records: &[Arc<AccessLogRecord>]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::ClickHouse(database) => database.insert(records).await,
+      Self::Postgres(database) => database.insert(records).await,
+    }
+  }
+}
+
+/// Inserts the buffered access log records into the database in batches.
+/// Returns `false` if an insert failed, in which case the records are kept in the buffer.
+async fn flush_log_buffer(
+  database: &mut LogDatabase,
+  buffer: &Mutex<LogBuffer<Arc<AccessLogRecord>>>,
+  batch_size: usize,
+) -> bool {
+  loop {
+    let batch = match buffer.lock() {
+      Ok(mut buffer) => buffer.take_batch(batch_size),
+      Err(_) => return true,
+    };
+    if batch.is_empty() {
+      return true;
+    }
+    let batch_length = batch.len();
+    if let Err(err) = database.insert(&batch).await {
+      eprintln!("Failed to insert access logs into the database: {err}");
+      if let Ok(mut buffer) = buffer.lock() {
+        buffer.return_batch(batch);
+      }
+      return false;
+    }
+    if batch_length < batch_size {
+      return true;
+    }
+  }
+}
+
+/// Database access log observability backend loader
+pub struct DatabaseLogObservabilityBackendLoader {
+  cache: ModuleCache<DatabaseLogObservabilityBackend>,
+}
+
+impl Default for DatabaseLogObservabilityBackendLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DatabaseLogObservabilityBackendLoader {
+  /// Creates a new observability backend loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["log_database"]),
+    }
+  }
+}
+
+impl ObservabilityBackendLoader for DatabaseLogObservabilityBackendLoader {
+  fn load_observability_backend(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn ObservabilityBackend + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, move |config| {
+          let cancel_token = CancellationToken::new();
+          let log_database = get_entry!("log_database", config);
+          let url = match log_database.and_then(|e| e.values.first()).and_then(|v| v.as_str()) {
+            Some(url) => url.to_string(),
+            None => {
+              return Ok(Arc::new(DatabaseLogObservabilityBackend {
+                cancel_token,
+                logging_tx: None,
+              }))
+            }
+          };
+          let table = log_database
+            .and_then(|e| e.props.get("table"))
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_TABLE)
+            .to_string();
+          let batch_size = log_database
+            .and_then(|e| e.props.get("batch_size"))
+            .and_then(|v| v.as_i128())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_BATCH_SIZE);
+          let flush_interval = log_database
+            .and_then(|e| e.props.get("flush_interval"))
+            .and_then(|v| v.as_duration())
+            .unwrap_or(Duration::from_millis(DEFAULT_FLUSH_INTERVAL));
+          let buffer_size = log_database
+            .and_then(|e| e.props.get("buffer_size"))
+            .and_then(|v| v.as_i128())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_BUFFER_SIZE);
+
+          let tls_config = build_tls_client_config()?;
+          let mut database = if is_postgres_url(&url) {
+            LogDatabase::Postgres(Box::new(PostgresDatabase::new(url, table, tls_config)))
+          } else {
+            LogDatabase::ClickHouse(Box::new(ClickHouseDatabase::new(url.parse()?, table, tls_config)))
+          };
+
+          let buffer = Arc::new(Mutex::new(LogBuffer::new(buffer_size)));
+          let batch_ready = Arc::new(Notify::new());
+          let (logging_tx, logging_rx) = async_channel::unbounded::<LogMessage>();
+
+          // Buffering loop
+          let buffer_clone = buffer.clone();
+          let batch_ready_clone = batch_ready.clone();
+          let cancel_token_clone = cancel_token.clone();
+          secondary_runtime.spawn(async move {
+            loop {
+              tokio::select! {
+                message = logging_rx.recv() => {
+                  match message {
+                    Ok(message) => {
+                      if let Some(record) = message.get_access_log_record() {
+                        if let Ok(mut buffer) = buffer_clone.lock() {
+                          buffer.push(record);
+                          if buffer.len() >= batch_size {
+                            batch_ready_clone.notify_one();
+                          }
+                        }
+                      }
+                    }
+                    Err(_) => break, // Channel closed
+                  }
+                }
+                _ = cancel_token_clone.cancelled() => break,
+              }
+            }
+          });
+
+          // Inserting loop
+          let cancel_token_clone = cancel_token.clone();
+          secondary_runtime.spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            let mut retry_delay: Option<Duration> = None;
+            loop {
+              tokio::select! {
+                _ = batch_ready.notified() => {}
+                _ = interval.tick() => {}
+                _ = cancel_token_clone.cancelled() => {
+                  // Insert the remaining access log records before the backend is shut down
+                  flush_log_buffer(&mut database, &buffer, batch_size).await;
+                  return;
+                }
+              }
+
+              if !flush_log_buffer(&mut database, &buffer, batch_size).await {
+                // Back off exponentially, while the database is unavailable
+                let delay = retry_delay.map_or(flush_interval, |delay| (delay * 2).min(MAX_RETRY_DELAY));
+                retry_delay = Some(delay);
+                tokio::select! {
+                  _ = tokio::time::sleep(delay) => {}
+                  _ = cancel_token_clone.cancelled() => return,
+                }
+              } else {
+                retry_delay = None;
+              }
+
+              let dropped = buffer.lock().map_or(0, |mut buffer| buffer.take_dropped());
+              if dropped > 0 {
+                eprintln!("Dropped {dropped} access log records, because the database access log buffer is full");
+              }
+            }
+          });
+
+          Ok(Arc::new(DatabaseLogObservabilityBackend {
+            cancel_token,
+            logging_tx: Some(logging_tx),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["log_database"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ferron_common::config::ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("log_database", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `log_database` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .is_some_and(|url| is_postgres_url(url) || is_clickhouse_url(url))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid access log database URL (PostgreSQL connection URLs and ClickHouse HTTP interface URLs are supported)"
+          ))?
+        } else if !entry
+          .props
+          .get("table")
+          .is_none_or(|v| v.as_str().is_some_and(is_valid_table_name))
+        {
+          Err(anyhow::anyhow!("Invalid access log database table name"))?
+        } else if entry
+          .props
+          .get("batch_size")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!("Invalid access log database batch size"))?
+        } else if entry
+          .props
+          .get("flush_interval")
+          .is_some_and(|v| v.as_duration().is_none_or(|v| v.is_zero()))
+        {
+          Err(anyhow::anyhow!("Invalid access log database flush interval"))?
+        } else if entry
+          .props
+          .get("buffer_size")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!("Invalid access log database buffer size"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+struct DatabaseLogObservabilityBackend {
+  cancel_token: CancellationToken,
+  logging_tx: Option<Sender<LogMessage>>,
+}
+
+impl ObservabilityBackend for DatabaseLogObservabilityBackend {
+  fn get_log_channel(&self) -> Option<Sender<LogMessage>> {
+    self.logging_tx.clone()
+  }
+}
+
+impl Drop for DatabaseLogObservabilityBackend {
+  fn drop(&mut self) {
+    self.cancel_token.cancel();
+  }
+}
@@ -1,13 +1,26 @@
+#[cfg(feature = "dblog")]
+mod dblog;
 #[cfg(feature = "logfile")]
 mod logfile;
 #[cfg(feature = "otlp")]
 mod otlp;
+#[cfg(feature = "statsd")]
+mod statsd;
 #[cfg(feature = "stdlog")]
 mod stdlog;
+#[cfg(feature = "streamlog")]
+mod streamlog;
+mod util;
 
+#[cfg(feature = "dblog")]
+pub use dblog::*;
 #[cfg(feature = "logfile")]
 pub use logfile::*;
 #[cfg(feature = "otlp")]
 pub use otlp::*;
+#[cfg(feature = "statsd")]
+pub use statsd::*;
 #[cfg(feature = "stdlog")]
 pub use stdlog::*;
+#[cfg(feature = "streamlog")]
+pub use streamlog::*;
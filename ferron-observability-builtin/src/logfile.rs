@@ -7,7 +7,7 @@ use ferron_common::{
   get_entries_for_validation, get_value,
   logging::LogMessage,
   observability::{ObservabilityBackend, ObservabilityBackendLoader},
-  util::ModuleCache,
+  util::{register_scheduled_task, ModuleCache, ScheduledTaskHandler},
 };
 use tokio::io::{AsyncWriteExt, BufWriter};
 
@@ -193,18 +193,23 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
           let log_rotate_size = get_value!("log_rotate_size", config)
-            .and_then(|v| v.as_i128())
+            .and_then(|v| v.as_size())
             .map(|v| v as usize);
           let log_rotate_keep = get_value!("log_rotate_keep", config)
             .and_then(|v| v.as_i128())
             .map(|v| v as usize);
           let error_log_rotate_size = get_value!("error_log_rotate_size", config)
-            .and_then(|v| v.as_i128())
+            .and_then(|v| v.as_size())
             .map(|v| v as usize);
           let error_log_rotate_keep = get_value!("error_log_rotate_keep", config)
             .and_then(|v| v.as_i128())
             .map(|v| v as usize);
           let (logging_tx, logging_rx) = async_channel::unbounded::<LogMessage>();
+          // The log files can be also rotated by the `log_rotate` scheduled task
+          let rotate_notify = Arc::new(tokio::sync::Notify::new());
+          let rotate_notify_clone = rotate_notify.clone();
+          let log_rotate_handler: Arc<ScheduledTaskHandler> = Arc::new(move || rotate_notify_clone.notify_one());
+          register_scheduled_task("log_rotate", &log_rotate_handler);
           secondary_runtime.spawn(async move {
             let mut access_log = if let Some(filename) = log_filename {
               match LogFile::new(filename, log_rotate_size, log_rotate_keep).await {
@@ -261,6 +266,18 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
                         log.flush().await;
                     }
                 }
+                _ = rotate_notify.notified() => {
+                    if let Some(log) = &mut access_log {
+                        if let Err(e) = log.rotate().await {
+                            eprintln!("Failed to rotate log file: {e}");
+                        }
+                    }
+                    if let Some(log) = &mut error_log {
+                        if let Err(e) = log.rotate().await {
+                            eprintln!("Failed to rotate error log file: {e}");
+                        }
+                    }
+                }
                 _ = cancel_token.cancelled() => return,
               }
             }
@@ -268,6 +285,7 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
           Ok(Arc::new(LogFileObservabilityBackend {
             cancel_token: cancel_token_clone,
             logging_tx,
+            _log_rotate_handler: log_rotate_handler,
           }))
         })?,
     )
@@ -312,7 +330,7 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
           Err(anyhow::anyhow!(
             "The `log_rotate_size` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 0) {
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid log rotation maximum size"))?
         }
       }
@@ -336,7 +354,7 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
           Err(anyhow::anyhow!(
             "The `error_log_rotate_size` configuration property must have exactly one value"
           ))?
-        } else if !entry.values[0].is_null() && entry.values[0].as_i128().is_none_or(|v| v < 0) {
+        } else if !entry.values[0].is_null() && entry.values[0].as_size().is_none() {
           Err(anyhow::anyhow!("Invalid error log rotation maximum size"))?
         }
       }
@@ -361,6 +379,7 @@ impl ObservabilityBackendLoader for LogFileObservabilityBackendLoader {
 struct LogFileObservabilityBackend {
   cancel_token: tokio_util::sync::CancellationToken,
   logging_tx: Sender<LogMessage>,
+  _log_rotate_handler: Arc<ScheduledTaskHandler>,
 }
 
 impl ObservabilityBackend for LogFileObservabilityBackend {
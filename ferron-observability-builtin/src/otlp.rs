@@ -4,7 +4,7 @@ use async_channel::Sender;
 use ferron_common::{
   config::ServerConfiguration,
   get_entries_for_validation, get_entry, get_value,
-  logging::LogMessage,
+  logging::{LogLevel, LogMessage},
   observability::{
     Metric, MetricAttributeValue, MetricType, MetricValue, ObservabilityBackend, ObservabilityBackendLoader,
     TraceSignal,
@@ -16,7 +16,7 @@ use hyper::header::HeaderValue;
 use opentelemetry::trace::{Tracer, TracerProvider};
 use opentelemetry::KeyValue;
 use opentelemetry::{
-  logs::{LogRecord, Logger, LoggerProvider},
+  logs::{LogRecord, Logger, LoggerProvider, Severity},
   Context,
 };
 use opentelemetry::{metrics::MeterProvider, trace::TraceContextExt};
@@ -188,9 +188,22 @@ impl ObservabilityBackendLoader for OtlpObservabilityBackendLoader {
                       return;
                   },
                 } {
+                  let level = message.get_level();
+                  let target = message.get_target();
                   let (message_inner, is_error) = message.get_message();
                   if is_error {
                     let mut log_record = error_logger.create_log_record();
+                    log_record.set_severity_number(match level {
+                      LogLevel::Error => Severity::Error,
+                      LogLevel::Warn => Severity::Warn,
+                      LogLevel::Info => Severity::Info,
+                      LogLevel::Debug => Severity::Debug,
+                      LogLevel::Trace => Severity::Trace,
+                    });
+                    log_record.set_severity_text(level.as_str());
+                    if let Some(target) = target {
+                      log_record.set_target(target);
+                    }
                     log_record.set_body(message_inner.into());
                     error_logger.emit(log_record);
                   } else {
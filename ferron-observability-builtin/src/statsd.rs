@@ -0,0 +1,276 @@
+use std::{error::Error, sync::Arc, time::Duration};
+
+use async_channel::Sender;
+use ferron_common::{
+  config::ServerConfiguration,
+  get_entries_for_validation, get_entry,
+  observability::{Metric, ObservabilityBackend, ObservabilityBackendLoader},
+  util::ModuleCache,
+};
+use tokio::net::UdpSocket;
+#[cfg(unix)]
+use tokio::net::UnixDatagram;
+use tokio_util::sync::CancellationToken;
+
+use crate::util::StatsdFormatter;
+
+/// The default interval, after which the buffered metrics are sent, in milliseconds
+const DEFAULT_FLUSH_INTERVAL: u64 = 1000;
+/// The default maximum size of a UDP packet, fitting in the Ethernet MTU
+const DEFAULT_UDP_MAX_PACKET_SIZE: usize = 1432;
+/// The default maximum size of a Unix datagram, as recommended for DogStatsD
+const DEFAULT_UNIX_MAX_PACKET_SIZE: usize = 8192;
+
+/// A socket, to which StatsD metrics are sent
+enum StatsdSocket {
+  Udp(UdpSocket),
+  #[cfg(unix)]
+  Unix(UnixDatagram),
+}
+
+impl StatsdSocket {
+  /// Connects to the StatsD server specified by the URL (`udp://<host>:<port>` or `unix://<path>`)
+  async fn connect(url: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    if let Some(address) = url.strip_prefix("udp://") {
+      let address = tokio::net::lookup_host(address)
+        .await?
+        .next()
+        .ok_or(anyhow::anyhow!("Cannot resolve the StatsD server address"))?;
+      let socket = UdpSocket::bind(if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+      socket.connect(address).await?;
+      Ok(Self::Udp(socket))
+    } else if let Some(path) = url.strip_prefix("unix://") {
+      #[cfg(unix)]
+      {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::Unix(socket))
+      }
+      #[cfg(not(unix))]
+      {
+        let _ = path;
+        Err(anyhow::anyhow!("Unix sockets aren't supported on this platform").into())
+      }
+    } else {
+      Err(anyhow::anyhow!("Unsupported StatsD server URL: {url}").into())
+    }
+  }
+
+  /// Sends the packet to the StatsD server
+  async fn send(&self, packet: &[u8]) -> std::io::Result<usize> {
+    match self {
+      Self::Udp(socket) => socket.send(packet).await,
+      #[cfg(unix)]
+      Self::Unix(socket) => socket.send(packet).await,
+    }
+  }
+}
+
+/// StatsD observability backend loader
+pub struct StatsdObservabilityBackendLoader {
+  cache: ModuleCache<StatsdObservabilityBackend>,
+}
+
+impl Default for StatsdObservabilityBackendLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StatsdObservabilityBackendLoader {
+  /// Creates a new observability backend loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["statsd"]),
+    }
+  }
+}
+
+impl ObservabilityBackendLoader for StatsdObservabilityBackendLoader {
+  fn load_observability_backend(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn ObservabilityBackend + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, move |config| {
+          let cancel_token = CancellationToken::new();
+          let statsd = get_entry!("statsd", config);
+          let url = match statsd.and_then(|e| e.values.first()).and_then(|v| v.as_str()) {
+            Some(url) => url.to_string(),
+            None => {
+              return Ok(Arc::new(StatsdObservabilityBackend {
+                cancel_token,
+                metrics_tx: None,
+              }))
+            }
+          };
+          let prefix = statsd
+            .and_then(|e| e.props.get("prefix"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+          let dogstatsd = statsd
+            .and_then(|e| e.props.get("dogstatsd"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+          let flush_interval = statsd
+            .and_then(|e| e.props.get("flush_interval"))
+            .and_then(|v| v.as_duration())
+            .unwrap_or(Duration::from_millis(DEFAULT_FLUSH_INTERVAL));
+          let max_packet_size = statsd
+            .and_then(|e| e.props.get("max_packet_size"))
+            .and_then(|v| v.as_size())
+            .map(|v| v as usize)
+            .unwrap_or(if url.starts_with("unix://") {
+              DEFAULT_UNIX_MAX_PACKET_SIZE
+            } else {
+              DEFAULT_UDP_MAX_PACKET_SIZE
+            });
+
+          let (metrics_tx, metrics_rx) = async_channel::unbounded::<Metric>();
+          let cancel_token_clone = cancel_token.clone();
+          secondary_runtime.spawn(async move {
+            let mut formatter = StatsdFormatter::new(&prefix, dogstatsd);
+            let mut socket: Option<StatsdSocket> = None;
+            let mut connection_error_logged = false;
+            let mut packets: Vec<String> = Vec::new();
+            let mut packet = String::new();
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+              let flush = tokio::select! {
+                metric = metrics_rx.recv() => match metric {
+                  Ok(metric) => {
+                    // The metrics are batched into packets, separated by newlines
+                    for line in formatter.format(&metric) {
+                      if !packet.is_empty() && packet.len() + line.len() + 1 > max_packet_size {
+                        packets.push(std::mem::take(&mut packet));
+                      }
+                      if !packet.is_empty() {
+                        packet.push('\n');
+                      }
+                      packet.push_str(&line);
+                    }
+                    false
+                  }
+                  Err(_) => true,
+                },
+                _ = interval.tick() => true,
+                _ = cancel_token_clone.cancelled() => true,
+              };
+              if !flush {
+                continue;
+              }
+              if !packet.is_empty() {
+                packets.push(std::mem::take(&mut packet));
+              }
+              if !packets.is_empty() {
+                if socket.is_none() {
+                  match StatsdSocket::connect(&url).await {
+                    Ok(connected_socket) => {
+                      socket = Some(connected_socket);
+                      connection_error_logged = false;
+                    }
+                    Err(e) => {
+                      if !connection_error_logged {
+                        eprintln!("Failed to connect to the StatsD server: {e}");
+                        connection_error_logged = true;
+                      }
+                    }
+                  }
+                }
+                let mut send_failed = false;
+                if let Some(connected_socket) = &socket {
+                  for packet in &packets {
+                    if connected_socket.send(packet.as_bytes()).await.is_err() {
+                      send_failed = true;
+                      break;
+                    }
+                  }
+                }
+                if send_failed {
+                  // Reconnect on the next flush, since the StatsD server (for example, a Datadog agent) might have restarted
+                  socket = None;
+                }
+                // The metrics that weren't sent are dropped, like with StatsD over UDP
+                packets.clear();
+              }
+              if cancel_token_clone.is_cancelled() || metrics_rx.is_closed() {
+                return;
+              }
+            }
+          });
+          Ok(Arc::new(StatsdObservabilityBackend {
+            cancel_token,
+            metrics_tx: Some(metrics_tx),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["statsd"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ferron_common::config::ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("statsd", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `statsd` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .is_some_and(|v| v.starts_with("udp://") || v.starts_with("unix://"))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid StatsD server URL (the URL must start with `udp://` or `unix://`)"
+          ))?
+        } else if !entry.props.get("prefix").is_none_or(|v| v.is_string()) {
+          Err(anyhow::anyhow!("Invalid StatsD metric name prefix"))?
+        } else if !entry.props.get("dogstatsd").is_none_or(|v| v.is_bool()) {
+          Err(anyhow::anyhow!("Invalid DogStatsD extensions enabling option"))?
+        } else if entry
+          .props
+          .get("flush_interval")
+          .is_some_and(|v| v.as_duration().is_none_or(|v| v.is_zero()))
+        {
+          Err(anyhow::anyhow!("Invalid StatsD flush interval"))?
+        } else if entry
+          .props
+          .get("max_packet_size")
+          .is_some_and(|v| v.as_size().is_none_or(|v| v == 0))
+        {
+          Err(anyhow::anyhow!("Invalid StatsD maximum packet size"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+struct StatsdObservabilityBackend {
+  cancel_token: CancellationToken,
+  metrics_tx: Option<Sender<Metric>>,
+}
+
+impl ObservabilityBackend for StatsdObservabilityBackend {
+  fn get_metric_channel(&self) -> Option<Sender<Metric>> {
+    self.metrics_tx.clone()
+  }
+}
+
+impl Drop for StatsdObservabilityBackend {
+  fn drop(&mut self) {
+    self.cancel_token.cancel();
+  }
+}
@@ -0,0 +1,648 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use async_channel::Sender;
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use ferron_common::{
+  config::ServerConfiguration,
+  get_entries_for_validation, get_entry,
+  logging::{AccessLogRecord, LogLevel, LogMessage},
+  observability::{ObservabilityBackend, ObservabilityBackendLoader},
+  util::ModuleCache,
+};
+use rskafka::{
+  client::{
+    partition::{Compression, PartitionClient, UnknownTopicHandling},
+    ClientBuilder,
+  },
+  record::Record,
+};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::util::{build_tls_client_config, kafka_partition, LogBuffer};
+
+/// The default name of the topic (or subject) for access logs
+const DEFAULT_TOPIC: &str = "ferron.access";
+
+/// The default maximum number of log entries published at once
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// The default interval (in milliseconds) of publishing buffered log entries
+const DEFAULT_FLUSH_INTERVAL: u64 = 1000;
+
+/// The default maximum number of buffered log entries
+const DEFAULT_BUFFER_SIZE: usize = 100000;
+
+/// The maximum delay between retries of failed publishes
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Checks if the topic name is valid for both Kafka topics and NATS subjects
+fn is_valid_topic(topic: &str) -> bool {
+  topic.len() <= 249
+    && topic
+      .split('.')
+      .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+}
+
+/// Checks if the URL is a Kafka bootstrap brokers URL
+fn is_kafka_url(url: &str) -> bool {
+  url
+    .strip_prefix("kafka://")
+    .or_else(|| url.strip_prefix("kafkas://"))
+    .is_some_and(|brokers| brokers.split(',').all(|broker| !broker.is_empty()))
+}
+
+/// Checks if the URL is a NATS server URL
+fn is_nats_url(url: &str) -> bool {
+  url.starts_with("nats://") || url.starts_with("tls://")
+}
+
+/// The delivery guarantee for published log entries
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+  /// Log entries, which failed to be published, are dropped
+  AtMostOnce,
+  /// Log entries, which failed to be published, are retried, and publishes are acknowledged by the server
+  AtLeastOnce,
+}
+
+/// The request property used as the partition key of access log entries
+#[derive(Clone, Copy)]
+enum PartitionKey {
+  Host,
+  ClientIp,
+}
+
+/// A log entry waiting to be published
+struct StreamLogEntry {
+  topic: Arc<str>,
+  key: Option<String>,
+  payload: Bytes,
+  timestamp: DateTime<Utc>,
+}
+
+/// Serializes an access log record into a JSON log entry payload
+fn access_log_payload(record: &AccessLogRecord) -> Bytes {
+  Bytes::from(
+    serde_json::json!({
+      "timestamp": DateTime::<Utc>::from(record.timestamp).to_rfc3339_opts(SecondsFormat::Millis, true),
+      "client_ip": record.client_ip.to_string(),
+      "auth_user": record.auth_user,
+      "method": record.method,
+      "path_and_query": record.path_and_query,
+      "version": record.version,
+      "status_code": record.status_code,
+      "content_length": record.content_length,
+      "host": record.host,
+      "referer": record.referer,
+      "user_agent": record.user_agent,
+    })
+    .to_string(),
+  )
+}
+
+/// Serializes an error log message into a JSON log entry payload
+fn error_log_payload(message: &str, level: LogLevel, timestamp: DateTime<Utc>) -> Bytes {
+  Bytes::from(
+    serde_json::json!({
+      "timestamp": timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+      "level": level.as_str(),
+      "message": message,
+    })
+    .to_string(),
+  )
+}
+
+/// A Kafka cluster, into which log entries are produced
+struct KafkaStream {
+  brokers: Vec<String>,
+  tls_config: Option<Arc<rustls::ClientConfig>>,
+  client: Option<rskafka::client::Client>,
+  partitions: HashMap<Arc<str>, Vec<i32>>,
+  partition_clients: HashMap<(Arc<str>, i32), Arc<PartitionClient>>,
+  next_partition: usize,
+}
+
+impl KafkaStream {
+  fn new(brokers: Vec<String>, tls_config: Option<Arc<rustls::ClientConfig>>) -> Self {
+    Self {
+      brokers,
+      tls_config,
+      client: None,
+      partitions: HashMap::new(),
+      partition_clients: HashMap::new(),
+      next_partition: 0,
+    }
+  }
+
+  /// Connects to the Kafka cluster, if there is no client yet
+  async fn client(&mut self) -> Result<&rskafka::client::Client, Box<dyn Error + Send + Sync>> {
+    let client = match self.client.take() {
+      Some(client) => client,
+      None => {
+        let mut client_builder = ClientBuilder::new(self.brokers.clone());
+        if let Some(tls_config) = &self.tls_config {
+          client_builder = client_builder.tls_config(tls_config.clone());
+        }
+        client_builder.build().await?
+      }
+    };
+    Ok(self.client.insert(client))
+  }
+
+  /// Selects the partition of the topic for the log entry. Entries with a key are assigned to partitions
+  /// the same way as the Kafka Java client does, while entries without a key are distributed evenly.
+  async fn select_partition(
+    &mut self,
+    topic: &Arc<str>,
+    key: Option<&str>,
+  ) -> Result<i32, Box<dyn Error + Send + Sync>> {
+    if !self.partitions.contains_key(topic) {
+      let topics = self.client().await?.list_topics().await?;
+      let partitions = topics
+        .into_iter()
+        .find(|metadata| metadata.name.as_str() == &**topic)
+        .map(|metadata| metadata.partitions.into_iter().collect::<Vec<_>>())
+        .filter(|partitions| !partitions.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("The Kafka topic \"{topic}\" doesn't exist"))?;
+      self.partitions.insert(topic.clone(), partitions);
+    }
+
+    let partitions = &self.partitions[topic];
+    let index = match key {
+      Some(key) => kafka_partition(key.as_bytes(), partitions.len()),
+      None => {
+        self.next_partition = self.next_partition.wrapping_add(1);
+        self.next_partition % partitions.len()
+      }
+    };
+    Ok(partitions[index])
+  }
+
+  /// Obtains the client for a partition of the topic
+  async fn partition_client(
+    &mut self,
+    topic: &Arc<str>,
+    partition: i32,
+  ) -> Result<Arc<PartitionClient>, Box<dyn Error + Send + Sync>> {
+    if let Some(partition_client) = self.partition_clients.get(&(topic.clone(), partition)) {
+      return Ok(partition_client.clone());
+    }
+
+    let partition_client = Arc::new(
+      self
+        .client()
+        .await?
+        .partition_client(topic.to_string(), partition, UnknownTopicHandling::Error)
+        .await?,
+    );
+    self
+      .partition_clients
+      .insert((topic.clone(), partition), partition_client.clone());
+    Ok(partition_client)
+  }
+
+  /// Produces log entries into the Kafka cluster
+  async fn publish(&mut self, entries: &[StreamLogEntry]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let result = self.try_publish(entries).await;
+    if result.is_err() {
+      // Reconnect to the Kafka cluster and refresh the topic metadata on the next attempt
+      self.client = None;
+      self.partitions.clear();
+      self.partition_clients.clear();
+    }
+    result
+  }
+
+  async fn try_publish(&mut self, entries: &[StreamLogEntry]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Group the log entries by topics and partitions
+    let mut batches: HashMap<(Arc<str>, i32), Vec<Record>> = HashMap::new();
+    for entry in entries {
+      let partition = self.select_partition(&entry.topic, entry.key.as_deref()).await?;
+      batches
+        .entry((entry.topic.clone(), partition))
+        .or_default()
+        .push(Record {
+          key: entry.key.as_ref().map(|key| key.as_bytes().to_vec()),
+          value: Some(entry.payload.to_vec()),
+          headers: Default::default(),
+          timestamp: entry.timestamp,
+        });
+    }
+
+    for ((topic, partition), records) in batches {
+      self
+        .partition_client(&topic, partition)
+        .await?
+        .produce(records, Compression::NoCompression)
+        .await?;
+    }
+
+    Ok(())
+  }
+}
+
+/// A NATS server, to which log entries are published
+struct NatsStream {
+  url: String,
+  delivery: Delivery,
+  client: Option<async_nats::Client>,
+}
+
+impl NatsStream {
+  fn new(url: String, delivery: Delivery) -> Self {
+    Self {
+      url,
+      delivery,
+      client: None,
+    }
+  }
+
+  /// Obtains the subject for the log entry. The partition key is appended as the last subject token.
+  fn subject(entry: &StreamLogEntry) -> String {
+    match &entry.key {
+      Some(key) => format!(
+        "{}.{}",
+        entry.topic,
+        key
+          .chars()
+          .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+          .collect::<String>()
+      ),
+      None => entry.topic.to_string(),
+    }
+  }
+
+  /// Publishes log entries to the NATS server. With the at-least-once delivery, the entries are published
+  /// via JetStream, and the publishes are acknowledged.
+  async fn publish(&mut self, entries: &[StreamLogEntry]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = match &self.client {
+      Some(client) => client.clone(),
+      None => self
+        .client
+        .insert(async_nats::connect(self.url.as_str()).await?)
+        .clone(),
+    };
+
+    match self.delivery {
+      Delivery::AtMostOnce => {
+        for entry in entries {
+          client.publish(Self::subject(entry), entry.payload.clone()).await?;
+        }
+        client.flush().await?;
+      }
+      Delivery::AtLeastOnce => {
+        let jetstream = async_nats::jetstream::new(client);
+        let mut acks = Vec::with_capacity(entries.len());
+        for entry in entries {
+          acks.push(jetstream.publish(Self::subject(entry), entry.payload.clone()).await?);
+        }
+        for ack in acks {
+          ack.await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A streaming platform, to which log entries are published
+enum LogStream {
+  Kafka(KafkaStream),
+  Nats(NatsStream),
+}
+
+impl LogStream {
+  /// Publishes log entries to the streaming platform
+  async fn publish(&mut self, entries: &[StreamLogEntry]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match self {
+      Self::Kafka(stream) => stream.publish(entries).await,
+      Self::Nats(stream) => stream.publish(entries).await,
+    }
+  }
+}
+
+/// Publishes the buffered log entries in batches.
+/// Returns `false` if a publish failed. With the at-least-once delivery, the log entries are then kept in the buffer.
+async fn flush_log_buffer(
+  stream: &mut LogStream,
+  buffer: &Mutex<LogBuffer<StreamLogEntry>>,
+  batch_size: usize,
+  delivery: Delivery,
+) -> bool {
+  loop {
+    let batch = match buffer.lock() {
+      Ok(mut buffer) => buffer.take_batch(batch_size),
+      Err(_) => return true,
+    };
+    if batch.is_empty() {
+      return true;
+    }
+    let batch_length = batch.len();
+    if let Err(err) = stream.publish(&batch).await {
+      eprintln!("Failed to publish logs to the streaming platform: {err}");
+      if delivery == Delivery::AtLeastOnce {
+        if let Ok(mut buffer) = buffer.lock() {
+          buffer.return_batch(batch);
+        }
+      }
+      return false;
+    }
+    if batch_length < batch_size {
+      return true;
+    }
+  }
+}
+
+/// Kafka and NATS log export observability backend loader
+pub struct StreamLogObservabilityBackendLoader {
+  cache: ModuleCache<StreamLogObservabilityBackend>,
+}
+
+impl Default for StreamLogObservabilityBackendLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StreamLogObservabilityBackendLoader {
+  /// Creates a new observability backend loader
+  pub fn new() -> Self {
+    Self {
+      cache: ModuleCache::new(vec!["log_stream"]),
+    }
+  }
+}
+
+impl ObservabilityBackendLoader for StreamLogObservabilityBackendLoader {
+  fn load_observability_backend(
+    &mut self,
+    config: &ServerConfiguration,
+    _global_config: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Arc<dyn ObservabilityBackend + Send + Sync>, Box<dyn Error + Send + Sync>> {
+    Ok(
+      self
+        .cache
+        .get_or_init::<_, Box<dyn std::error::Error + Send + Sync>>(config, move |config| {
+          let cancel_token = CancellationToken::new();
+          let log_stream = get_entry!("log_stream", config);
+          let url = match log_stream.and_then(|e| e.values.first()).and_then(|v| v.as_str()) {
+            Some(url) => url.to_string(),
+            None => {
+              return Ok(Arc::new(StreamLogObservabilityBackend {
+                cancel_token,
+                logging_tx: None,
+              }))
+            }
+          };
+          let topic: Arc<str> = log_stream
+            .and_then(|e| e.props.get("topic"))
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_TOPIC)
+            .into();
+          let error_topic: Option<Arc<str>> = log_stream
+            .and_then(|e| e.props.get("error_topic"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.into());
+          let partition_key = match log_stream.and_then(|e| e.props.get("partition_key")) {
+            Some(v) if v.is_null() => None,
+            Some(v) if v.as_str() == Some("client_ip") => Some(PartitionKey::ClientIp),
+            _ => Some(PartitionKey::Host),
+          };
+          let delivery = match log_stream
+            .and_then(|e| e.props.get("delivery"))
+            .and_then(|v| v.as_str())
+          {
+            Some("at_most_once") => Delivery::AtMostOnce,
+            _ => Delivery::AtLeastOnce,
+          };
+          let batch_size = log_stream
+            .and_then(|e| e.props.get("batch_size"))
+            .and_then(|v| v.as_i128())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_BATCH_SIZE);
+          let flush_interval = log_stream
+            .and_then(|e| e.props.get("flush_interval"))
+            .and_then(|v| v.as_duration())
+            .unwrap_or(Duration::from_millis(DEFAULT_FLUSH_INTERVAL));
+          let buffer_size = log_stream
+            .and_then(|e| e.props.get("buffer_size"))
+            .and_then(|v| v.as_i128())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_BUFFER_SIZE);
+
+          let mut stream = if let Some(brokers) = url.strip_prefix("kafka://") {
+            LogStream::Kafka(KafkaStream::new(
+              brokers.split(',').map(|broker| broker.to_string()).collect(),
+              None,
+            ))
+          } else if let Some(brokers) = url.strip_prefix("kafkas://") {
+            LogStream::Kafka(KafkaStream::new(
+              brokers.split(',').map(|broker| broker.to_string()).collect(),
+              Some(Arc::new(build_tls_client_config()?)),
+            ))
+          } else {
+            LogStream::Nats(NatsStream::new(url, delivery))
+          };
+
+          let buffer = Arc::new(Mutex::new(LogBuffer::new(buffer_size)));
+          let batch_ready = Arc::new(Notify::new());
+          let (logging_tx, logging_rx) = async_channel::unbounded::<LogMessage>();
+
+          // Buffering loop
+          let buffer_clone = buffer.clone();
+          let batch_ready_clone = batch_ready.clone();
+          let cancel_token_clone = cancel_token.clone();
+          secondary_runtime.spawn(async move {
+            loop {
+              tokio::select! {
+                message = logging_rx.recv() => {
+                  match message {
+                    Ok(message) => {
+                      let entry = if let Some(record) = message.get_access_log_record() {
+                        Some(StreamLogEntry {
+                          topic: topic.clone(),
+                          key: partition_key.and_then(|partition_key| match partition_key {
+                            PartitionKey::Host => record.host.clone(),
+                            PartitionKey::ClientIp => Some(record.client_ip.to_string()),
+                          }),
+                          payload: access_log_payload(&record),
+                          timestamp: record.timestamp.into(),
+                        })
+                      } else {
+                        let level = message.get_level();
+                        let (message, is_error) = message.get_message();
+                        match &error_topic {
+                          Some(error_topic) if is_error => {
+                            let timestamp = Utc::now();
+                            Some(StreamLogEntry {
+                              topic: error_topic.clone(),
+                              key: None,
+                              payload: error_log_payload(&message, level, timestamp),
+                              timestamp,
+                            })
+                          }
+                          _ => None,
+                        }
+                      };
+                      if let Some(entry) = entry {
+                        if let Ok(mut buffer) = buffer_clone.lock() {
+                          buffer.push(entry);
+                          if buffer.len() >= batch_size {
+                            batch_ready_clone.notify_one();
+                          }
+                        }
+                      }
+                    }
+                    Err(_) => break, // Channel closed
+                  }
+                }
+                _ = cancel_token_clone.cancelled() => break,
+              }
+            }
+          });
+
+          // Publishing loop
+          let cancel_token_clone = cancel_token.clone();
+          secondary_runtime.spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            let mut retry_delay: Option<Duration> = None;
+            loop {
+              tokio::select! {
+                _ = batch_ready.notified() => {}
+                _ = interval.tick() => {}
+                _ = cancel_token_clone.cancelled() => {
+                  // Publish the remaining log entries before the backend is shut down
+                  flush_log_buffer(&mut stream, &buffer, batch_size, delivery).await;
+                  return;
+                }
+              }
+
+              if !flush_log_buffer(&mut stream, &buffer, batch_size, delivery).await {
+                // Back off exponentially, while the streaming platform is unavailable
+                let delay = retry_delay.map_or(flush_interval, |delay| (delay * 2).min(MAX_RETRY_DELAY));
+                retry_delay = Some(delay);
+                tokio::select! {
+                  _ = tokio::time::sleep(delay) => {}
+                  _ = cancel_token_clone.cancelled() => return,
+                }
+              } else {
+                retry_delay = None;
+              }
+
+              let dropped = buffer.lock().map_or(0, |mut buffer| buffer.take_dropped());
+              if dropped > 0 {
+                eprintln!("Dropped {dropped} log entries, because the log export buffer is full");
+              }
+            }
+          });
+
+          Ok(Arc::new(StreamLogObservabilityBackend {
+            cancel_token,
+            logging_tx: Some(logging_tx),
+          }))
+        })?,
+    )
+  }
+
+  fn get_requirements(&self) -> Vec<&'static str> {
+    vec!["log_stream"]
+  }
+
+  fn validate_configuration(
+    &self,
+    config: &ferron_common::config::ServerConfiguration,
+    used_properties: &mut std::collections::HashSet<String>,
+  ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(entries) = get_entries_for_validation!("log_stream", config, used_properties) {
+      for entry in &entries.inner {
+        if entry.values.len() != 1 {
+          Err(anyhow::anyhow!(
+            "The `log_stream` configuration property must have exactly one value"
+          ))?
+        } else if !entry.values[0].is_null()
+          && !entry.values[0]
+            .as_str()
+            .is_some_and(|url| is_kafka_url(url) || is_nats_url(url))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid log export URL (Kafka bootstrap broker URLs and NATS server URLs are supported)"
+          ))?
+        } else if !entry
+          .props
+          .get("topic")
+          .is_none_or(|v| v.as_str().is_some_and(is_valid_topic))
+        {
+          Err(anyhow::anyhow!("Invalid log export topic"))?
+        } else if !entry
+          .props
+          .get("error_topic")
+          .is_none_or(|v| v.is_null() || v.as_str().is_some_and(is_valid_topic))
+        {
+          Err(anyhow::anyhow!("Invalid log export error topic"))?
+        } else if !entry
+          .props
+          .get("partition_key")
+          .is_none_or(|v| v.is_null() || matches!(v.as_str(), Some("host") | Some("client_ip")))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid log export partition key (the partition key must be `host`, `client_ip` or `#null`)"
+          ))?
+        } else if !entry
+          .props
+          .get("delivery")
+          .is_none_or(|v| matches!(v.as_str(), Some("at_most_once") | Some("at_least_once")))
+        {
+          Err(anyhow::anyhow!(
+            "Invalid log export delivery guarantee (the delivery guarantee must be `at_most_once` or `at_least_once`)"
+          ))?
+        } else if entry
+          .props
+          .get("batch_size")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!("Invalid log export batch size"))?
+        } else if entry
+          .props
+          .get("flush_interval")
+          .is_some_and(|v| v.as_duration().is_none_or(|v| v.is_zero()))
+        {
+          Err(anyhow::anyhow!("Invalid log export flush interval"))?
+        } else if entry
+          .props
+          .get("buffer_size")
+          .is_some_and(|v| v.as_i128().is_none_or(|v| v <= 0))
+        {
+          Err(anyhow::anyhow!("Invalid log export buffer size"))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+struct StreamLogObservabilityBackend {
+  cancel_token: CancellationToken,
+  logging_tx: Option<Sender<LogMessage>>,
+}
+
+impl ObservabilityBackend for StreamLogObservabilityBackend {
+  fn get_log_channel(&self) -> Option<Sender<LogMessage>> {
+    self.logging_tx.clone()
+  }
+}
+
+impl Drop for StreamLogObservabilityBackend {
+  fn drop(&mut self) {
+    self.cancel_token.cancel();
+  }
+}
@@ -0,0 +1,60 @@
+/// Computes the MurmurHash2 hash of the data, as computed by the Kafka Java client
+fn murmur2(data: &[u8]) -> u32 {
+  const SEED: u32 = 0x9747b28c;
+  const M: u32 = 0x5bd1e995;
+  const R: u32 = 24;
+
+  let mut hash = SEED ^ (data.len() as u32);
+  let chunks = data.chunks_exact(4);
+  let remainder = chunks.remainder();
+  for chunk in chunks {
+    let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    k = k.wrapping_mul(M);
+    k ^= k >> R;
+    k = k.wrapping_mul(M);
+    hash = hash.wrapping_mul(M);
+    hash ^= k;
+  }
+
+  if !remainder.is_empty() {
+    for (index, byte) in remainder.iter().enumerate().rev() {
+      hash ^= (*byte as u32) << (index * 8);
+    }
+    hash = hash.wrapping_mul(M);
+  }
+
+  hash ^= hash >> 13;
+  hash = hash.wrapping_mul(M);
+  hash ^= hash >> 15;
+  hash
+}
+
+/// Selects the Kafka partition index for the record key, compatible with the default partitioner of the Kafka Java client
+pub fn kafka_partition(key: &[u8], partition_count: usize) -> usize {
+  (murmur2(key) & 0x7fffffff) as usize % partition_count
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_murmur2() {
+    // Test vectors from the Kafka Java client
+    assert_eq!(murmur2(b"21") as i32, -973932308);
+    assert_eq!(murmur2(b"foobar") as i32, -790332482);
+    assert_eq!(murmur2(b"a-little-bit-long-string") as i32, -985981536);
+    assert_eq!(murmur2(b"a-little-bit-longer-string") as i32, -1486304829);
+    assert_eq!(
+      murmur2(b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8") as i32,
+      -58897971
+    );
+    assert_eq!(murmur2(b"abc") as i32, 479470107);
+  }
+
+  #[test]
+  fn test_kafka_partition() {
+    assert_eq!(kafka_partition(b"foobar", 1), 0);
+    assert_eq!(kafka_partition(b"foobar", 7), (-790332482i32 & 0x7fffffff) as usize % 7);
+  }
+}
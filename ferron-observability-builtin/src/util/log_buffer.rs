@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// A bounded buffer of log entries waiting to be exported
+pub struct LogBuffer<T> {
+  entries: VecDeque<T>,
+  capacity: usize,
+  dropped: u64,
+}
+
+impl<T> LogBuffer<T> {
+  /// Creates a new log buffer with the specified maximum number of entries
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      entries: VecDeque::new(),
+      capacity,
+      dropped: 0,
+    }
+  }
+
+  /// Returns the number of buffered log entries
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Adds a log entry to the buffer. If the buffer is full, the entry is dropped.
+  pub fn push(&mut self, entry: T) {
+    if self.entries.len() < self.capacity {
+      self.entries.push_back(entry);
+    } else {
+      self.dropped += 1;
+    }
+  }
+
+  /// Takes up to the specified number of the oldest log entries from the buffer
+  pub fn take_batch(&mut self, batch_size: usize) -> Vec<T> {
+    let batch_size = batch_size.min(self.entries.len());
+    self.entries.drain(..batch_size).collect()
+  }
+
+  /// Returns log entries, which couldn't be exported, to the front of the buffer.
+  /// If the buffer is full, the newest entries are dropped.
+  pub fn return_batch(&mut self, batch: Vec<T>) {
+    for entry in batch.into_iter().rev() {
+      self.entries.push_front(entry);
+    }
+    while self.entries.len() > self.capacity {
+      self.entries.pop_back();
+      self.dropped += 1;
+    }
+  }
+
+  /// Takes the number of log entries dropped since the last call
+  pub fn take_dropped(&mut self) -> u64 {
+    std::mem::take(&mut self.dropped)
+  }
+}
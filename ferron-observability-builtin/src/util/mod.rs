@@ -0,0 +1,17 @@
+#[cfg(feature = "streamlog")]
+mod kafka_partitioner;
+#[cfg(any(feature = "dblog", feature = "streamlog"))]
+mod log_buffer;
+#[cfg(feature = "statsd")]
+mod statsd_format;
+#[cfg(any(feature = "dblog", feature = "streamlog"))]
+mod tls_config;
+
+#[cfg(feature = "streamlog")]
+pub use kafka_partitioner::*;
+#[cfg(any(feature = "dblog", feature = "streamlog"))]
+pub use log_buffer::*;
+#[cfg(feature = "statsd")]
+pub use statsd_format::*;
+#[cfg(any(feature = "dblog", feature = "streamlog"))]
+pub use tls_config::*;
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue};
+
+/// Replaces characters having a special meaning in the StatsD protocol
+fn sanitize(value: &str, extra_reserved: &[char]) -> String {
+  value
+    .chars()
+    .map(|c| {
+      if c == ':' || c == '|' || c == '@' || c == '#' || c.is_whitespace() || extra_reserved.contains(&c) {
+        '_'
+      } else {
+        c
+      }
+    })
+    .collect()
+}
+
+/// Converts the metric value into a floating-point number
+fn metric_value_as_f64(value: MetricValue) -> f64 {
+  match value {
+    MetricValue::F64(value) => value,
+    MetricValue::U64(value) => value as f64,
+    MetricValue::I64(value) => value as f64,
+    _ => 0.0,
+  }
+}
+
+/// Formats the metric value, without a fractional part for integers
+fn format_metric_value(value: MetricValue) -> String {
+  match value {
+    MetricValue::U64(value) => value.to_string(),
+    MetricValue::I64(value) => value.to_string(),
+    value => metric_value_as_f64(value).to_string(),
+  }
+}
+
+/// Formats metrics as lines of the StatsD protocol, optionally with DogStatsD extensions (tags and histograms)
+pub struct StatsdFormatter {
+  prefix: String,
+  dogstatsd: bool,
+  up_down_counters: HashMap<(&'static str, String), f64>,
+}
+
+impl StatsdFormatter {
+  /// Creates a new StatsD formatter
+  pub fn new(prefix: &str, dogstatsd: bool) -> Self {
+    Self {
+      prefix: prefix.to_string(),
+      dogstatsd,
+      up_down_counters: HashMap::new(),
+    }
+  }
+
+  /// Formats the attributes as DogStatsD tags
+  fn format_tags(&self, attributes: &[(&'static str, MetricAttributeValue)]) -> String {
+    if !self.dogstatsd || attributes.is_empty() {
+      return String::new();
+    }
+    let mut tags = String::from("|#");
+    for (index, (key, value)) in attributes.iter().enumerate() {
+      if index > 0 {
+        tags.push(',');
+      }
+      let value = match value {
+        MetricAttributeValue::String(value) => value.clone(),
+        MetricAttributeValue::Bool(value) => value.to_string(),
+        MetricAttributeValue::I64(value) => value.to_string(),
+        MetricAttributeValue::F64(value) => value.to_string(),
+      };
+      let _ = write!(tags, "{}:{}", sanitize(key, &[',']), sanitize(&value, &[',']));
+    }
+    tags
+  }
+
+  /// Formats the metric as StatsD lines.
+  /// Up-down counters are summed up and sent as gauges, since StatsD gauges set absolute values.
+  pub fn format(&mut self, metric: &Metric) -> Vec<String> {
+    let name = format!("{}{}", self.prefix, sanitize(metric.name, &[]));
+    let tags = self.format_tags(&metric.attributes);
+    match &metric.ty {
+      MetricType::Counter => vec![format!("{name}:{}|c{tags}", format_metric_value(metric.value))],
+      MetricType::Gauge => self.format_gauge(&name, metric_value_as_f64(metric.value), &tags),
+      MetricType::UpDownCounter => {
+        let total = self.up_down_counters.entry((metric.name, tags.clone())).or_insert(0.0);
+        *total += metric_value_as_f64(metric.value);
+        let total = *total;
+        self.format_gauge(&name, total, &tags)
+      }
+      MetricType::Histogram(_) => {
+        if self.dogstatsd {
+          vec![format!("{name}:{}|h{tags}", format_metric_value(metric.value))]
+        } else if metric.unit == Some("s") {
+          // Plain StatsD timers are in milliseconds
+          vec![format!("{name}:{}|ms", metric_value_as_f64(metric.value) * 1000.0)]
+        } else {
+          vec![format!("{name}:{}|ms", format_metric_value(metric.value))]
+        }
+      }
+    }
+  }
+
+  /// Formats the gauge value as StatsD lines
+  fn format_gauge(&self, name: &str, value: f64, tags: &str) -> Vec<String> {
+    if value < 0.0 && !self.dogstatsd {
+      // Negative values are treated as decrements in plain StatsD, so the gauge is reset to zero first
+      vec![format!("{name}:0|g"), format!("{name}:{value}|g")]
+    } else {
+      vec![format!("{name}:{value}|g{tags}")]
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn metric(name: &'static str, ty: MetricType, value: MetricValue, unit: Option<&'static str>) -> Metric {
+    Metric::new(
+      name,
+      vec![
+        ("http.request.method", MetricAttributeValue::String("GET".to_string())),
+        ("http.response.status_code", MetricAttributeValue::I64(200)),
+      ],
+      ty,
+      value,
+      unit,
+      None,
+    )
+  }
+
+  #[test]
+  fn formats_dogstatsd_metrics_with_tags() {
+    let mut formatter = StatsdFormatter::new("", true);
+    assert_eq!(
+      formatter.format(&metric(
+        "ferron.requests",
+        MetricType::Counter,
+        MetricValue::U64(1),
+        None
+      )),
+      vec!["ferron.requests:1|c|#http.request.method:GET,http.response.status_code:200"]
+    );
+    assert_eq!(
+      formatter.format(&metric(
+        "http.server.request.duration",
+        MetricType::Histogram(None),
+        MetricValue::F64(0.25),
+        Some("s")
+      )),
+      vec!["http.server.request.duration:0.25|h|#http.request.method:GET,http.response.status_code:200"]
+    );
+  }
+
+  #[test]
+  fn sums_up_down_counters_into_gauges() {
+    let mut formatter = StatsdFormatter::new("app.", false);
+    let active = |value| {
+      metric(
+        "active_requests",
+        MetricType::UpDownCounter,
+        MetricValue::I64(value),
+        None,
+      )
+    };
+    assert_eq!(formatter.format(&active(1)), vec!["app.active_requests:1|g"]);
+    assert_eq!(formatter.format(&active(1)), vec!["app.active_requests:2|g"]);
+    assert_eq!(
+      formatter.format(&active(-3)),
+      vec!["app.active_requests:0|g", "app.active_requests:-1|g"]
+    );
+  }
+
+  #[test]
+  fn formats_plain_statsd_timers_in_milliseconds() {
+    let mut formatter = StatsdFormatter::new("", false);
+    assert_eq!(
+      formatter.format(&metric(
+        "request.duration",
+        MetricType::Histogram(None),
+        MetricValue::F64(0.25),
+        Some("s")
+      )),
+      vec!["request.duration:250|ms"]
+    );
+  }
+
+  #[test]
+  fn sanitizes_reserved_characters() {
+    let mut formatter = StatsdFormatter::new("", true);
+    let metric = Metric::new(
+      "bad:name|x",
+      vec![("path", MetricAttributeValue::String("/a,b|c".to_string()))],
+      MetricType::Counter,
+      MetricValue::U64(2),
+      None,
+      None,
+    );
+    assert_eq!(formatter.format(&metric), vec!["bad_name_x:2|c|#path:/a_b_c"]);
+  }
+}
@@ -0,0 +1,29 @@
+use std::{error::Error, sync::Arc};
+
+use rustls::{client::WebPkiServerVerifier, ClientConfig};
+use rustls_platform_verifier::BuilderVerifierExt;
+
+/// Builds the TLS client configuration verifying the server certificates with the platform verifier
+/// (or with the Mozilla root certificates, if the platform verifier is unavailable)
+pub fn build_tls_client_config() -> Result<ClientConfig, Box<dyn Error + Send + Sync>> {
+  let crypto_provider = rustls::crypto::CryptoProvider::get_default()
+    .cloned()
+    .unwrap_or(Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+  Ok(
+    (if let Ok(client_config) = BuilderVerifierExt::with_platform_verifier(
+      ClientConfig::builder_with_provider(crypto_provider.clone()).with_safe_default_protocol_versions()?,
+    ) {
+      client_config
+    } else {
+      ClientConfig::builder_with_provider(crypto_provider)
+        .with_safe_default_protocol_versions()?
+        .with_webpki_verifier(
+          WebPkiServerVerifier::builder(Arc::new(rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+          }))
+          .build()?,
+        )
+    })
+    .with_no_client_auth(),
+  )
+}
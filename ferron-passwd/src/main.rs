@@ -1,44 +1,314 @@
-use clap::Parser;
+use argon2::Argon2;
+use clap::{Parser, ValueEnum};
+use kdl::{KdlEntry, KdlNode, KdlValue};
 use mimalloc::MiMalloc;
-use password_auth::generate_hash;
+use password_hash::rand_core::OsRng;
+use password_hash::{PasswordHasher, SaltString};
+use pbkdf2::Pbkdf2;
 use rpassword::prompt_password;
-use std::process;
+use scrypt::Scrypt;
+use sha2::{Digest, Sha256};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+/// The password hashing algorithm
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HashAlgorithm {
+  Argon2,
+  Scrypt,
+  Pbkdf2,
+}
+
+/// The format of the generated password hashes
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+  /// Human-readable text
+  Text,
+  /// htpasswd-compatible "username:hash" lines
+  Htpasswd,
+  /// KDL `user` directives
+  Kdl,
+}
+
 /// A password tool for Ferron
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-struct Args;
+struct Args {
+  /// The username, used to generate a hash for HTTP Digest authentication
+  #[arg(short, long)]
+  username: Option<String>,
+
+  /// The HTTP authentication realm, used to generate a hash for HTTP Digest authentication
+  #[arg(short, long)]
+  realm: Option<String>,
+
+  /// Read "username:password" pairs (one per line) from the standard input, and hash the passwords in batch
+  #[arg(short, long, conflicts_with_all = ["username", "csv"])]
+  batch: bool,
+
+  /// Read "username,password" records from a CSV file, and hash the passwords in batch
+  #[arg(short, long, value_name = "FILE", conflicts_with = "username")]
+  csv: Option<PathBuf>,
+
+  /// The output format [default: "text" for a single password, "htpasswd" in batch mode]
+  #[arg(short, long, value_enum)]
+  format: Option<OutputFormat>,
+
+  /// The password hashing algorithm
+  #[arg(short, long, value_enum, default_value_t = HashAlgorithm::Argon2)]
+  algorithm: HashAlgorithm,
+
+  /// The Argon2 memory cost in KiB
+  #[arg(long, value_name = "KIB")]
+  argon2_memory_cost: Option<u32>,
+
+  /// The Argon2 time cost (the number of iterations)
+  #[arg(long, value_name = "ITERATIONS")]
+  argon2_time_cost: Option<u32>,
+
+  /// The Argon2 degree of parallelism
+  #[arg(long, value_name = "THREADS")]
+  argon2_parallelism: Option<u32>,
+
+  /// The scrypt CPU/memory cost, as a base-2 logarithm
+  #[arg(long, value_name = "LOG_N")]
+  scrypt_log_n: Option<u8>,
+
+  /// The scrypt block size
+  #[arg(long, value_name = "R")]
+  scrypt_block_size: Option<u32>,
+
+  /// The scrypt parallelization parameter
+  #[arg(long, value_name = "P")]
+  scrypt_parallelism: Option<u32>,
+
+  /// The number of PBKDF2 rounds
+  #[arg(long, value_name = "ROUNDS")]
+  pbkdf2_rounds: Option<u32>,
+}
+
+/// Generates a password hash in the PHC string format, using the selected algorithm and cost parameters
+fn generate_hash(password: &str, args: &Args) -> Result<String, String> {
+  let salt = SaltString::generate(&mut OsRng);
+  let password_hash = match args.algorithm {
+    HashAlgorithm::Argon2 => {
+      let params = argon2::Params::new(
+        args.argon2_memory_cost.unwrap_or(argon2::Params::DEFAULT_M_COST),
+        args.argon2_time_cost.unwrap_or(argon2::Params::DEFAULT_T_COST),
+        args.argon2_parallelism.unwrap_or(argon2::Params::DEFAULT_P_COST),
+        None,
+      )
+      .map_err(|e| format!("Invalid Argon2 parameters: {e}"))?;
+      Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params).hash_password(password.as_bytes(), &salt)
+    }
+    HashAlgorithm::Scrypt => {
+      let params = scrypt::Params::new(
+        args.scrypt_log_n.unwrap_or(scrypt::Params::RECOMMENDED_LOG_N),
+        args.scrypt_block_size.unwrap_or(scrypt::Params::RECOMMENDED_R),
+        args.scrypt_parallelism.unwrap_or(scrypt::Params::RECOMMENDED_P),
+        scrypt::Params::RECOMMENDED_LEN,
+      )
+      .map_err(|e| format!("Invalid scrypt parameters: {e}"))?;
+      Scrypt.hash_password_customized(password.as_bytes(), None, None, params, &salt)
+    }
+    HashAlgorithm::Pbkdf2 => {
+      let default_params = pbkdf2::Params::default();
+      let params = pbkdf2::Params {
+        rounds: args.pbkdf2_rounds.unwrap_or(default_params.rounds),
+        ..default_params
+      };
+      Pbkdf2.hash_password_customized(password.as_bytes(), None, None, params, &salt)
+    }
+  };
+
+  password_hash
+    .map(|password_hash| password_hash.to_string())
+    .map_err(|e| format!("Error generating the password hash: {e}"))
+}
+
+/// Generates a hash for HTTP Digest authentication (a hexadecimal SHA-256 hash of "username:realm:password")
+fn generate_digest_hash(username: &str, realm: &str, password: &str) -> String {
+  Sha256::digest(format!("{username}:{realm}:{password}").as_bytes())
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect::<String>()
+}
+
+/// Parses a CSV record. Quoted fields (with doubled quotes as escapes) are supported.
+fn parse_csv_record(line: &str) -> Vec<String> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' if in_quotes => {
+        if chars.peek() == Some(&'"') {
+          chars.next();
+          field.push('"');
+        } else {
+          in_quotes = false;
+        }
+      }
+      '"' if field.is_empty() => in_quotes = true,
+      ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+      _ => field.push(c),
+    }
+  }
+  fields.push(field);
+  fields
+}
+
+/// Reads the "username:password" pairs from the standard input
+fn read_stdin_credentials() -> Result<Vec<(String, String)>, String> {
+  let mut credentials = Vec::new();
+  for (index, line) in io::stdin().lock().lines().enumerate() {
+    let line = line.map_err(|e| format!("Error reading the standard input: {e}"))?;
+    if line.is_empty() {
+      continue;
+    }
+    match line.split_once(':') {
+      Some((username, password)) if !username.is_empty() => {
+        credentials.push((username.to_string(), password.to_string()));
+      }
+      _ => Err(format!(
+        "Invalid \"username:password\" pair at line {} of the standard input",
+        index + 1
+      ))?,
+    }
+  }
+  Ok(credentials)
+}
+
+/// Reads the "username,password" records from a CSV file. The "username,password" header row is skipped.
+fn read_csv_credentials(path: &Path) -> Result<Vec<(String, String)>, String> {
+  let contents = fs::read_to_string(path).map_err(|e| format!("Error reading the CSV file: {e}"))?;
+  let mut credentials = Vec::new();
+  for (index, line) in contents.lines().enumerate() {
+    if line.is_empty() {
+      continue;
+    }
+    match parse_csv_record(line).as_slice() {
+      [username, password] if index == 0 && username == "username" && password == "password" => (),
+      [username, password] if !username.is_empty() => {
+        credentials.push((username.to_string(), password.to_string()));
+      }
+      _ => Err(format!("Invalid CSV record at line {} of the CSV file", index + 1))?,
+    }
+  }
+  Ok(credentials)
+}
+
+/// Formats the generated hashes for a user in the selected output format
+fn format_user(format: OutputFormat, username: Option<&str>, password_hash: &str, digest_hash: Option<&str>) -> String {
+  match (format, username) {
+    (OutputFormat::Htpasswd, Some(username)) => format!("{username}:{password_hash}"),
+    (OutputFormat::Kdl, Some(username)) => {
+      let mut kdl_user = KdlNode::new("user");
+      kdl_user.push(KdlValue::String(username.to_string()));
+      kdl_user.push(KdlValue::String(password_hash.to_string()));
+      if let Some(digest_hash) = digest_hash {
+        kdl_user.push(KdlEntry::new_prop("digest", KdlValue::String(digest_hash.to_string())));
+      }
+      kdl_user.to_string()
+    }
+    (_, username) => {
+      let mut text = match username {
+        Some(username) => format!("The generated password hash for \"{username}\": {password_hash}"),
+        None => format!("The generated password hash: {password_hash}"),
+      };
+      if let Some(digest_hash) = digest_hash {
+        text.push_str(&format!(
+          "\nThe generated HTTP Digest authentication hash: {digest_hash}"
+        ));
+      }
+      text
+    }
+  }
+}
 
 fn main() {
-  Args::parse();
+  let args = Args::parse();
+  let batch = args.batch || args.csv.is_some();
+  let format = args.format.unwrap_or(if batch {
+    OutputFormat::Htpasswd
+  } else {
+    OutputFormat::Text
+  });
 
-  let password = match prompt_password("Password: ") {
-    Ok(pass) => pass,
-    Err(e) => {
-      eprintln!("Error reading password: {e}");
+  if !batch && args.username.is_some() != args.realm.is_some() {
+    eprintln!("The username and the realm must be specified together");
+    process::exit(1);
+  } else if !batch && format != OutputFormat::Text && args.username.is_none() {
+    eprintln!("The username must be specified for the \"htpasswd\" and \"kdl\" output formats");
+    process::exit(1);
+  } else if format == OutputFormat::Htpasswd && args.realm.is_some() {
+    eprintln!("HTTP Digest authentication hashes can't be written in the \"htpasswd\" output format");
+    process::exit(1);
+  }
+
+  let credentials = if let Some(csv_path) = &args.csv {
+    read_csv_credentials(csv_path)
+  } else if args.batch {
+    read_stdin_credentials()
+  } else {
+    let password = match prompt_password("Password: ") {
+      Ok(pass) => pass,
+      Err(e) => {
+        eprintln!("Error reading password: {e}");
+        process::exit(1);
+      }
+    };
+    let password2 = match prompt_password("Confirm password: ") {
+      Ok(pass) => pass,
+      Err(e) => {
+        eprintln!("Error reading password confirmation: {e}");
+        process::exit(1);
+      }
+    };
+
+    if password != password2 {
+      eprintln!("Passwords don't match!");
       process::exit(1);
     }
+
+    Ok(vec![(args.username.clone().unwrap_or_default(), password)])
   };
-  let password2 = match prompt_password("Confirm password: ") {
-    Ok(pass) => pass,
+  let credentials = match credentials {
+    Ok(credentials) => credentials,
     Err(e) => {
-      eprintln!("Error reading password confirmation: {e}");
+      eprintln!("{e}");
       process::exit(1);
     }
   };
 
-  if password != password2 {
-    eprintln!("Passwords don't match!");
-    process::exit(1);
-  }
+  for (username, password) in credentials {
+    let password_hash = match generate_hash(&password, &args) {
+      Ok(password_hash) => password_hash,
+      Err(e) => {
+        eprintln!("{e}");
+        process::exit(1);
+      }
+    };
+    let digest_hash = args
+      .realm
+      .as_ref()
+      .map(|realm| generate_digest_hash(&username, realm, &password));
+    let username = (!username.is_empty()).then_some(username.as_str());
 
-  let password_hash = generate_hash(password);
+    println!(
+      "{}",
+      format_user(format, username, &password_hash, digest_hash.as_deref())
+    );
+  }
 
-  println!("The generated password hash: {password_hash}");
-  println!(
-    "Refer to the Ferron configuration documentation for information on how to configure the users with passwords"
-  )
+  if format == OutputFormat::Text {
+    println!(
+      "Refer to the Ferron configuration documentation for information on how to configure the users with passwords"
+    )
+  }
 }
@@ -3,6 +3,7 @@ use std::str::FromStr;
 
 use clap::{crate_name, crate_version, Arg, ArgAction, ArgMatches, Command};
 use mimalloc::MiMalloc;
+use sha2::{Digest, Sha256};
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -28,11 +29,19 @@ fn parse_arguments() -> ArgMatches {
         .action(ArgAction::Append)
         .value_parser(usize::from_str),
     )
+    .arg(
+      Arg::new("checksums")
+        .long("checksums")
+        .short('c')
+        .help("The checksum algorithm used to generate checksum sidecar files for ETags")
+        .action(ArgAction::Set)
+        .value_parser(["sha256", "xxhash"]),
+    )
     .get_matches()
 }
 
-/// Obtains the paths of the assets
-fn get_paths(assets_pathbuf: &PathBuf) -> Result<Vec<PathBuf>, std::io::Error> {
+/// Obtains the paths of the assets, along with information on whether the assets are compressible
+fn get_paths(assets_pathbuf: &PathBuf) -> Result<Vec<(PathBuf, bool)>, std::io::Error> {
   if assets_pathbuf.is_dir() {
     let metadata = std::fs::read_dir(assets_pathbuf)?;
     let mut paths = Vec::new();
@@ -40,9 +49,9 @@ fn get_paths(assets_pathbuf: &PathBuf) -> Result<Vec<PathBuf>, std::io::Error> {
       let path = entry?.path();
       let extension = path.extension().and_then(|ext| ext.to_str());
 
-      // Compressed files
+      // Compressed files and checksum sidecar files
       match extension {
-        Some("gz") | Some("deflate") | Some("br") | Some("zst") => continue,
+        Some("gz") | Some("deflate") | Some("br") | Some("zst") | Some("sha256") | Some("xxh3") => continue,
         _ => {}
       };
 
@@ -172,19 +181,17 @@ fn get_paths(assets_pathbuf: &PathBuf) -> Result<Vec<PathBuf>, std::io::Error> {
         "zip",
         "zst",
       ];
-      if extension.is_some_and(|ext| non_compressible_file_extensions.contains(&ext)) {
-        continue;
-      }
+      let compressible = !extension.is_some_and(|ext| non_compressible_file_extensions.contains(&ext));
 
       if path.is_file() {
-        paths.push(path);
+        paths.push((path, compressible));
       } else if path.is_dir() {
         paths.extend(get_paths(&path)?);
       }
     }
     Ok(paths)
   } else if assets_pathbuf.is_file() {
-    Ok(vec![assets_pathbuf.clone()])
+    Ok(vec![(assets_pathbuf.clone(), true)])
   } else {
     Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path"))
   }
@@ -262,6 +269,30 @@ fn compress_asset(path: &PathBuf) -> Result<(), std::io::Error> {
   Ok(())
 }
 
+/// Writes a checksum sidecar file for an asset, which is used by Ferron to generate ETags
+fn write_checksum_sidecar(path: &PathBuf, algorithm: &str) -> Result<(), std::io::Error> {
+  let data = std::fs::read(path)?;
+  let (checksum, extension) = match algorithm {
+    "sha256" => (
+      Sha256::digest(&data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>(),
+      "sha256",
+    ),
+    _ => (format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&data)), "xxh3"),
+  };
+  let sidecar_path = path.with_extension(
+    path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .map_or(extension.to_string(), |ext| format!("{}.{}", ext, extension)),
+  );
+  let file_name = path.file_name().map_or("".into(), |name| name.to_string_lossy());
+  std::fs::write(sidecar_path, format!("{checksum}  {file_name}\n"))?;
+  Ok(())
+}
+
 /// The main entry point of the application
 fn main() {
   // Parse command-line arguments
@@ -285,6 +316,9 @@ fn main() {
     }
   };
 
+  // Obtain the checksum algorithm
+  let checksum_algorithm = args.get_one::<String>("checksums").cloned();
+
   let mut paths = Vec::new();
 
   // Obtain the paths
@@ -309,12 +343,23 @@ fn main() {
 
   // Compress the assets
   thread_pool.scope(move |scope| {
-    for path in paths {
-      println!("Compressing asset at {}...", path.display());
+    for (path, compressible) in paths {
+      let checksum_algorithm = checksum_algorithm.clone();
+      if compressible {
+        println!("Compressing asset at {}...", path.display());
+      }
       scope.spawn(move |_| {
-        if let Err(err) = compress_asset(&path) {
-          eprintln!("Error compressing asset at {}: {}", path.display(), err);
-          std::process::exit(1);
+        if compressible {
+          if let Err(err) = compress_asset(&path) {
+            eprintln!("Error compressing asset at {}: {}", path.display(), err);
+            std::process::exit(1);
+          }
+        }
+        if let Some(checksum_algorithm) = checksum_algorithm {
+          if let Err(err) = write_checksum_sidecar(&path, &checksum_algorithm) {
+            eprintln!("Error writing the checksum for asset at {}: {}", path.display(), err);
+            std::process::exit(1);
+          }
         }
       });
     }
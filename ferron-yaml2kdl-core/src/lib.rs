@@ -6,23 +6,67 @@ use load_config::load_config;
 use yaml_rust2::Yaml;
 
 mod load_config;
+mod mappings;
 
-/// Converts Ferron 1.x YAML configuration to Ferron 2.x KDL configuration
-pub fn convert_yaml_to_kdl(input_path: PathBuf) -> Result<KdlDocument, Box<dyn Error + Send + Sync>> {
+pub use mappings::*;
+
+/// The YAML properties used to identify hosts, locations and error configurations, rather than to configure them
+const STRUCTURAL_PROPERTIES: [&str; 4] = ["domain", "ip", "path", "scode"];
+
+/// The report of the conversion of Ferron 1.x YAML configuration to Ferron 2.x KDL configuration
+#[derive(Debug, Default)]
+pub struct ConversionReport {
+  /// The YAML properties that weren't converted to KDL directives, along with their scopes
+  /// (like `hosts["example.com"].locations["/api"].someProperty`)
+  pub unmapped_properties: Vec<String>,
+}
+
+impl ConversionReport {
+  /// Checks whether all the YAML properties were converted to KDL directives
+  pub fn is_complete(&self) -> bool {
+    self.unmapped_properties.is_empty()
+  }
+}
+
+/// Converts Ferron 1.x YAML configuration to Ferron 2.x KDL configuration,
+/// and reports the YAML properties that couldn't be converted.
+/// The properties not known to the converter are converted using the user-supplied `mappings`.
+pub fn convert_yaml_to_kdl(
+  input_path: PathBuf,
+  mappings: &ConversionMappings,
+) -> Result<(KdlDocument, ConversionReport), Box<dyn Error + Send + Sync>> {
   let yaml_configuration = load_config(input_path)?;
   let mut kdl_configuration = KdlDocument::new();
+  let mut report = ConversionReport::default();
 
   let kdl_configuration_nodes = kdl_configuration.nodes_mut();
+  let mut global_only_unmapped_properties = Vec::new();
   let (global_configuration, sni_configurations, load_server_modules, secure_port) =
-    obtain_global_configuration(&yaml_configuration);
+    obtain_global_configuration(&yaml_configuration, mappings, &mut global_only_unmapped_properties);
   kdl_configuration_nodes.push(global_configuration);
   for sni_configuration in sni_configurations {
     kdl_configuration_nodes.push(sni_configuration);
   }
 
   let mut custom_headers = HashMap::new();
-  let (global_configuration, secure_global_configuration) =
-    obtain_host_configuration(&yaml_configuration["global"], &load_server_modules, &mut custom_headers);
+  let mut global_unmapped_properties = Vec::new();
+  let (global_configuration, secure_global_configuration) = obtain_host_configuration(
+    &yaml_configuration["global"],
+    &load_server_modules,
+    &mut custom_headers,
+    mappings,
+    "global",
+    &mut global_unmapped_properties,
+  );
+  // Global properties are converted either to global-only directives or to directives in the "*" block,
+  // while global locations and error configurations are converted only to directives in the "*" block
+  report
+    .unmapped_properties
+    .extend(global_unmapped_properties.into_iter().filter(|property| {
+      global_only_unmapped_properties.contains(property)
+        || property.starts_with("global.locations[")
+        || property.starts_with("global.errorConfig[")
+    }));
   if !global_configuration.is_empty() {
     let mut kdl_global_configuration = KdlNode::new("*");
     kdl_global_configuration.set_children(global_configuration);
@@ -58,8 +102,14 @@ pub fn convert_yaml_to_kdl(input_path: PathBuf) -> Result<KdlDocument, Box<dyn E
         host["ip"].as_str()
       };
       if let Some(hostname) = hostname {
-        let (host_configuration, secure_host_configuration) =
-          obtain_host_configuration(host, &load_server_modules, &mut custom_headers.clone());
+        let (host_configuration, secure_host_configuration) = obtain_host_configuration(
+          host,
+          &load_server_modules,
+          &mut custom_headers.clone(),
+          mappings,
+          &format!("hosts[\"{}\"]", host["domain"].as_str().unwrap_or(hostname)),
+          &mut report.unmapped_properties,
+        );
         if !host_configuration.is_empty() {
           let mut kdl_host_configuration = KdlNode::new(hostname);
           kdl_host_configuration.set_children(host_configuration);
@@ -84,13 +134,89 @@ pub fn convert_yaml_to_kdl(input_path: PathBuf) -> Result<KdlDocument, Box<dyn E
 
   kdl_configuration.autoformat();
 
-  Ok(kdl_configuration)
+  Ok((kdl_configuration, report))
+}
+
+/// Validates the converted KDL configuration by parsing its textual form again,
+/// and checking the host, location and error configuration blocks the same way as the KDL configuration adapter.
+/// The directives themselves are validated by the server, when it loads the configuration.
+pub fn validate_kdl_configuration(kdl_configuration: &KdlDocument) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let kdl_configuration: KdlDocument = kdl_configuration
+    .to_string()
+    .parse()
+    .map_err(|err| anyhow::anyhow!("The converted configuration can't be parsed: {err}"))?;
+  for kdl_node in kdl_configuration.nodes() {
+    let host_specifier = kdl_node.name().value();
+    if host_specifier.is_empty() {
+      Err(anyhow::anyhow!("Empty host specifier in the converted configuration"))?
+    }
+    if let Some(children) = kdl_node.children() {
+      validate_kdl_block(children, host_specifier, false, false)?;
+    }
+  }
+  Ok(())
 }
 
+/// Validates the locations and error configurations in the KDL configuration block
+fn validate_kdl_block(
+  kdl_block: &KdlDocument,
+  host_specifier: &str,
+  is_location: bool,
+  is_error_config: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  for kdl_node in kdl_block.nodes() {
+    match kdl_node.name().value() {
+      "location" => {
+        if is_location {
+          Err(anyhow::anyhow!(
+            "Nested locations aren't allowed (in \"{host_specifier}\")"
+          ))?
+        } else if is_error_config {
+          Err(anyhow::anyhow!(
+            "Locations in error configurations aren't allowed (in \"{host_specifier}\")"
+          ))?
+        }
+        let location_path = kdl_node
+          .get(0)
+          .and_then(|v| v.as_string())
+          .ok_or_else(|| anyhow::anyhow!("Invalid location path (in \"{host_specifier}\")"))?;
+        let children = kdl_node.children().ok_or_else(|| {
+          anyhow::anyhow!(
+            "The \"{location_path}\" location should have children, but it doesn't (in \"{host_specifier}\")"
+          )
+        })?;
+        validate_kdl_block(children, host_specifier, true, is_error_config)?;
+      }
+      "error_config" => {
+        if is_error_config {
+          Err(anyhow::anyhow!(
+            "Nested error configurations aren't allowed (in \"{host_specifier}\")"
+          ))?
+        }
+        if kdl_node.get(0).is_some_and(|v| v.as_integer().is_none()) {
+          Err(anyhow::anyhow!(
+            "Invalid error handler status code (in \"{host_specifier}\")"
+          ))?
+        }
+        if let Some(children) = kdl_node.children() {
+          validate_kdl_block(children, host_specifier, is_location, true)?;
+        }
+      }
+      _ => (),
+    }
+  }
+  Ok(())
+}
+
+/// Converts the host, location or error configuration. The YAML properties that can't be converted
+/// are appended to `unmapped_properties`, prefixed with the `scope`.
 pub fn obtain_host_configuration(
   yaml_subconfiguration: &Yaml,
   loaded_modules: &[String],
   custom_headers: &mut HashMap<String, String>,
+  mappings: &ConversionMappings,
+  scope: &str,
+  unmapped_properties: &mut Vec<String>,
 ) -> (KdlDocument, Option<KdlDocument>) {
   let empty_hashmap = yaml_rust2::yaml::Hash::new();
   let yaml_properties = yaml_subconfiguration.as_hash().unwrap_or(&empty_hashmap);
@@ -119,8 +245,14 @@ pub fn obtain_host_configuration(
           if let Some(locations) = value.as_vec() {
             for location in locations.iter().rev() {
               if let Some(location_path) = location["path"].as_str() {
-                let (location_config, secure_location_config_option) =
-                  obtain_host_configuration(location, loaded_modules, &mut custom_headers.clone());
+                let (location_config, secure_location_config_option) = obtain_host_configuration(
+                  location,
+                  loaded_modules,
+                  &mut custom_headers.clone(),
+                  mappings,
+                  &format!("{scope}.locations[\"{location_path}\"]"),
+                  unmapped_properties,
+                );
                 let mut kdl_location = KdlNode::new("location");
                 kdl_location.push(KdlValue::String(location_path.to_string()));
                 kdl_location.set_children(location_config);
@@ -138,8 +270,18 @@ pub fn obtain_host_configuration(
         "errorConfig" => {
           if let Some(error_configs) = value.as_vec() {
             for error_config in error_configs.iter().rev() {
-              let (error_config_d, secure_error_config_d_option) =
-                obtain_host_configuration(error_config, loaded_modules, &mut custom_headers.clone());
+              let error_config_scope = match error_config["scode"].as_i64() {
+                Some(status_code) => format!("{scope}.errorConfig[{status_code}]"),
+                None => format!("{scope}.errorConfig[*]"),
+              };
+              let (error_config_d, secure_error_config_d_option) = obtain_host_configuration(
+                error_config,
+                loaded_modules,
+                &mut custom_headers.clone(),
+                mappings,
+                &error_config_scope,
+                unmapped_properties,
+              );
               let mut kdl_error_config = KdlNode::new("error_config");
               if let Some(status_code) = error_config["scode"].as_i64() {
                 kdl_error_config.push(KdlValue::Integer(status_code as i128));
@@ -574,7 +716,11 @@ pub fn obtain_host_configuration(
             }
           }
         }
-        _ => (),
+        _ if STRUCTURAL_PROPERTIES.contains(&property) => (),
+        _ => match mappings.convert_property(property, value, loaded_modules, false) {
+          Some(kdl_properties) => kdl_config_nodes.extend(kdl_properties),
+          None => unmapped_properties.push(format!("{scope}.{property}")),
+        },
       }
     }
   }
@@ -707,7 +853,13 @@ pub fn obtain_host_configuration(
   )
 }
 
-pub fn obtain_global_configuration(yaml_configuration: &Yaml) -> (KdlNode, Vec<KdlNode>, Vec<String>, u16) {
+/// Converts the global-only configuration. The global YAML properties that can't be converted
+/// to global-only directives are appended to `unmapped_properties`.
+pub fn obtain_global_configuration(
+  yaml_configuration: &Yaml,
+  mappings: &ConversionMappings,
+  unmapped_properties: &mut Vec<String>,
+) -> (KdlNode, Vec<KdlNode>, Vec<String>, u16) {
   let empty_hashmap = yaml_rust2::yaml::Hash::new();
   let yaml_global_properties = yaml_configuration["global"].as_hash().unwrap_or(&empty_hashmap);
   let mut kdl_global_properties = KdlNode::new("*");
@@ -750,7 +902,12 @@ pub fn obtain_global_configuration(yaml_configuration: &Yaml) -> (KdlNode, Vec<K
             let kdl_property = KdlNode::new("forward_proxy");
             kdl_global_children_nodes.push(kdl_property);
           }
-          _ => (),
+          _ => {
+            if let Some(directive) = mappings.modules.get(module) {
+              let kdl_property = KdlNode::new(directive.as_str());
+              kdl_global_children_nodes.push(kdl_property);
+            }
+          }
         }
         load_server_modules.push(module.to_string());
       }
@@ -1044,7 +1201,12 @@ pub fn obtain_global_configuration(yaml_configuration: &Yaml) -> (KdlNode, Vec<K
             kdl_global_children_nodes.push(kdl_property);
           }
         }
-        _ => (),
+        // The modules are loaded before converting the other properties
+        "loadModules" => (),
+        _ => match mappings.convert_property(property, value, &load_server_modules, true) {
+          Some(kdl_properties) => kdl_global_children_nodes.extend(kdl_properties),
+          None => unmapped_properties.push(format!("global.{property}")),
+        },
       }
     }
   }
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use kdl::{KdlDocument, KdlNode, KdlValue};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// The mapping of a Ferron 1.x YAML property to a Ferron 2.x KDL directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyMapping {
+  /// The name of the KDL directive
+  pub directive: String,
+  /// The module, which has to be loaded for the property to be converted
+  pub module: Option<String>,
+  /// Whether the property is a global-only property
+  pub global: bool,
+}
+
+/// User-supplied mappings of Ferron 1.x modules and their YAML properties to Ferron 2.x KDL directives,
+/// used to convert the configuration of modules not known to the converter (like third-party modules)
+#[derive(Debug, Clone, Default)]
+pub struct ConversionMappings {
+  /// The KDL directives enabling the modules, keyed by the module names used in the `loadModules` property
+  pub modules: HashMap<String, String>,
+  /// The mappings of YAML properties, keyed by the property names
+  pub properties: HashMap<String, PropertyMapping>,
+}
+
+impl ConversionMappings {
+  /// Loads the conversion mappings from a KDL file (with the `.kdl` extension) or a YAML file
+  pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let file_contents = match fs::read_to_string(path) {
+      Ok(file_contents) => file_contents,
+      Err(err) => Err(anyhow::anyhow!(
+        "Failed to read from the mapping file at \"{}\": {}",
+        path.to_string_lossy(),
+        err
+      ))?,
+    };
+
+    if path
+      .extension()
+      .is_some_and(|extension| extension.eq_ignore_ascii_case("kdl"))
+    {
+      Self::parse_kdl(&file_contents)
+    } else {
+      Self::parse_yaml(&file_contents)
+    }
+  }
+
+  /// Parses the conversion mappings in the KDL format, for example:
+  ///
+  /// ```kdl
+  /// module "example2" "example2_handler"
+  /// property "example2Message" "example2_message" module="example2"
+  /// property "example2CacheSize" "example2_cache_size" module="example2" global=#true
+  /// ```
+  pub fn parse_kdl(mappings: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let kdl_mappings: KdlDocument = match mappings.parse() {
+      Ok(kdl_mappings) => kdl_mappings,
+      Err(err) => Err(anyhow::anyhow!("Failed to parse the mapping file: {}", err))?,
+    };
+
+    let mut conversion_mappings = Self::default();
+    for kdl_node in kdl_mappings.nodes() {
+      let name = kdl_node.get(0).and_then(|v| v.as_string());
+      let directive = kdl_node.get(1).and_then(|v| v.as_string());
+      match (kdl_node.name().value(), name, directive) {
+        ("module", Some(module), Some(directive)) => {
+          conversion_mappings
+            .modules
+            .insert(module.to_string(), directive.to_string());
+        }
+        ("property", Some(property), Some(directive)) => {
+          conversion_mappings.properties.insert(
+            property.to_string(),
+            PropertyMapping {
+              directive: directive.to_string(),
+              module: kdl_node.get("module").and_then(|v| v.as_string()).map(String::from),
+              global: kdl_node.get("global").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+          );
+        }
+        (node_name @ ("module" | "property"), _, _) => Err(anyhow::anyhow!(
+          "The \"{}\" mapping should have a name and a directive name",
+          node_name
+        ))?,
+        (node_name, _, _) => Err(anyhow::anyhow!("Invalid mapping type: \"{}\"", node_name))?,
+      }
+    }
+
+    Ok(conversion_mappings)
+  }
+
+  /// Parses the conversion mappings in the YAML format, for example:
+  ///
+  /// ```yaml
+  /// modules:
+  ///   example2: example2_handler
+  /// properties:
+  ///   example2Message:
+  ///     directive: example2_message
+  ///     module: example2
+  ///   example2CacheSize:
+  ///     directive: example2_cache_size
+  ///     module: example2
+  ///     global: true
+  /// ```
+  pub fn parse_yaml(mappings: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    let yaml_mappings = match YamlLoader::load_from_str(mappings) {
+      Ok(yaml_mappings) => yaml_mappings.into_iter().next().unwrap_or(Yaml::Null),
+      Err(err) => Err(anyhow::anyhow!("Failed to parse the mapping file: {}", err))?,
+    };
+
+    let mut conversion_mappings = Self::default();
+    if let Some(modules) = yaml_mappings["modules"].as_hash() {
+      for (module, directive) in modules {
+        match (module.as_str(), directive.as_str()) {
+          (Some(module), Some(directive)) => {
+            conversion_mappings
+              .modules
+              .insert(module.to_string(), directive.to_string());
+          }
+          _ => Err(anyhow::anyhow!("Invalid module mapping in the mapping file"))?,
+        }
+      }
+    }
+    if let Some(properties) = yaml_mappings["properties"].as_hash() {
+      for (property, mapping) in properties {
+        let Some(property) = property.as_str() else {
+          return Err(anyhow::anyhow!("Invalid property name in the mapping file").into());
+        };
+        // The mapping can be either a directive name, or a hash with the directive name and additional options
+        let property_mapping = if let Some(directive) = mapping.as_str() {
+          PropertyMapping {
+            directive: directive.to_string(),
+            module: None,
+            global: false,
+          }
+        } else if let Some(directive) = mapping["directive"].as_str() {
+          PropertyMapping {
+            directive: directive.to_string(),
+            module: mapping["module"].as_str().map(String::from),
+            global: mapping["global"].as_bool().unwrap_or(false),
+          }
+        } else {
+          Err(anyhow::anyhow!(
+            "The \"{}\" property mapping is missing a directive name",
+            property
+          ))?
+        };
+        conversion_mappings
+          .properties
+          .insert(property.to_string(), property_mapping);
+      }
+    }
+
+    Ok(conversion_mappings)
+  }
+
+  /// Converts the YAML property using the property mappings. Arrays are converted to multiple directives,
+  /// and hashes are converted to multiple directives with the key and the value.
+  /// Returns `None`, if there is no applicable mapping for the property.
+  pub fn convert_property(
+    &self,
+    property: &str,
+    value: &Yaml,
+    loaded_modules: &[String],
+    global: bool,
+  ) -> Option<Vec<KdlNode>> {
+    let mapping = self.properties.get(property)?;
+    if mapping.global != global
+      || mapping
+        .module
+        .as_ref()
+        .is_some_and(|module| !loaded_modules.contains(module))
+    {
+      return None;
+    }
+
+    let kdl_properties = match value {
+      Yaml::Array(values) => values
+        .iter()
+        .filter_map(|value| {
+          let mut kdl_property = KdlNode::new(mapping.directive.as_str());
+          kdl_property.push(yaml_to_kdl_value(value)?);
+          Some(kdl_property)
+        })
+        .collect(),
+      Yaml::Hash(values) => values
+        .iter()
+        .filter_map(|(key, value)| {
+          let mut kdl_property = KdlNode::new(mapping.directive.as_str());
+          kdl_property.push(yaml_to_kdl_value(key)?);
+          kdl_property.push(yaml_to_kdl_value(value)?);
+          Some(kdl_property)
+        })
+        .collect(),
+      _ => {
+        let mut kdl_property = KdlNode::new(mapping.directive.as_str());
+        kdl_property.push(yaml_to_kdl_value(value)?);
+        vec![kdl_property]
+      }
+    };
+    Some(kdl_properties)
+  }
+}
+
+/// Converts a scalar YAML value to a KDL value
+fn yaml_to_kdl_value(value: &Yaml) -> Option<KdlValue> {
+  match value {
+    Yaml::String(value) => Some(KdlValue::String(value.to_string())),
+    Yaml::Integer(value) => Some(KdlValue::Integer(*value as i128)),
+    Yaml::Real(value) => value.parse().ok().map(KdlValue::Float),
+    Yaml::Boolean(value) => Some(KdlValue::Bool(*value)),
+    Yaml::Null => Some(KdlValue::Null),
+    _ => None,
+  }
+}
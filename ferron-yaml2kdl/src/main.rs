@@ -2,7 +2,7 @@ use std::str::FromStr;
 use std::{fs, path::PathBuf};
 
 use clap::{crate_name, crate_version, Arg, ArgAction, ArgMatches, Command};
-use ferron_yaml2kdl_core::convert_yaml_to_kdl;
+use ferron_yaml2kdl_core::{convert_yaml_to_kdl, validate_kdl_configuration, ConversionMappings};
 use mimalloc::MiMalloc;
 
 #[global_allocator]
@@ -27,6 +27,21 @@ fn parse_arguments() -> ArgMatches {
         .action(ArgAction::Set)
         .value_parser(PathBuf::from_str),
     )
+    .arg(
+      Arg::new("mappings")
+        .long("mappings")
+        .short('m')
+        .help("The name of a KDL or YAML file, describing how the properties of additional modules are converted")
+        .action(ArgAction::Set)
+        .value_parser(PathBuf::from_str),
+    )
+    .arg(
+      Arg::new("strict")
+        .long("strict")
+        .short('s')
+        .help("Fail without writing the output file, if some of the YAML properties couldn't be converted")
+        .action(ArgAction::SetTrue),
+    )
     .get_matches()
 }
 
@@ -51,8 +66,22 @@ fn main() {
     }
   };
 
+  let strict = args.get_flag("strict");
+
+  // Load the mappings for additional modules
+  let mappings = match args.get_one::<PathBuf>("mappings") {
+    Some(mappings_pathbuf) => match ConversionMappings::load(mappings_pathbuf) {
+      Ok(mappings) => mappings,
+      Err(err) => {
+        eprintln!("Error loading the mappings: {err}");
+        std::process::exit(1);
+      }
+    },
+    None => ConversionMappings::default(),
+  };
+
   // Convert the server configuration
-  let kdl_config = match convert_yaml_to_kdl(input_pathbuf) {
+  let (kdl_config, report) = match convert_yaml_to_kdl(input_pathbuf, &mappings) {
     Ok(config) => config,
     Err(err) => {
       eprintln!("Error converting the server configuration: {err}");
@@ -60,6 +89,26 @@ fn main() {
     }
   };
 
+  // Report the properties that couldn't be converted
+  if !report.is_complete() {
+    eprintln!(
+      "{} YAML properties couldn't be converted, and require manual migration:",
+      report.unmapped_properties.len()
+    );
+    for unmapped_property in &report.unmapped_properties {
+      eprintln!("  {unmapped_property}");
+    }
+    if strict {
+      std::process::exit(1);
+    }
+  }
+
+  // Validate the converted server configuration
+  if let Err(err) = validate_kdl_configuration(&kdl_config) {
+    eprintln!("Error validating the converted server configuration: {err}");
+    std::process::exit(1);
+  }
+
   // Write the converted server configuration
   if let Err(err) = fs::write(output_pathbuf, kdl_config.to_string()) {
     eprintln!("Error writing the server configuration: {err}");
@@ -4,10 +4,10 @@ use std::{
   future::Future,
   net::IpAddr,
   ops::{Deref, Sub},
-  path::PathBuf,
+  path::{Path, PathBuf},
   pin::Pin,
   sync::Arc,
-  time::{Duration, SystemTime},
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use base64::Engine;
@@ -30,7 +30,7 @@ use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
 use serde::{Deserialize, Serialize};
 use tokio::{io::AsyncWriteExt, sync::RwLock, time::Instant};
 use x509_parser::prelude::{FromDer, X509Certificate};
-use xxhash_rust::xxh3::xxh3_128;
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
 
 use crate::util::SniResolverLock;
 use ferron_common::dns::DnsProvider;
@@ -38,6 +38,8 @@ use ferron_common::logging::ErrorLogger;
 
 pub const ACME_TLS_ALPN_NAME: &[u8] = b"acme-tls/1";
 const SECONDS_BEFORE_RENEWAL: u64 = 86400; // 1 day before expiration
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(21600); // 6 hours
 
 pub type TlsAlpn01DataLock = Arc<RwLock<Option<(Arc<CertifiedKey>, String)>>>;
 pub type Http01DataLock = Arc<RwLock<Option<(String, String)>>>;
@@ -56,6 +58,10 @@ pub struct AcmeConfig {
   pub directory: String,
   /// The optional EAB key
   pub eab_key: Option<Arc<ExternalAccountKey>>,
+  /// The name of the ACME issuer.
+  pub issuer_name: String,
+  /// The ACME issuers used if the preferred ACME issuer fails to issue the certificate, in the fallback order.
+  pub fallback_issuers: Vec<AcmeIssuer>,
   /// The optional ACME profile name
   pub profile: Option<String>,
   /// The cache for storing ACME account information.
@@ -72,6 +78,10 @@ pub struct AcmeConfig {
   pub dns_provider: Option<Arc<dyn DnsProvider + Send + Sync>>,
   /// The certificate renewal information.
   pub renewal_info: Option<(RenewalInfo, Instant)>,
+  /// Whether the ACME server doesn't support the renewal information endpoint.
+  pub renewal_info_unsupported: bool,
+  /// The schedule of certificate provisioning attempts, loaded from the ACME cache when it's first needed.
+  pub renewal_schedule: Option<RenewalSchedule>,
   /// The ACME account information
   pub account: Option<Account>,
   /// The paths to TLS certificate and private key files to save the obtained certificate and private key.
@@ -81,6 +91,21 @@ pub struct AcmeConfig {
   pub post_obtain_command: Option<String>,
 }
 
+/// Represents an ACME issuer (an ACME server, along with the ACME account used with it).
+#[derive(Clone)]
+pub struct AcmeIssuer {
+  /// The name of the ACME issuer.
+  pub name: String,
+  /// The Rustls client configuration to use for ACME communication.
+  pub rustls_client_config: ClientConfig,
+  /// The directory URL for the ACME server.
+  pub directory: String,
+  /// The optional EAB key
+  pub eab_key: Option<Arc<ExternalAccountKey>>,
+  /// The ACME account information
+  pub account: Option<Account>,
+}
+
 /// Represents the type of cache to use for storing ACME data.
 pub enum AcmeCache {
   /// Use an in-memory cache.
@@ -141,6 +166,32 @@ struct CertificateCacheData {
   private_key_pem: String,
 }
 
+/// The schedule of certificate provisioning attempts, persisted in the ACME cache across restarts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenewalSchedule {
+  /// The UNIX timestamp (in seconds) of the next certificate provisioning attempt.
+  next_attempt: u64,
+  /// The number of consecutive failed certificate provisioning attempts.
+  failed_attempts: u32,
+}
+
+impl RenewalSchedule {
+  /// Creates a schedule with the next certificate provisioning attempt at the specified time.
+  fn new(next_attempt: SystemTime, failed_attempts: u32) -> Self {
+    Self {
+      next_attempt: next_attempt
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()),
+      failed_attempts,
+    }
+  }
+
+  /// Obtains the time of the next certificate provisioning attempt.
+  pub fn next_attempt(&self) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(self.next_attempt)
+  }
+}
+
 /// Represents the on-demand configuration for the ACME client.
 pub struct AcmeOnDemandConfig {
   /// The Rustls client configuration to use for ACME communication.
@@ -153,6 +204,10 @@ pub struct AcmeOnDemandConfig {
   pub directory: String,
   /// The optional EAB key
   pub eab_key: Option<Arc<ExternalAccountKey>>,
+  /// The name of the ACME issuer.
+  pub issuer_name: String,
+  /// The ACME issuers used if the preferred ACME issuer fails to issue the certificate, in the fallback order.
+  pub fallback_issuers: Vec<AcmeIssuer>,
   /// The optional ACME profile name
   pub profile: Option<String>,
   /// The path to the cache directory for storing ACME information.
@@ -171,27 +226,110 @@ pub struct AcmeOnDemandConfig {
   pub port: u16,
 }
 
-/// Checks if the TLS certificate is valid
-fn check_certificate_validity(
+/// Determines when the TLS certificate should be renewed. The renewal time is spread across the renewal window
+/// (the one suggested by the ACME server, or the one derived from the certificate validity period) based on the certificate,
+/// so that the certificates obtained at the same time aren't renewed at the same time,
+/// while the renewal time of a certificate stays the same across the checks.
+fn certificate_renewal_time(
   certificate: &CertificateDer,
   renewal_info: Option<&RenewalInfo>,
-) -> Result<bool, Box<dyn Error + Send + Sync>> {
+) -> Result<SystemTime, Box<dyn Error + Send + Sync>> {
+  let jitter = xxh3_64(certificate) as f64 / u64::MAX as f64;
   if let Some(renewal_info) = renewal_info {
-    return Ok(SystemTime::now() < renewal_info.suggested_window.start);
+    let window_start = SystemTime::from(renewal_info.suggested_window.start);
+    let window_end = SystemTime::from(renewal_info.suggested_window.end);
+    let window_duration = window_end.duration_since(window_start).unwrap_or(Duration::ZERO);
+    return Ok(window_start + window_duration.mul_f64(jitter));
   }
   let (_, x509_certificate) = X509Certificate::from_der(certificate)?;
   let validity = x509_certificate.validity();
-  if let Some(time_to_expiration) = validity.time_to_expiration() {
-    let time_before_expiration = if let Some(valid_duration) = validity.not_after.sub(validity.not_before) {
-      (valid_duration.whole_seconds().unsigned_abs() / 2).min(SECONDS_BEFORE_RENEWAL)
-    } else {
-      SECONDS_BEFORE_RENEWAL
-    };
-    if time_to_expiration >= Duration::from_secs(time_before_expiration) {
-      return Ok(true);
+  let not_after = UNIX_EPOCH + Duration::from_secs(validity.not_after.timestamp().max(0) as u64);
+  let time_before_expiration = if let Some(valid_duration) = validity.not_after.sub(validity.not_before) {
+    (valid_duration.whole_seconds().unsigned_abs() / 2).min(SECONDS_BEFORE_RENEWAL)
+  } else {
+    SECONDS_BEFORE_RENEWAL
+  };
+  // The certificate is renewed between 1 and 1.5 times the time before expiration before it expires
+  Ok(
+    not_after
+      .checked_sub(Duration::from_secs(time_before_expiration).mul_f64(1.0 + jitter / 2.0))
+      .unwrap_or(UNIX_EPOCH),
+  )
+}
+
+/// Checks if the TLS certificate is valid
+fn check_certificate_validity(
+  certificate: &CertificateDer,
+  renewal_info: Option<&RenewalInfo>,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+  Ok(SystemTime::now() < certificate_renewal_time(certificate, renewal_info)?)
+}
+
+/// Determines the delay before retrying to provision a certificate after consecutive failed attempts.
+/// The delay grows exponentially, so that the rate limits of the ACME server are respected.
+fn retry_delay(failed_attempts: u32) -> Duration {
+  MIN_RETRY_DELAY
+    .saturating_mul(2u32.saturating_pow(failed_attempts.saturating_sub(1)))
+    .min(MAX_RETRY_DELAY)
+}
+
+/// Updates the certificate renewal information (ACME Renewal Information), if it's outdated.
+async fn update_renewal_info(config: &mut AcmeConfig, acme_account: &Account, certificate: &CertificateDer<'_>) {
+  if config.renewal_info_unsupported
+    || config
+      .renewal_info
+      .as_ref()
+      .is_some_and(|v| v.1.elapsed() == Duration::ZERO)
+  {
+    return;
+  }
+  let Ok(certificate_id) = CertificateIdentifier::try_from(certificate) else {
+    return;
+  };
+  match acme_account.renewal_info(&certificate_id).await {
+    Ok(renewal_info) => {
+      let mut renewal_instant = Instant::now();
+      renewal_instant += renewal_info.1;
+      config.renewal_info = Some((renewal_info.0, renewal_instant));
     }
+    Err(instant_acme::Error::Unsupported(_)) => {
+      // Internal ACME servers often don't implement the renewal information endpoint,
+      // so the certificate validity period is used to determine the renewal time instead
+      config.renewal_info_unsupported = true;
+    }
+    Err(_) => (),
   }
-  Ok(false)
+}
+
+/// Describes the error returned when accessing the ACME directory, with hints for the common misconfigurations.
+fn describe_acme_directory_error(directory: &str, err: instant_acme::Error) -> anyhow::Error {
+  if let instant_acme::Error::Api(problem) = &err {
+    if problem.r#type.as_deref() == Some("urn:ietf:params:acme:error:externalAccountRequired") {
+      return anyhow::anyhow!(
+        "The ACME server at \"{directory}\" requires External Account Binding, \
+        specify the EAB key using the `auto_tls_eab` directive"
+      );
+    }
+  }
+
+  let mut source: Option<&(dyn Error + 'static)> = Some(&err);
+  while let Some(current_source) = source {
+    if current_source
+      .downcast_ref::<std::io::Error>()
+      .and_then(|io_err| io_err.get_ref())
+      .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+      .is_some_and(|tls_err| matches!(tls_err, rustls::Error::InvalidCertificate(_)))
+    {
+      return anyhow::anyhow!(
+        "Failed to verify the TLS certificate of the ACME directory \"{directory}\": {err}. \
+        If the ACME server uses a TLS certificate issued by an internal CA, \
+        specify the CA certificate using the `auto_tls_directory_ca` directive"
+      );
+    }
+    source = current_source.source();
+  }
+
+  anyhow::anyhow!("Failed to access the ACME directory \"{directory}\": {err}")
 }
 
 /// Determines the account cache key
@@ -224,6 +362,11 @@ fn get_certificate_cache_key(config: &AcmeConfig) -> String {
   )
 }
 
+/// Determines the renewal schedule cache key
+fn get_renewal_schedule_cache_key(config: &AcmeConfig) -> String {
+  get_certificate_cache_key(config).replacen("certificate_", "schedule_", 1)
+}
+
 /// Determines the account cache key
 fn get_hostname_cache_key(config: &AcmeOnDemandConfig) -> String {
   format!(
@@ -281,22 +424,11 @@ pub async fn check_certificate_validity_or_install_cached(
   config: &mut AcmeConfig,
   acme_account: Option<&Account>,
 ) -> Result<bool, Box<dyn Error + Send + Sync>> {
-  if let Some(certified_key) = config.certified_key_lock.read().await.as_deref() {
+  let certified_key = config.certified_key_lock.read().await.clone();
+  if let Some(certified_key) = certified_key {
     if let Some(certificate) = certified_key.cert.first() {
       if let Some(acme_account) = acme_account {
-        if config
-          .renewal_info
-          .as_ref()
-          .is_none_or(|v| v.1.elapsed() > Duration::ZERO)
-        {
-          if let Ok(certificate_id) = CertificateIdentifier::try_from(certificate) {
-            if let Ok(renewal_info) = acme_account.renewal_info(&certificate_id).await {
-              let mut renewal_instant = Instant::now();
-              renewal_instant += renewal_info.1;
-              config.renewal_info = Some((renewal_info.0, renewal_instant));
-            }
-          }
-        }
+        update_renewal_info(config, acme_account, certificate).await;
       }
       if check_certificate_validity(certificate, config.renewal_info.as_ref().map(|i| &i.0))? {
         return Ok(true);
@@ -314,19 +446,7 @@ pub async fn check_certificate_validity_or_install_cached(
       {
         if let Some(certificate) = certs.first() {
           if let Some(acme_account) = acme_account {
-            if config
-              .renewal_info
-              .as_ref()
-              .is_none_or(|v| v.1.elapsed() > Duration::ZERO)
-            {
-              if let Ok(certificate_id) = CertificateIdentifier::try_from(certificate) {
-                if let Ok(renewal_info) = acme_account.renewal_info(&certificate_id).await {
-                  let mut renewal_instant = Instant::now();
-                  renewal_instant += renewal_info.1;
-                  config.renewal_info = Some((renewal_info.0, renewal_instant));
-                }
-              }
-            }
+            update_renewal_info(config, acme_account, certificate).await;
           }
           if check_certificate_validity(certificate, config.renewal_info.as_ref().map(|i| &i.0))? {
             // Corrupted private key would be skipped
@@ -356,10 +476,109 @@ pub async fn check_certificate_validity_or_install_cached(
   Ok(false)
 }
 
-/// Provisions TLS certificates using the ACME protocol.
+/// Loads the schedule of certificate provisioning attempts from the ACME cache, if it's not loaded yet.
+pub async fn load_renewal_schedule(config: &mut AcmeConfig) -> RenewalSchedule {
+  if let Some(renewal_schedule) = config.renewal_schedule {
+    return renewal_schedule;
+  }
+  let has_certificate = config.certified_key_lock.read().await.is_some();
+  let renewal_schedule = config
+    .certificate_cache
+    .get(&get_renewal_schedule_cache_key(config))
+    .await
+    .and_then(|data| serde_json::from_slice::<RenewalSchedule>(&data).ok())
+    // The schedule after a successful attempt is used only if the certificate is installed,
+    // because the certificate might have been removed from the cache
+    .filter(|renewal_schedule| renewal_schedule.failed_attempts > 0 || has_certificate)
+    .unwrap_or(RenewalSchedule::new(UNIX_EPOCH, 0));
+  config.renewal_schedule = Some(renewal_schedule);
+  renewal_schedule
+}
+
+/// Schedules the next certificate provisioning attempt after an attempt, and saves the schedule into the ACME cache.
+/// After a successful attempt, the next attempt is at the certificate renewal time, or when the renewal information
+/// should be checked again. After a failed attempt, the next attempt is delayed exponentially.
+pub async fn schedule_next_attempt(config: &mut AcmeConfig, succeeded: bool) -> RenewalSchedule {
+  let now = SystemTime::now();
+  let renewal_schedule = if succeeded {
+    let certified_key = config.certified_key_lock.read().await.clone();
+    let renewal_time = certified_key
+      .as_ref()
+      .and_then(|certified_key| certified_key.cert.first())
+      .and_then(|certificate| certificate_renewal_time(certificate, config.renewal_info.as_ref().map(|i| &i.0)).ok())
+      .unwrap_or(now);
+    let renewal_info_check_time = config.renewal_info.as_ref().map_or(renewal_time, |(_, instant)| {
+      now + instant.saturating_duration_since(Instant::now())
+    });
+    RenewalSchedule::new(renewal_time.min(renewal_info_check_time).max(now + MIN_RETRY_DELAY), 0)
+  } else {
+    let failed_attempts = config
+      .renewal_schedule
+      .map_or(0, |renewal_schedule| renewal_schedule.failed_attempts)
+      .saturating_add(1);
+    let delay = retry_delay(failed_attempts).mul_f64(rand::random_range(1.0..1.25));
+    RenewalSchedule::new(now + delay, failed_attempts)
+  };
+  config.renewal_schedule = Some(renewal_schedule);
+  if let Ok(data) = serde_json::to_vec(&renewal_schedule) {
+    let _ = config
+      .certificate_cache
+      .set(&get_renewal_schedule_cache_key(config), data)
+      .await;
+  }
+  renewal_schedule
+}
+
+/// Swaps the ACME issuer used by the ACME configuration with one of the fallback ACME issuers.
+fn swap_issuer(config: &mut AcmeConfig, fallback_issuer_index: usize) {
+  let fallback_issuer = &mut config.fallback_issuers[fallback_issuer_index];
+  std::mem::swap(&mut config.issuer_name, &mut fallback_issuer.name);
+  std::mem::swap(
+    &mut config.rustls_client_config,
+    &mut fallback_issuer.rustls_client_config,
+  );
+  std::mem::swap(&mut config.directory, &mut fallback_issuer.directory);
+  std::mem::swap(&mut config.eab_key, &mut fallback_issuer.eab_key);
+  std::mem::swap(&mut config.account, &mut fallback_issuer.account);
+}
+
+/// Provisions TLS certificates using the ACME protocol. If the preferred ACME issuer fails to issue the certificate,
+/// the fallback ACME issuers are tried in order. The preferred ACME issuer is tried first again on the next renewal.
 pub async fn provision_certificate(
   config: &mut AcmeConfig,
   error_logger: &ErrorLogger,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut error = match provision_certificate_from_issuer(config, error_logger).await {
+    Ok(()) => return Ok(()),
+    Err(err) => err,
+  };
+  for fallback_issuer_index in 0..config.fallback_issuers.len() {
+    error_logger
+      .log(&format!(
+        "Error while obtaining a TLS certificate from the \"{}\" ACME issuer: {error}. Trying the \"{}\" ACME issuer",
+        if fallback_issuer_index == 0 {
+          &config.issuer_name
+        } else {
+          &config.fallback_issuers[fallback_issuer_index - 1].name
+        },
+        config.fallback_issuers[fallback_issuer_index].name
+      ))
+      .await;
+    swap_issuer(config, fallback_issuer_index);
+    let result = provision_certificate_from_issuer(config, error_logger).await;
+    swap_issuer(config, fallback_issuer_index);
+    match result {
+      Ok(()) => return Ok(()),
+      Err(err) => error = err,
+    }
+  }
+  Err(error)
+}
+
+/// Provisions TLS certificates from the ACME issuer used by the ACME configuration.
+async fn provision_certificate_from_issuer(
+  config: &mut AcmeConfig,
+  error_logger: &ErrorLogger,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
   let account_cache_key = get_account_cache_key(config);
   let certificate_cache_key = get_certificate_cache_key(config);
@@ -377,7 +596,10 @@ pub async fn provision_certificate(
       .await
       .and_then(|c| serde_json::from_slice::<AccountCredentials>(&c).ok())
     {
-      acme_account_builder.from_credentials(account_credentials).await?
+      acme_account_builder
+        .from_credentials(account_credentials)
+        .await
+        .map_err(|err| describe_acme_directory_error(&config.directory, err))?
     } else {
       let (account, account_credentials) = acme_account_builder
         .create(
@@ -389,7 +611,8 @@ pub async fn provision_certificate(
           config.directory.clone(),
           config.eab_key.as_deref(),
         )
-        .await?;
+        .await
+        .map_err(|err| describe_acme_directory_error(&config.directory, err))?;
 
       if let Err(err) = config
         .account_cache
@@ -574,6 +797,9 @@ pub async fn provision_certificate(
 
     config.account.replace(acme_account);
 
+    // The renewal information of the previous certificate doesn't apply to the new certificate
+    config.renewal_info = None;
+
     *config.certified_key_lock.write().await = Some(Arc::new(CertifiedKey::new(certs, signing_key)));
 
     Ok::<_, Box<dyn Error + Send + Sync>>(())
@@ -607,6 +833,49 @@ pub async fn provision_certificate(
   Ok(())
 }
 
+/// Removes the expired certificates from the ACME cache directory and its subdirectories (used for per-host caches),
+/// and returns the number of removed certificates.
+pub async fn remove_expired_cached_certificates(cache_path: &Path) -> Result<usize, Box<dyn Error + Send + Sync>> {
+  let mut removed_certificates = 0;
+  let mut directories = vec![(cache_path.to_path_buf(), true)];
+  while let Some((directory, scan_subdirectories)) = directories.pop() {
+    let mut entries = match tokio::fs::read_dir(&directory).await {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(err) => Err(err)?,
+    };
+    while let Some(entry) = entries.next_entry().await? {
+      let file_type = entry.file_type().await?;
+      if file_type.is_dir() {
+        if scan_subdirectories {
+          directories.push((entry.path(), false));
+        }
+        continue;
+      } else if !file_type.is_file() || !entry.file_name().to_string_lossy().starts_with("certificate_") {
+        continue;
+      }
+      let Ok(certificate_data) = serde_json::from_slice::<CertificateCacheData>(&tokio::fs::read(entry.path()).await?)
+      else {
+        continue;
+      };
+      let is_expired = CertificateDer::pem_slice_iter(certificate_data.certificate_chain_pem.as_bytes())
+        .next()
+        .and_then(|certificate| certificate.ok())
+        .and_then(|certificate| {
+          X509Certificate::from_der(&certificate)
+            .ok()
+            .map(|(_, certificate)| certificate.validity().time_to_expiration().is_none())
+        })
+        .unwrap_or(false);
+      if is_expired {
+        tokio::fs::remove_file(entry.path()).await?;
+        removed_certificates += 1;
+      }
+    }
+  }
+  Ok(removed_certificates)
+}
+
 /// Obtains the list of domains for which `AcmeOnDemandConfig` was converted into `AcmeConfig` from cache.
 pub async fn get_cached_domains(config: &AcmeOnDemandConfig) -> Vec<String> {
   if let Some(pathbuf) = config.cache_path.clone() {
@@ -689,6 +958,8 @@ pub async fn convert_on_demand_config(
     contact: config.contact.clone(),
     directory: config.directory.clone(),
     eab_key: config.eab_key.clone(),
+    issuer_name: config.issuer_name.clone(),
+    fallback_issuers: config.fallback_issuers.clone(),
     profile: config.profile.clone(),
     account_cache: if let Some(account_cache_path) = account_cache_path {
       AcmeCache::File(account_cache_path)
@@ -705,6 +976,8 @@ pub async fn convert_on_demand_config(
     http_01_data_lock: http_01_data_lock.clone(),
     dns_provider: config.dns_provider.clone(),
     renewal_info: None,
+    renewal_info_unsupported: false,
+    renewal_schedule: None,
     account: None,
     save_paths: None,
     post_obtain_command: None,
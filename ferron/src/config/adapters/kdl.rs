@@ -7,13 +7,16 @@ use std::{
   str::FromStr,
 };
 
+use fancy_regex::RegexBuilder;
 use ferron_common::observability::ObservabilityBackendChannels;
+use ferron_common::util::parse_ip_literal;
 use glob::glob;
 use kdl::{KdlDocument, KdlNode, KdlValue};
 
 use crate::config::{
-  parse_conditional_data, Conditional, ConditionalData, Conditions, ErrorHandlerStatus, ServerConfiguration,
-  ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationFilters, ServerConfigurationValue,
+  parse_conditional_data, Conditional, ConditionalData, Conditions, ErrorHandlerStatus, LocationModifier,
+  ServerConfiguration, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationFilters,
+  ServerConfigurationValue,
 };
 
 use super::ConfigurationAdapter;
@@ -109,35 +112,52 @@ fn load_configuration_inner(
         Err(anyhow::anyhow!("Invalid or missing snippet name"))?
       }
     } else if let Some(children) = children {
-      for global_name in global_name.split(",") {
+      // Regular expression hostnames aren't comma-separated, since commas can be a part of regular expressions
+      let global_names = if global_name.starts_with('~') {
+        vec![global_name]
+      } else {
+        global_name.split(",").collect()
+      };
+      for global_name in global_names {
         let host_filter = if global_name == "globals" {
           (None, None, None, false)
+        } else if let Some(host_regex) = global_name.strip_prefix('~') {
+          if let Err(err) = RegexBuilder::new(host_regex).build() {
+            let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+            Err(anyhow::anyhow!(
+              "Invalid host regular expression at \"{}\": {}",
+              canonical_path,
+              err
+            ))?
+          }
+          (Some(global_name.to_string()), None, None, true)
         } else if let Ok(socket_addr) = global_name.parse::<SocketAddr>() {
           (None, Some(socket_addr.ip()), Some(socket_addr.port()), true)
+        } else if let Some((ip_address, _)) = parse_ip_literal(global_name) {
+          (None, Some(ip_address), None, true)
         } else if let Some((address, port_str)) = global_name.rsplit_once(':') {
           if let Ok(port) = port_str.parse::<u16>() {
-            if let Ok(ip_address) = address
-              .strip_prefix('[')
-              .and_then(|s| s.strip_suffix(']'))
-              .unwrap_or(address)
-              .parse::<IpAddr>()
-            {
+            if let Some((ip_address, _)) = parse_ip_literal(address) {
               (None, Some(ip_address), Some(port), true)
             } else if address == "*" || address.is_empty() {
               (None, None, Some(port), true)
+            } else if address.starts_with('[') {
+              let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+              Err(anyhow::anyhow!("Invalid host specifier at \"{}\"", canonical_path))?
             } else {
               (Some(address.to_string()), None, Some(port), true)
             }
           } else if port_str == "*" {
-            if let Ok(ip_address) = address
-              .strip_prefix('[')
-              .and_then(|s| s.strip_suffix(']'))
-              .unwrap_or(address)
-              .parse::<IpAddr>()
-            {
+            if let Some((ip_address, _)) = parse_ip_literal(address) {
               (None, Some(ip_address), None, true)
             } else if address == "*" || address.is_empty() {
               (None, None, None, true)
+            } else if address.starts_with('[') {
+              let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+              Err(anyhow::anyhow!("Invalid host specifier at \"{}\"", canonical_path))?
             } else {
               (Some(address.to_string()), None, None, true)
             }
@@ -146,20 +166,19 @@ fn load_configuration_inner(
 
             Err(anyhow::anyhow!("Invalid host specifier at \"{}\"", canonical_path))?
           }
-        } else if let Ok(ip_address) = global_name
-          .strip_prefix('[')
-          .and_then(|s| s.strip_suffix(']'))
-          .unwrap_or(global_name)
-          .parse::<IpAddr>()
-        {
-          (None, Some(ip_address), None, true)
         } else if global_name == "*" || global_name.is_empty() {
           (None, None, None, true)
+        } else if global_name.starts_with('[') {
+          let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+          Err(anyhow::anyhow!("Invalid host specifier at \"{}\"", canonical_path))?
         } else {
           (Some(global_name.to_string()), None, None, true)
         };
 
         let mut configuration_entries: HashMap<String, ServerConfigurationEntries> = HashMap::new();
+        // Regular expressions of the regular expression locations in the host block, in the configuration order
+        let mut location_regexes = Vec::new();
         for kdl_node in children.nodes() {
           #[allow(clippy::too_many_arguments)]
           fn kdl_iterate_fn(
@@ -171,6 +190,7 @@ fn load_configuration_inner(
             conditions: &mut Option<&mut Conditions>,
             is_error_config: bool,
             loaded_conditions: &mut HashMap<String, Vec<ConditionalData>>,
+            location_regexes: &mut Vec<ConditionalData>,
             snippets: &HashMap<String, KdlDocument>,
           ) -> Result<(), Box<dyn Error + Send + Sync>> {
             let (hostname, ip, port, is_host) = host_filter;
@@ -189,6 +209,7 @@ fn load_configuration_inner(
                       conditions,
                       is_error_config,
                       loaded_conditions,
+                      location_regexes,
                       snippets,
                     )?;
                   }
@@ -210,12 +231,79 @@ fn load_configuration_inner(
               }
               let mut configuration_entries: HashMap<String, ServerConfigurationEntries> = HashMap::new();
               if let Some(children) = children {
-                if let Some(location) = kdl_node.entry(0) {
+                // The location modifier is specified before the location path
+                let location_modifier = kdl_node
+                  .entry(1)
+                  .and_then(|_| kdl_node.entry(0))
+                  .and_then(|e| e.value().as_string());
+                if let Some(location) = kdl_node.entry(if location_modifier.is_some() { 1 } else { 0 }) {
                   if let Some(location_str) = location.value().as_string() {
                     let mut conditions = Conditions {
                       location_prefix: location_str.to_string(),
+                      location_modifier: LocationModifier::Prefix,
                       conditionals: vec![],
                     };
+                    match location_modifier {
+                      None => (),
+                      Some("^~") => {
+                        // Prefix locations with the `^~` modifier take priority over regular expression locations
+                        conditions.location_modifier = LocationModifier::PriorityPrefix;
+                      }
+                      Some("=") => {
+                        // Exact locations are more specific than prefix locations with the same path
+                        conditions.location_modifier = LocationModifier::Exact;
+                        conditions
+                          .conditionals
+                          .push(Conditional::If(vec![ConditionalData::IsEqual(
+                            "{path}".to_string(),
+                            location_str.to_string(),
+                          )]));
+                      }
+                      Some(location_modifier @ ("~" | "~*")) => {
+                        let location_regex = match RegexBuilder::new(location_str)
+                          .case_insensitive(location_modifier == "~*")
+                          .build()
+                        {
+                          Ok(regex) => ConditionalData::IsRegex("{path}".to_string(), regex),
+                          Err(err) => {
+                            let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+                            Err(anyhow::anyhow!(
+                              "Invalid location regular expression at \"{}\": {}",
+                              canonical_path,
+                              err
+                            ))?
+                          }
+                        };
+                        if kdl_node.entry("remove_base").is_some() {
+                          Err(anyhow::anyhow!(
+                            "The `remove_base` prop isn't supported for regular expression locations"
+                          ))?;
+                        }
+                        // Regular expression locations don't match if any previous regular expression location
+                        // in the host block matches, so that the first matching one is chosen
+                        conditions.location_prefix = "/".to_string();
+                        conditions.location_modifier = LocationModifier::Regex;
+                        conditions
+                          .conditionals
+                          .push(Conditional::If(vec![location_regex.clone()]));
+                        for previous_location_regex in location_regexes.iter() {
+                          conditions
+                            .conditionals
+                            .push(Conditional::IfNot(vec![previous_location_regex.clone()]));
+                        }
+                        location_regexes.push(location_regex);
+                      }
+                      Some(location_modifier) => {
+                        let canonical_path = canonical_pathbuf.to_string_lossy().into_owned();
+
+                        Err(anyhow::anyhow!(
+                          "Invalid location modifier \"{}\" at \"{}\"",
+                          location_modifier,
+                          canonical_path
+                        ))?
+                      }
+                    }
                     let mut loaded_conditions = loaded_conditions.clone();
                     for kdl_node in children.nodes() {
                       kdl_iterate_fn(
@@ -227,6 +315,7 @@ fn load_configuration_inner(
                         &mut Some(&mut conditions),
                         is_error_config,
                         &mut loaded_conditions,
+                        location_regexes,
                         snippets,
                       )?;
                     }
@@ -354,6 +443,7 @@ fn load_configuration_inner(
                     } else {
                       Conditions {
                         location_prefix: "/".to_string(),
+                        location_modifier: LocationModifier::Prefix,
                         conditionals: vec![],
                       }
                     };
@@ -377,6 +467,7 @@ fn load_configuration_inner(
                         &mut Some(&mut new_conditions),
                         is_error_config,
                         &mut loaded_conditions,
+                        location_regexes,
                         snippets,
                       )?;
                     }
@@ -425,6 +516,7 @@ fn load_configuration_inner(
                     } else {
                       Conditions {
                         location_prefix: "/".to_string(),
+                        location_modifier: LocationModifier::Prefix,
                         conditionals: vec![],
                       }
                     };
@@ -450,6 +542,7 @@ fn load_configuration_inner(
                         &mut Some(&mut new_conditions),
                         is_error_config,
                         &mut loaded_conditions,
+                        location_regexes,
                         snippets,
                       )?;
                     }
@@ -504,6 +597,7 @@ fn load_configuration_inner(
                         conditions,
                         true,
                         &mut loaded_conditions,
+                        location_regexes,
                         snippets,
                       )?;
                     }
@@ -585,6 +679,7 @@ fn load_configuration_inner(
             &mut None,
             false,
             &mut loaded_conditions,
+            &mut location_regexes,
             snippets,
           )?;
         }
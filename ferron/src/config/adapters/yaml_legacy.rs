@@ -1,17 +1,13 @@
-use std::{
-  collections::HashMap,
-  error::Error,
-  net::{IpAddr, SocketAddr},
-  path::Path,
-};
+use std::{collections::HashMap, error::Error, net::SocketAddr, path::Path};
 
 use ferron_common::observability::ObservabilityBackendChannels;
-use ferron_yaml2kdl_core::convert_yaml_to_kdl;
+use ferron_common::util::parse_ip_literal;
+use ferron_yaml2kdl_core::{convert_yaml_to_kdl, ConversionMappings};
 use kdl::{KdlDocument, KdlNode, KdlValue};
 
 use crate::config::{
-  Conditions, ErrorHandlerStatus, ServerConfiguration, ServerConfigurationEntries, ServerConfigurationEntry,
-  ServerConfigurationFilters, ServerConfigurationValue,
+  Conditions, ErrorHandlerStatus, LocationModifier, ServerConfiguration, ServerConfigurationEntries,
+  ServerConfigurationEntry, ServerConfigurationFilters, ServerConfigurationValue,
 };
 
 use super::ConfigurationAdapter;
@@ -53,8 +49,8 @@ impl YamlLegacyConfigurationAdapter {
 impl ConfigurationAdapter for YamlLegacyConfigurationAdapter {
   fn load_configuration(&self, path: &Path) -> Result<Vec<ServerConfiguration>, Box<dyn Error + Send + Sync>> {
     // Read and parse the configuration file contents
-    let kdl_document: KdlDocument = match convert_yaml_to_kdl(path.to_path_buf()) {
-      Ok(document) => document,
+    let kdl_document: KdlDocument = match convert_yaml_to_kdl(path.to_path_buf(), &ConversionMappings::default()) {
+      Ok((document, _)) => document,
       Err(err) => Err(anyhow::anyhow!(
         "Failed to read and parse the server configuration file: {}",
         err
@@ -74,45 +70,36 @@ impl ConfigurationAdapter for YamlLegacyConfigurationAdapter {
             (None, None, None, false)
           } else if let Ok(socket_addr) = global_name.parse::<SocketAddr>() {
             (None, Some(socket_addr.ip()), Some(socket_addr.port()), true)
+          } else if let Some((ip_address, _)) = parse_ip_literal(global_name) {
+            (None, Some(ip_address), None, true)
           } else if let Some((address, port_str)) = global_name.rsplit_once(':') {
             if let Ok(port) = port_str.parse::<u16>() {
-              if let Ok(ip_address) = address
-                .strip_prefix('[')
-                .and_then(|s| s.strip_suffix(']'))
-                .unwrap_or(address)
-                .parse::<IpAddr>()
-              {
+              if let Some((ip_address, _)) = parse_ip_literal(address) {
                 (None, Some(ip_address), Some(port), true)
               } else if address == "*" || address.is_empty() {
                 (None, None, Some(port), true)
+              } else if address.starts_with('[') {
+                Err(anyhow::anyhow!("Invalid host specifier"))?
               } else {
                 (Some(address.to_string()), None, Some(port), true)
               }
             } else if port_str == "*" {
-              if let Ok(ip_address) = address
-                .strip_prefix('[')
-                .and_then(|s| s.strip_suffix(']'))
-                .unwrap_or(address)
-                .parse::<IpAddr>()
-              {
+              if let Some((ip_address, _)) = parse_ip_literal(address) {
                 (None, Some(ip_address), None, true)
               } else if address == "*" || address.is_empty() {
                 (None, None, None, true)
+              } else if address.starts_with('[') {
+                Err(anyhow::anyhow!("Invalid host specifier"))?
               } else {
                 (Some(address.to_string()), None, None, true)
               }
             } else {
               Err(anyhow::anyhow!("Invalid host specifier"))?
             }
-          } else if let Ok(ip_address) = global_name
-            .strip_prefix('[')
-            .and_then(|s| s.strip_suffix(']'))
-            .unwrap_or(global_name)
-            .parse::<IpAddr>()
-          {
-            (None, Some(ip_address), None, true)
           } else if global_name == "*" || global_name.is_empty() {
             (None, None, None, true)
+          } else if global_name.starts_with('[') {
+            Err(anyhow::anyhow!("Invalid host specifier"))?
           } else {
             (Some(global_name.to_string()), None, None, true)
           };
@@ -155,6 +142,7 @@ impl ConfigurationAdapter for YamlLegacyConfigurationAdapter {
                                   port,
                                   condition: Some(Conditions {
                                     location_prefix: location_str.to_string(),
+                                    location_modifier: LocationModifier::Prefix,
                                     conditionals: vec![],
                                   }),
                                   error_handler_status: Some(ErrorHandlerStatus::Status(error_status_code as u16)),
@@ -187,6 +175,7 @@ impl ConfigurationAdapter for YamlLegacyConfigurationAdapter {
                                 port,
                                 condition: Some(Conditions {
                                   location_prefix: location_str.to_string(),
+                                  location_modifier: LocationModifier::Prefix,
                                   conditionals: vec![],
                                 }),
                                 error_handler_status: Some(ErrorHandlerStatus::Any),
@@ -236,6 +225,7 @@ impl ConfigurationAdapter for YamlLegacyConfigurationAdapter {
                         port,
                         condition: Some(Conditions {
                           location_prefix: location_str.to_string(),
+                          location_modifier: LocationModifier::Prefix,
                           conditionals: vec![],
                         }),
                         error_handler_status: None,
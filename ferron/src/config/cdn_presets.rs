@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::config::{
+  ServerConfiguration, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationValue,
+};
+
+/// A CDN integration preset
+struct CdnPreset {
+  /// The request header with the original client IP address
+  client_ip_header: &'static str,
+  /// The URLs of the published IP address lists of the CDN
+  ip_list_urls: &'static [&'static str],
+  /// The response header with the caching policy for the CDN (overriding the `Cache-Control` header)
+  cache_control_header: &'static str,
+}
+
+/// Obtains the CDN integration preset
+fn get_cdn_preset(cdn: &str) -> Option<CdnPreset> {
+  match cdn {
+    "cloudflare" => Some(CdnPreset {
+      client_ip_header: "CF-Connecting-IP",
+      ip_list_urls: &["https://www.cloudflare.com/ips-v4", "https://www.cloudflare.com/ips-v6"],
+      cache_control_header: "CDN-Cache-Control",
+    }),
+    "fastly" => Some(CdnPreset {
+      client_ip_header: "Fastly-Client-IP",
+      ip_list_urls: &["https://api.fastly.com/public-ip-list"],
+      cache_control_header: "Surrogate-Control",
+    }),
+    _ => None,
+  }
+}
+
+/// Creates a configuration entry with the specified values and props
+fn create_entry(
+  values: Vec<ServerConfigurationValue>,
+  props: Vec<(&str, ServerConfigurationValue)>,
+) -> ServerConfigurationEntries {
+  ServerConfigurationEntries {
+    inner: vec![ServerConfigurationEntry {
+      values,
+      props: props
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect::<HashMap<_, _>>(),
+    }],
+  }
+}
+
+/// Expands the `cdn` directive into the directives for the CDN integration. The client IP address is obtained
+/// from the CDN-specific header only for requests from the published CDN IP address ranges. Directives specified
+/// explicitly in the configuration take precedence over the preset.
+pub fn expand_cdn_presets(mut server_configurations: Vec<ServerConfiguration>) -> Vec<ServerConfiguration> {
+  for server_configuration in &mut server_configurations {
+    let Some(cdn_entry) = server_configuration
+      .entries
+      .get("cdn")
+      .and_then(|entries| entries.inner.last())
+      .cloned()
+    else {
+      continue;
+    };
+    let Some(preset) = cdn_entry
+      .values
+      .first()
+      .and_then(|v| v.as_str())
+      .and_then(get_cdn_preset)
+    else {
+      continue;
+    };
+    let entries = &mut server_configuration.entries;
+
+    entries
+      .entry("trust_x_forwarded_for".to_string())
+      .or_insert_with(|| create_entry(vec![ServerConfigurationValue::Bool(true)], vec![]));
+    entries.entry("real_ip_header".to_string()).or_insert_with(|| {
+      create_entry(
+        vec![ServerConfigurationValue::String(preset.client_ip_header.to_string())],
+        vec![],
+      )
+    });
+    let trusted_proxies = entries.entry("real_ip_trusted_proxies".to_string()).or_default();
+    for url in preset.ip_list_urls {
+      trusted_proxies
+        .inner
+        .extend(create_entry(vec![], vec![("url", ServerConfigurationValue::String(url.to_string()))]).inner);
+    }
+    if let Some(cache_control) = cdn_entry.props.get("cache_control").and_then(|v| v.as_str()) {
+      // The `header` directive doesn't override the header, if it's already set by the backend server
+      let headers = entries.entry("header".to_string()).or_default();
+      headers.inner.extend(
+        create_entry(
+          vec![
+            ServerConfigurationValue::String(preset.cache_control_header.to_string()),
+            ServerConfigurationValue::String(cache_control.to_string()),
+          ],
+          vec![],
+        )
+        .inner,
+      );
+    }
+  }
+
+  server_configurations
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ferron_common::observability::ObservabilityBackendChannels;
+
+  use crate::config::ServerConfigurationFilters;
+
+  fn configuration_with_entries(entries: Vec<(&str, ServerConfigurationEntries)>) -> ServerConfiguration {
+    ServerConfiguration {
+      entries: entries
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect(),
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: Some("example.com".to_string()),
+        ip: None,
+        port: None,
+        condition: None,
+        error_handler_status: None,
+      },
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  #[test]
+  fn test_expand_cloudflare_preset() {
+    let configurations = expand_cdn_presets(vec![configuration_with_entries(vec![(
+      "cdn",
+      create_entry(
+        vec![ServerConfigurationValue::String("cloudflare".to_string())],
+        vec![(
+          "cache_control",
+          ServerConfigurationValue::String("max-age=3600".to_string()),
+        )],
+      ),
+    )])]);
+    let entries = &configurations[0].entries;
+
+    assert_eq!(
+      entries["trust_x_forwarded_for"].inner[0].values,
+      vec![ServerConfigurationValue::Bool(true)]
+    );
+    assert_eq!(
+      entries["real_ip_header"].inner[0].values,
+      vec![ServerConfigurationValue::String("CF-Connecting-IP".to_string())]
+    );
+    assert_eq!(entries["real_ip_trusted_proxies"].inner.len(), 2);
+    assert_eq!(
+      entries["header"].inner[0].values,
+      vec![
+        ServerConfigurationValue::String("CDN-Cache-Control".to_string()),
+        ServerConfigurationValue::String("max-age=3600".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_expand_preset_keeps_explicit_directives() {
+    let configurations = expand_cdn_presets(vec![configuration_with_entries(vec![
+      (
+        "cdn",
+        create_entry(vec![ServerConfigurationValue::String("fastly".to_string())], vec![]),
+      ),
+      (
+        "trust_x_forwarded_for",
+        create_entry(vec![ServerConfigurationValue::Bool(false)], vec![]),
+      ),
+    ])]);
+    let entries = &configurations[0].entries;
+
+    assert_eq!(
+      entries["trust_x_forwarded_for"].inner[0].values,
+      vec![ServerConfigurationValue::Bool(false)]
+    );
+    assert_eq!(entries["real_ip_trusted_proxies"].inner.len(), 1);
+    assert!(!entries.contains_key("header"));
+  }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use ferron_common::get_entry;
+use ferron_common::util::TtlCache;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::{header, Method, Request, StatusCode, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::config::{
+  ServerConfiguration, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationValue,
+};
+use crate::setup::acme::build_raw_rustls_client_config;
+
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum size of a response body from the dynamic host provider
+const MAX_RESPONSE_SIZE: usize = 65536;
+
+/// Appends the `domain` query parameter with the hostname to the dynamic host provider URL
+fn append_domain_query(endpoint: &Uri, hostname: &str) -> Result<Uri, Box<dyn Error + Send + Sync>> {
+  let mut url_parts = endpoint.clone().into_parts();
+  let domain_query = format!("domain={}", urlencoding::encode(hostname));
+  let path_and_query_str = match url_parts.path_and_query {
+    Some(path_and_query) => match path_and_query.query() {
+      Some(query) => format!("{}?{}&{}", path_and_query.path(), query, domain_query),
+      None => format!("{}?{}", path_and_query.path(), domain_query),
+    },
+    None => format!("/?{domain_query}"),
+  };
+  url_parts.path_and_query = Some(path_and_query_str.parse()?);
+  Ok(Uri::from_parts(url_parts)?)
+}
+
+/// A response from the dynamic host provider
+#[derive(Deserialize)]
+struct DynamicHostResponse {
+  root: Option<String>,
+  #[serde(default)]
+  variables: HashMap<String, String>,
+}
+
+/// A virtual host provisioned by the dynamic host provider
+pub struct DynamicHost {
+  /// The webroot of the virtual host
+  root: Option<String>,
+
+  /// The request variables for the virtual host, available via the `{var:NAME}` placeholder
+  pub variables: HashMap<String, String>,
+}
+
+impl DynamicHost {
+  /// Synthesizes the virtual host configuration from the configuration of the host block without a hostname
+  pub fn synthesize_configuration(
+    &self,
+    base_configuration: &ServerConfiguration,
+    hostname: &str,
+  ) -> ServerConfiguration {
+    let mut configuration = base_configuration.clone();
+    configuration.filters.hostname = Some(hostname.to_string());
+    if let Some(root) = &self.root {
+      configuration.entries.insert(
+        "root".to_string(),
+        ServerConfigurationEntries {
+          inner: vec![ServerConfigurationEntry {
+            values: vec![ServerConfigurationValue::String(root.clone())],
+            props: HashMap::new(),
+          }],
+        },
+      );
+    }
+    configuration
+  }
+}
+
+/// Obtains the virtual host from the dynamic host provider. This function is executed on the Tokio runtime.
+async fn fetch_dynamic_host(
+  client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+  url: Uri,
+) -> Result<Option<Arc<DynamicHost>>, Box<dyn Error + Send + Sync>> {
+  let request = Request::builder()
+    .method(Method::GET)
+    .uri(url)
+    .header(header::ACCEPT, "application/json")
+    .body(Empty::<Bytes>::new())?;
+  let response = client.request(request).await?;
+  match response.status() {
+    StatusCode::NOT_FOUND => return Ok(None),
+    status if !status.is_success() => Err(anyhow::anyhow!(
+      "The dynamic host provider responded with the {status} status code"
+    ))?,
+    _ => (),
+  }
+  let response_body = Limited::new(response.into_body(), MAX_RESPONSE_SIZE)
+    .collect()
+    .await?
+    .to_bytes();
+  let dynamic_host_response = serde_json::from_slice::<DynamicHostResponse>(&response_body)?;
+  Ok(Some(Arc::new(DynamicHost {
+    root: dynamic_host_response.root,
+    variables: dynamic_host_response.variables,
+  })))
+}
+
+/// A provider of virtual hosts resolved at request time against an HTTP endpoint
+pub struct DynamicHostProvider {
+  endpoint: Uri,
+  client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+  timeout: Duration,
+  cache: Option<RwLock<TtlCache<String, Option<Arc<DynamicHost>>>>>,
+  runtime: tokio::runtime::Handle,
+}
+
+impl Debug for DynamicHostProvider {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DynamicHostProvider")
+      .field("endpoint", &self.endpoint)
+      .field("timeout", &self.timeout)
+      .finish()
+  }
+}
+
+impl DynamicHostProvider {
+  /// Creates a dynamic host provider, if it's specified in the global configuration
+  pub fn from_global_configuration(
+    global_configuration: Option<&ServerConfiguration>,
+    secondary_runtime: &tokio::runtime::Runtime,
+  ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+    let Some(entry) = global_configuration.and_then(|c| get_entry!("dynamic_hosts", c)) else {
+      return Ok(None);
+    };
+    let Some(endpoint) = entry.values.first().and_then(|v| v.as_str()) else {
+      return Ok(None);
+    };
+    let endpoint = endpoint
+      .parse::<Uri>()
+      .map_err(|err| anyhow::anyhow!("Failed to parse the dynamic host provider URL: {err}"))?;
+    let no_verification = entry
+      .props
+      .get("no_verification")
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+    let client = Client::builder(TokioExecutor::new()).build(
+      hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(build_raw_rustls_client_config(
+          no_verification,
+          Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+        )?)
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .build(),
+    );
+    let cache_ttl = entry.props.get("ttl").map_or(Some(DEFAULT_CACHE_TTL), |v| {
+      if v.is_null() {
+        None
+      } else {
+        Some(v.as_duration().unwrap_or(DEFAULT_CACHE_TTL))
+      }
+    });
+
+    Ok(Some(Self {
+      endpoint,
+      client,
+      timeout: entry
+        .props
+        .get("timeout")
+        .and_then(|v| v.as_duration())
+        .unwrap_or(DEFAULT_TIMEOUT),
+      cache: cache_ttl
+        .filter(|ttl| !ttl.is_zero())
+        .map(|ttl| RwLock::new(TtlCache::new(ttl))),
+      runtime: secondary_runtime.handle().clone(),
+    }))
+  }
+
+  /// Resolves the hostname into a virtual host. Returns `None` if the dynamic host provider doesn't know the hostname.
+  pub async fn resolve(&self, hostname: &str) -> Result<Option<Arc<DynamicHost>>, Box<dyn Error + Send + Sync>> {
+    let hostname = hostname.to_string();
+    if let Some(cache) = &self.cache {
+      if let Some(cached_dynamic_host) = cache.read().await.get(&hostname) {
+        return Ok(cached_dynamic_host);
+      }
+    }
+
+    let url = append_domain_query(&self.endpoint, &hostname)?;
+    let client = self.client.clone();
+    let timeout = self.timeout;
+    // The HTTP client is driven by the Tokio runtime
+    let dynamic_host = self
+      .runtime
+      .spawn(async move {
+        tokio::time::timeout(timeout, fetch_dynamic_host(client, url))
+          .await
+          .map_err(|_| anyhow::anyhow!("The dynamic host provider request has timed out"))?
+      })
+      .await
+      .map_err(|_| anyhow::anyhow!("Can't spawn a task to query the dynamic host provider"))??;
+
+    // Unknown hostnames are cached too, so that requests with random "Host" headers don't flood the provider
+    if let Some(cache) = &self.cache {
+      let mut rwlock_write = cache.write().await;
+      rwlock_write.cleanup();
+      rwlock_write.insert(hostname, dynamic_host.clone());
+    }
+
+    Ok(dynamic_host)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_append_domain_query() {
+    let endpoint = "http://provisioner.internal/hosts".parse::<Uri>().unwrap();
+    assert_eq!(
+      append_domain_query(&endpoint, "example.com").unwrap().to_string(),
+      "http://provisioner.internal/hosts?domain=example.com"
+    );
+
+    let endpoint = "http://provisioner.internal/hosts?token=abc".parse::<Uri>().unwrap();
+    assert_eq!(
+      append_domain_query(&endpoint, "example.com").unwrap().to_string(),
+      "http://provisioner.internal/hosts?token=abc&domain=example.com"
+    );
+  }
+
+  #[test]
+  fn test_synthesize_configuration_overrides_root() {
+    let dynamic_host = DynamicHost {
+      root: Some("/srv/tenants/example".to_string()),
+      variables: HashMap::new(),
+    };
+    let base_configuration = ServerConfiguration {
+      entries: HashMap::new(),
+      filters: ferron_common::config::ServerConfigurationFilters {
+        is_host: true,
+        hostname: None,
+        ip: None,
+        port: None,
+        condition: None,
+        error_handler_status: None,
+      },
+      modules: vec![],
+      observability: ferron_common::observability::ObservabilityBackendChannels::new(),
+    };
+
+    let configuration = dynamic_host.synthesize_configuration(&base_configuration, "example.com");
+    assert_eq!(configuration.filters.hostname.as_deref(), Some("example.com"));
+    assert_eq!(
+      ferron_common::get_value!("root", configuration).and_then(|v| v.as_str()),
+      Some("/srv/tenants/example")
+    );
+  }
+}
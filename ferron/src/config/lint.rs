@@ -0,0 +1,333 @@
+use ferron_common::config::{Conditional, ConditionalData};
+use ferron_common::util::is_localhost;
+use ferron_common::{get_entries, get_value};
+
+use super::ServerConfiguration;
+
+/// Common file extensions of already compressed files (images, audio, video and archives)
+const COMPRESSED_FILE_EXTENSIONS: &[&str] = &[
+  "7z", "apng", "avif", "br", "bz2", "docx", "epub", "flv", "gif", "gz", "heic", "jar", "jpeg", "jpg", "jxl", "m4a",
+  "m4v", "mkv", "mov", "mp3", "mp4", "mpeg", "mpg", "oga", "ogg", "ogv", "opus", "png", "rar", "tgz", "webm", "webp",
+  "woff", "woff2", "xz", "zip", "zst",
+];
+
+/// URL rewriting regular expressions (without the leading `^`) matching all request URLs
+const CATCH_ALL_REWRITE_REGEXES: &[&str] = &[
+  "", "/", ".*", "(.*)", ".*$", "(.*)$", "/.*", "/(.*)", "(/.*)", "/.*$", "/(.*)$", "(/.*)$",
+];
+
+/// Checks the configurations for common pitfalls
+/// Returns warnings about directives, which are likely misconfigured, like conflicting directives, or directives
+/// that have no effect.
+///
+/// This function should be called before the configurations are pre-merged, so that directives inherited from parent
+/// configuration blocks aren't reported.
+pub fn lint_configuration(server_configurations: &[ServerConfiguration]) -> Vec<String> {
+  let global_configuration = server_configurations.iter().find(|c| c.filters.is_global());
+  // Global directives (like `auto_tls`) apply to configuration blocks, which don't specify them
+  let get_inherited_value = |name: &str, config: &ServerConfiguration| {
+    config
+      .entries
+      .get(name)
+      .or_else(|| global_configuration.and_then(|c| c.entries.get(name)))
+      .and_then(|e| e.get_value())
+      .cloned()
+  };
+
+  let mut warnings = Vec::new();
+  for server_configuration in server_configurations {
+    let filters = &server_configuration.filters;
+    let is_host_block = filters.condition.is_none() && filters.error_handler_status.is_none();
+
+    // The reverse proxy handles the requests before the static file serving
+    let proxies_requests = ["proxy", "proxy_srv"].iter().any(|name| {
+      server_configuration
+        .entries
+        .get(*name)
+        .is_some_and(|e| e.inner.iter().any(|e| e.values.first().is_some_and(|v| !v.is_null())))
+    });
+    if proxies_requests
+      && server_configuration.entries.contains_key("root")
+      && !server_configuration.entries.contains_key("try_backends")
+    {
+      warnings.push(format!(
+        "The `root` configuration property at {filters} has no effect on requests forwarded by the reverse proxy \
+          (the `proxy` directive). Move either directive into a `location` block, \
+          or use the `try_backends` directive to specify the order of the backends explicitly"
+      ));
+    }
+
+    if is_host_block && filters.is_host && filters.hostname.is_none() && filters.port.is_none() {
+      if let Some(ip) = filters.ip.filter(|ip| !is_localhost(Some(ip), None)) {
+        let auto_tls = get_inherited_value("auto_tls", server_configuration)
+          .and_then(|v| v.as_bool())
+          .unwrap_or(!server_configuration.entries.contains_key("tls"));
+        let on_demand = get_inherited_value("auto_tls_on_demand", server_configuration)
+          .and_then(|v| v.as_bool())
+          .unwrap_or(false);
+        let has_profile = get_inherited_value("auto_tls_profile", server_configuration).is_some_and(|v| !v.is_null());
+        if auto_tls && !on_demand && !has_profile {
+          warnings.push(format!(
+            "Automatic TLS is enabled at {filters}, which has no hostname. Most ACME certificate authorities \
+              don't issue certificates for IP addresses (like \"{ip}\"), unless a certificate profile supporting \
+              IP addresses is chosen with the `auto_tls_profile` directive"
+          ));
+        }
+      }
+    }
+
+    let compression_enabled = ["compressed", "dynamic_compressed"].iter().any(|name| {
+      server_configuration
+        .entries
+        .get(*name)
+        .and_then(|e| e.get_value())
+        .and_then(|v| v.as_bool())
+        == Some(true)
+    });
+    if compression_enabled {
+      if let Some(extensions) = filters.condition.as_ref().and_then(|condition| {
+        condition.conditionals.iter().find_map(|conditional| match conditional {
+          Conditional::If(conditional_data) => match conditional_data.as_slice() {
+            [ConditionalData::IsRegex(path, regex)] if path == "{path}" => regex_file_extensions(regex.as_str()),
+            _ => None,
+          },
+          _ => None,
+        })
+      }) {
+        if extensions
+          .iter()
+          .all(|extension| COMPRESSED_FILE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        {
+          warnings.push(format!(
+            "HTTP compression is enabled at {filters}, which matches only already compressed files \
+              (like images, videos or archives). Such files don't benefit from HTTP compression"
+          ));
+        }
+      }
+    }
+
+    if let Some(rewrite_entries) = get_entries!("rewrite", server_configuration) {
+      let mut catch_all_rule: Option<&str> = None;
+      for entry in &rewrite_entries.inner {
+        let Some(regex) = entry.values.first().and_then(|v| v.as_str()) else {
+          continue;
+        };
+        if let Some(catch_all_rule) = catch_all_rule {
+          warnings.push(format!(
+            "The URL rewrite rule \"{regex}\" at {filters} can never match, since the previous URL rewrite rule \
+              \"{catch_all_rule}\" matches all request URLs and is the last rule applied"
+          ));
+        } else if regex
+          .strip_prefix('^')
+          .and_then(|regex| regex.chars().next())
+          .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '~' | '%'))
+        {
+          warnings.push(format!(
+            "The URL rewrite rule \"{regex}\" at {filters} can never match, since request URLs always start with \"/\""
+          ));
+        }
+        let is_prop_enabled =
+          |name: &str, default: bool| entry.props.get(name).and_then(|v| v.as_bool()).unwrap_or(default);
+        if catch_all_rule.is_none()
+          && is_prop_enabled("last", false)
+          && is_prop_enabled("directory", true)
+          && is_prop_enabled("file", true)
+          && CATCH_ALL_REWRITE_REGEXES.contains(&regex.strip_prefix('^').unwrap_or(regex))
+        {
+          catch_all_rule = Some(regex);
+        }
+      }
+    }
+
+    // The redirects are performed only on the default HTTP port to the default HTTPS port
+    if get_value!("no_redirect_to_https", server_configuration).and_then(|v| v.as_bool()) == Some(false) {
+      for default_port_directive in ["default_http_port", "default_https_port"] {
+        if global_configuration
+          .and_then(|c| c.entries.get(default_port_directive))
+          .and_then(|e| e.get_value())
+          .is_some_and(|v| v.is_null())
+        {
+          warnings.push(format!(
+            "HTTP to HTTPS redirects are enabled at {filters}, but they aren't performed, \
+              since the `{default_port_directive}` global configuration property is set to `#null`"
+          ));
+        }
+      }
+    }
+  }
+
+  warnings
+}
+
+/// Obtains the file extensions matched by a location regular expression (like `\.(png|jpe?g)$`)
+/// Returns `None` if the regular expression doesn't match only specific file extensions.
+fn regex_file_extensions(regex: &str) -> Option<Vec<String>> {
+  let regex = regex.strip_suffix('$')?;
+  let (_, extensions) = regex.rsplit_once("\\.")?;
+  let extensions = extensions
+    .strip_prefix("(?:")
+    .or_else(|| extensions.strip_prefix('('))
+    .and_then(|extensions| extensions.strip_suffix(')'))
+    .unwrap_or(extensions);
+  let mut expanded_extensions = Vec::new();
+  for extension in extensions.split('|') {
+    match extension.split_once('?') {
+      // An optional character (like `jpe?g`)
+      Some((before, after)) if before.ends_with(|c: char| c.is_ascii_alphanumeric()) && !after.contains('?') => {
+        expanded_extensions.push(format!("{before}{after}"));
+        expanded_extensions.push(format!("{}{after}", &before[..before.len() - 1]));
+      }
+      Some(_) => return None,
+      None => expanded_extensions.push(extension.to_string()),
+    }
+  }
+  expanded_extensions
+    .iter()
+    .all(|extension| !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+    .then_some(expanded_extensions)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::net::{IpAddr, Ipv4Addr};
+
+  use ferron_common::config::{
+    Conditions, LocationModifier, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationFilters,
+    ServerConfigurationValue,
+  };
+  use ferron_common::observability::ObservabilityBackendChannels;
+
+  use super::*;
+
+  fn config(
+    hostname: Option<&str>,
+    ip: Option<IpAddr>,
+    condition: Option<Conditions>,
+    entries: Vec<(&str, Vec<ServerConfigurationEntry>)>,
+  ) -> ServerConfiguration {
+    ServerConfiguration {
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: hostname.map(String::from),
+        ip,
+        port: None,
+        condition,
+        error_handler_status: None,
+      },
+      entries: entries
+        .into_iter()
+        .map(|(name, inner)| (name.to_string(), ServerConfigurationEntries { inner }))
+        .collect(),
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  fn entry(
+    values: Vec<ServerConfigurationValue>,
+    props: Vec<(&str, ServerConfigurationValue)>,
+  ) -> ServerConfigurationEntry {
+    ServerConfigurationEntry {
+      values,
+      props: props
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect::<HashMap<_, _>>(),
+    }
+  }
+
+  fn string(value: &str) -> ServerConfigurationValue {
+    ServerConfigurationValue::String(value.to_string())
+  }
+
+  #[test]
+  fn test_regex_file_extensions() {
+    assert_eq!(
+      regex_file_extensions("\\.(png|jpe?g)$"),
+      Some(vec!["png".to_string(), "jpeg".to_string(), "jpg".to_string()])
+    );
+    assert_eq!(regex_file_extensions("\\.mp4$"), Some(vec!["mp4".to_string()]));
+    assert_eq!(regex_file_extensions("\\.(png|.*)$"), None);
+    assert_eq!(regex_file_extensions("^/images/"), None);
+  }
+
+  #[test]
+  fn test_lint_conflicting_directives() {
+    let configs = vec![
+      config(
+        Some("example.com"),
+        None,
+        None,
+        vec![
+          ("proxy", vec![entry(vec![string("http://localhost:3000")], vec![])]),
+          ("root", vec![entry(vec![string("/var/www/html")], vec![])]),
+          (
+            "rewrite",
+            vec![
+              entry(vec![string("^api/(.*)"), string("/v1/$1")], vec![]),
+              entry(
+                vec![string("^/(.*)$"), string("/index.php")],
+                vec![("last", ServerConfigurationValue::Bool(true))],
+              ),
+              entry(vec![string("^/old/(.*)"), string("/new/$1")], vec![]),
+            ],
+          ),
+        ],
+      ),
+      config(
+        Some("example.com"),
+        None,
+        Some(Conditions {
+          location_prefix: "/".to_string(),
+          location_modifier: LocationModifier::Prefix,
+          conditionals: vec![Conditional::If(vec![ConditionalData::IsRegex(
+            "{path}".to_string(),
+            fancy_regex::Regex::new("\\.(png|jpe?g|webp)$").unwrap(),
+          )])],
+        }),
+        vec![(
+          "compressed",
+          vec![entry(vec![ServerConfigurationValue::Bool(true)], vec![])],
+        )],
+      ),
+    ];
+
+    let warnings = lint_configuration(&configs);
+    assert_eq!(warnings.len(), 4);
+    assert!(warnings[0].contains("`root`"));
+    assert!(warnings[1].contains("\"^api/(.*)\"") && warnings[1].contains("start with \"/\""));
+    assert!(warnings[2].contains("\"^/old/(.*)\"") && warnings[2].contains("\"^/(.*)$\""));
+    assert!(warnings[3].contains("HTTP compression"));
+  }
+
+  #[test]
+  fn test_lint_tls_and_redirects() {
+    let configs = vec![
+      config(
+        None,
+        None,
+        None,
+        vec![(
+          "default_http_port",
+          vec![entry(vec![ServerConfigurationValue::Null], vec![])],
+        )],
+      ),
+      config(
+        None,
+        Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+        None,
+        vec![(
+          "no_redirect_to_https",
+          vec![entry(vec![ServerConfigurationValue::Bool(false)], vec![])],
+        )],
+      ),
+      config(None, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), None, vec![]),
+    ];
+
+    let warnings = lint_configuration(&configs);
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].contains("Automatic TLS") && warnings[0].contains("\"192.0.2.1\""));
+    assert!(warnings[1].contains("`default_http_port`"));
+  }
+}
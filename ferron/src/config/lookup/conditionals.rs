@@ -6,9 +6,11 @@ use std::{collections::HashMap, net::IpAddr};
 use ferron_common::util::{parse_q_value_header, replace_header_placeholders};
 use ferron_common::{
   config::{Conditional, ConditionalData},
-  modules::SocketData,
+  modules::{BufferedRequestBody, RequestTags, SocketData},
 };
 
+use crate::util::match_body_json_field;
+
 /// Condition match data
 pub struct ConditionMatchData<'a> {
   pub request: &'a hyper::http::request::Parts,
@@ -204,6 +206,22 @@ fn match_condition(
           .is_some_and(|l| l == language || language.split_once('-').is_some_and(|(v, _)| v == l)),
       )
     }
+    ConditionalData::IsTagged(tag) => Ok(
+      request
+        .extensions
+        .get::<RequestTags>()
+        .is_some_and(|request_tags| request_tags.contains(tag)),
+    ),
+    ConditionalData::IsBodyJson(pointer, value) => Ok(
+      request
+        .extensions
+        .get::<BufferedRequestBody>()
+        .is_some_and(|body| match_body_json_field(body, pointer, value)),
+    ),
+    ConditionalData::IsBodyRegex(regex) => match request.extensions.get::<BufferedRequestBody>() {
+      Some(body) => Ok(regex.is_match(&String::from_utf8_lossy(&body.data))?),
+      None => Ok(false),
+    },
     _ => Ok(false),
   }
 }
@@ -3,12 +3,16 @@ mod tree;
 
 use std::{collections::HashMap, net::IpAddr, sync::Arc};
 
+use fancy_regex::{Regex, RegexBuilder};
 use ferron_common::{
-  config::{ErrorHandlerStatus, ServerConfiguration, ServerConfigurationFilters},
+  config::{ErrorHandlerStatus, LocationModifier, ServerConfiguration, ServerConfigurationFilters},
+  get_value,
   modules::SocketData,
+  util::RequestClassifier,
 };
 use hashlink::LinkedHashMap;
 
+use crate::config::dynamic_hosts::DynamicHostProvider;
 use crate::config::lookup::{
   conditionals::ConditionMatchData,
   tree::{ConfigFilterTree, ConfigFilterTreeSingleKey},
@@ -47,7 +51,8 @@ fn convert_filters_to_node_key(filters: &ServerConfigurationFilters) -> Vec<Conf
       }
     }
 
-    if let Some(hostname) = &filters.hostname {
+    // Regular expression hostnames are matched outside of the configuration filter tree
+    if let Some(hostname) = filters.hostname.as_ref().filter(|_| !filters.has_regex_hostname()) {
       for part in hostname.split('.').rev() {
         if part.is_empty() {
           continue;
@@ -67,6 +72,9 @@ fn convert_filters_to_node_key(filters: &ServerConfigurationFilters) -> Vec<Conf
         }
         is_first = false;
       }
+      if conditions.location_modifier == LocationModifier::Regex {
+        node_key.push(ConfigFilterTreeSingleKey::IsRegexLocation);
+      }
       for conditional in &conditions.conditionals {
         // Had to clone the conditional here because the ConfigFilterTreeSingleKey::Conditional variant needs
         // to own its data, and we can't move it out of the loop since it's borrowed from the filters struct...
@@ -124,18 +132,145 @@ impl<T> ErrorHandlerStatusLookup<T> {
   }
 }
 
+/// Inserts a server configuration into the configuration filter tree. The `fallback_default` configuration is used
+/// as the default value for the new tree node, if there are no less specific configurations in the tree.
+fn insert_configuration(
+  tree: &mut ConfigFilterTree<ErrorHandlerStatusLookupWithConfiguration>,
+  config: Arc<ServerConfiguration>,
+  fallback_default: Option<Arc<ServerConfiguration>>,
+) {
+  let error_handler_status = &config.filters.error_handler_status;
+  let node_key = convert_filters_to_node_key(&config.filters);
+  let parent_config_default_value = tree
+    .get(node_key, None)
+    .expect("configuration filter tree's get method shouldn't error out if no conditionals are checked against")
+    .and_then(|lookup: &ErrorHandlerStatusLookup<_>| lookup.get_default().cloned())
+    .or(fallback_default);
+  let node_key = convert_filters_to_node_key(&config.filters);
+  let value_option = tree.insert_node(node_key);
+  if value_option.is_none() {
+    let mut new_value = ErrorHandlerStatusLookup::new();
+    if let Some(parent_default) = parent_config_default_value {
+      new_value.set_default(parent_default);
+    }
+    *value_option = Some(new_value);
+  }
+  if let Some(value) = value_option.as_mut() {
+    match error_handler_status {
+      Some(ErrorHandlerStatus::Status(status_code)) => value.insert(Some(*status_code), config),
+      Some(ErrorHandlerStatus::Any) => value.insert(None, config),
+      None => value.set_default(config),
+    }
+  }
+}
+
+/// Finds the configuration for the request in the configuration filter tree, given the node key identifying the host.
+/// Regular expression locations take priority over prefix locations, except for exact locations
+/// and prefix locations with the `^~` modifier.
+fn find_location_configuration<'a>(
+  tree: &'a ConfigFilterTree<ErrorHandlerStatusLookupWithConfiguration>,
+  host_node_key: Vec<ConfigFilterTreeSingleKey>,
+  request: &hyper::http::request::Parts,
+  socket_data: &SocketData,
+) -> Result<Option<&'a ErrorHandlerStatusLookupWithConfiguration>, Box<dyn std::error::Error + Send + Sync>> {
+  let mut node_key = host_node_key.clone();
+  for part in request.uri.path().split("/") {
+    node_key.push(ConfigFilterTreeSingleKey::LocationSegment(part.to_string()));
+  }
+  let configuration = tree.get(node_key, Some(ConditionMatchData { request, socket_data }))?;
+  let Some(config) = configuration.and_then(|lookup| lookup.get_default()) else {
+    return Ok(configuration);
+  };
+  if config.filters.condition.as_ref().is_some_and(|conditions| {
+    matches!(
+      conditions.location_modifier,
+      LocationModifier::Exact | LocationModifier::PriorityPrefix
+    )
+  }) {
+    return Ok(configuration);
+  }
+
+  let mut regex_location_node_key = host_node_key;
+  regex_location_node_key.push(ConfigFilterTreeSingleKey::LocationSegment("".to_string()));
+  regex_location_node_key.push(ConfigFilterTreeSingleKey::IsRegexLocation);
+  let regex_location_configuration = tree.get(
+    regex_location_node_key,
+    Some(ConditionMatchData { request, socket_data }),
+  )?;
+  // The lookup ends at a less specific configuration if no regular expression location matches,
+  // and the matching regular expression location must be in the same host block as the prefix location
+  if regex_location_configuration
+    .and_then(|lookup| lookup.get_default())
+    .is_some_and(|regex_config| {
+      regex_config
+        .filters
+        .condition
+        .as_ref()
+        .is_some_and(|conditions| conditions.location_modifier == LocationModifier::Regex)
+        && regex_config.filters.is_host == config.filters.is_host
+        && regex_config.filters.hostname == config.filters.hostname
+        && regex_config.filters.ip == config.filters.ip
+        && regex_config.filters.port == config.filters.port
+    })
+  {
+    return Ok(regex_location_configuration);
+  }
+
+  Ok(configuration)
+}
+
+/// The filters identifying a host block (the hostname, the port and the IP address)
+type HostFilterKey = (Option<String>, Option<u16>, Option<IpAddr>);
+
+/// A host block with a regular expression hostname
+#[derive(Debug)]
+struct RegexHost {
+  /// The hostname of the host block, including the `~` prefix
+  hostname: String,
+  /// The compiled regular expression
+  regex: Regex,
+  /// The priority of the host block, specified with the `host_priority` directive
+  priority: i128,
+  /// The configuration filter tree with the configurations of the host block
+  inner: ConfigFilterTree<ErrorHandlerStatusLookupWithConfiguration>,
+}
+
 #[derive(Debug)]
 pub struct ServerConfigurations {
   inner: ConfigFilterTree<ErrorHandlerStatusLookupWithConfiguration>,
 
+  /// Host blocks with regular expression hostnames, ordered by priority and then by the configuration order
+  regex_hosts: Vec<RegexHost>,
+
   /// A vector of all host configurations, used for quickly finding host configurations without needing
   /// to traverse the configuration filter tree
   pub host_configs: Vec<Arc<ServerConfiguration>>,
+
+  /// The provider of virtual hosts resolved at request time, used for hostnames without a matching host block
+  pub dynamic_hosts: Option<DynamicHostProvider>,
+
+  /// The request classifiers built from the `classify` directives, keyed by the host block filters
+  request_classifiers: Vec<(HostFilterKey, RequestClassifier)>,
 }
 
 impl ServerConfigurations {
   /// Creates the server configurations struct
   pub fn new(mut inner: Vec<ServerConfiguration>) -> Self {
+    // Remember the configuration order of regular expression hostnames, since host blocks with the same priority
+    // are matched in the configuration order
+    let mut regex_host_order = HashMap::new();
+    for config in &inner {
+      if let Some(hostname) = config
+        .filters
+        .hostname
+        .as_ref()
+        .filter(|_| config.filters.has_regex_hostname())
+      {
+        let order = regex_host_order.len();
+        regex_host_order.entry(hostname.clone()).or_insert(order);
+      }
+    }
+
     // Reverse the inner vector to ensure the location configurations are checked in the correct order
     inner.reverse();
 
@@ -144,7 +279,8 @@ impl ServerConfigurations {
     // without needing to backtrack to update parent nodes with default values from more specific child nodes
     inner.sort_by(|a, b| a.filters.cmp(&b.filters));
 
-    let mut new_inner = ConfigFilterTree::new();
+    let mut new_inner: ConfigFilterTree<ErrorHandlerStatusLookupWithConfiguration> = ConfigFilterTree::new();
+    let mut regex_hosts: HashMap<String, RegexHost> = HashMap::new();
     let mut host_config_filters = LinkedHashMap::new();
 
     for config in inner {
@@ -161,33 +297,67 @@ impl ServerConfigurations {
             .or_insert_with(|| config.clone());
         }
       }
-      let error_handler_status = &config.filters.error_handler_status;
-      let node_key = convert_filters_to_node_key(&config.filters);
-      let parent_config_default_value = new_inner
-        .get(node_key, None)
-        .expect("configuration filter tree's get method shouldn't error out if no conditionals are checked against")
-        .and_then(|lookup: &ErrorHandlerStatusLookup<_>| lookup.get_default().cloned());
-      let node_key = convert_filters_to_node_key(&config.filters);
-      let value_option = new_inner.insert_node(node_key);
-      if value_option.is_none() {
-        let mut new_value = ErrorHandlerStatusLookup::new();
-        if let Some(parent_default) = parent_config_default_value {
-          new_value.set_default(parent_default);
-        }
-        *value_option = Some(new_value);
-      }
-      if let Some(value) = value_option.as_mut() {
-        match error_handler_status {
-          Some(ErrorHandlerStatus::Status(status_code)) => value.insert(Some(*status_code), config),
-          Some(ErrorHandlerStatus::Any) => value.insert(None, config),
-          None => value.set_default(config),
+      if let Some(hostname) = config
+        .filters
+        .hostname
+        .as_ref()
+        .filter(|_| config.filters.has_regex_hostname())
+      {
+        // Host blocks with regular expression hostnames are stored in separate configuration filter trees,
+        // falling back to the global host configuration
+        let regex_host = match regex_hosts.entry(hostname.clone()) {
+          std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+          std::collections::hash_map::Entry::Vacant(entry) => {
+            // The regular expression is validated when the configuration is loaded
+            let Ok(regex) = RegexBuilder::new(&hostname[1..]).case_insensitive(true).build() else {
+              continue;
+            };
+            entry.insert(RegexHost {
+              hostname: hostname.clone(),
+              regex,
+              priority: 0,
+              inner: ConfigFilterTree::new(),
+            })
+          }
+        };
+        if config.filters.condition.is_none() && config.filters.error_handler_status.is_none() {
+          regex_host.priority = get_value!("host_priority", config)
+            .and_then(|v| v.as_i128())
+            .unwrap_or(0);
         }
+        let global_host_default = new_inner
+          .get(vec![ConfigFilterTreeSingleKey::IsHostConfiguration], None)
+          .expect("configuration filter tree's get method shouldn't error out if no conditionals are checked against")
+          .and_then(|lookup| lookup.get_default().cloned());
+        insert_configuration(&mut regex_host.inner, config, global_host_default);
+      } else {
+        insert_configuration(&mut new_inner, config, None);
       }
     }
 
+    let mut regex_hosts = regex_hosts.into_values().collect::<Vec<_>>();
+    regex_hosts.sort_by_key(|regex_host| {
+      (
+        std::cmp::Reverse(regex_host.priority),
+        regex_host_order.get(&regex_host.hostname).copied(),
+      )
+    });
+
+    // The `classify` directive can't be specified in locations and conditional blocks,
+    // so the request classifiers are built only from host configurations
+    let request_classifiers = host_config_filters
+      .iter()
+      .filter_map(|(host_filter_key, config)| {
+        RequestClassifier::from_config(config).map(|classifier| (host_filter_key.clone(), classifier))
+      })
+      .collect();
+
     Self {
       inner: new_inner,
+      regex_hosts,
       host_configs: host_config_filters.into_iter().map(|(_, config)| config).collect(),
+      dynamic_hosts: None,
+      request_classifiers,
     }
   }
 
@@ -226,13 +396,78 @@ impl ServerConfigurations {
         node_key.push(ConfigFilterTreeSingleKey::HostDomainLevel(part.to_string()))
       }
     }
-    for part in request.uri.path().split("/") {
-      node_key.push(ConfigFilterTreeSingleKey::LocationSegment(part.to_string()));
+    let configuration = find_location_configuration(&self.inner, node_key, request, socket_data)?;
+
+    // Host blocks with regular expression hostnames are less specific than host blocks with exact and wildcard hostnames,
+    // but more specific than host blocks without hostnames
+    if let Some(hostname) = hostname {
+      if configuration
+        .and_then(|lookup| lookup.get_default())
+        .is_none_or(|config| config.filters.hostname.is_none())
+      {
+        if let Some(regex_host) = self.find_regex_host(hostname) {
+          let node_key = vec![ConfigFilterTreeSingleKey::IsHostConfiguration];
+          if let Some(regex_host_configuration) =
+            find_location_configuration(&regex_host.inner, node_key, request, socket_data)?
+          {
+            return Ok(Some(regex_host_configuration));
+          }
+        }
+      }
+    }
+
+    Ok(configuration)
+  }
+
+  /// Finds the host block with a regular expression hostname matching the hostname
+  fn find_regex_host(&self, hostname: &str) -> Option<&RegexHost> {
+    let hostname = hostname.trim_end_matches('.');
+    self
+      .regex_hosts
+      .iter()
+      .find(|regex_host| regex_host.regex.is_match(hostname).unwrap_or(false))
+  }
+
+  /// Obtains the request variables from the groups captured by the regular expression hostname of the server configuration.
+  /// Named groups are available by their names, and all groups are available by their numbers.
+  pub fn capture_host_variables(
+    &self,
+    filters: &ServerConfigurationFilters,
+    hostname: &str,
+  ) -> HashMap<String, String> {
+    let mut variables = HashMap::new();
+    let Some(regex_host) = self
+      .regex_hosts
+      .iter()
+      .find(|regex_host| filters.hostname.as_ref() == Some(&regex_host.hostname))
+    else {
+      return variables;
+    };
+    if let Ok(Some(captures)) = regex_host.regex.captures(hostname.trim_end_matches('.')) {
+      for (index, name) in regex_host.regex.capture_names().enumerate().skip(1) {
+        if let Some(capture) = captures.get(index) {
+          variables.insert(index.to_string(), capture.as_str().to_string());
+          if let Some(name) = name {
+            variables.insert(name.to_string(), capture.as_str().to_string());
+          }
+        }
+      }
     }
+    variables
+  }
 
+  /// Classifies the request using the `classify` directives of the host block, and returns the request tags
+  pub fn classify_request(
+    &self,
+    filters: &ServerConfigurationFilters,
+    request: &hyper::http::request::Parts,
+    socket_data: &SocketData,
+  ) -> Vec<String> {
     self
-      .inner
-      .get(node_key, Some(ConditionMatchData { request, socket_data }))
+      .request_classifiers
+      .iter()
+      .find(|((hostname, port, ip), _)| *hostname == filters.hostname && *port == filters.port && *ip == filters.ip)
+      .map_or_else(Vec::new, |(_, classifier)| classifier.classify(request, socket_data))
   }
 
   /// Finds the global server configuration (host or non-host)
@@ -245,3 +480,117 @@ impl ServerConfigurations {
       .cloned()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+  use ferron_common::config::{
+    Conditional, ConditionalData, Conditions, ServerConfigurationEntries, ServerConfigurationEntry,
+    ServerConfigurationValue,
+  };
+  use ferron_common::observability::ObservabilityBackendChannels;
+
+  use super::*;
+
+  /// Creates a configuration of the "example.com" host block, with the "name" directive identifying the configuration
+  fn config(name: &str, condition: Option<Conditions>) -> ServerConfiguration {
+    ServerConfiguration {
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: Some("example.com".to_string()),
+        ip: None,
+        port: None,
+        condition,
+        error_handler_status: None,
+      },
+      entries: HashMap::from([(
+        "name".to_string(),
+        ServerConfigurationEntries {
+          inner: vec![ServerConfigurationEntry {
+            values: vec![ServerConfigurationValue::String(name.to_string())],
+            props: HashMap::new(),
+          }],
+        },
+      )]),
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  fn location(location_prefix: &str, location_modifier: LocationModifier) -> Option<Conditions> {
+    Some(Conditions {
+      location_prefix: location_prefix.to_string(),
+      location_modifier,
+      conditionals: vec![],
+    })
+  }
+
+  fn regex_location(regex: &str, previous_regexes: &[&str]) -> Option<Conditions> {
+    let regex_conditional = |regex: &str| {
+      vec![ConditionalData::IsRegex(
+        "{path}".to_string(),
+        Regex::new(regex).unwrap(),
+      )]
+    };
+    let mut conditionals = vec![Conditional::If(regex_conditional(regex))];
+    for previous_regex in previous_regexes {
+      conditionals.push(Conditional::IfNot(regex_conditional(previous_regex)));
+    }
+    Some(Conditions {
+      location_prefix: "/".to_string(),
+      location_modifier: LocationModifier::Regex,
+      conditionals,
+    })
+  }
+
+  #[test]
+  fn test_location_precedence() {
+    let mut exact_location = location("/images/logo.png", LocationModifier::Exact);
+    if let Some(conditions) = exact_location.as_mut() {
+      conditions
+        .conditionals
+        .push(Conditional::If(vec![ConditionalData::IsEqual(
+          "{path}".to_string(),
+          "/images/logo.png".to_string(),
+        )]));
+    }
+    let configurations = ServerConfigurations::new(vec![
+      config("host", None),
+      config("images", location("/images/", LocationModifier::Prefix)),
+      config("static", location("/static/", LocationModifier::PriorityPrefix)),
+      config("logo", exact_location),
+      config("png", regex_location("\\.png$", &[])),
+      config("image", regex_location("\\.(png|jpe?g)$", &["\\.png$"])),
+    ]);
+    let socket_data = SocketData {
+      remote_addr: SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 12345),
+      local_addr: SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 80),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+
+    for (path, expected_name) in [
+      ("/images/photo.png", "png"),
+      ("/images/photo.jpg", "image"),
+      ("/images/photo.gif", "images"),
+      ("/images/logo.png", "logo"),
+      ("/static/photo.png", "static"),
+      ("/photo.png", "png"),
+      ("/index.html", "host"),
+    ] {
+      let (request, _) = hyper::Request::get(path).body(()).unwrap().into_parts();
+      let configuration = configurations
+        .find_configuration(&request, Some("example.com"), &socket_data)
+        .unwrap()
+        .and_then(|lookup| lookup.get_default())
+        .unwrap();
+      assert_eq!(
+        get_value!("name", configuration).and_then(|v| v.as_str()),
+        Some(expected_name),
+        "unexpected configuration for \"{path}\""
+      );
+    }
+  }
+}
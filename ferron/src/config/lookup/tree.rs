@@ -23,6 +23,8 @@ pub enum ConfigFilterTreeSingleKey {
   HostDomainLevelWildcard,
   /// A location path segment
   LocationSegment(String),
+  /// The configuration is for regular expression locations, which are looked up separately from prefix locations
+  IsRegexLocation,
   /// A conditional
   Conditional(Conditional),
   // Note how error handler status isn't included in the tree key,
@@ -228,6 +230,7 @@ impl<T> ConfigFilterTree<T> {
     current_nodes.push_back((&self.root, ConfigFilterTreeMultiKey(key)));
     let mut value = current_nodes[0].0.value.as_ref();
     while let Some((mut current_node, mut key)) = current_nodes.pop_front() {
+      let mut is_interrupted = false;
       while !key.0.is_empty() {
         let key_end = key.0.split_off(1);
         let mut partial_key = ConfigFilterTreeMultiKey(key.0);
@@ -254,6 +257,7 @@ impl<T> ConfigFilterTree<T> {
           }
           if !is_matching || (index >= key.0.len() && secondary_index < child_key.0.len()) {
             // The keys differ or are out of bounds, so the fixed multi-key does not match
+            is_interrupted = true;
             break;
           }
           key.0 = key.0.split_off(index);
@@ -317,11 +321,29 @@ impl<T> ConfigFilterTree<T> {
           }
         }
         if conditional_predicate_matched {
+          is_interrupted = true;
           break;
         }
 
         // If nothing happened, probably no matching fixed multi-key or predicate single key found...
       }
+
+      if !is_interrupted {
+        // The whole key matched, but there can still be conditionals applying to the matched node
+        // (for example, conditionals in a location matching the exact request path)
+        if let Some(condition_match_data) = condition_match_data.as_ref() {
+          for (predicate_key, child) in &current_node.children_predicate {
+            if let ConfigFilterTreeSingleKey::Conditional(conditional) = predicate_key {
+              if match_conditional(conditional, condition_match_data)? {
+                if child.value.is_some() {
+                  value = child.value.as_ref();
+                }
+                current_nodes.push_back((child, key.clone()));
+              }
+            }
+          }
+        }
+      }
     }
 
     Ok(value)
@@ -330,6 +352,9 @@ impl<T> ConfigFilterTree<T> {
 
 #[cfg(test)]
 mod tests {
+  use ferron_common::config::ConditionalData;
+  use ferron_common::modules::SocketData;
+
   use super::*;
 
   #[test]
@@ -589,6 +614,56 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_conditionals_after_whole_key_match() {
+    let mut tree = ConfigFilterTree::new();
+    tree.insert(
+      vec![
+        ConfigFilterTreeSingleKey::LocationSegment("".to_string()),
+        ConfigFilterTreeSingleKey::LocationSegment("exact".to_string()),
+      ],
+      "Prefix",
+    );
+    tree.insert(
+      vec![
+        ConfigFilterTreeSingleKey::LocationSegment("".to_string()),
+        ConfigFilterTreeSingleKey::LocationSegment("exact".to_string()),
+        ConfigFilterTreeSingleKey::Conditional(Conditional::If(vec![ConditionalData::IsEqual(
+          "{path}".to_string(),
+          "/exact".to_string(),
+        )])),
+      ],
+      "Exact",
+    );
+
+    let socket_data = SocketData {
+      remote_addr: "127.0.0.1:12345".parse().unwrap(),
+      local_addr: "127.0.0.1:80".parse().unwrap(),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+    for (path, expected_value) in [("/exact", "Exact"), ("/exact/", "Prefix"), ("/exact/file", "Prefix")] {
+      let (request, _) = hyper::Request::builder().uri(path).body(()).unwrap().into_parts();
+      let key = path
+        .split("/")
+        .map(|segment| ConfigFilterTreeSingleKey::LocationSegment(segment.to_string()))
+        .collect();
+      assert_eq!(
+        tree
+          .get(
+            key,
+            Some(ConditionMatchData {
+              request: &request,
+              socket_data: &socket_data
+            })
+          )
+          .unwrap(),
+        Some(&expected_value)
+      );
+    }
+  }
+
   #[test]
   fn test_keys_with_redundant_in_between() {
     let mut tree = ConfigFilterTree::new();
@@ -1,6 +1,11 @@
 pub mod adapters;
+mod cdn_presets;
+mod dynamic_hosts;
+mod lint;
 mod lookup;
 pub mod processing;
+mod snapshot;
+mod trusted_proxy_lists;
 
 pub use ferron_common::config::*;
 
@@ -9,8 +14,13 @@ use std::sync::Arc;
 
 use fancy_regex::RegexBuilder;
 
+pub use self::cdn_presets::*;
+pub use self::dynamic_hosts::*;
+pub use self::lint::*;
 pub use self::lookup::*;
-use crate::util::IpBlockList;
+pub use self::snapshot::*;
+pub use self::trusted_proxy_lists::*;
+use crate::util::{json_path_to_pointer, IpBlockList};
 
 /// Parses conditional data
 pub fn parse_conditional_data(
@@ -152,6 +162,44 @@ pub fn parse_conditional_data(
         ))?
         .to_string(),
     ),
+    "is_tagged" => ConditionalData::IsTagged(
+      value
+        .values
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or(anyhow::anyhow!(
+          "Missing or invalid request tag in a \"is_tagged\" subcondition"
+        ))?
+        .to_string(),
+    ),
+    "is_body_json" => {
+      let json_path = value.values.first().and_then(|v| v.as_str()).ok_or(anyhow::anyhow!(
+        "Missing or invalid JSON path in a \"is_body_json\" subcondition"
+      ))?;
+      let desired_value = value.values.get(1).and_then(|v| v.as_str()).ok_or(anyhow::anyhow!(
+        "Missing or invalid desired value in a \"is_body_json\" subcondition"
+      ))?;
+      ConditionalData::IsBodyJson(
+        json_path_to_pointer(json_path).map_err(|err| anyhow::anyhow!(err))?,
+        desired_value.to_string(),
+      )
+    }
+    "is_body_regex" => {
+      let regex = value.values.first().and_then(|v| v.as_str()).ok_or(anyhow::anyhow!(
+        "Missing or invalid regular expression in a \"is_body_regex\" subcondition"
+      ))?;
+      ConditionalData::IsBodyRegex(
+        RegexBuilder::new(regex)
+          .case_insensitive(
+            value
+              .props
+              .get("case_insensitive")
+              .and_then(|p| p.as_bool())
+              .unwrap_or(false),
+          )
+          .build()?,
+      )
+    }
     _ => Err(anyhow::anyhow!("Unrecognized subcondition: {name}"))?,
   })
 }
@@ -2,11 +2,13 @@ use std::{
   collections::{BTreeMap, HashMap, HashSet, VecDeque},
   error::Error,
   net::IpAddr,
+  sync::Arc,
 };
 
 use ferron_common::{
   config::{Conditional, ErrorHandlerStatus},
-  modules::ModuleLoader,
+  logging::{LogLevel, LogLevelFilter},
+  modules::{DirectiveScope, ModuleLoader},
   observability::{ObservabilityBackendChannels, ObservabilityBackendLoader},
 };
 
@@ -313,6 +315,92 @@ pub fn premerge_configuration(mut server_configurations: Vec<ServerConfiguration
   new_server_configurations
 }
 
+/// Validates scopes of configuration directives
+/// Checks whether configuration directives are specified in configuration blocks allowed by their scopes.
+///
+/// The directive scopes are declared by module loaders. This function should be called before the configurations
+/// are pre-merged, so that directives inherited from parent configuration blocks aren't reported. Directives specified
+/// in locations, conditional blocks or error handler blocks outside of their scopes are errors, while global-only
+/// directives specified in host blocks are reported as warnings, since such directives are ignored.
+pub fn validate_directive_scopes(
+  server_configurations: &[ServerConfiguration],
+  server_modules: &[Box<dyn ModuleLoader + Send + Sync>],
+) -> (Option<Box<dyn Error + Send + Sync>>, Vec<String>) {
+  // Collect the directive scopes declared by all modules
+  let mut directive_scopes = HashMap::new();
+  for server_module in server_modules {
+    directive_scopes.extend(server_module.get_directive_scopes());
+  }
+
+  let mut first_error = None;
+  let mut warnings = Vec::new();
+  for server_configuration in server_configurations {
+    let filters = &server_configuration.filters;
+    let is_global = filters.is_global() || filters.is_global_non_host();
+    let is_host_block = filters.condition.is_none() && filters.error_handler_status.is_none();
+    // Sort the directive names, so that the diagnostics are deterministic
+    let mut directive_names = server_configuration.entries.keys().collect::<Vec<_>>();
+    directive_names.sort();
+    for directive_name in directive_names {
+      match directive_scopes.get(directive_name.as_str()) {
+        Some(directive_scope) if !is_host_block && first_error.is_none() => {
+          first_error.replace(
+            anyhow::anyhow!(
+              "The `{directive_name}` configuration property isn't allowed at {filters}, since it can be specified {}",
+              match directive_scope {
+                DirectiveScope::Global => "only in the global configuration",
+                DirectiveScope::Host => "only in the global configuration and host blocks",
+              }
+            )
+            .into_boxed_dyn_error(),
+          );
+        }
+        Some(_) if !is_host_block => (),
+        Some(DirectiveScope::Global) if !is_global => warnings.push(format!(
+          "The `{directive_name}` configuration property at {filters} is ignored, \
+            since it can be specified only in the global configuration"
+        )),
+        _ => (),
+      }
+    }
+  }
+
+  (first_error, warnings)
+}
+
+/// Checks whether the module requirements are satisfied by the server configuration
+/// The requirements are satisfied, if there are no requirements, or if any of the required properties exists and has
+/// a non-null value (other than `#false`).
+pub fn are_requirements_met(requirements: &[&str], server_configuration: &ServerConfiguration) -> bool {
+  requirements.is_empty()
+    || requirements.iter().any(|requirement| {
+      server_configuration
+        .entries
+        .get(*requirement)
+        .and_then(|e| e.get_value())
+        .is_some_and(|v| !v.is_null() && v.as_bool().unwrap_or(true))
+    })
+}
+
+/// Creates a log level filter from the `log_level` directives in the server configuration.
+/// A `log_level` directive without the `module` property sets the default level, while a directive with the `module`
+/// property overrides the level for the specified module.
+pub fn load_log_level_filter(server_configuration: &ServerConfiguration) -> LogLevelFilter {
+  let mut log_level_filter = LogLevelFilter::default();
+  if let Some(entries) = server_configuration.entries.get("log_level") {
+    for entry in &entries.inner {
+      let Some(level) = entry.values.first().and_then(|v| v.as_str()).and_then(LogLevel::parse) else {
+        continue;
+      };
+      match entry.props.get("module").and_then(|v| v.as_str()) {
+        Some(module) => log_level_filter.set_module_level(module, level),
+        None => log_level_filter = LogLevelFilter::new(level),
+      }
+    }
+  }
+  log_level_filter
+}
+
 /// Loads Ferron modules into its configurations
 /// Loads and validates modules for each server configuration.
 ///
@@ -344,25 +432,14 @@ pub fn load_modules(
     // Track which properties are used by modules
     let mut used_properties = HashSet::new();
 
+    server_configuration.observability.log_level_filter = Arc::new(load_log_level_filter(&server_configuration));
+
     // Process each available observability backend
     for server_observability_backend in server_observability_backends.iter_mut() {
       // Get observability backend requirements
       let requirements = server_observability_backend.get_requirements();
       // Check if this observability backend's requirements are satisfied by this configuration
-      let mut requirements_met = true;
-      for requirement in requirements {
-        requirements_met = false;
-        // Check if the required property exists and has a non-null value
-        if server_configuration
-          .entries
-          .get(requirement)
-          .and_then(|e| e.get_value())
-          .is_some_and(|v| !v.is_null() && v.as_bool().unwrap_or(true))
-        {
-          requirements_met = true;
-          break;
-        }
-      }
+      let requirements_met = are_requirements_met(&requirements, &server_configuration);
       // Validate the configuration against this observability backend
       if let Err(error) =
         server_observability_backend.validate_configuration(&server_configuration, &mut used_properties)
@@ -407,26 +484,42 @@ pub fn load_modules(
       }
     }
 
+    // Deduplicate and rate-limit error log messages before dispatching them to the observability backends
+    if let Some(error_log_dedup) = server_configuration
+      .entries
+      .get("error_log_dedup")
+      .and_then(|e| e.get_entry())
+    {
+      if let Some(window) = error_log_dedup
+        .values
+        .first()
+        .and_then(|v| v.as_duration())
+        .filter(|window| !window.is_zero() && !server_configuration.observability.log_channels.is_empty())
+      {
+        let max_messages = error_log_dedup
+          .props
+          .get("max_messages")
+          .and_then(|v| v.as_i128())
+          .map(|v| v as u64);
+        let (logging_tx, logging_rx) = async_channel::unbounded();
+        let log_channels = std::mem::take(&mut server_configuration.observability.log_channels);
+        secondary_runtime.spawn(crate::util::forward_deduplicated_logs(
+          logging_rx,
+          log_channels,
+          window,
+          max_messages,
+        ));
+        server_configuration.observability.add_log_channel(logging_tx);
+      }
+    }
+
     if first_server_module_error.is_none() {
       // Process each available server module
       for server_module in server_modules.iter_mut() {
         // Get module requirements
         let requirements = server_module.get_requirements();
         // Check if this module's requirements are satisfied by this configuration
-        let mut requirements_met = true;
-        for requirement in requirements {
-          requirements_met = false;
-          // Check if the required property exists and has a non-null value
-          if server_configuration
-            .entries
-            .get(requirement)
-            .and_then(|e| e.get_value())
-            .is_some_and(|v| !v.is_null() && v.as_bool().unwrap_or(true))
-          {
-            requirements_met = true;
-            break;
-          }
-        }
+        let requirements_met = are_requirements_met(&requirements, &server_configuration);
         // Validate the configuration against this module
         if let Err(error) = server_module.validate_configuration(&server_configuration, &mut used_properties) {
           // Store the first error encountered
@@ -522,6 +615,7 @@ mod tests {
       port,
       condition: location_prefix.map(|prefix| Conditions {
         location_prefix: prefix.to_string(),
+        location_modifier: LocationModifier::Prefix,
         conditionals: vec![],
       }),
       error_handler_status,
@@ -562,6 +656,7 @@ mod tests {
         port,
         condition: location_prefix.map(|prefix| Conditions {
           location_prefix: prefix.to_string(),
+          location_modifier: LocationModifier::Prefix,
           conditionals: vec![],
         }),
         error_handler_status,
@@ -798,6 +893,119 @@ mod tests {
     assert!(merged_entries.contains_key("route2"));
   }
 
+  /// A module loader declaring directive scopes, used for testing the directive scope validation
+  struct ScopedModuleLoader;
+
+  impl ModuleLoader for ScopedModuleLoader {
+    fn load_module(
+      &mut self,
+      _config: &ServerConfiguration,
+      _global_config: Option<&ServerConfiguration>,
+      _secondary_runtime: &tokio::runtime::Runtime,
+    ) -> Result<std::sync::Arc<dyn ferron_common::modules::Module + Send + Sync>, Box<dyn Error + Send + Sync>> {
+      Err(anyhow::anyhow!("Not implemented"))?
+    }
+
+    fn get_directive_scopes(&self) -> Vec<(&'static str, DirectiveScope)> {
+      vec![("protocols", DirectiveScope::Global), ("tls", DirectiveScope::Host)]
+    }
+  }
+
+  #[test]
+  fn test_directive_scopes_valid() {
+    let modules: Vec<Box<dyn ModuleLoader + Send + Sync>> = vec![Box::new(ScopedModuleLoader)];
+    let configs = vec![
+      config_with_filters(
+        true,
+        None,
+        None,
+        None,
+        None,
+        None,
+        vec![make_entry_premerge(
+          "protocols",
+          ServerConfigurationValue::String("h1".into()),
+        )],
+      ),
+      config_with_filters(
+        true,
+        Some("example.com"),
+        None,
+        None,
+        None,
+        None,
+        vec![make_entry_premerge(
+          "tls",
+          ServerConfigurationValue::String("cert.pem".into()),
+        )],
+      ),
+      config_with_filters(
+        true,
+        Some("example.com"),
+        None,
+        None,
+        Some("/api"),
+        None,
+        vec![make_entry_premerge(
+          "root",
+          ServerConfigurationValue::String("/var/www".into()),
+        )],
+      ),
+    ];
+
+    let (error, warnings) = validate_directive_scopes(&configs, &modules);
+    assert!(error.is_none());
+    assert!(warnings.is_empty());
+  }
+
+  #[test]
+  fn test_directive_scopes_host_directive_in_location() {
+    let modules: Vec<Box<dyn ModuleLoader + Send + Sync>> = vec![Box::new(ScopedModuleLoader)];
+    let configs = vec![config_with_filters(
+      true,
+      Some("example.com"),
+      None,
+      None,
+      Some("/api"),
+      None,
+      vec![make_entry_premerge(
+        "tls",
+        ServerConfigurationValue::String("cert.pem".into()),
+      )],
+    )];
+
+    let (error, warnings) = validate_directive_scopes(&configs, &modules);
+    let error = error
+      .expect("the host-level directive in a location should be rejected")
+      .to_string();
+    assert!(error.contains("`tls`"));
+    assert!(error.contains("\"/api\" location"));
+    assert!(warnings.is_empty());
+  }
+
+  #[test]
+  fn test_directive_scopes_global_directive_in_host() {
+    let modules: Vec<Box<dyn ModuleLoader + Send + Sync>> = vec![Box::new(ScopedModuleLoader)];
+    let configs = vec![config_with_filters(
+      true,
+      Some("example.com"),
+      None,
+      None,
+      None,
+      None,
+      vec![make_entry_premerge(
+        "protocols",
+        ServerConfigurationValue::String("h1".into()),
+      )],
+    )];
+
+    let (error, warnings) = validate_directive_scopes(&configs, &modules);
+    assert!(error.is_none());
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("`protocols`"));
+    assert!(warnings[0].contains("\"example.com\" host block"));
+  }
+
   #[test]
   fn test_no_merge_returns_all() {
     let config1 = config_with_filters(
@@ -984,4 +1192,41 @@ mod tests {
     assert_eq!(entries.len(), 1);
     assert_eq!(entries[0].values[0].as_str(), Some("v3"));
   }
+
+  #[test]
+  fn loads_log_level_filter_with_module_overrides() {
+    let mut module_props = HashMap::new();
+    module_props.insert(
+      "module".to_string(),
+      ServerConfigurationValue::String("http_proxy".to_string()),
+    );
+    let config = config_with_filters(
+      false,
+      None,
+      None,
+      None,
+      None,
+      None,
+      vec![(
+        "log_level".to_string(),
+        ServerConfigurationEntries {
+          inner: vec![
+            ServerConfigurationEntry {
+              values: vec![ServerConfigurationValue::String("info".to_string())],
+              props: HashMap::new(),
+            },
+            ServerConfigurationEntry {
+              values: vec![ServerConfigurationValue::String("trace".to_string())],
+              props: module_props,
+            },
+          ],
+        },
+      )],
+    );
+
+    let filter = load_log_level_filter(&config);
+    assert!(filter.is_enabled(LogLevel::Info, "static"));
+    assert!(!filter.is_enabled(LogLevel::Debug, "static"));
+    assert!(filter.is_enabled(LogLevel::Trace, "http_proxy"));
+  }
 }
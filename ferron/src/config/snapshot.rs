@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use ferron_common::get_entry;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ServerConfiguration, ServerConfigurationEntries, ServerConfigurationValue};
+use crate::setup::tls::{manual_tls_entry, read_default_port, resolve_sni_hostname, should_skip_server};
+
+/// The name of the global configuration block in the snapshot
+const GLOBAL_BLOCK_NAME: &str = "global configuration";
+
+/// Formats a configuration value in a KDL-like notation
+fn format_value(value: &ServerConfigurationValue) -> String {
+  match value {
+    ServerConfigurationValue::String(value) => format!("{value:?}"),
+    ServerConfigurationValue::Integer(value) => value.to_string(),
+    ServerConfigurationValue::Float(value) => value.to_string(),
+    ServerConfigurationValue::Bool(value) => format!("#{value}"),
+    ServerConfigurationValue::Null => "#null".to_string(),
+  }
+}
+
+/// Formats configuration entries in a KDL-like notation, with props sorted by name
+fn format_entries(entries: &ServerConfigurationEntries) -> String {
+  entries
+    .inner
+    .iter()
+    .map(|entry| {
+      let mut props = entry.props.iter().collect::<Vec<_>>();
+      props.sort_by(|a, b| a.0.cmp(b.0));
+      entry
+        .values
+        .iter()
+        .map(format_value)
+        .chain(
+          props
+            .into_iter()
+            .map(|(name, value)| format!("{name}={}", format_value(value))),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+    })
+    .collect::<Vec<_>>()
+    .join("; ")
+}
+
+/// A snapshot of the server configuration, used to compare the running configuration with the new one
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ConfigurationSnapshot {
+  /// The process ID of the server running the configuration
+  #[serde(default)]
+  pub pid: Option<u32>,
+
+  /// The configuration blocks, with formatted directives keyed by their names
+  blocks: BTreeMap<String, BTreeMap<String, String>>,
+
+  /// The listening ports, like "http:80" or "https:443"
+  listeners: BTreeSet<String>,
+
+  /// The sources of TLS certificates, keyed by the host block descriptions
+  certificates: BTreeMap<String, String>,
+}
+
+impl ConfigurationSnapshot {
+  /// Creates a snapshot of the processed server configurations
+  pub fn new(configs: &[ServerConfiguration]) -> Self {
+    let mut blocks = BTreeMap::new();
+    for config in configs {
+      let directives = config
+        .entries
+        .iter()
+        .filter(|(name, _)| !name.starts_with("UNDOCUMENTED_"))
+        .map(|(name, entries)| (name.clone(), format_entries(entries)))
+        .collect::<BTreeMap<_, _>>();
+      let block_name = if config.filters.is_global() {
+        GLOBAL_BLOCK_NAME.to_string()
+      } else {
+        config.filters.to_string()
+      };
+      blocks
+        .entry(block_name)
+        .or_insert_with(BTreeMap::new)
+        .extend(directives);
+    }
+
+    // The listening ports and TLS certificates are determined similarly as when the server is started
+    let global_configuration = configs.iter().find(|c| c.filters.is_global());
+    let default_http_port = read_default_port(global_configuration, false);
+    let default_https_port = read_default_port(global_configuration, true);
+    let mut http_ports = BTreeSet::new();
+    let mut https_ports = BTreeSet::new();
+    let mut certificates = BTreeMap::new();
+    for server in configs
+      .iter()
+      .filter(|c| c.filters.condition.is_none() && c.filters.error_handler_status.is_none())
+    {
+      if should_skip_server(server) {
+        continue;
+      }
+
+      let manual_tls = manual_tls_entry(server);
+      let auto_tls_explicit = get_entry!("auto_tls", server)
+        .and_then(|e| e.values.first())
+        .and_then(|v| v.as_bool());
+      if get_entry!("tls", server).is_none() && auto_tls_explicit != Some(true) {
+        if let Some(http_port) = server.filters.port.or(default_http_port) {
+          http_ports.insert(http_port);
+        }
+      }
+
+      if let Some(https_port) = server.filters.port.or(default_https_port) {
+        let auto_tls = auto_tls_explicit
+          .unwrap_or(server.filters.port.is_none() && manual_tls.is_none() && !server.filters.has_regex_hostname())
+          && (resolve_sni_hostname(&server.filters).is_some()
+            || get_entry!("auto_tls_on_demand", server)
+              .and_then(|e| e.values.first())
+              .and_then(|v| v.as_bool())
+              .unwrap_or(false));
+        if auto_tls {
+          https_ports.insert(https_port);
+          certificates.insert(server.filters.to_string(), "automatic TLS".to_string());
+        } else if let Some((certificate, private_key)) = manual_tls {
+          https_ports.insert(https_port);
+          certificates.insert(
+            server.filters.to_string(),
+            format!("{certificate} (private key: {private_key})"),
+          );
+        }
+      }
+    }
+
+    // If HTTP/1.1 isn't enabled, the server doesn't listen to non-encrypted ports
+    let protocols = global_configuration
+      .and_then(|c| get_entry!("protocols", c))
+      .map(|e| e.values.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+      .unwrap_or(vec!["h1", "h2"]);
+    if !protocols.contains(&"h1") {
+      http_ports.clear();
+    }
+
+    let listeners = http_ports
+      .difference(&https_ports)
+      .map(|port| format!("http:{port}"))
+      .chain(https_ports.iter().map(|port| format!("https:{port}")))
+      .collect();
+
+    Self {
+      pid: None,
+      blocks,
+      listeners,
+      certificates,
+    }
+  }
+
+  /// Describes the changes between this snapshot and the new one
+  pub fn diff(&self, new: &Self) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for (block, directives) in &self.blocks {
+      match new.blocks.get(block) {
+        None => changes.push(format!("- {block} removed")),
+        Some(new_directives) => {
+          let changed_directives = directives
+            .keys()
+            .chain(new_directives.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|name| directives.get(*name) != new_directives.get(*name))
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+          if !changed_directives.is_empty() {
+            changes.push(format!(
+              "~ {block} changed (directives: {})",
+              changed_directives.join(", ")
+            ));
+          }
+        }
+      }
+    }
+    for block in new.blocks.keys().filter(|block| !self.blocks.contains_key(*block)) {
+      changes.push(format!("+ {block} added"));
+    }
+
+    for listener in self.listeners.difference(&new.listeners) {
+      changes.push(format!("- listener {listener} removed"));
+    }
+    for listener in new.listeners.difference(&self.listeners) {
+      changes.push(format!("+ listener {listener} added"));
+    }
+
+    for (host, certificate) in &self.certificates {
+      match new.certificates.get(host) {
+        None => changes.push(format!("- certificate for {host} removed ({certificate})")),
+        Some(new_certificate) if new_certificate != certificate => changes.push(format!(
+          "~ certificate for {host} changed ({certificate} -> {new_certificate})"
+        )),
+        Some(_) => (),
+      }
+    }
+    for (host, certificate) in &new.certificates {
+      if !self.certificates.contains_key(host) {
+        changes.push(format!("+ certificate for {host} added ({certificate})"));
+      }
+    }
+
+    changes
+  }
+
+  /// Checks whether the global configuration differs from the one in the new snapshot
+  pub fn global_configuration_differs(&self, new: &Self) -> bool {
+    self.blocks.get(GLOBAL_BLOCK_NAME) != new.blocks.get(GLOBAL_BLOCK_NAME)
+  }
+
+  /// Loads the configuration snapshot from a file
+  pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+  }
+
+  /// Saves the configuration snapshot of the running server into a file
+  pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let snapshot = Self {
+      pid: Some(std::process::id()),
+      ..self.clone()
+    };
+    std::fs::write(path, serde_json::to_vec(&snapshot)?)?;
+    Ok(())
+  }
+}
+
+/// Resolves the path to the configuration snapshot of the server running with the specified configuration file
+pub fn resolve_snapshot_path(configuration_path: &Path) -> Option<PathBuf> {
+  let configuration_path = std::fs::canonicalize(configuration_path).ok()?;
+  let mut snapshot_path = dirs::data_local_dir()?;
+  snapshot_path.push("ferron-snapshots");
+  snapshot_path.push(format!(
+    "{:016x}.json",
+    xxhash_rust::xxh3::xxh3_64(configuration_path.as_os_str().as_encoded_bytes())
+  ));
+  Some(snapshot_path)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+  use crate::config::{ServerConfigurationEntry, ServerConfigurationFilters};
+
+  fn make_configuration(
+    hostname: Option<&str>,
+    entries: Vec<(&str, Vec<ServerConfigurationValue>)>,
+  ) -> ServerConfiguration {
+    ServerConfiguration {
+      entries: entries
+        .into_iter()
+        .map(|(name, values)| {
+          (
+            name.to_string(),
+            ServerConfigurationEntries {
+              inner: vec![ServerConfigurationEntry {
+                values,
+                props: HashMap::new(),
+              }],
+            },
+          )
+        })
+        .collect(),
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: hostname.map(|h| h.to_string()),
+        ip: None,
+        port: None,
+        condition: None,
+        error_handler_status: None,
+      },
+      modules: vec![],
+      observability: ferron_common::observability::ObservabilityBackendChannels::new(),
+    }
+  }
+
+  #[test]
+  fn test_snapshot_listeners_and_certificates() {
+    let snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(
+        None,
+        vec![("root", vec![ServerConfigurationValue::String("/var/www".to_string())])],
+      ),
+      make_configuration(Some("example.com"), vec![]),
+      make_configuration(
+        Some("example.org"),
+        vec![(
+          "tls",
+          vec![
+            ServerConfigurationValue::String("/etc/cert.pem".to_string()),
+            ServerConfigurationValue::String("/etc/key.pem".to_string()),
+          ],
+        )],
+      ),
+    ]);
+    assert_eq!(
+      snapshot.listeners,
+      BTreeSet::from(["http:80".to_string(), "https:443".to_string()])
+    );
+    assert_eq!(
+      snapshot
+        .certificates
+        .get("\"example.com\" host block")
+        .map(|c| c.as_str()),
+      Some("automatic TLS")
+    );
+    assert_eq!(
+      snapshot
+        .certificates
+        .get("\"example.org\" host block")
+        .map(|c| c.as_str()),
+      Some("/etc/cert.pem (private key: /etc/key.pem)")
+    );
+  }
+
+  #[test]
+  fn test_snapshot_diff() {
+    let old_snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(
+        Some("example.com"),
+        vec![("root", vec![ServerConfigurationValue::String("/var/www".to_string())])],
+      ),
+      make_configuration(Some("example.org"), vec![]),
+    ]);
+    let new_snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(
+        Some("example.com"),
+        vec![
+          ("root", vec![ServerConfigurationValue::String("/srv/www".to_string())]),
+          ("auto_tls", vec![ServerConfigurationValue::Bool(false)]),
+        ],
+      ),
+      make_configuration(Some("example.net"), vec![]),
+    ]);
+    assert_eq!(
+      old_snapshot.diff(&new_snapshot),
+      vec![
+        "~ \"example.com\" host block changed (directives: auto_tls, root)".to_string(),
+        "- \"example.org\" host block removed".to_string(),
+        "+ \"example.net\" host block added".to_string(),
+        "- certificate for \"example.com\" host block removed (automatic TLS)".to_string(),
+        "- certificate for \"example.org\" host block removed (automatic TLS)".to_string(),
+        "+ certificate for \"example.net\" host block added (automatic TLS)".to_string(),
+      ]
+    );
+    assert!(old_snapshot.diff(&old_snapshot).is_empty());
+  }
+
+  #[test]
+  fn test_snapshot_global_configuration_differs() {
+    let old_snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(None, vec![("timeout", vec![ServerConfigurationValue::Integer(300000)])]),
+      make_configuration(Some("example.com"), vec![]),
+    ]);
+    let host_changed_snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(None, vec![("timeout", vec![ServerConfigurationValue::Integer(300000)])]),
+      make_configuration(
+        Some("example.com"),
+        vec![("root", vec![ServerConfigurationValue::String("/var/www".to_string())])],
+      ),
+    ]);
+    let global_changed_snapshot = ConfigurationSnapshot::new(&[
+      make_configuration(None, vec![("timeout", vec![ServerConfigurationValue::Integer(60000)])]),
+      make_configuration(Some("example.com"), vec![]),
+    ]);
+    assert!(!old_snapshot.global_configuration_differs(&host_changed_snapshot));
+    assert!(old_snapshot.global_configuration_differs(&global_changed_snapshot));
+    assert_eq!(
+      old_snapshot.diff(&global_changed_snapshot),
+      vec!["~ global configuration changed (directives: timeout)".to_string()]
+    );
+  }
+}
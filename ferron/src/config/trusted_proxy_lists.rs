@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::{Method, Request, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+
+use crate::config::{ServerConfiguration, ServerConfigurationValue};
+use crate::setup::acme::build_raw_rustls_client_config;
+
+/// The timeout for obtaining a trusted proxy IP address list
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size of a trusted proxy IP address list
+const MAX_LIST_SIZE: usize = 1048576;
+
+/// Extracts the IP addresses and CIDR ranges from an IP address list, which can be either a plain text list
+/// (like the Cloudflare IP ranges) or a JSON document (like the Fastly public IP list).
+fn extract_ip_ranges(list: &str) -> Vec<String> {
+  list
+    .split(|c: char| !(c.is_ascii_hexdigit() || matches!(c, '.' | ':' | '/')))
+    .filter(|token| match token.split_once('/') {
+      Some((address, prefix_length)) => match (address.parse::<IpAddr>(), prefix_length.parse::<u8>()) {
+        (Ok(IpAddr::V4(_)), Ok(prefix_length)) => prefix_length <= 32,
+        (Ok(IpAddr::V6(_)), Ok(prefix_length)) => prefix_length <= 128,
+        _ => false,
+      },
+      None => token.parse::<IpAddr>().is_ok(),
+    })
+    .map(|token| token.to_string())
+    .collect()
+}
+
+/// Obtains the trusted proxy IP address list. This function is executed on the Tokio runtime.
+async fn fetch_ip_ranges(
+  client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+  url: Uri,
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+  let request = Request::builder()
+    .method(Method::GET)
+    .uri(url)
+    .body(Empty::<Bytes>::new())?;
+  let response = client.request(request).await?;
+  if !response.status().is_success() {
+    Err(anyhow::anyhow!(
+      "The server responded with the {} status code",
+      response.status()
+    ))?
+  }
+  let response_body = Limited::new(response.into_body(), MAX_LIST_SIZE)
+    .collect()
+    .await?
+    .to_bytes();
+  Ok(extract_ip_ranges(&String::from_utf8_lossy(&response_body)))
+}
+
+/// Loads the trusted proxy IP address lists specified via the `url` prop of the `real_ip_trusted_proxies` directive,
+/// and adds the obtained IP addresses and CIDR ranges to the directive values.
+pub fn load_trusted_proxy_lists(
+  mut server_configurations: Vec<ServerConfiguration>,
+  secondary_runtime: &tokio::runtime::Runtime,
+) -> Result<Vec<ServerConfiguration>, Box<dyn Error + Send + Sync>> {
+  // The lists are shared by the configurations, so each list is obtained only once
+  let mut ip_range_lists: HashMap<String, Arc<Vec<String>>> = HashMap::new();
+
+  for server_configuration in &mut server_configurations {
+    let Some(entries) = server_configuration.entries.get_mut("real_ip_trusted_proxies") else {
+      continue;
+    };
+    for entry in &mut entries.inner {
+      let Some(url) = entry.props.get("url").and_then(|v| v.as_str()).map(|v| v.to_string()) else {
+        continue;
+      };
+      let ip_ranges = match ip_range_lists.get(&url) {
+        Some(ip_ranges) => ip_ranges.clone(),
+        None => {
+          let parsed_url = url
+            .parse::<Uri>()
+            .map_err(|err| anyhow::anyhow!("Failed to parse the trusted proxy IP address list URL: {err}"))?;
+          let client = Client::builder(TokioExecutor::new()).build(
+            hyper_rustls::HttpsConnectorBuilder::new()
+              .with_tls_config(build_raw_rustls_client_config(
+                false,
+                Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+              )?)
+              .https_or_http()
+              .enable_http1()
+              .enable_http2()
+              .build(),
+          );
+          // The HTTP client is driven by the Tokio runtime
+          let ip_ranges = secondary_runtime
+            .block_on(tokio::time::timeout(FETCH_TIMEOUT, fetch_ip_ranges(client, parsed_url)))
+            .map_err(|_| anyhow::anyhow!("The trusted proxy IP address list request to \"{url}\" has timed out"))?
+            .map_err(|err| anyhow::anyhow!("Can't obtain the trusted proxy IP address list from \"{url}\": {err}"))?;
+          if ip_ranges.is_empty() {
+            Err(anyhow::anyhow!(
+              "The trusted proxy IP address list from \"{url}\" doesn't contain any IP addresses"
+            ))?
+          }
+          let ip_ranges = Arc::new(ip_ranges);
+          ip_range_lists.insert(url, ip_ranges.clone());
+          ip_ranges
+        }
+      };
+      entry.values.extend(
+        ip_ranges
+          .iter()
+          .map(|ip_range| ServerConfigurationValue::String(ip_range.clone())),
+      );
+    }
+  }
+
+  Ok(server_configurations)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_extract_ip_ranges_from_text() {
+    assert_eq!(
+      extract_ip_ranges("173.245.48.0/20\n103.21.244.0/22\n2400:cb00::/32\n"),
+      vec!["173.245.48.0/20", "103.21.244.0/22", "2400:cb00::/32"]
+    );
+  }
+
+  #[test]
+  fn test_extract_ip_ranges_from_json() {
+    assert_eq!(
+      extract_ip_ranges(r#"{"addresses":["23.235.32.0/20","43.249.72.0/22"],"ipv6_addresses":["2a04:4e40::/32"]}"#),
+      vec!["23.235.32.0/20", "43.249.72.0/22", "2a04:4e40::/32"]
+    );
+    assert!(extract_ip_ranges(r#"{"invalid":"10.0.0.0/33"}"#).is_empty());
+  }
+}
@@ -1,11 +1,15 @@
+#[cfg(not(feature = "runtime-vibeio"))]
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Display;
 use std::net::{IpAddr, SocketAddr};
+#[cfg(not(feature = "runtime-vibeio"))]
+use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Duration;
 #[cfg(not(feature = "runtime-vibeio"))]
 use std::time::SystemTime;
+use std::time::{Duration, Instant};
 
 use crate::acme::ACME_TLS_ALPN_NAME;
 use crate::config::ServerConfigurations;
@@ -14,19 +18,32 @@ use crate::listener_handler_communication::ConnectionData;
 use crate::request_handler::request_handler;
 #[cfg(feature = "runtime-monoio")]
 use crate::util::SendAsyncIo;
-use crate::util::{read_proxy_header, MultiCancel};
+#[cfg(not(feature = "runtime-vibeio"))]
+use crate::util::{
+  encode_h2c_upgrade_headers_frame, is_h2c_upgrade_request, read_http2_preface, H2cUpgradedIo, Rewind,
+};
+use crate::util::{
+  match_hostname, parse_client_hello, read_proxy_header, ClientHelloInfo, ClientHelloParseResult, ConnectionKeepAlive,
+  KeepAlivePolicy, MultiCancel, RequestHeaderLimits, TlsServerConfigs,
+};
 use arc_swap::ArcSwap;
 use async_channel::{Receiver, Sender};
 #[cfg(not(feature = "runtime-vibeio"))]
 use bytes::{Buf, Bytes};
 #[cfg(feature = "runtime-vibeio")]
 use core_affinity::CoreId;
+#[cfg(not(feature = "runtime-vibeio"))]
+use ferron_common::get_entry;
 use ferron_common::logging::LogMessage;
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+use ferron_common::util::receive_warmup_task;
+#[cfg(not(feature = "runtime-vibeio"))]
+use futures_util::future::Either;
 use http_body_util::BodyExt;
 #[cfg(not(feature = "runtime-vibeio"))]
 use http_body_util::StreamBody;
 #[cfg(not(feature = "runtime-vibeio"))]
-use hyper::body::{Frame, Incoming};
+use hyper::body::{Body, Frame, Incoming};
 #[cfg(not(feature = "runtime-vibeio"))]
 use hyper::service::service_fn;
 use hyper::Request;
@@ -63,6 +80,16 @@ static HTTP3_INVALID_HEADERS: [hyper::header::HeaderName; 5] = [
   hyper::header::UPGRADE,
 ];
 
+/// The maximum time, for which the TLS ClientHello message is awaited for SNI passthrough routing
+const SNI_PEEK_TIMEOUT: Duration = Duration::from_secs(10);
+/// The interval between attempts to peek the complete TLS ClientHello message
+const SNI_PEEK_INTERVAL: Duration = Duration::from_millis(10);
+/// The size of the buffer for peeking the TLS ClientHello message
+const SNI_PEEK_BUFFER_SIZE: usize = 32768;
+/// The maximum time, for which the beginning of a non-encrypted connection is awaited to detect HTTP/2 over cleartext (h2c)
+#[cfg(not(feature = "runtime-vibeio"))]
+const H2C_PREFACE_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// A struct holding reloadable data for handler threads
 #[allow(clippy::type_complexity)]
 pub struct ReloadableHandlerData {
@@ -72,14 +99,18 @@ pub struct ReloadableHandlerData {
   pub acme_http_01_resolvers: AcmeHttp01Resolvers,
   /// Server configurations
   pub configurations: Arc<ServerConfigurations>,
-  /// TLS configurations
-  pub tls_configs: Arc<HashMap<(Option<IpAddr>, u16), Arc<ServerConfig>>>,
+  /// TLS configurations (with TLS configurations for hosts with their own TLS settings)
+  pub tls_configs: Arc<HashMap<(Option<IpAddr>, u16), Arc<TlsServerConfigs>>>,
   /// Whether HTTP/3 is enabled
   pub http3_enabled: bool,
   /// Whether PROXY protocol is enabled
   pub enable_proxy_protocol: bool,
+  /// SNI passthrough routes (hostnames and backend server addresses)
+  pub sni_routes: Arc<Vec<(String, Option<String>, String)>>,
   /// QUIC TLS configurations
   pub quic_tls_configs: Arc<HashMap<(Option<IpAddr>, u16), Arc<quinn::ServerConfig>>>,
+  /// The time, for which connections finish in-flight requests during a graceful shutdown before they're aborted
+  pub graceful_shutdown_timeout: Option<Duration>,
 }
 
 type AcmeHttp01Resolvers = Arc<tokio::sync::RwLock<Vec<crate::acme::Http01DataLock>>>;
@@ -203,17 +234,24 @@ async fn http_handler_fn(
                 break;
             }
         }
+        Some(warmup_task) = receive_warmup_task() => {
+            // Warmup tasks (like pre-establishing connections to backend servers) are run on the handler threads
+            crate::runtime::spawn(warmup_task());
+            continue;
+        }
     };
     let ReloadableHandlerData {
       configurations,
       tls_configs,
       http3_enabled,
       acme_tls_alpn_01_configs,
-      acme_http_01_resolvers,
       enable_proxy_protocol,
       quic_tls_configs,
+      sni_routes,
+      ..
     } = &**reloadable_data.load();
     let quic_tls_configs = quic_tls_configs.clone();
+    let sni_routes = sni_routes.clone();
     let configurations = configurations.clone();
     let tls_config = if matches!(
       conn_data.connection,
@@ -247,15 +285,32 @@ async fn http_handler_fn(
             .cloned()
         })
     };
-    let acme_http_01_resolvers = acme_http_01_resolvers.clone();
     let connections_references_cloned = connections_references.clone();
     let shutdown_rx_clone = shutdown_rx.clone();
     let http3_enabled = *http3_enabled;
     let enable_proxy_protocol = *enable_proxy_protocol;
     let graceful_shutdown_token = graceful_shutdown_token.load().clone();
+    let reloadable_data_cloned = reloadable_data.clone();
     crate::runtime::spawn(async move {
       match conn_data.connection {
         crate::listener_handler_communication::Connection::Tcp(tcp_stream) => {
+          // The SNI passthrough routes are matched before the TLS handshake, since the ClientHello message is only peeked
+          let sni_route_backend = if tls_config.is_some() && !sni_routes.is_empty() {
+            peek_client_hello(&tcp_stream).await.and_then(|client_hello| {
+              sni_routes
+                .iter()
+                .find(|(hostname, alpn, _)| {
+                  match_hostname(Some(hostname), client_hello.server_name.as_deref())
+                    && alpn
+                      .as_ref()
+                      .is_none_or(|alpn| client_hello.alpn_protocols.contains(alpn))
+                })
+                .map(|(_, _, backend)| backend.clone())
+            })
+          } else {
+            None
+          };
+
           // Toggle O_NONBLOCK for TCP stream, when using Monoio.
           // Unset it when io_uring is enabled, and set it otherwise.
           #[cfg(feature = "runtime-monoio")]
@@ -271,6 +326,20 @@ async fn http_handler_fn(
               return;
             }
           };
+          if let Some(backend_address) = sni_route_backend {
+            sni_passthrough_handler_fn(
+              tcp_stream,
+              backend_address,
+              configurations,
+              connections_references_cloned,
+              shutdown_rx_clone,
+              graceful_shutdown_token,
+              reloadable_data_cloned,
+            )
+            .await;
+            return;
+          }
+
           let encrypted = tls_config.is_some();
           http_tcp_handler_fn(
             tcp_stream,
@@ -281,10 +350,10 @@ async fn http_handler_fn(
             http3_enabled && encrypted,
             connections_references_cloned,
             acme_tls_alpn_01_config,
-            acme_http_01_resolvers,
             enable_proxy_protocol,
             shutdown_rx_clone,
             graceful_shutdown_token,
+            reloadable_data_cloned,
           )
           .await;
         }
@@ -298,6 +367,7 @@ async fn http_handler_fn(
             connections_references_cloned,
             shutdown_rx_clone,
             graceful_shutdown_token,
+            reloadable_data_cloned,
           )
           .await;
         }
@@ -352,23 +422,25 @@ fn get_http2_settings(configurations: &ServerConfigurations) -> Http2Settings {
     initial_window_size: global_configuration
       .as_deref()
       .and_then(|c| get_value!("h2_initial_window_size", c))
-      .and_then(|v| v.as_i128())
+      .and_then(|v| v.as_size())
       .map(|v| v as u32),
     max_frame_size: global_configuration
       .as_deref()
       .and_then(|c| get_value!("h2_max_frame_size", c))
-      .and_then(|v| v.as_i128())
+      .and_then(|v| v.as_size())
       .map(|v| v as u32),
     max_concurrent_streams: global_configuration
       .as_deref()
       .and_then(|c| get_value!("h2_max_concurrent_streams", c))
       .and_then(|v| v.as_i128())
       .map(|v| v as u32),
+    // The maximum header list size is aligned with the request header limits, unless it's specified explicitly
     max_header_list_size: global_configuration
       .as_deref()
       .and_then(|c| get_value!("h2_max_header_list_size", c))
-      .and_then(|v| v.as_i128())
-      .map(|v| v as u32),
+      .and_then(|v| v.as_size())
+      .map(|v| v as u32)
+      .or_else(|| Some(get_request_header_limits(configurations).max_header_list_size())),
     enable_connect_protocol: global_configuration
       .as_deref()
       .and_then(|c| get_value!("h2_enable_connect_protocol", c))
@@ -377,6 +449,24 @@ fn get_http2_settings(configurations: &ServerConfigurations) -> Http2Settings {
   }
 }
 
+/// Checks whether the protocol is enabled with the `protocols` directive
+#[cfg(not(feature = "runtime-vibeio"))]
+#[inline]
+fn is_protocol_enabled(configurations: &ServerConfigurations, protocol: &str) -> bool {
+  configurations
+    .find_global_configuration()
+    .as_deref()
+    .and_then(|c| get_entry!("protocols", c))
+    .map_or(matches!(protocol, "h1" | "h2"), |entry| {
+      entry.values.iter().any(|v| v.as_str() == Some(protocol))
+    })
+}
+
+#[inline]
+fn get_request_header_limits(configurations: &ServerConfigurations) -> RequestHeaderLimits {
+  RequestHeaderLimits::from_configuration(configurations.find_global_configuration().as_deref())
+}
+
 #[inline]
 fn get_http3_port(http3_enabled: bool, server_address: SocketAddr) -> Option<u16> {
   if http3_enabled {
@@ -386,6 +476,25 @@ fn get_http3_port(http3_enabled: bool, server_address: SocketAddr) -> Option<u16
   }
 }
 
+/// Obtains the server name sent by the client in the TLS SNI extension of a QUIC connection
+fn quic_tls_sni(connection: &quinn::Connection) -> Option<String> {
+  connection
+    .handshake_data()?
+    .downcast::<quinn::crypto::rustls::HandshakeData>()
+    .ok()?
+    .server_name
+}
+
+/// Obtains the application protocol negotiated using ALPN for a QUIC connection
+fn quic_tls_alpn(connection: &quinn::Connection) -> Option<String> {
+  connection
+    .handshake_data()?
+    .downcast::<quinn::crypto::rustls::HandshakeData>()
+    .ok()?
+    .protocol
+    .map(|protocol| String::from_utf8_lossy(&protocol).into_owned())
+}
+
 #[inline]
 fn empty_acme_http_01_resolvers() -> AcmeHttp01Resolvers {
   Arc::new(tokio::sync::RwLock::new(Vec::new()))
@@ -412,6 +521,85 @@ async fn log_connection_accept_error(configurations: &ServerConfigurations, err:
 }
 
 #[inline]
+/// Waits for the connection to finish in-flight requests after the graceful shutdown is initiated. If the connection
+/// doesn't finish within the graceful shutdown timeout, it's aborted, and `None` is returned.
+async fn drain_connection<F: std::future::Future>(
+  connection_future: F,
+  reloadable_data: &ArcSwap<ReloadableHandlerData>,
+) -> Option<F::Output> {
+  // The handler data is loaded after the graceful shutdown is initiated, so that the timeout from the new configuration is used
+  let handler_data = reloadable_data.load_full();
+  let result = match handler_data.graceful_shutdown_timeout {
+    Some(graceful_shutdown_timeout) => crate::runtime::timeout(graceful_shutdown_timeout, connection_future)
+      .await
+      .ok(),
+    None => Some(connection_future.await),
+  };
+  record_drained_connection(&handler_data.configurations, result.is_some()).await;
+  result
+}
+
+/// Records a connection closed during the graceful shutdown in the metrics
+async fn record_drained_connection(configurations: &ServerConfigurations, drained: bool) {
+  let Some(global_configuration) = configurations.find_global_configuration() else {
+    return;
+  };
+  if global_configuration.observability.metric_channels.is_empty() {
+    return;
+  }
+  MetricsMultiSender::new_multiple(global_configuration.observability.metric_channels.clone())
+    .send(Metric::new(
+      "ferron.http.server.drained_connections",
+      vec![(
+        "ferron.http.connection.drain_result",
+        MetricAttributeValue::String(if drained { "drained" } else { "aborted" }.to_string()),
+      )],
+      MetricType::Counter,
+      MetricValue::U64(1),
+      Some("{connection}"),
+      Some("Number of connections closed during a graceful shutdown."),
+    ))
+    .await;
+}
+
+/// Creates a client connection tracker enforcing the keep-alive policy
+fn create_connection_keepalive(
+  configurations: &ServerConfigurations,
+  server_address: SocketAddr,
+) -> Arc<ConnectionKeepAlive> {
+  let global_configuration = configurations.find_global_configuration();
+  Arc::new(ConnectionKeepAlive::new(
+    KeepAlivePolicy::from_configuration(global_configuration.as_deref()),
+    global_configuration
+      .as_ref()
+      .filter(|c| !c.observability.metric_channels.is_empty())
+      .map(|c| MetricsMultiSender::new_multiple(c.observability.metric_channels.clone())),
+    server_address.port(),
+  ))
+}
+
+/// Serves the client connection, and closes it gracefully when the keep-alive policy requires it
+async fn serve_with_keepalive<F>(
+  http_future: &mut F,
+  keepalive: &ConnectionKeepAlive,
+  close: impl FnOnce(&mut F),
+) -> F::Output
+where
+  F: std::future::Future + Unpin,
+{
+  crate::runtime::select! {
+    biased;
+
+    result = &mut *http_future => {
+      return result;
+    }
+    _ = keepalive.closed() => {
+      close(http_future);
+    }
+  }
+  http_future.await
+}
+
 async fn log_http_connection_error(configurations: &ServerConfigurations, protocol: &str, err: impl Display) {
   log_handler_error(configurations, format!("Error serving {protocol} connection: {err}")).await;
 }
@@ -477,7 +665,7 @@ async fn maybe_read_proxy_protocol_header(
 #[inline]
 async fn maybe_accept_tls_stream(
   tcp_stream: HttpTcpStream,
-  tls_config: Option<Arc<ServerConfig>>,
+  tls_config: Option<Arc<TlsServerConfigs>>,
   acme_tls_alpn_01_config: Option<Arc<ServerConfig>>,
   configurations: &Arc<ServerConfigurations>,
 ) -> Option<MaybeTlsStream> {
@@ -508,6 +696,8 @@ async fn maybe_accept_tls_stream(
     }
   }
 
+  // The TLS configuration is selected based on the server name, since hosts can have their own TLS settings
+  let tls_config = tls_config.select(start_handshake.client_hello().server_name());
   match start_handshake.into_stream(tls_config).await {
     Ok(tls_stream) => Some(MaybeTlsStream::Tls(tls_stream)),
     Err(err) => {
@@ -517,6 +707,114 @@ async fn maybe_accept_tls_stream(
   }
 }
 
+/// TCP stream, from which the TLS ClientHello message is peeked for SNI passthrough routing
+#[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+type PeekableTcpStream = std::net::TcpStream;
+
+#[cfg(feature = "runtime-tokio")]
+type PeekableTcpStream = TcpStream;
+
+/// Peeks the server name (SNI) and the application protocols (ALPN) from the TLS ClientHello message,
+/// without consuming the data from the TCP stream.
+/// Returns `None`, if the ClientHello message doesn't have the server name, or if it didn't arrive in time.
+async fn peek_client_hello(tcp_stream: &PeekableTcpStream) -> Option<ClientHelloInfo> {
+  // The standard library TCP stream is peeked in the non-blocking mode, so that the handler thread isn't blocked
+  #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+  tcp_stream.set_nonblocking(true).ok()?;
+
+  let mut buffer = vec![0u8; SNI_PEEK_BUFFER_SIZE];
+  let deadline = Instant::now() + SNI_PEEK_TIMEOUT;
+  loop {
+    #[cfg(any(feature = "runtime-vibeio", feature = "runtime-monoio"))]
+    let result = tcp_stream.peek(&mut buffer);
+    #[cfg(feature = "runtime-tokio")]
+    let result = crate::runtime::timeout(
+      deadline.saturating_duration_since(Instant::now()),
+      tcp_stream.peek(&mut buffer),
+    )
+    .await
+    .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()));
+
+    match result {
+      Ok(0) => return None,
+      Ok(length) => match parse_client_hello(&buffer[..length]) {
+        ClientHelloParseResult::ClientHello(client_hello) => {
+          return Some(client_hello).filter(|client_hello| client_hello.server_name.is_some())
+        }
+        ClientHelloParseResult::Incomplete if length < buffer.len() => (),
+        _ => return None,
+      },
+      Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => (),
+      Err(_) => return None,
+    }
+
+    if Instant::now() >= deadline {
+      return None;
+    }
+    // Peeking doesn't consume the data, so wait for more data to arrive before peeking again
+    crate::runtime::sleep(SNI_PEEK_INTERVAL).await;
+  }
+}
+
+/// SNI passthrough handler function, which forwards the TCP connection to the backend server without terminating TLS
+#[inline]
+async fn sni_passthrough_handler_fn(
+  tcp_stream: TcpStream,
+  backend_address: String,
+  configurations: Arc<ServerConfigurations>,
+  connection_reference: Arc<()>,
+  shutdown_rx: CancellationToken,
+  graceful_shutdown_token: Arc<CancellationToken>,
+  reloadable_data: Arc<ArcSwap<ReloadableHandlerData>>,
+) {
+  let _connection_reference = Arc::downgrade(&connection_reference);
+  let Some(mut tcp_stream) = convert_tcp_stream_for_runtime(tcp_stream, &configurations).await else {
+    return;
+  };
+  let backend_stream = match TcpStream::connect(backend_address.as_str()).await {
+    Ok(stream) => stream,
+    Err(err) => {
+      log_handler_error(
+        &configurations,
+        format!("Error connecting to the SNI passthrough backend server \"{backend_address}\": {err}"),
+      )
+      .await;
+      return;
+    }
+  };
+  let _ = backend_stream.set_nodelay(true);
+  let Some(mut backend_stream) = convert_tcp_stream_for_runtime(backend_stream, &configurations).await else {
+    return;
+  };
+
+  let passthrough_future = tokio::io::copy_bidirectional(&mut tcp_stream, &mut backend_stream);
+  let mut passthrough_future_pin = std::pin::pin!(passthrough_future);
+  let passthrough_result = crate::runtime::select! {
+    result = &mut passthrough_future_pin => {
+      result
+    }
+    _ = shutdown_rx.cancelled() => {
+        let Some(result) = drain_connection(passthrough_future_pin, &reloadable_data).await else {
+          return;
+        };
+        result
+    }
+    _ = graceful_shutdown_token.cancelled() => {
+        let Some(result) = drain_connection(passthrough_future_pin, &reloadable_data).await else {
+          return;
+        };
+        result
+    }
+  };
+  if let Err(err) = passthrough_result {
+    log_handler_error(
+      &configurations,
+      format!("Error forwarding the SNI passthrough connection to \"{backend_address}\": {err}"),
+    )
+    .await;
+  }
+}
+
 #[cfg(not(feature = "runtime-vibeio"))]
 #[inline]
 fn sanitize_http3_response_headers(response_headers: &mut hyper::HeaderMap) {
@@ -545,16 +843,17 @@ async fn http_tcp_handler_fn(
   client_address: SocketAddr,
   server_address: SocketAddr,
   configurations: Arc<ServerConfigurations>,
-  tls_config: Option<Arc<ServerConfig>>,
+  tls_config: Option<Arc<TlsServerConfigs>>,
   http3_enabled: bool,
   connection_reference: Arc<()>,
   acme_tls_alpn_01_config: Option<Arc<ServerConfig>>,
-  acme_http_01_resolvers: AcmeHttp01Resolvers,
   enable_proxy_protocol: bool,
   shutdown_rx: CancellationToken,
   graceful_shutdown_token: Arc<CancellationToken>,
+  reloadable_data: Arc<ArcSwap<ReloadableHandlerData>>,
 ) {
   let _connection_reference = Arc::downgrade(&connection_reference);
+  let keepalive = create_connection_keepalive(&configurations, server_address);
   let Some(tcp_stream) = convert_tcp_stream_for_runtime(tcp_stream, &configurations).await else {
     return;
   };
@@ -571,6 +870,8 @@ async fn http_tcp_handler_fn(
 
   if let MaybeTlsStream::Tls(tls_stream) = maybe_tls_stream {
     let alpn_protocol = tls_stream.get_ref().1.alpn_protocol();
+    let tls_sni = tls_stream.get_ref().1.server_name().map(String::from);
+    let tls_alpn = alpn_protocol.map(|protocol| String::from_utf8_lossy(protocol).into_owned());
     let is_http2 = alpn_protocol == Some("h2".as_bytes());
 
     #[cfg(feature = "runtime-tokio")]
@@ -600,7 +901,8 @@ async fn http_tcp_handler_fn(
         http2_builder.enable_connect_protocol();
       }
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
       let graceful_shutdown_token2 = CancellationToken::new();
       let connection_reference = _connection_reference.clone();
       let http_future = vibeio_http::Http2::new(tls_stream, h2_options)
@@ -611,17 +913,20 @@ async fn http_tcp_handler_fn(
             request_parts,
             request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
           );
-          let fut = request_handler(
+          let reloadable_data = reloadable_data_clone.load();
+          let fut = keepalive_clone.track_request(request_handler(
             request,
             client_address,
             server_address,
             true,
-            configurations_clone.clone(),
+            reloadable_data.configurations.clone(),
             get_http3_port(http3_enabled, server_address),
-            acme_http_01_resolvers.clone(),
+            reloadable_data.acme_http_01_resolvers.clone(),
             proxy_protocol_client_address,
             proxy_protocol_server_address,
-          );
+            tls_sni.clone(),
+            tls_alpn.clone(),
+          ));
           let connection_reference = connection_reference.clone();
           async move {
             let r = fut.await.map_err(|e| std::io::Error::other(e.to_string()));
@@ -631,16 +936,22 @@ async fn http_tcp_handler_fn(
         });
       let mut http_future_pin = std::pin::pin!(http_future);
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future_pin => {
+        result = serve_with_keepalive(&mut http_future_pin, &keepalive, |_| graceful_shutdown_token2.cancel()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
             graceful_shutdown_token2.cancel();
-            http_future_pin.await
+            let Some(result) = drain_connection(http_future_pin, &reloadable_data).await else {
+              return;
+            };
+            result
         }
         _ = graceful_shutdown_token.cancelled() => {
             graceful_shutdown_token2.cancel();
-          http_future_pin.await
+            let Some(result) = drain_connection(http_future_pin, &reloadable_data).await else {
+              return;
+            };
+            result
         }
       };
       if let Err(err) = http_future_result {
@@ -649,11 +960,19 @@ async fn http_tcp_handler_fn(
     } else {
       use vibeio_http::{Http1Options, HttpProtocol};
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
       let graceful_shutdown_token2 = CancellationToken::new();
       let connection_reference = _connection_reference.clone();
+
+      // The request header limits are enforced by the request handler, and vibeio-http's limits serve as a fallback
+      let header_limits = get_request_header_limits(&configurations);
+      let http1_options = Http1Options::default()
+        .max_header_size(header_limits.http1_max_buffer_size())
+        .max_header_count(header_limits.http1_max_headers());
+
       let mut http_future = Box::pin(
-        vibeio_http::Http1::new(tls_stream, Http1Options::default())
+        vibeio_http::Http1::new(tls_stream, http1_options)
           .graceful_shutdown_token(graceful_shutdown_token2.clone())
           .handle(move |request: Request<vibeio_http::Incoming>| {
             let (request_parts, request_body) = request.into_parts();
@@ -661,17 +980,20 @@ async fn http_tcp_handler_fn(
               request_parts,
               request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
             );
-            let fut = request_handler(
+            let reloadable_data = reloadable_data_clone.load();
+            let fut = keepalive_clone.track_request(request_handler(
               request,
               client_address,
               server_address,
               true,
-              configurations_clone.clone(),
+              reloadable_data.configurations.clone(),
               get_http3_port(http3_enabled, server_address),
-              acme_http_01_resolvers.clone(),
+              reloadable_data.acme_http_01_resolvers.clone(),
               proxy_protocol_client_address,
               proxy_protocol_server_address,
-            );
+              tls_sni.clone(),
+              tls_alpn.clone(),
+            ));
             let connection_reference = connection_reference.clone();
             async move {
               let r = fut.await.map_err(|e| std::io::Error::other(e.to_string()));
@@ -681,16 +1003,22 @@ async fn http_tcp_handler_fn(
           }),
       );
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future => {
+        result = serve_with_keepalive(&mut http_future, &keepalive, |_| graceful_shutdown_token2.cancel()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
             graceful_shutdown_token2.cancel();
-            http_future.await
+            let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+              return;
+            };
+            result
         }
         _ = graceful_shutdown_token.cancelled() => {
             graceful_shutdown_token2.cancel();
-          http_future.await
+            let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+              return;
+            };
+            result
         }
       };
       if let Err(err) = http_future_result {
@@ -734,7 +1062,8 @@ async fn http_tcp_handler_fn(
         http2_builder.enable_connect_protocol();
       }
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
       let mut http_future = http2_builder.serve_connection(
         io,
         service_fn(move |request: Request<Incoming>| {
@@ -743,30 +1072,39 @@ async fn http_tcp_handler_fn(
             request_parts,
             request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
           );
-          request_handler(
+          let reloadable_data = reloadable_data_clone.load();
+          keepalive_clone.track_request(request_handler(
             request,
             client_address,
             server_address,
             true,
-            configurations_clone.clone(),
+            reloadable_data.configurations.clone(),
             get_http3_port(http3_enabled, server_address),
-            acme_http_01_resolvers.clone(),
+            reloadable_data.acme_http_01_resolvers.clone(),
             proxy_protocol_client_address,
             proxy_protocol_server_address,
-          )
+            tls_sni.clone(),
+            tls_alpn.clone(),
+          ))
         }),
       );
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future => {
+        result = serve_with_keepalive(&mut http_future, &keepalive, |http_future| std::pin::Pin::new(http_future).graceful_shutdown()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
         _ = graceful_shutdown_token.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
       };
       if let Err(err) = http_future_result {
@@ -788,6 +1126,11 @@ async fn http_tcp_handler_fn(
         // The timer is neccessary for the header timeout to work to mitigate Slowloris.
         http1_builder.timer(MonoioTimer);
 
+        // The request header limits are enforced by the request handler, and Hyper's limits serve as a fallback
+        let header_limits = get_request_header_limits(&configurations);
+        http1_builder.max_buf_size(header_limits.http1_max_buffer_size());
+        http1_builder.max_headers(header_limits.http1_max_headers());
+
         http1_builder
       };
       #[cfg(feature = "runtime-tokio")]
@@ -797,10 +1140,16 @@ async fn http_tcp_handler_fn(
         // The timer is neccessary for the header timeout to work to mitigate Slowloris.
         http1_builder.timer(TokioTimer::new());
 
+        // The request header limits are enforced by the request handler, and Hyper's limits serve as a fallback
+        let header_limits = get_request_header_limits(&configurations);
+        http1_builder.max_buf_size(header_limits.http1_max_buffer_size());
+        http1_builder.max_headers(header_limits.http1_max_headers());
+
         http1_builder
       };
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
       let mut http_future = http1_builder
         .serve_connection(
           io,
@@ -810,31 +1159,40 @@ async fn http_tcp_handler_fn(
               request_parts,
               request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
             );
-            request_handler(
+            let reloadable_data = reloadable_data_clone.load();
+            keepalive_clone.track_request(request_handler(
               request,
               client_address,
               server_address,
               true,
-              configurations_clone.clone(),
+              reloadable_data.configurations.clone(),
               get_http3_port(http3_enabled, server_address),
-              acme_http_01_resolvers.clone(),
+              reloadable_data.acme_http_01_resolvers.clone(),
               proxy_protocol_client_address,
               proxy_protocol_server_address,
-            )
+              tls_sni.clone(),
+              tls_alpn.clone(),
+            ))
           }),
         )
         .with_upgrades();
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future => {
+        result = serve_with_keepalive(&mut http_future, &keepalive, |http_future| std::pin::Pin::new(http_future).graceful_shutdown()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
         _ = graceful_shutdown_token.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
       };
       if let Err(err) = http_future_result {
@@ -851,11 +1209,19 @@ async fn http_tcp_handler_fn(
     {
       use vibeio_http::{Http1Options, HttpProtocol};
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
       let connection_reference = _connection_reference.clone();
       let graceful_shutdown_token2 = CancellationToken::new();
-      let http1 = vibeio_http::Http1::new(stream, Http1Options::default())
-        .graceful_shutdown_token(graceful_shutdown_token2.clone());
+
+      // The request header limits are enforced by the request handler, and vibeio-http's limits serve as a fallback
+      let header_limits = get_request_header_limits(&configurations);
+      let http1_options = Http1Options::default()
+        .max_header_size(header_limits.http1_max_buffer_size())
+        .max_header_count(header_limits.http1_max_headers());
+
+      let http1 =
+        vibeio_http::Http1::new(stream, http1_options).graceful_shutdown_token(graceful_shutdown_token2.clone());
 
       #[cfg(target_os = "linux")]
       let mut http_future = Box::pin(http1.zerocopy().handle(move |request: Request<vibeio_http::Incoming>| {
@@ -864,17 +1230,20 @@ async fn http_tcp_handler_fn(
           request_parts,
           request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
         );
-        let fut = request_handler(
+        let reloadable_data = reloadable_data_clone.load();
+        let fut = keepalive_clone.track_request(request_handler(
           request,
           client_address,
           server_address,
           false,
-          configurations_clone.clone(),
+          reloadable_data.configurations.clone(),
           get_http3_port(http3_enabled, server_address),
-          acme_http_01_resolvers.clone(),
+          reloadable_data.acme_http_01_resolvers.clone(),
           proxy_protocol_client_address,
           proxy_protocol_server_address,
-        );
+          None,
+          None,
+        ));
         let connection_reference = connection_reference.clone();
         async move {
           let r = fut.await.map_err(|e| std::io::Error::other(e.to_string()));
@@ -889,17 +1258,20 @@ async fn http_tcp_handler_fn(
           request_parts,
           request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
         );
-        let fut = request_handler(
+        let reloadable_data = reloadable_data_clone.load();
+        let fut = keepalive_clone.track_request(request_handler(
           request,
           client_address,
           server_address,
           true,
-          configurations_clone.clone(),
+          reloadable_data.configurations.clone(),
           get_http3_port(http3_enabled, server_address),
-          acme_http_01_resolvers.clone(),
+          reloadable_data.acme_http_01_resolvers.clone(),
           proxy_protocol_client_address,
           proxy_protocol_server_address,
-        );
+          None,
+          None,
+        ));
         let connection_reference = connection_reference.clone();
         async move {
           let r = fut.await.map_err(|e| std::io::Error::other(e.to_string()));
@@ -908,16 +1280,22 @@ async fn http_tcp_handler_fn(
         }
       }));
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future => {
+        result = serve_with_keepalive(&mut http_future, &keepalive, |_| graceful_shutdown_token2.cancel()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
             graceful_shutdown_token2.cancel();
-            http_future.await
+            let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+              return;
+            };
+            result
         }
         _ = graceful_shutdown_token.cancelled() => {
             graceful_shutdown_token2.cancel();
-          http_future.await
+            let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+              return;
+            };
+            result
         }
       };
       if let Err(err) = http_future_result {
@@ -927,9 +1305,42 @@ async fn http_tcp_handler_fn(
     #[cfg(not(feature = "runtime-vibeio"))]
     {
       #[cfg(feature = "runtime-monoio")]
-      let io = MonoioIo::new(stream);
+      let mut io = MonoioIo::new(stream);
       #[cfg(feature = "runtime-tokio")]
-      let io = TokioIo::new(stream);
+      let mut io = TokioIo::new(stream);
+
+      // HTTP/2 over cleartext (h2c) with prior knowledge is detected by the HTTP/2 connection preface
+      let h2c_enabled = is_protocol_enabled(&configurations, "h2c");
+      let io = if h2c_enabled {
+        match crate::runtime::timeout(H2C_PREFACE_TIMEOUT, read_http2_preface(&mut io)).await {
+          Ok(Ok((true, preface))) => {
+            serve_h2c_connection(
+              Rewind::new(io, preface),
+              client_address,
+              server_address,
+              http3_enabled,
+              proxy_protocol_client_address,
+              proxy_protocol_server_address,
+              &configurations,
+              &shutdown_rx,
+              &graceful_shutdown_token,
+              &reloadable_data,
+              &keepalive,
+            )
+            .await;
+            return;
+          }
+          Ok(Ok((false, _))) if !is_protocol_enabled(&configurations, "h1") => return,
+          Ok(Ok((false, data))) => Rewind::new(io, data),
+          Ok(Err(err)) => {
+            log_http_connection_error(&configurations, "HTTP", err).await;
+            return;
+          }
+          Err(_) => return,
+        }
+      } else {
+        Rewind::new(io, Bytes::new())
+      };
 
       #[cfg(feature = "runtime-monoio")]
       let http1_builder = {
@@ -938,6 +1349,11 @@ async fn http_tcp_handler_fn(
         // The timer is neccessary for the header timeout to work to mitigate Slowloris.
         http1_builder.timer(MonoioTimer);
 
+        // The request header limits are enforced by the request handler, and Hyper's limits serve as a fallback
+        let header_limits = get_request_header_limits(&configurations);
+        http1_builder.max_buf_size(header_limits.http1_max_buffer_size());
+        http1_builder.max_headers(header_limits.http1_max_headers());
+
         http1_builder
       };
       #[cfg(feature = "runtime-tokio")]
@@ -947,44 +1363,78 @@ async fn http_tcp_handler_fn(
         // The timer is neccessary for the header timeout to work to mitigate Slowloris.
         http1_builder.timer(TokioTimer::new());
 
+        // The request header limits are enforced by the request handler, and Hyper's limits serve as a fallback
+        let header_limits = get_request_header_limits(&configurations);
+        http1_builder.max_buf_size(header_limits.http1_max_buffer_size());
+        http1_builder.max_headers(header_limits.http1_max_headers());
+
         http1_builder
       };
 
-      let configurations_clone = configurations.clone();
+      let reloadable_data_clone = reloadable_data.clone();
+      let keepalive_clone = keepalive.clone();
+      let h2c_upgrade = Rc::new(Cell::new(None));
+      let h2c_upgrade_clone = h2c_upgrade.clone();
       let mut http_future = http1_builder
         .serve_connection(
           io,
           service_fn(move |request: Request<Incoming>| {
             let (request_parts, request_body) = request.into_parts();
+
+            // The HTTP/1.1 request upgraded to HTTP/2 over cleartext (h2c) is served over HTTP/2 after the upgrade
+            if h2c_enabled && is_h2c_upgrade_request(&request_parts, !request_body.is_end_stream()) {
+              if let Some(headers_frame) = encode_h2c_upgrade_headers_frame(&request_parts) {
+                let mut request = Request::from_parts(request_parts, request_body);
+                h2c_upgrade_clone.set(Some((hyper::upgrade::on(&mut request), headers_frame)));
+                let response: Result<Response<http_body_util::combinators::BoxBody<Bytes, std::io::Error>>, _> =
+                  Response::builder()
+                    .status(hyper::StatusCode::SWITCHING_PROTOCOLS)
+                    .header(hyper::header::CONNECTION, "Upgrade")
+                    .header(hyper::header::UPGRADE, "h2c")
+                    .body(http_body_util::Empty::<Bytes>::new().map_err(|e| match e {}).boxed())
+                    .map_err(anyhow::Error::from);
+                return Either::Left(std::future::ready(response));
+              }
+            }
+
             let request = Request::from_parts(
               request_parts,
               request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
             );
-            request_handler(
+            let reloadable_data = reloadable_data_clone.load();
+            Either::Right(keepalive_clone.track_request(request_handler(
               request,
               client_address,
               server_address,
               false,
-              configurations_clone.clone(),
+              reloadable_data.configurations.clone(),
               get_http3_port(http3_enabled, server_address),
-              acme_http_01_resolvers.clone(),
+              reloadable_data.acme_http_01_resolvers.clone(),
               proxy_protocol_client_address,
               proxy_protocol_server_address,
-            )
+              None,
+              None,
+            )))
           }),
         )
         .with_upgrades();
       let http_future_result = crate::runtime::select! {
-        result = &mut http_future => {
+        result = serve_with_keepalive(&mut http_future, &keepalive, |http_future| std::pin::Pin::new(http_future).graceful_shutdown()) => {
           result
         }
         _ = shutdown_rx.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
         _ = graceful_shutdown_token.cancelled() => {
           std::pin::Pin::new(&mut http_future).graceful_shutdown();
-          http_future.await
+          let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+            return;
+          };
+          result
         }
       };
       if let Err(err) = http_future_result {
@@ -995,10 +1445,136 @@ async fn http_tcp_handler_fn(
         };
         log_http_connection_error(&configurations, "HTTP", error_to_log).await;
       }
+
+      if let Some((on_upgrade, headers_frame)) = h2c_upgrade.take() {
+        match on_upgrade.await {
+          Ok(upgraded) => {
+            serve_h2c_connection(
+              H2cUpgradedIo::new(upgraded, headers_frame),
+              client_address,
+              server_address,
+              http3_enabled,
+              proxy_protocol_client_address,
+              proxy_protocol_server_address,
+              &configurations,
+              &shutdown_rx,
+              &graceful_shutdown_token,
+              &reloadable_data,
+              &keepalive,
+            )
+            .await
+          }
+          Err(err) => log_http_connection_error(&configurations, "HTTP", err).await,
+        }
+      }
     }
   }
 }
 
+/// HTTP/2 over cleartext (h2c) handler function
+#[inline]
+#[cfg(not(feature = "runtime-vibeio"))]
+#[allow(clippy::too_many_arguments)]
+async fn serve_h2c_connection<I>(
+  io: I,
+  client_address: SocketAddr,
+  server_address: SocketAddr,
+  http3_enabled: bool,
+  proxy_protocol_client_address: Option<SocketAddr>,
+  proxy_protocol_server_address: Option<SocketAddr>,
+  configurations: &Arc<ServerConfigurations>,
+  shutdown_rx: &CancellationToken,
+  graceful_shutdown_token: &Arc<CancellationToken>,
+  reloadable_data: &Arc<ArcSwap<ReloadableHandlerData>>,
+  keepalive: &Arc<ConnectionKeepAlive>,
+) where
+  I: hyper::rt::Read + hyper::rt::Write + Unpin + 'static,
+{
+  #[cfg(feature = "runtime-monoio")]
+  let mut http2_builder = {
+    let mut http2_builder = hyper::server::conn::http2::Builder::new(MonoioExecutor);
+    http2_builder.timer(MonoioTimer);
+    http2_builder
+  };
+  #[cfg(feature = "runtime-tokio")]
+  let mut http2_builder = {
+    let mut http2_builder = hyper::server::conn::http2::Builder::new(TokioLocalExecutor);
+    http2_builder.timer(TokioTimer::new());
+    http2_builder
+  };
+
+  let http2_settings = get_http2_settings(configurations);
+  if let Some(initial_window_size) = http2_settings.initial_window_size {
+    http2_builder.initial_stream_window_size(initial_window_size);
+  }
+  if let Some(max_frame_size) = http2_settings.max_frame_size {
+    http2_builder.max_frame_size(max_frame_size);
+  }
+  if let Some(max_concurrent_streams) = http2_settings.max_concurrent_streams {
+    http2_builder.max_concurrent_streams(max_concurrent_streams);
+  }
+  if let Some(max_header_list_size) = http2_settings.max_header_list_size {
+    http2_builder.max_header_list_size(max_header_list_size);
+  }
+  if http2_settings.enable_connect_protocol {
+    http2_builder.enable_connect_protocol();
+  }
+
+  let reloadable_data_clone = reloadable_data.clone();
+  let keepalive_clone = keepalive.clone();
+  let mut http_future = http2_builder.serve_connection(
+    io,
+    service_fn(move |request: Request<Incoming>| {
+      let (request_parts, request_body) = request.into_parts();
+      let request = Request::from_parts(
+        request_parts,
+        request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
+      );
+      let reloadable_data = reloadable_data_clone.load();
+      keepalive_clone.track_request(request_handler(
+        request,
+        client_address,
+        server_address,
+        false,
+        reloadable_data.configurations.clone(),
+        get_http3_port(http3_enabled, server_address),
+        reloadable_data.acme_http_01_resolvers.clone(),
+        proxy_protocol_client_address,
+        proxy_protocol_server_address,
+        None,
+        None,
+      ))
+    }),
+  );
+  let http_future_result = crate::runtime::select! {
+    result = serve_with_keepalive(&mut http_future, &keepalive, |http_future| std::pin::Pin::new(http_future).graceful_shutdown()) => {
+      result
+    }
+    _ = shutdown_rx.cancelled() => {
+      std::pin::Pin::new(&mut http_future).graceful_shutdown();
+      let Some(result) = drain_connection(http_future, reloadable_data).await else {
+        return;
+      };
+      result
+    }
+    _ = graceful_shutdown_token.cancelled() => {
+      std::pin::Pin::new(&mut http_future).graceful_shutdown();
+      let Some(result) = drain_connection(http_future, reloadable_data).await else {
+        return;
+      };
+      result
+    }
+  };
+  if let Err(err) = http_future_result {
+    let error_to_log = if err.is_user() {
+      err.source().unwrap_or(&err)
+    } else {
+      &err
+    };
+    log_http_connection_error(configurations, "HTTP", error_to_log).await;
+  }
+}
+
 /// HTTP/3 handler function
 #[inline]
 #[cfg(feature = "runtime-vibeio")]
@@ -1013,6 +1589,7 @@ async fn http_quic_handler_fn(
   connection_reference: Arc<()>,
   shutdown_rx: CancellationToken,
   graceful_shutdown_token: Arc<CancellationToken>,
+  reloadable_data: Arc<ArcSwap<ReloadableHandlerData>>,
 ) {
   use vibeio_http::{Http3Options, HttpProtocol};
 
@@ -1044,8 +1621,10 @@ async fn http_quic_handler_fn(
     }
   };
 
+  let tls_sni = quic_tls_sni(&connection);
+  let tls_alpn = quic_tls_alpn(&connection);
   let _connection_reference = Arc::downgrade(&connection_reference);
-  let configurations_clone = configurations.clone();
+  let reloadable_data_clone = reloadable_data.clone();
   let graceful_shutdown_token2 = CancellationToken::new();
   let mut http_future = Box::pin(
     vibeio_http::Http3::new(h3_quinn::Connection::new(connection), Http3Options::default())
@@ -1056,16 +1635,19 @@ async fn http_quic_handler_fn(
           request_parts,
           request_body.map_err(|e| std::io::Error::other(e.to_string())).boxed(),
         );
+        let reloadable_data = reloadable_data_clone.load();
         let fut = request_handler(
           request,
           client_address,
           server_address,
           true,
-          configurations_clone.clone(),
+          reloadable_data.configurations.clone(),
           None,
           empty_acme_http_01_resolvers(),
           None,
           None,
+          tls_sni.clone(),
+          tls_alpn.clone(),
         );
         let connection_reference = connection_reference.clone();
         async move {
@@ -1081,11 +1663,17 @@ async fn http_quic_handler_fn(
     }
     _ = shutdown_rx.cancelled() => {
         graceful_shutdown_token2.cancel();
-        http_future.await
+        let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+          return;
+        };
+        result
     }
     _ = graceful_shutdown_token.cancelled() => {
         graceful_shutdown_token2.cancel();
-      http_future.await
+        let Some(result) = drain_connection(http_future, &reloadable_data).await else {
+          return;
+        };
+        result
     }
   };
   if let Err(err) = http_future_result {
@@ -1107,6 +1695,7 @@ async fn http_quic_handler_fn(
   connection_reference: Arc<()>,
   shutdown_rx: CancellationToken,
   graceful_shutdown_token: Arc<CancellationToken>,
+  reloadable_data: Arc<ArcSwap<ReloadableHandlerData>>,
 ) {
   let connection = if let Some(tls_config) = quic_tls_configs
     .get(&(Some(server_address.ip().to_canonical()), server_address.port()))
@@ -1136,15 +1725,24 @@ async fn http_quic_handler_fn(
     }
   };
 
+  let tls_sni = quic_tls_sni(&connection);
+  let tls_alpn = quic_tls_alpn(&connection);
   let connection_reference = Arc::downgrade(&connection_reference);
-  let mut h3_conn: h3::server::Connection<h3_quinn::Connection, Bytes> =
-    match h3::server::Connection::new(h3_quinn::Connection::new(connection)).await {
-      Ok(h3_conn) => h3_conn,
-      Err(err) => {
-        log_http_connection_error(&configurations, "HTTP/3", err).await;
-        return;
-      }
-    };
+  let mut h3_conn: h3::server::Connection<h3_quinn::Connection, Bytes> = match h3::server::builder()
+    .max_field_section_size(get_request_header_limits(&configurations).max_header_list_size().into())
+    .build(h3_quinn::Connection::new(connection))
+    .await
+  {
+    Ok(h3_conn) => h3_conn,
+    Err(err) => {
+      log_http_connection_error(&configurations, "HTTP/3", err).await;
+      return;
+    }
+  };
+
+  // The references to in-flight requests, and the token used to abort them after the graceful shutdown timeout
+  let request_references = Arc::new(());
+  let abort_token = CancellationToken::new();
 
   loop {
     match crate::runtime::select! {
@@ -1152,20 +1750,25 @@ async fn http_quic_handler_fn(
 
         _ = shutdown_rx.cancelled() => {
           h3_conn.shutdown(0).await.unwrap_or_default();
-          return;
+          break;
         }
         _ = graceful_shutdown_token.cancelled() => {
           h3_conn.shutdown(0).await.unwrap_or_default();
-          return;
+          break;
         }
         result = h3_conn.accept() => {
           result
         }
     } {
       Ok(Some(resolver)) => {
-        let configurations = configurations.clone();
+        // The server configurations are loaded for each request, so that the requests use the newest configuration
+        let configurations = reloadable_data.load().configurations.clone();
+        let tls_sni = tls_sni.clone();
+        let tls_alpn = tls_alpn.clone();
         let connection_reference = connection_reference.clone();
-        crate::runtime::spawn(async move {
+        let request_reference = request_references.clone();
+        let abort_token = abort_token.clone();
+        let request_future = async move {
           let _connection_reference = connection_reference;
           let (request, stream) = match resolver.resolve_request().await {
             Ok(resolved) => resolved,
@@ -1211,6 +1814,8 @@ async fn http_quic_handler_fn(
             empty_acme_http_01_resolvers(),
             None,
             None,
+            tls_sni,
+            tls_alpn,
           )
           .await
           {
@@ -1286,9 +1891,16 @@ async fn http_quic_handler_fn(
               }
             }
           }
+        };
+        crate::runtime::spawn(async move {
+          let _request_reference = request_reference;
+          crate::runtime::select! {
+            _ = abort_token.cancelled() => {}
+            _ = request_future => {}
+          }
         });
       }
-      Ok(None) => break,
+      Ok(None) => return,
       Err(err) => {
         if !err.is_h3_no_error() {
           log_http_connection_error(&configurations, "HTTP/3", err).await;
@@ -1297,4 +1909,14 @@ async fn http_quic_handler_fn(
       }
     }
   }
+
+  // Wait for in-flight requests to finish, and abort them after the graceful shutdown timeout
+  let requests_finished = async {
+    while Arc::strong_count(&request_references) > 1 {
+      crate::runtime::sleep(Duration::from_millis(100)).await;
+    }
+  };
+  if drain_connection(requests_finished, &reloadable_data).await.is_none() {
+    abort_token.cancel();
+  }
 }
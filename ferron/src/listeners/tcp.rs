@@ -1,8 +1,10 @@
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::{error::Error, time::Duration};
 
 use async_channel::Sender;
-use ferron_common::logging::LogMessage;
+use ferron_common::logging::{LogLevel, LogMessage};
 #[cfg(feature = "runtime-monoio")]
 use monoio::net::TcpListener;
 #[cfg(feature = "runtime-tokio")]
@@ -48,8 +50,54 @@ fn log_listening(encrypted: bool, address: SocketAddr) {
   println!("{} server is listening on {address}...", protocol_name(encrypted));
 }
 
+/// Sets the FreeBSD accept filter (`accf_http` for HTTP, `accf_data` for HTTPS) on a listening socket
+#[cfg(target_os = "freebsd")]
+fn set_accept_filter(socket: &socket2::Socket, encrypted: bool) -> Result<(), std::io::Error> {
+  use std::os::fd::AsRawFd;
+
+  let filter_name: &[u8] = if encrypted { b"dataready" } else { b"httpready" };
+  // Safety: `accept_filter_arg` is a plain C struct, for which the all-zero byte pattern is valid.
+  let mut accept_filter: libc::accept_filter_arg = unsafe { std::mem::zeroed() };
+  for (dest, src) in accept_filter.af_name.iter_mut().zip(filter_name) {
+    *dest = *src as libc::c_char;
+  }
+
+  // Safety: the pointer and the length passed to `setsockopt` refer to the `accept_filter` variable,
+  // which outlives the call, and the file descriptor is owned by the socket.
+  let result = unsafe {
+    libc::setsockopt(
+      socket.as_raw_fd(),
+      libc::SOL_SOCKET,
+      libc::SO_ACCEPTFILTER,
+      &accept_filter as *const libc::accept_filter_arg as *const libc::c_void,
+      std::mem::size_of::<libc::accept_filter_arg>() as libc::socklen_t,
+    )
+  };
+  if result == -1 {
+    Err(std::io::Error::last_os_error())
+  } else {
+    Ok(())
+  }
+}
+
+/// Checks whether the listener failed, because IPv6 isn't available (for example, in an IPv4-only FreeBSD jail)
+#[cfg(unix)]
+#[inline]
+fn is_ipv6_unavailable(address: SocketAddr, err: &std::io::Error) -> bool {
+  address.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    && (err.kind() == std::io::ErrorKind::AddrNotAvailable
+      || err.raw_os_error() == Some(libc::EAFNOSUPPORT)
+      || err.raw_os_error() == Some(libc::EPROTONOSUPPORT))
+}
+
 #[inline]
-fn build_tcp_listener(address: SocketAddr, tcp_buffer_sizes: (Option<usize>, Option<usize>)) -> ListenerResult {
+fn build_tcp_listener(
+  address: SocketAddr,
+  encrypted: bool,
+  tcp_buffer_sizes: (Option<usize>, Option<usize>),
+  accept_filter: bool,
+  accept_filter_error: &mut Option<std::io::Error>,
+) -> ListenerResult {
   // Create a new socket
   let listener_socket2 = socket2::Socket::new(
     if address.is_ipv6() {
@@ -92,12 +140,54 @@ fn build_tcp_listener(address: SocketAddr, tcp_buffer_sizes: (Option<usize>, Opt
 
   // Bind the socket to the address
   listener_socket2.bind(&address.into())?;
+  // On Windows, a negative backlog is interpreted as `SOMAXCONN_HINT`, which is clamped to 200 pending connections,
+  // so `SOMAXCONN` is passed instead to let Winsock choose the maximum reasonable backlog.
+  #[cfg(windows)]
+  listener_socket2.listen(i32::MAX)?;
+  #[cfg(not(windows))]
   listener_socket2.listen(-1)?;
 
+  // The accept filter may be unavailable, if the `accf_http` or `accf_data` kernel module isn't loaded
+  #[cfg(target_os = "freebsd")]
+  if accept_filter {
+    *accept_filter_error = set_accept_filter(&listener_socket2, encrypted).err();
+  }
+  #[cfg(not(target_os = "freebsd"))]
+  let _ = (encrypted, accept_filter, accept_filter_error);
+
   // Wrap the socket into a TcpListener
   TcpListener::from_std(listener_socket2.into())
 }
 
+/// Checks whether the error returned when accepting a connection is caused by resource exhaustion
+#[inline]
+fn is_resource_exhaustion_error(err: &std::io::Error) -> bool {
+  // 24 = EMFILE
+  #[cfg(unix)]
+  let exhaustion_errors = [24];
+  // 10024 = WSAEMFILE, 10055 = WSAENOBUFS
+  #[cfg(windows)]
+  let exhaustion_errors = [10024, 10055];
+  #[cfg(not(any(unix, windows)))]
+  let exhaustion_errors: [i32; 0] = [];
+
+  err.raw_os_error().is_some_and(|code| exhaustion_errors.contains(&code))
+}
+
+#[inline]
+async fn log_accept_filter_error(logging_tx: &Option<Sender<LogMessage>>, address: SocketAddr, err: &std::io::Error) {
+  if let Some(logging_tx) = logging_tx {
+    logging_tx
+      .send(LogMessage::with_level(
+        format!("Cannot set the TCP accept filter for {address}, accepting connections without it: {err}"),
+        LogLevel::Warn,
+        "tcp",
+      ))
+      .await
+      .unwrap_or_default();
+  }
+}
+
 #[inline]
 async fn log_accept_error(logging_tx: &Option<Sender<LogMessage>>, err: &std::io::Error) {
   if let Some(logging_tx) = logging_tx {
@@ -118,6 +208,7 @@ pub fn create_tcp_listener(
   logging_tx: Option<Sender<LogMessage>>,
   first_startup: bool,
   tcp_buffer_sizes: (Option<usize>, Option<usize>),
+  accept_filter: bool,
   io_uring_disabled: Sender<Option<std::io::Error>>,
 ) -> Result<CancellationToken, ListenerError> {
   let shutdown_tx = CancellationToken::new();
@@ -149,6 +240,7 @@ pub fn create_tcp_listener(
           logging_tx,
           first_startup,
           tcp_buffer_sizes,
+          accept_filter,
           shutdown_rx,
         )
         .await
@@ -175,12 +267,41 @@ async fn tcp_listener_fn(
   logging_tx: Option<Sender<LogMessage>>,
   first_startup: bool,
   tcp_buffer_sizes: (Option<usize>, Option<usize>),
+  accept_filter: bool,
   shutdown_rx: CancellationToken,
 ) -> Result<(), ListenerError> {
   let mut listener_result;
+  let mut accept_filter_error = None;
   let mut tries: u64 = 0;
+  #[allow(unused_mut)]
+  let mut address = address;
   loop {
-    listener_result = build_tcp_listener(address, tcp_buffer_sizes);
+    listener_result = build_tcp_listener(
+      address,
+      encrypted,
+      tcp_buffer_sizes,
+      accept_filter,
+      &mut accept_filter_error,
+    );
+    #[cfg(unix)]
+    if listener_result
+      .as_ref()
+      .is_err_and(|err| is_ipv6_unavailable(address, err))
+    {
+      // Fall back to listening on all IPv4 addresses, if IPv6 isn't available
+      let fallback_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), address.port());
+      let fallback_listener_result = build_tcp_listener(
+        fallback_address,
+        encrypted,
+        tcp_buffer_sizes,
+        accept_filter,
+        &mut accept_filter_error,
+      );
+      if fallback_listener_result.is_ok() {
+        address = fallback_address;
+        listener_result = fallback_listener_result;
+      }
+    }
     if first_startup || listener_result.is_ok() {
       break;
     }
@@ -201,8 +322,10 @@ async fn tcp_listener_fn(
 
   log_listening(encrypted, address);
   listen_error_tx.send(None).await.unwrap_or_default();
+  if let Some(err) = accept_filter_error {
+    log_accept_filter_error(&logging_tx, address, &err).await;
+  }
 
-  #[cfg(unix)]
   let mut handle_exhaustion_backoff = Duration::from_millis(10);
 
   loop {
@@ -215,18 +338,13 @@ async fn tcp_listener_fn(
       }
     } {
       Ok(data) => {
-        #[cfg(unix)]
-        {
-          handle_exhaustion_backoff = Duration::from_millis(10);
-        }
+        handle_exhaustion_backoff = Duration::from_millis(10);
         data
       }
       Err(err) => {
         log_accept_error(&logging_tx, &err).await;
 
-        // 24 = EMFILE
-        #[cfg(unix)]
-        if err.raw_os_error() == Some(24) {
+        if is_resource_exhaustion_error(&err) {
           crate::runtime::sleep(handle_exhaustion_backoff).await;
           handle_exhaustion_backoff *= 2;
           if handle_exhaustion_backoff > Duration::from_secs(1) {
@@ -15,46 +15,66 @@ use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::path::Path;
 use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use arc_swap::ArcSwap;
 use async_channel::{Receiver, Sender};
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use ferron_common::logging::{ErrorLogger, LogMessage};
+use ferron_common::observability::MetricsMultiSender;
+use ferron_common::util::{parse_ip_literal, socket_addr_with_zone, MEMORY_BUDGET};
 use ferron_common::{get_entry, get_value};
-use ferron_load_modules::{obtain_module_loaders, obtain_observability_backend_loaders};
+use ferron_load_modules::{
+  obtain_module_loader_names, obtain_module_loaders, obtain_observability_backend_loaders, EMBEDDED_ASSETS,
+};
 #[cfg(feature = "runtime-vibeio")]
 use malloc_best_effort::BEMalloc;
 #[cfg(not(feature = "runtime-vibeio"))]
 use mimalloc::MiMalloc;
-use rustls::server::{ResolvesServerCert, WebPkiClientVerifier};
-use rustls::{RootCertStore, ServerConfig};
-use rustls_native_certs::load_native_certs;
+use rustls::server::ResolvesServerCert;
+use rustls::ServerConfig;
 use shadow_rs::shadow;
 use tempfile::NamedTempFile;
 use tokio_util::sync::CancellationToken;
 
 use crate::acme::{
-  check_certificate_validity_or_install_cached, convert_on_demand_config, get_cached_domains, ACME_TLS_ALPN_NAME,
+  check_certificate_validity_or_install_cached, convert_on_demand_config, get_cached_domains, AcmeCache,
+  ACME_TLS_ALPN_NAME,
 };
 use crate::config::adapters::ConfigurationAdapter;
 use crate::config::processing::{
   load_modules, merge_duplicates, premerge_configuration, remove_and_add_global_configuration,
+  validate_directive_scopes,
+};
+use crate::config::{
+  expand_cdn_presets, lint_configuration, load_trusted_proxy_lists, resolve_snapshot_path, ConfigurationSnapshot,
+  DynamicHostProvider, ServerConfigurations,
 };
-use crate::config::ServerConfigurations;
 use crate::handler::{create_http_handler, ReloadableHandlerData};
 use crate::listener_handler_communication::ConnectionData;
 use crate::listeners::{create_quic_listener, create_tcp_listener};
 use crate::panic::install_panic_hook;
 use crate::setup::acme::background_acme_task;
-use crate::setup::cli::{Command, ConfigAdapter, FerronArgs, LogOutput};
-use crate::setup::ocsp::OcspStapler;
+use crate::setup::admin::{
+  configure_admin_server, loaded_module_names, publish_server_status, AcmeCertificateStatus, ConfigurationChecker,
+  ListenerStatus, ServerStatus,
+};
+#[cfg(windows)]
+use crate::setup::cli::ServiceAction;
+use crate::setup::cli::{Command, CompletionShell, ConfigAdapter, ExplainArgs, FerronArgs, LogOutput};
+use crate::setup::config_schema::build_configuration_schema;
+use crate::setup::explain::explain_request;
+use crate::setup::ocsp::{OcspFailurePolicy, OcspStapler, OcspStaplingOptions};
+use crate::setup::scheduler::{background_scheduler, parse_scheduled_tasks};
+use crate::setup::shutdown::drain_before_shutdown;
 use crate::setup::tls::{
-  handle_automatic_tls, handle_manual_tls, handle_nonencrypted_ports, manual_tls_entry, read_default_port,
-  resolve_sni_hostname, should_skip_server, TlsBuildContext,
+  handle_automatic_tls, handle_manual_tls, handle_nonencrypted_ports, load_default_certified_key, manual_tls_entry,
+  read_default_port, read_sni_routes, resolve_sni_hostname, should_skip_server, CertifiedKeyCache, TlsBuildContext,
+};
+use crate::setup::tls_single::{
+  build_client_cert_verifier, build_tls_config_builder, has_host_tls_settings, init_crypto_provider, set_tls_options,
 };
-use crate::setup::tls_single::{init_crypto_provider, set_tls_version};
-use crate::util::{load_certs, MultiCancel};
+use crate::util::{CtStatusResolver, MultiCancel, TlsServerConfigs};
 
 #[cfg(not(feature = "runtime-vibeio"))]
 #[global_allocator]
@@ -82,7 +102,7 @@ static URING_ENABLED: LazyLockMutex<Option<bool>> = LazyLock::new(|| Arc::new(Mu
 static LISTENER_LOGGING_CHANNEL: LazyLockArc<(Sender<LogMessage>, Receiver<LogMessage>)> =
   LazyLock::new(|| Arc::new(async_channel::unbounded()));
 
-/// Handles shutdown signals (SIGHUP and CTRL+C) and returns whether to continue running
+/// Handles shutdown signals (SIGHUP, SIGTERM and CTRL+C) and returns whether to continue running
 fn handle_shutdown_signals(runtime: &tokio::runtime::Runtime) -> bool {
   runtime.block_on(async move {
     #[cfg(unix)]
@@ -96,19 +116,70 @@ fn handle_shutdown_signals(runtime: &tokio::runtime::Runtime) -> bool {
     #[cfg(not(unix))]
     let configuration_reload_future = async { futures_util::future::pending::<Option<()>>().await };
 
+    // Configuration reloads requested by the `blocklist_refresh` scheduled task
+    let scheduled_reload_future = async {
+      let (_, reload_rx) = &**crate::setup::scheduler::SCHEDULED_RELOAD_CHANNEL;
+      if reload_rx.recv().await.is_err() {
+        futures_util::future::pending().await
+      }
+    };
+
+    // Configuration reloads requested via the admin API
+    let admin_reload_future = async {
+      let (_, reload_rx) = &**crate::setup::admin::ADMIN_RELOAD_CHANNEL;
+      if reload_rx.recv().await.is_err() {
+        futures_util::future::pending().await
+      }
+    };
+
     let shutdown_future = async {
       if tokio::signal::ctrl_c().await.is_err() {
         futures_util::future::pending().await
       }
     };
+    #[cfg(unix)]
+    let termination_future = async {
+      if let Ok(mut signal) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        signal.recv().await
+      } else {
+        futures_util::future::pending().await
+      }
+    };
+    #[cfg(not(unix))]
+    let termination_future = async { futures_util::future::pending::<Option<()>>().await };
+
+    // Service Control Manager events (stop and reload) when running as a Windows service
+    #[cfg(windows)]
+    let service_control_future = async {
+      let (_, control_rx) = &**crate::setup::windows_service::SERVICE_CONTROL_CHANNEL;
+      match control_rx.recv().await {
+        Ok(crate::setup::windows_service::ServiceControlEvent::Reload) => true,
+        Ok(crate::setup::windows_service::ServiceControlEvent::Stop) => false,
+        Err(_) => futures_util::future::pending().await,
+      }
+    };
+    #[cfg(not(windows))]
+    let service_control_future = async { futures_util::future::pending::<bool>().await };
 
     let continue_running = tokio::select! {
       _ = shutdown_future => {
         false
       }
+      _ = termination_future => {
+        false
+      }
       _ = configuration_reload_future => {
         true
       }
+      _ = scheduled_reload_future => {
+        true
+      }
+      _ = admin_reload_future => {
+        true
+      }
+      continue_running = service_control_future => {
+        continue_running
+      }
     };
     continue_running
   })
@@ -205,32 +276,36 @@ fn before_starting_server(
         std::fs::write(temp_config_file.path(), config_string)?;
         temp_config_file.path()
       }
+      #[cfg(windows)]
+      Command::Service(_) => args.config.as_path(),
+      Command::Reload(_) | Command::Explain(_) | Command::ConfigSchema | Command::Completions(_) => {
+        args.config.as_path()
+      }
     }
   } else {
     args.config.as_path()
   };
-  let configuration_adapter: &str = if let Some(config_adapter) = args.config_adapter.as_ref() {
-    match config_adapter {
-      ConfigAdapter::Kdl => "kdl",
-      #[cfg(feature = "config-yaml-legacy")]
-      ConfigAdapter::YamlLegacy => "yaml-legacy",
-      #[cfg(feature = "config-docker-auto")]
-      ConfigAdapter::DockerAuto => "docker-auto",
-    }
-  } else if args.config_string.is_some() {
-    // When a config string is specified but no configuration adapter is specified, default to using kdl.
-    "kdl"
-  } else {
-    determine_default_configuration_adapter(configuration_path)
+  let configuration_adapters = Arc::new(configuration_adapters);
+  let configuration_adapter = obtain_configuration_adapter(&args, configuration_path, &configuration_adapters)?;
+
+  // The configuration is validated before it's reloaded via the admin API
+  let configuration_checker: ConfigurationChecker = {
+    let args = args.clone();
+    let configuration_path = configuration_path.to_path_buf();
+    let configuration_adapters = configuration_adapters.clone();
+    Arc::new(move || {
+      let configuration_adapter = obtain_configuration_adapter(&args, &configuration_path, &configuration_adapters)?;
+      load_configuration_snapshot(configuration_adapter, &configuration_path)
+    })
   };
 
-  // Obtain the configuration adapter
-  let configuration_adapter = configuration_adapters
-    .get(configuration_adapter)
-    .ok_or(anyhow::anyhow!(
-      "The \"{}\" configuration adapter isn't supported",
-      configuration_adapter
-    ))?;
+  // The configuration snapshot is saved only for configuration files, so that it can be compared with
+  // the new configuration before reloading
+  let configuration_snapshot_path = if args.config_string.is_none() && configuration_path == args.config.as_path() {
+    resolve_snapshot_path(configuration_path)
+  } else {
+    None
+  };
 
   // Determine the available parallelism
   let available_parallelism = thread::available_parallelism()?.get();
@@ -244,6 +319,16 @@ fn before_starting_server(
   // Cancel token for OCSP stapling
   let mut ocsp_cancel_token: CancellationToken = CancellationToken::new();
 
+  // The snapshot of the previously loaded configuration, used to determine what changed during a reload
+  let mut previous_configuration_snapshot: Option<ConfigurationSnapshot> = None;
+
+  // Certified keys and in-memory ACME account data, which are kept between configuration reloads
+  let mut certified_key_cache = CertifiedKeyCache::default();
+  let memory_acme_account_cache_data: Arc<tokio::sync::RwLock<HashMap<String, Vec<u8>>>> = Default::default();
+
+  // Register the static assets embedded into the binary
+  ferron_common::util::register_embedded_assets(EMBEDDED_ASSETS);
+
   // Secondary Tokio runtime
   let secondary_runtime = tokio::runtime::Builder::new_multi_thread()
     .worker_threads(match available_parallelism / 2 {
@@ -267,26 +352,63 @@ fn before_starting_server(
     // Process the configurations
     let configs_to_process = merge_duplicates(configs_to_process);
     let configs_to_process = remove_and_add_global_configuration(configs_to_process);
+    let (first_scope_error, scope_warnings) = validate_directive_scopes(&configs_to_process, &module_loaders);
+    let lint_warnings = lint_configuration(&configs_to_process);
     let configs_to_process = premerge_configuration(configs_to_process);
+    let configs_to_process = expand_cdn_presets(configs_to_process);
+    let configs_to_process = load_trusted_proxy_lists(configs_to_process, &secondary_runtime)?;
     let (configs_to_process, first_module_error, unused_properties) = load_modules(
       configs_to_process,
       &mut module_loaders,
       &mut observability_backend_loaders,
       &secondary_runtime,
     );
+    let first_module_error = first_scope_error.or(first_module_error);
+    let configuration_snapshot = ConfigurationSnapshot::new(&configs_to_process);
+    let configuration_changes = previous_configuration_snapshot
+      .as_ref()
+      .map(|previous_snapshot| previous_snapshot.diff(&configuration_snapshot));
+    // Connections are cycled only if the global configuration (which applies to the connections) has changed,
+    // since the host configurations are obtained for each request
+    let global_configuration_changed = previous_configuration_snapshot
+      .as_ref()
+      .is_none_or(|previous_snapshot| previous_snapshot.global_configuration_differs(&configuration_snapshot));
+    previous_configuration_snapshot = Some(configuration_snapshot.clone());
+    let loaded_modules = loaded_module_names(&obtain_module_loader_names(), &module_loaders, &configs_to_process);
 
     // Finalize the configurations
-    let server_configurations = Arc::new(ServerConfigurations::new(configs_to_process));
+    let mut server_configurations = ServerConfigurations::new(configs_to_process);
+    server_configurations.dynamic_hosts = DynamicHostProvider::from_global_configuration(
+      server_configurations.find_global_configuration().as_deref(),
+      &secondary_runtime,
+    )?;
+    let server_configurations = Arc::new(server_configurations);
 
     let global_configuration = server_configurations.find_global_configuration();
     let global_configuration_clone = global_configuration.clone();
 
+    // Apply the global memory budget for buffered bodies and cached responses
+    MEMORY_BUDGET.set_limit(
+      global_configuration
+        .as_deref()
+        .and_then(|c| get_value!("memory_budget", c))
+        .and_then(|v| v.as_size())
+        .and_then(|v| v.try_into().ok()),
+    );
+
     let secondary_runtime_ref = &secondary_runtime;
 
+    let configuration_snapshot_path_ref = configuration_snapshot_path.as_deref();
+    let configuration_checker_ref = &configuration_checker;
+
     // Reference to cancel tokens
     let background_cancel_token_ref = &mut background_cancel_token;
     let ocsp_cancel_token_ref = &mut ocsp_cancel_token;
 
+    // References to the data kept between configuration reloads
+    let certified_key_cache_ref = &mut certified_key_cache;
+    let memory_acme_account_cache_data = memory_acme_account_cache_data.clone();
+
     // Execute the rest
     let execute_rest = move || {
       if let Some(first_module_error) = first_module_error {
@@ -294,6 +416,18 @@ fn before_starting_server(
         Err(first_module_error)?;
       }
 
+      // Log configuration properties specified outside of their scopes, and likely misconfigurations
+      for configuration_warning in scope_warnings.into_iter().chain(lint_warnings) {
+        for logging_tx in global_configuration
+          .as_ref()
+          .map_or(&vec![], |c| &c.observability.log_channels)
+        {
+          logging_tx
+            .send_blocking(LogMessage::new(configuration_warning.clone(), true))
+            .unwrap_or_default();
+        }
+      }
+
       // Log unused properties
       for unused_property in unused_properties {
         for logging_tx in global_configuration
@@ -309,6 +443,21 @@ fn before_starting_server(
         }
       }
 
+      // Log the configuration changes applied by the reload
+      for configuration_change in configuration_changes.iter().flatten() {
+        for logging_tx in global_configuration
+          .as_ref()
+          .map_or(&vec![], |c| &c.observability.log_channels)
+        {
+          logging_tx
+            .send_blocking(LogMessage::new(
+              format!("Configuration reloaded: {configuration_change}"),
+              true,
+            ))
+            .unwrap_or_default();
+        }
+      }
+
       // Configure cryptography provider for Rustls
       let crypto_provider = init_crypto_provider(global_configuration.as_deref())?;
 
@@ -320,53 +469,12 @@ fn before_starting_server(
       let crypto_provider = Arc::new(crypto_provider);
 
       // Build TLS configuration
-      let tls_config_builder_wants_versions = ServerConfig::builder_with_provider(crypto_provider.clone());
-      let tls_config_builder_wants_verifier =
-        set_tls_version(tls_config_builder_wants_versions, global_configuration.as_deref())?;
-
-      let tls_config_builder_wants_server_cert = if let Some(client_cert_path) = global_configuration
-        .as_deref()
-        .and_then(|c| get_value!("tls_client_certificate", c))
-        .and_then(|v| v.as_str())
-      {
-        let mut roots = RootCertStore::empty();
-        let client_certificate_cas = load_certs(client_cert_path)?;
-        for cert in client_certificate_cas {
-          roots.add(cert)?;
-        }
-        tls_config_builder_wants_verifier
-          .with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build()?)
-      } else if global_configuration
-        .as_deref()
-        .and_then(|c| get_value!("tls_client_certificate", c))
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false)
-      {
-        let roots = (|| {
-          let certs_result = load_native_certs();
-          if !certs_result.errors.is_empty() {
-            return None;
-          }
-          let certs = certs_result.certs;
-
-          let mut roots = RootCertStore::empty();
-          for cert in certs {
-            if roots.add(cert).is_err() {
-              return None;
-            }
-          }
-
-          Some(roots)
-        })()
-        .unwrap_or(RootCertStore {
-          roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-        });
-
-        tls_config_builder_wants_verifier
-          .with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build()?)
-      } else {
-        tls_config_builder_wants_verifier.with_no_client_auth()
-      };
+      let client_cert_verifier = build_client_cert_verifier(global_configuration.as_deref())?;
+      let tls_config_builder_wants_server_cert = build_tls_config_builder(
+        crypto_provider.clone(),
+        global_configuration.as_deref(),
+        client_cert_verifier,
+      )?;
 
       let enable_proxy_protocol = global_configuration
         .as_ref()
@@ -383,17 +491,28 @@ fn before_starting_server(
         Err(anyhow::anyhow!("PROXY protocol isn't supported with HTTP/3"))?
       }
 
+      #[cfg(feature = "runtime-vibeio")]
+      if protocols.contains(&"h2c") {
+        Err(anyhow::anyhow!(
+          "HTTP/2 over cleartext (h2c) isn't supported with the Vibeio runtime"
+        ))?
+      }
+
+      let sni_routes = read_sni_routes(global_configuration.as_deref());
+      if enable_proxy_protocol && !sni_routes.is_empty() {
+        Err(anyhow::anyhow!("PROXY protocol isn't supported with SNI passthrough"))?
+      }
+
       let default_http_port = read_default_port(global_configuration.as_deref(), false);
       let default_https_port = read_default_port(global_configuration.as_deref(), true);
 
       let mut tls_build_ctx = TlsBuildContext::default();
-      let memory_acme_account_cache_data: Arc<tokio::sync::RwLock<HashMap<String, Vec<u8>>>> = Default::default();
       let mut invalid_wildcard_domains: HashSet<String> = HashSet::new();
 
       // Iterate server configurations (TLS configuration)
       for server in &server_configurations.host_configs {
         let hostname = server.filters.hostname.as_deref();
-        if let Some(hostname) = hostname {
+        if let Some(hostname) = hostname.filter(|_| !server.filters.has_regex_hostname()) {
           if hostname.contains("*.") && (hostname == "*." || !hostname.starts_with("*.")) {
             invalid_wildcard_domains.insert(hostname.to_string());
           }
@@ -409,11 +528,23 @@ fn before_starting_server(
         handle_nonencrypted_ports(&mut tls_build_ctx, server, default_http_port);
 
         if let Some(https_port) = https_port {
+          if has_host_tls_settings(server, global_configuration.as_deref()) {
+            tls_build_ctx
+              .host_tls_configurations
+              .entry((server.filters.ip, https_port))
+              .or_default()
+              .push((sni_hostname.clone(), server.clone()));
+          }
+
           let manual_tls_entry_option = manual_tls_entry(server);
           if get_entry!("auto_tls", server)
             .and_then(|e| e.values.first())
             .and_then(|v| v.as_bool())
-            .unwrap_or(server.filters.port.is_none() && manual_tls_entry_option.is_none())
+            .unwrap_or(
+              server.filters.port.is_none()
+                && manual_tls_entry_option.is_none()
+                && !server.filters.has_regex_hostname(),
+            )
           {
             if let Some(error_log_message) = handle_automatic_tls(
               &mut tls_build_ctx,
@@ -443,10 +574,22 @@ fn before_starting_server(
               sni_hostname,
               cert,
               key,
+              certified_key_cache_ref,
             )?;
           }
         }
       }
+      let default_certified_key = load_default_certified_key(
+        global_configuration.as_deref(),
+        &crypto_provider,
+        certified_key_cache_ref,
+      )?;
+      let strict_sni = global_configuration
+        .as_ref()
+        .and_then(|c| get_value!("strict_sni", c))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+      certified_key_cache_ref.remove_unused();
 
       for invalid_wildcard_domain in invalid_wildcard_domains {
         for logging_tx in global_configuration
@@ -465,8 +608,8 @@ fn before_starting_server(
         }
       }
 
-      // If HTTP/1.1 isn't enabled, don't listen to non-encrypted ports
-      if !protocols.contains(&"h1") {
+      // If neither HTTP/1.1 nor HTTP/2 over cleartext is enabled, don't listen to non-encrypted ports
+      if !protocols.contains(&"h1") && !protocols.contains(&"h2c") {
         tls_build_ctx.nonencrypted_ports.clear();
       }
 
@@ -485,7 +628,12 @@ fn before_starting_server(
       let mut tls_configs = HashMap::new();
       let mut acme_tls_alpn_01_configs = HashMap::new();
       let certified_keys_to_preload = Arc::new(tls_build_ctx.certified_keys_to_preload);
-      for (tls_port, sni_resolver) in tls_build_ctx.tls_ports.into_iter() {
+      for (tls_port, mut sni_resolver) in tls_build_ctx.tls_ports.into_iter() {
+        // Certificate used when the client doesn't send a server name, or no host matches it
+        if let Some(default_certified_key) = default_certified_key.clone() {
+          sni_resolver.load_default_certified_key(default_certified_key);
+        }
+        sni_resolver.set_strict_sni(strict_sni);
         let enable_ocsp_stapling = global_configuration
           .as_ref()
           .and_then(|c| get_value!("ocsp_stapling", c))
@@ -495,8 +643,29 @@ fn before_starting_server(
           let logging_tx = global_configuration
             .as_ref()
             .map_or(vec![], |c| c.observability.log_channels.clone());
-
-          let stapler = OcspStapler::new(Arc::new(sni_resolver), secondary_runtime_ref, logging_tx);
+          let metrics_tx = global_configuration
+            .as_ref()
+            .map_or(vec![], |c| c.observability.metric_channels.clone());
+          let ocsp_stapling_options = OcspStaplingOptions {
+            failure_policy: global_configuration
+              .as_ref()
+              .and_then(|c| get_value!("ocsp_stapling_failure_policy", c))
+              .and_then(|v| v.as_str())
+              .and_then(OcspFailurePolicy::parse)
+              .unwrap_or_default(),
+            max_age: global_configuration
+              .as_ref()
+              .and_then(|c| get_value!("ocsp_stapling_max_age", c))
+              .and_then(|v| v.as_duration()),
+          };
+
+          let stapler = OcspStapler::new(
+            Arc::new(sni_resolver),
+            secondary_runtime_ref,
+            logging_tx,
+            metrics_tx,
+            ocsp_stapling_options,
+          );
           if let Some(certified_keys_to_preload) = certified_keys_to_preload.get(&tls_port) {
             for certified_key in certified_keys_to_preload {
               stapler.preload(certified_key.clone());
@@ -515,9 +684,22 @@ fn before_starting_server(
         } else {
           Arc::new(sni_resolver)
         };
+        // Record the certificate transparency status of served certificates, if metrics are enabled
+        let metrics_tx = global_configuration
+          .as_ref()
+          .map_or(vec![], |c| c.observability.metric_channels.clone());
+        let resolver: Arc<dyn ResolvesServerCert> = if metrics_tx.is_empty() {
+          resolver
+        } else {
+          Arc::new(CtStatusResolver::new(
+            resolver,
+            MetricsMultiSender::new_multiple(metrics_tx),
+          ))
+        };
         let mut tls_config = tls_config_builder_wants_server_cert
           .clone()
-          .with_cert_resolver(resolver);
+          .with_cert_resolver(resolver.clone());
+        set_tls_options(&mut tls_config, global_configuration.as_deref());
         if protocols.contains(&"h3") {
           // TLS configuration used for QUIC listener
           let mut quic_tls_config = tls_config.clone();
@@ -526,14 +708,41 @@ fn before_starting_server(
           quic_tls_config.alpn_protocols.insert(0, b"h3".to_vec());
           quic_tls_configs.insert(tls_port, Arc::new(quic_tls_config));
         }
-        if protocols.contains(&"h1") {
-          tls_config.alpn_protocols.insert(0, b"http/1.0".to_vec());
-          tls_config.alpn_protocols.insert(0, b"http/1.1".to_vec());
-        }
-        if protocols.contains(&"h2") {
-          tls_config.alpn_protocols.insert(0, b"h2".to_vec());
+        let set_alpn_protocols = |tls_config: &mut ServerConfig| {
+          if protocols.contains(&"h1") {
+            tls_config.alpn_protocols.insert(0, b"http/1.0".to_vec());
+            tls_config.alpn_protocols.insert(0, b"http/1.1".to_vec());
+          }
+          if protocols.contains(&"h2") {
+            tls_config.alpn_protocols.insert(0, b"h2".to_vec());
+          }
+        };
+        set_alpn_protocols(&mut tls_config);
+        let mut tls_server_configs = TlsServerConfigs::new(Arc::new(tls_config));
+
+        // TLS configurations for hosts with their own TLS settings, selected by the server name sent by the client
+        for (sni_hostname, host_configuration) in tls_build_ctx
+          .host_tls_configurations
+          .get(&tls_port)
+          .map_or(&[][..], |c| c.as_slice())
+        {
+          let host_configuration = host_configuration.as_ref();
+          let host_crypto_provider = Arc::new(init_crypto_provider(Some(host_configuration))?);
+          let host_client_cert_verifier = build_client_cert_verifier(Some(host_configuration))?;
+          let mut host_tls_config = build_tls_config_builder(
+            host_crypto_provider,
+            Some(host_configuration),
+            host_client_cert_verifier,
+          )?
+          .with_cert_resolver(resolver.clone());
+          set_tls_options(&mut host_tls_config, Some(host_configuration));
+          set_alpn_protocols(&mut host_tls_config);
+          match sni_hostname {
+            Some(sni_hostname) => tls_server_configs.insert_host_config(sni_hostname, Arc::new(host_tls_config)),
+            None => tls_server_configs.set_default_config(Arc::new(host_tls_config)),
+          }
         }
-        tls_configs.insert(tls_port, Arc::new(tls_config));
+        tls_configs.insert(tls_port, Arc::new(tls_server_configs));
       }
       for (tls_port, sni_resolver) in tls_build_ctx.acme_tls_alpn_01_resolvers.into_iter() {
         let mut tls_config = tls_config_builder_wants_server_cert
@@ -552,19 +761,23 @@ fn before_starting_server(
         .map_err(|_| anyhow::anyhow!("Can't access the QUIC listeners"))?;
       let mut listened_socket_addresses = Vec::new();
       let mut quic_listened_socket_addresses = Vec::new();
-      let listen_ip_addr = global_configuration
+      // The listen address with the port 0, to which the port is set later (the IPv6 zone is resolved here)
+      let listen_socket_addr = global_configuration
         .as_deref()
         .and_then(|c| get_value!("listen_ip", c))
         .and_then(|v| v.as_str())
-        .map_or(Ok(IpAddr::V6(Ipv6Addr::UNSPECIFIED)), |a| a.parse())
-        .map_err(|_| anyhow::anyhow!("Invalid IP address to listen to"))?;
+        .map_or(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0)), |a| {
+          parse_ip_literal(a).and_then(|(ip, zone)| socket_addr_with_zone(ip, zone, 0))
+        })
+        .ok_or(anyhow::anyhow!("Invalid IP address to listen to"))?;
       for (tcp_port, encrypted) in tls_build_ctx
         .nonencrypted_ports
         .iter()
         .map(|p| (*p, false))
         .chain(tls_configs.keys().map(|p| (p.1, true)))
       {
-        let socket_address = SocketAddr::new(listen_ip_addr, tcp_port);
+        let mut socket_address = listen_socket_addr;
+        socket_address.set_port(tcp_port);
         listened_socket_addresses.push((socket_address, encrypted));
       }
       let mut quic_tls_configs_processed: HashMap<(Option<IpAddr>, u16), Arc<quinn::ServerConfig>> =
@@ -579,7 +792,8 @@ fn before_starting_server(
             Arc::new(quinn::ServerConfig::with_crypto(Arc::new(quic_tls_config2))),
           );
         }
-        let socket_address = SocketAddr::new(listen_ip_addr, quic_port);
+        let mut socket_address = listen_socket_addr;
+        socket_address.set_port(quic_port);
         if quic_ip.is_none() {
           if had_quic_ports.contains(&quic_port) {
             quic_listened_socket_addresses.retain(|(sa, _)| sa != &socket_address);
@@ -606,6 +820,27 @@ fn before_starting_server(
         .as_deref()
         .and_then(|c| get_value!("io_uring", c))
         .and_then(|v| v.as_bool());
+
+      // `io_uring` is available only on Linux, so don't restart handlers and listeners when the option is toggled
+      // on other operating systems (like FreeBSD), and warn if `io_uring` is explicitly enabled.
+      #[cfg(not(target_os = "linux"))]
+      let enable_uring = {
+        if enable_uring == Some(true) {
+          for logging_tx in global_configuration
+            .as_ref()
+            .map_or(&vec![], |c| &c.observability.log_channels)
+          {
+            logging_tx
+              .send_blocking(LogMessage::new(
+                "io_uring isn't supported on this operating system. Ferron will run with io_uring disabled."
+                  .to_string(),
+                true,
+              ))
+              .unwrap_or_default();
+          }
+        }
+        None
+      };
       let mut uring_enabled_locked = URING_ENABLED
         .lock()
         .map_err(|_| anyhow::anyhow!("Can't access the enabled `io_uring` option"))?;
@@ -735,6 +970,32 @@ fn before_starting_server(
         (acme_configs, acme_on_demand_configs, existing_combinations)
       });
 
+      // The automatic TLS certificates reported by the admin API
+      let acme_certificates = acme_configs
+        .iter()
+        .map(|acme_config| AcmeCertificateStatus {
+          domains: acme_config.domains.clone(),
+          issuer: acme_config.issuer_name.clone(),
+          certified_key_lock: acme_config.certified_key_lock.clone(),
+        })
+        .collect::<Vec<_>>();
+
+      // The ACME cache directories are cleaned up by the `cert_cache_cleanup` scheduled task
+      let mut certificate_cache_paths = acme_configs
+        .iter()
+        .filter_map(|acme_config| match &acme_config.certificate_cache {
+          AcmeCache::File(path) => path.parent().map(|path| path.to_path_buf()),
+          AcmeCache::Memory(_) => None,
+        })
+        .chain(
+          acme_on_demand_configs
+            .iter()
+            .filter_map(|acme_on_demand_config| acme_on_demand_config.cache_path.clone()),
+        )
+        .collect::<Vec<_>>();
+      certificate_cache_paths.sort();
+      certificate_cache_paths.dedup();
+
       let inner_handler_data = ReloadableHandlerData {
         configurations: server_configurations,
         tls_configs: Arc::new(tls_configs),
@@ -743,6 +1004,11 @@ fn before_starting_server(
         acme_http_01_resolvers: tls_build_ctx.acme_http_01_resolvers,
         quic_tls_configs: Arc::new(quic_tls_configs_processed),
         enable_proxy_protocol,
+        sni_routes: Arc::new(sni_routes),
+        graceful_shutdown_timeout: global_configuration
+          .as_deref()
+          .and_then(|c| get_value!("graceful_shutdown_timeout", c))
+          .and_then(|v| v.as_duration()),
       };
       let reloadable_handler_data = if let Some(data) = SERVER_CONFIG_ARCSWAP.get().cloned() {
         data.swap(Arc::new(inner_handler_data));
@@ -762,15 +1028,25 @@ fn before_starting_server(
         } else {
           for (_, graceful_shutdown) in handlers_locked.iter() {
             start_new_handlers = false;
-            let _ = graceful_shutdown.send_blocking(());
+            if global_configuration_changed {
+              let _ = graceful_shutdown.send_blocking(());
+            }
           }
         }
       }
 
       let acme_on_demand_rx = tls_build_ctx.acme_on_demand_rx;
-      let on_demand_tls_ask_endpoint = match global_configuration
+      // If the asking endpoint isn't specified, the dynamic host provider is asked instead
+      let on_demand_tls_ask_entry = global_configuration
         .as_ref()
-        .and_then(|c| get_value!("auto_tls_on_demand_ask", c))
+        .and_then(|c| get_entry!("auto_tls_on_demand_ask", c))
+        .or_else(|| {
+          global_configuration
+            .as_ref()
+            .and_then(|c| get_entry!("dynamic_hosts", c))
+        });
+      let on_demand_tls_ask_endpoint = match on_demand_tls_ask_entry
+        .and_then(|e| e.values.first())
         .and_then(|v| v.as_str())
         .map(|u| u.parse::<hyper::Uri>())
       {
@@ -784,6 +1060,7 @@ fn before_starting_server(
       let on_demand_tls_ask_endpoint_verify = !global_configuration
         .as_ref()
         .and_then(|c| get_value!("auto_tls_on_demand_ask_no_verification", c))
+        .or_else(|| on_demand_tls_ask_entry.and_then(|e| e.props.get("no_verification")))
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
@@ -833,6 +1110,66 @@ fn before_starting_server(
         });
       }
 
+      // Buffer pool metrics initialization
+      if let Some(metrics_channels) = global_configuration
+        .as_ref()
+        .map(|c| &c.observability.metric_channels)
+        .filter(|metric_channels| !metric_channels.is_empty())
+        .cloned()
+      {
+        let background_cancel_token = background_cancel_token_ref.clone();
+        secondary_runtime_ref.spawn(async move {
+          tokio::select! {
+            biased;
+
+            _ = background_cancel_token.cancelled() => {}
+            _ = crate::setup::buffer_pool::background_buffer_pool_metrics(metrics_channels) => {}
+          }
+        });
+      }
+
+      // Spawn the memory budget monitor
+      if let Some(global_configuration) = global_configuration
+        .as_deref()
+        .filter(|_| MEMORY_BUDGET.limit().is_some())
+      {
+        let metrics_channels = global_configuration.observability.metric_channels.clone();
+        let memory_budget_logger = Arc::new(ErrorLogger::new_multiple(
+          global_configuration.observability.log_channels.clone(),
+        ));
+        let background_cancel_token = background_cancel_token_ref.clone();
+        secondary_runtime_ref.spawn(async move {
+          tokio::select! {
+            biased;
+
+            _ = background_cancel_token.cancelled() => {}
+            _ = crate::setup::memory_budget::background_memory_budget_monitor(
+              metrics_channels,
+              memory_budget_logger,
+            ) => {}
+          }
+        });
+      }
+
+      // Spawn the scheduler of maintenance tasks
+      if let Some(global_configuration) = global_configuration.as_deref() {
+        let scheduled_tasks = parse_scheduled_tasks(global_configuration, certificate_cache_paths)?;
+        if !scheduled_tasks.is_empty() {
+          let scheduler_logger = Arc::new(ErrorLogger::new_multiple(
+            global_configuration.observability.log_channels.clone(),
+          ));
+          let background_cancel_token = background_cancel_token_ref.clone();
+          secondary_runtime_ref.spawn(async move {
+            tokio::select! {
+              biased;
+
+              _ = background_cancel_token.cancelled() => {}
+              _ = background_scheduler(scheduled_tasks, scheduler_logger) => {}
+            }
+          });
+        }
+      }
+
       // Spawn request handler threads
       if start_new_handlers {
         let mut handler_shutdown_channels = HANDLERS.lock().expect("Can't access the handler threads");
@@ -882,16 +1219,40 @@ fn before_starting_server(
         Err(anyhow::anyhow!("The server is configured to listen to no port"))?
       }
 
+      // The listeners reported by the admin API
+      let listeners = listened_socket_addresses
+        .iter()
+        .map(|(socket_address, encrypted)| ListenerStatus {
+          address: *socket_address,
+          protocol: "tcp",
+          tls: *encrypted,
+        })
+        .chain(
+          quic_listened_socket_addresses
+            .iter()
+            .map(|(socket_address, _)| ListenerStatus {
+              address: *socket_address,
+              protocol: "quic",
+              tls: true,
+            }),
+        )
+        .collect::<Vec<_>>();
+
       let tcp_send_buffer_size = global_configuration
         .as_deref()
         .and_then(|c| get_value!("tcp_send_buffer", c))
-        .and_then(|v| v.as_i128())
+        .and_then(|v| v.as_size())
         .map(|v| v as usize);
       let tcp_recv_buffer_size = global_configuration
         .as_deref()
         .and_then(|c| get_value!("tcp_recv_buffer", c))
-        .and_then(|v| v.as_i128())
+        .and_then(|v| v.as_size())
         .map(|v| v as usize);
+      let tcp_accept_filter = global_configuration
+        .as_deref()
+        .and_then(|c| get_value!("tcp_accept_filter", c))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
       for (socket_address, encrypted) in listened_socket_addresses {
         if let std::collections::hash_map::Entry::Vacant(e) = tcp_listeners.entry(socket_address) {
           // Create a TCP listener
@@ -903,6 +1264,7 @@ fn before_starting_server(
             global_logger.clone(),
             first_startup,
             (tcp_send_buffer_size, tcp_recv_buffer_size),
+            tcp_accept_filter,
             io_uring_disabled_tx.clone(),
           )?);
         }
@@ -934,6 +1296,49 @@ fn before_starting_server(
       // Drop QUIC listener mutex guard
       drop(quic_listeners);
 
+      // Save the snapshot of the running configuration
+      if let Some(configuration_snapshot_path) = configuration_snapshot_path_ref {
+        if let Err(err) = configuration_snapshot.save(configuration_snapshot_path) {
+          for logging_tx in global_configuration
+            .as_ref()
+            .map_or(&vec![], |c| &c.observability.log_channels)
+          {
+            logging_tx
+              .send_blocking(LogMessage::new(
+                format!("Can't save the configuration snapshot: {err}"),
+                true,
+              ))
+              .unwrap_or_default();
+          }
+        }
+      }
+
+      // Publish the status of the server, and start the admin API server
+      publish_server_status(ServerStatus {
+        listeners,
+        modules: loaded_modules,
+        certificates: acme_certificates,
+        changes: configuration_changes.unwrap_or_default(),
+        loaded_at: SystemTime::now(),
+      });
+      configure_admin_server(
+        global_configuration
+          .as_deref()
+          .and_then(|c| get_value!("admin_listen", c))
+          .and_then(|v| v.as_str()),
+        global_configuration
+          .as_deref()
+          .and_then(|c| get_value!("admin_token", c))
+          .and_then(|v| v.as_str()),
+        secondary_runtime_ref,
+        configuration_checker_ref.clone(),
+        ErrorLogger::new_multiple(
+          global_configuration
+            .as_ref()
+            .map_or(vec![], |c| c.observability.log_channels.clone()),
+        ),
+      )?;
+
       let shutdown_result = handle_shutdown_signals(secondary_runtime_ref);
 
       Ok::<_, Box<dyn Error + Send + Sync>>(shutdown_result)
@@ -945,6 +1350,8 @@ fn before_starting_server(
           first_startup = false;
           println!("Reloading the server configuration...");
         } else {
+          // Deregister the server and drain it, while the listeners still accept connections
+          drain_before_shutdown(&secondary_runtime, global_configuration_clone.as_deref());
           if let Ok(mut handlers_locked) = HANDLERS.lock() {
             while let Some((cancel_token, _)) = handlers_locked.pop() {
               cancel_token.cancel();
@@ -1040,6 +1447,189 @@ fn print_version() {
   }
 }
 
+/// Obtains the configuration adapter for the configuration file
+fn obtain_configuration_adapter<'a>(
+  args: &FerronArgs,
+  configuration_path: &Path,
+  configuration_adapters: &'a HashMap<String, Box<dyn ConfigurationAdapter + Send + Sync>>,
+) -> Result<&'a (dyn ConfigurationAdapter + Send + Sync), Box<dyn Error + Send + Sync>> {
+  let configuration_adapter: &str = if let Some(config_adapter) = args.config_adapter.as_ref() {
+    match config_adapter {
+      ConfigAdapter::Kdl => "kdl",
+      #[cfg(feature = "config-yaml-legacy")]
+      ConfigAdapter::YamlLegacy => "yaml-legacy",
+      #[cfg(feature = "config-docker-auto")]
+      ConfigAdapter::DockerAuto => "docker-auto",
+    }
+  } else if args.config_string.is_some() {
+    // When a config string is specified but no configuration adapter is specified, default to using kdl.
+    "kdl"
+  } else {
+    determine_default_configuration_adapter(configuration_path)
+  };
+
+  Ok(
+    configuration_adapters
+      .get(configuration_adapter)
+      .ok_or(anyhow::anyhow!(
+        "The \"{}\" configuration adapter isn't supported",
+        configuration_adapter
+      ))?
+      .as_ref(),
+  )
+}
+
+/// Loads and processes the configuration the same way as when the server is started, and creates its snapshot
+fn load_configuration_snapshot(
+  configuration_adapter: &(dyn ConfigurationAdapter + Send + Sync),
+  configuration_path: &Path,
+) -> Result<ConfigurationSnapshot, Box<dyn Error + Send + Sync>> {
+  let secondary_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+  let mut module_loaders = obtain_module_loaders();
+  let mut observability_backend_loaders = obtain_observability_backend_loaders();
+  let configs_to_process = configuration_adapter.load_configuration(configuration_path)?;
+  let configs_to_process = merge_duplicates(configs_to_process);
+  let configs_to_process = remove_and_add_global_configuration(configs_to_process);
+  let (first_scope_error, _) = validate_directive_scopes(&configs_to_process, &module_loaders);
+  let configs_to_process = premerge_configuration(configs_to_process);
+  let configs_to_process = expand_cdn_presets(configs_to_process);
+  let configs_to_process = load_trusted_proxy_lists(configs_to_process, &secondary_runtime)?;
+  let (configs_to_process, first_module_error, _) = load_modules(
+    configs_to_process,
+    &mut module_loaders,
+    &mut observability_backend_loaders,
+    &secondary_runtime,
+  );
+  if let Some(first_module_error) = first_scope_error.or(first_module_error) {
+    Err(first_module_error)?
+  }
+
+  Ok(ConfigurationSnapshot::new(&configs_to_process))
+}
+
+/// Reloads the configuration of the server running with the specified configuration file,
+/// or prints the changes the reload would make
+fn reload_configuration(
+  args: &FerronArgs,
+  diff: bool,
+  configuration_adapters: &HashMap<String, Box<dyn ConfigurationAdapter + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let configuration_path = args.config.as_path();
+  let running_snapshot = resolve_snapshot_path(configuration_path)
+    .and_then(|snapshot_path| ConfigurationSnapshot::load(&snapshot_path).ok())
+    .ok_or(anyhow::anyhow!(
+      "Can't find the running server configuration. Is the server running with the \"{}\" configuration file?",
+      configuration_path.display()
+    ))?;
+
+  if diff {
+    let configuration_adapter = obtain_configuration_adapter(args, configuration_path, configuration_adapters)?;
+    let changes = running_snapshot.diff(&load_configuration_snapshot(configuration_adapter, configuration_path)?);
+    if changes.is_empty() {
+      println!("The configuration reload wouldn't change anything.");
+    } else {
+      println!("The configuration reload would make these changes:");
+      for change in changes {
+        println!("  {change}");
+      }
+    }
+    return Ok(());
+  }
+
+  #[cfg(unix)]
+  {
+    let pid = running_snapshot
+      .pid
+      .ok_or(anyhow::anyhow!("Can't determine the process ID of the running server"))?;
+    // Safety: `kill` doesn't access any memory of this process, it only sends a signal to another process.
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGHUP) } != 0 {
+      Err(std::io::Error::last_os_error())?
+    }
+    println!("The server configuration reload has been requested.");
+  }
+
+  #[cfg(not(unix))]
+  Err(anyhow::anyhow!(
+    "Reloading the server configuration with this command isn't supported on this platform"
+  ))?;
+
+  Ok(())
+}
+
+/// Prints how the server with the specified configuration would handle the request described by the command-line arguments
+fn explain_routing(
+  args: &FerronArgs,
+  explain_args: &ExplainArgs,
+  configuration_adapters: &HashMap<String, Box<dyn ConfigurationAdapter + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  // Load and process the configuration the same way as when the server is started
+  let configuration_path = explain_args.config.as_deref().unwrap_or(args.config.as_path());
+  let configuration_adapter = obtain_configuration_adapter(args, configuration_path, configuration_adapters)?;
+  let secondary_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+  let mut module_loaders = obtain_module_loaders();
+  let mut observability_backend_loaders = obtain_observability_backend_loaders();
+  let configs_to_process = configuration_adapter.load_configuration(configuration_path)?;
+  let configs_to_process = merge_duplicates(configs_to_process);
+  let configs_to_process = remove_and_add_global_configuration(configs_to_process);
+  let (first_scope_error, scope_warnings) = validate_directive_scopes(&configs_to_process, &module_loaders);
+  let lint_warnings = lint_configuration(&configs_to_process);
+  let configs_to_process = premerge_configuration(configs_to_process);
+  let configs_to_process = expand_cdn_presets(configs_to_process);
+  let configs_to_process = load_trusted_proxy_lists(configs_to_process, &secondary_runtime)?;
+  let (configs_to_process, first_module_error, _) = load_modules(
+    configs_to_process,
+    &mut module_loaders,
+    &mut observability_backend_loaders,
+    &secondary_runtime,
+  );
+  if let Some(first_module_error) = first_scope_error.or(first_module_error) {
+    Err(first_module_error)?
+  }
+  for configuration_warning in scope_warnings.into_iter().chain(lint_warnings) {
+    eprintln!("Warning: {configuration_warning}");
+  }
+  let configurations = ServerConfigurations::new(configs_to_process);
+
+  let port = match explain_args.port {
+    Some(port) => port,
+    None => read_default_port(
+      configurations.find_global_configuration().as_deref(),
+      explain_args.https,
+    )
+    .ok_or(anyhow::anyhow!(
+      "The default {} port is disabled, so the port must be specified",
+      if explain_args.https { "HTTPS" } else { "HTTP" }
+    ))?,
+  };
+  let socket_data = ferron_common::modules::SocketData {
+    remote_addr: SocketAddr::new(explain_args.client_ip, 0),
+    local_addr: SocketAddr::new(explain_args.server_ip, port),
+    encrypted: explain_args.https,
+    tls_sni: explain_args.host.clone().filter(|_| explain_args.https),
+    tls_alpn: None,
+  };
+  let mut request_builder = hyper::Request::builder()
+    .method(explain_args.method.as_str())
+    .uri(explain_args.path.as_str());
+  if let Some(host) = explain_args.host.as_ref() {
+    request_builder = request_builder.header(hyper::header::HOST, host);
+  }
+  let (request, _) = request_builder.body(())?.into_parts();
+
+  let module_loaders = obtain_module_loader_names()
+    .into_iter()
+    .zip(
+      module_loaders
+        .iter()
+        .map(|module_loader| module_loader.get_requirements()),
+    )
+    .collect::<Vec<_>>();
+  for line in explain_request(&configurations, &module_loaders, request, &socket_data)? {
+    println!("{line}");
+  }
+  Ok(())
+}
+
 /// The main entry point of the application
 fn main() {
   #[cfg(feature = "runtime-vibeio")]
@@ -1063,6 +1653,65 @@ fn main() {
     return;
   }
 
+  match args.command.as_ref() {
+    Some(Command::Reload(reload_args)) => {
+      if let Err(err) = reload_configuration(&args, reload_args.diff, &configuration_adapters) {
+        eprintln!("Error while reloading the server configuration: {err}");
+        std::process::exit(1);
+      }
+      return;
+    }
+    Some(Command::Explain(explain_args)) => {
+      if let Err(err) = explain_routing(&args, explain_args, &configuration_adapters) {
+        eprintln!("Error while explaining the request: {err}");
+        std::process::exit(1);
+      }
+      return;
+    }
+    Some(Command::ConfigSchema) => {
+      // Dump the configuration schema of the compiled-in modules and exit
+      let configuration_schema =
+        build_configuration_schema(&obtain_module_loaders(), &obtain_observability_backend_loaders());
+      match serde_json::to_string_pretty(&configuration_schema) {
+        Ok(configuration_schema) => println!("{configuration_schema}"),
+        Err(err) => {
+          eprintln!("Error while serializing the configuration schema: {err}");
+          std::process::exit(1);
+        }
+      }
+      return;
+    }
+    Some(Command::Completions(completions_args)) => {
+      // Print the shell completion script and exit
+      let shell = match completions_args.shell {
+        CompletionShell::Bash => clap_complete::Shell::Bash,
+        CompletionShell::Zsh => clap_complete::Shell::Zsh,
+        CompletionShell::Fish => clap_complete::Shell::Fish,
+      };
+      clap_complete::generate(shell, &mut FerronArgs::command(), "ferron", &mut std::io::stdout());
+      return;
+    }
+    _ => (),
+  }
+
+  #[cfg(windows)]
+  if let Some(Command::Service(service_args)) = args.command.as_ref() {
+    // Manage or run the Windows service
+    let service_result = match service_args.action {
+      ServiceAction::Install => crate::setup::windows_service::install_service(&args.config),
+      ServiceAction::Uninstall => crate::setup::windows_service::uninstall_service(),
+      ServiceAction::Run => crate::setup::windows_service::run_service(|| {
+        let (configuration_adapters, _all_adapters) = obtain_configuration_adapters();
+        before_starting_server(FerronArgs::parse(), configuration_adapters)
+      }),
+    };
+    if let Err(err) = service_result {
+      eprintln!("Error while managing a Windows service: {err}");
+      std::process::exit(1);
+    }
+    return;
+  }
+
   // Start the server!
   if let Err(err) = before_starting_server(args, configuration_adapters) {
     eprintln!("Error while running a server: {err}");
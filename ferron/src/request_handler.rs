@@ -7,12 +7,15 @@ use std::time::Duration;
 use async_channel::Sender;
 use chrono::{DateTime, Local};
 use ferron_common::logging::{ErrorLogger, LogMessage};
-use ferron_common::observability::{MetricsMultiSender, TraceSignal};
+use ferron_common::observability::{
+  Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender, TraceSignal,
+};
 #[cfg(feature = "runtime-vibeio")]
 use ferron_common::util::FileStream;
+use ferron_common::util::{ErrorCode, ERROR_CODE_HEADER_NAME, MEMORY_BUDGET};
 use futures_util::stream::TryStreamExt;
 use http_body_util::combinators::BoxBody;
-use http_body_util::{BodyExt, Empty, Full, StreamBody};
+use http_body_util::{BodyExt, Empty, Full, Limited, StreamBody};
 use hyper::body::{Body, Bytes, Frame};
 use hyper::header::{HeaderName, HeaderValue};
 use hyper::{header, HeaderMap, Method, Request, Response, StatusCode};
@@ -24,25 +27,36 @@ use tokio_util::io::ReaderStream;
 use crate::config::{ServerConfiguration, ServerConfigurationValue, ServerConfigurations};
 use crate::get_value;
 use crate::runtime::timeout;
+use crate::setup::shutdown::is_shutdown_draining;
 #[cfg(feature = "runtime-monoio")]
 use crate::util::MonoioFileStreamNoSpawn;
 use crate::util::{
-  generate_access_log_message, generate_default_error_page, replace_header_placeholders, sanitize_url, SERVER_SOFTWARE,
+  buffer_request_body, generate_access_log_message, generate_access_log_record, generate_default_error_page,
+  replace_header_placeholders, sanitize_url, BodyMatchBufferResult, ClientAbortBody, ClientAbortGuard,
+  ClientAbortPhase, RequestHeaderLimit, RequestHeaderLimits, SERVER_SOFTWARE,
 };
 
-use ferron_common::modules::{ModuleHandlers, RequestData, SocketData};
-use ferron_common::{get_entries, get_entry};
+use ferron_common::modules::{ModuleHandlers, RequestData, RequestTags, SocketData};
+use ferron_common::{get_entries, get_entry, get_values};
 
-/// Generates an error response
+/// Generates an error response. The internal error code is obtained from the `X-Ferron-Error-Code` header,
+/// and is exposed in the response only if the `expose_error_codes` directive is enabled.
 async fn generate_error_response(
   status_code: StatusCode,
   config: &ServerConfiguration,
   headers: &Option<HeaderMap>,
 ) -> Response<BoxBody<Bytes, std::io::Error>> {
-  let bare_body = generate_default_error_page(
+  let error_code = headers
+    .as_ref()
+    .and_then(ErrorCode::from_headers)
+    .filter(|_| get_value!("expose_error_codes", config).and_then(|v| v.as_bool()) == Some(true));
+  let mut bare_body = generate_default_error_page(
     status_code,
     get_value!("server_administrator_email", config).and_then(|v| v.as_str()),
   );
+  if let Some(error_code) = error_code {
+    bare_body.push_str(&format!("\n<!-- Error code: {error_code} -->\n"));
+  }
   let mut content_length: Option<u64> = bare_body.len().try_into().ok();
   let mut response_body = Full::new(Bytes::from(bare_body)).map_err(|e| match e {}).boxed();
 
@@ -111,12 +125,16 @@ async fn generate_error_response(
   if let Some(headers) = headers {
     let headers_iter = headers.iter();
     for (name, value) in headers_iter {
-      if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+      if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH && name != ERROR_CODE_HEADER_NAME {
         response_builder = response_builder.header(name, value);
       }
     }
   }
 
+  if let Some(error_code) = error_code {
+    response_builder = response_builder.header(ERROR_CODE_HEADER_NAME, error_code.to_string());
+  }
+
   if let Some(content_length) = content_length {
     response_builder = response_builder.header(header::CONTENT_LENGTH, content_length);
   }
@@ -150,9 +168,19 @@ async fn log_access(
     log_format,
     log_json_props,
   );
+  let access_log_record = Arc::new(generate_access_log_record(
+    request_parts,
+    socket_data,
+    auth_user,
+    status_code,
+    content_length,
+  ));
   for logger in loggers {
     logger
-      .send(LogMessage::new(log_message_string.clone(), false))
+      .send(LogMessage::new_access_log(
+        log_message_string.clone(),
+        access_log_record.clone(),
+      ))
       .await
       .unwrap_or_default();
   }
@@ -187,18 +215,12 @@ fn add_custom_headers(
 #[inline]
 fn add_http3_alt_svc_header(response_parts: &mut hyper::http::response::Parts, http3_alt_port: Option<u16>) {
   if let Some(http3_alt_port) = http3_alt_port {
+    let header_value_new = format!("h3=\":{http3_alt_port}\", h3-29=\":{http3_alt_port}\"");
     if let Ok(header_value) = match response_parts.headers.get(header::ALT_SVC) {
-      Some(value) => {
-        let header_value_old = String::from_utf8_lossy(value.as_bytes());
-        let header_value_new = format!("h3=\":{http3_alt_port}\", h3-29=\":{http3_alt_port}\"");
-
-        if header_value_old != header_value_new {
-          HeaderValue::from_bytes(format!("{header_value_old}, {header_value_new}").as_bytes())
-        } else {
-          HeaderValue::from_bytes(header_value_old.as_bytes())
-        }
-      }
-      None => HeaderValue::from_bytes(format!("h3=\":{http3_alt_port}\", h3-29=\":{http3_alt_port}\"").as_bytes()),
+      Some(value) if value.as_bytes() == header_value_new.as_bytes() => return,
+      Some(value) => HeaderValue::from_bytes(&[value.as_bytes(), b", ", header_value_new.as_bytes()].concat()),
+      // The formatted header value is moved into the header value without copying
+      None => HeaderValue::from_maybe_shared(Bytes::from(header_value_new)),
     } {
       response_parts.headers.insert(header::ALT_SVC, header_value);
     }
@@ -298,6 +320,28 @@ fn build_custom_headers(
   (headers_to_add, headers_to_replace, headers_to_remove)
 }
 
+/// Checks the request expectations (the "Expect" header) and the declared request body size against the configuration.
+/// Returns the status code of the final response, if the request is rejected before the request body is received.
+/// The "100 Continue" interim response is sent by the HTTP implementation only after a module starts reading the request body,
+/// so requests rejected by the modules (for example, because of missing authentication) don't have to be uploaded.
+fn check_request_expectations(config: &ServerConfiguration, request_headers: &HeaderMap) -> Option<StatusCode> {
+  if let Some(expect) = request_headers.get(header::EXPECT) {
+    // "100-continue" is the only expectation defined in RFC 9110
+    if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue")
+      || get_value!("expect_continue", config).and_then(|v| v.as_str()) == Some("reject")
+    {
+      return Some(StatusCode::EXPECTATION_FAILED);
+    }
+  }
+
+  let max_request_body_size = get_value!("max_request_body_size", config).and_then(|v| v.as_size())?;
+  let content_length = request_headers
+    .get(header::CONTENT_LENGTH)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.parse::<u64>().ok())?;
+  (content_length > max_request_body_size).then_some(StatusCode::PAYLOAD_TOO_LARGE)
+}
+
 /// Helper function to apply all response headers and log if needed
 #[allow(clippy::too_many_arguments)]
 async fn finalize_response_and_log(
@@ -457,14 +501,22 @@ async fn execute_response_modifying_handlers(
         for logger in loggers {
           logger
             .send(LogMessage::new(
-              format!("Unexpected error while serving a request: {err}"),
+              format!(
+                "Unexpected error while serving a request ({}): {err}",
+                ErrorCode::RESPONSE_HANDLER_FAILED
+              ),
               true,
             ))
             .await
             .unwrap_or_default();
         }
 
-        let error_response = generate_error_response(StatusCode::INTERNAL_SERVER_ERROR, configuration, &None).await;
+        let error_response = generate_error_response(
+          StatusCode::INTERNAL_SERVER_ERROR,
+          configuration,
+          &Some(ErrorCode::RESPONSE_HANDLER_FAILED.to_headers()),
+        )
+        .await;
 
         let final_response = finalize_response_and_log(
           error_response,
@@ -642,9 +694,96 @@ fn sanitize_url_request(
   Ok((request, false))
 }
 
-/// The HTTP request handler, with timeout
+/// The HTTP request handler, with client disconnect handling.
+/// If the client disconnects, the request handler future is dropped, which cancels the request handling
+/// (including the requests to backend servers), unless there is a grace period configured.
 #[allow(clippy::too_many_arguments)]
 pub async fn request_handler(
+  request: Request<BoxBody<Bytes, std::io::Error>>,
+  client_address: SocketAddr,
+  server_address: SocketAddr,
+  encrypted: bool,
+  configurations: Arc<ServerConfigurations>,
+  http3_alt_port: Option<u16>,
+  acme_http_01_resolvers: Arc<tokio::sync::RwLock<Vec<crate::acme::Http01DataLock>>>,
+  proxy_protocol_client_address: Option<SocketAddr>,
+  proxy_protocol_server_address: Option<SocketAddr>,
+  tls_sni: Option<String>,
+  tls_alpn: Option<String>,
+) -> Result<Response<BoxBody<Bytes, std::io::Error>>, anyhow::Error> {
+  let global_configuration = configurations.find_global_configuration();
+  let metrics_sender = global_configuration
+    .as_ref()
+    .filter(|c| !c.observability.metric_channels.is_empty())
+    .map(|c| MetricsMultiSender::new_multiple(c.observability.metric_channels.clone()));
+  let client_abort_grace = global_configuration
+    .as_deref()
+    .and_then(|c| get_value!("client_abort_grace", c))
+    .and_then(|v| v.as_duration())
+    .filter(|d| !d.is_zero());
+  let is_head_request = request.method() == Method::HEAD;
+
+  // The guard records the request as client-aborted, if the future is dropped before the response is ready
+  let mut client_abort_guard = ClientAbortGuard::new(metrics_sender.clone(), ClientAbortPhase::Request);
+  let request_handler_future = request_handler_inner(
+    request,
+    client_address,
+    server_address,
+    encrypted,
+    configurations,
+    http3_alt_port,
+    acme_http_01_resolvers,
+    proxy_protocol_client_address,
+    proxy_protocol_server_address,
+    tls_sni,
+    tls_alpn,
+  );
+  let result = if let Some(client_abort_grace) = client_abort_grace {
+    // Handle the request in a separate task, so that it can continue for the grace period after the client disconnects
+    let (mut result_tx, result_rx) = tokio::sync::oneshot::channel();
+    crate::runtime::spawn(async move {
+      let mut request_handler_future = Box::pin(request_handler_future);
+      let result = crate::runtime::select! {
+        biased;
+
+        result = &mut request_handler_future => Some(result),
+        _ = result_tx.closed() => None,
+      };
+      match result {
+        Some(result) => result_tx.send(result).unwrap_or_default(),
+        None => {
+          let _ = timeout(client_abort_grace, request_handler_future).await;
+        }
+      }
+    });
+    result_rx
+      .await
+      .unwrap_or_else(|_| Err(anyhow::anyhow!("The request handler task was cancelled")))
+  } else {
+    request_handler_future.await
+  };
+  client_abort_guard.disarm();
+
+  let response = result?;
+  let response_status = response.status();
+  if is_head_request
+    || response_status.is_informational()
+    || response_status == StatusCode::NO_CONTENT
+    || response_status == StatusCode::NOT_MODIFIED
+  {
+    // These responses have no response body sent to the client
+    return Ok(response);
+  }
+  let (response_parts, response_body) = response.into_parts();
+  Ok(Response::from_parts(
+    response_parts,
+    ClientAbortBody::new(response_body, metrics_sender).boxed(),
+  ))
+}
+
+/// The HTTP request handler, with timeout
+#[allow(clippy::too_many_arguments)]
+async fn request_handler_inner(
   mut request: Request<BoxBody<Bytes, std::io::Error>>,
   client_address: SocketAddr,
   server_address: SocketAddr,
@@ -654,6 +793,8 @@ pub async fn request_handler(
   acme_http_01_resolvers: Arc<tokio::sync::RwLock<Vec<crate::acme::Http01DataLock>>>,
   proxy_protocol_client_address: Option<SocketAddr>,
   proxy_protocol_server_address: Option<SocketAddr>,
+  tls_sni: Option<String>,
+  tls_alpn: Option<String>,
 ) -> Result<Response<BoxBody<Bytes, std::io::Error>>, anyhow::Error> {
   // Global configuration
   let global_configuration = configurations.find_global_configuration();
@@ -682,8 +823,11 @@ pub async fn request_handler(
   let timeout_duration = if timeout_from_config.is_some_and(|v| v.is_null()) {
     None
   } else {
-    let timeout_millis = timeout_from_config.and_then(|v| v.as_i128()).unwrap_or(300000) as u64;
-    Some(Duration::from_millis(timeout_millis))
+    Some(
+      timeout_from_config
+        .and_then(|v| v.as_duration())
+        .unwrap_or(Duration::from_millis(300000)),
+    )
   };
   let timeout_instant = std::time::Instant::now();
 
@@ -725,6 +869,8 @@ pub async fn request_handler(
     remote_addr: proxy_protocol_client_address.unwrap_or(client_address),
     local_addr: proxy_protocol_server_address.unwrap_or(server_address),
     encrypted,
+    tls_sni,
+    tls_alpn,
   };
 
   // Sanitize "Host" header
@@ -831,7 +977,10 @@ pub async fn request_handler(
       for logger in global_loggers {
         logger
           .send(LogMessage::new(
-            format!("Cannot determine server configuration: {err}"),
+            format!(
+              "Cannot determine server configuration ({}): {err}",
+              ErrorCode::CONFIGURATION_NOT_FOUND
+            ),
             true,
           ))
           .await
@@ -860,7 +1009,10 @@ pub async fn request_handler(
       for logger in global_loggers {
         logger
           .send(LogMessage::new(
-            "Cannot determine server configuration: No matching configuration found".to_string(),
+            format!(
+              "Cannot determine server configuration ({}): No matching configuration found",
+              ErrorCode::CONFIGURATION_NOT_FOUND
+            ),
             true,
           ))
           .await
@@ -900,6 +1052,67 @@ pub async fn request_handler(
     request = Request::from_parts(request_parts, request_body);
   }
 
+  // Check the request headers against the request header limits
+  if let Some(exceeded_limit) =
+    RequestHeaderLimits::from_configuration(global_configuration.as_deref()).check(request.headers())
+  {
+    for logger in &configuration.observability.log_channels {
+      logger
+        .send(LogMessage::new(
+          match exceeded_limit {
+            RequestHeaderLimit::Size => {
+              "The request headers exceed the maximum request header size (the `max_request_header_size` directive)"
+            }
+            RequestHeaderLimit::Count => {
+              "The request headers exceed the maximum number of request headers (the `max_header_count` directive)"
+            }
+          }
+          .to_string(),
+          true,
+        ))
+        .await
+        .unwrap_or_default();
+    }
+    if !configuration.observability.metric_channels.is_empty() {
+      MetricsMultiSender::new_multiple(configuration.observability.metric_channels.clone())
+        .send(Metric::new(
+          "ferron.http.server.header_limit_exceeded",
+          vec![(
+            "ferron.http.request.header_limit",
+            MetricAttributeValue::String(exceeded_limit.as_metric_value().to_string()),
+          )],
+          MetricType::Counter,
+          MetricValue::U64(1),
+          Some("{request}"),
+          Some("Number of requests rejected, because the request headers exceeded the limits."),
+        ))
+        .await;
+    }
+
+    let response = generate_error_response(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, &configuration, &None).await;
+
+    let (request_parts, _) = request.into_parts();
+    let (headers_to_add, headers_to_replace, headers_to_remove) = build_custom_headers(&configuration, &request_parts);
+
+    return Ok(
+      finalize_response_and_log(
+        response,
+        http3_alt_port,
+        headers_to_add,
+        headers_to_replace,
+        headers_to_remove,
+        &configuration.observability.log_channels,
+        &log_request_parts,
+        &socket_data,
+        None,
+        log_date_format,
+        log_format,
+        log_json_props.as_ref(),
+      )
+      .await,
+    );
+  }
+
   // Sanitize the URL, if the URL sanitizer is enabled
   if !get_value!("disable_url_sanitizer", configuration)
     .and_then(|v| v.as_bool())
@@ -931,7 +1144,10 @@ pub async fn request_handler(
               for logger in &configuration.observability.log_channels {
                 logger
                   .send(LogMessage::new(
-                    format!("Cannot determine server configuration: {err}"),
+                    format!(
+                      "Cannot determine server configuration ({}): {err}",
+                      ErrorCode::CONFIGURATION_NOT_FOUND
+                    ),
                     true,
                   ))
                   .await
@@ -999,7 +1215,86 @@ pub async fn request_handler(
     };
   }
 
-  let (request_parts, request_body) = request.into_parts();
+  // Classify the request, and find the server configuration again, so that the conditions can match the request tags
+  let (mut request_parts, mut request_body) = request.into_parts();
+  let request_tags = configurations.classify_request(&configuration.filters, &request_parts, &socket_data);
+  if !request_tags.is_empty() {
+    let request_tags = RequestTags(request_tags);
+    if let Some(log_request_parts) = log_request_parts.as_mut() {
+      log_request_parts.extensions.insert(request_tags.clone());
+    }
+    request_parts.extensions.insert(request_tags);
+    if let Ok(Some(new_configuration)) =
+      configurations.find_configuration(&request_parts, hostname_determinant.as_deref(), &socket_data)
+    {
+      if let Some(new_config2) = new_configuration.get_default().cloned() {
+        configuration_error_handler_lookup = new_configuration;
+        configuration = new_config2;
+        log_date_format = get_value!("log_date_format", configuration).and_then(|v| v.as_str());
+        log_format = get_value!("log_format", configuration).and_then(|v| v.as_str());
+        log_json_props = configuration
+          .entries
+          .get("log_json")
+          .and_then(|entries| entries.get_entry())
+          .map(|entry| entry.props.clone());
+      }
+    }
+  }
+
+  // Buffer the beginning of the request body, and find the server configuration again, so that the conditions can match the request body.
+  // The buffered bytes are reserved in the global memory budget until the request is handled.
+  let mut body_match_reservation = MEMORY_BUDGET.reserve();
+  let mut body_match_memory_budget_exceeded = false;
+  if let Some(body_match_buffer_size) = get_value!("body_match_buffer", configuration)
+    .and_then(|v| v.as_size())
+    .filter(|v| *v > 0)
+  {
+    if request_parts.method != Method::GET && request_parts.method != Method::HEAD && !request_body.is_end_stream() {
+      let (buffered_body, buffer_result, new_request_body) = buffer_request_body(
+        request_body,
+        body_match_buffer_size as usize,
+        &mut body_match_reservation,
+      )
+      .await;
+      request_body = new_request_body;
+      body_match_memory_budget_exceeded = buffer_result == BodyMatchBufferResult::MemoryBudgetExceeded;
+      if !configuration.observability.metric_channels.is_empty() {
+        MetricsMultiSender::new_multiple(configuration.observability.metric_channels.clone())
+          .send(Metric::new(
+            "ferron.http.server.body_match_buffered",
+            vec![(
+              "ferron.body_match.result",
+              MetricAttributeValue::String(buffer_result.as_metric_value().to_string()),
+            )],
+            MetricType::Counter,
+            MetricValue::U64(1),
+            Some("{request}"),
+            Some("Number of requests with request bodies buffered for body-based routing."),
+          ))
+          .await;
+      }
+      request_parts.extensions.insert(buffered_body);
+      // The request rejected because of the memory budget isn't routed by the partially buffered request body
+      if !body_match_memory_budget_exceeded {
+        if let Ok(Some(new_configuration)) =
+          configurations.find_configuration(&request_parts, hostname_determinant.as_deref(), &socket_data)
+        {
+          if let Some(new_config2) = new_configuration.get_default().cloned() {
+            configuration_error_handler_lookup = new_configuration;
+            configuration = new_config2;
+            log_date_format = get_value!("log_date_format", configuration).and_then(|v| v.as_str());
+            log_format = get_value!("log_format", configuration).and_then(|v| v.as_str());
+            log_json_props = configuration
+              .entries
+              .get("log_json")
+              .and_then(|entries| entries.get_entry())
+              .map(|entry| entry.props.clone());
+          }
+        }
+      }
+    }
+  }
+
   let (headers_to_add, headers_to_replace, headers_to_remove) = build_custom_headers(&configuration, &request_parts);
   let mut request = Request::from_parts(request_parts, request_body);
 
@@ -1035,6 +1330,100 @@ pub async fn request_handler(
     );
   }
 
+  // Requests, whose request bodies can't be buffered for body-based routing because of the memory budget, are rejected
+  if body_match_memory_budget_exceeded {
+    let response = generate_error_response(
+      StatusCode::SERVICE_UNAVAILABLE,
+      &configuration,
+      &Some(ErrorCode::MEMORY_BUDGET_EXCEEDED.to_headers()),
+    )
+    .await;
+    return Ok(
+      finalize_response_and_log(
+        response,
+        http3_alt_port,
+        headers_to_add,
+        headers_to_replace,
+        headers_to_remove,
+        &configuration.observability.log_channels,
+        &log_request_parts,
+        &socket_data,
+        None,
+        log_date_format,
+        log_format,
+        log_json_props.as_ref(),
+      )
+      .await,
+    );
+  }
+
+  // Health checks fail during the shutdown drain period, so that load balancers stop sending new requests
+  if is_shutdown_draining()
+    && get_values!("shutdown_drain_health_check", configuration)
+      .iter()
+      .any(|path| path.as_str() == Some(request.uri().path()))
+  {
+    let response = generate_error_response(
+      StatusCode::SERVICE_UNAVAILABLE,
+      &configuration,
+      &Some(ErrorCode::SHUTDOWN_DRAINING.to_headers()),
+    )
+    .await;
+    return Ok(
+      finalize_response_and_log(
+        response,
+        http3_alt_port,
+        headers_to_add,
+        headers_to_replace,
+        headers_to_remove,
+        &configuration.observability.log_channels,
+        &log_request_parts,
+        &socket_data,
+        None,
+        log_date_format,
+        log_format,
+        log_json_props.as_ref(),
+      )
+      .await,
+    );
+  }
+
+  // Requests with unmet expectations or too large request bodies are rejected before the request body is received
+  if let Some(status_code) = check_request_expectations(&configuration, request.headers()) {
+    let response = generate_error_response(status_code, &configuration, &None).await;
+    return Ok(
+      finalize_response_and_log(
+        response,
+        http3_alt_port,
+        headers_to_add,
+        headers_to_replace,
+        headers_to_remove,
+        &configuration.observability.log_channels,
+        &log_request_parts,
+        &socket_data,
+        None,
+        log_date_format,
+        log_format,
+        log_json_props.as_ref(),
+      )
+      .await,
+    );
+  }
+
+  // Limit the size of the request bodies, which might not declare their size with the "Content-Length" header
+  if let Some(max_request_body_size) = get_value!("max_request_body_size", configuration).and_then(|v| v.as_size()) {
+    let (request_parts, request_body) = request.into_parts();
+    request = Request::from_parts(
+      request_parts,
+      Limited::new(
+        request_body,
+        usize::try_from(max_request_body_size).unwrap_or(usize::MAX),
+      )
+      .map_err(std::io::Error::other)
+      .boxed(),
+    );
+  }
+
   // HTTP-01 ACME challenge for automatic TLS
   let acme_http_01_resolvers_inner = acme_http_01_resolvers.read().await;
   if !acme_http_01_resolvers_inner.is_empty() {
@@ -1076,8 +1465,80 @@ pub async fn request_handler(
   };
   drop(acme_http_01_resolvers_inner);
 
+  // Expose the groups captured by the regular expression hostname as request variables
+  let mut request_variables = HashMap::new();
+  if let Some(hostname) = hostname_determinant.as_deref() {
+    if configuration.filters.has_regex_hostname() {
+      request_variables = configurations.capture_host_variables(&configuration.filters, hostname);
+    }
+  }
+
+  // Synthesize the virtual host configuration for hostnames provisioned by the dynamic host provider
+  if let (Some(dynamic_hosts), Some(hostname)) =
+    (configurations.dynamic_hosts.as_ref(), hostname_determinant.as_deref())
+  {
+    if configuration.filters.hostname.is_none() {
+      match dynamic_hosts.resolve(hostname).await {
+        Ok(Some(dynamic_host)) => {
+          configuration = Arc::new(dynamic_host.synthesize_configuration(&configuration, hostname));
+          log_date_format = get_value!("log_date_format", configuration).and_then(|v| v.as_str());
+          log_format = get_value!("log_format", configuration).and_then(|v| v.as_str());
+          log_json_props = configuration
+            .entries
+            .get("log_json")
+            .and_then(|entries| entries.get_entry())
+            .map(|entry| entry.props.clone());
+          request_variables = dynamic_host.variables.clone();
+        }
+        result => {
+          let (status_code, error_code) = match result {
+            Err(err) => {
+              for logger in &configuration.observability.log_channels {
+                logger
+                  .send(LogMessage::new(
+                    format!(
+                      "Cannot resolve the \"{hostname}\" host using the dynamic host provider ({}): {err}",
+                      ErrorCode::DYNAMIC_HOST_RESOLUTION_FAILED
+                    ),
+                    true,
+                  ))
+                  .await
+                  .unwrap_or_default();
+              }
+              (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Some(ErrorCode::DYNAMIC_HOST_RESOLUTION_FAILED.to_headers()),
+              )
+            }
+            _ => (StatusCode::NOT_FOUND, None),
+          };
+          let response = generate_error_response(status_code, &configuration, &error_code).await;
+
+          return Ok(
+            finalize_response_and_log(
+              response,
+              http3_alt_port,
+              headers_to_add,
+              headers_to_replace,
+              headers_to_remove,
+              &configuration.observability.log_channels,
+              &log_request_parts,
+              &socket_data,
+              None,
+              log_date_format,
+              log_format,
+              log_json_props.as_ref(),
+            )
+            .await,
+          );
+        }
+      }
+    }
+  }
+
   let mut error_logger = if !configuration.observability.log_channels.is_empty() {
     ErrorLogger::new_multiple(configuration.observability.log_channels.clone())
+      .with_level_filter(configuration.observability.log_level_filter.clone())
   } else {
     ErrorLogger::without_logger()
   };
@@ -1101,6 +1562,16 @@ pub async fn request_handler(
     vec![]
   };
 
+  // Streaming reverse proxy responses (like Server-Sent Events or long-polling responses) can take longer than the timeout
+  let timeout_duration = if get_value!("proxy_sse", configuration)
+    .and_then(|v| v.as_bool())
+    .unwrap_or(false)
+  {
+    None
+  } else {
+    timeout_duration
+  };
+
   // Obtain module handlers
   let mut module_handlers = Vec::with_capacity(configuration.modules.len());
   for module in &configuration.modules {
@@ -1112,6 +1583,9 @@ pub async fn request_handler(
     auth_user: None,
     original_url: None,
     error_status_code: None,
+    peer_address: Some(client_address),
+    proxy_protocol_address: proxy_protocol_client_address,
+    variables: request_variables,
   });
   let mut executed_handlers = Vec::new();
   let (request_parts, request_body) = request.into_parts();
@@ -1275,6 +1749,7 @@ pub async fn request_handler(
                       .map(|entry| entry.props.clone());
                     error_logger = if !configuration.observability.log_channels.is_empty() {
                       ErrorLogger::new_multiple(configuration.observability.log_channels.clone())
+                        .with_level_filter(configuration.observability.log_level_filter.clone())
                     } else {
                       ErrorLogger::without_logger()
                     };
@@ -1338,14 +1813,22 @@ pub async fn request_handler(
         }
       }
       Err(err) => {
-        let response = generate_error_response(StatusCode::INTERNAL_SERVER_ERROR, &configuration, &None).await;
+        let response = generate_error_response(
+          StatusCode::INTERNAL_SERVER_ERROR,
+          &configuration,
+          &Some(ErrorCode::REQUEST_HANDLER_FAILED.to_headers()),
+        )
+        .await;
         let err_string = err.to_string();
 
         if !err_string.is_empty() {
           for logger in &configuration.observability.log_channels {
             logger
               .send(LogMessage::new(
-                format!("Unexpected error while serving a request: {err}"),
+                format!(
+                  "Unexpected error while serving a request ({}): {err}",
+                  ErrorCode::REQUEST_HANDLER_FAILED
+                ),
                 true,
               ))
               .await
@@ -4,38 +4,76 @@ use std::{
   path::PathBuf,
   str::FromStr,
   sync::Arc,
-  time::Duration,
+  time::{Duration, SystemTime},
 };
 
 use base64::Engine;
 use instant_acme::{ExternalAccountKey, LetsEncrypt};
-use rustls::{client::WebPkiServerVerifier, crypto::CryptoProvider, ClientConfig};
+use rustls::{client::WebPkiServerVerifier, crypto::CryptoProvider, ClientConfig, RootCertStore};
 use rustls_platform_verifier::BuilderVerifierExt;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use tokio_util::sync::CancellationToken;
 use xxhash_rust::xxh3::xxh3_128;
 
 use crate::acme::{
-  add_domain_to_cache, convert_on_demand_config, provision_certificate, AcmeConfig, AcmeOnDemandConfig,
+  add_domain_to_cache, convert_on_demand_config, load_renewal_schedule, provision_certificate, schedule_next_attempt,
+  AcmeConfig, AcmeIssuer, AcmeOnDemandConfig,
 };
-use ferron_common::{get_entry, get_value, util::match_hostname};
+use crate::util::load_certs;
+use ferron_common::{get_entries, get_entry, get_value, util::match_hostname};
 use ferron_common::{logging::ErrorLogger, util::NoServerVerifier};
 
+/// The maximum time the ACME scheduler sleeps for, so that the system clock changes (like after suspending) are noticed
+const MAX_SCHEDULER_SLEEP: Duration = Duration::from_secs(3600);
+
 /// Builds a Rustls client configuration for ACME.
 pub fn build_rustls_client_config(
   server_configuration: &ferron_common::config::ServerConfiguration,
   crypto_provider: Arc<CryptoProvider>,
 ) -> Result<ClientConfig, Box<dyn Error + Send + Sync>> {
-  build_raw_rustls_client_config(
-    get_value!("auto_tls_no_verification", server_configuration)
-      .and_then(|v| v.as_bool())
-      .unwrap_or(false),
-    crypto_provider,
+  let no_verification = get_value!("auto_tls_no_verification", server_configuration)
+    .and_then(|v| v.as_bool())
+    .unwrap_or(false);
+  if !no_verification {
+    if let Some(directory_ca_path) = get_value!("auto_tls_directory_ca", server_configuration).and_then(|v| v.as_str())
+    {
+      return build_rustls_client_config_with_ca(directory_ca_path, crypto_provider);
+    }
+  }
+  build_raw_rustls_client_config(no_verification, crypto_provider)
+}
+
+/// Builds a Rustls client configuration for ACME, which trusts only the CA certificates from the specified file.
+/// This is used for ACME servers with TLS certificates issued by an internal CA (like step-ca).
+fn build_rustls_client_config_with_ca(
+  directory_ca_path: &str,
+  crypto_provider: Arc<CryptoProvider>,
+) -> Result<ClientConfig, Box<dyn Error + Send + Sync>> {
+  let certificates = load_certs(directory_ca_path).map_err(|err| {
+    anyhow::anyhow!("Failed to load the ACME directory CA certificates from \"{directory_ca_path}\": {err}")
+  })?;
+  let mut roots = RootCertStore::empty();
+  for certificate in certificates {
+    roots
+      .add(certificate)
+      .map_err(|err| anyhow::anyhow!("Invalid ACME directory CA certificate in \"{directory_ca_path}\": {err}"))?;
+  }
+  if roots.is_empty() {
+    Err(anyhow::anyhow!(
+      "No ACME directory CA certificates found in \"{directory_ca_path}\""
+    ))?
+  }
+
+  Ok(
+    ClientConfig::builder_with_provider(crypto_provider)
+      .with_safe_default_protocol_versions()?
+      .with_root_certificates(roots)
+      .with_no_client_auth(),
   )
 }
 
 /// Builds a raw Rustls client configuration for ACME.
-fn build_raw_rustls_client_config(
+pub fn build_raw_rustls_client_config(
   no_verification: bool,
   crypto_provider: Arc<CryptoProvider>,
 ) -> Result<ClientConfig, Box<dyn Error + Send + Sync>> {
@@ -90,16 +128,122 @@ pub fn parse_eab(
         )
       })
     {
-      match base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(eab_key_hmac.trim_end_matches('=')) {
-        Ok(decoded_key) => Some(Arc::new(ExternalAccountKey::new(eab_key_id.to_string(), &decoded_key))),
-        Err(err) => Err(anyhow::anyhow!("Failed to decode EAB key HMAC: {}", err))?,
-      }
+      Some(decode_eab(eab_key_id, eab_key_hmac)?)
     } else {
       None
     },
   )
 }
 
+/// Decodes the External Account Binding (EAB) key.
+fn decode_eab(eab_key_id: &str, eab_key_hmac: &str) -> Result<Arc<ExternalAccountKey>, anyhow::Error> {
+  // Some ACME servers issue the EAB HMAC keys in the standard Base64 encoding instead of the URL-safe one
+  let eab_key_hmac = eab_key_hmac.trim_end_matches('=').replace('+', "-").replace('/', "_");
+  match base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(eab_key_hmac) {
+    Ok(decoded_key) => Ok(Arc::new(ExternalAccountKey::new(eab_key_id.to_string(), &decoded_key))),
+    Err(err) => Err(anyhow::anyhow!("Failed to decode EAB key HMAC: {}", err)),
+  }
+}
+
+/// Resolves the ACME issuers based on the server configuration. Returns the preferred ACME issuer
+/// and the fallback ACME issuers, in the order specified in the `auto_tls_issuer` directive.
+/// If the `auto_tls_issuer` directive isn't specified, the ACME issuer is determined by the `auto_tls_directory` directive.
+pub fn resolve_acme_issuers(
+  server_configuration: &ferron_common::config::ServerConfiguration,
+  crypto_provider: Arc<CryptoProvider>,
+) -> Result<(AcmeIssuer, Vec<AcmeIssuer>), Box<dyn Error + Send + Sync>> {
+  let issuer_names = get_entry!("auto_tls_issuer", server_configuration)
+    .map(|entry| entry.values.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+    .unwrap_or_default();
+  let Some((preferred_issuer_name, fallback_issuer_names)) = issuer_names.split_first() else {
+    let directory = resolve_acme_directory(server_configuration);
+    return Ok((
+      AcmeIssuer {
+        name: directory.clone(),
+        rustls_client_config: build_rustls_client_config(server_configuration, crypto_provider)?,
+        directory,
+        eab_key: parse_eab(server_configuration)?,
+        account: None,
+      },
+      Vec::new(),
+    ));
+  };
+
+  let preferred_issuer = resolve_acme_issuer(server_configuration, preferred_issuer_name, crypto_provider.clone())?;
+  let fallback_issuers = fallback_issuer_names
+    .iter()
+    .map(|issuer_name| resolve_acme_issuer(server_configuration, issuer_name, crypto_provider.clone()))
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok((preferred_issuer, fallback_issuers))
+}
+
+/// Resolves the ACME issuer with the specified name. The ACME issuers defined with the `auto_tls_issuer_directory` directive
+/// take precedence over the built-in ones.
+fn resolve_acme_issuer(
+  server_configuration: &ferron_common::config::ServerConfiguration,
+  issuer_name: &str,
+  crypto_provider: Arc<CryptoProvider>,
+) -> Result<AcmeIssuer, Box<dyn Error + Send + Sync>> {
+  let no_verification = get_value!("auto_tls_no_verification", server_configuration)
+    .and_then(|v| v.as_bool())
+    .unwrap_or(false);
+  let issuer_entry = get_entries!("auto_tls_issuer_directory", server_configuration).and_then(|entries| {
+    entries
+      .inner
+      .iter()
+      .rev()
+      .find(|entry| entry.values.first().and_then(|v| v.as_str()) == Some(issuer_name))
+  });
+
+  if let Some(issuer_entry) = issuer_entry {
+    let directory = issuer_entry
+      .values
+      .get(1)
+      .and_then(|v| v.as_str())
+      .ok_or(anyhow::anyhow!(
+        "Invalid ACME directory URL for the \"{issuer_name}\" ACME issuer"
+      ))?;
+    let eab_key = match (
+      issuer_entry.props.get("eab_key_id").and_then(|v| v.as_str()),
+      issuer_entry.props.get("eab_key_hmac").and_then(|v| v.as_str()),
+    ) {
+      (Some(eab_key_id), Some(eab_key_hmac)) => Some(decode_eab(eab_key_id, eab_key_hmac)?),
+      _ => None,
+    };
+    let rustls_client_config = match issuer_entry
+      .props
+      .get("directory_ca")
+      .and_then(|v| v.as_str())
+      .filter(|_| !no_verification)
+    {
+      Some(directory_ca_path) => build_rustls_client_config_with_ca(directory_ca_path, crypto_provider)?,
+      None => build_raw_rustls_client_config(no_verification, crypto_provider)?,
+    };
+    Ok(AcmeIssuer {
+      name: issuer_name.to_string(),
+      rustls_client_config,
+      directory: directory.to_string(),
+      eab_key,
+      account: None,
+    })
+  } else {
+    let directory = match issuer_name {
+      "letsencrypt" => LetsEncrypt::Production.url(),
+      "letsencrypt-staging" => LetsEncrypt::Staging.url(),
+      _ => Err(anyhow::anyhow!(
+        "The \"{issuer_name}\" ACME issuer isn't defined (define it using the `auto_tls_issuer_directory` directive)"
+      ))?,
+    };
+    Ok(AcmeIssuer {
+      name: issuer_name.to_string(),
+      rustls_client_config: build_raw_rustls_client_config(no_verification, crypto_provider)?,
+      directory: directory.to_string(),
+      eab_key: None,
+      account: None,
+    })
+  }
+}
+
 pub fn resolve_acme_cache_path(
   server_configuration: &ferron_common::config::ServerConfiguration,
 ) -> Result<Option<PathBuf>, anyhow::Error> {
@@ -164,10 +308,14 @@ pub async fn background_acme_task(
   // Wrap ACME configurations in a mutex
   let acme_configs_mutex = Arc::new(tokio::sync::Mutex::new(acme_configs));
 
+  // Notified when an ACME configuration is added for automatic TLS on demand
+  let acme_configs_added = Arc::new(Notify::new());
+
   let prevent_file_race_conditions_sem = Arc::new(tokio::sync::Semaphore::new(1));
 
   let acme_logger_clone = acme_logger.clone();
   let acme_configs_mutex_clone = acme_configs_mutex.clone();
+  let acme_configs_added_clone = acme_configs_added.clone();
   if !acme_on_demand_configs.is_empty() {
     let cancel_token_clone = cancel_token.clone();
     let cancelled_future = async move {
@@ -192,6 +340,7 @@ pub async fn background_acme_task(
           acme_logger_clone,
           crypto_provider,
           acme_configs_mutex_clone,
+          acme_configs_added_clone,
           acme_on_demand_configs,
           memory_acme_account_cache_data,
           prevent_file_race_conditions_sem,
@@ -209,6 +358,7 @@ pub async fn background_acme_task(
   });
 
   loop {
+    let mut next_attempt: Option<SystemTime> = None;
     for acme_config in &mut *(tokio::select! {
         biased;
         _ = &mut cancelled_future => {
@@ -216,19 +366,55 @@ pub async fn background_acme_task(
         },
         result = acme_configs_mutex.lock() => result
     }) {
-      if let Err(acme_error) = tokio::select! {
+      // Skip the certificates, which aren't scheduled to be provisioned or renewed yet
+      let renewal_schedule = load_renewal_schedule(acme_config).await;
+      if renewal_schedule.next_attempt() > SystemTime::now() {
+        next_attempt = Some(next_attempt.map_or(renewal_schedule.next_attempt(), |t| {
+          t.min(renewal_schedule.next_attempt())
+        }));
+        continue;
+      }
+
+      let result = tokio::select! {
         biased;
         _ = &mut cancelled_future => {
             return;
         },
         result = provision_certificate(acme_config, &acme_logger) => result
-      } {
+      };
+      let renewal_schedule = schedule_next_attempt(acme_config, result.is_ok()).await;
+      if let Err(acme_error) = result {
         acme_logger
-          .log(&format!("Error while obtaining a TLS certificate: {acme_error}"))
+          .log(&format!(
+            "Error while obtaining a TLS certificate: {acme_error}. The next attempt will be made in {} seconds",
+            renewal_schedule
+              .next_attempt()
+              .duration_since(SystemTime::now())
+              .unwrap_or_default()
+              .as_secs()
+          ))
           .await
       }
+      next_attempt = Some(next_attempt.map_or(renewal_schedule.next_attempt(), |t| {
+        t.min(renewal_schedule.next_attempt())
+      }));
+    }
+
+    // Sleep until the next scheduled attempt, or until an ACME configuration is added for automatic TLS on demand
+    let sleep_duration = next_attempt.map_or(MAX_SCHEDULER_SLEEP, |next_attempt| {
+      next_attempt
+        .duration_since(SystemTime::now())
+        .unwrap_or_default()
+        .min(MAX_SCHEDULER_SLEEP)
+    });
+    tokio::select! {
+      biased;
+      _ = &mut cancelled_future => {
+        return;
+      },
+      _ = acme_configs_added.notified() => {},
+      _ = tokio::time::sleep(sleep_duration) => {}
     }
-    tokio::time::sleep(Duration::from_secs(10)).await;
   }
 }
 
@@ -243,6 +429,7 @@ pub async fn background_on_demand_acme_task(
   acme_logger: Arc<ErrorLogger>,
   crypto_provider: Arc<CryptoProvider>,
   acme_configs_mutex: Arc<tokio::sync::Mutex<Vec<AcmeConfig>>>,
+  acme_configs_added: Arc<Notify>,
   acme_on_demand_configs: Vec<AcmeOnDemandConfig>,
   memory_acme_account_cache_data: Arc<RwLock<HashMap<String, Vec<u8>>>>,
   prevent_file_race_conditions_sem: Arc<tokio::sync::Semaphore>,
@@ -337,6 +524,7 @@ pub async fn background_on_demand_acme_task(
     }
     let (sni_hostname, port) = received_data;
     let acme_configs_mutex = acme_configs_mutex.clone();
+    let acme_configs_added = acme_configs_added.clone();
     let acme_on_demand_configs = acme_on_demand_configs.clone();
     let memory_acme_account_cache_data = memory_acme_account_cache_data.clone();
     let prevent_file_race_conditions_sem = prevent_file_race_conditions_sem.clone();
@@ -359,6 +547,7 @@ pub async fn background_on_demand_acme_task(
             )
             .await,
           );
+          acme_configs_added.notify_one();
           break;
         }
       }
@@ -0,0 +1,603 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use arc_swap::{ArcSwap, ArcSwapOption};
+use async_channel::{Receiver, Sender};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::modules::ModuleLoader;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{header, HeaderMap, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rustls::sign::CertifiedKey;
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::processing::are_requirements_met;
+use crate::config::ConfigurationSnapshot;
+use crate::util::connection_counts;
+
+/// The maximum time, for which the admin API waits for the requested configuration reload to complete
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The channel used by the admin API to request a configuration reload
+pub static ADMIN_RELOAD_CHANNEL: LazyLock<Arc<(Sender<()>, Receiver<()>)>> =
+  LazyLock::new(|| Arc::new(async_channel::bounded(1)));
+
+/// The status of the running server, published after the server configuration is loaded
+static SERVER_STATUS: LazyLock<watch::Sender<Option<Arc<ServerStatus>>>> = LazyLock::new(|| watch::Sender::new(None));
+
+/// The address of the running admin API server, and the task accepting its connections
+static ADMIN_SERVER: Mutex<Option<(String, JoinHandle<()>)>> = Mutex::new(None);
+
+/// The bearer token required for configuration reloads via the admin API (the `admin_token` global directive)
+static ADMIN_TOKEN: ArcSwapOption<String> = ArcSwapOption::const_empty();
+
+/// The error logger of the admin API server, replaced when the server configuration is reloaded
+static ADMIN_LOGGER: LazyLock<ArcSwap<ErrorLogger>> =
+  LazyLock::new(|| ArcSwap::from_pointee(ErrorLogger::without_logger()));
+
+/// A function validating the server configuration before it's reloaded, returning the snapshot of the new configuration
+pub type ConfigurationChecker =
+  Arc<dyn Fn() -> Result<ConfigurationSnapshot, Box<dyn Error + Send + Sync>> + Send + Sync>;
+
+/// A listener accepting client connections
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ListenerStatus {
+  /// The listened socket address
+  pub address: SocketAddr,
+  /// The transport protocol, either "tcp" or "quic"
+  pub protocol: &'static str,
+  /// Whether the connections are encrypted with TLS
+  pub tls: bool,
+}
+
+/// A TLS certificate obtained with automatic TLS
+#[derive(Clone)]
+pub struct AcmeCertificateStatus {
+  /// The domains, for which the certificate is obtained
+  pub domains: Vec<String>,
+  /// The name of the ACME issuer
+  pub issuer: String,
+  /// The lock for the certified key, which is replaced when the certificate is renewed
+  pub certified_key_lock: Arc<RwLock<Option<Arc<CertifiedKey>>>>,
+}
+
+/// The status of the running server, reported by the admin API
+pub struct ServerStatus {
+  /// The listeners accepting client connections
+  pub listeners: Vec<ListenerStatus>,
+  /// The names of the loaded modules
+  pub modules: Vec<&'static str>,
+  /// The TLS certificates obtained with automatic TLS
+  pub certificates: Vec<AcmeCertificateStatus>,
+  /// The changes made by the last configuration reload
+  pub changes: Vec<String>,
+  /// The time, at which the server configuration was loaded
+  pub loaded_at: SystemTime,
+}
+
+/// Publishes the status of the server, after the server configuration is loaded or reloaded
+pub fn publish_server_status(server_status: ServerStatus) {
+  SERVER_STATUS.send_replace(Some(Arc::new(server_status)));
+}
+
+/// Obtains the names of the modules loaded for any of the server configurations
+pub fn loaded_module_names(
+  module_loader_names: &[&'static str],
+  module_loaders: &[Box<dyn ModuleLoader + Send + Sync>],
+  configurations: &[ServerConfiguration],
+) -> Vec<&'static str> {
+  module_loader_names
+    .iter()
+    .zip(module_loaders)
+    .filter(|(_, module_loader)| {
+      let requirements = module_loader.get_requirements();
+      configurations
+        .iter()
+        .any(|configuration| are_requirements_met(&requirements, configuration))
+    })
+    .map(|(module_loader_name, _)| *module_loader_name)
+    .collect()
+}
+
+/// Checks if the admin API listen address (the `admin_listen` global directive) is valid
+pub fn is_valid_admin_address(address: &str) -> bool {
+  match address.strip_prefix("unix:") {
+    Some(path) => !path.is_empty(),
+    None => address
+      .parse::<SocketAddr>()
+      .is_ok_and(|address| address.ip().is_loopback()),
+  }
+}
+
+/// A listener of the admin API server
+enum AdminListener {
+  Tcp(tokio::net::TcpListener),
+  #[cfg(unix)]
+  Unix(tokio::net::UnixListener),
+}
+
+impl AdminListener {
+  /// Binds the admin API server to the address. Must be called within the Tokio runtime context.
+  fn bind(address: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+    if !is_valid_admin_address(address) {
+      Err(anyhow::anyhow!("Invalid admin API listen address: {address}"))?
+    }
+    match address.strip_prefix("unix:") {
+      #[cfg(unix)]
+      Some(path) => {
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+        // Remove the socket left by the previous server process
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+          std::fs::remove_file(path)?;
+        }
+        // The socket is created in a directory accessible only by the user running the server, and moved to
+        // its path after its permissions are restricted, so that other users can't connect to it in between
+        let socket_path = std::path::Path::new(path);
+        let socket_name = socket_path
+          .file_name()
+          .ok_or_else(|| anyhow::anyhow!("Invalid admin API Unix socket path: {path}"))?;
+        let mut private_directory_name = std::ffi::OsString::from(".");
+        private_directory_name.push(socket_name);
+        private_directory_name.push(format!(".{}", std::process::id()));
+        let private_directory = socket_path.with_file_name(private_directory_name);
+        std::fs::DirBuilder::new().mode(0o700).create(&private_directory)?;
+        let private_socket_path = private_directory.join(socket_name);
+        let bind_result = std::os::unix::net::UnixListener::bind(&private_socket_path).and_then(|listener| {
+          // Only the user running the server can control it
+          std::fs::set_permissions(&private_socket_path, std::fs::Permissions::from_mode(0o600))?;
+          std::fs::rename(&private_socket_path, socket_path)?;
+          Ok(listener)
+        });
+        std::fs::remove_file(&private_socket_path).unwrap_or_default();
+        std::fs::remove_dir(&private_directory).unwrap_or_default();
+        let listener = bind_result?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Unix(tokio::net::UnixListener::from_std(listener)?))
+      }
+      #[cfg(not(unix))]
+      Some(_) => Err(anyhow::anyhow!(
+        "The admin API over Unix sockets isn't supported on this platform"
+      ))?,
+      None => {
+        let listener = std::net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Tcp(tokio::net::TcpListener::from_std(listener)?))
+      }
+    }
+  }
+
+  /// Accepts a connection, and spawns a task serving the admin API over it
+  async fn accept_and_serve(&self, configuration_checker: &ConfigurationChecker) -> Result<(), std::io::Error> {
+    match self {
+      Self::Tcp(listener) => {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(serve_admin_connection(stream, configuration_checker.clone(), true));
+      }
+      #[cfg(unix)]
+      Self::Unix(listener) => {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(serve_admin_connection(stream, configuration_checker.clone(), false));
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Starts the admin API server listening on the specified address (the `admin_listen` global directive),
+/// or stops it, if no address is specified. The server keeps running across configuration reloads,
+/// unless its address changes.
+pub fn configure_admin_server(
+  address: Option<&str>,
+  token: Option<&str>,
+  runtime: &tokio::runtime::Runtime,
+  configuration_checker: ConfigurationChecker,
+  error_logger: ErrorLogger,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  ADMIN_LOGGER.store(Arc::new(error_logger));
+  ADMIN_TOKEN.store(token.map(|token| Arc::new(token.to_string())));
+  let mut admin_server = ADMIN_SERVER
+    .lock()
+    .map_err(|_| anyhow::anyhow!("Can't access the admin API server"))?;
+  if admin_server.as_ref().map(|(address, _)| address.as_str()) == address {
+    return Ok(());
+  }
+
+  if let Some((previous_address, admin_server_task)) = admin_server.take() {
+    // Wait for the listener to be closed, so that its address can be bound again
+    admin_server_task.abort();
+    runtime.block_on(admin_server_task).unwrap_or_default();
+    if let Some(path) = previous_address.strip_prefix("unix:") {
+      std::fs::remove_file(path).unwrap_or_default();
+    }
+  }
+
+  if let Some(address) = address {
+    let listener = {
+      let _runtime_guard = runtime.enter();
+      AdminListener::bind(address)?
+    };
+    let admin_server_task = runtime.spawn(async move {
+      loop {
+        if let Err(err) = listener.accept_and_serve(&configuration_checker).await {
+          ADMIN_LOGGER
+            .load_full()
+            .log(&format!("Can't accept the admin API connection: {err}"))
+            .await;
+          tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+      }
+    });
+    admin_server.replace((address.to_string(), admin_server_task));
+  }
+
+  Ok(())
+}
+
+/// Serves the admin API over the connection. The `Host` header is checked only for TCP connections,
+/// since web browsers can't connect to Unix sockets.
+async fn serve_admin_connection<S>(stream: S, configuration_checker: ConfigurationChecker, check_host: bool)
+where
+  S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  let service = service_fn(move |request: Request<Incoming>| {
+    let configuration_checker = configuration_checker.clone();
+    async move {
+      let (status_code, response_body) = handle_admin_request(
+        request.method(),
+        request.uri().path(),
+        request.headers(),
+        check_host,
+        &configuration_checker,
+      )
+      .await;
+      let response_body = serde_json::to_vec_pretty(&response_body).unwrap_or_default();
+      let mut response_builder = Response::builder()
+        .status(status_code)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, "no-store");
+      if status_code == StatusCode::UNAUTHORIZED {
+        response_builder = response_builder.header(header::WWW_AUTHENTICATE, "Bearer");
+      }
+      Ok::<_, Infallible>(
+        response_builder
+          .body(Full::new(Bytes::from(response_body)))
+          .unwrap_or_default(),
+      )
+    }
+  });
+  http1::Builder::new()
+    .serve_connection(TokioIo::new(stream), service)
+    .await
+    .unwrap_or_default();
+}
+
+/// Handles the admin API request, and returns the response status code and the JSON response body
+async fn handle_admin_request(
+  method: &Method,
+  path: &str,
+  headers: &HeaderMap,
+  check_host: bool,
+  configuration_checker: &ConfigurationChecker,
+) -> (StatusCode, serde_json::Value) {
+  // Web browsers send the `Origin` header with cross-origin requests, and the `Host` header with a domain name
+  // with DNS rebinding attacks, so such requests (possibly sent by websites visited on the same machine) are rejected
+  if headers.contains_key(header::ORIGIN) {
+    return (
+      StatusCode::FORBIDDEN,
+      json!({ "error": "Requests with the Origin header aren't allowed" }),
+    );
+  }
+  if check_host
+    && !headers
+      .get(header::HOST)
+      .and_then(|host| host.to_str().ok())
+      .is_some_and(is_loopback_host)
+  {
+    return (
+      StatusCode::FORBIDDEN,
+      json!({ "error": "The Host header must be a loopback IP address" }),
+    );
+  }
+
+  let allowed_method = match path {
+    "/reload" => Method::POST,
+    "/status" | "/listeners" | "/modules" | "/certificates" | "/connections" => Method::GET,
+    _ => return (StatusCode::NOT_FOUND, json!({ "error": "Not found" })),
+  };
+  if method != allowed_method {
+    return (
+      StatusCode::METHOD_NOT_ALLOWED,
+      json!({ "error": format!("Only the {allowed_method} method is allowed") }),
+    );
+  }
+
+  if path == "/reload" {
+    let Some(token) = ADMIN_TOKEN.load_full() else {
+      return (
+        StatusCode::FORBIDDEN,
+        json!({ "error": "Configuration reloads via the admin API require the `admin_token` directive" }),
+      );
+    };
+    if !is_authorized(headers, &token) {
+      return (
+        StatusCode::UNAUTHORIZED,
+        json!({ "error": "Invalid or missing bearer token" }),
+      );
+    }
+    return reload_configuration(configuration_checker).await;
+  }
+
+  let Some(server_status) = SERVER_STATUS.borrow().clone() else {
+    return (
+      StatusCode::SERVICE_UNAVAILABLE,
+      json!({ "error": "The server configuration isn't loaded yet" }),
+    );
+  };
+  let response_body = match path {
+    "/listeners" => json!(server_status.listeners),
+    "/modules" => json!(server_status.modules),
+    "/certificates" => certificates_status(&server_status.certificates).await,
+    "/connections" => connections_status(),
+    _ => json!({
+      "version": env!("CARGO_PKG_VERSION"),
+      "loaded_at": format_time(server_status.loaded_at),
+      "changes": server_status.changes,
+      "listeners": server_status.listeners,
+      "modules": server_status.modules,
+      "certificates": certificates_status(&server_status.certificates).await,
+      "connections": connections_status(),
+    }),
+  };
+  (StatusCode::OK, response_body)
+}
+
+/// Checks if the `Host` header value is a loopback IP address, optionally with a port
+fn is_loopback_host(host: &str) -> bool {
+  host
+    .parse::<SocketAddr>()
+    .map(|address| address.ip())
+    .or_else(|_| {
+      host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse::<IpAddr>()
+    })
+    .is_ok_and(|address| address.is_loopback())
+}
+
+/// Checks if the request has the `Authorization` header with the bearer token
+fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+  headers
+    .get(header::AUTHORIZATION)
+    .and_then(|authorization| authorization.to_str().ok())
+    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+    .is_some_and(|request_token| constant_time_eq(request_token.as_bytes(), token.as_bytes()))
+}
+
+/// Compares two byte strings in time independent of their contents
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  let difference = a.iter().zip(b).fold(0u8, |difference, (x, y)| difference | (x ^ y));
+  std::hint::black_box(difference) == 0
+}
+
+/// Validates the new server configuration, requests the configuration reload, and waits for the reload to complete
+async fn reload_configuration(configuration_checker: &ConfigurationChecker) -> (StatusCode, serde_json::Value) {
+  // Configuration reloads requested via the admin API are processed one at a time
+  static RELOAD_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+  let _reload_lock = RELOAD_LOCK.lock().await;
+
+  // Invalid configuration would make the server exit when it's reloaded, so it's rejected beforehand
+  let configuration_checker = configuration_checker.clone();
+  match tokio::task::spawn_blocking(move || configuration_checker().map_err(|err| err.to_string())).await {
+    Ok(Ok(_)) => (),
+    Ok(Err(err)) => {
+      return (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({ "status": "invalid", "error": err }),
+      )
+    }
+    Err(err) => {
+      return (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        json!({ "status": "failed", "error": err.to_string() }),
+      )
+    }
+  }
+
+  let mut server_status_rx = SERVER_STATUS.subscribe();
+  let (reload_tx, _) = &**ADMIN_RELOAD_CHANNEL;
+  // If a reload is already pending, another one isn't needed
+  reload_tx.try_send(()).unwrap_or_default();
+  ADMIN_LOGGER
+    .load_full()
+    .log("The server configuration reload has been requested via the admin API")
+    .await;
+
+  match tokio::time::timeout(RELOAD_TIMEOUT, server_status_rx.changed()).await {
+    Ok(Ok(())) => {
+      let changes = server_status_rx
+        .borrow_and_update()
+        .as_ref()
+        .map_or(vec![], |server_status| server_status.changes.clone());
+      (StatusCode::OK, json!({ "status": "reloaded", "changes": changes }))
+    }
+    _ => (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      json!({ "status": "failed", "error": "The server configuration reload didn't complete in time" }),
+    ),
+  }
+}
+
+/// Obtains the status of the TLS certificates obtained with automatic TLS
+async fn certificates_status(certificates: &[AcmeCertificateStatus]) -> serde_json::Value {
+  let mut certificates_status = Vec::with_capacity(certificates.len());
+  for certificate in certificates {
+    let certified_key = certificate.certified_key_lock.read().await.clone();
+    let not_after = certified_key
+      .as_ref()
+      .and_then(|certified_key| certified_key.end_entity_cert().ok())
+      .and_then(|certificate| X509Certificate::from_der(certificate).ok())
+      .and_then(|(_, certificate)| DateTime::<Utc>::from_timestamp(certificate.validity().not_after.timestamp(), 0));
+    let status = match not_after {
+      None => "pending",
+      Some(not_after) if not_after <= Utc::now() => "expired",
+      Some(_) => "valid",
+    };
+    certificates_status.push(json!({
+      "domains": certificate.domains,
+      "issuer": certificate.issuer,
+      "status": status,
+      "not_after": not_after.map(|not_after| not_after.to_rfc3339()),
+    }));
+  }
+  json!(certificates_status)
+}
+
+/// Obtains the numbers of open and idle HTTP/1.x and HTTP/2 client connections
+fn connections_status() -> serde_json::Value {
+  let (open, idle) = connection_counts();
+  json!({ "open": open, "idle": idle })
+}
+
+/// Formats the time as an RFC 3339 timestamp
+fn format_time(time: SystemTime) -> String {
+  DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures_util::FutureExt;
+
+  fn configuration_checker() -> ConfigurationChecker {
+    Arc::new(|| Err(anyhow::anyhow!("Not checked").into()))
+  }
+
+  #[test]
+  fn validates_admin_addresses() {
+    assert!(is_valid_admin_address("127.0.0.1:8081"));
+    assert!(is_valid_admin_address("[::1]:8081"));
+    assert!(is_valid_admin_address("unix:/run/ferron/admin.sock"));
+    assert!(!is_valid_admin_address("0.0.0.0:8081"));
+    assert!(!is_valid_admin_address("192.168.1.1:8081"));
+    assert!(!is_valid_admin_address("localhost:8081"));
+    assert!(!is_valid_admin_address("unix:"));
+  }
+
+  #[test]
+  fn rejects_unknown_endpoints_and_methods() {
+    let configuration_checker = configuration_checker();
+    let (status_code, _) = handle_admin_request(
+      &Method::GET,
+      "/unknown",
+      &HeaderMap::new(),
+      false,
+      &configuration_checker,
+    )
+    .now_or_never()
+    .unwrap();
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+    let (status_code, _) = handle_admin_request(
+      &Method::GET,
+      "/reload",
+      &HeaderMap::new(),
+      false,
+      &configuration_checker,
+    )
+    .now_or_never()
+    .unwrap();
+    assert_eq!(status_code, StatusCode::METHOD_NOT_ALLOWED);
+    let (status_code, _) = handle_admin_request(
+      &Method::DELETE,
+      "/status",
+      &HeaderMap::new(),
+      false,
+      &configuration_checker,
+    )
+    .now_or_never()
+    .unwrap();
+    assert_eq!(status_code, StatusCode::METHOD_NOT_ALLOWED);
+  }
+
+  #[test]
+  fn validates_loopback_hosts() {
+    assert!(is_loopback_host("127.0.0.1"));
+    assert!(is_loopback_host("127.0.0.1:8081"));
+    assert!(is_loopback_host("[::1]"));
+    assert!(is_loopback_host("[::1]:8081"));
+    assert!(!is_loopback_host("localhost:8081"));
+    assert!(!is_loopback_host("attacker.example:8081"));
+    assert!(!is_loopback_host("192.168.1.1:8081"));
+  }
+
+  #[test]
+  fn rejects_browser_requests() {
+    let configuration_checker = configuration_checker();
+    let mut headers = HeaderMap::new();
+    headers.insert(header::HOST, "attacker.example:8081".parse().unwrap());
+    let (status_code, _) = handle_admin_request(&Method::GET, "/status", &headers, true, &configuration_checker)
+      .now_or_never()
+      .unwrap();
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+    let mut headers = HeaderMap::new();
+    headers.insert(header::HOST, "127.0.0.1:8081".parse().unwrap());
+    headers.insert(header::ORIGIN, "http://attacker.example".parse().unwrap());
+    let (status_code, _) = handle_admin_request(&Method::POST, "/reload", &headers, true, &configuration_checker)
+      .now_or_never()
+      .unwrap();
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+  }
+
+  #[test]
+  fn requires_bearer_token_for_reloads() {
+    let configuration_checker = configuration_checker();
+    let mut headers = HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+    ADMIN_TOKEN.store(None);
+    let (status_code, _) = handle_admin_request(&Method::POST, "/reload", &headers, false, &configuration_checker)
+      .now_or_never()
+      .unwrap();
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+    ADMIN_TOKEN.store(Some(Arc::new("secret".to_string())));
+    let (status_code, _) = handle_admin_request(&Method::POST, "/reload", &headers, false, &configuration_checker)
+      .now_or_never()
+      .unwrap();
+    assert_eq!(status_code, StatusCode::UNAUTHORIZED);
+    headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+    assert!(is_authorized(&headers, "secret"));
+    ADMIN_TOKEN.store(None);
+  }
+
+  #[test]
+  fn reports_pending_certificates() {
+    let certificates = vec![AcmeCertificateStatus {
+      domains: vec!["example.com".to_string()],
+      issuer: "letsencrypt".to_string(),
+      certified_key_lock: Arc::new(RwLock::new(None)),
+    }];
+    let certificates_status = certificates_status(&certificates).now_or_never().unwrap();
+    assert_eq!(certificates_status[0]["domains"], json!(["example.com"]));
+    assert_eq!(certificates_status[0]["status"], "pending");
+    assert!(certificates_status[0]["not_after"].is_null());
+  }
+}
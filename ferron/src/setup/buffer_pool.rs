@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use async_channel::Sender;
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue};
+use ferron_common::util::take_buffer_pool_statistics;
+
+/// Performs background periodic collection of the buffer pool metrics.
+pub async fn background_buffer_pool_metrics(metrics_channels: Vec<Sender<Metric>>) {
+  loop {
+    // Sleep for 1 second
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let statistics = take_buffer_pool_statistics();
+    for (result, acquisitions) in [("hit", statistics.hits), ("miss", statistics.misses)] {
+      if acquisitions == 0 {
+        continue;
+      }
+      for metrics_sender in &metrics_channels {
+        metrics_sender
+          .send(Metric::new(
+            "ferron.buffer_pool.acquisitions",
+            vec![(
+              "ferron.buffer_pool.result",
+              MetricAttributeValue::String(result.to_string()),
+            )],
+            MetricType::Counter,
+            MetricValue::U64(acquisitions),
+            Some("{acquisition}"),
+            Some("Number of buffer acquisitions from the buffer pool."),
+          ))
+          .await
+          .unwrap_or_default();
+      }
+    }
+  }
+}
@@ -1,3 +1,4 @@
+use std::net::{IpAddr, Ipv6Addr};
 use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
@@ -56,14 +57,105 @@ pub struct ServeArgs {
   pub error_log: LogOutput,
 }
 
+#[cfg(windows)]
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ServiceAction {
+  /// Installs Ferron as a Windows service using the specified configuration file.
+  Install,
+
+  /// Uninstalls the Ferron Windows service.
+  Uninstall,
+
+  /// Runs Ferron under the Service Control Manager. This is used by the installed service.
+  Run,
+}
+
+#[cfg(windows)]
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ServiceArgs {
+  #[command(subcommand)]
+  pub action: ServiceAction,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum CompletionShell {
+  Bash,
+  Zsh,
+  Fish,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct CompletionsArgs {
+  /// The shell to generate the completion script for.
+  pub shell: CompletionShell,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ReloadArgs {
+  /// Whether to only print the changes the reload would make, without reloading the server configuration.
+  #[arg(long)]
+  pub diff: bool,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ExplainArgs {
+  /// The path to the server configuration file. Overrides the path specified before the subcommand.
+  #[arg(short, long)]
+  pub config: Option<PathBuf>,
+
+  /// The HTTP method of the request.
+  #[arg(long, default_value = "GET")]
+  pub method: String,
+
+  /// The hostname sent in the "Host" header of the request.
+  #[arg(long)]
+  pub host: Option<String>,
+
+  /// The path (and the query string) of the request.
+  #[arg(long, default_value = "/")]
+  pub path: String,
+
+  /// The port the request is received on. Defaults to the default HTTP or HTTPS port.
+  #[arg(long)]
+  pub port: Option<u16>,
+
+  /// Whether the request is received over HTTPS.
+  #[arg(long)]
+  pub https: bool,
+
+  /// The IP address of the client.
+  #[arg(long, default_value = "127.0.0.1")]
+  pub client_ip: IpAddr,
+
+  /// The IP address the request is received on.
+  #[arg(long, default_value_t = IpAddr::V6(Ipv6Addr::UNSPECIFIED))]
+  pub server_ip: IpAddr,
+}
+
 #[derive(Subcommand, Debug, Clone, PartialEq)]
 pub enum Command {
   /// Utility command to start up a basic HTTP server.
   Serve(ServeArgs),
+
+  /// Reloads the configuration of the server running with the specified configuration file.
+  Reload(ReloadArgs),
+
+  /// Explains how the server with the specified configuration would handle a request.
+  Explain(ExplainArgs),
+
+  /// Prints the JSON schema of the configuration directives supported by the compiled-in modules.
+  ConfigSchema,
+
+  /// Prints the shell completion script for Ferron.
+  Completions(CompletionsArgs),
+
+  /// Manages the Ferron Windows service.
+  #[cfg(windows)]
+  Service(ServiceArgs),
 }
 
 /// A fast, memory-safe web server written in Rust
-#[derive(Parser, Debug, PartialEq)]
+#[derive(Parser, Debug, Clone, PartialEq)]
 #[command(about, long_about = None)]
 pub struct FerronArgs {
   /// The path to the server configuration file
@@ -179,6 +271,7 @@ mod tests {
         assert_eq!(LogOutput::Stdout, http_serve_args.log);
         assert_eq!(LogOutput::Stderr, http_serve_args.error_log);
       }
+      _ => panic!("Expected the \"serve\" command"),
     }
   }
 
@@ -227,6 +320,117 @@ mod tests {
         assert_eq!(LogOutput::Off, http_serve_args.log);
         assert_eq!(LogOutput::Off, http_serve_args.error_log);
       }
+      _ => panic!("Expected the \"serve\" command"),
     }
   }
+
+  #[test]
+  fn test_supported_reload_args() {
+    let args = FerronArgs::parse_from(vec!["ferron", "--config", "/etc/ferron.kdl", "reload", "--diff"]);
+    assert_eq!(PathBuf::from("/etc/ferron.kdl"), args.config);
+    assert_eq!(Some(Command::Reload(ReloadArgs { diff: true })), args.command);
+
+    let args = FerronArgs::parse_from(vec!["ferron", "reload"]);
+    assert_eq!(Some(Command::Reload(ReloadArgs { diff: false })), args.command);
+  }
+
+  #[test]
+  fn test_supported_explain_args() {
+    let args = FerronArgs::parse_from(vec![
+      "ferron",
+      "explain",
+      "--config",
+      "/etc/ferron.kdl",
+      "--method",
+      "POST",
+      "--host",
+      "example.com",
+      "--path",
+      "/foo?bar=baz",
+      "--https",
+    ]);
+    assert_eq!(
+      Some(Command::Explain(ExplainArgs {
+        config: Some(PathBuf::from("/etc/ferron.kdl")),
+        method: "POST".to_string(),
+        host: Some("example.com".to_string()),
+        path: "/foo?bar=baz".to_string(),
+        port: None,
+        https: true,
+        client_ip: IpAddr::from([127, 0, 0, 1]),
+        server_ip: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+      })),
+      args.command
+    );
+  }
+
+  #[test]
+  fn test_supported_config_schema_args() {
+    let args = FerronArgs::parse_from(vec!["ferron", "config-schema"]);
+    assert_eq!(Some(Command::ConfigSchema), args.command);
+  }
+
+  #[test]
+  fn test_supported_completions_args() {
+    let args = FerronArgs::parse_from(vec!["ferron", "completions", "bash"]);
+    assert_eq!(
+      Some(Command::Completions(CompletionsArgs {
+        shell: CompletionShell::Bash
+      })),
+      args.command
+    );
+
+    let args = FerronArgs::parse_from(vec!["ferron", "completions", "zsh"]);
+    assert_eq!(
+      Some(Command::Completions(CompletionsArgs {
+        shell: CompletionShell::Zsh
+      })),
+      args.command
+    );
+
+    let args = FerronArgs::parse_from(vec!["ferron", "completions", "fish"]);
+    assert_eq!(
+      Some(Command::Completions(CompletionsArgs {
+        shell: CompletionShell::Fish
+      })),
+      args.command
+    );
+
+    assert!(FerronArgs::try_parse_from(vec!["ferron", "completions", "powershell"]).is_err());
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn test_supported_service_args() {
+    let args = FerronArgs::parse_from(vec![
+      "ferron",
+      "--config",
+      "C:\\ferron\\ferron.kdl",
+      "service",
+      "install",
+    ]);
+    assert_eq!(PathBuf::from("C:\\ferron\\ferron.kdl"), args.config);
+    assert_eq!(
+      Some(Command::Service(ServiceArgs {
+        action: ServiceAction::Install
+      })),
+      args.command
+    );
+
+    let args = FerronArgs::parse_from(vec!["ferron", "service", "uninstall"]);
+    assert_eq!(
+      Some(Command::Service(ServiceArgs {
+        action: ServiceAction::Uninstall
+      })),
+      args.command
+    );
+
+    let args = FerronArgs::parse_from(vec!["ferron", "service", "run"]);
+    assert_eq!(
+      Some(Command::Service(ServiceArgs {
+        action: ServiceAction::Run
+      })),
+      args.command
+    );
+  }
 }
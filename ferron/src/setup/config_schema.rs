@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use ferron_common::config::{ServerConfiguration, ServerConfigurationFilters};
+use ferron_common::modules::{DirectiveScope, ModuleLoader};
+use ferron_common::observability::{ObservabilityBackendChannels, ObservabilityBackendLoader};
+use serde::Serialize;
+
+/// The configuration reference documents, from which the directive syntax and descriptions are obtained
+const DIRECTIVE_DOCUMENTS: [&str; 8] = [
+  include_str!("../../../docs/configuration/core-directives.md"),
+  include_str!("../../../docs/configuration/security-tls.md"),
+  include_str!("../../../docs/configuration/routing-url-processing.md"),
+  include_str!("../../../docs/configuration/static-content.md"),
+  include_str!("../../../docs/configuration/reverse-proxying.md"),
+  include_str!("../../../docs/configuration/application-backends.md"),
+  include_str!("../../../docs/configuration/traffic-control.md"),
+  include_str!("../../../docs/configuration/observability-logging.md"),
+];
+
+/// An argument or a prop of a configuration directive
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DirectiveArgument {
+  name: String,
+  types: Vec<String>,
+  optional: bool,
+  repeated: bool,
+}
+
+/// A configuration directive in the configuration schema
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct DirectiveSchema {
+  syntax: Option<String>,
+  arguments: Vec<DirectiveArgument>,
+  props: Vec<DirectiveArgument>,
+  scope: &'static str,
+  default: Option<String>,
+  description: Option<String>,
+}
+
+/// The configuration schema
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConfigurationSchema {
+  directives: BTreeMap<String, DirectiveSchema>,
+}
+
+/// Parses an argument token (like `<name: string|null>`) into its name and its types
+fn parse_argument_token(token: &str) -> Option<(String, Vec<String>)> {
+  let (name, types) = token.split_once(':')?;
+  Some((
+    name.trim().to_string(),
+    types
+      .split('|')
+      .map(|t| t.trim().to_string())
+      .filter(|t| !t.is_empty())
+      .collect(),
+  ))
+}
+
+/// Parses the directive syntax from the configuration reference (like `name <arg: string> [prop=<prop: bool>]`)
+fn parse_directive_syntax(syntax: &str) -> (Vec<DirectiveArgument>, Vec<DirectiveArgument>) {
+  let mut arguments: Vec<DirectiveArgument> = Vec::new();
+  let mut props = Vec::new();
+  let mut optional_depth = 0usize;
+  let mut chars = syntax.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '[' => optional_depth += 1,
+      ']' => optional_depth = optional_depth.saturating_sub(1),
+      '.' if chars.peek() == Some(&'.') => {
+        // A repeated argument (`<arg: string> ...` or `[<arg: string> ...]`)
+        while chars.peek() == Some(&'.') {
+          chars.next();
+        }
+        if let Some(last_argument) = arguments.last_mut() {
+          last_argument.repeated = true;
+        }
+      }
+      '<' => {
+        let token = chars.by_ref().take_while(|c| *c != '>').collect::<String>();
+        let Some((name, types)) = parse_argument_token(&token) else {
+          continue;
+        };
+        if let Some(existing_argument) = arguments.iter_mut().find(|a| a.name == name && a.types == types) {
+          // The optional repetition of the previous argument, like `<arg: string> [<arg: string> ...]`
+          existing_argument.repeated = true;
+          while chars.peek().is_some_and(|c| *c == ' ' || *c == '.') {
+            chars.next();
+          }
+          continue;
+        }
+        arguments.push(DirectiveArgument {
+          name,
+          types,
+          optional: optional_depth > 0,
+          repeated: false,
+        });
+      }
+      _ if c.is_alphanumeric() || c == '_' => {
+        let mut word = String::from(c);
+        while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '-') {
+          word.push(c);
+        }
+        if optional_depth > 0 && chars.peek() == Some(&':') {
+          // An optional argument without angle brackets (`[arg: type]`)
+          let token = chars.by_ref().take_while(|c| *c != ']').collect::<String>();
+          optional_depth -= 1;
+          if let Some((_, types)) = parse_argument_token(&token) {
+            arguments.push(DirectiveArgument {
+              name: word,
+              types,
+              optional: true,
+              repeated: false,
+            });
+          }
+        } else if chars.next_if_eq(&'=').is_some() && chars.next_if_eq(&'<').is_some() {
+          // A prop (`prop=<prop: type>`)
+          let token = chars.by_ref().take_while(|c| *c != '>').collect::<String>();
+          if let Some((_, types)) = parse_argument_token(&token) {
+            props.push(DirectiveArgument {
+              name: word,
+              types,
+              optional: optional_depth > 0,
+              repeated: false,
+            });
+          }
+        }
+      }
+      _ => (),
+    }
+  }
+  (arguments, props)
+}
+
+/// Parses the directive entries in the configuration reference document
+fn parse_directive_document(document: &str, schemas: &mut HashMap<String, DirectiveSchema>) {
+  let mut section_scope = "general";
+  let mut lines = document.lines().peekable();
+  while let Some(line) = lines.next() {
+    if let Some(heading) = line.strip_prefix("## ") {
+      section_scope = match heading.trim() {
+        "Global-only directives" => "global",
+        "Global and virtual host directives" => "host",
+        _ => "general",
+      };
+      continue;
+    }
+    let Some(syntax) = line
+      .strip_prefix("- `")
+      .and_then(|line| line.split_once('`'))
+      .map(|(syntax, _)| syntax.trim())
+    else {
+      continue;
+    };
+    let Some(name) = syntax.split_whitespace().next() else {
+      continue;
+    };
+    if schemas.contains_key(name) {
+      // The first occurrence of the directive in the configuration reference is used
+      continue;
+    }
+
+    let description = lines
+      .next_if(|line| line.starts_with("  - "))
+      .map(|line| line.trim_start_matches("  - ").trim().to_string());
+    let default = description.as_deref().and_then(|description| {
+      let default = description.rsplit_once("Default: ")?.1;
+      if default.starts_with("none") {
+        None
+      } else if let Some(default) = default.strip_prefix('`') {
+        default.split_once('`').map(|(default, _)| default.to_string())
+      } else {
+        Some(default.trim_end_matches('.').to_string())
+      }
+    });
+    let (arguments, props) = parse_directive_syntax(syntax);
+    schemas.insert(
+      name.to_string(),
+      DirectiveSchema {
+        syntax: Some(syntax.to_string()),
+        arguments,
+        props,
+        scope: section_scope,
+        default,
+        description,
+      },
+    );
+  }
+}
+
+/// Builds the configuration schema of all directives registered by the compiled-in modules
+pub fn build_configuration_schema(
+  module_loaders: &[Box<dyn ModuleLoader + Send + Sync>],
+  observability_backend_loaders: &[Box<dyn ObservabilityBackendLoader + Send + Sync>],
+) -> ConfigurationSchema {
+  // Validating an empty configuration marks all the properties validated by a module as used
+  let empty_configuration = ServerConfiguration {
+    entries: HashMap::new(),
+    filters: ServerConfigurationFilters {
+      is_host: true,
+      hostname: None,
+      ip: None,
+      port: None,
+      condition: None,
+      error_handler_status: None,
+    },
+    modules: vec![],
+    observability: ObservabilityBackendChannels::new(),
+  };
+  let mut directive_names = HashSet::new();
+  let mut directive_scopes = HashMap::new();
+  for module_loader in module_loaders {
+    let _ = module_loader.validate_configuration(&empty_configuration, &mut directive_names);
+    directive_names.extend(module_loader.get_requirements().into_iter().map(|r| r.to_string()));
+    for (directive_name, directive_scope) in module_loader.get_directive_scopes() {
+      directive_names.insert(directive_name.to_string());
+      directive_scopes.insert(directive_name, directive_scope);
+    }
+  }
+  for observability_backend_loader in observability_backend_loaders {
+    let _ = observability_backend_loader.validate_configuration(&empty_configuration, &mut directive_names);
+    directive_names.extend(
+      observability_backend_loader
+        .get_requirements()
+        .into_iter()
+        .map(|r| r.to_string()),
+    );
+  }
+
+  let mut documented_schemas = HashMap::new();
+  for document in DIRECTIVE_DOCUMENTS {
+    parse_directive_document(document, &mut documented_schemas);
+  }
+
+  let directives = directive_names
+    .into_iter()
+    .filter(|directive_name| !directive_name.starts_with("UNDOCUMENTED_"))
+    .map(|directive_name| {
+      let mut schema = documented_schemas.remove(&directive_name).unwrap_or(DirectiveSchema {
+        scope: "general",
+        ..Default::default()
+      });
+      match directive_scopes.get(directive_name.as_str()) {
+        Some(DirectiveScope::Global) => schema.scope = "global",
+        Some(DirectiveScope::Host) => schema.scope = "host",
+        None => (),
+      }
+      (directive_name, schema)
+    })
+    .collect();
+
+  ConfigurationSchema { directives }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_directive_syntax() {
+    let (arguments, props) = parse_directive_syntax(
+      "dynamic_hosts <provider_url: string|null> [ttl=<ttl: string|integer|null>] [no_verification=<no_verification: bool>]",
+    );
+    assert_eq!(
+      arguments,
+      vec![DirectiveArgument {
+        name: "provider_url".to_string(),
+        types: vec!["string".to_string(), "null".to_string()],
+        optional: false,
+        repeated: false,
+      }]
+    );
+    assert_eq!(props.len(), 2);
+    assert_eq!(props[0].name, "ttl");
+    assert_eq!(props[0].types, vec!["string", "integer", "null"]);
+    assert!(props[0].optional);
+    assert_eq!(props[1].name, "no_verification");
+    assert_eq!(props[1].types, vec!["bool"]);
+  }
+
+  #[test]
+  fn test_parse_directive_syntax_repeated_arguments() {
+    let (arguments, props) = parse_directive_syntax("protocols <protocol: string> [<protocol: string> ...]");
+    assert_eq!(
+      arguments,
+      vec![DirectiveArgument {
+        name: "protocol".to_string(),
+        types: vec!["string".to_string()],
+        optional: false,
+        repeated: true,
+      }]
+    );
+    assert!(props.is_empty());
+
+    let (arguments, _) = parse_directive_syntax("protocol_proxy [enable_proxy_protocol: bool]");
+    assert_eq!(
+      arguments,
+      vec![DirectiveArgument {
+        name: "enable_proxy_protocol".to_string(),
+        types: vec!["bool".to_string()],
+        optional: true,
+        repeated: false,
+      }]
+    );
+  }
+
+  #[test]
+  fn test_parse_directive_document() {
+    let mut schemas = HashMap::new();
+    parse_directive_document(
+      "## Global-only directives\n\n- `listen_ip <listen_ip: string>`\n  - This directive specifies the IP address to listen. Default: `listen_ip \"::\"`\n\n## Directives\n\n- `tcp_send_buffer <tcp_send_buffer: integer>` (_tcp_ module)\n  - This directive specifies the send buffer size. Default: none\n",
+      &mut schemas,
+    );
+    let listen_ip = schemas.get("listen_ip").unwrap();
+    assert_eq!(listen_ip.scope, "global");
+    assert_eq!(listen_ip.default.as_deref(), Some("listen_ip \"::\""));
+    assert_eq!(listen_ip.syntax.as_deref(), Some("listen_ip <listen_ip: string>"));
+    let tcp_send_buffer = schemas.get("tcp_send_buffer").unwrap();
+    assert_eq!(tcp_send_buffer.scope, "general");
+    assert_eq!(tcp_send_buffer.default, None);
+    assert_eq!(
+      tcp_send_buffer.description.as_deref(),
+      Some("This directive specifies the send buffer size. Default: none")
+    );
+  }
+}
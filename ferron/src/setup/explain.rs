@@ -0,0 +1,386 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+use fancy_regex::RegexBuilder;
+use ferron_common::config::{Conditional, ConditionalData, ServerConfiguration, ServerConfigurationFilters};
+use ferron_common::modules::{RequestTags, SocketData};
+use ferron_common::{get_entries, get_entry, get_value};
+use hyper::header;
+
+use crate::config::processing::are_requirements_met;
+use crate::config::ServerConfigurations;
+use crate::util::{replace_header_placeholders, sanitize_url};
+
+/// Explains how the server would handle the request: which host block and location would be used,
+/// which conditions and URL rewrite rules would match, and which modules would handle the request.
+/// The module loaders are specified as pairs of module loader names and module requirements.
+pub fn explain_request(
+  configurations: &ServerConfigurations,
+  module_loaders: &[(&str, Vec<&'static str>)],
+  mut request: hyper::http::request::Parts,
+  socket_data: &SocketData,
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+  let hostname_determinant = request.headers.get(header::HOST).and_then(|value| {
+    value.to_str().ok().map(|h| {
+      h.rsplit_once(':')
+        .and_then(|(left, right)| {
+          if right.parse::<u16>().is_ok() {
+            Some(left.to_string())
+          } else {
+            None
+          }
+        })
+        .unwrap_or_else(|| h.to_string())
+    })
+  });
+
+  let mut lines = vec![format!(
+    "Request: {} {} (host: {}, local address: {}, client address: {}{})",
+    request.method,
+    request.uri,
+    hostname_determinant.as_deref().unwrap_or("none"),
+    socket_data.local_addr,
+    socket_data.remote_addr,
+    if socket_data.encrypted { ", HTTPS" } else { "" }
+  )];
+
+  let find_configuration =
+    |request: &hyper::http::request::Parts| -> Result<Arc<ServerConfiguration>, Box<dyn Error + Send + Sync>> {
+      configurations
+        .find_configuration(request, hostname_determinant.as_deref(), socket_data)?
+        .and_then(|lookup| lookup.get_default().cloned())
+        .ok_or_else(|| anyhow::anyhow!("No matching configuration found").into_boxed_dyn_error())
+    };
+  let mut configuration = find_configuration(&request)?;
+
+  // The server configuration is found again after the URL is sanitized, like when handling the request
+  if !get_value!("disable_url_sanitizer", configuration)
+    .and_then(|v| v.as_bool())
+    .unwrap_or(false)
+  {
+    let sanitized_url_pathname = sanitize_url(
+      request.uri.path(),
+      get_value!("allow_double_slashes", configuration)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false),
+    )?;
+    if sanitized_url_pathname != request.uri.path() {
+      lines.push(format!(
+        "URL sanitizer: the path \"{}\" is normalized to \"{sanitized_url_pathname}\"",
+        request.uri.path()
+      ));
+      let mut url_parts = request.uri.clone().into_parts();
+      url_parts.path_and_query = Some(
+        format!(
+          "{}{}",
+          sanitized_url_pathname,
+          request.uri.query().map_or("".to_string(), |q| format!("?{q}"))
+        )
+        .parse()?,
+      );
+      request.uri = hyper::Uri::from_parts(url_parts)?;
+      configuration = find_configuration(&request)?;
+    }
+  }
+
+  // The server configuration is also found again after the request is classified
+  let request_tags = configurations.classify_request(&configuration.filters, &request, socket_data);
+  if !request_tags.is_empty() {
+    lines.push(format!("Request tags: {}", request_tags.join(", ")));
+    request.extensions.insert(RequestTags(request_tags));
+    configuration = find_configuration(&request)?;
+  }
+
+  let host_filters = ServerConfigurationFilters {
+    condition: None,
+    error_handler_status: None,
+    ..configuration.filters.clone()
+  };
+  if host_filters.is_global() {
+    lines.push("Host block: none (the global configuration applies)".to_string());
+  } else {
+    lines.push(format!("Host block: {host_filters}"));
+  }
+
+  match configuration.filters.condition.as_ref() {
+    Some(condition) => {
+      if condition.location_prefix.is_empty() || condition.location_prefix == "/" {
+        lines.push("Location: none".to_string());
+      } else {
+        lines.push(format!("Location: \"{}\"", condition.location_prefix));
+      }
+      if !condition.conditionals.is_empty() {
+        lines.push("Matched conditions:".to_string());
+        for conditional in &condition.conditionals {
+          let (prefix, data) = match conditional {
+            Conditional::If(data) => ("if", data),
+            Conditional::IfNot(data) => ("if_not (not all of these hold)", data),
+          };
+          let descriptions = data
+            .iter()
+            .map(|condition| describe_condition(condition, &request, socket_data))
+            .collect::<Vec<_>>();
+          lines.push(format!("  {prefix}: {}", descriptions.join("; ")));
+        }
+      }
+    }
+    None => lines.push("Location: none".to_string()),
+  }
+
+  lines.append(&mut explain_url_rewriting(&configuration, &request));
+
+  lines.push("Modules (in the order they would handle the request):".to_string());
+  for (index, (module_loader_name, requirements)) in module_loaders
+    .iter()
+    .filter(|(_, requirements)| are_requirements_met(requirements, &configuration))
+    .enumerate()
+  {
+    if requirements.is_empty() {
+      lines.push(format!("  {}. {module_loader_name} (always loaded)", index + 1));
+    } else {
+      let enabled_by = requirements
+        .iter()
+        .filter(|requirement| are_requirements_met(&[**requirement], &configuration))
+        .map(|requirement| format!("\"{requirement}\""))
+        .collect::<Vec<_>>();
+      lines.push(format!(
+        "  {}. {module_loader_name} (enabled by {})",
+        index + 1,
+        enabled_by.join(", ")
+      ));
+    }
+  }
+
+  Ok(lines)
+}
+
+/// Describes the condition matched against the request
+fn describe_condition(
+  condition: &ConditionalData,
+  request: &hyper::http::request::Parts,
+  socket_data: &SocketData,
+) -> String {
+  let resolve = |value: &str| replace_header_placeholders(value, request, Some(socket_data));
+  match condition {
+    ConditionalData::IsRemoteIp(_) => "the client IP address is in the list".to_string(),
+    ConditionalData::IsForwardedFor(_) => "the forwarded client IP address is in the list".to_string(),
+    ConditionalData::IsNotRemoteIp(_) => "the client IP address isn't in the list".to_string(),
+    ConditionalData::IsNotForwardedFor(_) => "the forwarded client IP address isn't in the list".to_string(),
+    ConditionalData::IsEqual(v1, v2) => format!("\"{v1}\" (\"{}\") is equal to \"{v2}\"", resolve(v1)),
+    ConditionalData::IsNotEqual(v1, v2) => format!("\"{v1}\" (\"{}\") isn't equal to \"{v2}\"", resolve(v1)),
+    ConditionalData::IsRegex(v1, regex) => format!("\"{v1}\" (\"{}\") matches \"{}\"", resolve(v1), regex.as_str()),
+    ConditionalData::IsNotRegex(v1, regex) => {
+      format!("\"{v1}\" (\"{}\") doesn't match \"{}\"", resolve(v1), regex.as_str())
+    }
+    ConditionalData::IsRego(_) => "the Rego policy passes".to_string(),
+    ConditionalData::SetConstant(name, value) => format!("the \"{name}\" constant is set to \"{value}\""),
+    ConditionalData::IsLanguage(language) => format!("the preferred language is \"{language}\""),
+    ConditionalData::IsTagged(tag) => format!("the request is tagged with \"{tag}\""),
+    ConditionalData::IsBodyJson(pointer, value) => {
+      format!("the request body field at the \"{pointer}\" JSON pointer is equal to \"{value}\"")
+    }
+    ConditionalData::IsBodyRegex(regex) => format!("the request body matches \"{}\"", regex.as_str()),
+    _ => "an unknown condition".to_string(),
+  }
+}
+
+/// Explains which URL rewrite rules would match the request, the same way as the URL rewriting module applies them
+fn explain_url_rewriting(configuration: &ServerConfiguration, request: &hyper::http::request::Parts) -> Vec<String> {
+  let mut lines = Vec::new();
+  let Some(rewrite_entries) = get_entries!("rewrite", configuration) else {
+    return lines;
+  };
+  let original_url = format!(
+    "{}{}",
+    request.uri.path(),
+    request.uri.query().map_or("".to_string(), |q| format!("?{q}"))
+  );
+  let mut rewritten_url = original_url.clone();
+  lines.push(format!("URL rewriting (the URL is \"{original_url}\"):"));
+
+  for (index, rewrite_entry) in rewrite_entries.inner.iter().enumerate() {
+    let (Some(regex_str), Some(replacement)) = (
+      rewrite_entry.values.first().and_then(|v| v.as_str()),
+      rewrite_entry.values.get(1).and_then(|v| v.as_str()),
+    ) else {
+      continue;
+    };
+    let Ok(regex) = RegexBuilder::new(regex_str).case_insensitive(cfg!(windows)).build() else {
+      continue;
+    };
+    let rule_prefix = format!("  rule {} (\"{regex_str}\" -> \"{replacement}\")", index + 1);
+    let is_not_directory = !rewrite_entry
+      .props
+      .get("directory")
+      .and_then(|v| v.as_bool())
+      .unwrap_or(true);
+    let is_not_file = !rewrite_entry
+      .props
+      .get("file")
+      .and_then(|v| v.as_bool())
+      .unwrap_or(true);
+    let last = rewrite_entry
+      .props
+      .get("last")
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+    let allow_double_slashes = rewrite_entry
+      .props
+      .get("allow_double_slashes")
+      .and_then(|v| v.as_bool())
+      .unwrap_or(false);
+
+    // Check if it's a file or a directory according to the rewrite rule
+    if is_not_directory || is_not_file {
+      if let Some(wwwroot) = get_entry!("root", configuration)
+        .and_then(|e| e.values.first())
+        .and_then(|v| v.as_str())
+      {
+        let relative_path = rewritten_url.trim_start_matches('/');
+        let relative_path = relative_path.split('?').next().unwrap_or(relative_path);
+        let (is_file, is_directory) = std::fs::metadata(Path::new(wwwroot).join(relative_path))
+          .map_or((false, false), |metadata| (metadata.is_file(), metadata.is_dir()));
+        if is_not_file && is_file {
+          lines.push(format!("{rule_prefix}: skipped, because the URL points to a file"));
+          continue;
+        } else if is_not_directory && is_directory {
+          lines.push(format!("{rule_prefix}: skipped, because the URL points to a directory"));
+          continue;
+        }
+      }
+    }
+
+    if !allow_double_slashes {
+      while rewritten_url.contains("//") {
+        rewritten_url = rewritten_url.replace("//", "/");
+      }
+    }
+
+    if !regex.is_match(&rewritten_url).unwrap_or(false) {
+      lines.push(format!("{rule_prefix}: didn't match"));
+      continue;
+    }
+    let old_rewritten_url = rewritten_url;
+    rewritten_url = regex.replace(&old_rewritten_url, replacement).to_string();
+    if !rewritten_url.starts_with('/') {
+      lines.push(format!(
+        "{rule_prefix}: matched, but the rewritten URL \"{rewritten_url}\" doesn't start with \"/\", \
+        so the request would be rejected with a 400 Bad Request response"
+      ));
+      return lines;
+    }
+    if last && old_rewritten_url != rewritten_url {
+      lines.push(format!(
+        "{rule_prefix}: matched, the URL is rewritten to \"{rewritten_url}\", and it's the last rule applied"
+      ));
+      break;
+    }
+    lines.push(format!(
+      "{rule_prefix}: matched, the URL is rewritten to \"{rewritten_url}\""
+    ));
+  }
+
+  if rewritten_url != original_url {
+    lines.push(format!(
+      "  The request would be handled with the \"{rewritten_url}\" URL"
+    ));
+  } else {
+    lines.push("  The URL wouldn't be rewritten".to_string());
+  }
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+  use ferron_common::config::{
+    Conditions, LocationModifier, ServerConfigurationEntries, ServerConfigurationEntry, ServerConfigurationValue,
+  };
+  use ferron_common::observability::ObservabilityBackendChannels;
+
+  use super::*;
+
+  fn config(
+    hostname: Option<&str>,
+    location_prefix: Option<&str>,
+    entries: Vec<(&str, Vec<ServerConfigurationEntry>)>,
+  ) -> ServerConfiguration {
+    ServerConfiguration {
+      filters: ServerConfigurationFilters {
+        is_host: true,
+        hostname: hostname.map(String::from),
+        ip: None,
+        port: None,
+        condition: location_prefix.map(|location_prefix| Conditions {
+          location_prefix: location_prefix.to_string(),
+          location_modifier: LocationModifier::Prefix,
+          conditionals: vec![],
+        }),
+        error_handler_status: None,
+      },
+      entries: entries
+        .into_iter()
+        .map(|(name, inner)| (name.to_string(), ServerConfigurationEntries { inner }))
+        .collect(),
+      modules: vec![],
+      observability: ObservabilityBackendChannels::new(),
+    }
+  }
+
+  fn rewrite_entry(regex: &str, replacement: &str, last: bool) -> ServerConfigurationEntry {
+    ServerConfigurationEntry {
+      values: vec![
+        ServerConfigurationValue::String(regex.to_string()),
+        ServerConfigurationValue::String(replacement.to_string()),
+      ],
+      props: HashMap::from([("last".to_string(), ServerConfigurationValue::Bool(last))]),
+    }
+  }
+
+  #[test]
+  fn test_explain_request() {
+    let configurations = ServerConfigurations::new(vec![
+      config(None, None, vec![]),
+      config(Some("example.com"), None, vec![]),
+      config(
+        Some("example.com"),
+        Some("/api"),
+        vec![(
+          "rewrite",
+          vec![
+            rewrite_entry("^/api/v1/(.*)", "/api/$1", true),
+            rewrite_entry("^/api/(.*)", "/index.php/$1", false),
+          ],
+        )],
+      ),
+    ]);
+    let module_loaders = vec![("CoreModuleLoader", vec![]), ("RewriteModuleLoader", vec!["rewrite"])];
+    let (request, _) = hyper::Request::get("/api/v1/users")
+      .header(header::HOST, "example.com")
+      .body(())
+      .unwrap()
+      .into_parts();
+    let socket_data = SocketData {
+      remote_addr: SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 12345),
+      local_addr: SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 80),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+
+    let lines = explain_request(&configurations, &module_loaders, request, &socket_data).unwrap();
+    assert!(lines.contains(&"Host block: \"example.com\" host block".to_string()));
+    assert!(lines.contains(&"Location: \"/api\"".to_string()));
+    assert!(lines.contains(
+      &"  rule 1 (\"^/api/v1/(.*)\" -> \"/api/$1\"): matched, the URL is rewritten to \"/api/users\", \
+      and it's the last rule applied"
+        .to_string()
+    ));
+    assert!(!lines.iter().any(|line| line.starts_with("  rule 2")));
+    assert!(lines.contains(&"  1. CoreModuleLoader (always loaded)".to_string()));
+    assert!(lines.contains(&"  2. RewriteModuleLoader (enabled by \"rewrite\")".to_string()));
+  }
+}
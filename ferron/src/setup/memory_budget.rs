@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_channel::Sender;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::observability::{Metric, MetricType, MetricValue};
+use ferron_common::util::{sizify, MEMORY_BUDGET};
+
+/// Performs background periodic monitoring of the memory budget.
+/// Entering and leaving the shed mode is logged into the error log, and the memory budget metrics are collected.
+pub async fn background_memory_budget_monitor(metrics_channels: Vec<Sender<Metric>>, logger: Arc<ErrorLogger>) {
+  let mut was_shedding = false;
+  loop {
+    // Sleep for 1 second
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let statistics = MEMORY_BUDGET.take_statistics();
+    let limit = MEMORY_BUDGET.limit().unwrap_or(0) as u64;
+    if statistics.shed_episodes > 0 && !(was_shedding && statistics.shedding) {
+      logger
+        .log(&format!(
+          "The memory budget ({}) was exceeded, entering the shed mode: requests needing body buffering are rejected, and response bodies are streamed without buffering or caching",
+          sizify(limit, true)
+        ))
+        .await;
+    }
+    if (was_shedding || statistics.shed_episodes > 0) && !statistics.shedding {
+      logger
+        .log(&format!(
+          "Left the memory budget shed mode ({} of {} in use)",
+          sizify(statistics.used as u64, true),
+          sizify(limit, true)
+        ))
+        .await;
+    }
+    was_shedding = statistics.shedding;
+
+    for metrics_sender in &metrics_channels {
+      metrics_sender
+        .send(Metric::new(
+          "ferron.memory_budget.used",
+          vec![],
+          MetricType::Gauge,
+          MetricValue::U64(statistics.used as u64),
+          Some("By"),
+          Some("Number of bytes of buffered bodies and cached responses reserved in the memory budget."),
+        ))
+        .await
+        .unwrap_or_default();
+      metrics_sender
+        .send(Metric::new(
+          "ferron.memory_budget.shedding",
+          vec![],
+          MetricType::Gauge,
+          MetricValue::U64(statistics.shedding as u64),
+          None,
+          Some("Whether the memory budget is in the shed mode (1) or not (0)."),
+        ))
+        .await
+        .unwrap_or_default();
+      if statistics.rejections > 0 {
+        metrics_sender
+          .send(Metric::new(
+            "ferron.memory_budget.rejections",
+            vec![],
+            MetricType::Counter,
+            MetricValue::U64(statistics.rejections),
+            Some("{reservation}"),
+            Some("Number of buffering reservations rejected because of the memory budget."),
+          ))
+          .await
+          .unwrap_or_default();
+      }
+    }
+  }
+}
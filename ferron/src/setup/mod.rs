@@ -1,7 +1,16 @@
 pub mod acme;
+pub mod admin;
+pub mod buffer_pool;
 pub mod cli;
+pub mod config_schema;
+pub mod explain;
+pub mod memory_budget;
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub mod metrics;
 pub mod ocsp;
+pub mod scheduler;
+pub mod shutdown;
 pub mod tls;
 pub mod tls_single;
+#[cfg(windows)]
+pub mod windows_service;
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use ferron_common::logging::LogMessage;
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
 use hyper::Request;
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
@@ -19,7 +20,74 @@ use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 use x509_parser::prelude::*;
 
-type OcspCache = Arc<RwLock<HashMap<Vec<u8>, Option<Arc<CertifiedKey>>>>>;
+type OcspCache = Arc<RwLock<HashMap<Vec<u8>, Option<StapledCertifiedKey>>>>;
+
+/// The object identifier of the TLS Feature X.509 extension (RFC 7633)
+const TLS_FEATURE_EXTENSION_OID: &str = "1.3.6.1.5.5.7.1.24";
+/// The "status_request" TLS extension number, which marks the certificate as OCSP must-staple
+const TLS_FEATURE_STATUS_REQUEST: u8 = 5;
+
+/// The policy applied when no valid OCSP response is available for a certificate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OcspFailurePolicy {
+  /// Serve the certificate without the OCSP response
+  #[default]
+  Serve,
+
+  /// Fail the TLS handshake for certificates with the OCSP must-staple extension,
+  /// and serve other certificates without the OCSP response
+  FailMustStaple,
+
+  /// Fail the TLS handshake
+  Fail,
+}
+
+impl OcspFailurePolicy {
+  /// Parses the OCSP stapling failure policy from the configuration value
+  pub fn parse(value: &str) -> Option<Self> {
+    match value {
+      "serve" => Some(Self::Serve),
+      "fail_must_staple" => Some(Self::FailMustStaple),
+      "fail" => Some(Self::Fail),
+      _ => None,
+    }
+  }
+}
+
+/// OCSP stapling options
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OcspStaplingOptions {
+  /// The policy applied when no valid OCSP response is available for a certificate
+  pub failure_policy: OcspFailurePolicy,
+  /// The maximum age of the OCSP response, after which the OCSP response is considered stale
+  pub max_age: Option<Duration>,
+}
+
+/// A certified key with the stapled OCSP response
+#[derive(Debug)]
+struct StapledCertifiedKey {
+  certified_key: Arc<CertifiedKey>,
+  this_update: SystemTime,
+  next_update: Option<SystemTime>,
+}
+
+impl StapledCertifiedKey {
+  /// Checks whether the OCSP response is neither expired nor older than the maximum age
+  fn is_fresh(&self, now: SystemTime, max_age: Option<Duration>) -> bool {
+    self.next_update.is_none_or(|next_update| now < next_update)
+      && max_age.is_none_or(|max_age| {
+        now.duration_since(self.this_update).is_ok_and(|age| age <= max_age) || now < self.this_update
+      })
+  }
+}
+
+/// The OCSP response obtained from the OCSP responder
+struct FetchedOcspResponse {
+  response: Vec<u8>,
+  this_update: SystemTime,
+  next_update: Option<SystemTime>,
+  refresh_at: SystemTime,
+}
 
 #[derive(Debug)]
 pub struct OcspStapler {
@@ -27,6 +95,8 @@ pub struct OcspStapler {
   cache: OcspCache,
   sender: async_channel::Sender<CertifiedKey>,
   cancel_token: CancellationToken,
+  options: OcspStaplingOptions,
+  metrics_sender: MetricsMultiSender,
 }
 
 impl OcspStapler {
@@ -34,16 +104,21 @@ impl OcspStapler {
     inner: Arc<dyn ResolvesServerCert>,
     runtime: &tokio::runtime::Runtime,
     logging_tx: Vec<async_channel::Sender<LogMessage>>,
+    metrics_tx: Vec<async_channel::Sender<Metric>>,
+    options: OcspStaplingOptions,
   ) -> Self {
     let (sender, receiver) = async_channel::unbounded();
     let cache = Arc::new(RwLock::new(HashMap::new()));
     let cancel_token = CancellationToken::new();
+    let metrics_sender = MetricsMultiSender::new_multiple(metrics_tx);
 
     let stapler = Self {
       inner,
       cache,
       sender,
       cancel_token: cancel_token.clone(),
+      options,
+      metrics_sender: metrics_sender.clone(),
     };
 
     runtime.spawn(background_ocsp_task(
@@ -51,6 +126,8 @@ impl OcspStapler {
       stapler.cache.clone(),
       cancel_token,
       logging_tx,
+      metrics_sender,
+      options,
     ));
 
     stapler
@@ -80,35 +157,116 @@ impl ResolvesServerCert for OcspStapler {
       #[cfg(feature = "runtime-tokio")]
       let cache = futures_executor::block_on(async { self.cache.read().await });
 
-      if let Some(cached_key_option) = cache.get(&leaf.to_vec()) {
-        if let Some(cached_key) = cached_key_option.as_ref() {
-          // If cached key has OCSP, return it.
-          // Note: We might want to check if it's expired here, but the background task handles cleanup/refresh.
-          // For simplicity, we return what's in cache.
-          if cached_key.ocsp.is_some() {
-            return Some(cached_key.clone());
+      match cache.get(&leaf.to_vec()) {
+        Some(Some(stapled_key)) => {
+          // Stale OCSP responses are refreshed by the background task, and aren't stapled in the meantime
+          if stapled_key.is_fresh(SystemTime::now(), self.options.max_age) {
+            return Some(stapled_key.certified_key.clone());
           }
         }
-        // If cached key has no OCSP, don't trigger fetch.
-      } else {
-        // Not in cache or no OCSP yet. Trigger fetch.
-        let _ = self.sender.send_blocking((*original_key).clone());
+        // The certificate doesn't support OCSP, don't trigger fetch.
+        Some(None) => return Some(original_key),
+        None => {
+          // Not in cache or no OCSP yet. Trigger fetch.
+          let _ = self.sender.send_blocking((*original_key).clone());
+        }
+      }
+
+      // No valid OCSP response is available, so the failure policy is applied
+      if self.options.failure_policy != OcspFailurePolicy::Serve {
+        let must_staple = is_must_staple(leaf);
+        if must_staple || self.options.failure_policy == OcspFailurePolicy::Fail {
+          self.metrics_sender.try_send(Metric::new(
+            "ferron.tls.ocsp_stapling.rejected_handshakes",
+            vec![(
+              "ferron.tls.certificate.must_staple",
+              MetricAttributeValue::Bool(must_staple),
+            )],
+            MetricType::Counter,
+            MetricValue::U64(1),
+            Some("{handshake}"),
+            Some("Number of TLS handshakes failed, because no valid OCSP response was available."),
+          ));
+          return None;
+        }
       }
     }
     Some(original_key)
   }
 }
 
+/// Checks whether the certificate has the OCSP must-staple extension (the TLS Feature extension with "status_request")
+fn is_must_staple(certificate: &CertificateDer<'_>) -> bool {
+  let Ok((_, certificate)) = X509Certificate::from_der(certificate) else {
+    return false;
+  };
+  certificate
+    .extensions()
+    .iter()
+    .filter(|extension| extension.oid.to_id_string() == TLS_FEATURE_EXTENSION_OID)
+    .any(|extension| tls_features_contain_status_request(extension.value))
+}
+
+/// Checks whether the DER-encoded TLS Feature extension value (a sequence of integers) contains "status_request"
+fn tls_features_contain_status_request(value: &[u8]) -> bool {
+  let Some((&0x30, rest)) = value.split_first() else {
+    return false;
+  };
+  let Some((&length, mut features)) = rest.split_first() else {
+    return false;
+  };
+  if length >= 0x80 || features.len() < length as usize {
+    return false;
+  }
+  features = &features[..length as usize];
+  while let [0x02, feature_length, rest @ ..] = features {
+    let feature_length = *feature_length as usize;
+    if rest.len() < feature_length {
+      return false;
+    }
+    if rest[..feature_length] == [TLS_FEATURE_STATUS_REQUEST] {
+      return true;
+    }
+    features = &rest[feature_length..];
+  }
+  false
+}
+
+/// Records an OCSP response fetch in the metrics
+async fn record_ocsp_fetch(metrics_sender: &MetricsMultiSender, success: bool, must_staple: bool) {
+  metrics_sender
+    .send(Metric::new(
+      "ferron.tls.ocsp_stapling.fetches",
+      vec![
+        (
+          "ferron.tls.ocsp_stapling.fetch_result",
+          MetricAttributeValue::String(if success { "success" } else { "failure" }.to_string()),
+        ),
+        (
+          "ferron.tls.certificate.must_staple",
+          MetricAttributeValue::Bool(must_staple),
+        ),
+      ],
+      MetricType::Counter,
+      MetricValue::U64(1),
+      Some("{fetch}"),
+      Some("Number of OCSP response fetches for OCSP stapling."),
+    ))
+    .await;
+}
+
 async fn background_ocsp_task(
   receiver: async_channel::Receiver<CertifiedKey>,
   cache: OcspCache,
   cancel_token: CancellationToken,
   logging_tx: Vec<async_channel::Sender<LogMessage>>,
+  metrics_sender: MetricsMultiSender,
+  options: OcspStaplingOptions,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
   // Track next update times
   let mut next_updates: HashMap<Vec<u8>, SystemTime> = HashMap::new();
-  // Track known cert chains
-  let mut known_certs: HashMap<Vec<u8>, CertifiedKey> = HashMap::new();
+  // Track known cert chains, and whether the certificates are OCSP must-staple
+  let mut known_certs: HashMap<Vec<u8>, (CertifiedKey, bool)> = HashMap::new();
 
   // Create HTTP client
   let tls_config_builder =
@@ -193,7 +351,8 @@ async fn background_ocsp_task(
       if let Some(leaf) = chain.first() {
         let key = leaf.to_vec();
         if !known_certs.contains_key(&key) {
-          known_certs.insert(key.clone(), certified_key);
+          let must_staple = is_must_staple(leaf);
+          known_certs.insert(key.clone(), (certified_key, must_staple));
           // Trigger immediate update for new cert
           next_updates.insert(key, SystemTime::now());
         }
@@ -210,26 +369,55 @@ async fn background_ocsp_task(
     }
 
     for key in updates_to_fetch {
-      if let Some(certified_key) = known_certs.get(&key) {
+      if let Some((certified_key, must_staple)) = known_certs.get(&key) {
         match fetch_ocsp_response(&client, &certified_key.cert).await {
-          Ok(Some((response, next_update_time))) => {
+          Ok(Some(fetched_response)) => {
+            record_ocsp_fetch(&metrics_sender, true, *must_staple).await;
             let mut new_certified_key = certified_key.clone();
-            new_certified_key.ocsp = Some(response.clone());
-            cache
-              .write()
-              .await
-              .insert(certified_key.cert[0].to_vec(), Some(Arc::new(new_certified_key)));
-            next_updates.insert(key, next_update_time);
+            new_certified_key.ocsp = Some(fetched_response.response);
+            cache.write().await.insert(
+              certified_key.cert[0].to_vec(),
+              Some(StapledCertifiedKey {
+                certified_key: Arc::new(new_certified_key),
+                this_update: fetched_response.this_update,
+                next_update: fetched_response.next_update,
+              }),
+            );
+            let mut refresh_at = fetched_response.refresh_at;
+            if let Some(max_age) = options.max_age {
+              // Refresh the OCSP response before it becomes stale, but not more often than every 5 minutes
+              let stale_refresh_at = (fetched_response.this_update + max_age / 2).max(now + Duration::from_secs(300));
+              refresh_at = refresh_at.min(stale_refresh_at);
+            }
+            next_updates.insert(key, refresh_at);
           }
           Ok(None) => {
+            if *must_staple {
+              for tx in &logging_tx {
+                let _ = tx
+                  .send(LogMessage::new(
+                    "The TLS certificate has the OCSP must-staple extension, but no OCSP responder can be contacted \
+                     for it, so clients might reject the certificate"
+                      .to_string(),
+                    true,
+                  ))
+                  .await;
+              }
+            }
             // Don't retry OCSP stapling
             cache.write().await.insert(certified_key.cert[0].to_vec(), None);
             next_updates.remove(&key);
           }
           Err(e) => {
+            record_ocsp_fetch(&metrics_sender, false, *must_staple).await;
             // Log error
+            let message = if *must_staple {
+              format!("OCSP fetch failed for a TLS certificate with the OCSP must-staple extension: {e}")
+            } else {
+              format!("OCSP fetch failed: {e}")
+            };
             for tx in &logging_tx {
-              let _ = tx.send(LogMessage::new(format!("OCSP fetch failed: {e}"), true)).await;
+              let _ = tx.send(LogMessage::new(message.clone(), true)).await;
             }
             // Retry later; with some randomness to avoid refresh storm.
             next_updates.insert(key, now + Duration::from_secs(rand::random_range(100..=500)));
@@ -247,7 +435,7 @@ async fn fetch_ocsp_response(
     http_body_util::Full<hyper::body::Bytes>,
   >,
   chain: &[CertificateDer<'_>],
-) -> anyhow::Result<Option<(Vec<u8>, SystemTime)>> {
+) -> anyhow::Result<Option<FetchedOcspResponse>> {
   // Try SHA-256 first
   let response = fetch_ocsp_response_inner(client, chain, true).await;
 
@@ -271,7 +459,7 @@ async fn fetch_ocsp_response_inner(
   >,
   chain: &[CertificateDer<'_>],
   use_sha256: bool,
-) -> anyhow::Result<Option<(Vec<u8>, SystemTime)>> {
+) -> anyhow::Result<Option<FetchedOcspResponse>> {
   if chain.len() < 2 {
     // Certificate chain too short, don't bother with OCSP
     return Ok(None);
@@ -335,14 +523,20 @@ async fn fetch_ocsp_response_inner(
   // Check validities of all single responses.
   // For simplicity, take the earliest next_update.
   let mut min_next_update = None;
+  let mut min_this_update: Option<SystemTime> = None;
+  let mut min_expiration = None;
 
   // Need to adjust for data types. `rasn_ocsp` uses `rasn::types::UtcTime` or `GeneralizedTime`.
   // We need to convert to SystemTime.
 
   for single_res in basic_response.tbs_response_data.responses {
     let next_update = single_res.next_update.map(SystemTime::from);
+    let this_update = SystemTime::from(single_res.this_update);
+    min_this_update = Some(min_this_update.map_or(this_update, |min| min.min(this_update)));
 
     if let Some(mut nu) = next_update {
+      min_expiration = Some(min_expiration.map_or(nu, |min: SystemTime| min.min(nu)));
+
       // Next update with safety margin.
       let nu_safety_margin = nu
         .duration_since(SystemTime::from(single_res.this_update))
@@ -368,9 +562,14 @@ async fn fetch_ocsp_response_inner(
     }
   }
 
-  let next_update = min_next_update.unwrap_or_else(|| SystemTime::now() + Duration::from_hours(12));
+  let refresh_at = min_next_update.unwrap_or_else(|| SystemTime::now() + Duration::from_hours(12));
 
-  Ok(Some((response_der, next_update)))
+  Ok(Some(FetchedOcspResponse {
+    response: response_der,
+    this_update: min_this_update.unwrap_or_else(SystemTime::now),
+    next_update: min_expiration,
+    refresh_at,
+  }))
 }
 
 fn extract_ocsp_url(cert: &X509Certificate) -> Option<String> {
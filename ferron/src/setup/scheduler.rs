@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use async_channel::{Receiver, Sender};
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, Local, TimeDelta, Timelike};
+use ferron_common::config::ServerConfiguration;
+use ferron_common::get_entries;
+use ferron_common::logging::ErrorLogger;
+use ferron_common::util::{run_scheduled_task, CronSchedule};
+use http_body_util::Empty;
+use hyper::{Method, Request, Uri};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+
+use crate::acme::remove_expired_cached_certificates;
+use crate::setup::acme::build_raw_rustls_client_config;
+
+/// The timeout for webhook ping requests
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The channel used by the scheduler to request a configuration reload
+pub static SCHEDULED_RELOAD_CHANNEL: LazyLock<Arc<(Sender<()>, Receiver<()>)>> =
+  LazyLock::new(|| Arc::new(async_channel::bounded(1)));
+
+/// A built-in maintenance task
+enum MaintenanceTask {
+  /// A task executed by the handlers registered by modules and observability backends
+  /// (`cache_prune` or `log_rotate`)
+  Registered(&'static str),
+  /// Reloads the server configuration, which refreshes the blocklists and the IP address lists obtained from URLs
+  BlocklistRefresh,
+  /// Removes the expired certificates from the ACME cache directories
+  CertificateCacheCleanup(Arc<Vec<PathBuf>>),
+  /// Sends a GET request to the webhook URL
+  Webhook(Uri),
+}
+
+/// A maintenance task executed on a schedule
+pub struct ScheduledTask {
+  schedule: CronSchedule,
+  task: Arc<MaintenanceTask>,
+}
+
+/// Parses the scheduled maintenance tasks from the `schedule` global directives
+pub fn parse_scheduled_tasks(
+  global_configuration: &ServerConfiguration,
+  certificate_cache_paths: Vec<PathBuf>,
+) -> Result<Vec<ScheduledTask>, Box<dyn Error + Send + Sync>> {
+  let certificate_cache_paths = Arc::new(certificate_cache_paths);
+  let mut scheduled_tasks = Vec::new();
+  if let Some(entries) = get_entries!("schedule", global_configuration) {
+    for entry in &entries.inner {
+      let Some(expression) = entry.values.first().and_then(|v| v.as_str()) else {
+        continue;
+      };
+      let schedule =
+        CronSchedule::parse(expression).ok_or_else(|| anyhow::anyhow!("Invalid schedule: \"{expression}\""))?;
+      let task = match entry.props.get("task").and_then(|v| v.as_str()) {
+        Some("cache_prune") => MaintenanceTask::Registered("cache_prune"),
+        Some("log_rotate") => MaintenanceTask::Registered("log_rotate"),
+        Some("blocklist_refresh") => MaintenanceTask::BlocklistRefresh,
+        Some("cert_cache_cleanup") => MaintenanceTask::CertificateCacheCleanup(certificate_cache_paths.clone()),
+        Some("webhook") => MaintenanceTask::Webhook(
+          entry
+            .props
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("The webhook scheduled task must have a URL specified"))?
+            .parse::<Uri>()
+            .map_err(|err| anyhow::anyhow!("Failed to parse the webhook URL: {err}"))?,
+        ),
+        Some(task) => Err(anyhow::anyhow!("Unsupported scheduled task: \"{task}\""))?,
+        None => Err(anyhow::anyhow!("The scheduled task must be specified"))?,
+      };
+      scheduled_tasks.push(ScheduledTask {
+        schedule,
+        task: Arc::new(task),
+      });
+    }
+  }
+  Ok(scheduled_tasks)
+}
+
+/// Sends a GET request to the webhook URL
+async fn ping_webhook(url: Uri) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let client = Client::builder(TokioExecutor::new()).build(
+    hyper_rustls::HttpsConnectorBuilder::new()
+      .with_tls_config(build_raw_rustls_client_config(
+        false,
+        Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+      )?)
+      .https_or_http()
+      .enable_http1()
+      .enable_http2()
+      .build(),
+  );
+  let request = Request::builder()
+    .method(Method::GET)
+    .uri(url)
+    .body(Empty::<Bytes>::new())?;
+  let response = tokio::time::timeout(WEBHOOK_TIMEOUT, client.request(request))
+    .await
+    .map_err(|_| anyhow::anyhow!("The request has timed out"))??;
+  if !response.status().is_success() {
+    Err(anyhow::anyhow!(
+      "The server responded with the {} status code",
+      response.status()
+    ))?
+  }
+  Ok(())
+}
+
+/// Executes a maintenance task
+async fn run_maintenance_task(task: &MaintenanceTask, logger: &ErrorLogger) {
+  match task {
+    MaintenanceTask::Registered(task) => {
+      let task = *task;
+      tokio::task::spawn_blocking(move || run_scheduled_task(task))
+        .await
+        .unwrap_or_default();
+    }
+    MaintenanceTask::BlocklistRefresh => {
+      let (reload_tx, _) = &**SCHEDULED_RELOAD_CHANNEL;
+      // If a reload is already pending, another one isn't needed
+      reload_tx.try_send(()).unwrap_or_default();
+    }
+    MaintenanceTask::CertificateCacheCleanup(certificate_cache_paths) => {
+      for certificate_cache_path in certificate_cache_paths.iter() {
+        if let Err(err) = remove_expired_cached_certificates(certificate_cache_path).await {
+          logger
+            .log(&format!(
+              "Can't remove expired certificates from the \"{}\" ACME cache directory: {err}",
+              certificate_cache_path.display()
+            ))
+            .await;
+        }
+      }
+    }
+    MaintenanceTask::Webhook(url) => {
+      if let Err(err) = ping_webhook(url.clone()).await {
+        logger.log(&format!("Can't ping the \"{url}\" webhook: {err}")).await;
+      }
+    }
+  }
+}
+
+/// Runs the scheduled maintenance tasks. The schedules are evaluated in the local time zone at the start of each minute.
+pub async fn background_scheduler(scheduled_tasks: Vec<ScheduledTask>, logger: Arc<ErrorLogger>) {
+  loop {
+    let now = Local::now();
+    let Some(next_minute) = now
+      .with_second(0)
+      .and_then(|time| time.with_nanosecond(0))
+      .and_then(|time| time.checked_add_signed(TimeDelta::minutes(1)))
+    else {
+      // The next minute can't be represented, so just wait a minute
+      tokio::time::sleep(Duration::from_secs(60)).await;
+      continue;
+    };
+    tokio::time::sleep((next_minute - now).to_std().unwrap_or_default()).await;
+
+    for scheduled_task in &scheduled_tasks {
+      if schedule_matches(&scheduled_task.schedule, next_minute) {
+        // The tasks are executed concurrently, so that a long-running task doesn't delay other tasks
+        let task = scheduled_task.task.clone();
+        let logger = logger.clone();
+        tokio::spawn(async move {
+          run_maintenance_task(&task, &logger).await;
+        });
+      }
+    }
+  }
+}
+
+/// Checks if the schedule matches the specified time
+fn schedule_matches(schedule: &CronSchedule, time: DateTime<Local>) -> bool {
+  schedule.matches(
+    time.minute(),
+    time.hour(),
+    time.day(),
+    time.month(),
+    time.weekday().num_days_from_sunday(),
+  )
+}
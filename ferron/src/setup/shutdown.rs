@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use ferron_common::config::ServerConfiguration;
+use ferron_common::logging::LogMessage;
+use ferron_common::{get_entries, get_value};
+
+/// Whether the server is draining before shutting down
+static SHUTDOWN_DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// Checks whether the server is draining before shutting down.
+/// During the drain period, health checks are answered with "503 Service Unavailable".
+pub fn is_shutdown_draining() -> bool {
+  SHUTDOWN_DRAINING.load(Ordering::Relaxed)
+}
+
+/// Obtains the shutdown drain period from the `shutdown_drain_period` global directive
+fn read_shutdown_drain_period(global_configuration: &ServerConfiguration) -> Option<Duration> {
+  get_value!("shutdown_drain_period", global_configuration).and_then(|v| v.as_duration())
+}
+
+/// Runs the shutdown hooks (`shutdown_hook` global directives), and waits for the shutdown drain period
+/// (`shutdown_drain_period` global directive), while the listeners still accept connections.
+/// The hooks are run in the configuration order, each one after the previous one exits.
+/// The drain period is cut short, if another shutdown signal (CTRL+C) is received.
+pub fn drain_before_shutdown(runtime: &tokio::runtime::Runtime, global_configuration: Option<&ServerConfiguration>) {
+  let Some(global_configuration) = global_configuration else {
+    return;
+  };
+  let shutdown_hooks = get_entries!("shutdown_hook", global_configuration)
+    .map(|entries| {
+      entries
+        .inner
+        .iter()
+        .filter_map(|entry| {
+          entry
+            .values
+            .iter()
+            .map(|v| v.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()
+        })
+        .filter(|command| !command.is_empty())
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  let drain_period = read_shutdown_drain_period(global_configuration);
+  if shutdown_hooks.is_empty() && drain_period.is_none() {
+    return;
+  }
+
+  SHUTDOWN_DRAINING.store(true, Ordering::Relaxed);
+  let log_channels = &global_configuration.observability.log_channels;
+
+  runtime.block_on(async move {
+    for command in shutdown_hooks {
+      let status = tokio::process::Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await;
+      let error_message = match status {
+        Ok(status) if status.success() => continue,
+        Ok(status) => format!("The \"{}\" shutdown hook exited with {status}", command[0]),
+        Err(err) => format!("Can't run the \"{}\" shutdown hook: {err}", command[0]),
+      };
+      for logging_tx in log_channels {
+        logging_tx
+          .send(LogMessage::new(error_message.clone(), true))
+          .await
+          .unwrap_or_default();
+      }
+    }
+
+    if let Some(drain_period) = drain_period {
+      for logging_tx in log_channels {
+        logging_tx
+          .send(LogMessage::new(
+            format!(
+              "Draining the server for {} seconds before shutting down...",
+              drain_period.as_secs_f64()
+            ),
+            false,
+          ))
+          .await
+          .unwrap_or_default();
+      }
+      tokio::select! {
+        _ = tokio::time::sleep(drain_period) => {}
+        _ = tokio::signal::ctrl_c() => {}
+      }
+    }
+  });
+}
@@ -17,11 +17,12 @@ use std::net::IpAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use async_channel::{Receiver, Sender};
 use ferron_common::config::ServerConfigurationFilters;
-use ferron_common::get_entry;
 use ferron_common::logging::LogMessage;
+use ferron_common::{get_entries, get_entry};
 use instant_acme::ChallengeType;
 use rustls::crypto::CryptoProvider;
 use rustls::sign::CertifiedKey;
@@ -40,6 +41,7 @@ use crate::util::{
 /// - Preload certificates
 /// - Run ACME background tasks
 /// - Handle on-demand certificate issuance
+/// - Build TLS server configurations for hosts with their own TLS settings
 ///
 /// It intentionally groups multiple maps and locks to avoid threading a large
 /// number of parameters through builder functions.
@@ -58,6 +60,8 @@ pub struct TlsBuildContext {
   pub acme_on_demand_configs: Vec<AcmeOnDemandConfig>,
   pub acme_on_demand_tx: Sender<(String, u16)>,
   pub acme_on_demand_rx: Receiver<(String, u16)>,
+  pub host_tls_configurations:
+    HashMap<(Option<IpAddr>, u16), Vec<(Option<String>, Arc<ferron_common::config::ServerConfiguration>)>>,
 }
 
 impl Default for TlsBuildContext {
@@ -77,10 +81,60 @@ impl Default for TlsBuildContext {
       acme_on_demand_configs: Vec::new(),
       acme_on_demand_tx,
       acme_on_demand_rx,
+      host_tls_configurations: HashMap::new(),
     }
   }
 }
 
+/// Caches certified keys loaded from manually provided certificate and private key files.
+///
+/// The cache outlives configuration reloads, so that unchanged certificates of
+/// unaffected hosts aren't loaded from disk again. A cached certified key is
+/// reused only if the modification times of both files haven't changed.
+#[derive(Default)]
+#[allow(clippy::type_complexity)]
+pub struct CertifiedKeyCache {
+  entries: HashMap<(String, String), ((SystemTime, SystemTime), Arc<CertifiedKey>)>,
+  used: HashSet<(String, String)>,
+}
+
+impl CertifiedKeyCache {
+  /// Obtains the cached certified key, or loads it using the provided function.
+  fn get_or_load(
+    &mut self,
+    cert_path: &str,
+    key_path: &str,
+    load: impl FnOnce() -> anyhow::Result<Arc<CertifiedKey>>,
+  ) -> anyhow::Result<Arc<CertifiedKey>> {
+    let cache_key = (cert_path.to_string(), key_path.to_string());
+    let modification_times = std::fs::metadata(cert_path)
+      .and_then(|m| m.modified())
+      .and_then(|cert_modified| Ok((cert_modified, std::fs::metadata(key_path)?.modified()?)))
+      .ok();
+    if let Some((cached_modification_times, certified_key)) = self.entries.get(&cache_key) {
+      if modification_times.as_ref() == Some(cached_modification_times) {
+        self.used.insert(cache_key);
+        return Ok(certified_key.clone());
+      }
+    }
+
+    let certified_key = load()?;
+    if let Some(modification_times) = modification_times {
+      self
+        .entries
+        .insert(cache_key.clone(), (modification_times, certified_key.clone()));
+      self.used.insert(cache_key);
+    }
+    Ok(certified_key)
+  }
+
+  /// Removes the certified keys that weren't used since the previous removal.
+  pub fn remove_unused(&mut self) {
+    let used = std::mem::take(&mut self.used);
+    self.entries.retain(|cache_key, _| used.contains(cache_key));
+  }
+}
+
 /// Reads the default port from the given server configuration.
 pub fn read_default_port(config: Option<&ferron_common::config::ServerConfiguration>, is_https: bool) -> Option<u16> {
   let fallback = if is_https { 443 } else { 80 };
@@ -102,16 +156,47 @@ pub fn read_default_port(config: Option<&ferron_common::config::ServerConfigurat
     })
 }
 
-/// Resolves the SNI hostname from the given filters.
+/// Reads the SNI passthrough routes (hostnames, ALPN protocols and backend server addresses) from the server configuration.
+pub fn read_sni_routes(
+  config: Option<&ferron_common::config::ServerConfiguration>,
+) -> Vec<(String, Option<String>, String)> {
+  config
+    .and_then(|c| get_entries!("sni_route", c))
+    .map(|entries| {
+      entries
+        .inner
+        .iter()
+        .filter_map(|entry| {
+          let hostname = entry.values.first()?.as_str()?;
+          // The "->" separator between the hostname and the backend server address is optional
+          let backend = entry.values.last().filter(|_| entry.values.len() > 1)?.as_str()?;
+          // The route can apply only to connections, for which the client offers a specific application protocol (ALPN)
+          let alpn = entry.props.get("alpn").and_then(|v| v.as_str()).map(str::to_string);
+          Some((
+            hostname.to_lowercase(),
+            alpn,
+            backend.strip_prefix("tcp://").unwrap_or(backend).to_string(),
+          ))
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Resolves the SNI hostname from the given filters. Regular expression hostnames don't have SNI hostnames.
 pub fn resolve_sni_hostname(filters: &ServerConfigurationFilters) -> Option<String> {
-  filters.hostname.clone().or_else(|| {
-    if filters.ip.is_some_and(|ip| ip.is_loopback()) {
-      // Host blocks with "localhost" specified will have "localhost" SNI hostname
-      return Some("localhost".to_string());
-    }
+  filters
+    .hostname
+    .clone()
+    .filter(|_| !filters.has_regex_hostname())
+    .or_else(|| {
+      if filters.ip.is_some_and(|ip| ip.is_loopback()) {
+        // Host blocks with "localhost" specified will have "localhost" SNI hostname
+        return Some("localhost".to_string());
+      }
 
-    None
-  })
+      None
+    })
 }
 
 /// Ensures that a TLS SNI resolver exists for the given port.
@@ -131,11 +216,12 @@ fn ensure_tls_port_resolver(ctx: &mut TlsBuildContext, port: u16, ip: Option<IpA
 /// Configures a manually provided TLS certificate and private key.
 ///
 /// This function:
-/// - Loads and validates the certificate and private key
+/// - Loads and validates the certificate and private key, unless they're cached and unchanged
 /// - Registers the certificate for preloading
 /// - Installs an SNI resolver (or fallback resolver) for the given port
 ///
 /// Manual TLS always takes precedence over automatic TLS.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_manual_tls(
   ctx: &mut TlsBuildContext,
   crypto_provider: &CryptoProvider,
@@ -144,17 +230,11 @@ pub fn handle_manual_tls(
   sni_hostname: Option<String>,
   cert_path: &str,
   key_path: &str,
+  certified_key_cache: &mut CertifiedKeyCache,
 ) -> anyhow::Result<()> {
-  let certs = load_certs(cert_path).map_err(|e| anyhow::anyhow!("Cannot load certificate {cert_path}: {e}"))?;
-
-  let key = load_private_key(key_path).map_err(|e| anyhow::anyhow!("Cannot load key {key_path}: {e}"))?;
-
-  let signing_key = crypto_provider
-    .key_provider
-    .load_private_key(key)
-    .map_err(|e| anyhow::anyhow!("Invalid private key {key_path}: {e}"))?;
-
-  let certified_key = Arc::new(CertifiedKey::new(certs, signing_key));
+  let certified_key = certified_key_cache.get_or_load(cert_path, key_path, || {
+    load_certified_key(crypto_provider, cert_path, key_path)
+  })?;
 
   ctx
     .certified_keys_to_preload
@@ -174,6 +254,67 @@ pub fn handle_manual_tls(
   Ok(())
 }
 
+/// Loads a certified key from the certificate and private key files.
+fn load_certified_key(
+  crypto_provider: &CryptoProvider,
+  cert_path: &str,
+  key_path: &str,
+) -> anyhow::Result<Arc<CertifiedKey>> {
+  let certs = load_certs(cert_path).map_err(|e| anyhow::anyhow!("Cannot load certificate {cert_path}: {e}"))?;
+
+  let key = load_private_key(key_path).map_err(|e| anyhow::anyhow!("Cannot load key {key_path}: {e}"))?;
+
+  let signing_key = crypto_provider
+    .key_provider
+    .load_private_key(key)
+    .map_err(|e| anyhow::anyhow!("Invalid private key {key_path}: {e}"))?;
+
+  Ok(Arc::new(CertifiedKey::new(certs, signing_key)))
+}
+
+/// Loads the default TLS certificate specified in the global configuration.
+///
+/// The default certificate is used when the client doesn't send a server name (SNI),
+/// or no host matches it. It's either loaded from the certificate and private key files,
+/// or generated as a self-signed certificate.
+pub fn load_default_certified_key(
+  config: Option<&ferron_common::config::ServerConfiguration>,
+  crypto_provider: &CryptoProvider,
+  certified_key_cache: &mut CertifiedKeyCache,
+) -> anyhow::Result<Option<Arc<CertifiedKey>>> {
+  let Some(entry) = config.and_then(|c| get_entry!("tls_default", c)) else {
+    return Ok(None);
+  };
+
+  match (
+    entry.values.first().and_then(|v| v.as_str()),
+    entry.values.get(1).and_then(|v| v.as_str()),
+  ) {
+    (Some(cert_path), Some(key_path)) => certified_key_cache
+      .get_or_load(cert_path, key_path, || {
+        load_certified_key(crypto_provider, cert_path, key_path)
+      })
+      .map(Some),
+    (Some("self_signed"), None) => {
+      let params = rcgen::CertificateParams::new(vec!["localhost".to_string()])?;
+      let key_pair = rcgen::KeyPair::generate()?;
+      let certificate = params.self_signed(&key_pair)?;
+      let private_key = rustls_pki_types::PrivateKeyDer::try_from(key_pair.serialize_der())
+        .map_err(|e| anyhow::anyhow!("Invalid private key of the self-signed default certificate: {e}"))?;
+      let signing_key = crypto_provider
+        .key_provider
+        .load_private_key(private_key)
+        .map_err(|e| anyhow::anyhow!("Invalid private key of the self-signed default certificate: {e}"))?;
+
+      Ok(Some(Arc::new(CertifiedKey::new(
+        vec![certificate.der().to_owned()],
+        signing_key,
+      ))))
+    }
+    _ => Ok(None),
+  }
+}
+
 /// Parses ACME challenge type from server configuration.
 fn parse_challenge_type(
   server: &ferron_common::config::ServerConfiguration,
@@ -382,19 +523,21 @@ fn build_on_demand_acme(
   let sni_resolver = ensure_tls_port_resolver(ctx, port, ip);
   sni_resolver.load_fallback_sender(fallback_sender, port);
 
-  let rustls_client_config =
-    super::acme::build_rustls_client_config(server, crypto_provider).map_err(|e| anyhow::anyhow!(e))?;
+  let (issuer, fallback_issuers) =
+    super::acme::resolve_acme_issuers(server, crypto_provider).map_err(|e| anyhow::anyhow!(e))?;
 
   let config = AcmeOnDemandConfig {
-    rustls_client_config,
+    rustls_client_config: issuer.rustls_client_config,
     challenge_type,
     contact: get_entry!("auto_tls_contact", server)
       .and_then(|e| e.values.first())
       .and_then(|v| v.as_str())
       .map(|c| vec![format!("mailto:{c}")])
       .unwrap_or_default(),
-    directory: super::acme::resolve_acme_directory(server),
-    eab_key: super::acme::parse_eab(server)?,
+    directory: issuer.directory,
+    eab_key: issuer.eab_key,
+    issuer_name: issuer.name,
+    fallback_issuers,
     profile: get_entry!("auto_tls_profile", server)
       .and_then(|e| e.values.first())
       .and_then(|v| v.as_str())
@@ -451,8 +594,8 @@ fn build_eager_acme(
     return Ok(());
   };
 
-  let rustls_client_config =
-    super::acme::build_rustls_client_config(server, crypto_provider).map_err(|e| anyhow::anyhow!(e))?;
+  let (issuer, fallback_issuers) =
+    super::acme::resolve_acme_issuers(server, crypto_provider).map_err(|e| anyhow::anyhow!(e))?;
   let (account_cache_path, certificate_cache_path) = super::acme::resolve_cache_paths(server, port, &domain)?;
 
   let save_paths = get_entry!("auto_tls_save_data", server).and_then(|e| {
@@ -470,7 +613,7 @@ fn build_eager_acme(
   });
 
   let acme_config = AcmeConfig {
-    rustls_client_config,
+    rustls_client_config: issuer.rustls_client_config,
     domains: vec![domain],
     challenge_type: challenge_type.clone(),
     contact: get_entry!("auto_tls_contact", server)
@@ -478,8 +621,10 @@ fn build_eager_acme(
       .and_then(|v| v.as_str())
       .map(|c| vec![format!("mailto:{c}")])
       .unwrap_or_default(),
-    directory: super::acme::resolve_acme_directory(server),
-    eab_key: super::acme::parse_eab(server)?,
+    directory: issuer.directory,
+    eab_key: issuer.eab_key,
+    issuer_name: issuer.name,
+    fallback_issuers,
     profile: get_entry!("auto_tls_profile", server)
       .and_then(|e| e.values.first())
       .and_then(|v| v.as_str())
@@ -499,6 +644,8 @@ fn build_eager_acme(
     http_01_data_lock: http_01_data_lock.clone(),
     dns_provider,
     renewal_info: None,
+    renewal_info_unsupported: false,
+    renewal_schedule: None,
     account: None,
     save_paths,
     post_obtain_command: get_entry!("auto_tls_post_obtain_command", server)
@@ -1,28 +1,152 @@
+use std::sync::Arc;
+
 use ferron_common::{get_value, get_values};
 use rustls::crypto::aws_lc_rs::cipher_suite::*;
 use rustls::crypto::aws_lc_rs::default_provider;
 use rustls::crypto::aws_lc_rs::kx_group::*;
-use rustls::crypto::CryptoProvider;
+use rustls::crypto::{CryptoProvider, SupportedKxGroup};
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::{WantsServerCert, WebPkiClientVerifier};
 use rustls::version::{TLS12, TLS13};
-use rustls::{ConfigBuilder, ServerConfig, WantsVerifier, WantsVersions};
+use rustls::{
+  ConfigBuilder, RootCertStore, ServerConfig, SupportedCipherSuite, SupportedProtocolVersion, WantsVerifier,
+  WantsVersions,
+};
+use rustls_native_certs::load_native_certs;
+
+use crate::util::load_certs;
+
+/// The directives configuring the TLS settings, which can differ between hosts
+const HOST_TLS_DIRECTIVES: [&str; 6] = [
+  "tls_policy",
+  "tls_cipher_suite",
+  "tls_ecdh_curve",
+  "tls_min_version",
+  "tls_max_version",
+  "tls_client_certificate",
+];
+
+/// The TLS versions of the TLS policies allowing only TLS 1.3
+static TLS13_VERSIONS: &[&SupportedProtocolVersion] = &[&TLS13];
+
+/// The TLS versions of the TLS policies allowing both TLS 1.3 and TLS 1.2
+static TLS13_AND_TLS12_VERSIONS: &[&SupportedProtocolVersion] = &[&TLS13, &TLS12];
+
+/// A TLS policy profile, which expands to a curated set of TLS versions, cipher suites and key exchange groups.
+/// The profiles track the Mozilla server side TLS guidelines, limited to what Rustls supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsPolicy {
+  /// TLS 1.3 only, for modern clients
+  Modern,
+
+  /// TLS 1.2 and TLS 1.3 with forward secrecy and AEAD cipher suites, for general-purpose servers
+  Intermediate,
+
+  /// TLS 1.3 only, with the X25519MLKEM768 post-quantum hybrid key exchange required
+  PqHybrid,
+
+  /// All TLS versions and cipher suites supported by Rustls, with the server's cipher suite preference
+  Legacy,
+}
+
+impl TlsPolicy {
+  /// Parses the TLS policy name
+  pub fn parse(policy: &str) -> Option<Self> {
+    match policy {
+      "modern" => Some(Self::Modern),
+      "intermediate" => Some(Self::Intermediate),
+      "pq-hybrid" => Some(Self::PqHybrid),
+      "legacy" => Some(Self::Legacy),
+      _ => None,
+    }
+  }
+
+  /// Obtains the cipher suites of the TLS policy, in the order of preference
+  fn cipher_suites(self) -> Vec<SupportedCipherSuite> {
+    let mut cipher_suites = vec![
+      TLS13_AES_128_GCM_SHA256,
+      TLS13_AES_256_GCM_SHA384,
+      TLS13_CHACHA20_POLY1305_SHA256,
+    ];
+    if matches!(self, Self::Intermediate | Self::Legacy) {
+      cipher_suites.extend([
+        TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+        TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+        TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+        TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+        TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+        TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
+      ]);
+    }
+    cipher_suites
+  }
+
+  /// Obtains the key exchange groups of the TLS policy, in the order of preference
+  fn kx_groups(self) -> Vec<&'static dyn SupportedKxGroup> {
+    match self {
+      Self::PqHybrid => vec![X25519MLKEM768],
+      _ => vec![X25519MLKEM768, X25519, SECP256R1, SECP384R1],
+    }
+  }
+
+  /// Obtains the TLS versions of the TLS policy
+  fn protocol_versions(self) -> &'static [&'static SupportedProtocolVersion] {
+    match self {
+      Self::Modern | Self::PqHybrid => TLS13_VERSIONS,
+      Self::Intermediate | Self::Legacy => TLS13_AND_TLS12_VERSIONS,
+    }
+  }
+}
+
+/// Reads the TLS policy from the configuration
+fn read_tls_policy(configuration: Option<&ferron_common::config::ServerConfiguration>) -> Option<TlsPolicy> {
+  configuration
+    .and_then(|c| get_value!("tls_policy", c))
+    .and_then(|v| v.as_str())
+    .and_then(TlsPolicy::parse)
+}
+
+/// Checks whether the TLS settings of the host configuration differ from the ones of the global configuration.
+/// Host configurations inherit the TLS settings from the global configuration.
+pub fn has_host_tls_settings(
+  host_configuration: &ferron_common::config::ServerConfiguration,
+  global_configuration: Option<&ferron_common::config::ServerConfiguration>,
+) -> bool {
+  HOST_TLS_DIRECTIVES.iter().any(|directive| {
+    host_configuration.entries.get(*directive) != global_configuration.and_then(|c| c.entries.get(*directive))
+  })
+}
+
+/// Sets the TLS server configuration options, which aren't set by the configuration builder, based on the configuration.
+pub fn set_tls_options(
+  tls_config: &mut ServerConfig,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
+) {
+  // The Mozilla "old" configuration uses the server's cipher suite preference
+  tls_config.ignore_client_order = read_tls_policy(configuration) == Some(TlsPolicy::Legacy);
+}
 
 /// Initializes the cryptography provider for Rustls.
 pub fn init_crypto_provider(
-  global_configuration: Option<&ferron_common::config::ServerConfiguration>,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
 ) -> Result<CryptoProvider, anyhow::Error> {
   let mut crypto_provider = default_provider();
-  set_cipher_suites(&mut crypto_provider, global_configuration)?;
-  set_ecdh_curves(&mut crypto_provider, global_configuration)?;
+  if let Some(tls_policy) = read_tls_policy(configuration) {
+    crypto_provider.cipher_suites = tls_policy.cipher_suites();
+    crypto_provider.kx_groups = tls_policy.kx_groups();
+  }
+  set_cipher_suites(&mut crypto_provider, configuration)?;
+  set_ecdh_curves(&mut crypto_provider, configuration)?;
   Ok(crypto_provider)
 }
 
-/// Sets cipher suites based on the global configuration
+/// Sets cipher suites based on the configuration
 fn set_cipher_suites(
   crypto_provider: &mut CryptoProvider,
-  global_configuration: Option<&ferron_common::config::ServerConfiguration>,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
 ) -> Result<(), anyhow::Error> {
   let cipher_suite: Vec<&ferron_common::config::ServerConfigurationValue> =
-    global_configuration.map_or(vec![], |c| get_values!("tls_cipher_suite", c));
+    configuration.map_or(vec![], |c| get_values!("tls_cipher_suite", c));
   if !cipher_suite.is_empty() {
     let mut cipher_suites = Vec::new();
     let cipher_suite_iter = cipher_suite.iter();
@@ -51,12 +175,12 @@ fn set_cipher_suites(
   Ok(())
 }
 
-/// Sets ECDH curves based on the global configuration.
+/// Sets ECDH curves based on the configuration.
 fn set_ecdh_curves(
   crypto_provider: &mut CryptoProvider,
-  global_configuration: Option<&ferron_common::config::ServerConfiguration>,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
 ) -> Result<(), anyhow::Error> {
-  let ecdh_curves = global_configuration.map_or(vec![], |c| get_values!("tls_ecdh_curve", c));
+  let ecdh_curves = configuration.map_or(vec![], |c| get_values!("tls_ecdh_curve", c));
   if !ecdh_curves.is_empty() {
     let mut kx_groups = Vec::new();
     let ecdh_curves_iter = ecdh_curves.iter();
@@ -66,8 +190,9 @@ fn set_ecdh_curves(
           "secp256r1" => SECP256R1,
           "secp384r1" => SECP384R1,
           "x25519" => X25519,
-          "x25519mklem768" => X25519MLKEM768,
-          "mklem768" => MLKEM768,
+          // The misspelled "x25519mklem768" and "mklem768" names are supported for backwards compatibility
+          "x25519mlkem768" | "x25519mklem768" => X25519MLKEM768,
+          "mlkem768" | "mklem768" => MLKEM768,
           _ => Err(anyhow::anyhow!("The \"{}\" ECDH curve is not supported", ecdh_curve))?,
         };
         kx_groups.push(kx_group_to_add);
@@ -78,20 +203,23 @@ fn set_ecdh_curves(
   Ok(())
 }
 
-/// Sets the TLS version based on the global configuration.
+/// Sets the TLS version based on the configuration.
 pub fn set_tls_version(
   tls_config_builder_wants_versions: ConfigBuilder<ServerConfig, WantsVersions>,
-  global_configuration: Option<&ferron_common::config::ServerConfiguration>,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
 ) -> Result<ConfigBuilder<ServerConfig, WantsVerifier>, anyhow::Error> {
-  let min_tls_version_option = global_configuration
+  let min_tls_version_option = configuration
     .and_then(|c| get_value!("tls_min_version", c))
     .and_then(|v| v.as_str());
-  let max_tls_version_option = global_configuration
+  let max_tls_version_option = configuration
     .and_then(|c| get_value!("tls_max_version", c))
     .and_then(|v| v.as_str());
 
   let tls_config_builder_wants_verifier = if min_tls_version_option.is_none() && max_tls_version_option.is_none() {
-    tls_config_builder_wants_versions.with_safe_default_protocol_versions()?
+    match read_tls_policy(configuration) {
+      Some(tls_policy) => tls_config_builder_wants_versions.with_protocol_versions(tls_policy.protocol_versions())?,
+      None => tls_config_builder_wants_versions.with_safe_default_protocol_versions()?,
+    }
   } else {
     let tls_versions = [("TLSv1.2", &TLS12), ("TLSv1.3", &TLS13)];
     let min_tls_version_index = min_tls_version_option
@@ -115,3 +243,64 @@ pub fn set_tls_version(
 
   Ok(tls_config_builder_wants_verifier)
 }
+
+/// Builds the TLS client certificate verifier based on the configuration.
+/// Returns `None`, if the TLS client certificate verification is disabled.
+pub fn build_client_cert_verifier(
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
+) -> Result<Option<Arc<dyn ClientCertVerifier>>, anyhow::Error> {
+  let roots = if let Some(client_cert_path) = configuration
+    .and_then(|c| get_value!("tls_client_certificate", c))
+    .and_then(|v| v.as_str())
+  {
+    let mut roots = RootCertStore::empty();
+    let client_certificate_cas = load_certs(client_cert_path)?;
+    for cert in client_certificate_cas {
+      roots.add(cert)?;
+    }
+    roots
+  } else if configuration
+    .and_then(|c| get_value!("tls_client_certificate", c))
+    .and_then(|v| v.as_bool())
+    .unwrap_or(false)
+  {
+    (|| {
+      let certs_result = load_native_certs();
+      if !certs_result.errors.is_empty() {
+        return None;
+      }
+      let certs = certs_result.certs;
+
+      let mut roots = RootCertStore::empty();
+      for cert in certs {
+        if roots.add(cert).is_err() {
+          return None;
+        }
+      }
+
+      Some(roots)
+    })()
+    .unwrap_or(RootCertStore {
+      roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    })
+  } else {
+    return Ok(None);
+  };
+
+  Ok(Some(WebPkiClientVerifier::builder(Arc::new(roots)).build()?))
+}
+
+/// Builds the TLS server configuration builder with the cryptography provider, the TLS versions
+/// and the TLS client certificate verifier.
+pub fn build_tls_config_builder(
+  crypto_provider: Arc<CryptoProvider>,
+  configuration: Option<&ferron_common::config::ServerConfiguration>,
+  client_cert_verifier: Option<Arc<dyn ClientCertVerifier>>,
+) -> Result<ConfigBuilder<ServerConfig, WantsServerCert>, anyhow::Error> {
+  let tls_config_builder_wants_versions = ServerConfig::builder_with_provider(crypto_provider);
+  let tls_config_builder_wants_verifier = set_tls_version(tls_config_builder_wants_versions, configuration)?;
+  Ok(match client_cert_verifier {
+    Some(client_cert_verifier) => tls_config_builder_wants_verifier.with_client_cert_verifier(client_cert_verifier),
+    None => tls_config_builder_wants_verifier.with_no_client_auth(),
+  })
+}
@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+
+use async_channel::{Receiver, Sender};
+use windows_service::service::{
+  ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+  ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// The name of the Windows service
+pub const SERVICE_NAME: &str = "ferron";
+
+/// The display name of the Windows service
+const SERVICE_DISPLAY_NAME: &str = "Ferron";
+
+/// The description of the Windows service
+const SERVICE_DESCRIPTION: &str = "This service runs Ferron - a fast, memory-safe web server written in Rust.";
+
+/// The user-defined service control code used to reload the server configuration
+pub const SERVICE_CONTROL_RELOAD: u32 = 128;
+
+/// A control event received from the Service Control Manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceControlEvent {
+  /// Stop the server
+  Stop,
+
+  /// Reload the server configuration
+  Reload,
+}
+
+/// The channel used to forward Service Control Manager events to the server loop
+pub static SERVICE_CONTROL_CHANNEL: LazyLock<Arc<(Sender<ServiceControlEvent>, Receiver<ServiceControlEvent>)>> =
+  LazyLock::new(|| Arc::new(async_channel::unbounded()));
+
+/// The function that starts the server when running as a service
+static SERVICE_SERVER_FN: OnceLock<fn() -> Result<(), Box<dyn Error + Send + Sync>>> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Installs Ferron as a Windows service that uses the specified configuration file
+pub fn install_service(configuration_path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let manager = ServiceManager::local_computer(
+    None::<&str>,
+    ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+  )?;
+
+  let configuration_path = std::path::absolute(configuration_path)?;
+  let service_info = ServiceInfo {
+    name: OsString::from(SERVICE_NAME),
+    display_name: OsString::from(SERVICE_DISPLAY_NAME),
+    service_type: ServiceType::OWN_PROCESS,
+    start_type: ServiceStartType::AutoStart,
+    error_control: ServiceErrorControl::Normal,
+    executable_path: std::env::current_exe()?,
+    launch_arguments: vec![
+      OsString::from("--config"),
+      configuration_path.into_os_string(),
+      OsString::from("service"),
+      OsString::from("run"),
+    ],
+    dependencies: vec![],
+    account_name: None,
+    account_password: None,
+  };
+
+  let service = manager.create_service(&service_info, ServiceAccess::CHANGE_CONFIG)?;
+  service.set_description(SERVICE_DESCRIPTION)?;
+
+  println!("The \"{SERVICE_NAME}\" service has been installed.");
+
+  Ok(())
+}
+
+/// Uninstalls the Ferron Windows service, stopping it first if it's running
+pub fn uninstall_service() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+  let service = manager.open_service(
+    SERVICE_NAME,
+    ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+  )?;
+
+  // Mark the service for deletion; it's removed once it's stopped and all handles are closed
+  service.delete()?;
+  if service.query_status()?.current_state != ServiceState::Stopped {
+    service.stop()?;
+  }
+
+  println!("The \"{SERVICE_NAME}\" service has been uninstalled.");
+
+  Ok(())
+}
+
+/// Runs Ferron under the Service Control Manager. This function blocks until the service is stopped.
+pub fn run_service(
+  server_fn: fn() -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  SERVICE_SERVER_FN
+    .set(server_fn)
+    .map_err(|_| anyhow::anyhow!("The service is already running"))?;
+  service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+  Ok(())
+}
+
+/// The service entry point called by the Service Control Manager
+fn service_main(_arguments: Vec<OsString>) {
+  if let Err(err) = run_service_inner() {
+    eprintln!("Error while running a service: {err}");
+  }
+}
+
+/// Registers the service control handler and runs the server
+fn run_service_inner() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let (control_tx, _) = &**SERVICE_CONTROL_CHANNEL;
+  let control_tx = control_tx.clone();
+  let status_handle = service_control_handler::register(SERVICE_NAME, move |control_event| match control_event {
+    ServiceControl::Stop | ServiceControl::Shutdown => {
+      control_tx.send_blocking(ServiceControlEvent::Stop).unwrap_or_default();
+      ServiceControlHandlerResult::NoError
+    }
+    ServiceControl::Paramchange => {
+      control_tx
+        .send_blocking(ServiceControlEvent::Reload)
+        .unwrap_or_default();
+      ServiceControlHandlerResult::NoError
+    }
+    ServiceControl::UserEvent(code) if code.to_raw() == SERVICE_CONTROL_RELOAD => {
+      control_tx
+        .send_blocking(ServiceControlEvent::Reload)
+        .unwrap_or_default();
+      ServiceControlHandlerResult::NoError
+    }
+    ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+    _ => ServiceControlHandlerResult::NotImplemented,
+  })?;
+
+  status_handle.set_service_status(ServiceStatus {
+    service_type: ServiceType::OWN_PROCESS,
+    current_state: ServiceState::Running,
+    controls_accepted: ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN | ServiceControlAccept::PARAMCHANGE,
+    exit_code: ServiceExitCode::Win32(0),
+    checkpoint: 0,
+    wait_hint: Duration::default(),
+    process_id: None,
+  })?;
+
+  let server_result = match SERVICE_SERVER_FN.get() {
+    Some(server_fn) => server_fn(),
+    None => Err(anyhow::anyhow!("The service server function isn't set").into()),
+  };
+
+  status_handle.set_service_status(ServiceStatus {
+    service_type: ServiceType::OWN_PROCESS,
+    current_state: ServiceState::Stopped,
+    controls_accepted: ServiceControlAccept::empty(),
+    exit_code: if server_result.is_ok() {
+      ServiceExitCode::Win32(0)
+    } else {
+      ServiceExitCode::ServiceSpecific(1)
+    },
+    checkpoint: 0,
+    wait_hint: Duration::default(),
+    process_id: None,
+  })?;
+
+  server_result
+}
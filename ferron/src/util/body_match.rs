@@ -0,0 +1,249 @@
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use ferron_common::modules::BufferedRequestBody;
+use ferron_common::util::MemoryReservation;
+use futures_util::stream::{self, StreamExt};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use hyper::body::Frame;
+
+/// The result of buffering the request body for body-based routing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyMatchBufferResult {
+  /// The whole request body was buffered
+  Complete,
+  /// The request body exceeds the buffer size, so only its beginning was buffered
+  Truncated,
+  /// The request body couldn't be read
+  Error,
+  /// The request body couldn't be buffered, because the global memory budget is exceeded
+  MemoryBudgetExceeded,
+}
+
+impl BodyMatchBufferResult {
+  /// Obtains the value of the metric attribute for the buffering result
+  pub fn as_metric_value(&self) -> &'static str {
+    match self {
+      Self::Complete => "complete",
+      Self::Truncated => "truncated",
+      Self::Error => "error",
+      Self::MemoryBudgetExceeded => "memory_budget_exceeded",
+    }
+  }
+}
+
+/// Buffers up to `max_size` bytes of the request body, reserving the buffered bytes in the memory reservation.
+/// Returns the buffered body data, and the request body reconstructed from the buffered frames followed by the rest of the original body.
+pub async fn buffer_request_body(
+  mut body: BoxBody<Bytes, io::Error>,
+  max_size: usize,
+  reservation: &mut MemoryReservation,
+) -> (BufferedRequestBody, BodyMatchBufferResult, BoxBody<Bytes, io::Error>) {
+  let mut frames = Vec::new();
+  let mut data = BytesMut::new();
+  let mut result = None;
+  while data.len() <= max_size {
+    match body.frame().await {
+      Some(Ok(frame)) => match frame.into_data() {
+        Ok(chunk) => {
+          if !reservation.try_grow(chunk.len()) {
+            frames.push(Ok(Frame::data(chunk)));
+            result = Some(BodyMatchBufferResult::MemoryBudgetExceeded);
+            break;
+          }
+          data.extend_from_slice(&chunk);
+          frames.push(Ok(Frame::data(chunk)));
+        }
+        Err(frame) => {
+          // Trailers end the request body
+          frames.push(Ok(frame));
+          result = Some(BodyMatchBufferResult::Complete);
+          break;
+        }
+      },
+      Some(Err(err)) => {
+        frames.push(Err(err));
+        result = Some(BodyMatchBufferResult::Error);
+        break;
+      }
+      None => {
+        result = Some(BodyMatchBufferResult::Complete);
+        break;
+      }
+    }
+  }
+
+  let result = result.unwrap_or(BodyMatchBufferResult::Truncated);
+  let complete = result == BodyMatchBufferResult::Complete;
+  data.truncate(max_size);
+  let buffered_frames = stream::iter(frames);
+  let new_body = if matches!(
+    result,
+    BodyMatchBufferResult::Truncated | BodyMatchBufferResult::MemoryBudgetExceeded
+  ) {
+    BodyExt::boxed(StreamBody::new(buffered_frames.chain(BodyStream::new(body))))
+  } else {
+    BodyExt::boxed(StreamBody::new(buffered_frames))
+  };
+
+  (
+    BufferedRequestBody {
+      data: data.freeze(),
+      complete,
+    },
+    result,
+    new_body,
+  )
+}
+
+/// Converts a JSON path (like `$.event.action`, `$.items[0]`, or `$['event']`) into a JSON pointer
+pub fn json_path_to_pointer(path: &str) -> Result<String, String> {
+  let mut remaining = path
+    .strip_prefix('$')
+    .ok_or_else(|| format!("The JSON path \"{path}\" must start with \"$\""))?;
+  let mut pointer = String::new();
+  while !remaining.is_empty() {
+    let segment = if let Some(rest) = remaining.strip_prefix('.') {
+      let end = rest.find(['.', '[']).unwrap_or(rest.len());
+      if end == 0 || &rest[..end] == "*" {
+        return Err(format!("Invalid member name in the JSON path \"{path}\""));
+      }
+      remaining = &rest[end..];
+      &rest[..end]
+    } else if let Some(rest) = remaining.strip_prefix("['") {
+      let end = rest
+        .find("']")
+        .ok_or_else(|| format!("Unterminated member name in the JSON path \"{path}\""))?;
+      remaining = &rest[end + 2..];
+      &rest[..end]
+    } else if let Some(rest) = remaining.strip_prefix('[') {
+      let end = rest
+        .find(']')
+        .ok_or_else(|| format!("Unterminated array index in the JSON path \"{path}\""))?;
+      if end == 0 || !rest[..end].bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid array index in the JSON path \"{path}\""));
+      }
+      remaining = &rest[end + 1..];
+      &rest[..end]
+    } else {
+      return Err(format!("Unexpected character in the JSON path \"{path}\""));
+    };
+    pointer.push('/');
+    pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+  }
+  Ok(pointer)
+}
+
+/// Checks if the field at the JSON pointer in the buffered request body is equal to the value.
+/// Strings are compared as they are, and other JSON values are compared in their JSON representation.
+pub fn match_body_json_field(body: &BufferedRequestBody, pointer: &str, value: &str) -> bool {
+  if !body.complete {
+    // A truncated JSON document can't be parsed
+    return false;
+  }
+  let Ok(document) = serde_json::from_slice::<serde_json::Value>(&body.data) else {
+    return false;
+  };
+  match document.pointer(pointer) {
+    Some(serde_json::Value::String(field)) => field == value,
+    Some(field) => serde_json::to_string(field).is_ok_and(|field| field == value),
+    None => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use ferron_common::util::MemoryBudget;
+  use futures_util::FutureExt;
+  use http_body_util::Full;
+
+  fn buffered(data: &str, complete: bool) -> BufferedRequestBody {
+    BufferedRequestBody {
+      data: Bytes::from(data.to_string()),
+      complete,
+    }
+  }
+
+  fn buffer(
+    body: &'static str,
+    max_size: usize,
+    budget: Option<usize>,
+  ) -> (BufferedRequestBody, BodyMatchBufferResult, Bytes) {
+    let memory_budget: &'static MemoryBudget = Box::leak(Box::new(MemoryBudget::new()));
+    memory_budget.set_limit(budget);
+    let mut reservation = memory_budget.reserve();
+    let (buffered_body, result, new_body) = buffer_request_body(
+      Full::new(Bytes::from_static(body.as_bytes()))
+        .map_err(|e| match e {})
+        .boxed(),
+      max_size,
+      &mut reservation,
+    )
+    .now_or_never()
+    .unwrap();
+    let new_body = new_body.collect().now_or_never().unwrap().unwrap().to_bytes();
+    (buffered_body, result, new_body)
+  }
+
+  #[test]
+  fn buffers_request_bodies() {
+    let (buffered_body, result, new_body) = buffer(r#"{"event":"push"}"#, 1024, None);
+    assert_eq!(result, BodyMatchBufferResult::Complete);
+    assert!(buffered_body.complete);
+    assert_eq!(buffered_body.data, Bytes::from_static(br#"{"event":"push"}"#));
+    assert_eq!(new_body, Bytes::from_static(br#"{"event":"push"}"#));
+
+    let (buffered_body, result, new_body) = buffer(r#"{"event":"push"}"#, 1024, Some(4));
+    assert_eq!(result, BodyMatchBufferResult::MemoryBudgetExceeded);
+    assert!(!buffered_body.complete);
+    assert!(buffered_body.data.is_empty());
+    assert_eq!(new_body, Bytes::from_static(br#"{"event":"push"}"#));
+  }
+
+  #[test]
+  fn converts_json_paths_to_pointers() {
+    assert_eq!(json_path_to_pointer("$").unwrap(), "");
+    assert_eq!(json_path_to_pointer("$.event").unwrap(), "/event");
+    assert_eq!(json_path_to_pointer("$.event.action").unwrap(), "/event/action");
+    assert_eq!(json_path_to_pointer("$.items[0].name").unwrap(), "/items/0/name");
+    assert_eq!(json_path_to_pointer("$['a/b']['c~d']").unwrap(), "/a~1b/c~0d");
+  }
+
+  #[test]
+  fn rejects_invalid_json_paths() {
+    assert!(json_path_to_pointer("event").is_err());
+    assert!(json_path_to_pointer("$.").is_err());
+    assert!(json_path_to_pointer("$.items[*]").is_err());
+    assert!(json_path_to_pointer("$.items[0").is_err());
+    assert!(json_path_to_pointer("$['event").is_err());
+    assert!(json_path_to_pointer("$event").is_err());
+  }
+
+  #[test]
+  fn matches_json_fields() {
+    let body = buffered(r#"{"event":"push","count":3,"draft":false,"tags":["a"]}"#, true);
+    assert!(match_body_json_field(&body, "/event", "push"));
+    assert!(!match_body_json_field(&body, "/event", "pull_request"));
+    assert!(match_body_json_field(&body, "/count", "3"));
+    assert!(match_body_json_field(&body, "/draft", "false"));
+    assert!(match_body_json_field(&body, "/tags/0", "a"));
+    assert!(!match_body_json_field(&body, "/missing", "push"));
+  }
+
+  #[test]
+  fn doesnt_match_truncated_or_invalid_bodies() {
+    assert!(!match_body_json_field(
+      &buffered(r#"{"event":"push"}"#, false),
+      "/event",
+      "push"
+    ));
+    assert!(!match_body_json_field(
+      &buffered(r#"{"event":"push""#, true),
+      "/event",
+      "push"
+    ));
+  }
+}
@@ -0,0 +1,183 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+use hyper::body::{Body, Frame, SizeHint};
+
+/// The phase of the request, in which the client disconnected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientAbortPhase {
+  /// The client disconnected before the response was sent
+  Request,
+  /// The client disconnected while the response body was sent
+  Response,
+}
+
+impl ClientAbortPhase {
+  /// Obtains the value of the metric attribute for the phase
+  pub fn as_metric_value(&self) -> &'static str {
+    match self {
+      Self::Request => "request",
+      Self::Response => "response",
+    }
+  }
+}
+
+/// A guard recording a client-aborted request in the metrics, if it's dropped while still armed
+pub struct ClientAbortGuard {
+  metrics_sender: Option<MetricsMultiSender>,
+  phase: ClientAbortPhase,
+  armed: bool,
+}
+
+impl ClientAbortGuard {
+  /// Creates an armed guard
+  pub fn new(metrics_sender: Option<MetricsMultiSender>, phase: ClientAbortPhase) -> Self {
+    Self {
+      metrics_sender,
+      phase,
+      armed: true,
+    }
+  }
+
+  /// Disarms the guard, so that it doesn't record the request as client-aborted
+  pub fn disarm(&mut self) {
+    self.armed = false;
+  }
+}
+
+impl Drop for ClientAbortGuard {
+  fn drop(&mut self) {
+    if !self.armed {
+      return;
+    }
+    if let Some(metrics_sender) = &self.metrics_sender {
+      metrics_sender.try_send(Metric::new(
+        "ferron.http.server.client_aborted_requests",
+        vec![(
+          "ferron.http.client_abort.phase",
+          MetricAttributeValue::String(self.phase.as_metric_value().to_string()),
+        )],
+        MetricType::Counter,
+        MetricValue::U64(1),
+        Some("{request}"),
+        Some("Number of requests aborted, because the client disconnected."),
+      ));
+    }
+  }
+}
+
+/// A response body recording a client-aborted request, if it's dropped before it's fully sent
+pub struct ClientAbortBody<B> {
+  inner: Pin<Box<B>>,
+  guard: ClientAbortGuard,
+}
+
+impl<B> ClientAbortBody<B>
+where
+  B: Body,
+{
+  /// Wraps the response body
+  pub fn new(inner: B, metrics_sender: Option<MetricsMultiSender>) -> Self {
+    let mut guard = ClientAbortGuard::new(metrics_sender, ClientAbortPhase::Response);
+    if inner.is_end_stream() {
+      guard.disarm();
+    }
+    Self {
+      inner: Box::pin(inner),
+      guard,
+    }
+  }
+}
+
+impl<B> Body for ClientAbortBody<B>
+where
+  B: Body,
+{
+  type Data = B::Data;
+  type Error = B::Error;
+
+  #[inline]
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    let frame = self.inner.as_mut().poll_frame(cx);
+    match &frame {
+      // Errors while reading the response body aren't caused by the client
+      Poll::Ready(None) | Poll::Ready(Some(Err(_))) => self.guard.disarm(),
+      Poll::Ready(Some(Ok(_))) if self.inner.is_end_stream() => self.guard.disarm(),
+      _ => (),
+    }
+    frame
+  }
+
+  #[inline]
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use bytes::Bytes;
+  use futures_util::FutureExt;
+  use http_body_util::{BodyExt, Empty, StreamBody};
+
+  fn metrics_sender() -> (MetricsMultiSender, async_channel::Receiver<Metric>) {
+    let (sender, receiver) = async_channel::unbounded();
+    (MetricsMultiSender::new(sender), receiver)
+  }
+
+  fn stream_body(
+    chunks: &[&'static str],
+  ) -> StreamBody<impl futures_util::Stream<Item = Result<Frame<Bytes>, std::io::Error>>> {
+    StreamBody::new(futures_util::stream::iter(
+      chunks
+        .iter()
+        .map(|chunk| Ok(Frame::data(Bytes::from_static(chunk.as_bytes()))))
+        .collect::<Vec<_>>(),
+    ))
+  }
+
+  #[test]
+  fn records_dropped_guards() {
+    let (sender, receiver) = metrics_sender();
+    drop(ClientAbortGuard::new(Some(sender.clone()), ClientAbortPhase::Request));
+    let metric = receiver.try_recv().unwrap();
+    assert_eq!(metric.name, "ferron.http.server.client_aborted_requests");
+
+    let mut guard = ClientAbortGuard::new(Some(sender), ClientAbortPhase::Request);
+    guard.disarm();
+    drop(guard);
+    assert!(receiver.try_recv().is_err());
+  }
+
+  #[test]
+  fn doesnt_record_fully_sent_bodies() {
+    let (sender, receiver) = metrics_sender();
+    let body = ClientAbortBody::new(stream_body(&["Hello, ", "world!"]), Some(sender.clone()));
+    assert_eq!(
+      body.collect().now_or_never().unwrap().unwrap().to_bytes(),
+      Bytes::from_static(b"Hello, world!")
+    );
+    drop(ClientAbortBody::new(Empty::<Bytes>::new(), Some(sender)));
+    assert!(receiver.try_recv().is_err());
+  }
+
+  #[test]
+  fn records_partially_sent_bodies() {
+    let (sender, receiver) = metrics_sender();
+    let mut body = ClientAbortBody::new(stream_body(&["Hello, ", "world!"]), Some(sender));
+    body.frame().now_or_never().unwrap().unwrap().unwrap();
+    drop(body);
+    assert!(receiver.try_recv().is_ok());
+  }
+}
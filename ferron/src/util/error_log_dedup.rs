@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant};
+
+use async_channel::{Receiver, Sender};
+use ferron_common::logging::LogMessage;
+
+/// Deduplicates and rate-limits error log messages.
+/// Identical consecutive messages are summarized as "last message repeated N times", and at most a specified number
+/// of error log messages are logged in a single time window.
+pub struct ErrorLogDeduplicator {
+  window: Duration,
+  max_messages: Option<u64>,
+  window_start: Instant,
+  window_messages: u64,
+  last_message: Option<String>,
+  repeated: u64,
+  rate_limited: u64,
+}
+
+impl ErrorLogDeduplicator {
+  /// Creates a new error log deduplicator with the specified window and the optional maximum number of messages
+  /// logged in a window
+  pub fn new(window: Duration, max_messages: Option<u64>, now: Instant) -> Self {
+    Self {
+      window,
+      max_messages,
+      window_start: now,
+      window_messages: 0,
+      last_message: None,
+      repeated: 0,
+      rate_limited: 0,
+    }
+  }
+
+  /// Obtains the summaries of the suppressed messages, and resets the counters
+  fn take_summaries(&mut self) -> Vec<String> {
+    let mut summaries = Vec::new();
+    if self.repeated > 0 {
+      summaries.push(format!("Last message repeated {} times", self.repeated));
+      self.repeated = 0;
+    }
+    if self.rate_limited > 0 {
+      summaries.push(format!(
+        "{} error log messages were suppressed due to rate limiting",
+        self.rate_limited
+      ));
+      self.rate_limited = 0;
+    }
+    summaries
+  }
+
+  /// Starts a new window, if the current one has ended.
+  /// Returns the summaries of the messages suppressed in the ended window.
+  pub fn flush(&mut self, now: Instant) -> Vec<String> {
+    if now.saturating_duration_since(self.window_start) < self.window {
+      return Vec::new();
+    }
+    self.window_start = now;
+    self.window_messages = 0;
+    self.take_summaries()
+  }
+
+  /// Processes the error log message.
+  /// Returns the summaries to log before the message, and whether the message itself should be logged.
+  pub fn process(&mut self, message: &str, now: Instant) -> (Vec<String>, bool) {
+    let mut summaries = self.flush(now);
+    if self.last_message.as_deref() == Some(message) {
+      self.repeated += 1;
+      return (summaries, false);
+    }
+    if self.repeated > 0 {
+      summaries.push(format!("Last message repeated {} times", self.repeated));
+      self.repeated = 0;
+    }
+    if self
+      .max_messages
+      .is_some_and(|max_messages| self.window_messages >= max_messages)
+    {
+      self.rate_limited += 1;
+      return (summaries, false);
+    }
+    self.window_messages += 1;
+    self.last_message = Some(message.to_string());
+    (summaries, true)
+  }
+}
+
+/// Forwards log messages to the log channels, deduplicating and rate-limiting error log messages.
+/// Access log messages are forwarded as is. The task ends after all senders of the log messages are dropped.
+pub async fn forward_deduplicated_logs(
+  logging_rx: Receiver<LogMessage>,
+  log_channels: Vec<Sender<LogMessage>>,
+  window: Duration,
+  max_messages: Option<u64>,
+) {
+  let mut deduplicator = ErrorLogDeduplicator::new(window, max_messages, Instant::now());
+  let mut interval = tokio::time::interval(window);
+  loop {
+    let (summaries, message) = tokio::select! {
+      message = logging_rx.recv() => match message {
+        Ok(message) => {
+          let (text, is_error) = message.peek_message();
+          if !is_error {
+            (Vec::new(), Some(message))
+          } else {
+            let (summaries, log_message) = deduplicator.process(text, Instant::now());
+            (summaries, log_message.then_some(message))
+          }
+        }
+        Err(_) => (deduplicator.flush(Instant::now() + window), None),
+      },
+      _ = interval.tick() => (deduplicator.flush(Instant::now()), None),
+    };
+    for summary in summaries {
+      for log_channel in &log_channels {
+        log_channel
+          .send(LogMessage::new(summary.clone(), true))
+          .await
+          .unwrap_or_default();
+      }
+    }
+    if let Some(message) = message {
+      for log_channel in &log_channels {
+        log_channel.send(message.clone()).await.unwrap_or_default();
+      }
+    }
+    if logging_rx.is_closed() && logging_rx.is_empty() {
+      return;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn summarizes_repeated_messages() {
+    let start = Instant::now();
+    let mut deduplicator = ErrorLogDeduplicator::new(Duration::from_secs(10), None, start);
+    assert_eq!(deduplicator.process("Backend down", start), (vec![], true));
+    assert_eq!(deduplicator.process("Backend down", start), (vec![], false));
+    assert_eq!(deduplicator.process("Backend down", start), (vec![], false));
+    assert_eq!(
+      deduplicator.process("Other error", start),
+      (vec!["Last message repeated 2 times".to_string()], true)
+    );
+  }
+
+  #[test]
+  fn summarizes_repeated_messages_after_window_ends() {
+    let start = Instant::now();
+    let mut deduplicator = ErrorLogDeduplicator::new(Duration::from_secs(10), None, start);
+    deduplicator.process("Backend down", start);
+    deduplicator.process("Backend down", start + Duration::from_secs(1));
+    assert!(deduplicator.flush(start + Duration::from_secs(5)).is_empty());
+    assert_eq!(
+      deduplicator.flush(start + Duration::from_secs(10)),
+      vec!["Last message repeated 1 times".to_string()]
+    );
+    // The message is still deduplicated in the next window
+    assert_eq!(
+      deduplicator.process("Backend down", start + Duration::from_secs(11)),
+      (vec![], false)
+    );
+  }
+
+  #[test]
+  fn rate_limits_messages() {
+    let start = Instant::now();
+    let mut deduplicator = ErrorLogDeduplicator::new(Duration::from_secs(10), Some(2), start);
+    assert_eq!(deduplicator.process("Error 1", start), (vec![], true));
+    assert_eq!(deduplicator.process("Error 2", start), (vec![], true));
+    assert_eq!(deduplicator.process("Error 3", start), (vec![], false));
+    assert_eq!(deduplicator.process("Error 4", start), (vec![], false));
+    assert_eq!(
+      deduplicator.process("Error 5", start + Duration::from_secs(10)),
+      (
+        vec!["2 error log messages were suppressed due to rate limiting".to_string()],
+        true
+      )
+    );
+  }
+}
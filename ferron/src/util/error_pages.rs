@@ -48,7 +48,7 @@ pub fn generate_default_error_page(status_code: hyper::StatusCode, server_admini
     426 => "The client must upgrade its protocol to proceed.",
     428 => "A precondition is required for this request, but it wasn't included.",
     429 => "Too many requests were sent in a short period.",
-    431 => "The request headers are too large.",
+    431 => "The request headers are too large. Clearing the cookies for this website might resolve the error.",
     451 => "Access to this resource is restricted due to legal reasons.",
     497 => "A non-TLS request was sent to an HTTPS server.",
     500 => &error_500,
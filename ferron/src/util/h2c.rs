@@ -0,0 +1,458 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use hyper::header::{self, HeaderMap};
+use hyper::rt::{Read, ReadBuf, ReadBufCursor, Write};
+use hyper::{Method, Version};
+
+/// The HTTP/2 client connection preface (RFC 9113, section 3.4)
+pub const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The length of the HTTP/2 frame header
+const FRAME_HEADER_LENGTH: usize = 9;
+/// The default maximum HTTP/2 frame size, which the server accepts before its settings are acknowledged
+const DEFAULT_MAX_FRAME_SIZE: usize = 16384;
+/// The HTTP/2 HEADERS frame type
+const FRAME_TYPE_HEADERS: u8 = 0x1;
+/// The HTTP/2 SETTINGS frame type
+const FRAME_TYPE_SETTINGS: u8 = 0x4;
+/// The END_STREAM and END_HEADERS flags of the HTTP/2 HEADERS frame
+const FRAME_FLAGS_END_STREAM_END_HEADERS: u8 = 0x1 | 0x4;
+
+/// The connection-specific headers, which aren't allowed in HTTP/2 (RFC 9113, section 8.2.2)
+const CONNECTION_SPECIFIC_HEADERS: [&str; 7] = [
+  "connection",
+  "keep-alive",
+  "proxy-connection",
+  "transfer-encoding",
+  "upgrade",
+  "http2-settings",
+  "host",
+];
+
+/// Checks whether the data read from the connection is the HTTP/2 connection preface.
+/// Returns `None`, if more data is needed to decide.
+pub fn match_http2_preface(data: &[u8]) -> Option<bool> {
+  let length = data.len().min(HTTP2_PREFACE.len());
+  if data[..length] != HTTP2_PREFACE[..length] {
+    Some(false)
+  } else if length == HTTP2_PREFACE.len() {
+    Some(true)
+  } else {
+    None
+  }
+}
+
+/// Reads the beginning of the connection to detect the HTTP/2 connection preface (HTTP/2 with prior knowledge).
+/// Returns whether the preface was detected, and the data read from the connection, which has to be replayed.
+pub async fn read_http2_preface<T: Read + Unpin>(io: &mut T) -> io::Result<(bool, Bytes)> {
+  let mut data = BytesMut::with_capacity(HTTP2_PREFACE.len());
+  loop {
+    let mut buffer = [0u8; HTTP2_PREFACE.len()];
+    let length = std::future::poll_fn(|cx| {
+      let mut read_buf = ReadBuf::new(&mut buffer[..HTTP2_PREFACE.len() - data.len()]);
+      match Pin::new(&mut *io).poll_read(cx, read_buf.unfilled()) {
+        Poll::Ready(Ok(())) => Poll::Ready(Ok(read_buf.filled().len())),
+        Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+        Poll::Pending => Poll::Pending,
+      }
+    })
+    .await?;
+    data.extend_from_slice(&buffer[..length]);
+    if length == 0 {
+      return Ok((false, data.freeze()));
+    }
+    if let Some(is_preface) = match_http2_preface(&data) {
+      return Ok((is_preface, data.freeze()));
+    }
+  }
+}
+
+/// Checks whether the request is an HTTP/1.1 request to upgrade to HTTP/2 over cleartext (RFC 7540, section 3.2).
+/// Requests with bodies aren't upgraded, since the request body would have to be sent over HTTP/1.1.
+pub fn is_h2c_upgrade_request(request: &hyper::http::request::Parts, has_body: bool) -> bool {
+  let has_token = |name: header::HeaderName, token: &str| {
+    request.headers.get_all(name).iter().any(|value| {
+      value
+        .to_str()
+        .is_ok_and(|value| value.split(',').any(|v| v.trim().eq_ignore_ascii_case(token)))
+    })
+  };
+  request.version == Version::HTTP_11
+    && request.method != Method::CONNECT
+    && !has_body
+    && request.headers.get_all("http2-settings").iter().count() == 1
+    && has_token(header::UPGRADE, "h2c")
+    && has_token(header::CONNECTION, "upgrade")
+    && has_token(header::CONNECTION, "http2-settings")
+}
+
+/// Encodes an HPACK integer with the specified prefix (RFC 7541, section 5.1)
+fn encode_hpack_integer(buffer: &mut BytesMut, first_byte: u8, prefix_bits: u8, value: usize) {
+  let max_prefix_value = (1usize << prefix_bits) - 1;
+  if value < max_prefix_value {
+    buffer.put_u8(first_byte | value as u8);
+  } else {
+    buffer.put_u8(first_byte | max_prefix_value as u8);
+    let mut value = value - max_prefix_value;
+    while value >= 128 {
+      buffer.put_u8((value % 128) as u8 | 0x80);
+      value /= 128;
+    }
+    buffer.put_u8(value as u8);
+  }
+}
+
+/// Encodes a header field as a literal header field without indexing (RFC 7541, section 6.2.2),
+/// so that the HPACK dynamic table of the client isn't affected.
+fn encode_hpack_literal(buffer: &mut BytesMut, name: &[u8], value: &[u8]) {
+  buffer.put_u8(0x00);
+  encode_hpack_integer(buffer, 0x00, 7, name.len());
+  buffer.put_slice(name);
+  encode_hpack_integer(buffer, 0x00, 7, value.len());
+  buffer.put_slice(value);
+}
+
+/// Encodes the HTTP/1.1 request upgraded to HTTP/2 over cleartext as an HTTP/2 HEADERS frame on the stream 1,
+/// which is the stream the response to the upgraded request is sent on (RFC 7540, section 3.2).
+/// Returns `None`, if the request headers don't fit in a single frame.
+pub fn encode_h2c_upgrade_headers_frame(request: &hyper::http::request::Parts) -> Option<Bytes> {
+  let mut header_block = BytesMut::new();
+  let path = request.uri.path_and_query().map_or("/", |path| path.as_str());
+  let authority = request
+    .headers
+    .get(header::HOST)
+    .map(|host| host.as_bytes())
+    .or_else(|| request.uri.authority().map(|authority| authority.as_str().as_bytes()));
+  encode_hpack_literal(&mut header_block, b":method", request.method.as_str().as_bytes());
+  encode_hpack_literal(&mut header_block, b":scheme", b"http");
+  if let Some(authority) = authority {
+    encode_hpack_literal(&mut header_block, b":authority", authority);
+  }
+  encode_hpack_literal(&mut header_block, b":path", path.as_bytes());
+  let connection_headers = connection_header_names(&request.headers);
+  for (name, value) in &request.headers {
+    let name = name.as_str();
+    if CONNECTION_SPECIFIC_HEADERS.contains(&name)
+      || connection_headers.iter().any(|header| header == name)
+      || (name == "te" && !value.as_bytes().eq_ignore_ascii_case(b"trailers"))
+    {
+      continue;
+    }
+    encode_hpack_literal(&mut header_block, name.as_bytes(), value.as_bytes());
+  }
+
+  if header_block.len() > DEFAULT_MAX_FRAME_SIZE {
+    return None;
+  }
+  let mut frame = BytesMut::with_capacity(FRAME_HEADER_LENGTH + header_block.len());
+  frame.put_uint(header_block.len() as u64, 3);
+  frame.put_u8(FRAME_TYPE_HEADERS);
+  frame.put_u8(FRAME_FLAGS_END_STREAM_END_HEADERS);
+  frame.put_u32(1);
+  frame.put_slice(&header_block);
+  Some(frame.freeze())
+}
+
+/// Obtains the names of the headers listed in the `Connection` header
+fn connection_header_names(headers: &HeaderMap) -> Vec<String> {
+  headers
+    .get_all(header::CONNECTION)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .map(|name| name.trim().to_lowercase())
+    .filter(|name| !name.is_empty())
+    .collect()
+}
+
+/// A connection, on which the data already read from it is replayed before reading from the connection
+pub struct Rewind<T> {
+  prefix: Bytes,
+  inner: T,
+}
+
+impl<T> Rewind<T> {
+  /// Creates a connection replaying the data
+  pub fn new(inner: T, prefix: Bytes) -> Self {
+    Self { prefix, inner }
+  }
+}
+
+impl<T: Read + Unpin> Read for Rewind<T> {
+  fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, mut buf: ReadBufCursor<'_>) -> Poll<io::Result<()>> {
+    if !self.prefix.is_empty() {
+      let length = self.prefix.len().min(buf.remaining());
+      let data = self.prefix.split_to(length);
+      buf.put_slice(&data);
+      return Poll::Ready(Ok(()));
+    }
+    Pin::new(&mut self.inner).poll_read(cx, buf)
+  }
+}
+
+impl<T: Write + Unpin> Write for Rewind<T> {
+  fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.inner).poll_write(cx, buf)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.inner).poll_flush(cx)
+  }
+
+  fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.inner).poll_shutdown(cx)
+  }
+
+  fn is_write_vectored(&self) -> bool {
+    self.inner.is_write_vectored()
+  }
+
+  fn poll_write_vectored(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    bufs: &[io::IoSlice<'_>],
+  ) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+  }
+}
+
+/// The position of the upgraded request in the data read from the client
+#[derive(Debug, PartialEq, Eq)]
+enum H2cInjectionState {
+  /// The client connection preface and the frame header of the first SETTINGS frame are being read
+  ReadingPreface(usize, [u8; HTTP2_PREFACE.len() + FRAME_HEADER_LENGTH]),
+  /// The payload of the first SETTINGS frame is being read
+  ReadingSettings(usize),
+  /// The HEADERS frame of the upgraded request is being injected
+  Injecting,
+  /// The rest of the data is passed through
+  Done,
+}
+
+impl H2cInjectionState {
+  /// Obtains the maximum number of bytes to read from the client before the state changes
+  fn read_limit(&self) -> Option<usize> {
+    match self {
+      Self::ReadingPreface(read, _) => Some(HTTP2_PREFACE.len() + FRAME_HEADER_LENGTH - read),
+      Self::ReadingSettings(remaining) => Some(*remaining),
+      Self::Injecting | Self::Done => None,
+    }
+  }
+
+  /// Advances the state after the data was read from the client
+  fn advance(&mut self, data: &[u8]) {
+    match self {
+      Self::ReadingPreface(read, header) => {
+        header[*read..*read + data.len()].copy_from_slice(data);
+        *read += data.len();
+        if *read == header.len() {
+          let frame_header = &header[HTTP2_PREFACE.len()..];
+          *self = if &header[..HTTP2_PREFACE.len()] != HTTP2_PREFACE || frame_header[3] != FRAME_TYPE_SETTINGS {
+            // Not a valid client connection preface, the HTTP/2 implementation rejects the connection
+            Self::Done
+          } else {
+            let length = u32::from_be_bytes([0, frame_header[0], frame_header[1], frame_header[2]]) as usize;
+            if length == 0 {
+              Self::Injecting
+            } else {
+              Self::ReadingSettings(length)
+            }
+          };
+        }
+      }
+      Self::ReadingSettings(remaining) => {
+        *remaining -= data.len();
+        if *remaining == 0 {
+          *self = Self::Injecting;
+        }
+      }
+      Self::Injecting | Self::Done => (),
+    }
+  }
+}
+
+/// A connection upgraded to HTTP/2 over cleartext, on which the upgraded HTTP/1.1 request is injected
+/// as a HEADERS frame on the stream 1 right after the client connection preface.
+pub struct H2cUpgradedIo<T> {
+  inner: T,
+  headers_frame: Bytes,
+  state: H2cInjectionState,
+}
+
+impl<T> H2cUpgradedIo<T> {
+  /// Creates an upgraded connection, on which the HEADERS frame is injected
+  pub fn new(inner: T, headers_frame: Bytes) -> Self {
+    Self {
+      inner,
+      headers_frame,
+      state: H2cInjectionState::ReadingPreface(0, [0u8; HTTP2_PREFACE.len() + FRAME_HEADER_LENGTH]),
+    }
+  }
+}
+
+impl<T: Read + Unpin> Read for H2cUpgradedIo<T> {
+  fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, mut buf: ReadBufCursor<'_>) -> Poll<io::Result<()>> {
+    if self.state == H2cInjectionState::Injecting {
+      let length = self.headers_frame.len().min(buf.remaining());
+      let data = self.headers_frame.split_to(length);
+      buf.put_slice(&data);
+      if self.headers_frame.is_empty() {
+        self.state = H2cInjectionState::Done;
+      }
+      return Poll::Ready(Ok(()));
+    }
+    let Some(read_limit) = self.state.read_limit() else {
+      return Pin::new(&mut self.inner).poll_read(cx, buf);
+    };
+
+    // The data is read up to the end of the first SETTINGS frame, so that the HEADERS frame is injected right after it
+    let mut buffer = vec![0u8; read_limit.min(buf.remaining())];
+    let mut read_buf = ReadBuf::new(&mut buffer);
+    match Pin::new(&mut self.inner).poll_read(cx, read_buf.unfilled()) {
+      Poll::Ready(Ok(())) => {
+        let data = read_buf.filled();
+        buf.put_slice(data);
+        self.state.advance(data);
+        Poll::Ready(Ok(()))
+      }
+      other => other,
+    }
+  }
+}
+
+impl<T: Write + Unpin> Write for H2cUpgradedIo<T> {
+  fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.inner).poll_write(cx, buf)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.inner).poll_flush(cx)
+  }
+
+  fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.inner).poll_shutdown(cx)
+  }
+
+  fn is_write_vectored(&self) -> bool {
+    self.inner.is_write_vectored()
+  }
+
+  fn poll_write_vectored(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    bufs: &[io::IoSlice<'_>],
+  ) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn request_parts(request: hyper::Request<()>) -> hyper::http::request::Parts {
+    request.into_parts().0
+  }
+
+  #[test]
+  fn matches_http2_preface() {
+    assert_eq!(match_http2_preface(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"), Some(true));
+    assert_eq!(match_http2_preface(b"PRI * HTTP/2.0\r\n"), None);
+    assert_eq!(match_http2_preface(b"GET / HTTP/1.1\r\n"), Some(false));
+    assert_eq!(match_http2_preface(b"P"), None);
+    assert_eq!(match_http2_preface(b"PO"), Some(false));
+  }
+
+  #[test]
+  fn detects_h2c_upgrade_requests() {
+    let upgrade_request = || {
+      hyper::Request::builder()
+        .uri("/")
+        .header("Host", "example.com")
+        .header("Connection", "Upgrade, HTTP2-Settings")
+        .header("Upgrade", "h2c")
+        .header("HTTP2-Settings", "AAMAAABkAARAAAAAAAIAAAAA")
+    };
+    assert!(is_h2c_upgrade_request(
+      &request_parts(upgrade_request().body(()).unwrap()),
+      false
+    ));
+    assert!(!is_h2c_upgrade_request(
+      &request_parts(upgrade_request().body(()).unwrap()),
+      true
+    ));
+    assert!(!is_h2c_upgrade_request(
+      &request_parts(upgrade_request().version(Version::HTTP_10).body(()).unwrap()),
+      false
+    ));
+    let websocket_request = hyper::Request::builder()
+      .uri("/")
+      .header("Connection", "Upgrade")
+      .header("Upgrade", "websocket")
+      .body(())
+      .unwrap();
+    assert!(!is_h2c_upgrade_request(&request_parts(websocket_request), false));
+  }
+
+  #[test]
+  fn encodes_upgraded_request_headers_frame() {
+    let request = hyper::Request::builder()
+      .uri("/a?b")
+      .header("Host", "example.com")
+      .header("Connection", "Upgrade, HTTP2-Settings, X-Hop")
+      .header("Upgrade", "h2c")
+      .header("HTTP2-Settings", "AAMAAABkAARAAAAAAAIAAAAA")
+      .header("X-Hop", "1")
+      .header("Accept", "*/*")
+      .body(())
+      .unwrap();
+    let frame = encode_h2c_upgrade_headers_frame(&request_parts(request)).unwrap();
+
+    let mut expected_block = Vec::new();
+    for (name, value) in [
+      (":method", "GET"),
+      (":scheme", "http"),
+      (":authority", "example.com"),
+      (":path", "/a?b"),
+      ("accept", "*/*"),
+    ] {
+      expected_block.push(0x00);
+      expected_block.push(name.len() as u8);
+      expected_block.extend_from_slice(name.as_bytes());
+      expected_block.push(value.len() as u8);
+      expected_block.extend_from_slice(value.as_bytes());
+    }
+    let mut expected_frame = vec![0, 0, expected_block.len() as u8, 0x1, 0x5, 0, 0, 0, 1];
+    expected_frame.extend_from_slice(&expected_block);
+    assert_eq!(&frame[..], &expected_frame[..]);
+  }
+
+  #[test]
+  fn encodes_long_hpack_integers() {
+    let mut buffer = BytesMut::new();
+    encode_hpack_integer(&mut buffer, 0x00, 5, 1337);
+    assert_eq!(&buffer[..], &[0x1f, 0x9a, 0x0a]);
+  }
+
+  #[test]
+  fn injects_headers_frame_after_first_settings_frame() {
+    let mut state = H2cInjectionState::ReadingPreface(0, [0u8; HTTP2_PREFACE.len() + FRAME_HEADER_LENGTH]);
+    assert_eq!(state.read_limit(), Some(33));
+    state.advance(HTTP2_PREFACE);
+    assert_eq!(state.read_limit(), Some(9));
+    state.advance(&[0, 0, 6, FRAME_TYPE_SETTINGS, 0, 0, 0, 0, 0]);
+    assert_eq!(state, H2cInjectionState::ReadingSettings(6));
+    state.advance(&[0, 3, 0, 0]);
+    assert_eq!(state.read_limit(), Some(2));
+    state.advance(&[0, 100]);
+    assert_eq!(state, H2cInjectionState::Injecting);
+
+    let mut state = H2cInjectionState::ReadingPreface(0, [0u8; HTTP2_PREFACE.len() + FRAME_HEADER_LENGTH]);
+    state.advance(HTTP2_PREFACE);
+    state.advance(&[0, 0, 4, 0x8, 0, 0, 0, 0, 0]);
+    assert_eq!(state, H2cInjectionState::Done);
+  }
+}
@@ -0,0 +1,144 @@
+use crate::config::ServerConfiguration;
+use crate::get_value;
+use hyper::HeaderMap;
+
+/// The default maximum total size of the request header names and values (in bytes)
+const DEFAULT_MAX_REQUEST_HEADER_SIZE: usize = 65536;
+/// The default maximum number of request headers
+const DEFAULT_MAX_HEADER_COUNT: usize = 100;
+/// The minimum HTTP/1.x read buffer size accepted by Hyper
+const MINIMUM_HTTP1_BUFFER_SIZE: usize = 8192;
+/// The per-field overhead counted in the HTTP/2 and HTTP/3 header list sizes
+const HEADER_FIELD_OVERHEAD: usize = 32;
+
+/// The exceeded request header limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestHeaderLimit {
+  /// The total size of the request headers
+  Size,
+  /// The number of the request headers
+  Count,
+}
+
+impl RequestHeaderLimit {
+  /// Obtains the value of the metric attribute for the exceeded limit
+  pub fn as_metric_value(&self) -> &'static str {
+    match self {
+      Self::Size => "size",
+      Self::Count => "count",
+    }
+  }
+}
+
+/// The request header limits. The limits enforced by the HTTP implementations are twice as large as the configured ones,
+/// so that requests exceeding the configured limits reach the request handler, which responds with a
+/// "431 Request Header Fields Too Large" response with a response body, instead of closing the connection or resetting the stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestHeaderLimits {
+  /// The maximum total size of the request header names and values (in bytes)
+  pub max_size: usize,
+  /// The maximum number of request headers
+  pub max_count: usize,
+}
+
+impl Default for RequestHeaderLimits {
+  fn default() -> Self {
+    Self {
+      max_size: DEFAULT_MAX_REQUEST_HEADER_SIZE,
+      max_count: DEFAULT_MAX_HEADER_COUNT,
+    }
+  }
+}
+
+impl RequestHeaderLimits {
+  /// Obtains the request header limits from the global configuration
+  pub fn from_configuration(global_configuration: Option<&ServerConfiguration>) -> Self {
+    Self {
+      max_size: global_configuration
+        .and_then(|c| get_value!("max_request_header_size", c))
+        .and_then(|v| v.as_size())
+        .map_or(DEFAULT_MAX_REQUEST_HEADER_SIZE, |v| v as usize),
+      max_count: global_configuration
+        .and_then(|c| get_value!("max_header_count", c))
+        .and_then(|v| v.as_i128())
+        .map_or(DEFAULT_MAX_HEADER_COUNT, |v| v as usize),
+    }
+  }
+
+  /// Obtains the maximum HTTP/1.x read buffer size, which limits the size of the request line and the request headers
+  pub fn http1_max_buffer_size(&self) -> usize {
+    self.max_size.saturating_mul(2).max(MINIMUM_HTTP1_BUFFER_SIZE)
+  }
+
+  /// Obtains the maximum number of HTTP/1.x request headers
+  pub fn http1_max_headers(&self) -> usize {
+    self.max_count.saturating_mul(2)
+  }
+
+  /// Obtains the maximum HTTP/2 header list size (advertised in the HTTP/2 SETTINGS frame)
+  /// and the maximum HTTP/3 field section size
+  pub fn max_header_list_size(&self) -> u32 {
+    self
+      .max_size
+      .saturating_add(self.max_count.saturating_mul(HEADER_FIELD_OVERHEAD))
+      .saturating_mul(2)
+      .try_into()
+      .unwrap_or(u32::MAX)
+  }
+
+  /// Checks the request headers against the limits, and returns the exceeded limit, if any
+  pub fn check(&self, headers: &HeaderMap) -> Option<RequestHeaderLimit> {
+    if headers.len() > self.max_count {
+      return Some(RequestHeaderLimit::Count);
+    }
+    let size = headers
+      .iter()
+      .map(|(name, value)| name.as_str().len() + value.len())
+      .sum::<usize>();
+    if size > self.max_size {
+      return Some(RequestHeaderLimit::Size);
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use hyper::header::{HeaderValue, COOKIE, HOST};
+
+  #[test]
+  fn test_check_request_header_limits() {
+    let limits = RequestHeaderLimits {
+      max_size: 32,
+      max_count: 2,
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(HOST, HeaderValue::from_static("example.com"));
+    headers.insert(COOKIE, HeaderValue::from_static("a=1"));
+    assert_eq!(limits.check(&headers), None);
+
+    headers.insert(COOKIE, HeaderValue::from_static("session=0123456789abcdef"));
+    assert_eq!(limits.check(&headers), Some(RequestHeaderLimit::Size));
+
+    headers.insert(COOKIE, HeaderValue::from_static("a=1"));
+    headers.append(COOKIE, HeaderValue::from_static("b=2"));
+    assert_eq!(limits.check(&headers), Some(RequestHeaderLimit::Count));
+  }
+
+  #[test]
+  fn test_transport_limits_exceed_configured_limits() {
+    let limits = RequestHeaderLimits::default();
+    assert_eq!(limits.http1_max_buffer_size(), 131072);
+    assert_eq!(limits.http1_max_headers(), 200);
+    assert_eq!(limits.max_header_list_size(), 137472);
+
+    let small_limits = RequestHeaderLimits {
+      max_size: 1024,
+      max_count: 10,
+    };
+    assert_eq!(small_limits.http1_max_buffer_size(), 8192);
+  }
+}
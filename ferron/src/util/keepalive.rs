@@ -0,0 +1,359 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
+use http_body_util::combinators::BoxBody;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use hyper::Response;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::ServerConfiguration;
+use crate::get_value;
+
+/// The number of open client connections, across all the request handler threads
+static OPEN_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of client connections without in-flight requests, across all the request handler threads
+static IDLE_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Obtains the numbers of open client connections, and of client connections without in-flight requests
+pub fn connection_counts() -> (usize, usize) {
+  (
+    OPEN_CONNECTIONS.load(Ordering::Relaxed),
+    IDLE_CONNECTIONS.load(Ordering::Relaxed),
+  )
+}
+
+/// The keep-alive policy for client connections
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeepAlivePolicy {
+  /// The maximum time, for which a connection without in-flight requests is kept open
+  pub timeout: Option<Duration>,
+  /// The maximum number of requests served over a connection
+  pub max_requests: Option<u64>,
+}
+
+impl KeepAlivePolicy {
+  /// Obtains the keep-alive policy from the global configuration
+  pub fn from_configuration(global_configuration: Option<&ServerConfiguration>) -> Self {
+    Self {
+      timeout: global_configuration
+        .and_then(|c| get_value!("keepalive_timeout", c))
+        .and_then(|v| v.as_duration()),
+      max_requests: global_configuration
+        .and_then(|c| get_value!("keepalive_max_requests", c))
+        .and_then(|v| v.as_i128())
+        .and_then(|v| v.try_into().ok()),
+    }
+  }
+}
+
+/// The reason, for which a client connection is closed by the keep-alive policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeepAliveCloseReason {
+  /// The connection was idle for longer than the keep-alive timeout
+  Idle,
+  /// The connection served the maximum number of requests
+  MaxRequests,
+}
+
+impl KeepAliveCloseReason {
+  /// Obtains the value of the metric attribute for the reason
+  pub fn as_metric_value(&self) -> &'static str {
+    match self {
+      Self::Idle => "idle",
+      Self::MaxRequests => "max_requests",
+    }
+  }
+}
+
+/// A client connection tracker enforcing the keep-alive policy, and recording the open and idle connections in the metrics
+pub struct ConnectionKeepAlive {
+  policy: KeepAlivePolicy,
+  opened_at: Instant,
+  last_active_millis: AtomicU64,
+  in_flight: AtomicUsize,
+  requests: AtomicU64,
+  max_requests_reached: CancellationToken,
+  metrics_sender: Option<MetricsMultiSender>,
+  port: u16,
+}
+
+impl ConnectionKeepAlive {
+  /// Creates a tracker for a newly accepted connection on the listener with the specified port
+  pub fn new(policy: KeepAlivePolicy, metrics_sender: Option<MetricsMultiSender>, port: u16) -> Self {
+    let keepalive = Self {
+      policy,
+      opened_at: Instant::now(),
+      last_active_millis: AtomicU64::new(0),
+      in_flight: AtomicUsize::new(0),
+      requests: AtomicU64::new(0),
+      max_requests_reached: CancellationToken::new(),
+      metrics_sender,
+      port,
+    };
+    keepalive.record_connections("ferron.http.server.open_connections", 1);
+    keepalive.record_connections("ferron.http.server.idle_connections", 1);
+    keepalive
+  }
+
+  /// Records the change in the number of open or idle connections in the connection counts and the metrics
+  fn record_connections(&self, name: &'static str, change: i64) {
+    let connections = if name == "ferron.http.server.open_connections" {
+      &OPEN_CONNECTIONS
+    } else {
+      &IDLE_CONNECTIONS
+    };
+    if change > 0 {
+      connections.fetch_add(change as usize, Ordering::Relaxed);
+    } else {
+      connections.fetch_sub(change.unsigned_abs() as usize, Ordering::Relaxed);
+    }
+    if let Some(metrics_sender) = &self.metrics_sender {
+      metrics_sender.try_send(Metric::new(
+        name,
+        vec![("server.port", MetricAttributeValue::I64(self.port as i64))],
+        MetricType::UpDownCounter,
+        MetricValue::I64(change),
+        Some("{connection}"),
+        Some(if name == "ferron.http.server.open_connections" {
+          "Number of open client connections."
+        } else {
+          "Number of client connections without in-flight requests."
+        }),
+      ));
+    }
+  }
+
+  /// Records a connection closed by the keep-alive policy in the metrics
+  fn record_close(&self, reason: KeepAliveCloseReason) {
+    if let Some(metrics_sender) = &self.metrics_sender {
+      metrics_sender.try_send(Metric::new(
+        "ferron.http.server.keepalive_closed_connections",
+        vec![
+          ("server.port", MetricAttributeValue::I64(self.port as i64)),
+          (
+            "ferron.http.connection.close_reason",
+            MetricAttributeValue::String(reason.as_metric_value().to_string()),
+          ),
+        ],
+        MetricType::Counter,
+        MetricValue::U64(1),
+        Some("{connection}"),
+        Some("Number of client connections closed by the keep-alive policy."),
+      ));
+    }
+  }
+
+  /// Marks the start of a request. The returned guard marks the end of the request when dropped.
+  pub fn request_started(self: &Arc<Self>) -> KeepAliveRequestGuard {
+    if self.in_flight.fetch_add(1, Ordering::AcqRel) == 0 {
+      self.record_connections("ferron.http.server.idle_connections", -1);
+    }
+    let requests = self.requests.fetch_add(1, Ordering::AcqRel) + 1;
+    if self
+      .policy
+      .max_requests
+      .is_some_and(|max_requests| requests >= max_requests)
+    {
+      self.max_requests_reached.cancel();
+    }
+    KeepAliveRequestGuard {
+      keepalive: self.clone(),
+    }
+  }
+
+  /// Tracks the request, until the response body is sent
+  pub fn track_request<F, E>(
+    self: &Arc<Self>,
+    request_future: F,
+  ) -> impl Future<Output = Result<Response<BoxBody<Bytes, std::io::Error>>, E>>
+  where
+    F: Future<Output = Result<Response<BoxBody<Bytes, std::io::Error>>, E>>,
+  {
+    let guard = self.request_started();
+    async move {
+      let (response_parts, response_body) = request_future.await?.into_parts();
+      Ok(Response::from_parts(
+        response_parts,
+        KeepAliveBody {
+          inner: response_body,
+          _guard: guard,
+        }
+        .boxed(),
+      ))
+    }
+  }
+
+  /// Obtains the time, for which the connection has no in-flight requests
+  fn idle_for(&self) -> Option<Duration> {
+    if self.in_flight.load(Ordering::Acquire) > 0 {
+      return None;
+    }
+    let last_active = Duration::from_millis(self.last_active_millis.load(Ordering::Acquire));
+    Some(self.opened_at.elapsed().saturating_sub(last_active))
+  }
+
+  /// Waits until the connection should be closed by the keep-alive policy
+  pub async fn closed(&self) {
+    let reason = match self.policy.timeout {
+      Some(timeout) => loop {
+        let wait = match self.idle_for() {
+          Some(idle_for) if idle_for >= timeout => break KeepAliveCloseReason::Idle,
+          Some(idle_for) => timeout - idle_for,
+          None => timeout,
+        };
+        crate::runtime::select! {
+          biased;
+
+          _ = self.max_requests_reached.cancelled() => {
+            break KeepAliveCloseReason::MaxRequests;
+          }
+          _ = crate::runtime::sleep(wait) => {}
+        }
+      },
+      None => {
+        self.max_requests_reached.cancelled().await;
+        KeepAliveCloseReason::MaxRequests
+      }
+    };
+    self.record_close(reason);
+  }
+}
+
+impl Drop for ConnectionKeepAlive {
+  fn drop(&mut self) {
+    self.record_connections("ferron.http.server.open_connections", -1);
+    if *self.in_flight.get_mut() == 0 {
+      self.record_connections("ferron.http.server.idle_connections", -1);
+    }
+  }
+}
+
+/// A guard marking the end of a request on the client connection, when dropped
+pub struct KeepAliveRequestGuard {
+  keepalive: Arc<ConnectionKeepAlive>,
+}
+
+impl Drop for KeepAliveRequestGuard {
+  fn drop(&mut self) {
+    let keepalive = &self.keepalive;
+    keepalive.last_active_millis.store(
+      keepalive.opened_at.elapsed().as_millis().try_into().unwrap_or(u64::MAX),
+      Ordering::Release,
+    );
+    if keepalive.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+      keepalive.record_connections("ferron.http.server.idle_connections", 1);
+    }
+  }
+}
+
+/// A response body holding the request guard, until the response body is sent or dropped
+struct KeepAliveBody {
+  inner: BoxBody<Bytes, std::io::Error>,
+  _guard: KeepAliveRequestGuard,
+}
+
+impl Body for KeepAliveBody {
+  type Data = Bytes;
+  type Error = std::io::Error;
+
+  #[inline]
+  fn poll_frame(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+    Pin::new(&mut self.inner).poll_frame(cx)
+  }
+
+  #[inline]
+  fn is_end_stream(&self) -> bool {
+    self.inner.is_end_stream()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> SizeHint {
+    self.inner.size_hint()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use futures_util::FutureExt;
+
+  fn keepalive(policy: KeepAlivePolicy) -> (Arc<ConnectionKeepAlive>, async_channel::Receiver<Metric>) {
+    let (sender, receiver) = async_channel::unbounded();
+    (
+      Arc::new(ConnectionKeepAlive::new(
+        policy,
+        Some(MetricsMultiSender::new(sender)),
+        80,
+      )),
+      receiver,
+    )
+  }
+
+  fn connection_changes(receiver: &async_channel::Receiver<Metric>, name: &str) -> i64 {
+    let mut total = 0;
+    while let Ok(metric) = receiver.try_recv() {
+      if metric.name == name {
+        if let MetricValue::I64(change) = metric.value {
+          total += change;
+        }
+      }
+    }
+    total
+  }
+
+  #[test]
+  fn tracks_idle_connections() {
+    let (keepalive, receiver) = keepalive(KeepAlivePolicy::default());
+    assert!(keepalive.idle_for().is_some());
+    let guard = keepalive.request_started();
+    assert!(keepalive.idle_for().is_none());
+    assert_eq!(connection_changes(&receiver, "ferron.http.server.idle_connections"), 0);
+    drop(guard);
+    assert!(keepalive.idle_for().is_some());
+    assert_eq!(connection_changes(&receiver, "ferron.http.server.idle_connections"), 1);
+  }
+
+  #[test]
+  fn records_closed_connections() {
+    let (keepalive, receiver) = keepalive(KeepAlivePolicy::default());
+    drop(keepalive.request_started());
+    drop(keepalive);
+    let mut open = 0;
+    let mut idle = 0;
+    while let Ok(metric) = receiver.try_recv() {
+      if let MetricValue::I64(change) = metric.value {
+        match metric.name {
+          "ferron.http.server.open_connections" => open += change,
+          "ferron.http.server.idle_connections" => idle += change,
+          _ => (),
+        }
+      }
+    }
+    assert_eq!(open, 0);
+    assert_eq!(idle, 0);
+  }
+
+  #[test]
+  fn closes_connections_after_max_requests() {
+    let (keepalive, _receiver) = keepalive(KeepAlivePolicy {
+      timeout: None,
+      max_requests: Some(2),
+    });
+    drop(keepalive.request_started());
+    assert!(keepalive.closed().now_or_never().is_none());
+    drop(keepalive.request_started());
+    assert!(keepalive.closed().now_or_never().is_some());
+  }
+}
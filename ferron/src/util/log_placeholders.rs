@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::time::SystemTime;
 
 use ferron_common::config::ServerConfigurationValue;
-use ferron_common::modules::SocketData;
+use ferron_common::logging::AccessLogRecord;
+use ferron_common::modules::{RequestTags, SocketData};
 use serde_json::{Map, Number, Value};
 
 const DEFAULT_ACCESS_LOG_FORMAT: &str =
@@ -50,6 +52,11 @@ fn resolve_log_placeholder(
     "server_port" => socket_data.local_addr.port().to_string(),
     "server_ip_canonical" => socket_data.local_addr.ip().to_canonical().to_string(),
     "auth_user" => auth_user.unwrap_or("-").to_string(),
+    "tags" => request_parts
+      .extensions
+      .get::<RequestTags>()
+      .filter(|request_tags| !request_tags.0.is_empty())
+      .map_or_else(|| "-".to_string(), |request_tags| request_tags.0.join(",")),
     "timestamp" => timestamp_str.to_string(),
     "status_code" => status_code.to_string(),
     "content_length" => content_length.map_or_else(|| "-".to_string(), |len| len.to_string()),
@@ -210,6 +217,40 @@ pub fn generate_access_log_message(
   }
 }
 
+/// Generates a structured access log record
+pub fn generate_access_log_record(
+  request_parts: &hyper::http::request::Parts,
+  socket_data: &SocketData,
+  auth_user: Option<&str>,
+  status_code: u16,
+  content_length: Option<u64>,
+) -> AccessLogRecord {
+  let header_value = |header_name: hyper::header::HeaderName| {
+    request_parts
+      .headers
+      .get(header_name)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.to_string())
+  };
+
+  AccessLogRecord {
+    timestamp: SystemTime::now(),
+    client_ip: socket_data.remote_addr.ip().to_canonical(),
+    auth_user: auth_user.map(|user| user.to_string()),
+    method: request_parts.method.as_str().to_string(),
+    path_and_query: request_parts
+      .uri
+      .path_and_query()
+      .map_or_else(|| request_parts.uri.path().to_string(), |p| p.as_str().to_string()),
+    version: http_version_to_str(request_parts.version).to_string(),
+    status_code,
+    content_length,
+    host: header_value(hyper::header::HOST).or_else(|| request_parts.uri.host().map(|host| host.to_string())),
+    referer: header_value(hyper::header::REFERER),
+    user_agent: header_value(hyper::header::USER_AGENT),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -251,6 +292,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -277,6 +320,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -298,6 +343,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -319,6 +366,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -339,6 +388,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -365,6 +416,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -391,6 +444,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -400,6 +455,26 @@ mod tests {
     assert_eq!(output, expected);
   }
 
+  #[test]
+  fn test_tags_placeholder() {
+    let mut parts = make_parts("/api/users", Method::GET, Version::HTTP_11, None);
+    let socket_data = SocketData {
+      remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
+      local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
+      encrypted: false,
+      tls_sni: None,
+      tls_alpn: None,
+    };
+    let output = replace_log_placeholders("Tags: {tags}", &parts, &socket_data, None, "", 200, None);
+    assert_eq!(output, "Tags: -");
+
+    parts
+      .extensions
+      .insert(RequestTags(vec!["api".to_string(), "bot".to_string()]));
+    let output = replace_log_placeholders("Tags: {tags}", &parts, &socket_data, None, "", 200, None);
+    assert_eq!(output, "Tags: api,bot");
+  }
+
   #[test]
   fn test_generate_access_log_message_plain_text() {
     let parts = make_parts("/test?hello=world", Method::GET, Version::HTTP_11, None);
@@ -409,6 +484,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -451,6 +528,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 443)),
         encrypted: true,
+        tls_sni: None,
+        tls_alpn: None,
       },
       Some("alice"),
       "06/Oct/2025:15:12:51 +0200",
@@ -495,6 +574,8 @@ mod tests {
         remote_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 40000)),
         local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
         encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
       },
       None,
       "06/Oct/2025:15:12:51 +0200",
@@ -509,4 +590,37 @@ mod tests {
     assert_eq!(output["content_length"], Value::Null);
     assert_eq!(output["auth_user"], Value::Null);
   }
+
+  #[test]
+  fn test_generate_access_log_record() {
+    let parts = make_parts(
+      "/index.html?lang=en",
+      Method::GET,
+      Version::HTTP_11,
+      Some(vec![("Host", "example.com"), ("User-Agent", "MyApp/1.0")]),
+    );
+    let record = generate_access_log_record(
+      &parts,
+      &SocketData {
+        remote_addr: "[::ffff:192.0.2.1]:40000".parse().unwrap(),
+        local_addr: std::net::SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, 80)),
+        encrypted: false,
+        tls_sni: None,
+        tls_alpn: None,
+      },
+      Some("admin"),
+      404,
+      Some(128),
+    );
+    assert_eq!(record.client_ip, "192.0.2.1".parse::<std::net::IpAddr>().unwrap());
+    assert_eq!(record.auth_user.as_deref(), Some("admin"));
+    assert_eq!(record.method, "GET");
+    assert_eq!(record.path_and_query, "/index.html?lang=en");
+    assert_eq!(record.version, "HTTP/1.1");
+    assert_eq!(record.status_code, 404);
+    assert_eq!(record.content_length, Some(128));
+    assert_eq!(record.host.as_deref(), Some("example.com"));
+    assert_eq!(record.referer, None);
+    assert_eq!(record.user_agent.as_deref(), Some("MyApp/1.0"));
+  }
 }
@@ -1,17 +1,33 @@
 pub use ferron_common::util::*;
 
+mod body_match;
+mod client_abort;
+mod error_log_dedup;
 mod error_pages;
+#[cfg(not(feature = "runtime-vibeio"))]
+mod h2c;
+mod header_limits;
 mod hostname_radix_tree;
+mod keepalive;
 mod log_placeholders;
 mod multi_cancel;
 mod proxy_protocol;
 mod tls;
+mod tls_client_hello;
 mod url_sanitizer;
 
+pub use body_match::*;
+pub use client_abort::*;
+pub use error_log_dedup::*;
 pub use error_pages::*;
+#[cfg(not(feature = "runtime-vibeio"))]
+pub use h2c::*;
+pub use header_limits::*;
 pub use hostname_radix_tree::*;
+pub use keepalive::*;
 pub use log_placeholders::*;
 pub use multi_cancel::*;
 pub use proxy_protocol::*;
 pub use tls::*;
+pub use tls_client_hello::*;
 pub use url_sanitizer::*;
@@ -1,6 +1,8 @@
-use crate::util::HostnameRadixTree;
+use crate::util::{parse_embedded_scts, HostnameRadixTree};
+use ferron_common::observability::{Metric, MetricAttributeValue, MetricType, MetricValue, MetricsMultiSender};
 use rustls::server::{ClientHello, ResolvesServerCert};
 use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
 use rustls_pki_types::pem::PemObject;
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 use std::sync::Arc;
@@ -14,6 +16,8 @@ pub struct CustomSniResolver {
   fallback_resolver: Option<Arc<dyn ResolvesServerCert>>,
   resolvers: SniResolverLock,
   fallback_sender: Option<(async_channel::Sender<(String, u16)>, u16)>,
+  default_certified_key: Option<Arc<CertifiedKey>>,
+  strict_sni: bool,
 }
 
 impl CustomSniResolver {
@@ -24,6 +28,8 @@ impl CustomSniResolver {
       fallback_resolver: None,
       resolvers: Arc::new(tokio::sync::RwLock::new(HostnameRadixTree::new())),
       fallback_sender: None,
+      default_certified_key: None,
+      strict_sni: false,
     }
   }
 
@@ -33,6 +39,8 @@ impl CustomSniResolver {
       fallback_resolver: None,
       resolvers,
       fallback_sender: None,
+      default_certified_key: None,
+      strict_sni: false,
     }
   }
 
@@ -50,6 +58,16 @@ impl CustomSniResolver {
   pub fn load_fallback_sender(&mut self, fallback_sender: async_channel::Sender<(String, u16)>, port: u16) {
     self.fallback_sender = Some((fallback_sender, port));
   }
+
+  /// Loads a default certified key, used when the client doesn't send a server name, or no host matches it
+  pub fn load_default_certified_key(&mut self, default_certified_key: Arc<CertifiedKey>) {
+    self.default_certified_key = Some(default_certified_key);
+  }
+
+  /// Sets whether to reject TLS handshakes, for which the server name sent by the client doesn't match any host
+  pub fn set_strict_sni(&mut self, strict_sni: bool) {
+    self.strict_sni = strict_sni;
+  }
 }
 
 impl ResolvesServerCert for CustomSniResolver {
@@ -67,18 +85,22 @@ impl ResolvesServerCert for CustomSniResolver {
       }
     }
     let hostname = hostname.map(|v| v.to_string());
-    self
-      .fallback_resolver
-      .as_ref()
-      .and_then(|r| r.resolve(client_hello))
-      .or_else(|| {
-        if let Some((sender, port)) = &self.fallback_sender {
-          if let Some(hostname) = hostname {
-            sender.send_blocking((hostname.to_string(), *port)).unwrap_or_default();
-          }
-        }
-        None
-      })
+    // With strict SNI, the certificates of hosts without a server name aren't used for unmatched server names
+    if !self.strict_sni {
+      if let Some(certified_key) = self.fallback_resolver.as_ref().and_then(|r| r.resolve(client_hello)) {
+        return Some(certified_key);
+      }
+    }
+    if let Some((sender, port)) = &self.fallback_sender {
+      if let Some(hostname) = hostname {
+        sender.send_blocking((hostname.to_string(), *port)).unwrap_or_default();
+      }
+    }
+    if self.strict_sni {
+      None
+    } else {
+      self.default_certified_key.clone()
+    }
   }
 }
 
@@ -101,6 +123,76 @@ impl ResolvesServerCert for OneCertifiedKeyResolver {
   }
 }
 
+/// A certificate resolver recording the certificate transparency (CT) status of served certificates in the metrics
+#[derive(Debug)]
+pub struct CtStatusResolver {
+  inner: Arc<dyn ResolvesServerCert>,
+  metrics_sender: MetricsMultiSender,
+}
+
+impl CtStatusResolver {
+  /// Creates a certificate resolver wrapping another resolver
+  pub fn new(inner: Arc<dyn ResolvesServerCert>, metrics_sender: MetricsMultiSender) -> Self {
+    Self { inner, metrics_sender }
+  }
+}
+
+impl ResolvesServerCert for CtStatusResolver {
+  fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+    let certified_key = self.inner.resolve(client_hello)?;
+    if let Some(leaf) = certified_key.cert.first() {
+      let sct_count = parse_embedded_scts(leaf).map_or(0, |scts| scts.len());
+      self.metrics_sender.try_send(Metric::new(
+        "ferron.tls.served_certificates",
+        vec![(
+          "ferron.tls.certificate.sct_count",
+          MetricAttributeValue::I64(sct_count as i64),
+        )],
+        MetricType::Counter,
+        MetricValue::U64(1),
+        Some("{certificate}"),
+        Some("Number of certificates served in TLS handshakes."),
+      ));
+    }
+    Some(certified_key)
+  }
+}
+
+/// TLS server configurations for a TLS port, selected by the server name (SNI) sent by the client
+pub struct TlsServerConfigs {
+  default_config: Arc<ServerConfig>,
+  host_configs: HostnameRadixTree<Arc<ServerConfig>>,
+}
+
+impl TlsServerConfigs {
+  /// Creates TLS server configurations with the default TLS server configuration
+  pub fn new(default_config: Arc<ServerConfig>) -> Self {
+    Self {
+      default_config,
+      host_configs: HostnameRadixTree::new(),
+    }
+  }
+
+  /// Replaces the default TLS server configuration, used when no host-specific TLS server configuration matches
+  pub fn set_default_config(&mut self, default_config: Arc<ServerConfig>) {
+    self.default_config = default_config;
+  }
+
+  /// Inserts a TLS server configuration for a specific host
+  pub fn insert_host_config(&mut self, host: &str, config: Arc<ServerConfig>) {
+    self.host_configs.insert(host.to_string(), config);
+  }
+
+  /// Selects the TLS server configuration for the server name sent by the client
+  pub fn select(&self, server_name: Option<&str>) -> Arc<ServerConfig> {
+    server_name
+      .map(|hn| hn.strip_suffix('.').unwrap_or(hn))
+      .and_then(|hostname| self.host_configs.get(hostname))
+      .unwrap_or(&self.default_config)
+      .clone()
+  }
+}
+
 /// Loads a public certificate from file
 pub fn load_certs(filename: &str) -> std::io::Result<Vec<CertificateDer<'static>>> {
   let mut certfile = std::fs::File::open(filename)?;
@@ -0,0 +1,280 @@
+/// The TLS record content type of handshake messages
+const TLS_CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+/// The TLS handshake message type of the ClientHello message
+const TLS_HANDSHAKE_TYPE_CLIENT_HELLO: u8 = 0x01;
+/// The TLS extension type of the "server_name" extension
+const TLS_EXTENSION_SERVER_NAME: u16 = 0x0000;
+/// The TLS extension type of the "application_layer_protocol_negotiation" extension
+const TLS_EXTENSION_ALPN: u16 = 0x0010;
+/// The "host_name" server name type
+const TLS_SERVER_NAME_TYPE_HOST_NAME: u8 = 0x00;
+
+/// The server name (SNI) and the application protocols (ALPN) sent in the TLS ClientHello message
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ClientHelloInfo {
+  /// The server name sent in the ClientHello message
+  pub server_name: Option<String>,
+
+  /// The application protocols offered in the ClientHello message, in the client's order of preference
+  pub alpn_protocols: Vec<String>,
+}
+
+/// The result of parsing the TLS ClientHello message
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClientHelloParseResult {
+  /// More data is needed to parse the ClientHello message
+  Incomplete,
+
+  /// The server name and the application protocols sent in the ClientHello message
+  ClientHello(ClientHelloInfo),
+
+  /// The data isn't a valid TLS ClientHello message
+  None,
+}
+
+/// A cursor over a byte slice, used for parsing the ClientHello message
+struct ByteCursor<'a> {
+  data: &'a [u8],
+}
+
+impl<'a> ByteCursor<'a> {
+  fn read_bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+    if self.data.len() < length {
+      return None;
+    }
+    let (bytes, rest) = self.data.split_at(length);
+    self.data = rest;
+    Some(bytes)
+  }
+
+  fn read_u8(&mut self) -> Option<u8> {
+    self.read_bytes(1).map(|bytes| bytes[0])
+  }
+
+  fn read_u16(&mut self) -> Option<u16> {
+    self.read_bytes(2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+  }
+
+  fn read_u8_prefixed(&mut self) -> Option<&'a [u8]> {
+    let length = self.read_u8()? as usize;
+    self.read_bytes(length)
+  }
+
+  fn read_u16_prefixed(&mut self) -> Option<&'a [u8]> {
+    let length = self.read_u16()? as usize;
+    self.read_bytes(length)
+  }
+}
+
+/// Obtains the server name (SNI) and the application protocols (ALPN) from the data received at the beginning of a TLS connection.
+/// The ClientHello message may span multiple TLS records.
+pub fn parse_client_hello(data: &[u8]) -> ClientHelloParseResult {
+  // Reassemble the handshake message from the TLS records
+  let mut handshake = Vec::new();
+  let mut records = data;
+  let handshake_length = loop {
+    if handshake.len() >= 4 {
+      let handshake_length = u32::from_be_bytes([0, handshake[1], handshake[2], handshake[3]]) as usize + 4;
+      if handshake.len() >= handshake_length {
+        break handshake_length;
+      }
+    }
+    if records.len() < 5 {
+      return ClientHelloParseResult::Incomplete;
+    }
+    if records[0] != TLS_CONTENT_TYPE_HANDSHAKE || records[1] != 0x03 {
+      return ClientHelloParseResult::None;
+    }
+    let record_length = u16::from_be_bytes([records[3], records[4]]) as usize;
+    if records.len() < record_length + 5 {
+      return ClientHelloParseResult::Incomplete;
+    }
+    handshake.extend_from_slice(&records[5..record_length + 5]);
+    records = &records[record_length + 5..];
+    if handshake
+      .first()
+      .is_some_and(|message_type| *message_type != TLS_HANDSHAKE_TYPE_CLIENT_HELLO)
+    {
+      return ClientHelloParseResult::None;
+    }
+  };
+
+  match parse_client_hello_body(&handshake[4..handshake_length]) {
+    Some(client_hello) => ClientHelloParseResult::ClientHello(client_hello),
+    None => ClientHelloParseResult::None,
+  }
+}
+
+/// Parses the ClientHello message body, and returns the server name and the application protocols, if present
+fn parse_client_hello_body(body: &[u8]) -> Option<ClientHelloInfo> {
+  let mut cursor = ByteCursor { data: body };
+  // Legacy version and random
+  cursor.read_bytes(34)?;
+  // Legacy session ID
+  cursor.read_u8_prefixed()?;
+  // Cipher suites
+  cursor.read_u16_prefixed()?;
+  // Legacy compression methods
+  cursor.read_u8_prefixed()?;
+  let mut client_hello = ClientHelloInfo::default();
+  if cursor.data.is_empty() {
+    // No extensions
+    return Some(client_hello);
+  }
+
+  let mut extensions = ByteCursor {
+    data: cursor.read_u16_prefixed()?,
+  };
+  while !extensions.data.is_empty() {
+    let extension_type = extensions.read_u16()?;
+    let extension_data = extensions.read_u16_prefixed()?;
+    match extension_type {
+      TLS_EXTENSION_SERVER_NAME => {
+        let mut server_name_list = ByteCursor {
+          data: ByteCursor { data: extension_data }.read_u16_prefixed()?,
+        };
+        while !server_name_list.data.is_empty() {
+          let name_type = server_name_list.read_u8()?;
+          let name = server_name_list.read_u16_prefixed()?;
+          if name_type == TLS_SERVER_NAME_TYPE_HOST_NAME {
+            let name = std::str::from_utf8(name).ok()?;
+            client_hello.server_name = Some(name.trim_end_matches('.').to_lowercase());
+            break;
+          }
+        }
+      }
+      TLS_EXTENSION_ALPN => {
+        let mut protocol_name_list = ByteCursor {
+          data: ByteCursor { data: extension_data }.read_u16_prefixed()?,
+        };
+        while !protocol_name_list.data.is_empty() {
+          let protocol_name = protocol_name_list.read_u8_prefixed()?;
+          client_hello
+            .alpn_protocols
+            .push(String::from_utf8_lossy(protocol_name).into_owned());
+        }
+      }
+      _ => (),
+    }
+  }
+
+  Some(client_hello)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn build_client_hello(server_name: Option<&str>, alpn_protocols: &[&str]) -> Vec<u8> {
+    let mut extensions = Vec::new();
+    // A "supported_versions" extension before the "server_name" extension
+    extensions.extend_from_slice(&[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04]);
+    if let Some(server_name) = server_name {
+      let name = server_name.as_bytes();
+      let mut server_name_list = vec![TLS_SERVER_NAME_TYPE_HOST_NAME];
+      server_name_list.extend_from_slice(&(name.len() as u16).to_be_bytes());
+      server_name_list.extend_from_slice(name);
+      let mut extension_data = (server_name_list.len() as u16).to_be_bytes().to_vec();
+      extension_data.extend_from_slice(&server_name_list);
+      extensions.extend_from_slice(&TLS_EXTENSION_SERVER_NAME.to_be_bytes());
+      extensions.extend_from_slice(&(extension_data.len() as u16).to_be_bytes());
+      extensions.extend_from_slice(&extension_data);
+    }
+    if !alpn_protocols.is_empty() {
+      let mut protocol_name_list = Vec::new();
+      for protocol in alpn_protocols {
+        protocol_name_list.push(protocol.len() as u8);
+        protocol_name_list.extend_from_slice(protocol.as_bytes());
+      }
+      let mut extension_data = (protocol_name_list.len() as u16).to_be_bytes().to_vec();
+      extension_data.extend_from_slice(&protocol_name_list);
+      extensions.extend_from_slice(&TLS_EXTENSION_ALPN.to_be_bytes());
+      extensions.extend_from_slice(&(extension_data.len() as u16).to_be_bytes());
+      extensions.extend_from_slice(&extension_data);
+    }
+
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&[0x42; 32]);
+    body.extend_from_slice(&[0x00]);
+    body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]);
+    body.extend_from_slice(&[0x01, 0x00]);
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut handshake = vec![TLS_HANDSHAKE_TYPE_CLIENT_HELLO];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+    handshake
+  }
+
+  fn wrap_in_records(handshake: &[u8], fragment_size: usize) -> Vec<u8> {
+    let mut records = Vec::new();
+    for fragment in handshake.chunks(fragment_size) {
+      records.extend_from_slice(&[TLS_CONTENT_TYPE_HANDSHAKE, 0x03, 0x01]);
+      records.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+      records.extend_from_slice(fragment);
+    }
+    records
+  }
+
+  fn server_name(name: &str) -> ClientHelloParseResult {
+    ClientHelloParseResult::ClientHello(ClientHelloInfo {
+      server_name: Some(name.to_string()),
+      alpn_protocols: vec![],
+    })
+  }
+
+  #[test]
+  fn test_parse_client_hello_sni() {
+    let data = wrap_in_records(&build_client_hello(Some("Mail.Example.com"), &[]), 16384);
+    assert_eq!(parse_client_hello(&data), server_name("mail.example.com"));
+  }
+
+  #[test]
+  fn test_parse_client_hello_sni_across_records() {
+    let data = wrap_in_records(&build_client_hello(Some("mail.example.com"), &[]), 16);
+    assert_eq!(parse_client_hello(&data), server_name("mail.example.com"));
+  }
+
+  #[test]
+  fn test_parse_client_hello_sni_incomplete() {
+    let data = wrap_in_records(&build_client_hello(Some("mail.example.com"), &[]), 16);
+    assert_eq!(parse_client_hello(&data[..3]), ClientHelloParseResult::Incomplete);
+    assert_eq!(
+      parse_client_hello(&data[..data.len() - 1]),
+      ClientHelloParseResult::Incomplete
+    );
+  }
+
+  #[test]
+  fn test_parse_client_hello_without_sni() {
+    let data = wrap_in_records(&build_client_hello(None, &[]), 16384);
+    assert_eq!(
+      parse_client_hello(&data),
+      ClientHelloParseResult::ClientHello(ClientHelloInfo::default())
+    );
+  }
+
+  #[test]
+  fn test_parse_client_hello_alpn() {
+    let data = wrap_in_records(
+      &build_client_hello(Some("xmpp.example.com"), &["xmpp-client", "h2"]),
+      16384,
+    );
+    assert_eq!(
+      parse_client_hello(&data),
+      ClientHelloParseResult::ClientHello(ClientHelloInfo {
+        server_name: Some("xmpp.example.com".to_string()),
+        alpn_protocols: vec!["xmpp-client".to_string(), "h2".to_string()],
+      })
+    );
+  }
+
+  #[test]
+  fn test_parse_non_tls_data() {
+    assert_eq!(
+      parse_client_hello(b"GET / HTTP/1.1\r\n\r\n"),
+      ClientHelloParseResult::None
+    );
+  }
+}